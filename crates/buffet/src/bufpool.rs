@@ -80,6 +80,9 @@ impl BufMut {
     /// anymore.
     #[inline]
     pub(crate) unsafe fn freeze_slice(&self, range: impl RangeBounds<usize>) -> Buf {
+        // the frozen slice is one more reference to the buffer: it's
+        // decremented when it's dropped.
+        privatepool::inc(self.index);
         let b = Buf {
             index: self.index,
             off: self.off,
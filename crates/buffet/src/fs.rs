@@ -0,0 +1,115 @@
+//! Reading files into owned buffers, e.g. to serve them
+//!
+//! With io_uring, opening files and reading from them is submitted to the
+//! ring, like socket reads. Without it, they're regular `open` and `pread`
+//! calls, which block the thread: fine for files the page cache holds, less
+//! so for cold files on slow disks. Metadata comes from the open file.
+
+use std::{
+    fs::Metadata,
+    path::{Path, PathBuf},
+};
+
+use crate::{BufResult, IoBufMut};
+
+#[derive(Debug)]
+pub struct File {
+    inner: std::fs::File,
+
+    /// what it was opened with, cf. [File::canonical_path]
+    #[cfg(not(target_os = "linux"))]
+    path: PathBuf,
+}
+
+impl File {
+    /// Opens the file (or directory) at `path` for reading
+    #[cfg(all(target_os = "linux", feature = "uring"))]
+    pub async fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        use std::{
+            ffi::CString,
+            os::{fd::FromRawFd, unix::ffi::OsStrExt},
+        };
+
+        // the kernel reads the path once the SQE is submitted, which may be
+        // after this future is dropped: it's only freed on completion.
+        // FIXME: this leaks the path (and the fd, if the open succeeds) if
+        // the future is dropped
+        let path = CString::new(path.as_ref().as_os_str().as_bytes())?.into_raw();
+        let sqe = io_uring::opcode::OpenAt::new(io_uring::types::Fd(libc::AT_FDCWD), path)
+            .flags(libc::O_RDONLY | libc::O_CLOEXEC)
+            .build();
+        let cqe = crate::get_ring().push(sqe).await;
+        drop(unsafe { CString::from_raw(path) });
+        let ret = cqe.result();
+        if ret < 0 {
+            return Err(std::io::Error::from_raw_os_error(-ret));
+        }
+        Ok(Self {
+            inner: unsafe { std::fs::File::from_raw_fd(ret) },
+        })
+    }
+
+    /// Opens the file (or directory) at `path` for reading
+    #[cfg(not(all(target_os = "linux", feature = "uring")))]
+    pub async fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(Self {
+            inner: std::fs::File::open(path.as_ref())?,
+            #[cfg(not(target_os = "linux"))]
+            path: path.as_ref().to_owned(),
+        })
+    }
+
+    /// Metadata of the open file, from `fstat(2)`: there's no path to look
+    /// up again.
+    pub fn metadata(&self) -> std::io::Result<Metadata> {
+        self.inner.metadata()
+    }
+
+    /// Where the open file is, with every symlink resolved, e.g. to check
+    /// that it's under a given directory. It's that of the file we opened,
+    /// even if the path it was opened with changed since.
+    #[cfg(target_os = "linux")]
+    pub fn canonical_path(&self) -> std::io::Result<PathBuf> {
+        use std::os::fd::AsRawFd;
+
+        std::fs::read_link(format!("/proc/self/fd/{}", self.inner.as_raw_fd()))
+    }
+
+    /// Where the open file is, with every symlink resolved. Elsewhere than
+    /// Linux, that's resolved from the path it was opened with.
+    #[cfg(not(target_os = "linux"))]
+    pub fn canonical_path(&self) -> std::io::Result<PathBuf> {
+        std::fs::canonicalize(&self.path)
+    }
+
+    /// Reads into `buf` from `offset` on, without moving any file cursor.
+    /// Returns 0 at the end of the file.
+    #[cfg(all(target_os = "linux", feature = "uring"))]
+    pub async fn read_at<B: IoBufMut>(&self, mut buf: B, offset: u64) -> BufResult<usize, B> {
+        use std::os::fd::AsRawFd;
+
+        let sqe = io_uring::opcode::Read::new(
+            io_uring::types::Fd(self.inner.as_raw_fd()),
+            buf.io_buf_mut_stable_mut_ptr(),
+            buf.io_buf_mut_capacity() as u32,
+        )
+        .offset(offset)
+        .build();
+        let cqe = crate::get_ring().push(sqe).await;
+        let ret = cqe.result();
+        if ret < 0 {
+            return (Err(std::io::Error::from_raw_os_error(-ret)), buf);
+        }
+        (Ok(ret as usize), buf)
+    }
+
+    /// Reads into `buf` from `offset` on, without moving any file cursor.
+    /// Returns 0 at the end of the file.
+    #[cfg(not(all(target_os = "linux", feature = "uring")))]
+    pub async fn read_at<B: IoBufMut>(&self, mut buf: B, offset: u64) -> BufResult<usize, B> {
+        use std::os::unix::fs::FileExt;
+
+        let res = self.inner.read_at(unsafe { buf.slice_mut() }, offset);
+        (res, buf)
+    }
+}
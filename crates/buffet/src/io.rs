@@ -1,3 +1,5 @@
+use std::os::fd::RawFd;
+
 use crate::{BufResult, IoBufMut, Piece, PieceList};
 
 mod pipe;
@@ -8,6 +10,13 @@ mod non_uring;
 #[allow(async_fn_in_trait)] // we never require Send
 pub trait ReadOwned {
     async fn read_owned<B: IoBufMut>(&mut self, buf: B) -> BufResult<usize, B>;
+
+    /// The file descriptor reads come straight from, if any: no TLS, no
+    /// userland buffering in between. Bytes can then be moved out of it with
+    /// `splice(2)` rather than read.
+    fn raw_fd(&self) -> Option<RawFd> {
+        None
+    }
 }
 
 #[allow(async_fn_in_trait)] // we never require Send
@@ -108,6 +117,12 @@ pub trait WriteOwned {
     /// Shuts down the write end of this socket. This flushes
     /// any data that may not have been send.
     async fn shutdown(&mut self) -> std::io::Result<()>;
+
+    /// The file descriptor writes go straight to, if any, cf.
+    /// [ReadOwned::raw_fd]
+    fn raw_fd(&self) -> Option<RawFd> {
+        None
+    }
 }
 
 #[cfg(all(test, not(feature = "miri")))]
@@ -6,7 +6,7 @@ use crate::{Piece, ReadOwned, WriteOwned};
 pub fn pipe() -> (PipeWrite, PipeRead) {
     let (tx, rx) = mpsc::channel(1);
     (
-        PipeWrite { tx },
+        PipeWrite { tx: Some(tx) },
         PipeRead {
             rx,
             state: Default::default(),
@@ -86,14 +86,47 @@ impl ReadOwned for PipeRead {
     }
 }
 
+impl PipeRead {
+    /// Returns whatever is next in the pipe, without copying it: `Ok(None)`
+    /// once the write end is dropped.
+    #[cfg(feature = "sim")]
+    pub(crate) async fn next_piece(&mut self) -> std::io::Result<Option<Piece>> {
+        if let Some(piece) = self.remain.take() {
+            return Ok(Some(piece));
+        }
+        loop {
+            match self.state {
+                ReadState::Live => {}
+                ReadState::Reset => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::ConnectionReset,
+                        "simulated connection reset",
+                    ))
+                }
+                ReadState::Eof => return Ok(None),
+            }
+            match self.rx.recv().await {
+                Some(PipeEvent::Piece(piece)) => return Ok(Some(piece)),
+                Some(PipeEvent::Reset) => self.state = ReadState::Reset,
+                None => self.state = ReadState::Eof,
+            }
+        }
+    }
+}
+
 pub struct PipeWrite {
-    tx: mpsc::Sender<PipeEvent>,
+    /// `None` once shut down
+    tx: Option<mpsc::Sender<PipeEvent>>,
 }
 
 impl PipeWrite {
     /// Simulate a connection reset
     pub async fn reset(self) {
-        self.tx.send(PipeEvent::Reset).await.unwrap()
+        // if the read end is gone (or we shut down), there's nobody left to
+        // tell
+        if let Some(tx) = self.tx {
+            _ = tx.send(PipeEvent::Reset).await;
+        }
     }
 }
 
@@ -104,7 +137,14 @@ impl WriteOwned for PipeWrite {
             // ignore 0-length writes
         }
 
-        if self.tx.send(PipeEvent::Piece(buf.clone())).await.is_err() {
+        let Some(tx) = &self.tx else {
+            let err = std::io::Error::new(
+                std::io::ErrorKind::BrokenPipe,
+                "simulated write after shutdown",
+            );
+            return (Err(err), buf);
+        };
+        if tx.send(PipeEvent::Piece(buf.clone())).await.is_err() {
             let err = std::io::Error::new(std::io::ErrorKind::BrokenPipe, "simulated broken pipe");
             return (Err(err), buf);
         }
@@ -112,7 +152,10 @@ impl WriteOwned for PipeWrite {
         (Ok(buf.len()), buf)
     }
 
+    /// Like dropping the write end: the read end gets EOF once it's read
+    /// everything written before
     async fn shutdown(&mut self) -> std::io::Result<()> {
+        self.tx = None;
         Ok(())
     }
 }
@@ -230,4 +273,32 @@ mod tests {
             }
         })
     }
+
+    #[test]
+    fn test_pipe_shutdown() {
+        crate::start(async move {
+            let (mut w, mut r) = pipe();
+
+            crate::spawn(async move {
+                w.write_all_owned("last words").await.unwrap();
+                w.shutdown().await.unwrap();
+                let err = w.write_all_owned("more").await.unwrap_err();
+                assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+            });
+
+            {
+                let buf = vec![0u8; 256];
+                let (res, buf) = r.read_owned(buf).await;
+                let n = res.unwrap();
+                assert_eq!(&buf[..n], b"last words");
+            }
+
+            {
+                let buf = vec![0u8; 256];
+                let (res, _) = r.read_owned(buf).await;
+                let n = res.unwrap();
+                assert_eq!(n, 0, "reached EOF");
+            }
+        })
+    }
 }
@@ -14,6 +14,11 @@ pub use io::*;
 
 pub mod net;
 
+pub mod fs;
+
+#[cfg(feature = "sim")]
+pub mod sim;
+
 #[cfg(all(target_os = "linux", feature = "uring"))]
 mod uring;
 
@@ -1,3 +1,5 @@
+use std::{os::unix::fs::FileTypeExt, path::Path};
+
 use crate::io::IntoHalves;
 
 #[cfg(all(target_os = "linux", feature = "uring"))]
@@ -20,3 +22,61 @@ impl IntoHalves for tokio::net::TcpStream {
         self.into_split()
     }
 }
+
+impl IntoHalves for tokio::net::UnixStream {
+    type Read = tokio::net::unix::OwnedReadHalf;
+    type Write = tokio::net::unix::OwnedWriteHalf;
+
+    fn into_halves(self) -> (Self::Read, Self::Write) {
+        self.into_split()
+    }
+}
+
+/// Removes the socket file at `path` if nobody is listening on it anymore.
+/// Errors out if something other than a socket lives there, or if another
+/// process is still accepting connections on it.
+fn remove_stale_unix_socket(path: &Path) -> std::io::Result<()> {
+    let meta = match std::fs::symlink_metadata(path) {
+        Ok(meta) => meta,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    if !meta.file_type().is_socket() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("{} exists and is not a unix socket", path.display()),
+        ));
+    }
+
+    match std::os::unix::net::UnixStream::connect(path) {
+        Ok(_) => Err(std::io::Error::new(
+            std::io::ErrorKind::AddrInUse,
+            format!("{} is already being listened on", path.display()),
+        )),
+        Err(e) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+            tracing::debug!(?path, "removing stale unix socket");
+            std::fs::remove_file(path)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// The path a listening unix socket is bound to
+fn unix_socket_path(fd: &std::os::fd::OwnedFd) -> std::io::Result<std::path::PathBuf> {
+    let std = std::os::unix::net::UnixListener::from(fd.try_clone()?);
+    let addr = std.local_addr()?;
+    addr.as_pathname()
+        .map(|path| path.to_owned())
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "unix socket is not bound to a path",
+            )
+        })
+}
+
+fn set_unix_socket_mode(path: &Path, mode: u32) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))
+}
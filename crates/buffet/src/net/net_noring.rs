@@ -1,4 +1,9 @@
-use std::net::SocketAddr;
+use std::{
+    cell::Cell,
+    net::SocketAddr,
+    os::fd::{AsFd, OwnedFd},
+    path::{Path, PathBuf},
+};
 use tokio::net::{TcpListener as TokListener, TcpStream as TokStream};
 
 pub type TcpStream = TokStream;
@@ -16,6 +21,39 @@ impl TcpListener {
         Ok(Self { tok })
     }
 
+    /// Like [TcpListener::bind], but sets `SO_REUSEPORT`, so that several
+    /// listeners (typically one per thread) can accept connections on the
+    /// same address.
+    pub async fn bind_reuseport(addr: SocketAddr) -> std::io::Result<Self> {
+        let addr: socket2::SockAddr = addr.into();
+        let socket = socket2::Socket::new(addr.domain(), socket2::Type::STREAM, None)?;
+
+        socket.set_reuse_port(true)?;
+        socket.set_reuse_address(true)?;
+        socket.set_nonblocking(true)?;
+        socket.bind(&addr)?;
+        socket.listen(256)?;
+
+        let tok = TokListener::from_std(socket.into())?;
+        Ok(Self { tok })
+    }
+
+    /// Wraps a socket that's already bound and listening, e.g. one handed
+    /// over by a previous instance of the server, cf. [TcpListener::export]
+    pub fn from_listening_fd(fd: OwnedFd) -> std::io::Result<Self> {
+        let std = std::net::TcpListener::from(fd);
+        std.set_nonblocking(true)?;
+        let tok = TokListener::from_std(std)?;
+        Ok(Self { tok })
+    }
+
+    /// Duplicates the listening socket, to hand it over to another process.
+    /// Connections keep getting queued as long as either process has it
+    /// open, and get accepted by whichever process accepts first.
+    pub fn export(&self) -> std::io::Result<OwnedFd> {
+        self.tok.as_fd().try_clone_to_owned()
+    }
+
     pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
         self.tok.local_addr()
     }
@@ -27,3 +65,82 @@ impl TcpListener {
         })
     }
 }
+
+pub type UnixStream = tokio::net::UnixStream;
+
+pub type UnixReadHalf = tokio::net::unix::OwnedReadHalf;
+pub type UnixWriteHalf = tokio::net::unix::OwnedWriteHalf;
+
+/// A unix domain socket listener. The socket file is removed when the
+/// listener is dropped.
+pub struct UnixListener {
+    tok: tokio::net::UnixListener,
+    path: PathBuf,
+    remove_on_drop: Cell<bool>,
+}
+
+impl UnixListener {
+    /// Binds to the given path. If a socket file already exists there but
+    /// nobody is listening on it (e.g. a previous instance of the server
+    /// crashed), it's removed first.
+    pub async fn bind(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        super::remove_stale_unix_socket(path)?;
+
+        let tok = tokio::net::UnixListener::bind(path)?;
+        Ok(Self {
+            tok,
+            path: path.to_owned(),
+            remove_on_drop: Cell::new(true),
+        })
+    }
+
+    /// Wraps a socket that's already bound and listening, e.g. one handed
+    /// over by a previous instance of the server, cf. [UnixListener::export]
+    pub fn from_listening_fd(fd: OwnedFd) -> std::io::Result<Self> {
+        let path = super::unix_socket_path(&fd)?;
+        let std = std::os::unix::net::UnixListener::from(fd);
+        std.set_nonblocking(true)?;
+        let tok = tokio::net::UnixListener::from_std(std)?;
+        Ok(Self {
+            tok,
+            path,
+            remove_on_drop: Cell::new(true),
+        })
+    }
+
+    /// Duplicates the listening socket, to hand it over to another process,
+    /// cf. [TcpListener::export]. The socket file is left alone when this
+    /// listener is dropped, since the other process is still using it.
+    pub fn export(&self) -> std::io::Result<OwnedFd> {
+        let fd = self.tok.as_fd().try_clone_to_owned()?;
+        self.remove_on_drop.set(false);
+        Ok(fd)
+    }
+
+    /// The path this listener is bound to
+    pub fn local_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Sets the permissions of the socket file, e.g. `0o660` to only allow
+    /// connections from the owner and group.
+    pub fn set_mode(&self, mode: u32) -> std::io::Result<()> {
+        super::set_unix_socket_mode(&self.path, mode)
+    }
+
+    pub async fn accept(&self) -> std::io::Result<UnixStream> {
+        self.tok.accept().await.map(|tuple| tuple.0)
+    }
+}
+
+impl Drop for UnixListener {
+    fn drop(&mut self) {
+        if !self.remove_on_drop.get() {
+            return;
+        }
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            tracing::debug!(path = ?self.path, "could not remove unix socket file: {e}");
+        }
+    }
+}
@@ -1,11 +1,13 @@
 use std::{
+    cell::Cell,
     mem::ManuallyDrop,
     net::SocketAddr,
-    os::fd::{AsRawFd, FromRawFd, IntoRawFd, RawFd},
+    os::fd::{AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd},
+    path::{Path, PathBuf},
     rc::Rc,
 };
 
-use io_uring::opcode::{Accept, Read, Write};
+use io_uring::opcode::{Accept, Read, Splice, Write};
 use nix::errno::Errno;
 
 use crate::{
@@ -89,6 +91,29 @@ impl TcpListener {
         Ok(Self { fd })
     }
 
+    /// Like [TcpListener::bind], but guarantees `SO_REUSEPORT` is set, so
+    /// that several listeners (typically one per thread) can accept
+    /// connections on the same address. For now, [TcpListener::bind] sets it
+    /// too on this backend, cf. the FIXME there.
+    pub async fn bind_reuseport(addr: SocketAddr) -> std::io::Result<Self> {
+        Self::bind(addr).await
+    }
+
+    /// Wraps a socket that's already bound and listening, e.g. one handed
+    /// over by a previous instance of the server, cf. [TcpListener::export]
+    pub fn from_listening_fd(fd: OwnedFd) -> std::io::Result<Self> {
+        Ok(Self {
+            fd: fd.into_raw_fd(),
+        })
+    }
+
+    /// Duplicates the listening socket, to hand it over to another process.
+    /// Connections keep getting queued as long as either process has it
+    /// open, and get accepted by whichever process accepts first.
+    pub fn export(&self) -> std::io::Result<OwnedFd> {
+        unsafe { BorrowedFd::borrow_raw(self.fd) }.try_clone_to_owned()
+    }
+
     pub fn local_addr(&self) -> std::io::Result<SocketAddr> {
         let socket = ManuallyDrop::new(unsafe { socket2::Socket::from_raw_fd(self.fd) });
         let addr = socket.local_addr()?;
@@ -131,25 +156,11 @@ impl TcpListener {
 pub struct TcpReadHalf(Rc<TcpStream>);
 
 impl ReadOwned for TcpReadHalf {
-    async fn read_owned<B: IoBufMut>(&mut self, mut buf: B) -> BufResult<usize, B> {
-        let sqe = Read::new(
-            io_uring::types::Fd(self.0.fd),
-            buf.io_buf_mut_stable_mut_ptr(),
-            buf.io_buf_mut_capacity() as u32,
-        )
-        .build();
-        tracing::trace!(
-            "submitting read_owned, reading from fd {} to {:p} with capacity {}",
-            self.0.fd,
-            buf.io_buf_mut_stable_mut_ptr(),
-            buf.io_buf_mut_capacity()
-        );
-        let cqe = get_ring().push(sqe).await;
-        let ret = match cqe.error_for_errno() {
-            Ok(ret) => ret,
-            Err(e) => return (Err(std::io::Error::from(e)), buf),
-        };
-        (Ok(ret as usize), buf)
+    async fn read_owned<B: IoBufMut>(&mut self, buf: B) -> BufResult<usize, B> {
+        read_owned_fd(self.0.fd, buf).await
+    }
+    fn raw_fd(&self) -> Option<RawFd> {
+        Some(self.0.fd)
     }
 }
 
@@ -157,61 +168,19 @@ pub struct TcpWriteHalf(Rc<TcpStream>);
 
 impl WriteOwned for TcpWriteHalf {
     async fn write_owned(&mut self, buf: impl Into<Piece>) -> BufResult<usize, Piece> {
-        let buf = buf.into();
-        let sqe = Write::new(
-            io_uring::types::Fd(self.0.fd),
-            buf.as_ref().as_ptr(),
-            buf.len().try_into().expect("usize -> u32"),
-        )
-        .build();
-
-        let cqe = get_ring().push(sqe).await;
-        let ret = match cqe.error_for_errno() {
-            Ok(ret) => ret,
-            Err(e) => return (Err(std::io::Error::from(e)), buf),
-        };
-        (Ok(ret as usize), buf)
+        write_owned_fd(self.0.fd, buf.into()).await
     }
 
-    // TODO: implement writev
-
     async fn writev_owned(&mut self, list: &crate::PieceList) -> std::io::Result<usize> {
-        use libc::iovec;
-        use io_uring::opcode::Writev;
-
-        let mut iovecs = Vec::with_capacity(list.pieces.len());
-        for piece in &list.pieces {
-            iovecs.push(iovec {
-                iov_base: piece.as_ref().as_ptr() as *mut libc::c_void,
-                iov_len: piece.len(),
-            });
-        }
-        let iov_ptr = iovecs.as_ptr();
-        let iov_cnt = iovecs.len();
-        std::mem::forget(iovecs); // FIXME: don't leak memory
-
-        let sqe = Writev::new(
-            io_uring::types::Fd(self.0.fd),
-            iov_ptr,
-            iov_cnt as u32,
-        )
-        .build();
-
-        let cqe = get_ring().push(sqe).await;
-        let ret = match cqe.error_for_errno() {
-            Ok(ret) => ret,
-            Err(e) => return Err(std::io::Error::from(e)),
-        };
-        Ok(ret as usize)
+        writev_owned_fd(self.0.fd, list).await
     }
 
     async fn shutdown(&mut self) -> std::io::Result<()> {
-        tracing::debug!("requesting shutdown");
-        let sqe =
-            io_uring::opcode::Shutdown::new(io_uring::types::Fd(self.0.fd), libc::SHUT_WR).build();
-        let cqe = get_ring().push(sqe).await;
-        cqe.error_for_errno()?;
-        Ok(())
+        shutdown_fd(self.0.fd).await
+    }
+
+    fn raw_fd(&self) -> Option<RawFd> {
+        Some(self.0.fd)
     }
 }
 
@@ -231,6 +200,305 @@ impl FromRawFd for TcpStream {
     }
 }
 
+/// A unix domain socket stream
+pub struct UnixStream {
+    fd: i32,
+}
+
+impl UnixStream {
+    pub async fn connect(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let addr = socket2::SockAddr::unix(path)?;
+        let socket = ManuallyDrop::new(socket2::Socket::new(
+            socket2::Domain::UNIX,
+            socket2::Type::STREAM,
+            None,
+        )?);
+        let fd = socket.as_raw_fd();
+
+        let u = get_ring();
+
+        let addr = Box::into_raw(Box::new(addr));
+        let sqe = unsafe {
+            io_uring::opcode::Connect::new(io_uring::types::Fd(fd), addr as *const _, (*addr).len())
+        }
+        .build();
+        let cqe = u.push(sqe).await;
+        cqe.error_for_errno()?;
+        Ok(Self { fd })
+    }
+}
+
+impl Drop for UnixStream {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+impl IntoRawFd for UnixStream {
+    fn into_raw_fd(self) -> RawFd {
+        let fd = self.fd;
+        std::mem::forget(self);
+        fd
+    }
+}
+
+impl FromRawFd for UnixStream {
+    unsafe fn from_raw_fd(fd: RawFd) -> Self {
+        Self { fd }
+    }
+}
+
+/// A unix domain socket listener. The socket file is removed when the
+/// listener is dropped.
+pub struct UnixListener {
+    fd: i32,
+    path: PathBuf,
+    remove_on_drop: Cell<bool>,
+}
+
+impl UnixListener {
+    /// Binds to the given path. If a socket file already exists there but
+    /// nobody is listening on it (e.g. a previous instance of the server
+    /// crashed), it's removed first.
+    pub async fn bind(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        super::remove_stale_unix_socket(path)?;
+
+        let addr = socket2::SockAddr::unix(path)?;
+        let socket = socket2::Socket::new(socket2::Domain::UNIX, socket2::Type::STREAM, None)?;
+        socket.bind(&addr)?;
+
+        // FIXME: magic values
+        socket.listen(256)?;
+
+        let fd = socket.as_raw_fd();
+        std::mem::forget(socket);
+
+        Ok(Self {
+            fd,
+            path: path.to_owned(),
+            remove_on_drop: Cell::new(true),
+        })
+    }
+
+    /// Wraps a socket that's already bound and listening, e.g. one handed
+    /// over by a previous instance of the server, cf. [UnixListener::export]
+    pub fn from_listening_fd(fd: OwnedFd) -> std::io::Result<Self> {
+        let path = super::unix_socket_path(&fd)?;
+        Ok(Self {
+            fd: fd.into_raw_fd(),
+            path,
+            remove_on_drop: Cell::new(true),
+        })
+    }
+
+    /// Duplicates the listening socket, to hand it over to another process,
+    /// cf. [TcpListener::export]. The socket file is left alone when this
+    /// listener is dropped, since the other process is still using it.
+    pub fn export(&self) -> std::io::Result<OwnedFd> {
+        let fd = unsafe { BorrowedFd::borrow_raw(self.fd) }.try_clone_to_owned()?;
+        self.remove_on_drop.set(false);
+        Ok(fd)
+    }
+
+    /// The path this listener is bound to
+    pub fn local_path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Sets the permissions of the socket file, e.g. `0o660` to only allow
+    /// connections from the owner and group.
+    pub fn set_mode(&self, mode: u32) -> std::io::Result<()> {
+        super::set_unix_socket_mode(&self.path, mode)
+    }
+
+    pub async fn accept(&self) -> std::io::Result<UnixStream> {
+        let u = get_ring();
+        // unix socket peers are usually unnamed, so we don't ask for the address
+        let sqe = Accept::new(
+            io_uring::types::Fd(self.fd),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+        .build();
+        let cqe = u.push(sqe).await;
+        let fd = cqe.error_for_errno()?;
+        Ok(UnixStream { fd })
+    }
+}
+
+impl Drop for UnixListener {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.fd);
+        }
+        if !self.remove_on_drop.get() {
+            return;
+        }
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            tracing::debug!(path = ?self.path, "could not remove unix socket file: {e}");
+        }
+    }
+}
+
+pub struct UnixReadHalf(Rc<UnixStream>);
+
+impl ReadOwned for UnixReadHalf {
+    async fn read_owned<B: IoBufMut>(&mut self, buf: B) -> BufResult<usize, B> {
+        read_owned_fd(self.0.fd, buf).await
+    }
+    fn raw_fd(&self) -> Option<RawFd> {
+        Some(self.0.fd)
+    }
+}
+
+pub struct UnixWriteHalf(Rc<UnixStream>);
+
+impl WriteOwned for UnixWriteHalf {
+    async fn write_owned(&mut self, buf: impl Into<Piece>) -> BufResult<usize, Piece> {
+        write_owned_fd(self.0.fd, buf.into()).await
+    }
+
+    async fn writev_owned(&mut self, list: &crate::PieceList) -> std::io::Result<usize> {
+        writev_owned_fd(self.0.fd, list).await
+    }
+
+    async fn shutdown(&mut self) -> std::io::Result<()> {
+        shutdown_fd(self.0.fd).await
+    }
+
+    fn raw_fd(&self) -> Option<RawFd> {
+        Some(self.0.fd)
+    }
+}
+
+impl IntoHalves for UnixStream {
+    type Read = UnixReadHalf;
+    type Write = UnixWriteHalf;
+
+    fn into_halves(self) -> (Self::Read, Self::Write) {
+        let self_rc = Rc::new(self);
+        (UnixReadHalf(self_rc.clone()), UnixWriteHalf(self_rc))
+    }
+}
+
+async fn read_owned_fd<B: IoBufMut>(fd: RawFd, mut buf: B) -> BufResult<usize, B> {
+    let sqe = Read::new(
+        io_uring::types::Fd(fd),
+        buf.io_buf_mut_stable_mut_ptr(),
+        buf.io_buf_mut_capacity() as u32,
+    )
+    .build();
+    tracing::trace!(
+        "submitting read_owned, reading from fd {} to {:p} with capacity {}",
+        fd,
+        buf.io_buf_mut_stable_mut_ptr(),
+        buf.io_buf_mut_capacity()
+    );
+    let cqe = get_ring().push(sqe).await;
+    let ret = match cqe.error_for_errno() {
+        Ok(ret) => ret,
+        Err(e) => return (Err(std::io::Error::from(e)), buf),
+    };
+    (Ok(ret as usize), buf)
+}
+
+async fn write_owned_fd(fd: RawFd, buf: Piece) -> BufResult<usize, Piece> {
+    let sqe = Write::new(
+        io_uring::types::Fd(fd),
+        buf.as_ref().as_ptr(),
+        buf.len().try_into().expect("usize -> u32"),
+    )
+    .build();
+
+    let cqe = get_ring().push(sqe).await;
+    let ret = match cqe.error_for_errno() {
+        Ok(ret) => ret,
+        Err(e) => return (Err(std::io::Error::from(e)), buf),
+    };
+    (Ok(ret as usize), buf)
+}
+
+async fn writev_owned_fd(fd: RawFd, list: &crate::PieceList) -> std::io::Result<usize> {
+    use io_uring::opcode::Writev;
+    use libc::iovec;
+
+    let mut iovecs = Vec::with_capacity(list.pieces.len());
+    for piece in &list.pieces {
+        iovecs.push(iovec {
+            iov_base: piece.as_ref().as_ptr() as *mut libc::c_void,
+            iov_len: piece.len(),
+        });
+    }
+    let iov_ptr = iovecs.as_ptr();
+    let iov_cnt = iovecs.len();
+    std::mem::forget(iovecs); // FIXME: don't leak memory
+
+    let sqe = Writev::new(io_uring::types::Fd(fd), iov_ptr, iov_cnt as u32).build();
+
+    let cqe = get_ring().push(sqe).await;
+    let ret = match cqe.error_for_errno() {
+        Ok(ret) => ret,
+        Err(e) => return Err(std::io::Error::from(e)),
+    };
+    Ok(ret as usize)
+}
+
+async fn shutdown_fd(fd: RawFd) -> std::io::Result<()> {
+    tracing::debug!("requesting shutdown");
+    let sqe = io_uring::opcode::Shutdown::new(io_uring::types::Fd(fd), libc::SHUT_WR).build();
+    let cqe = get_ring().push(sqe).await;
+    cqe.error_for_errno()?;
+    Ok(())
+}
+
+/// How many bytes to move per `splice(2)` call: the default capacity of a pipe
+const SPLICE_CHUNK: u32 = 64 * 1024;
+
+/// Moves bytes from `from` to `to` until `from` reaches EOF, with `splice(2)`
+/// through a pipe pair, so they never get copied to userland. Returns how
+/// many bytes were moved.
+pub async fn splice_all(from: RawFd, to: RawFd) -> std::io::Result<u64> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let (pipe_r, pipe_w) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+
+    let mut moved = 0u64;
+    loop {
+        let n = splice_fd(from, pipe_w.as_raw_fd(), SPLICE_CHUNK).await?;
+        if n == 0 {
+            return Ok(moved);
+        }
+
+        let mut remain = n;
+        while remain > 0 {
+            let written = splice_fd(pipe_r.as_raw_fd(), to, remain).await?;
+            if written == 0 {
+                return Err(std::io::ErrorKind::WriteZero.into());
+            }
+            remain -= written;
+        }
+        moved += n as u64;
+    }
+}
+
+async fn splice_fd(fd_in: RawFd, fd_out: RawFd, len: u32) -> std::io::Result<u32> {
+    let sqe = Splice::new(
+        io_uring::types::Fd(fd_in),
+        -1,
+        io_uring::types::Fd(fd_out),
+        -1,
+        len,
+    )
+    .build();
+    let cqe = get_ring().push(sqe).await;
+    Ok(cqe.error_for_errno()? as u32)
+}
+
 trait CqueueExt {
     fn error_for_errno(&self) -> Result<i32, Errno>;
 }
@@ -296,4 +564,91 @@ mod tests {
         }
         crate::start(async move { test_accept_inner().await });
     }
+
+    #[test]
+    fn test_unix_accept() {
+        async fn test_unix_accept_inner() {
+            let path =
+                std::env::temp_dir().join(format!("buffet-test-{}.sock", std::process::id()));
+
+            // a stale socket file (nobody listening) is cleaned up on bind
+            drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+            assert!(path.exists());
+
+            let listener = super::UnixListener::bind(&path).await.unwrap();
+            listener.set_mode(0o600).unwrap();
+
+            std::thread::spawn({
+                let path = path.clone();
+                move || {
+                    use std::io::{Read, Write};
+
+                    let mut sock = std::os::unix::net::UnixStream::connect(path).unwrap();
+                    let mut buf = [0u8; 5];
+                    sock.read_exact(&mut buf).unwrap();
+                    sock.write_all(b"hello").unwrap();
+                }
+            });
+
+            let stream = listener.accept().await.unwrap();
+            let (mut r, mut w) = stream.into_halves();
+            w.write_all_owned("howdy").await.unwrap();
+
+            let buf = vec![0u8; 1024];
+            let (res, buf) = r.read_owned(buf).await;
+            let n = res.unwrap();
+            assert_eq!(&buf[..n], b"hello");
+
+            // binding again while someone is listening fails
+            assert!(super::UnixListener::bind(&path).await.is_err());
+
+            drop(listener);
+            assert!(!path.exists());
+        }
+        crate::start(async move { test_unix_accept_inner().await });
+    }
+
+    #[test]
+    fn test_splice_all() {
+        async fn test_splice_all_inner() {
+            let listener = super::TcpListener::bind("127.0.0.1:0".parse().unwrap())
+                .await
+                .unwrap();
+            let addr = listener.local_addr().unwrap();
+            let payload: Vec<u8> = (0..200_000).map(|i| (i % 251) as u8).collect();
+
+            let sender = std::thread::spawn({
+                let payload = payload.clone();
+                move || {
+                    use std::io::Write;
+
+                    let mut sock = std::net::TcpStream::connect(addr).unwrap();
+                    sock.write_all(&payload).unwrap();
+                }
+            });
+            let (from, _) = listener.accept().await.unwrap();
+
+            let receiver = std::thread::spawn(move || {
+                use std::io::Read;
+
+                let mut sock = std::net::TcpStream::connect(addr).unwrap();
+                let mut received = Vec::new();
+                sock.read_to_end(&mut received).unwrap();
+                received
+            });
+            let (to, _) = listener.accept().await.unwrap();
+
+            let (from_r, _from_w) = from.into_halves();
+            let (_to_r, mut to_w) = to.into_halves();
+            let moved = super::splice_all(from_r.raw_fd().unwrap(), to_w.raw_fd().unwrap())
+                .await
+                .unwrap();
+            assert_eq!(moved, payload.len() as u64);
+            to_w.shutdown().await.unwrap();
+
+            sender.join().unwrap();
+            assert_eq!(receiver.join().unwrap(), payload);
+        }
+        crate::start(async move { test_splice_all_inner().await });
+    }
 }
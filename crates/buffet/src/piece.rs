@@ -1,6 +1,6 @@
 //! Types for performing vectored I/O.
 
-use http::header::HeaderName;
+use http::header::{HeaderName, HeaderValue};
 use std::{
     collections::VecDeque,
     fmt,
@@ -56,6 +56,7 @@ pub enum PieceCore {
     Vec(Rc<Vec<u8>>),
     Roll(Roll),
     HeaderName(HeaderName),
+    HeaderValue(HeaderValue),
 }
 
 impl<T> From<T> for Piece
@@ -123,6 +124,13 @@ impl From<HeaderName> for PieceCore {
     }
 }
 
+impl From<HeaderValue> for PieceCore {
+    #[inline(always)]
+    fn from(value: HeaderValue) -> Self {
+        PieceCore::HeaderValue(value)
+    }
+}
+
 impl Deref for PieceCore {
     type Target = [u8];
 
@@ -146,6 +154,7 @@ impl AsRef<[u8]> for PieceCore {
             PieceCore::Vec(vec) => vec.as_ref(),
             PieceCore::Roll(roll) => roll.as_ref(),
             PieceCore::HeaderName(name) => name.as_str().as_bytes(),
+            PieceCore::HeaderValue(value) => value.as_bytes(),
         }
     }
 }
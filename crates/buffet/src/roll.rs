@@ -1369,6 +1369,29 @@ mod tests {
         assert_eq!(roll, b"hello");
     }
 
+    #[test]
+    fn test_roll_outlives_roll_mut() {
+        crate::bufpool::initialize_allocator().unwrap();
+        let free_before = crate::bufpool::num_free();
+
+        let mut rm = RollMut::alloc().unwrap();
+        rm.put(b"hello").unwrap();
+        let roll = rm.take_all();
+        let filled = {
+            rm.put(b"world").unwrap();
+            rm.filled()
+        };
+        drop(rm);
+        assert_eq!(crate::bufpool::num_free(), free_before - 1);
+        assert_eq!(roll, b"hello");
+        assert_eq!(filled, b"world");
+
+        drop(roll);
+        assert_eq!(crate::bufpool::num_free(), free_before - 1);
+        drop(filled);
+        assert_eq!(crate::bufpool::num_free(), free_before);
+    }
+
     #[test]
     #[should_panic(expected = "take_all is pointless if the filled part is empty")]
     fn test_roll_take_all_empty() {
@@ -0,0 +1,432 @@
+//! Deterministic simulation: runs clients and servers on a single thread,
+//! against a virtual clock and an in-memory network that injects faults
+//! (latency, fragmentation, connection resets), all driven by one seed.
+//!
+//! Running the same code with the same seed does the same thing, down to
+//! which segment gets a connection reset, so a failure found with a random
+//! seed can be replayed. Timeouts cost nothing: the clock only moves forward
+//! when every task is waiting on it.
+//!
+//! ```no_run
+//! buffet::sim::run(buffet::sim::seed(), |net| async move {
+//!     let listener = net.bind("server:80").unwrap();
+//!     let client = net.connect("server:80").await.unwrap();
+//!     let server = listener.accept().await.unwrap();
+//!     // ...
+//! });
+//! ```
+//!
+//! Everything must go through [Net]: real sockets (and io_uring) aren't
+//! available in a simulation.
+
+use std::{
+    cell::{Cell, RefCell, RefMut},
+    collections::HashMap,
+    future::Future,
+    io,
+    rc::Rc,
+    time::Duration,
+};
+
+use tokio::sync::{mpsc, watch, Mutex};
+
+use crate::{pipe, IntoHalves, PipeRead, PipeWrite, WriteOwned};
+
+/// Environment variable [seed] reads the seed from, to replay a failure
+pub const SEED_ENV_VAR: &str = "BUFFET_SIM_SEED";
+
+/// Returns the seed from `$BUFFET_SIM_SEED` if it's set, or a random one
+/// otherwise.
+pub fn seed() -> u64 {
+    if let Ok(seed) = std::env::var(SEED_ENV_VAR) {
+        if let Ok(seed) = seed.parse() {
+            return seed;
+        }
+    }
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    Rng::new(now.as_nanos() as u64).next_u64()
+}
+
+/// Runs `f` in a simulation seeded with `seed`: on a current-thread runtime
+/// whose clock is paused (and auto-advances whenever all tasks are idle),
+/// with a [Net] to connect things with. Tasks can be spawned with
+/// [crate::spawn].
+///
+/// If it panics, the seed is printed so the run can be replayed.
+///
+/// Tokio picks `select!` branches at random: with `--cfg tokio_unstable`,
+/// that randomness is seeded from `seed` too. Without it, code that relies
+/// on which of several ready branches wins may not replay exactly.
+pub fn run<F, Fut>(seed: u64, f: F) -> Fut::Output
+where
+    F: FnOnce(Net) -> Fut,
+    Fut: Future,
+{
+    struct ReportSeed(u64);
+
+    impl Drop for ReportSeed {
+        fn drop(&mut self) {
+            if std::thread::panicking() {
+                eprintln!(
+                    "simulation failed with seed {}, replay it with {SEED_ENV_VAR}={}",
+                    self.0, self.0
+                );
+            }
+        }
+    }
+
+    let _report = ReportSeed(seed);
+    tracing::debug!(%seed, "starting simulation");
+
+    let mut builder = tokio::runtime::Builder::new_current_thread();
+    builder.enable_time().start_paused(true);
+    #[cfg(tokio_unstable)]
+    builder.rng_seed(tokio::runtime::RngSeed::from_bytes(&seed.to_le_bytes()));
+    let rt = builder.build().unwrap();
+
+    rt.block_on(async move {
+        crate::bufpool::initialize_allocator().unwrap();
+        let lset = tokio::task::LocalSet::new();
+        lset.run_until(f(Net::new(seed))).await
+    })
+}
+
+/// A small seedable random number generator (SplitMix64): good enough to
+/// pick faults, not for anything else.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..n`. `n` must not be zero.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    /// Returns `true` with probability `p`
+    pub fn chance(&mut self, p: f64) -> bool {
+        // 53 bits is all an f64 can hold
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < p
+    }
+
+    /// Returns a duration in `min..=max`
+    pub fn duration_between(&mut self, min: Duration, max: Duration) -> Duration {
+        if max <= min {
+            return min;
+        }
+        let span = (max - min).as_nanos() as u64;
+        min + Duration::from_nanos(self.below(span.saturating_add(1)))
+    }
+}
+
+/// What can go wrong on a simulated connection. The default is a perfect
+/// network: no latency, no fragmentation, no resets.
+#[derive(Debug, Clone, Copy)]
+pub struct Faults {
+    /// Shortest time a segment takes to reach the other end
+    pub min_latency: Duration,
+
+    /// Longest time a segment takes to reach the other end
+    pub max_latency: Duration,
+
+    /// Writes are cut into segments of random sizes, up to this many bytes
+    pub max_segment_size: usize,
+
+    /// Odds that any segment resets the connection (in both directions)
+    /// instead of being delivered
+    pub reset_probability: f64,
+}
+
+impl Default for Faults {
+    fn default() -> Self {
+        Self {
+            min_latency: Duration::ZERO,
+            max_latency: Duration::ZERO,
+            max_segment_size: usize::MAX,
+            reset_probability: 0.0,
+        }
+    }
+}
+
+/// An in-memory network: servers [Net::bind] to names (any string will do,
+/// e.g. `server:80`), and clients [Net::connect] to them. Connections suffer
+/// the network's [Faults].
+#[derive(Clone)]
+pub struct Net {
+    inner: Rc<NetInner>,
+}
+
+struct NetInner {
+    rng: RefCell<Rng>,
+    faults: Cell<Faults>,
+    listeners: RefCell<HashMap<String, mpsc::UnboundedSender<SimStream>>>,
+}
+
+impl Net {
+    /// A network without faults, seeded with `seed`. [run] makes one for
+    /// you.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            inner: Rc::new(NetInner {
+                rng: RefCell::new(Rng::new(seed)),
+                faults: Default::default(),
+                listeners: Default::default(),
+            }),
+        }
+    }
+
+    /// The network's random number generator, to make other choices
+    /// reproducible too. Don't hold on to it across an await point.
+    pub fn rng(&self) -> RefMut<'_, Rng> {
+        self.inner.rng.borrow_mut()
+    }
+
+    pub fn faults(&self) -> Faults {
+        self.inner.faults.get()
+    }
+
+    /// Changes the faults of connections opened from now on
+    pub fn set_faults(&self, faults: Faults) {
+        self.inner.faults.set(faults);
+    }
+
+    /// Starts accepting connections to `addr`. Fails if something is
+    /// already listening there.
+    pub fn bind(&self, addr: &str) -> io::Result<SimListener> {
+        let mut listeners = self.inner.listeners.borrow_mut();
+        if listeners.get(addr).is_some_and(|tx| !tx.is_closed()) {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("{addr} is already being listened on"),
+            ));
+        }
+        let (tx, rx) = mpsc::unbounded_channel();
+        listeners.insert(addr.to_string(), tx);
+        Ok(SimListener {
+            addr: addr.to_string(),
+            rx: Mutex::new(rx),
+        })
+    }
+
+    /// Connects to whatever is listening on `addr`, which takes one
+    /// latency. Fails with [io::ErrorKind::ConnectionRefused] if nothing
+    /// is.
+    pub async fn connect(&self, addr: &str) -> io::Result<SimStream> {
+        let faults = self.faults();
+        let latency = self
+            .rng()
+            .duration_between(faults.min_latency, faults.max_latency);
+        tokio::time::sleep(latency).await;
+
+        let refused = || {
+            io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("nothing is listening on {addr}"),
+            )
+        };
+        let tx = self
+            .inner
+            .listeners
+            .borrow()
+            .get(addr)
+            .cloned()
+            .ok_or_else(refused)?;
+        let (ours, theirs) = self.link();
+        tx.send(theirs).map_err(|_| refused())?;
+        Ok(ours)
+    }
+
+    /// Opens a connection without going through a listener, returning both
+    /// ends of it. Must be called from within [run], since data is carried
+    /// across by tasks.
+    pub fn link(&self) -> (SimStream, SimStream) {
+        let faults = self.faults();
+        let (reset_tx, _) = watch::channel(false);
+        let reset_tx = Rc::new(reset_tx);
+
+        let (a_write, a_wire) = pipe();
+        let (b_wire, b_read) = pipe();
+        crate::spawn(wire(self.clone(), faults, a_wire, b_wire, reset_tx.clone()));
+
+        let (b_write, b_wire) = pipe();
+        let (a_wire, a_read) = pipe();
+        crate::spawn(wire(self.clone(), faults, b_wire, a_wire, reset_tx));
+
+        (
+            SimStream {
+                read: a_read,
+                write: a_write,
+            },
+            SimStream {
+                read: b_read,
+                write: b_write,
+            },
+        )
+    }
+}
+
+/// Carries segments from one end of a connection to the other, injecting
+/// faults along the way
+async fn wire(
+    net: Net,
+    faults: Faults,
+    mut from: PipeRead,
+    mut to: PipeWrite,
+    reset_tx: Rc<watch::Sender<bool>>,
+) {
+    let mut reset_rx = reset_tx.subscribe();
+    let forward = async {
+        loop {
+            let mut piece = match from.next_piece().await {
+                Ok(Some(piece)) => piece,
+                // the writer is done: dropping `to` tells the reader
+                Ok(None) => return false,
+                Err(_) => return true,
+            };
+            while !piece.is_empty() {
+                let (segment, rest, latency, reset) = {
+                    let mut rng = net.rng();
+                    let max = faults.max_segment_size.clamp(1, piece.len());
+                    let len = 1 + rng.below(max as u64) as usize;
+                    let (segment, rest) = piece.split_at(len);
+                    let latency = rng.duration_between(faults.min_latency, faults.max_latency);
+                    (segment, rest, latency, rng.chance(faults.reset_probability))
+                };
+                tokio::time::sleep(latency).await;
+                if reset {
+                    tracing::debug!("simulated network is resetting a connection");
+                    return true;
+                }
+                if to.write_all_owned(segment).await.is_err() {
+                    // nobody's reading anymore: dropping `from` tells the writer
+                    return false;
+                }
+                piece = rest;
+            }
+        }
+    };
+
+    let reset = tokio::select! {
+        reset = forward => reset,
+        _ = reset_rx.wait_for(|reset| *reset) => true,
+    };
+    if reset {
+        reset_tx.send_replace(true);
+        to.reset().await;
+    }
+}
+
+/// Accepts connections made with [Net::connect]
+pub struct SimListener {
+    addr: String,
+    rx: Mutex<mpsc::UnboundedReceiver<SimStream>>,
+}
+
+impl SimListener {
+    pub async fn accept(&self) -> io::Result<SimStream> {
+        self.rx
+            .lock()
+            .await
+            .recv()
+            .await
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "the network is gone"))
+    }
+
+    /// The address this listener is bound to
+    pub fn local_addr(&self) -> &str {
+        &self.addr
+    }
+}
+
+/// One end of a simulated connection
+pub struct SimStream {
+    read: PipeRead,
+    write: PipeWrite,
+}
+
+impl IntoHalves for SimStream {
+    type Read = PipeRead;
+    type Write = PipeWrite;
+
+    fn into_halves(self) -> (Self::Read, Self::Write) {
+        (self.read, self.write)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{run, Faults};
+    use crate::{IntoHalves, ReadOwned, WriteOwned};
+
+    /// Sends a few KiB through a faulty link, returning what made it
+    /// across and how long (in virtual time) it took
+    fn send_through_faults(seed: u64) -> (Vec<u8>, Duration) {
+        run(seed, |net| async move {
+            net.set_faults(Faults {
+                min_latency: Duration::from_millis(1),
+                max_latency: Duration::from_millis(30),
+                max_segment_size: 100,
+                reset_probability: 0.01,
+            });
+            let listener = net.bind("server:80").unwrap();
+            let start = tokio::time::Instant::now();
+            let (_, mut write) = net.connect("server:80").await.unwrap().into_halves();
+            let (mut read, _) = listener.accept().await.unwrap().into_halves();
+
+            crate::spawn(async move {
+                for i in 0..64u8 {
+                    if write.write_all_owned(vec![i; 64]).await.is_err() {
+                        break;
+                    }
+                }
+            });
+
+            let mut received = vec![];
+            loop {
+                let (res, buf) = read.read_owned(vec![0u8; 256]).await;
+                match res {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => received.extend_from_slice(&buf[..n]),
+                }
+            }
+            (received, start.elapsed())
+        })
+    }
+
+    #[test]
+    fn same_seed_same_run() {
+        let (received, elapsed) = send_through_faults(42);
+        assert!(elapsed > Duration::from_millis(64));
+        assert_eq!(send_through_faults(42), (received, elapsed));
+    }
+
+    #[test]
+    fn connect_needs_a_listener() {
+        run(7, |net| async move {
+            let err = net.connect("nowhere:80").await.err().unwrap();
+            assert_eq!(err.kind(), std::io::ErrorKind::ConnectionRefused);
+
+            let listener = net.bind("somewhere:80").unwrap();
+            assert!(net.bind("somewhere:80").is_err());
+            drop(listener);
+            assert!(net.bind("somewhere:80").is_ok());
+        })
+    }
+}
@@ -0,0 +1,202 @@
+//! A compliance matrix, for projects to publish like h2spec reports: every
+//! requirement the suite knows of, and whether the server meets it.
+//! Requirements are what tests quote from their RFC (cf.
+//! [httpwg::Test::citation]), and sections no test checks show up as not
+//! covered.
+
+use std::fmt::Write;
+
+use httpwg::Section;
+use serde::Serialize;
+
+use crate::report::{Report, Status};
+
+/// Whether the server meets a requirement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    /// some of its tests passed, and none failed
+    Pass,
+    /// some of its tests failed, known failures and flaky ones included
+    Fail,
+    /// none of its tests ran, or it has none
+    NotCovered,
+}
+
+impl Verdict {
+    fn as_markdown(&self) -> &'static str {
+        match self {
+            Verdict::Pass => "✅ pass",
+            Verdict::Fail => "❌ fail",
+            Verdict::NotCovered => "⬜ not covered",
+        }
+    }
+}
+
+/// One requirement, or one section without any
+#[derive(Debug, Serialize)]
+pub struct Entry {
+    pub rfc: &'static str,
+    /// e.g. "6.5.2"
+    pub section: &'static str,
+    pub title: &'static str,
+    /// "must" or "should", unless the section has no tests
+    pub requirement: Option<&'static str>,
+    /// what the RFC says, unless the section has no tests
+    pub text: Option<&'static str>,
+    pub verdict: Verdict,
+    pub tests: Vec<TestVerdict>,
+}
+
+/// A test checking a requirement, and how it fared
+#[derive(Debug, Serialize)]
+pub struct TestVerdict {
+    /// e.g. "http2/6.7/1", cf. [httpwg::Test::id]
+    pub id: &'static str,
+    pub test: String,
+    pub status: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Matrix {
+    pub server: String,
+    pub requirements: Vec<Entry>,
+}
+
+impl Matrix {
+    /// Maps every requirement of `sections` to the tests of `report` that
+    /// check it
+    pub fn new(server: &str, sections: &[Section], report: &Report) -> Self {
+        let mut requirements = vec![];
+        for section in sections {
+            for &text in section.requirements {
+                let tests: Vec<_> = report
+                    .tests
+                    .iter()
+                    .filter(|t| {
+                        t.rfc == section.rfc
+                            && t.spec_section == section.number
+                            && t.citation == text
+                    })
+                    .collect();
+                let failed = tests.iter().any(|t| {
+                    matches!(
+                        t.status,
+                        Status::Failed { .. } | Status::KnownFailure { .. } | Status::Flaky { .. }
+                    )
+                });
+                let passed = tests.iter().any(|t| matches!(t.status, Status::Passed));
+                let verdict = match (failed, passed) {
+                    (true, _) => Verdict::Fail,
+                    (false, true) => Verdict::Pass,
+                    (false, false) => Verdict::NotCovered,
+                };
+                requirements.push(Entry {
+                    rfc: section.rfc,
+                    section: section.number,
+                    title: section.title,
+                    requirement: tests
+                        .iter()
+                        .map(|t| t.requirement)
+                        .max_by_key(|r| *r == "must"),
+                    text: Some(text),
+                    verdict,
+                    tests: tests
+                        .iter()
+                        .map(|t| TestVerdict {
+                            id: t.id,
+                            test: t.test.clone(),
+                            status: t.status.as_str(),
+                        })
+                        .collect(),
+                });
+            }
+
+            // "6.5" has nothing of its own to check, "6.5.1" does
+            let subsection = format!("{}.", section.number);
+            let has_subsections = sections
+                .iter()
+                .any(|s| s.rfc == section.rfc && s.number.starts_with(&subsection));
+            if section.requirements.is_empty() && !has_subsections {
+                requirements.push(Entry {
+                    rfc: section.rfc,
+                    section: section.number,
+                    title: section.title,
+                    requirement: None,
+                    text: None,
+                    verdict: Verdict::NotCovered,
+                    tests: vec![],
+                });
+            }
+        }
+
+        Self {
+            server: server.to_owned(),
+            requirements,
+        }
+    }
+
+    pub fn count(&self, verdict: Verdict) -> usize {
+        self.requirements
+            .iter()
+            .filter(|e| e.verdict == verdict)
+            .count()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("matrices serialize")
+    }
+
+    /// One table per RFC, one row per requirement
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        // writing to a String can't fail
+        _ = writeln!(out, "# Compliance of {}", md_escape(&self.server));
+        _ = writeln!(out);
+        _ = writeln!(
+            out,
+            "{} requirements met, {} not met, {} not covered.",
+            self.count(Verdict::Pass),
+            self.count(Verdict::Fail),
+            self.count(Verdict::NotCovered),
+        );
+
+        let mut rfc = None;
+        for e in &self.requirements {
+            if rfc != Some(e.rfc) {
+                rfc = Some(e.rfc);
+                _ = writeln!(out);
+                _ = writeln!(out, "## {}", e.rfc);
+                _ = writeln!(out);
+                _ = writeln!(out, "| Section | Requirement | Level | Verdict | Tests |");
+                _ = writeln!(out, "| --- | --- | --- | --- | --- |");
+            }
+            let tests = e
+                .tests
+                .iter()
+                .map(|t| format!("`{}` {} ({})", t.id, md_escape(&t.test), t.status))
+                .collect::<Vec<_>>()
+                .join("<br>");
+            _ = writeln!(
+                out,
+                "| {} {} | {} | {} | {} | {} |",
+                e.section,
+                md_escape(e.title),
+                md_escape(e.text.unwrap_or_default()),
+                e.requirement.unwrap_or_default().to_uppercase(),
+                e.verdict.as_markdown(),
+                tests,
+            );
+        }
+        out
+    }
+}
+
+/// Keeps text from breaking out of its table cell
+fn md_escape(s: &str) -> String {
+    s.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('|', "\\|")
+        .replace('<', "&lt;")
+}
@@ -1,11 +1,36 @@
 use std::{
-    cell::RefCell, collections::HashMap, ffi::OsString, net::SocketAddr, rc::Rc, time::Duration,
+    cell::RefCell,
+    collections::HashSet,
+    ffi::OsString,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
-use buffet::{net::TcpStream, IntoHalves};
-use httpwg::{Config, Conn};
+use base64::Engine;
+use buffet::IntoHalves;
+use compliance::{Matrix, Verdict};
+use httpwg::{
+    bench::Load,
+    capture::{Captured, Recorder},
+    chunking::Chunking,
+    stress::Stress,
+    target::{Subprocess, Target},
+    tls::TlsStream,
+    BoxedTest, Config, Conn, Connector, Group, Requirement, Suite, Test,
+};
+use report::{Report, Spec, Status};
+use rustls::RootCertStore;
+use tokio::sync::Semaphore;
 use tracing::Level;
 use tracing_subscriber::{filter::Targets, layer::SubscriberExt, util::SubscriberInitExt};
+use transport::{Endpoint, Stream};
+
+mod compliance;
+mod report;
+mod transport;
 
 #[derive(Default, Debug)]
 struct Args {
@@ -15,17 +40,126 @@ struct Args {
     /// the address/port the binary will listen on
     server_address: Option<SocketAddr>,
 
+    /// the Unix socket the binary will listen on, instead of an address
+    unix_socket: Option<PathBuf>,
+
+    /// environment variables to set for the binary
+    server_env: Vec<(String, String)>,
+
+    /// when to (re)start the binary
+    restart: Restart,
+
     /// the timeout for connections (in milliseconds)
     connect_timeout: Option<u64>,
 
     /// the timeout to wait for a frame (in milliseconds)
     frame_timeout: Option<u64>,
 
+    /// how long the server has to acknowledge our SETTINGS (in milliseconds)
+    settings_ack_timeout: Option<u64>,
+
+    /// how long soak tests keep a connection idle (in milliseconds)
+    soak: Option<u64>,
+
+    /// how many streams the rapid reset test opens and resets
+    rapid_reset_streams: Option<u32>,
+
+    /// how many streams per second the rapid reset test opens and resets
+    rapid_reset_rate: Option<u32>,
+
+    /// how to split what we send into writes
+    chunking: Option<Chunking>,
+
+    /// how many times to run each test, to catch flaky ones
+    repeat: Option<u32>,
+
+    /// how many tests to run at once
+    jobs: Option<usize>,
+
+    /// whether to benchmark the server rather than test it
+    bench: bool,
+
+    /// the load to benchmark the server with
+    load: Load,
+
+    /// whether to stress the server rather than test it
+    stress: bool,
+
+    /// the load to stress the server with, cf. `--stress`
+    stress_load: Stress,
+
+    /// by how much (in percent) the server's memory use or p99 latency may
+    /// grow over a stress run
+    max_growth: Option<f64>,
+
     /// which tests to run
     filter: Option<String>,
 
     /// whether to print verbose output
     verbose: bool,
+
+    /// whether to connect over TLS (and require ALPN to pick h2)
+    tls: bool,
+
+    /// whether to accept any certificate, e.g. a self-signed one
+    insecure: bool,
+
+    /// PEM file with the certificates to check the server's against
+    ca_cert: Option<PathBuf>,
+
+    /// the name to send with SNI and check the certificate against
+    server_name: Option<String>,
+
+    /// where to write results as JUnit XML
+    junit: Option<PathBuf>,
+
+    /// where to write results as JSON
+    json: Option<PathBuf>,
+
+    /// where to write a compliance matrix, cf. `compliance`
+    compliance: Option<PathBuf>,
+
+    /// only run tests for requirements at least this strict
+    level: Option<Requirement>,
+
+    /// tests the server is known to fail, one per line
+    known_failures: Option<PathBuf>,
+
+    /// where to dump the frames of failed tests
+    dump_failures: Option<PathBuf>,
+
+    /// a frame dump to print as a trace, and to replay against the server
+    /// if there's one
+    replay: Option<PathBuf>,
+}
+
+/// When to (re)start the server under test, cf. `--restart`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Restart {
+    /// Once, before the first test
+    #[default]
+    Never,
+
+    /// Whenever it's not running anymore, e.g. because a test crashed it
+    Crashed,
+
+    /// Before every test, so that each test gets a fresh server
+    Each,
+}
+
+impl std::str::FromStr for Restart {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "never" => Ok(Restart::Never),
+            "crashed" => Ok(Restart::Crashed),
+            "each" => Ok(Restart::Each),
+            _ => Err(eyre::eyre!(
+                "unknown restart policy {s:?}, expected never, crashed or each"
+            )),
+        }
+    }
 }
 
 pub trait IntoStringResult {
@@ -63,6 +197,19 @@ fn parse_args() -> eyre::Result<Args> {
                     }
                 });
             }
+            lexopt::Arg::Long("unix") => {
+                args.unix_socket = Some(PathBuf::from(parser.value()?.into_string_result()?));
+            }
+            lexopt::Arg::Long("server-env") => {
+                let value = parser.value()?.into_string_result()?;
+                let Some((key, value)) = value.split_once('=') else {
+                    eyre::bail!("Expected KEY=VALUE for --server-env, got {value:?}");
+                };
+                args.server_env.push((key.to_owned(), value.to_owned()));
+            }
+            lexopt::Arg::Long("restart") => {
+                args.restart = parser.value()?.into_string_result()?.parse()?;
+            }
             lexopt::Arg::Long("frame-timeout") => {
                 args.frame_timeout = Some(
                     parser
@@ -72,6 +219,14 @@ fn parse_args() -> eyre::Result<Args> {
                         .map_err(|e| eyre::eyre!("Failed to parse frame timeout: {}", e))?,
                 );
             }
+            lexopt::Arg::Long("settings-ack-timeout") => {
+                args.settings_ack_timeout =
+                    Some(
+                        parser.value()?.into_string_result()?.parse().map_err(|e| {
+                            eyre::eyre!("Failed to parse settings ack timeout: {}", e)
+                        })?,
+                    );
+            }
             lexopt::Arg::Long("connect-timeout") => {
                 args.connect_timeout = Some(
                     parser
@@ -81,18 +236,171 @@ fn parse_args() -> eyre::Result<Args> {
                         .map_err(|e| eyre::eyre!("Failed to parse connect timeout: {}", e))?,
                 );
             }
+            lexopt::Arg::Long("soak") => {
+                args.soak = Some(
+                    parser
+                        .value()?
+                        .into_string_result()?
+                        .parse()
+                        .map_err(|e| eyre::eyre!("Failed to parse soak duration: {}", e))?,
+                );
+            }
+            lexopt::Arg::Long("rapid-reset-streams") => {
+                args.rapid_reset_streams =
+                    Some(
+                        parser.value()?.into_string_result()?.parse().map_err(|e| {
+                            eyre::eyre!("Failed to parse rapid reset streams: {}", e)
+                        })?,
+                    );
+            }
+            lexopt::Arg::Long("rapid-reset-rate") => {
+                args.rapid_reset_rate = Some(
+                    parser
+                        .value()?
+                        .into_string_result()?
+                        .parse()
+                        .map_err(|e| eyre::eyre!("Failed to parse rapid reset rate: {}", e))?,
+                );
+            }
+            lexopt::Arg::Long("repeat") => {
+                let repeat: u32 = parser
+                    .value()?
+                    .into_string_result()?
+                    .parse()
+                    .map_err(|e| eyre::eyre!("Failed to parse repeat count: {}", e))?;
+                if repeat == 0 {
+                    eyre::bail!("--repeat needs to run tests at least once");
+                }
+                args.repeat = Some(repeat);
+            }
+            lexopt::Arg::Long("jobs") | lexopt::Arg::Short('j') => {
+                let jobs: usize = parser
+                    .value()?
+                    .into_string_result()?
+                    .parse()
+                    .map_err(|e| eyre::eyre!("Failed to parse job count: {}", e))?;
+                if jobs == 0 {
+                    eyre::bail!("--jobs needs to run at least one test at a time");
+                }
+                args.jobs = Some(jobs);
+            }
+            lexopt::Arg::Long("chunking") => {
+                args.chunking = Some(parser.value()?.into_string_result()?.parse()?);
+            }
+            lexopt::Arg::Long("bench") => {
+                args.bench = true;
+            }
+            lexopt::Arg::Long("connections") => {
+                args.load.connections = parser
+                    .value()?
+                    .into_string_result()?
+                    .parse()
+                    .map_err(|e| eyre::eyre!("Failed to parse connections: {}", e))?;
+                args.stress_load.connections = args.load.connections;
+            }
+            lexopt::Arg::Long("streams") => {
+                args.load.streams = parser
+                    .value()?
+                    .into_string_result()?
+                    .parse()
+                    .map_err(|e| eyre::eyre!("Failed to parse streams: {}", e))?;
+                args.stress_load.streams = args.load.streams;
+            }
+            lexopt::Arg::Long("requests") => {
+                args.load.requests = parser
+                    .value()?
+                    .into_string_result()?
+                    .parse()
+                    .map_err(|e| eyre::eyre!("Failed to parse requests: {}", e))?;
+            }
+            lexopt::Arg::Long("body") => {
+                args.load.body_len = parser
+                    .value()?
+                    .into_string_result()?
+                    .parse()
+                    .map_err(|e| eyre::eyre!("Failed to parse body size: {}", e))?;
+            }
+            lexopt::Arg::Long("stress") => {
+                args.stress = true;
+                let secs: u64 = parser
+                    .value()?
+                    .into_string_result()?
+                    .parse()
+                    .map_err(|e| eyre::eyre!("Failed to parse stress duration: {}", e))?;
+                args.stress_load.duration = Duration::from_secs(secs);
+            }
+            lexopt::Arg::Long("body-sizes") => {
+                args.stress_load.body_sizes = parser
+                    .value()?
+                    .into_string_result()?
+                    .split(',')
+                    .map(|size| size.trim().parse())
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| eyre::eyre!("Failed to parse body sizes: {}", e))?;
+            }
+            lexopt::Arg::Long("max-growth") => {
+                args.max_growth = Some(
+                    parser
+                        .value()?
+                        .into_string_result()?
+                        .parse()
+                        .map_err(|e| eyre::eyre!("Failed to parse max growth: {}", e))?,
+                );
+            }
             lexopt::Arg::Long("filter") | lexopt::Arg::Short('f') => {
                 args.filter = Some(parser.value()?.into_string_result()?);
             }
             lexopt::Arg::Long("verbose") | lexopt::Arg::Short('v') => {
                 args.verbose = true;
             }
+            lexopt::Arg::Long("tls") => {
+                args.tls = true;
+            }
+            lexopt::Arg::Long("insecure") | lexopt::Arg::Short('k') => {
+                args.insecure = true;
+            }
+            lexopt::Arg::Long("ca-cert") => {
+                args.ca_cert = Some(PathBuf::from(parser.value()?.into_string_result()?));
+            }
+            lexopt::Arg::Long("server-name") => {
+                args.server_name = Some(parser.value()?.into_string_result()?);
+            }
+            lexopt::Arg::Long("junit") => {
+                args.junit = Some(PathBuf::from(parser.value()?.into_string_result()?));
+            }
+            lexopt::Arg::Long("json") => {
+                args.json = Some(PathBuf::from(parser.value()?.into_string_result()?));
+            }
+            lexopt::Arg::Long("compliance") => {
+                args.compliance = Some(PathBuf::from(parser.value()?.into_string_result()?));
+            }
+            lexopt::Arg::Long("level") => {
+                args.level = Some(parser.value()?.into_string_result()?.parse()?);
+            }
+            lexopt::Arg::Long("known-failures") => {
+                args.known_failures = Some(PathBuf::from(parser.value()?.into_string_result()?));
+            }
+            lexopt::Arg::Long("dump-failures") => {
+                args.dump_failures = Some(PathBuf::from(parser.value()?.into_string_result()?));
+            }
+            lexopt::Arg::Long("replay") => {
+                args.replay = Some(PathBuf::from(parser.value()?.into_string_result()?));
+            }
             lexopt::Arg::Value(value) => {
                 args.server_binary.push(value.into_string_result()?);
             }
             _ => return Err(arg.unexpected().into()),
         }
     }
+    if args.server_address.is_some() && args.unix_socket.is_some() {
+        eyre::bail!("--address and --unix are mutually exclusive");
+    }
+    if (args.insecure || args.ca_cert.is_some()) && !args.tls {
+        eyre::bail!("--insecure and --ca-cert only make sense with --tls");
+    }
+    if args.tls && !args.insecure && args.ca_cert.is_none() {
+        eyre::bail!("--tls needs either --ca-cert or --insecure");
+    }
     Ok(args)
 }
 
@@ -102,10 +410,75 @@ fn print_usage() -> eyre::Result<()> {
 
 Options:
     -a, --address <ADDRESS>    The address/port the server will listen on
+    --unix <PATH>              The Unix socket the server will listen on,
+                               instead of an address
+    --server-env <KEY=VALUE>   Set an environment variable for the server
+                               (can be repeated)
+    --restart <POLICY>         When to (re)start the server: never (the
+                               default), crashed (once a test crashed it), or
+                               each (before every test). Tests run one at a
+                               time unless it's never, or there's a server
+                               per job
     --connect-timeout <MS>     The timeout for connections in milliseconds
     --frame-timeout <MS>       The timeout to wait for a frame in milliseconds
-    -f, --filter <FILTER>      Which tests to run
+    --settings-ack-timeout <MS>
+                               How long the server has to acknowledge our
+                               SETTINGS frames (default: 1000)
+    --soak <MS>                How long soak tests keep a connection idle,
+                               pinging it now and then (default: 1000)
+    --rapid-reset-streams <N>  How many streams the rapid reset test opens and
+                               resets (default: 10000)
+    --rapid-reset-rate <N>     How many streams per second it opens and resets
+                               (default: as fast as possible)
+    --chunking <STRATEGY>      Send everything in awkward writes: byte (one byte
+                               at a time), mid-header (frames split inside
+                               their header), <SIZE> (writes of SIZE bytes),
+                               or <SIZE>:<MS> (with MS-long pauses in between)
+    -f, --filter <FILTER>      Which tests to run: those whose name contains
+                               FILTER, or whose id starts with it, e.g. http2/6.7
+    -j, --jobs <N>             How many tests to run at once, each over a
+                               connection of its own (default: all of them).
+                               If the server's arguments or --server-env
+                               mention {{port}}, each job gets a server of its
+                               own, on the address's port plus 0..N
+    --repeat <N>               Run each test N times, over a fresh connection
+                               each time, and report the ones that only fail
+                               some of the time as flaky (default: 1)
+    --bench                    Benchmark the server instead: report requests per
+                               second and latency percentiles
+    --connections <N>          How many connections to benchmark with (default: 1)
+    --streams <N>              How many requests each keeps in flight (default: 10)
+    --requests <N>             How many requests each sends (default: 10000)
+    --body <BYTES>             The size of request bodies (default: 0, for GETs)
+    --stress <SECS>            Stress the server instead, for SECS seconds: keep
+                               --connections (default: 8) busy with --streams
+                               (default: 20) each, replacing every connection
+                               after 1000 requests, and sample latency and the
+                               server's memory use along the way
+    --body-sizes <LIST>        The request body sizes to cycle through under
+                               --stress (default: 0,100,1000,10000)
+    --max-growth <PERCENT>     Fail a --stress run if the server's memory use or
+                               p99 latency grew by more than PERCENT
     -v, --verbose              Print verbose output
+    --tls                      Connect over TLS, the server must pick h2 with ALPN
+    -k, --insecure             Accept any certificate, e.g. a self-signed one
+    --ca-cert <FILE>           Check the server's certificate against these (PEM)
+    --server-name <NAME>       The name to send with SNI (default: localhost)
+    --junit <FILE>             Also write results to FILE, as JUnit XML
+    --json <FILE>              Also write results to FILE, as JSON
+    --compliance <FILE>        Also write a compliance matrix to FILE: every
+                               requirement the suite knows of, quoted from
+                               its RFC, and whether the server meets it. As
+                               Markdown if FILE ends in .md, as JSON otherwise
+    --level <must|should>      Only run tests for MUSTs, or for SHOULDs too (default)
+    --known-failures <FILE>    Tests expected to fail, one per line, by id or by
+                               name: report them as xfail rather than failing
+                               the run
+    --dump-failures <DIR>      Record every frame, and dump those of failed tests
+                               to DIR, as JSON and in binary
+    --replay <FILE>            Print a binary frame dump as a trace, then send
+                               what we sent in it to the server (if there's
+                               one) and print that trace too
 
 Arguments:
     SERVER                     The server to run tests against
@@ -114,6 +487,18 @@ Arguments:
 Examples:
     httpwg-test-suite -a 127.0.0.1:8080 -- ./my_server
     httpwg-test-suite -f 'RFC 9113' -- ./my_server --go-fast
+    httpwg-test-suite --tls --insecure -a 127.0.0.1:8443
+    httpwg-test-suite --unix /tmp/my_server.sock -- ./my_server
+    httpwg-test-suite --junit results.xml -a 127.0.0.1:8080 -- ./my_server
+    httpwg-test-suite --compliance COMPLIANCE.md -- ./my_server
+    httpwg-test-suite --level must --known-failures known.txt -- ./my_server
+    httpwg-test-suite --restart crashed --server-env RUST_LOG=debug -- ./my_server
+    httpwg-test-suite -j 8 --restart each -a 127.0.0.1:8000 -- ./my_server --port {{port}}
+    httpwg-test-suite --chunking byte -f 'RFC 9113' -- ./my_server
+    httpwg-test-suite --repeat 20 -f '5.1' -- ./my_server
+    httpwg-test-suite --bench --connections 4 --streams 32 -- ./my_server
+    httpwg-test-suite --stress 600 --max-growth 20 -- ./my_server
+    httpwg-test-suite --replay dumps/sends_ping_frame.bin -- ./my_server
 "
     );
     Ok(())
@@ -136,11 +521,32 @@ fn main() -> eyre::Result<()> {
 }
 
 async fn async_main(mut args: Args) -> eyre::Result<()> {
-    let cat = catalog::<buffet::net::TcpStream>();
+    // this works around an oddity of Just when forwarding positional arguments
+    args.server_binary.retain(|s| !s.is_empty());
+
+    let recorded = match &args.replay {
+        Some(path) => {
+            let bin = std::fs::read(path)
+                .map_err(|e| eyre::eyre!("could not read {}: {e}", path.display()))?;
+            let recorded = httpwg::capture::read_bin(&bin)
+                .map_err(|e| eyre::eyre!("could not read {}: {e}", path.display()))?;
+            eprintln!("📼 Recorded session, from {}:", path.display());
+            eprint!("{}", httpwg::capture::render_trace(&recorded));
+            if args.server_address.is_none()
+                && args.unix_socket.is_none()
+                && args.server_binary.is_empty()
+            {
+                return Ok(());
+            }
+            Some(recorded)
+        }
+        None => None,
+    };
 
-    let addr = match args.server_address {
-        Some(addr) => addr,
-        None => {
+    let endpoint = match (args.server_address, &args.unix_socket) {
+        (Some(addr), _) => Endpoint::Tcp(addr),
+        (None, Some(path)) => Endpoint::Unix(path.clone()),
+        (None, None) => {
             eprintln!("No address specified");
             print_usage()?;
             std::process::exit(1);
@@ -155,132 +561,277 @@ async fn async_main(mut args: Args) -> eyre::Result<()> {
         None => Duration::from_millis(250),
     };
     let conf = Rc::new(Config {
+        connect_timeout,
         timeout: frame_timeout,
+        settings_ack_timeout: match args.settings_ack_timeout {
+            Some(timeout) => Duration::from_millis(timeout),
+            None => Config::default().settings_ack_timeout,
+        },
+        tls: args.tls,
+        record_frames: args.dump_failures.is_some() || recorded.is_some(),
+        soak_duration: match args.soak {
+            Some(soak) => Duration::from_millis(soak),
+            None => Config::default().soak_duration,
+        },
+        rapid_reset_streams: args
+            .rapid_reset_streams
+            .unwrap_or(Config::default().rapid_reset_streams),
+        rapid_reset_rate: args.rapid_reset_rate,
+        chunking: args.chunking.clone(),
+        concurrency: args.jobs,
         ..Default::default()
     });
+    if let Some(dir) = &args.dump_failures {
+        std::fs::create_dir_all(dir)?;
+    }
 
-    eprintln!("Will run tests against {addr}");
+    eprintln!("Will run tests against {endpoint}");
 
-    // this works around an oddity of Just when forwarding positional arguments
-    args.server_binary.retain(|s| !s.is_empty());
-
-    let mut server_name = format!("a server listening on {addr}");
+    let mut server_name = format!("a server listening on {endpoint}");
 
+    let mut endpoints = vec![endpoint.clone()];
+    let mut targets = vec![];
     if !args.server_binary.is_empty() {
-        let binary_and_args = args.server_binary;
+        let binary_and_args = std::mem::take(&mut args.server_binary);
         let binary_name = &binary_and_args[0];
-        server_name = format!("{binary_name} listening on {addr}");
+        server_name = format!("{binary_name} listening on {endpoint}");
 
-        eprintln!(
-            "Launching ({}) now and waiting until it listens on {addr}",
-            binary_and_args.join(" ::: ")
-        );
-        let mut iter = binary_and_args.into_iter();
-        let mut cmd = std::process::Command::new(iter.next().unwrap());
-        for arg in iter {
-            cmd.arg(arg);
+        // with `{port}` on its command line, each job gets a server of its own
+        let per_job = binary_and_args
+            .iter()
+            .chain(args.server_env.iter().map(|(_, value)| value))
+            .any(|s| s.contains("{port}"));
+        if per_job {
+            let Endpoint::Tcp(addr) = endpoint else {
+                eyre::bail!("{{port}} needs --address: Unix sockets don't have ports");
+            };
+            let jobs = args.jobs.unwrap_or(1);
+            endpoints = (0..jobs)
+                .map(|i| {
+                    let port = u16::try_from(i)
+                        .ok()
+                        .and_then(|i| addr.port().checked_add(i))
+                        .ok_or_else(|| {
+                            eyre::eyre!("not enough ports after {addr} for {jobs} jobs")
+                        })?;
+                    Ok(Endpoint::Tcp(SocketAddr::new(addr.ip(), port)))
+                })
+                .collect::<eyre::Result<_>>()?;
+            if jobs > 1 {
+                server_name =
+                    format!("{jobs} instances of {binary_name}, listening on {endpoint} and up");
+            }
         }
-        #[cfg(target_os = "linux")]
-        unsafe {
-            // avoid zombie children on unix: no matter how the test runner dies,
-            // the server will die with it.
-            use std::os::unix::process::CommandExt;
-            cmd.pre_exec(|| {
-                let ret = libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGTERM);
-                if ret != 0 {
-                    panic!("prctl failed");
-                }
-                Ok(())
-            });
+
+        for endpoint in &endpoints {
+            let port = match endpoint {
+                Endpoint::Tcp(addr) => addr.port().to_string(),
+                Endpoint::Unix(_) => Default::default(),
+            };
+            let with_port = |s: &String| s.replace("{port}", &port);
+            eprintln!(
+                "Launching ({}) now and waiting until it listens on {endpoint}",
+                binary_and_args
+                    .iter()
+                    .map(with_port)
+                    .collect::<Vec<_>>()
+                    .join(" ::: ")
+            );
+            let mut iter = binary_and_args.iter().map(with_port);
+            let mut server = Subprocess::new(iter.next().unwrap(), endpoint.readiness()).args(iter);
+            for (key, value) in &args.server_env {
+                server = server.env(key, with_port(value));
+            }
+            server.start().await?;
+            targets.push(server);
         }
-        let mut child = cmd.spawn().expect("Failed to launch server");
         eprintln!("Server started");
-        std::thread::spawn(move || {
-            let status = child.wait().unwrap();
-            eprintln!("Server exited with status: {status:?}");
-        });
     } else {
+        if args.restart != Restart::Never {
+            eyre::bail!("--restart needs a server binary to run");
+        }
         eprintln!("No server binary specified");
-    };
 
-    let max_startup_time = Duration::from_secs(1);
-    let sleep_time = Duration::from_millis(100);
-    eprintln!("Waiting until server is listening on {addr} (up to {max_startup_time:?})");
-    let start = std::time::Instant::now();
-    loop {
-        match tokio::time::timeout(sleep_time, TcpStream::connect(addr)).await {
-            Ok(Ok(_)) => break,
-            _ => {
-                if start.elapsed() >= max_startup_time {
-                    panic!("Server did not start listening within {max_startup_time:?}");
+        let max_startup_time = Duration::from_secs(1);
+        let sleep_time = Duration::from_millis(100);
+        eprintln!("Waiting until server is listening on {endpoint} (up to {max_startup_time:?})");
+        let start = std::time::Instant::now();
+        loop {
+            match tokio::time::timeout(sleep_time, endpoint.connect()).await {
+                Ok(Ok(_)) => break,
+                _ => {
+                    if start.elapsed() >= max_startup_time {
+                        panic!("Server did not start listening within {max_startup_time:?}");
+                    }
+                    tokio::time::sleep(sleep_time).await
                 }
-                tokio::time::sleep(sleep_time).await
             }
         }
-    }
-
-    let mut local_set = tokio::task::LocalSet::new();
-
-    let sequential = std::env::var("SEQUENTIAL")
-        .map(|v| v == "1")
-        .unwrap_or(false);
+    };
 
-    let mut num_tests = 0;
-    let num_passed: Rc<RefCell<usize>> = Rc::new(RefCell::new(0));
+    let known_failures = match &args.known_failures {
+        Some(path) => load_known_failures(path)?,
+        None => Default::default(),
+    };
 
     let start_time = std::time::Instant::now();
+    if args.bench {
+        let result = if args.tls {
+            httpwg::bench::run(
+                conf,
+                tls_connector(&args, &endpoint, connect_timeout)?,
+                &args.load,
+            )
+            .await
+        } else {
+            httpwg::bench::run(
+                conf,
+                plain_connector(&endpoint, connect_timeout),
+                &args.load,
+            )
+            .await
+        };
+        stop_targets(targets).await?;
+        let report = result?;
+        eprintln!("🏎️ {report}, against \x1b[1;36m{server_name}\x1b[0m");
+        if report.failed > 0 {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
-    for (rfc, sections) in cat {
-        for (section, tests) in sections {
-            for (test, boxed_test) in tests {
-                let test_name = format!("{rfc} :: {section} :: {test}");
-                if let Some(filter) = &args.filter {
-                    if !test_name.contains(filter) {
-                        continue;
-                    }
-                }
+    if args.stress {
+        // with a server per job, the first one is the one under stress
+        let rss = || targets.first().and_then(Subprocess::rss);
+        let on_sample = |sample: &httpwg::stress::Sample| eprintln!("📈 {sample}");
+        let result = if args.tls {
+            httpwg::stress::run(
+                conf,
+                tls_connector(&args, &endpoint, connect_timeout)?,
+                &args.stress_load,
+                rss,
+                on_sample,
+            )
+            .await
+        } else {
+            httpwg::stress::run(
+                conf,
+                plain_connector(&endpoint, connect_timeout),
+                &args.stress_load,
+                rss,
+                on_sample,
+            )
+            .await
+        };
+        stop_targets(targets).await?;
+        let report = result?;
+        eprintln!("🏋️ {report}, against \x1b[1;36m{server_name}\x1b[0m");
+        if let Some(why) = &report.last_drop {
+            eprintln!("🔌 Last dropped connection: {why}");
+        }
+        let too_much = |growth: Option<f64>| {
+            args.max_growth
+                .is_some_and(|max| growth.is_some_and(|growth| growth > max))
+        };
+        if report.failed() > 0
+            || too_much(report.memory_growth())
+            || too_much(report.latency_growth())
+        {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
 
-                num_tests += 1;
-                let stream = tokio::time::timeout(connect_timeout, TcpStream::connect(addr))
-                    .await
-                    .unwrap_or_else(|_| {
-                        panic!(
-                            "tested server failed to accept connction within {connect_timeout:?}"
-                        )
-                    })
-                    .unwrap();
-                eprintln!("Holding {num_tests} connections");
-                let conn = Conn::new(conf.clone(), stream);
-                let num_passed = num_passed.clone();
-
-                let test = async move {
-                    if args.verbose {
-                        eprintln!("🔷 Running test: {}", test_name);
-                    }
-                    match boxed_test(conn).await {
-                        Ok(()) => {
-                            eprintln!("✅ Test passed: {}", test_name);
-                            {
-                                *num_passed.borrow_mut() += 1;
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("❌ Test failed: {}\n{e:?}", test_name);
-                        }
-                    }
-                };
-                local_set.spawn_local(test);
-                if sequential {
-                    (&mut local_set).await;
-                }
-            }
+    if let Some(recorded) = recorded {
+        let result = if args.tls {
+            replay(
+                conf,
+                tls_connector(&args, &endpoint, connect_timeout)?,
+                &recorded,
+            )
+            .await
+        } else {
+            replay(conf, plain_connector(&endpoint, connect_timeout), &recorded).await
+        };
+        stop_targets(targets).await?;
+        let replayed = result?;
+        eprintln!("🔁 Replayed against \x1b[1;36m{server_name}\x1b[0m:");
+        eprint!("{}", httpwg::capture::render_trace(&replayed.frames));
+        match replayed.ended {
+            Some(why) => eprintln!("🔌 Connection ended: {why}"),
+            None => eprintln!("💤 The server went quiet"),
         }
+        return Ok(());
     }
 
-    eprintln!("Awaiting local set");
-    local_set.await;
-    let num_passed = *num_passed.borrow();
+    let mut targets = targets.into_iter().map(Some).collect::<Vec<_>>();
+    targets.resize_with(endpoints.len(), || None);
+    let (report, targets) = if args.tls {
+        let instances = endpoints
+            .iter()
+            .zip(targets)
+            .map(|(endpoint, target)| {
+                Ok(Instance {
+                    target,
+                    connector: tls_connector(&args, endpoint, connect_timeout)?,
+                    fresh: true,
+                })
+            })
+            .collect::<eyre::Result<_>>()?;
+        run_catalog(
+            httpwg::all_tests::<TlsStream<Stream>>(),
+            &args,
+            &known_failures,
+            conf,
+            instances,
+        )
+        .await
+    } else {
+        let instances = endpoints
+            .iter()
+            .zip(targets)
+            .map(|(endpoint, target)| Instance {
+                target,
+                connector: plain_connector(endpoint, connect_timeout),
+                fresh: true,
+            })
+            .collect();
+        run_catalog(
+            httpwg::all_tests::<Stream>(),
+            &args,
+            &known_failures,
+            conf,
+            instances,
+        )
+        .await
+    };
+    stop_targets(targets).await?;
+
+    if let Some(path) = &args.junit {
+        std::fs::write(path, report.to_junit_xml())?;
+        eprintln!("Wrote JUnit XML results to {}", path.display());
+    }
+    if let Some(path) = &args.json {
+        std::fs::write(path, report.to_json())?;
+        eprintln!("Wrote JSON results to {}", path.display());
+    }
+    if let Some(path) = &args.compliance {
+        let matrix = Matrix::new(&server_name, &sections(), &report);
+        if path.extension().is_some_and(|ext| ext == "md") {
+            std::fs::write(path, matrix.to_markdown())?;
+        } else {
+            std::fs::write(path, matrix.to_json())?;
+        }
+        eprintln!(
+            "Wrote a compliance matrix to {} ({} requirements met, {} not met, {} not covered)",
+            path.display(),
+            matrix.count(Verdict::Pass),
+            matrix.count(Verdict::Fail),
+            matrix.count(Verdict::NotCovered),
+        );
+    }
 
+    let (num_passed, num_tests) = (report.num_passed(), report.num_run());
     eprintln!(
         "🚄 Passed \x1b[1;32m{}/{}\x1b[0m tests in \x1b[1;33m{:.2}\x1b[0m seconds against \x1b[1;36m{}\x1b[0m",
         num_passed,
@@ -288,8 +839,31 @@ async fn async_main(mut args: Args) -> eyre::Result<()> {
         start_time.elapsed().as_secs_f32(),
         server_name,
     );
+    let num_flaky = report.num_flaky();
+    if num_flaky > 0 {
+        eprintln!("🎲 {num_flaky} tests failed some of their runs but not all (flaky):");
+        for t in report
+            .tests
+            .iter()
+            .filter(|t| matches!(t.status, Status::Flaky { .. }))
+        {
+            if let Status::Flaky {
+                failed_runs, runs, ..
+            } = t.status
+            {
+                eprintln!(
+                    "   {} :: {} :: {} failed {failed_runs} of {runs} runs",
+                    t.rfc, t.section, t.test
+                );
+            }
+        }
+    }
+    let num_known_failures = report.num_known_failures();
+    if num_known_failures > 0 {
+        eprintln!("🚧 {num_known_failures} tests failed as expected (xfail)");
+    }
 
-    if num_passed != num_tests {
+    if report.num_failed() > 0 {
         eprintln!("❌ Some tests failed");
         std::process::exit(1);
     }
@@ -297,20 +871,465 @@ async fn async_main(mut args: Args) -> eyre::Result<()> {
     Ok(())
 }
 
-type Catalog<IO> =
-    HashMap<&'static str, HashMap<&'static str, HashMap<&'static str, BoxedTest<IO>>>>;
+/// A server tests run against, and how to connect to it. There's one per
+/// job if the server's command line mentions `{port}`.
+struct Instance<T, IO> {
+    /// the server, if we started it
+    target: Option<T>,
+    connector: Connector<IO>,
+    /// whether no test ran against it since it (re)started
+    fresh: bool,
+}
+
+/// Runs the tests of `suites` that match the filter and level, each over a
+/// connection of its own (tests may open more with their connector), and
+/// prints how each section fared. The others are recorded as skipped.
+///
+/// Up to [Config::concurrency] tests run at once, spread over `instances`.
+///
+/// With `--repeat`, each test runs that many times, over a fresh connection
+/// every time, and tests that fail only some of the time are flaky.
+///
+/// Unless `--restart` is `never`, each test gets an instance to itself, whose
+/// server is (re)started as needed before the test, and tests that leave it
+/// dead fail. Returns the servers, for the caller to stop.
+async fn run_catalog<IO, T>(
+    suites: Vec<Suite<IO>>,
+    args: &Args,
+    known_failures: &HashSet<String>,
+    conf: Rc<Config>,
+    instances: Vec<Instance<T, IO>>,
+) -> (Report, Vec<T>)
+where
+    IO: IntoHalves,
+    T: Target + 'static,
+{
+    let local_set = tokio::task::LocalSet::new();
+
+    let restart = args.restart;
+    let repeat = args.repeat.unwrap_or(1);
+    // a server that gets restarted can't have other tests running against it
+    let exclusive = restart != Restart::Never;
+    let sequential = std::env::var("SEQUENTIAL")
+        .map(|v| v == "1")
+        .unwrap_or(false);
+    let jobs = match (sequential, conf.concurrency) {
+        (true, _) => 1,
+        (false, Some(concurrency)) => concurrency.max(1),
+        (false, None) => Semaphore::MAX_PERMITS,
+    };
+    let jobs = Arc::new(Semaphore::new(jobs));
+
+    // the instances no test has to itself
+    let (idle_tx, mut idle_rx) = tokio::sync::mpsc::unbounded_channel();
+    for instance in instances {
+        _ = idle_tx.send(instance);
+    }
+
+    let report: Rc<RefCell<Report>> = Default::default();
+
+    let verbose = args.verbose;
+    let level = args.level.unwrap_or(Requirement::Should);
+    local_set
+        .run_until(async {
+            for Suite { rfc, groups } in suites {
+                for Group {
+                    title: section,
+                    tests,
+                } in groups
+                {
+                    for Test {
+                        name: test,
+                        id,
+                        requirement,
+                        section: spec_section,
+                        citation,
+                        run,
+                    } in tests
+                    {
+                        let spec = Spec {
+                            id,
+                            requirement,
+                            section: spec_section,
+                            citation,
+                        };
+                        let test_name = format!("{rfc} :: {section} :: {test}");
+                        let key = (rfc, section, test);
+                        let filtered_out = args.filter.as_ref().is_some_and(|filter| {
+                            !test_name.contains(filter) && !id_matches(id, filter)
+                        });
+                        if filtered_out || requirement < level {
+                            report.borrow_mut().record(
+                                key,
+                                0,
+                                spec,
+                                Status::Skipped,
+                                Duration::ZERO,
+                            );
+                            continue;
+                        }
+                        let known_failure = known_failures.contains(&test_name)
+                            || known_failures.contains(test)
+                            || known_failures.contains(id);
+                        let run = Rc::new(run);
+
+                        for iteration in 0..repeat {
+                            let run_name = if repeat > 1 {
+                                format!("{test_name} (run {}/{repeat})", iteration + 1)
+                            } else {
+                                test_name.clone()
+                            };
 
-#[allow(unused)]
-fn print_catalog<IO: IntoHalves>(cat: &Catalog<IO>) {
-    for (rfc, sections) in cat {
-        println!("📕 {}", rfc);
-        for (section, tests) in sections {
-            println!("  🔷 {}", section);
-            for test in tests.keys() {
-                println!("    📄 {}", test);
+                            let job = jobs.clone().acquire_owned().await.expect("never closed");
+                            let instance = idle_rx.recv().await.expect("we hold a sender");
+                            let connector = instance.connector.clone();
+                            let instance = if exclusive {
+                                Some(instance)
+                            } else {
+                                _ = idle_tx.send(instance);
+                                None
+                            };
+
+                            let test = run_one(RunOne {
+                                key,
+                                spec,
+                                iteration,
+                                run_name,
+                                run: run.clone(),
+                                conf: conf.clone(),
+                                connector,
+                                instance,
+                                restart,
+                                known_failure,
+                                verbose,
+                                dump_dir: args.dump_failures.clone(),
+                                report: report.clone(),
+                            });
+                            let idle_tx = idle_tx.clone();
+                            tokio::task::spawn_local(async move {
+                                if let Some(instance) = test.await {
+                                    _ = idle_tx.send(instance);
+                                }
+                                drop(job);
+                            });
+                        }
+                    }
+                }
             }
+        })
+        .await;
+
+    eprintln!("Awaiting local set");
+    local_set.await;
+    drop(idle_tx);
+    let mut targets = vec![];
+    while let Some(instance) = idle_rx.recv().await {
+        targets.extend(instance.target);
+    }
+    let mut report = Rc::into_inner(report)
+        .expect("all tests are done")
+        .into_inner();
+    report.merge_runs();
+
+    eprintln!("Results by section:");
+    for (section, tests) in report.by_section() {
+        let run = tests
+            .iter()
+            .filter(|t| !matches!(t.status, Status::Skipped))
+            .count();
+        if run == 0 {
+            continue;
         }
+        let passed = tests
+            .iter()
+            .filter(|t| matches!(t.status, Status::Passed))
+            .count();
+        let failed = tests
+            .iter()
+            .any(|t| matches!(t.status, Status::Failed { .. } | Status::Flaky { .. }));
+        let mark = match (passed == run, failed) {
+            (true, _) => "✅",
+            (false, false) => "🚧",
+            (false, true) => "❌",
+        };
+        eprintln!("{mark} {passed}/{run} {section}");
+    }
+
+    (report, targets)
+}
+
+/// One run of one test, cf. [run_one]
+struct RunOne<T, IO: IntoHalves> {
+    key: (&'static str, &'static str, &'static str),
+    spec: Spec,
+    iteration: u32,
+    run_name: String,
+    run: Rc<BoxedTest<IO>>,
+    conf: Rc<Config>,
+    connector: Connector<IO>,
+    /// the instance the test has to itself, if it does
+    instance: Option<Instance<T, IO>>,
+    restart: Restart,
+    known_failure: bool,
+    verbose: bool,
+    dump_dir: Option<PathBuf>,
+    report: Rc<RefCell<Report>>,
+}
+
+/// (Re)starts the test's server if need be, runs the test and records how
+/// it went. Hands the instance back, if it had one to itself.
+async fn run_one<T: Target, IO: IntoHalves>(r: RunOne<T, IO>) -> Option<Instance<T, IO>> {
+    let RunOne {
+        key,
+        spec,
+        iteration,
+        run_name,
+        run,
+        conf,
+        connector,
+        mut instance,
+        restart,
+        known_failure,
+        verbose,
+        dump_dir,
+        report,
+    } = r;
+
+    let start = Instant::now();
+    let failed = |message: String| {
+        if known_failure {
+            Status::KnownFailure { message }
+        } else {
+            Status::Failed { message }
+        }
+    };
+
+    let res = match instance.as_mut() {
+        Some(Instance {
+            target: Some(target),
+            fresh,
+            ..
+        }) => match restart {
+            Restart::Never => Ok(()),
+            Restart::Crashed => match target.check() {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    eprintln!("💥 {e}, restarting it");
+                    target.restart().await
+                }
+            },
+            // the first test gets the server we just started
+            Restart::Each if std::mem::replace(fresh, false) => Ok(()),
+            Restart::Each => target.restart().await,
+        },
+        _ => Ok(()),
+    };
+    if let Err(e) = res {
+        eprintln!("❌ Test failed: {run_name}\n{e:?}");
+        let status = failed(format!("could not start the server: {e:#}"));
+        report
+            .borrow_mut()
+            .record(key, iteration, spec, status, start.elapsed());
+        return instance;
+    }
+
+    let stream = match connector().await {
+        Ok(stream) => stream,
+        Err(e) => {
+            eprintln!("❌ Test failed: {run_name}\n{e:?}");
+            let status = failed(format!("{e:#}"));
+            report
+                .borrow_mut()
+                .record(key, iteration, spec, status, start.elapsed());
+            return instance;
+        }
+    };
+    let conn = Conn::new(conf, stream).with_connector(connector);
+    let recorder = conn.recorder();
+
+    if verbose {
+        eprintln!("🔷 Running test: {run_name}");
+    }
+    let status = match run(conn).await {
+        Ok(()) => {
+            eprintln!("✅ Test passed: {run_name}");
+            if known_failure {
+                eprintln!("⚠️ Known failure passed, it can come off the list: {run_name}");
+            }
+            Status::Passed
+        }
+        Err(e) if known_failure => {
+            eprintln!("🚧 Test failed as expected: {run_name}\n{e:?}");
+            failed(format!("{e:#}"))
+        }
+        Err(e) => {
+            eprintln!("❌ Test failed: {run_name}\n{e:?}");
+            failed(format!("{e:#}"))
+        }
+    };
+    if let (Some(dir), Some(recorder)) = (dump_dir, recorder) {
+        if !matches!(status, Status::Passed) {
+            dump_frames(&dir, &run_name, &recorder);
+        }
+    }
+    report
+        .borrow_mut()
+        .record(key, iteration, spec, status, start.elapsed());
+
+    if restart != Restart::Never {
+        if let Some(Err(e)) = instance
+            .as_mut()
+            .and_then(|i| i.target.as_mut())
+            .map(|t| t.check())
+        {
+            eprintln!("💥 {run_name} crashed the server: {e}");
+            report.borrow_mut().fail(
+                key,
+                iteration,
+                spec,
+                format!("the server crashed during the test: {e:#}"),
+                known_failure,
+                start.elapsed(),
+            );
+        }
+    }
+    instance
+}
+
+/// Writes the frames of a failed test to `dir`, as `<test>.json` and
+/// `<test>.bin` (cf. `httpwg::capture`)
+fn dump_frames(dir: &Path, test_name: &str, recorder: &Recorder) {
+    let stem = test_name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("_");
+    for (ext, contents) in [
+        ("json", recorder.to_json().into_bytes()),
+        ("bin", recorder.to_bin()),
+    ] {
+        let path = dir.join(format!("{stem}.{ext}"));
+        match std::fs::write(&path, contents) {
+            Ok(()) => eprintln!("📼 Dumped frames to {}", path.display()),
+            Err(e) => eprintln!("⚠️ Could not dump frames to {}: {e}", path.display()),
+        }
+    }
+}
+
+/// Sends the client side of `recorded` over a connection of its own, cf.
+/// `httpwg::replay`
+async fn replay<IO: IntoHalves>(
+    conf: Rc<Config>,
+    connector: Connector<IO>,
+    recorded: &[Captured],
+) -> eyre::Result<httpwg::replay::Replayed> {
+    let conn = Conn::new(conf, connector().await?);
+    httpwg::replay::replay(conn, recorded).await
+}
+
+/// Connects to the server under test, panics if it doesn't accept in time
+fn plain_connector(endpoint: &Endpoint, connect_timeout: Duration) -> Connector<Stream> {
+    let endpoint = endpoint.clone();
+    Rc::new(move || {
+        let endpoint = endpoint.clone();
+        Box::pin(async move { Ok(connect(&endpoint, connect_timeout).await) })
+    })
+}
+
+/// Connects over TLS, as set up by `--insecure`, `--ca-cert` and
+/// `--server-name`
+fn tls_connector(
+    args: &Args,
+    endpoint: &Endpoint,
+    connect_timeout: Duration,
+) -> eyre::Result<Connector<TlsStream<Stream>>> {
+    let verify = match &args.ca_cert {
+        Some(path) => httpwg::tls::Verify::Roots(load_roots(path)?),
+        None => httpwg::tls::Verify::Insecure,
+    };
+    let tls_config = httpwg::tls::client_config(verify)?;
+    let tls_server_name = args
+        .server_name
+        .clone()
+        .unwrap_or_else(|| "localhost".to_owned());
+    let endpoint = endpoint.clone();
+    Ok(Rc::new(move || {
+        let endpoint = endpoint.clone();
+        let tls_config = tls_config.clone();
+        let tls_server_name = tls_server_name.clone();
+        Box::pin(async move {
+            let stream = connect(&endpoint, connect_timeout).await;
+            httpwg::tls::connect(stream, &tls_server_name, tls_config).await
+        })
+    }))
+}
+
+/// Checks on the servers we started, if any, and stops them
+async fn stop_targets(targets: Vec<Subprocess>) -> eyre::Result<()> {
+    for mut server in targets {
+        if let Err(e) = server.check() {
+            eprintln!("⚠️ {e}");
+        }
+        server.stop().await?;
+    }
+    Ok(())
+}
+
+async fn connect(endpoint: &Endpoint, connect_timeout: Duration) -> Stream {
+    tokio::time::timeout(connect_timeout, endpoint.connect())
+        .await
+        .unwrap_or_else(|_| {
+            panic!("tested server failed to accept connction within {connect_timeout:?}")
+        })
+        .unwrap()
+}
+
+/// Whether `filter` is `id`, or a prefix of it that ends at a component, e.g.
+/// "http2/6.7" for "http2/6.7/1" (but not for "http2/6.70/1")
+fn id_matches(id: &str, filter: &str) -> bool {
+    match id.strip_prefix(filter.trim_end_matches('/')) {
+        Some(rest) => rest.is_empty() || rest.starts_with(['/', '.']),
+        None => false,
+    }
+}
+
+/// Reads a known failures file: one test per line, either its id
+/// ("http2/6.7/1"), its full name ("RFC 9113 :: 6. frame definitions :: sends
+/// ping frame") or just the last part ("sends ping frame"). Blank lines and
+/// lines starting with `#` are ignored.
+fn load_known_failures(path: &Path) -> eyre::Result<HashSet<String>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| eyre::eyre!("could not read {}: {e}", path.display()))?;
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(ToOwned::to_owned)
+        .collect())
+}
+
+/// Reads every certificate of a PEM file
+fn load_roots(path: &Path) -> eyre::Result<RootCertStore> {
+    let pem = std::fs::read_to_string(path)
+        .map_err(|e| eyre::eyre!("could not read {}: {e}", path.display()))?;
+    let mut roots = RootCertStore::empty();
+    let mut rest = pem.as_str();
+    while let Some(begin) = rest.find("-----BEGIN CERTIFICATE-----") {
+        rest = &rest[begin + "-----BEGIN CERTIFICATE-----".len()..];
+        let Some(end) = rest.find("-----END CERTIFICATE-----") else {
+            eyre::bail!("{}: unterminated certificate", path.display());
+        };
+        let b64: String = rest[..end].split_whitespace().collect();
+        let der = base64::engine::general_purpose::STANDARD
+            .decode(b64)
+            .map_err(|e| eyre::eyre!("{}: invalid base64: {e}", path.display()))?;
+        roots.add(der.into())?;
+        rest = &rest[end..];
+    }
+    if roots.is_empty() {
+        eyre::bail!("{}: no certificates found", path.display());
     }
+    Ok(roots)
 }
 
 fn setup_tracing_and_error_reporting() {
@@ -338,4 +1357,4 @@ fn setup_tracing_and_error_reporting() {
         .init();
 }
 
-httpwg_macros::gen_catalog!(catalog);
+httpwg_macros::gen_sections!(sections);
@@ -0,0 +1,355 @@
+//! Machine-readable results for CI: JUnit XML, or JSON
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    time::Duration,
+};
+
+use httpwg::Requirement;
+use serde::Serialize;
+
+/// How a test fared
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Status {
+    Passed,
+    Failed {
+        message: String,
+    },
+    /// Failed, but it's on the `--known-failures` list
+    #[serde(rename = "xfail")]
+    KnownFailure {
+        message: String,
+    },
+    /// Failed some of its runs but not all of them, cf. `--repeat`.
+    /// `message` is why the first of those failed.
+    Flaky {
+        message: String,
+        failed_runs: u32,
+        runs: u32,
+    },
+    /// Left out by `--filter` or `--level`
+    Skipped,
+}
+
+impl Status {
+    /// As in JSON results
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Status::Passed => "passed",
+            Status::Failed { .. } => "failed",
+            Status::KnownFailure { .. } => "xfail",
+            Status::Flaky { .. } => "flaky",
+            Status::Skipped => "skipped",
+        }
+    }
+}
+
+/// One test, and how it fared
+#[derive(Debug, Serialize)]
+pub struct TestResult {
+    /// e.g. "http2/6.7/1", cf. [httpwg::Test::id]
+    pub id: &'static str,
+    pub rfc: String,
+    pub section: String,
+    pub test: String,
+    /// "must" or "should"
+    pub requirement: &'static str,
+    /// the section of the RFC the test checks, e.g. "6.5.2"
+    pub spec_section: &'static str,
+    /// what the RFC says there
+    pub citation: &'static str,
+    #[serde(flatten)]
+    pub status: Status,
+    /// in seconds
+    pub duration: f64,
+    /// which run of the test this is, cf. `--repeat`, until
+    /// [Report::merge_runs]
+    #[serde(skip)]
+    pub run: u32,
+}
+
+/// What a test checks, cf. [httpwg::Test]
+#[derive(Debug, Clone, Copy)]
+pub struct Spec {
+    pub id: &'static str,
+    pub requirement: Requirement,
+    pub section: &'static str,
+    pub citation: &'static str,
+}
+
+/// Every test of a run, in the order they were recorded
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub tests: Vec<TestResult>,
+}
+
+impl Report {
+    pub fn record(
+        &mut self,
+        (rfc, section, test): (&str, &str, &str),
+        run: u32,
+        spec: Spec,
+        status: Status,
+        duration: Duration,
+    ) {
+        self.tests.push(TestResult {
+            id: spec.id,
+            rfc: rfc.to_owned(),
+            section: section.to_owned(),
+            test: test.to_owned(),
+            requirement: spec.requirement.as_str(),
+            spec_section: spec.section,
+            citation: spec.citation,
+            status,
+            duration: duration.as_secs_f64(),
+            run,
+        });
+    }
+
+    /// Marks a run of a test as failed, whatever it made of itself, e.g.
+    /// because the server crashed during it. Records it if it didn't get to.
+    pub fn fail(
+        &mut self,
+        key: (&str, &str, &str),
+        run: u32,
+        spec: Spec,
+        message: String,
+        known_failure: bool,
+        duration: Duration,
+    ) {
+        let (rfc, section, test) = key;
+        self.tests.retain(|t| {
+            (t.rfc.as_str(), t.section.as_str(), t.test.as_str(), t.run)
+                != (rfc, section, test, run)
+        });
+        let status = if known_failure {
+            Status::KnownFailure { message }
+        } else {
+            Status::Failed { message }
+        };
+        self.record(key, run, spec, status, duration);
+    }
+
+    /// Folds the runs of each test into a single result, in the order the
+    /// tests first got recorded: passed if every run passed, like the first
+    /// failed run if they all failed, and flaky otherwise (or a known
+    /// failure, if it's on the list). Durations add up.
+    pub fn merge_runs(&mut self) {
+        let mut groups: Vec<Vec<TestResult>> = vec![];
+        let mut index: HashMap<(String, String, String), usize> = Default::default();
+        for t in self.tests.drain(..) {
+            let key = (t.rfc.clone(), t.section.clone(), t.test.clone());
+            match index.get(&key) {
+                Some(&i) => groups[i].push(t),
+                None => {
+                    index.insert(key, groups.len());
+                    groups.push(vec![t]);
+                }
+            }
+        }
+
+        for runs in groups {
+            let num_runs = runs.len() as u32;
+            let duration = runs.iter().map(|t| t.duration).sum();
+            let failed_runs = runs
+                .iter()
+                .filter(|t| {
+                    matches!(
+                        t.status,
+                        Status::Failed { .. } | Status::KnownFailure { .. }
+                    )
+                })
+                .count() as u32;
+            let first_failed = runs
+                .iter()
+                .position(|t| !matches!(t.status, Status::Passed | Status::Skipped));
+            let mut merged = match first_failed {
+                Some(i) => runs.into_iter().nth(i).unwrap(),
+                None => runs.into_iter().next().unwrap(),
+            };
+            if failed_runs > 0 && failed_runs < num_runs {
+                merged.status = match merged.status {
+                    Status::KnownFailure { message } => Status::KnownFailure {
+                        message: format!("failed {failed_runs} of {num_runs} runs: {message}"),
+                    },
+                    Status::Failed { message } => Status::Flaky {
+                        message,
+                        failed_runs,
+                        runs: num_runs,
+                    },
+                    status => status,
+                };
+            }
+            merged.duration = duration;
+            merged.run = 0;
+            self.tests.push(merged);
+        }
+    }
+
+    pub fn num_passed(&self) -> usize {
+        self.tests
+            .iter()
+            .filter(|t| matches!(t.status, Status::Passed))
+            .count()
+    }
+
+    /// How many tests failed, not counting known failures, but counting
+    /// flaky ones
+    pub fn num_failed(&self) -> usize {
+        self.tests
+            .iter()
+            .filter(|t| matches!(t.status, Status::Failed { .. } | Status::Flaky { .. }))
+            .count()
+    }
+
+    pub fn num_flaky(&self) -> usize {
+        self.tests
+            .iter()
+            .filter(|t| matches!(t.status, Status::Flaky { .. }))
+            .count()
+    }
+
+    pub fn num_known_failures(&self) -> usize {
+        self.tests
+            .iter()
+            .filter(|t| matches!(t.status, Status::KnownFailure { .. }))
+            .count()
+    }
+
+    /// How many tests ran, i.e. weren't skipped
+    pub fn num_run(&self) -> usize {
+        self.tests
+            .iter()
+            .filter(|t| !matches!(t.status, Status::Skipped))
+            .count()
+    }
+
+    /// The tests that ran, grouped by "rfc :: section", sorted so they read
+    /// in order
+    pub fn by_section(&self) -> BTreeMap<String, Vec<&TestResult>> {
+        let mut sections: BTreeMap<String, Vec<&TestResult>> = Default::default();
+        for t in &self.tests {
+            sections
+                .entry(format!("{} :: {}", t.rfc, t.section))
+                .or_default()
+                .push(t);
+        }
+        sections
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("reports serialize")
+    }
+
+    /// One `<testsuite>` per section, one `<testcase>` per test
+    pub fn to_junit_xml(&self) -> String {
+        let mut out = String::new();
+        // JUnit has no notion of expected failures, they're reported as skipped
+        let failures = self.num_failed();
+        let skipped = self.tests.len() - self.num_run() + self.num_known_failures();
+        let time: f64 = self.tests.iter().map(|t| t.duration).sum();
+
+        // writing to a String can't fail
+        _ = writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        _ = writeln!(
+            out,
+            r#"<testsuites name="httpwg" tests="{}" failures="{failures}" skipped="{skipped}" time="{time:.3}">"#,
+            self.tests.len(),
+        );
+        for (section, tests) in self.by_section() {
+            let failures = tests
+                .iter()
+                .filter(|t| matches!(t.status, Status::Failed { .. } | Status::Flaky { .. }))
+                .count();
+            let skipped = tests
+                .iter()
+                .filter(|t| matches!(t.status, Status::Skipped | Status::KnownFailure { .. }))
+                .count();
+            let time: f64 = tests.iter().map(|t| t.duration).sum();
+            _ = writeln!(
+                out,
+                r#"  <testsuite name="{}" tests="{}" failures="{failures}" skipped="{skipped}" time="{time:.3}">"#,
+                xml_escape(&section),
+                tests.len(),
+            );
+            for t in tests {
+                let start = format!(
+                    r#"    <testcase name="{}" classname="{}" time="{:.3}""#,
+                    xml_escape(&t.test),
+                    xml_escape(&section),
+                    t.duration
+                );
+                match &t.status {
+                    Status::Passed => {
+                        _ = writeln!(out, "{start}/>");
+                    }
+                    Status::Failed { message } => {
+                        let summary = message.lines().next().unwrap_or_default();
+                        _ = writeln!(out, "{start}>");
+                        _ = writeln!(
+                            out,
+                            r#"      <failure message="{}">{}</failure>"#,
+                            xml_escape(summary),
+                            xml_escape(message)
+                        );
+                        _ = writeln!(out, "    </testcase>");
+                    }
+                    Status::Flaky {
+                        message,
+                        failed_runs,
+                        runs,
+                    } => {
+                        let summary = message.lines().next().unwrap_or_default();
+                        _ = writeln!(out, "{start}>");
+                        _ = writeln!(
+                            out,
+                            r#"      <failure message="flaky, failed {failed_runs} of {runs} runs: {}">{}</failure>"#,
+                            xml_escape(summary),
+                            xml_escape(message)
+                        );
+                        _ = writeln!(out, "    </testcase>");
+                    }
+                    Status::KnownFailure { message } => {
+                        let summary = message.lines().next().unwrap_or_default();
+                        _ = writeln!(out, "{start}>");
+                        _ = writeln!(
+                            out,
+                            r#"      <skipped message="known failure: {}"/>"#,
+                            xml_escape(summary)
+                        );
+                        _ = writeln!(out, "    </testcase>");
+                    }
+                    Status::Skipped => {
+                        _ = writeln!(out, "{start}>");
+                        _ = writeln!(out, "      <skipped/>");
+                        _ = writeln!(out, "    </testcase>");
+                    }
+                }
+            }
+            _ = writeln!(out, "  </testsuite>");
+        }
+        _ = writeln!(out, "</testsuites>");
+        out
+    }
+}
+
+/// Escapes text for XML attributes and character data. Control characters
+/// aren't allowed at all, so they're dropped.
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\n' | '\t' => out.push(c),
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
@@ -0,0 +1,111 @@
+//! Where the server under test listens: a TCP address (`--address`) or a
+//! Unix socket (`--unix`), and a stream type that covers both, so the test
+//! catalog only gets built once.
+
+use std::{fmt, net::SocketAddr, path::PathBuf};
+
+use buffet::{
+    bufpool::{BufResult, IoBufMut},
+    net::{TcpReadHalf, TcpStream, TcpWriteHalf, UnixReadHalf, UnixStream, UnixWriteHalf},
+    IntoHalves, Piece, PieceList, ReadOwned, WriteOwned,
+};
+use httpwg::target::Readiness;
+
+/// Where the server under test listens
+#[derive(Debug, Clone)]
+pub enum Endpoint {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Endpoint::Tcp(addr) => write!(f, "{addr}"),
+            Endpoint::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+impl Endpoint {
+    /// How a [httpwg::target::Subprocess] tells the server is up
+    pub fn readiness(&self) -> Readiness {
+        match self {
+            Endpoint::Tcp(addr) => Readiness::Tcp(*addr),
+            Endpoint::Unix(path) => Readiness::Unix(path.clone()),
+        }
+    }
+
+    pub async fn connect(&self) -> std::io::Result<Stream> {
+        Ok(match self {
+            Endpoint::Tcp(addr) => Stream::Tcp(TcpStream::connect(*addr).await?),
+            Endpoint::Unix(path) => Stream::Unix(UnixStream::connect(path).await?),
+        })
+    }
+}
+
+/// A connection to an [Endpoint]
+pub enum Stream {
+    Tcp(TcpStream),
+    Unix(UnixStream),
+}
+
+impl IntoHalves for Stream {
+    type Read = StreamRead;
+    type Write = StreamWrite;
+
+    fn into_halves(self) -> (Self::Read, Self::Write) {
+        match self {
+            Stream::Tcp(s) => {
+                let (r, w) = s.into_halves();
+                (StreamRead::Tcp(r), StreamWrite::Tcp(w))
+            }
+            Stream::Unix(s) => {
+                let (r, w) = s.into_halves();
+                (StreamRead::Unix(r), StreamWrite::Unix(w))
+            }
+        }
+    }
+}
+
+pub enum StreamRead {
+    Tcp(TcpReadHalf),
+    Unix(UnixReadHalf),
+}
+
+impl ReadOwned for StreamRead {
+    async fn read_owned<B: IoBufMut>(&mut self, buf: B) -> BufResult<usize, B> {
+        match self {
+            StreamRead::Tcp(r) => r.read_owned(buf).await,
+            StreamRead::Unix(r) => r.read_owned(buf).await,
+        }
+    }
+}
+
+pub enum StreamWrite {
+    Tcp(TcpWriteHalf),
+    Unix(UnixWriteHalf),
+}
+
+impl WriteOwned for StreamWrite {
+    async fn write_owned(&mut self, buf: impl Into<Piece>) -> BufResult<usize, Piece> {
+        match self {
+            StreamWrite::Tcp(w) => w.write_owned(buf).await,
+            StreamWrite::Unix(w) => w.write_owned(buf).await,
+        }
+    }
+
+    async fn writev_owned(&mut self, list: &PieceList) -> std::io::Result<usize> {
+        match self {
+            StreamWrite::Tcp(w) => w.writev_owned(list).await,
+            StreamWrite::Unix(w) => w.writev_owned(list).await,
+        }
+    }
+
+    async fn shutdown(&mut self) -> std::io::Result<()> {
+        match self {
+            StreamWrite::Tcp(w) => w.shutdown().await,
+            StreamWrite::Unix(w) => w.shutdown().await,
+        }
+    }
+}
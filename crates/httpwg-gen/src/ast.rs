@@ -41,6 +41,8 @@ pub enum ItemInner {
     Struct(Struct),
     Constant(Constant),
     TypeAlias(TypeAlias),
+    Trait(Trait),
+    Import(Import),
 }
 
 #[derive(Deserialize)]
@@ -77,3 +79,9 @@ pub struct Constant {}
 
 #[derive(Deserialize)]
 pub struct TypeAlias {}
+
+#[derive(Deserialize)]
+pub struct Trait {}
+
+#[derive(Deserialize)]
+pub struct Import {}
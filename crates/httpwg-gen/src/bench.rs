@@ -0,0 +1,90 @@
+//! Generates a criterion harness out of the tests the catalog marks with
+//! `bench`, for servers to track performance with, cf. `--benches`
+
+use std::io::Write;
+
+use crate::catalog::Suite;
+
+/// The source of the harness's `main.rs`, before rustfmt. It expects a
+/// hand-written `server` module next to it.
+pub fn generate(suites: &[Suite]) -> Vec<u8> {
+    let mut out: Vec<u8> = vec![];
+
+    macro_rules! w {
+        ($tt:tt) => {
+            writeln!(&mut out, $tt).unwrap()
+        };
+    }
+
+    w!("//! Benchmarks some of httpwg's tests, the ones marked with `bench` in");
+    w!("//! httpwg-gen's catalog: every iteration runs the test against a");
+    w!("//! fresh server, from `server::start_server`");
+    w!("");
+    w!("// This file is automatically @generated by httpwg-gen");
+    w!("// It is not intended for manual editing");
+    w!("");
+    w!("use std::future::Future;");
+    w!("");
+    w!("use codspeed_criterion_compat::{{");
+    w!("    async_executor::AsyncExecutor, criterion_group, criterion_main, Criterion, Throughput,");
+    w!("}};");
+    w!("");
+    w!("mod server;");
+    w!("");
+    w!("/// Runs benchmarks on buffet, like the tests");
+    w!("struct Buffet;");
+    w!("");
+    w!("impl AsyncExecutor for Buffet {{");
+    w!("    fn block_on<T>(&self, future: impl Future<Output = T>) -> T {{");
+    w!("        buffet::start(future)");
+    w!("    }}");
+    w!("}}");
+
+    let mut benched_suites = vec![];
+    for suite in suites {
+        let benches: Vec<_> = suite
+            .groups
+            .iter()
+            .flat_map(|group| group.tests.iter().map(move |test| (group, test)))
+            .filter_map(|(group, test)| Some((group, test, test.bench.as_deref()?)))
+            .collect();
+        if benches.is_empty() {
+            continue;
+        }
+
+        let suite_name = &suite.name;
+        benched_suites.push(suite_name);
+        w!("");
+        w!("fn {suite_name}(c: &mut Criterion) {{");
+        w!("    let mut group = c.benchmark_group(\"{suite_name}\");");
+        w!("    group.throughput(Throughput::Elements(1));");
+        for (group, test, bench) in benches {
+            let group_name = &group.name;
+            let test_name = &test.name;
+            let id = &test.id;
+            w!("");
+            w!("    // {id}");
+            w!("    group.bench_function({bench:?}, |b| {{");
+            w!("        b.to_async(Buffet).iter(|| async {{");
+            w!("            httpwg::{suite_name}::{group_name}::{test_name}(server::start_server())");
+            w!("                .await");
+            w!("                .unwrap()");
+            w!("        }})");
+            w!("    }});");
+        }
+        w!("");
+        w!("    group.finish();");
+        w!("}}");
+    }
+
+    let benched_suites = benched_suites
+        .iter()
+        .map(|s| s.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    w!("");
+    w!("criterion_group!(benches, {benched_suites});");
+    w!("criterion_main!(benches);");
+
+    out
+}
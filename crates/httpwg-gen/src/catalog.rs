@@ -0,0 +1,270 @@
+//! Reads `catalog.toml`, which lists every suite, group and test of the
+//! httpwg crate, cf. the comment at the top of it
+
+use std::{collections::HashSet, fs};
+
+use toml_edit::{value, Document, Item, Table};
+
+#[derive(Debug)]
+pub struct Suite {
+    pub name: String,
+    /// filled in from rustdoc, once the catalog is read
+    pub docs: Option<String>,
+    pub groups: Vec<Group>,
+}
+
+#[derive(Debug)]
+pub struct Group {
+    pub name: String,
+    /// filled in from rustdoc, once the catalog is read
+    pub docs: Option<String>,
+    /// (number, title): the group's own, then every `//---- Section`
+    /// line of its source file
+    pub sections: Vec<(String, String)>,
+    pub tests: Vec<Test>,
+}
+
+#[derive(Debug)]
+pub struct Test {
+    pub name: String,
+    /// e.g. "http2/6.7/1", cf. [assign_ids]
+    pub id: String,
+    /// filled in from rustdoc, once the catalog is read
+    pub docs: Option<String>,
+    /// e.g. "6.5.2", for filtering
+    pub section: String,
+    /// "Must" or "Should", as in `httpwg::Requirement`
+    pub requirement: &'static str,
+    /// what the RFC says in `section`
+    pub citation: String,
+    /// what servers have to support for the test to make sense, e.g.
+    /// "extended-connect", its suite's and group's included
+    pub features: Vec<String>,
+    /// why the test is `#[ignore]`d, if it is
+    pub ignore: Option<String>,
+    /// how long the test gets to run, if it's not up to the harness
+    pub timeout_secs: Option<u64>,
+    /// what the test is benchmarked as, e.g. "handshake", if it is
+    pub bench: Option<String>,
+}
+
+impl Suite {
+    /// e.g. "RFC 9113", for humans
+    pub fn rfc(&self) -> String {
+        self.name.to_uppercase().replace("RFC", "RFC ")
+    }
+}
+
+impl Group {
+    /// e.g. "6. frame definitions", for humans
+    pub fn title(&self) -> String {
+        let name = self.name.strip_prefix('_').unwrap_or(&self.name);
+        name.replace('_', " ")
+            .replacen(' ', ". ", 1)
+            .trim()
+            .to_owned()
+    }
+}
+
+impl Test {
+    /// e.g. "sends ping frame", for humans
+    pub fn pretty_name(&self) -> String {
+        self.name.replace('_', " ")
+    }
+}
+
+/// Every suite of the catalog at `path`, in order. Panics with a message
+/// pointing at the offending entry if it's malformed.
+///
+/// Tests that don't have an id yet get one, which gets written back to
+/// `path`.
+pub fn read(path: &str) -> Vec<Suite> {
+    let source =
+        fs::read_to_string(path).unwrap_or_else(|err| panic!("{err} while reading {path}"));
+    let mut doc: Document = source
+        .parse()
+        .unwrap_or_else(|err| panic!("{path} is not valid TOML: {err}"));
+    let assigned = assign_ids(&mut doc, path);
+    if assigned > 0 {
+        println!("🏷️ Assigned ids to {assigned} new test(s) in {path}");
+        fs::write(path, doc.to_string()).unwrap();
+    }
+
+    tables(doc.as_table(), "suite", path)
+        .map(|suite| {
+            let name = string(suite, "name", path);
+            let suite_features = strings(suite, "features", &name);
+            let groups = tables(suite, "group", &name)
+                .map(|group| {
+                    let group_name = string(group, "name", &name);
+                    let context = format!("{name}::{group_name}");
+                    let mut group_features = suite_features.clone();
+                    group_features.extend(strings(group, "features", &context));
+                    let mut sections = vec![(group_section(&group_name), string(group, "title", &context))];
+                    if let Some(list) = group.get("sections") {
+                        let list = list
+                            .as_array()
+                            .unwrap_or_else(|| panic!("{context}: `sections` should be an array"));
+                        for section in list {
+                            let section = section
+                                .as_inline_table()
+                                .unwrap_or_else(|| panic!("{context}: sections should be {{ number, title }} tables"));
+                            let field = |key: &str| {
+                                section
+                                    .get(key)
+                                    .and_then(|v| v.as_str())
+                                    .unwrap_or_else(|| panic!("{context}: a section is missing its `{key}`"))
+                                    .to_owned()
+                            };
+                            sections.push((field("number"), field("title")));
+                        }
+                    }
+
+                    let tests = tables(group, "test", &context)
+                        .map(|test| {
+                            let test_name = string(test, "name", &context);
+                            let context = format!("{context}::{test_name}");
+                            let section = string(test, "section", &context);
+                            if !sections.iter().any(|(number, _)| *number == section) {
+                                panic!("{context}: section {section} isn't the group's, nor one of its `sections`");
+                            }
+                            let requirement = match string(test, "strictness", &context).as_str() {
+                                "must" => "Must",
+                                "should" => "Should",
+                                other => panic!("{context}: strictness should be \"must\" or \"should\", not {other:?}"),
+                            };
+                            let mut features = group_features.clone();
+                            features.extend(strings(test, "features", &context));
+                            let timeout_secs = test.get("timeout_secs").map(|item| {
+                                item.as_integer()
+                                    .and_then(|secs| u64::try_from(secs).ok())
+                                    .unwrap_or_else(|| panic!("{context}: `timeout_secs` should be a positive integer"))
+                            });
+                            Test {
+                                id: string(test, "id", &context),
+                                name: test_name,
+                                docs: None,
+                                section,
+                                requirement,
+                                citation: string(test, "requirement", &context).trim().to_owned(),
+                                features,
+                                ignore: test.contains_key("ignore").then(|| string(test, "ignore", &context)),
+                                timeout_secs,
+                                bench: test.contains_key("bench").then(|| string(test, "bench", &context)),
+                            }
+                        })
+                        .collect();
+
+                    Group {
+                        name: group_name,
+                        docs: None,
+                        sections,
+                        tests,
+                    }
+                })
+                .collect();
+
+            Suite {
+                name,
+                docs: None,
+                groups,
+            }
+        })
+        .collect()
+}
+
+/// Gives tests that don't have an id the next one of their section, e.g.
+/// "http2/6.7/4" after "http2/6.7/3", and makes sure no two tests share one.
+/// Returns how many it assigned.
+fn assign_ids(doc: &mut Document, path: &str) -> usize {
+    let mut taken: HashSet<String> = Default::default();
+    for suite in tables(doc.as_table(), "suite", path) {
+        for group in tables(suite, "group", path) {
+            for test in tables(group, "test", path) {
+                if let Some(id) = test.get("id").and_then(|id| id.as_str()) {
+                    if !taken.insert(id.to_owned()) {
+                        panic!("{path}: more than one test has id {id:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    let mut assigned = 0;
+    let Some(suites) = doc.get_mut("suite").and_then(Item::as_array_of_tables_mut) else {
+        return 0;
+    };
+    for suite in suites.iter_mut() {
+        let suite_id = string(suite, "id", path);
+        let Some(groups) = suite
+            .get_mut("group")
+            .and_then(Item::as_array_of_tables_mut)
+        else {
+            continue;
+        };
+        for group in groups.iter_mut() {
+            let Some(tests) = group.get_mut("test").and_then(Item::as_array_of_tables_mut) else {
+                continue;
+            };
+            for test in tests.iter_mut() {
+                if test.contains_key("id") {
+                    continue;
+                }
+                let prefix = format!("{suite_id}/{}/", string(test, "section", path));
+                let last = taken
+                    .iter()
+                    .filter_map(|id| id.strip_prefix(&prefix))
+                    .filter_map(|n| n.parse::<u32>().ok())
+                    .max()
+                    .unwrap_or_default();
+                let id = format!("{prefix}{}", last + 1);
+                test.insert("id", value(&id));
+                taken.insert(id);
+                assigned += 1;
+            }
+        }
+    }
+    assigned
+}
+
+/// The `[[key]]` entries of `table`, if any
+fn tables<'a>(table: &'a Table, key: &str, context: &str) -> impl Iterator<Item = &'a Table> {
+    let list = match table.get(key) {
+        None => None,
+        Some(Item::ArrayOfTables(list)) => Some(list),
+        Some(_) => panic!("{context}: `{key}` should be an array of tables, i.e. [[...]]"),
+    };
+    list.into_iter().flat_map(|list| list.iter())
+}
+
+fn string(table: &Table, key: &str, context: &str) -> String {
+    table
+        .get(key)
+        .and_then(|item| item.as_str())
+        .unwrap_or_else(|| panic!("{context}: missing string `{key}`"))
+        .to_owned()
+}
+
+/// The strings of the array at `key`, or none if there's no such key
+fn strings(table: &Table, key: &str, context: &str) -> Vec<String> {
+    let Some(item) = table.get(key) else {
+        return vec![];
+    };
+    item.as_array()
+        .and_then(|list| {
+            list.iter()
+                .map(|v| v.as_str().map(ToOwned::to_owned))
+                .collect::<Option<Vec<_>>>()
+        })
+        .unwrap_or_else(|| panic!("{context}: `{key}` should be an array of strings"))
+}
+
+/// "6" for "_6_frame_definitions"
+pub fn group_section(group_name: &str) -> String {
+    group_name
+        .trim_start_matches('_')
+        .split('_')
+        .next()
+        .unwrap_or_default()
+        .to_owned()
+}
@@ -5,8 +5,27 @@ use std::{
 };
 
 mod ast;
+mod bench;
+mod catalog;
+mod registry;
+mod skeleton;
+
+const CATALOG_PATH: &str = "crates/httpwg-gen/catalog.toml";
 
 fn main() {
+    // `--benches <dir>` also writes a criterion harness to `<dir>/main.rs`,
+    // cf. [bench]
+    let mut benches_dir = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--benches" => {
+                benches_dir = Some(args.next().expect("--benches needs a directory"));
+            }
+            _ => panic!("unknown argument {arg:?}, the only one is `--benches <dir>`"),
+        }
+    }
+
     let out_path = "crates/httpwg-macros/src/lib.rs";
     if std::fs::symlink_metadata(out_path).is_err() {
         eprintln!("⛔️ Output path doesn't exist: {out_path}");
@@ -17,6 +36,34 @@ fn main() {
         panic!("Refusing to proceed, read stderr above");
     }
 
+    println!("📖 Reading {CATALOG_PATH}...");
+    let mut suites = catalog::read(CATALOG_PATH);
+    let added = skeleton::write(&suites, "crates/httpwg/src");
+    if added > 0 {
+        println!("🦴 Added {added} test skeleton(s) to httpwg, fill them in!");
+    }
+
+    write_formatted(
+        registry::OUT_PATH,
+        "httpwg's registry",
+        registry::generate(&suites),
+    );
+
+    if let Some(benches_dir) = benches_dir {
+        let has_benches = suites
+            .iter()
+            .flat_map(|s| &s.groups)
+            .flat_map(|g| &g.tests)
+            .any(|t| t.bench.is_some());
+        assert!(has_benches, "no test of the catalog has a `bench`");
+        fs::create_dir_all(&benches_dir).unwrap();
+        write_formatted(
+            &format!("{benches_dir}/main.rs"),
+            "the benchmark harness",
+            bench::generate(&suites),
+        );
+    }
+
     println!("🧱 Generating rustdoc...");
 
     let mut cmd = Command::new("cargo");
@@ -34,25 +81,6 @@ fn main() {
         .spawn()
         .unwrap_or_else(|err| panic!("{err} while spawning command: {final_cmd}"));
 
-    enum StdoutOrStderr {
-        Stdout,
-        Stderr,
-    }
-
-    fn collect_output(kind: StdoutOrStderr, r: impl Read) -> Vec<String> {
-        let r = BufReader::new(r);
-        let mut lines = Vec::new();
-        for l in r.lines() {
-            let l = l.unwrap();
-            match kind {
-                StdoutOrStderr::Stdout => println!("{l}"),
-                StdoutOrStderr::Stderr => eprintln!("{l}"),
-            }
-            lines.push(l)
-        }
-        lines
-    }
-
     let stdout = std::thread::spawn({
         let r = child.stdout.take().unwrap();
         move || collect_output(StdoutOrStderr::Stdout, r)
@@ -97,137 +125,63 @@ fn main() {
         _ => panic!("Root has to be module"),
     };
 
-    #[derive(Debug)]
-    struct Suite {
-        name: String,
-        docs: Option<String>,
-        groups: Vec<Group>,
-    }
-
-    #[derive(Debug)]
-    struct Group {
-        name: String,
-        docs: Option<String>,
-        tests: Vec<Test>,
-    }
-
-    #[derive(Debug)]
-    struct Test {
-        name: String,
-        docs: Option<String>,
-    }
-
-    let mut suites: Vec<Suite> = Default::default();
-
+    // every test has to be in the catalog, which is what decides the order
+    // (and the sections) things get generated in: rustdoc only provides docs
     for item_id in &module.items {
         let item = doc.index.get(item_id).expect("Could not find some node");
-        match &item.inner {
-            ast::ItemInner::Module(module) => {
-                let suite_name = item.name.clone().unwrap();
-                if suite_name.starts_with("rfc") {
-                    // good!
-                } else {
-                    // skip
+        let ast::ItemInner::Module(module) = &item.inner else {
+            continue;
+        };
+        let suite_name = item.name.clone().unwrap();
+        if !suite_name.starts_with("rfc") {
+            continue;
+        }
+        println!("📚 {suite_name} ({item_id})");
+        let suite = suites
+            .iter_mut()
+            .find(|s| s.name == suite_name)
+            .unwrap_or_else(|| not_in_catalog(&suite_name));
+        suite.docs = item.docs.clone();
+
+        for item_id in &module.items {
+            let item = doc.index.get(item_id).expect("Could not find some node");
+            let ast::ItemInner::Module(module) = &item.inner else {
+                continue;
+            };
+            let group_name = item.name.clone().unwrap();
+            if !group_name.starts_with('_') {
+                continue;
+            }
+            println!("  📕 {group_name} ({item_id})");
+            let group = suite
+                .groups
+                .iter_mut()
+                .find(|g| g.name == group_name)
+                .unwrap_or_else(|| not_in_catalog(&format!("{suite_name}::{group_name}")));
+            group.docs = item.docs.clone();
+
+            for item_id in &module.items {
+                let item = doc.index.get(item_id).expect("Could not find some node");
+                let ast::ItemInner::Function(_) = &item.inner else {
                     continue;
-                }
-                println!("📚 {suite_name} ({item_id})");
-                let mut suite = Suite {
-                    name: suite_name,
-                    docs: item.docs.clone(),
-                    groups: Default::default(),
                 };
-
-                for item_id in &module.items {
-                    let item = doc.index.get(item_id).expect("Could not find some node");
-                    match &item.inner {
-                        ast::ItemInner::Module(module) => {
-                            let group_name = item.name.clone().unwrap();
-                            if group_name.starts_with('_') {
-                                // good!
-                            } else {
-                                // skip
-                                continue;
-                            }
-                            println!("  📕 {group_name} ({item_id})");
-
-                            let mut group = Group {
-                                name: group_name,
-                                docs: item.docs.clone(),
-                                tests: Default::default(),
-                            };
-
-                            for item_id in &module.items {
-                                let item =
-                                    doc.index.get(item_id).expect("Could not find some node");
-                                match &item.inner {
-                                    ast::ItemInner::Function(_) => {
-                                        let test_name = item.name.clone().unwrap();
-                                        println!("    📄 {test_name} ({item_id})");
-
-                                        let test = Test {
-                                            name: test_name,
-                                            docs: item.docs.clone(),
-                                        };
-                                        group.tests.push(test);
-                                    }
-                                    _ => {
-                                        // ignore
-                                    }
-                                }
-                            }
-
-                            suite.groups.push(group);
-                        }
-                        _ => {
-                            // ignore
-                        }
-                    }
-                }
-
-                suites.push(suite);
-            }
-            _ => {
-                // ignore
+                let test_name = item.name.clone().unwrap();
+                println!("    📄 {test_name} ({item_id})");
+                let test = group
+                    .tests
+                    .iter_mut()
+                    .find(|t| t.name == test_name)
+                    .unwrap_or_else(|| {
+                        not_in_catalog(&format!("{suite_name}::{group_name}::{test_name}"))
+                    });
+                test.docs = item.docs.clone();
             }
         }
     }
 
-    // Generate macro code, pipe it to rustfmt
-    let mut cmd = Command::new("rustfmt");
-    cmd.stdin(Stdio::piped());
-    cmd.stdout(Stdio::piped());
-    cmd.stderr(Stdio::piped());
-    let final_cmd = format!("{cmd:?}");
-    let mut child = cmd
-        .spawn()
-        .unwrap_or_else(|err| panic!("{err} while spawning command: {final_cmd}"));
-
-    let old_contents =
-        std::thread::spawn(move || std::fs::read_to_string(out_path).unwrap_or_default());
-
-    let stdout = std::thread::spawn({
-        let r = child.stdout.take().unwrap();
-
-        move || {
-            let r = BufReader::new(r);
-            let mut s = String::with_capacity(16 * 1024);
-            for line in r.lines() {
-                let line = line.unwrap();
-                use std::fmt::Write;
-                writeln!(&mut s, "{line}").unwrap();
-            }
-            s
-        }
-    });
-
-    let stderr = std::thread::spawn({
-        let stderr = child.stderr.take().unwrap();
-        move || collect_output(StdoutOrStderr::Stderr, stderr)
-    });
-
+    // Generate macro code
+    let mut out: Vec<u8> = vec![];
     {
-        let mut out = child.stdin.take().unwrap();
-
         macro_rules! w {
             ($tt:tt) => {
                 writeln!(&mut out, $tt).unwrap()
@@ -243,10 +197,26 @@ fn main() {
         w!("/// The `$body` argument is pasted inside those unit test, and");
         w!("/// in that scope, `test` is the `httpwg` function you can use");
         w!("/// to run the test (that takes a `mut conn: Conn<IO>`)");
+        w!("///");
+        w!("/// Tests that don't match `HTTPWG_FILTER` (cf. `httpwg::filter`)");
+        w!("/// return early, without running `$body`, and so do tests that need");
+        w!("/// a feature the server doesn't support, if it says so:");
+        w!("///");
+        w!("/// ```ignore");
+        w!("/// httpwg_macros::tests! {{ unsupported: [\"extended-connect\"], {{");
+        w!("///     // ...");
+        w!("/// }} }}");
+        w!("/// ```");
+        w!("///");
+        w!("/// Some tests are `#[ignore]`d, and some get a timeout of their own:");
+        w!("/// cf. httpwg-gen's catalog, for both.");
         w!("#[macro_export]");
         w!("macro_rules! tests {{");
         {
             w!("  ($body: tt) => {{");
+            w!("    $crate::tests! {{ unsupported: [], $body }}");
+            w!("  }};");
+            w!("  (unsupported: [$($feature: literal),* $(,)?], $body: tt) => {{");
             for suite in &suites {
                 let suite_name = &suite.name;
                 w!("");
@@ -272,10 +242,32 @@ fn main() {
                                 for line in test.docs.as_deref().unwrap_or_default().lines() {
                                     w!("/// {line}");
                                 }
+                                let section = &test.section;
                                 w!("#[test]");
+                                if let Some(reason) = &test.ignore {
+                                    w!("#[ignore = {reason:?}]");
+                                }
                                 w!("fn {test_name}() {{");
                                 {
-                                    w!("use __group::{test_name} as test;");
+                                    let id = &test.id;
+                                    w!("if !::httpwg::filter::Filter::from_env().matches(\"{id}\", \"{suite_name}\", \"{section}\", \"{test_name}\") {{");
+                                    w!("    return;");
+                                    w!("}}");
+                                    if !test.features.is_empty() {
+                                        let features = &test.features;
+                                        w!("if let Some(feature) = ::httpwg::filter::unsupported(&[$($feature),*], &{features:?}) {{");
+                                        w!("    eprintln!(\"skipping {id}: the server doesn't support {{feature}}\");");
+                                        w!("    return;");
+                                        w!("}}");
+                                    }
+                                    match test.timeout_secs {
+                                        Some(secs) => {
+                                            w!("fn test<IO: ::httpwg::macros::IntoHalves>(conn: ::httpwg::Conn<IO>) -> impl ::std::future::Future<Output = ::httpwg::macros::Result<()>> {{");
+                                            w!("    ::httpwg::with_timeout(::std::time::Duration::from_secs({secs}), __group::{test_name}(conn))");
+                                            w!("}}");
+                                        }
+                                        None => w!("use __group::{test_name} as test;"),
+                                    }
                                     w!("$body");
                                 }
                                 w!("}}");
@@ -292,58 +284,124 @@ fn main() {
 
         w!("");
         w!("/// This generates a function that returns a Catalog of type");
+        w!("/// `HashMap<rfc, HashMap<group, HashMap<test name, Test<IO>>>>`, out of");
+        w!("/// `httpwg::all_tests`");
         w!("#[macro_export]");
         w!("macro_rules! gen_catalog {{");
         w!("  ($catalog_fn_name:ident) => {{");
-        w!("    use ::httpwg::BoxedTest;");
-        w!("    pub fn $catalog_fn_name<IO: IntoHalves>() -> HashMap<&'static str, HashMap<&'static str, HashMap<&'static str, BoxedTest<IO>>>> {{");
-        w!("        let mut rfcs: HashMap<&'static str, HashMap<&'static str, HashMap<&'static str, BoxedTest<IO>>>> = Default::default();");
+        w!("    use ::httpwg::Test;");
+        w!("    pub fn $catalog_fn_name<IO: IntoHalves>() -> HashMap<&'static str, HashMap<&'static str, HashMap<&'static str, Test<IO>>>> {{");
+        w!("        let mut rfcs: HashMap<&'static str, HashMap<&'static str, HashMap<&'static str, Test<IO>>>> = Default::default();");
+        w!("        for suite in ::httpwg::all_tests::<IO>() {{");
+        w!("            let sections = rfcs.entry(suite.rfc).or_default();");
+        w!("            for group in suite.groups {{");
+        w!("                let tests = sections.entry(group.title).or_default();");
+        w!("                for test in group.tests {{");
+        w!("                    tests.insert(test.name, test);");
+        w!("                }}");
+        w!("            }}");
+        w!("        }}");
+        w!("        rfcs");
+        w!("    }}");
+        w!("  }}");
+        w!("}}");
+
         w!("");
+        w!("/// This generates a function that lists every section of every RFC,");
+        w!("/// with what their tests check, cf. `httpwg::Section`");
+        w!("#[macro_export]");
+        w!("macro_rules! gen_sections {{");
+        w!("  ($sections_fn_name:ident) => {{");
+        w!("    pub fn $sections_fn_name() -> Vec<::httpwg::Section> {{");
+        w!("        vec![");
         for suite in &suites {
+            let pretty_suite_name = suite.rfc();
+            let mut sections: Vec<(&catalog::Group, &String, &String)> = suite
+                .groups
+                .iter()
+                .flat_map(|g| g.sections.iter().map(move |(n, t)| (g, n, t)))
+                .collect();
+            sections.sort_by_key(|(_, number, _)| {
+                number
+                    .split('.')
+                    .map(|n| n.parse::<u32>().unwrap_or_default())
+                    .collect::<Vec<_>>()
+            });
             {
-                let suite_name = &suite.name;
-                let pretty_suite_name = suite_name.to_uppercase().replace("RFC", "RFC ");
-                w!("        {{");
-                w!("            let mut sections: HashMap<&'static str, _> = Default::default();");
-                w!("");
-                for group in &suite.groups {
-                    {
-                        let group_name = &group.name;
-                        let pretty_group_name = group_name.strip_prefix('_').unwrap_or(group_name);
-                        let pretty_group_name = pretty_group_name.replace('_', " ");
-                        let pretty_group_name = pretty_group_name.replacen(' ', ". ", 1);
-                        let pretty_group_name = pretty_group_name.trim();
-                        w!("            {{");
-                        w!("                use ::httpwg::{suite_name}::{group_name} as s;");
-                        w!("                let mut {group_name}: HashMap<&'static str, BoxedTest<IO>> = Default::default();");
-                        w!("");
-                        for test in &group.tests {
-                            {
-                                let test_name = &test.name;
-                                let pretty_test_name = test_name.replace('_', " ");
-                                w!("                {group_name}.insert(");
-                                w!("                    \"{pretty_test_name}\",");
-                                w!("                    Box::new(|conn: Conn<IO>| Box::pin(s::{test_name}(conn))),");
-                                w!("                );");
-                            }
+                for (group, number, title) in sections {
+                    let mut requirements: Vec<&str> = vec![];
+                    for test in group.tests.iter().filter(|t| &t.section == number) {
+                        if !requirements.contains(&test.citation.as_str()) {
+                            requirements.push(&test.citation);
                         }
-                        w!("");
-                        w!("                sections.insert(\"{pretty_group_name}\", {group_name});");
-                        w!("            }}");
                     }
+                    w!("            ::httpwg::Section {{");
+                    w!("                rfc: \"{pretty_suite_name}\",");
+                    w!("                number: \"{number}\",");
+                    w!("                title: {title:?},");
+                    w!("                requirements: &{requirements:?},");
+                    w!("            }},");
                 }
-                w!("");
-                w!("            rfcs.insert(\"{pretty_suite_name}\", sections);");
-                w!("        }}");
             }
         }
-        w!("");
-        w!("        rfcs");
+        w!("        ]");
         w!("    }}");
         w!("  }}");
         w!("}}");
+    }
+    write_formatted(out_path, "httpwg-macros", out);
+}
+
+/// Tests get listed in `catalog.toml` first: httpwg-gen only writes
+/// skeletons for what's in there, and refuses to guess about the rest
+fn not_in_catalog(path: &str) -> ! {
+    eprintln!("⛔️ {path} is in the httpwg crate, but not in {CATALOG_PATH}");
+    eprintln!("👉 Add it there, with the section it checks and what the RFC says.");
+    panic!("Refusing to proceed, read stderr above");
+}
+
+/// Pipes `code` through rustfmt, and writes it to `out_path`, unless it's
+/// there already. `what` is what to call it in messages.
+fn write_formatted(out_path: &str, what: &str, code: Vec<u8>) {
+    let mut cmd = Command::new("rustfmt");
+    cmd.args(["--edition", "2021"]);
+    cmd.stdin(Stdio::piped());
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+    let final_cmd = format!("{cmd:?}");
+    let mut child = cmd
+        .spawn()
+        .unwrap_or_else(|err| panic!("{err} while spawning command: {final_cmd}"));
+
+    let old_contents = {
+        let out_path = out_path.to_owned();
+        std::thread::spawn(move || std::fs::read_to_string(out_path).unwrap_or_default())
+    };
+
+    let stdout = std::thread::spawn({
+        let r = child.stdout.take().unwrap();
 
-        out.flush().unwrap();
+        move || {
+            let r = BufReader::new(r);
+            let mut s = String::with_capacity(16 * 1024);
+            for line in r.lines() {
+                let line = line.unwrap();
+                use std::fmt::Write;
+                writeln!(&mut s, "{line}").unwrap();
+            }
+            s
+        }
+    });
+
+    let stderr = std::thread::spawn({
+        let stderr = child.stderr.take().unwrap();
+        move || collect_output(StdoutOrStderr::Stderr, stderr)
+    });
+
+    {
+        let mut stdin = child.stdin.take().unwrap();
+        stdin.write_all(&code).unwrap();
+        stdin.flush().unwrap();
     }
 
     let status = child
@@ -367,9 +425,28 @@ fn main() {
     // Write the generated code to the file, only if the file is different
     let old_s = old_contents.join().unwrap();
     if old_s == s {
-        println!("📦 httpwg-macros is up-to-date");
+        println!("📦 {what} is up-to-date");
     } else {
         fs::write(out_path, &s).unwrap();
-        println!("✨ httpwg-macros updated!");
+        println!("✨ {what} updated!");
+    }
+}
+
+enum StdoutOrStderr {
+    Stdout,
+    Stderr,
+}
+
+fn collect_output(kind: StdoutOrStderr, r: impl Read) -> Vec<String> {
+    let r = BufReader::new(r);
+    let mut lines = Vec::new();
+    for l in r.lines() {
+        let l = l.unwrap();
+        match kind {
+            StdoutOrStderr::Stdout => println!("{l}"),
+            StdoutOrStderr::Stderr => eprintln!("{l}"),
+        }
+        lines.push(l)
     }
+    lines
 }
@@ -0,0 +1,72 @@
+//! Generates httpwg's `registry` module, which lists every test of the
+//! catalog at runtime, for runners that can't expand macros
+
+use std::io::Write;
+
+use crate::catalog::Suite;
+
+pub const OUT_PATH: &str = "crates/httpwg/src/registry.rs";
+
+/// The source of the `registry` module, before rustfmt
+pub fn generate(suites: &[Suite]) -> Vec<u8> {
+    let mut out: Vec<u8> = vec![];
+
+    macro_rules! w {
+        ($tt:tt) => {
+            writeln!(&mut out, $tt).unwrap()
+        };
+    }
+
+    w!("//! Every test of every suite, for runners to discover at runtime,");
+    w!("//! cf. [all_tests]");
+    w!("");
+    w!("// This file is automatically @generated by httpwg-gen");
+    w!("// It is not intended for manual editing");
+    w!("");
+    w!("use buffet::IntoHalves;");
+    w!("");
+    w!("use crate::{{Conn, Group, Requirement, Suite, Test}};");
+    w!("");
+    w!("/// Every test of every suite, grouped like the `httpwg_macros::tests`");
+    w!("/// tree, in catalog order");
+    w!("pub fn all_tests<IO: IntoHalves>() -> Vec<Suite<IO>> {{");
+    w!("    vec![");
+    for suite in suites {
+        let suite_name = &suite.name;
+        let rfc = suite.rfc();
+        w!("        Suite {{");
+        w!("            rfc: \"{rfc}\",");
+        w!("            groups: vec![");
+        for group in &suite.groups {
+            let group_name = &group.name;
+            let title = group.title();
+            w!("                Group {{");
+            w!("                    title: \"{title}\",");
+            w!("                    tests: vec![");
+            for test in &group.tests {
+                let test_name = &test.name;
+                let pretty_name = test.pretty_name();
+                let id = &test.id;
+                let requirement = test.requirement;
+                let section = &test.section;
+                let citation = &test.citation;
+                w!("                        Test {{");
+                w!("                            name: \"{pretty_name}\",");
+                w!("                            id: \"{id}\",");
+                w!("                            requirement: Requirement::{requirement},");
+                w!("                            section: \"{section}\",");
+                w!("                            citation: {citation:?},");
+                w!("                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::{suite_name}::{group_name}::{test_name}(conn))),");
+                w!("                        }},");
+            }
+            w!("                    ],");
+            w!("                }},");
+        }
+        w!("            ],");
+        w!("        }},");
+    }
+    w!("    ]");
+    w!("}}");
+
+    out
+}
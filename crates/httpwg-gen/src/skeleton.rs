@@ -0,0 +1,128 @@
+//! Adds whatever the catalog lists that the httpwg crate doesn't have yet:
+//! suite modules, group files, and test functions that quote the RFC and
+//! fail with a `todo!()` until somebody writes them
+
+use std::{fs, path::Path, process::Command};
+
+use crate::catalog::Suite;
+
+/// Writes skeletons under `src_dir` (httpwg's `src`), returns how many tests
+/// it added
+pub fn write(suites: &[Suite], src_dir: &str) -> usize {
+    let mut added = 0;
+    let mut touched: Vec<String> = vec![];
+
+    for suite in suites {
+        let suite_name = &suite.name;
+        let suite_dir = format!("{src_dir}/{suite_name}");
+        let mod_path = format!("{suite_dir}/mod.rs");
+        if !Path::new(&mod_path).exists() {
+            println!("🦴 Adding suite {suite_name}");
+            fs::create_dir_all(&suite_dir).unwrap();
+            fs::write(
+                &mod_path,
+                format!("//! cf. <https://httpwg.org/specs/{suite_name}.html>\n"),
+            )
+            .unwrap();
+            declare_mod(&format!("{src_dir}/lib.rs"), suite_name);
+        }
+
+        for group in &suite.groups {
+            let group_name = &group.name;
+            let group_path = format!("{suite_dir}/{group_name}.rs");
+            if !Path::new(&group_path).exists() {
+                println!("🦴 Adding group {suite_name}::{group_name}");
+                let (number, title) = &group.sections[0];
+                fs::write(
+                    &group_path,
+                    format!(
+                        "//! Section {number}: {title}\n\nuse buffet::IntoHalves;\n\nuse crate::Conn;\n"
+                    ),
+                )
+                .unwrap();
+                declare_mod(&mod_path, group_name);
+            }
+
+            let mut source = fs::read_to_string(&group_path).unwrap();
+            let before = source.len();
+            for test in &group.tests {
+                let test_name = &test.name;
+                if source.contains(&format!("fn {test_name}<")) {
+                    continue;
+                }
+                println!("🦴 Adding test {suite_name}::{group_name}::{test_name}");
+
+                let current_section = source
+                    .lines()
+                    .filter_map(|line| line.strip_prefix("//---- Section "))
+                    .filter_map(|rest| rest.split_once(':'))
+                    .map(|(section, _)| section.trim())
+                    .last()
+                    .unwrap_or(&group.sections[0].0);
+                if current_section != test.section {
+                    let (number, title) = group
+                        .sections
+                        .iter()
+                        .find(|(number, _)| *number == test.section)
+                        .expect("catalog::read checks sections");
+                    source.push_str(&format!("\n//---- Section {number}: {title}\n"));
+                }
+
+                source.push('\n');
+                for line in test.citation.lines() {
+                    match line {
+                        "" => source.push_str("///\n"),
+                        line => source.push_str(&format!("/// {line}\n")),
+                    }
+                }
+                source.push_str(&format!(
+                    "pub async fn {test_name}<IO: IntoHalves>(mut conn: Conn<IO>) -> eyre::Result<()> {{\n    \
+                     conn.handshake().await?;\n\n    \
+                     todo!(\"check what section {} says\")\n}}\n",
+                    test.section
+                ));
+                added += 1;
+            }
+            if source.len() != before {
+                fs::write(&group_path, source).unwrap();
+                touched.push(group_path);
+            }
+        }
+    }
+
+    if !touched.is_empty() {
+        let status = Command::new("rustfmt")
+            .args(["--edition", "2021"])
+            .args(&touched)
+            .status()
+            .expect("while spawning rustfmt");
+        assert!(status.success(), "rustfmt failed on {touched:?}");
+    }
+
+    added
+}
+
+/// Adds `pub mod {name};` to the file at `path`, keeping its `pub mod` lines
+/// sorted
+fn declare_mod(path: &str, name: &str) {
+    let source = fs::read_to_string(path).unwrap_or_default();
+    let decl = format!("pub mod {name};");
+    let mut lines: Vec<&str> = source.lines().collect();
+    let at = lines
+        .iter()
+        .position(|line| line.starts_with("pub mod ") && *line > decl.as_str())
+        .or_else(|| {
+            lines
+                .iter()
+                .rposition(|line| line.starts_with("pub mod "))
+                .map(|i| i + 1)
+        });
+    match at {
+        Some(at) => lines.insert(at, &decl),
+        None => {
+            lines.push("");
+            lines.push(&decl);
+        }
+    }
+    fs::write(path, lines.join("\n") + "\n").unwrap();
+}
@@ -82,7 +82,11 @@ This server serves the following routes:
 /status/{code} — Returns a response with the specified status code.
 /repeat-4k-blocks/{repeat} — Streams the specified number of 4KB blocks (from memory)
 /stream-file/{name} — Streams the contents of a file from `/tmp/stream-file/{name}` — see `scripts/mkfiles.sh`
-/"#
+/goaway — Shuts the connection down gracefully, with a GOAWAY frame over HTTP/2
+/
+
+Extended CONNECT requests (RFC 8441, over HTTP/2) open a tunnel on any path,
+which echoes back whatever goes through it."#
     }
 }
 
@@ -5,7 +5,7 @@ use hyper_util::rt::TokioIo;
 
 use hyper_util::server::conn::auto;
 use service::TestService;
-use std::sync::Arc;
+use std::{error::Error as StdError, sync::Arc};
 use tokio::net::TcpListener;
 
 mod service;
@@ -38,9 +38,7 @@ async fn main() {
                         }
                         _ => {}
                     }
-                    builder
-                        .serve_connection(TokioIo::new(stream), TestService)
-                        .await
+                    serve_connection(builder, TokioIo::new(stream)).await
                 });
             }
         }
@@ -62,11 +60,34 @@ async fn main() {
                             // nothing
                         }
                     }
-                    builder
-                        .serve_connection(TokioIo::new(stream), TestService)
-                        .await
+                    serve_connection(builder, TokioIo::new(stream)).await
                 });
             }
         }
     }
 }
+
+/// Serves `io` until it's done, shutting down gracefully once [TestService]
+/// gets a request for `/goaway`. Extended CONNECT is enabled over HTTP/2.
+async fn serve_connection<I>(
+    mut builder: auto::Builder<TokioExecutor>,
+    io: I,
+) -> Result<(), Box<dyn StdError + Send + Sync>>
+where
+    I: hyper::rt::Read + hyper::rt::Write + Unpin + Send + 'static,
+{
+    builder.http2().enable_connect_protocol();
+
+    let service = TestService::default();
+    let goaway = service.goaway.clone();
+
+    let conn = builder.serve_connection(io, service);
+    tokio::pin!(conn);
+    tokio::select! {
+        res = conn.as_mut() => res,
+        _ = goaway.notified() => {
+            conn.as_mut().graceful_shutdown();
+            conn.await
+        }
+    }
+}
@@ -5,29 +5,39 @@
 //! - `/repeat-4k-blocks/{repeat}`: Streams the specified number of 4KB blocks.
 //! - `/stream-file/{name}`: Streams the contents of a file from
 //!   `/tmp/stream-file/`.
+//! - `/goaway`: Shuts the connection down gracefully.
 //! - `/`: Returns a default message.
 //! - Any other path: Returns a 404 Not Found response.
+//!
+//! Extended CONNECT requests (RFC 8441) open a tunnel on any path, which
+//! echoes back whatever goes through it.
 
 use http_body_util::{BodyExt, StreamBody};
 use httpwg_harness::{Settings, SAMPLE_4K_BLOCK};
-use tokio::io::AsyncReadExt;
+use hyper_util::rt::TokioIo;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-use std::{convert::Infallible, fmt::Debug, pin::Pin};
-use tokio::sync::mpsc;
+use std::{convert::Infallible, fmt::Debug, pin::Pin, sync::Arc};
+use tokio::sync::{mpsc, Notify};
 
 use bytes::Bytes;
 use futures::Future;
 use hyper::{
     body::{Body, Frame},
     service::Service,
-    Request, Response,
+    Method, Request, Response,
 };
 use tokio_stream::wrappers::ReceiverStream;
 use tracing::debug;
 
 type BoxBody<E> = Pin<Box<dyn Body<Data = Bytes, Error = E> + Send + Sync + 'static>>;
 
-pub(super) struct TestService;
+#[derive(Default)]
+pub(super) struct TestService {
+    /// notified on requests for `/goaway`: the connection should shut down
+    /// gracefully
+    pub(super) goaway: Arc<Notify>,
+}
 
 impl<B, E> Service<Request<B>> for TestService
 where
@@ -39,8 +49,30 @@ where
     type Future =
         Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
 
-    fn call(&self, req: Request<B>) -> Self::Future {
+    fn call(&self, mut req: Request<B>) -> Self::Future {
+        let goaway = self.goaway.clone();
         Box::pin(async move {
+            if req.method() == Method::CONNECT {
+                let on_upgrade = hyper::upgrade::on(&mut req);
+                tokio::spawn(async move {
+                    match on_upgrade.await {
+                        Ok(upgraded) => {
+                            let (mut r, mut w) = tokio::io::split(TokioIo::new(upgraded));
+                            if let Err(e) = tokio::io::copy(&mut r, &mut w).await {
+                                debug!("tunnel echo failed: {e}");
+                            }
+                            _ = w.shutdown().await;
+                        }
+                        Err(e) => debug!("upgrade failed: {e}"),
+                    }
+                });
+
+                let body: BoxBody<E> =
+                    Box::pin(http_body_util::Empty::new().map_err(|_| unreachable!()));
+                let res = Response::builder().status(200).body(body).unwrap();
+                return Ok(res);
+            }
+
             let (parts, mut req_body) = req.into_parts();
 
             let path = parts.uri.path();
@@ -113,6 +145,17 @@ where
                     let res = Response::builder().body(body).unwrap();
                     Ok(res)
                 }
+                ["goaway"] => {
+                    // drain body
+                    while let Some(_frame) = req_body.frame().await {}
+
+                    goaway.notify_one();
+
+                    let body = "shutting down gracefully".to_string();
+                    let body: BoxBody<E> = Box::pin(body.map_err(|_| unreachable!()));
+                    let res = Response::builder().status(200).body(body).unwrap();
+                    Ok(res)
+                }
                 [""] => {
                     // drain body
                     while let Some(_frame) = req_body.frame().await {}
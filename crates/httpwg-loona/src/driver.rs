@@ -1,13 +1,21 @@
+use std::rc::Rc;
+
 use b_x::{BxForResults, BX};
 use httpwg_harness::{Settings, SAMPLE_4K_BLOCK};
 
 use buffet::Piece;
 use loona::{
     error::NeverError, http::StatusCode, Body, BodyChunk, Encoder, ExpectResponseHeaders,
-    HeadersExt, Responder, Response, ResponseDone, ServerDriver, SinglePieceBody,
+    HeadersExt, Method, Responder, Response, ResponseDone, ServerDriver, SinglePieceBody,
 };
+use tokio::sync::Notify;
 
-pub(super) struct TestDriver;
+#[derive(Default)]
+pub(super) struct TestDriver {
+    /// notified on requests for `/goaway`: the connection should shut down
+    /// gracefully
+    pub(super) goaway: Rc<Notify>,
+}
 
 impl<OurEncoder> ServerDriver<OurEncoder> for TestDriver
 where
@@ -29,6 +37,20 @@ where
             .await?;
         }
 
+        // extended CONNECT (RFC 8441): echo whatever goes through the tunnel
+        if req.method == Method::Connect {
+            let mut res = res
+                .write_final_response(Response {
+                    status: StatusCode::OK,
+                    ..Default::default()
+                })
+                .await?;
+            while let BodyChunk::Chunk(chunk) = req_body.next_chunk().await.bx()? {
+                res.write_chunk(chunk).await?;
+            }
+            return Ok(res.finish_body(None).await?);
+        }
+
         let parts = req
             .uri
             .path()
@@ -93,6 +115,20 @@ where
                 .await
                 .bx()?
             }
+            ["goaway"] => {
+                drain_body(req_body).await?;
+                self.goaway.notify_one();
+
+                res.write_final_response_with_body(
+                    Response {
+                        status: StatusCode::OK,
+                        ..Default::default()
+                    },
+                    &mut SinglePieceBody::from("shutting down gracefully"),
+                )
+                .await
+                .bx()?
+            }
             // apparently `/` gives us that
             [""] => {
                 drain_body(req_body).await?;
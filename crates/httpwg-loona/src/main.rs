@@ -38,10 +38,8 @@ async fn real_main() {
 
             match settings.proto {
                 Proto::H1 => {
-                    let driver = TestDriver;
-                    let server_conf = Rc::new(h1::ServerConf {
-                        ..Default::default()
-                    });
+                    let driver = TestDriver::default();
+                    let server_conf = Rc::new(h1::ServerConf::conformance_test());
                     let io = stream.into_halves();
 
                     if let Err(e) = h1::serve(io, server_conf, client_buf, driver).await {
@@ -50,13 +48,16 @@ async fn real_main() {
                     tracing::debug!("http/1 server done");
                 }
                 Proto::H2C => {
-                    let driver = Rc::new(TestDriver);
-                    let server_conf = Rc::new(h2::ServerConf {
-                        ..Default::default()
-                    });
+                    let driver = TestDriver::default();
+                    let goaway = driver.goaway.clone();
+                    let shutdown = async move { goaway.notified().await };
+                    let server_conf = Rc::new(h2::ServerConf::conformance_test());
                     let io = stream.into_halves();
 
-                    if let Err(e) = h2::serve(io, server_conf, client_buf, driver).await {
+                    if let Err(e) =
+                        h2::serve_until(io, server_conf, client_buf, Rc::new(driver), shutdown)
+                            .await
+                    {
                         let mut should_ignore = false;
                         match &e {
                             ServeError::H2ConnectionError(H2ConnectionError::WriteError(e)) => {
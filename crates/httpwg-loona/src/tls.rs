@@ -6,6 +6,7 @@ use httpwg_harness::Settings;
 use ktls::CorkStream;
 use loona::h1;
 use loona::h2;
+use std::io::Read;
 use std::mem::ManuallyDrop;
 use std::os::fd::AsRawFd;
 use std::os::fd::FromRawFd;
@@ -19,7 +20,8 @@ use crate::driver::TestDriver;
 pub(super) async fn handle_tls_conn(stream: TcpStream) -> b_x::Result<()> {
     let mut server_config = Settings::gen_rustls_server_config().unwrap();
     server_config.enable_secret_extraction = true;
-    let driver = TestDriver;
+    server_config.max_early_data_size = 16 * 1024;
+    let driver = TestDriver::default();
     let h1_conf = Rc::new(h1::ServerConf::default());
     let h2_conf = Rc::new(h2::ServerConf::default());
 
@@ -30,7 +32,15 @@ pub(super) async fn handle_tls_conn(stream: TcpStream) -> b_x::Result<()> {
     stream.set_nonblocking(true).unwrap();
     let stream = tokio::net::TcpStream::from_std(stream)?;
     let stream = CorkStream::new(stream);
-    let stream = acceptor.accept(stream).await?;
+    let mut stream = acceptor.accept(stream).await?;
+
+    // rustls keeps early data apart from the rest, and kTLS won't know
+    // about it: it goes first.
+    let mut early = Vec::new();
+    if let Some(mut early_data) = stream.get_mut().1.early_data() {
+        early_data.read_to_end(&mut early)?;
+    }
+    tracing::debug!("{} bytes of early data", early.len());
 
     let is_h2 = matches!(stream.get_ref().1.alpn_protocol(), Some(b"h2"));
     tracing::debug!(%is_h2, "Performed TLS handshake");
@@ -46,14 +56,30 @@ pub(super) async fn handle_tls_conn(stream: TcpStream) -> b_x::Result<()> {
     let stream = stream.to_uring_tcp_stream()?;
 
     let mut client_buf = RollMut::alloc()?;
+    client_buf.reserve_at_least(early.len() + drained.len())?;
+    client_buf.put(&early[..])?;
     client_buf.put(&drained[..])?;
 
     if is_h2 {
         tracing::debug!("Using HTTP/2");
-        h2::serve(stream.into_halves(), h2_conf, client_buf, Rc::new(driver)).await?;
+        h2::serve_with_early_data(
+            stream.into_halves(),
+            h2_conf,
+            client_buf,
+            early.len(),
+            Rc::new(driver),
+        )
+        .await?;
     } else {
         tracing::debug!("Using HTTP/1.1");
-        h1::serve(stream.into_halves(), h1_conf, client_buf, driver).await?;
+        h1::serve_with_early_data(
+            stream.into_halves(),
+            h1_conf,
+            client_buf,
+            early.len(),
+            driver,
+        )
+        .await?;
     }
     Ok(())
 }
@@ -7,9 +7,194 @@
 /// The `$body` argument is pasted inside those unit test, and
 /// in that scope, `test` is the `httpwg` function you can use
 /// to run the test (that takes a `mut conn: Conn<IO>`)
+///
+/// Tests that don't match `HTTPWG_FILTER` (cf. `httpwg::filter`)
+/// return early, without running `$body`, and so do tests that need
+/// a feature the server doesn't support, if it says so:
+///
+/// ```ignore
+/// httpwg_macros::tests! { unsupported: ["extended-connect"], {
+///     // ...
+/// } }
+/// ```
+///
+/// Some tests are `#[ignore]`d, and some get a timeout of their own:
+/// cf. httpwg-gen's catalog, for both.
 #[macro_export]
 macro_rules! tests {
   ($body: tt) => {
+    $crate::tests! { unsupported: [], $body }
+  };
+  (unsupported: [$($feature: literal),* $(,)?], $body: tt) => {
+
+/// RFC 8441 provides a mechanism for running the WebSocket Protocol
+/// (RFC 6455) over a single stream of an HTTP/2 connection: the extended
+/// CONNECT method, which servers opt into with the
+/// SETTINGS_ENABLE_CONNECT_PROTOCOL parameter.
+///
+/// The tests that open tunnels expect the server to accept extended CONNECT
+/// requests on any path, and to echo back whatever goes through them.
+///
+/// cf. <https://httpwg.org/specs/rfc8441.html>
+#[cfg(test)]
+mod rfc8441 {
+use ::httpwg::rfc8441 as __suite;
+
+/// Section 3: The SETTINGS_ENABLE_CONNECT_PROTOCOL SETTINGS Parameter
+mod _3_the_settings_enable_connect_protocol_settings_parameter {
+use super::__suite::_3_the_settings_enable_connect_protocol_settings_parameter as __group;
+
+/// Upon receipt of SETTINGS_ENABLE_CONNECT_PROTOCOL with a value of 1,
+/// a client MAY use the Extended CONNECT as defined in this document
+/// when creating new streams.
+///
+/// The rest of this suite relies on the server advertising it.
+#[test]
+fn advertises_settings_enable_connect_protocol() {
+if !::httpwg::filter::Filter::from_env().matches("extended-connect/3/1", "rfc8441", "3", "advertises_settings_enable_connect_protocol") {
+    return;
+}
+if let Some(feature) = ::httpwg::filter::unsupported(&[$($feature),*], &["extended-connect"]) {
+    eprintln!("skipping extended-connect/3/1: the server doesn't support {feature}");
+    return;
+}
+use __group::advertises_settings_enable_connect_protocol as test;
+$body
+}
+
+/// Receipt of this parameter by a server does not have any impact.
+#[test]
+fn sends_settings_enable_connect_protocol() {
+if !::httpwg::filter::Filter::from_env().matches("extended-connect/3/2", "rfc8441", "3", "sends_settings_enable_connect_protocol") {
+    return;
+}
+if let Some(feature) = ::httpwg::filter::unsupported(&[$($feature),*], &["extended-connect"]) {
+    eprintln!("skipping extended-connect/3/2: the server doesn't support {feature}");
+    return;
+}
+use __group::sends_settings_enable_connect_protocol as test;
+$body
+}
+}
+
+/// Section 4: The Extended CONNECT Method
+mod _4_the_extended_connect_method {
+use super::__suite::_4_the_extended_connect_method as __group;
+
+/// Upon receiving a CONNECT request bearing the :protocol pseudo-header
+/// field, the server establishes a tunnel to another service of the
+/// protocol type indicated by the pseudo-header field.
+///
+/// This sends data through the tunnel, which the server echoes back, then
+/// closes it.
+#[test]
+fn sends_extended_connect_request() {
+if !::httpwg::filter::Filter::from_env().matches("extended-connect/4/1", "rfc8441", "4", "sends_extended_connect_request") {
+    return;
+}
+if let Some(feature) = ::httpwg::filter::unsupported(&[$($feature),*], &["extended-connect"]) {
+    eprintln!("skipping extended-connect/4/1: the server doesn't support {feature}");
+    return;
+}
+use __group::sends_extended_connect_request as test;
+$body
+}
+
+/// On requests that contain the :protocol pseudo-header field, the
+/// :scheme and :path pseudo-header fields of the target URI (see
+/// Section 5) MUST also be included.
+#[test]
+fn sends_extended_connect_request_without_scheme() {
+if !::httpwg::filter::Filter::from_env().matches("extended-connect/4/2", "rfc8441", "4", "sends_extended_connect_request_without_scheme") {
+    return;
+}
+if let Some(feature) = ::httpwg::filter::unsupported(&[$($feature),*], &["extended-connect"]) {
+    eprintln!("skipping extended-connect/4/2: the server doesn't support {feature}");
+    return;
+}
+use __group::sends_extended_connect_request_without_scheme as test;
+$body
+}
+
+/// On requests that contain the :protocol pseudo-header field, the
+/// :scheme and :path pseudo-header fields of the target URI (see
+/// Section 5) MUST also be included.
+#[test]
+fn sends_extended_connect_request_without_path() {
+if !::httpwg::filter::Filter::from_env().matches("extended-connect/4/3", "rfc8441", "4", "sends_extended_connect_request_without_path") {
+    return;
+}
+if let Some(feature) = ::httpwg::filter::unsupported(&[$($feature),*], &["extended-connect"]) {
+    eprintln!("skipping extended-connect/4/3: the server doesn't support {feature}");
+    return;
+}
+use __group::sends_extended_connect_request_without_path as test;
+$body
+}
+
+/// A new pseudo-header field :protocol MAY be included on request
+/// HEADERS indicating the desired protocol to be spoken on the tunnel
+/// created by CONNECT.
+///
+/// There's no tunnel without CONNECT: this sends `:protocol` on a GET
+/// request, which makes it malformed.
+#[test]
+fn sends_protocol_pseudo_header_on_non_connect_request() {
+if !::httpwg::filter::Filter::from_env().matches("extended-connect/4/4", "rfc8441", "4", "sends_protocol_pseudo_header_on_non_connect_request") {
+    return;
+}
+if let Some(feature) = ::httpwg::filter::unsupported(&[$($feature),*], &["extended-connect"]) {
+    eprintln!("skipping extended-connect/4/4: the server doesn't support {feature}");
+    return;
+}
+use __group::sends_protocol_pseudo_header_on_non_connect_request as test;
+$body
+}
+}
+
+/// Section 5: Using Extended CONNECT to Bootstrap the WebSocket Protocol
+mod _5_using_extended_connect_to_bootstrap_the_websocket_protocol {
+use super::__suite::_5_using_extended_connect_to_bootstrap_the_websocket_protocol as __group;
+
+/// After successfully processing the opening handshake, the peers
+/// should proceed with The WebSocket Protocol [RFC6455] using the
+/// HTTP/2 stream from the CONNECT transaction as if it were the TCP
+/// connection referred to in [RFC6455].
+///
+/// This sends a WebSocket frame over the stream, which the server echoes
+/// back, then closes it, which is how orderly TCP-level closures are
+/// represented.
+#[test]
+fn sends_websocket_handshake() {
+if !::httpwg::filter::Filter::from_env().matches("extended-connect/5/1", "rfc8441", "5", "sends_websocket_handshake") {
+    return;
+}
+if let Some(feature) = ::httpwg::filter::unsupported(&[$($feature),*], &["extended-connect"]) {
+    eprintln!("skipping extended-connect/5/1: the server doesn't support {feature}");
+    return;
+}
+use __group::sends_websocket_handshake as test;
+$body
+}
+
+/// RST exceptions are represented with the stream error (Section 5.4.2
+/// of [RFC7540]) of type CANCEL (Section 7 of [RFC7540]).
+///
+/// This cancels a WebSocket stream, which must not affect the connection.
+#[test]
+fn sends_rst_stream_cancel_on_websocket_stream() {
+if !::httpwg::filter::Filter::from_env().matches("extended-connect/5/2", "rfc8441", "5", "sends_rst_stream_cancel_on_websocket_stream") {
+    return;
+}
+if let Some(feature) = ::httpwg::filter::unsupported(&[$($feature),*], &["extended-connect"]) {
+    eprintln!("skipping extended-connect/5/2: the server doesn't support {feature}");
+    return;
+}
+use __group::sends_rst_stream_cancel_on_websocket_stream as test;
+$body
+}
+}
+}
 
 /// RFC 9113 describes an optimized expression of the
 /// semantics of the Hypertext Transfer Protocol (HTTP), referred to as
@@ -26,6 +211,56 @@ macro_rules! tests {
 mod rfc9113 {
 use ::httpwg::rfc9113 as __suite;
 
+/// Section 10: Security Considerations
+mod _10_security_considerations {
+use super::__suite::_10_security_considerations as __group;
+
+/// An endpoint that doesn't monitor use of these features exposes itself
+/// to a risk of denial of service. Implementations SHOULD track the use
+/// of these features and set limits on their use. An endpoint MAY treat
+/// activity that is suspicious as a connection error (Section 5.4.1) of
+/// type ENHANCE_YOUR_CALM.
+///
+/// This opens [crate::Config::rapid_reset_streams] streams and resets each
+/// of them right away, at [crate::Config::rapid_reset_rate], cf. the 2023
+/// "rapid reset" attack (CVE-2023-44487): it costs the client next to
+/// nothing, and the server has to start on a request every time. The
+/// server must keep up, and push back at some point: with a GOAWAY, or by
+/// closing the connection (which can make us miss the GOAWAY, since we're
+/// still writing when it does).
+#[test]
+fn sends_rapid_resets() {
+if !::httpwg::filter::Filter::from_env().matches("http2/10.5/1", "rfc9113", "10.5", "sends_rapid_resets") {
+    return;
+}
+fn test<IO: ::httpwg::macros::IntoHalves>(conn: ::httpwg::Conn<IO>) -> impl ::std::future::Future<Output = ::httpwg::macros::Result<()>> {
+    ::httpwg::with_timeout(::std::time::Duration::from_secs(60), __group::sends_rapid_resets(conn))
+}
+$body
+}
+
+/// A large field block (Section 8.2) can cause an implementation to
+/// commit a large amount of state. [...] A server that receives a larger
+/// field block than it is willing to handle can send an HTTP 431 (Request
+/// Header Fields Too Large) status code. [...] An endpoint can treat
+/// this as a connection error (Section 5.4.1) of type
+/// ENHANCE_YOUR_CALM.
+///
+/// This sends CONTINUATION frames that never set END_HEADERS, cf. the
+/// 2024 "CONTINUATION flood" advisories: the server must stop buffering
+/// them at some point, and close the connection.
+#[test]
+fn sends_endless_continuation_frames() {
+if !::httpwg::filter::Filter::from_env().matches("http2/10.5.1/1", "rfc9113", "10.5.1", "sends_endless_continuation_frames") {
+    return;
+}
+fn test<IO: ::httpwg::macros::IntoHalves>(conn: ::httpwg::Conn<IO>) -> impl ::std::future::Future<Output = ::httpwg::macros::Result<()>> {
+    ::httpwg::with_timeout(::std::time::Duration::from_secs(60), __group::sends_endless_continuation_frames(conn))
+}
+$body
+}
+}
+
 /// Section 3: Starting HTTP/2
 mod _3_starting_http2 {
 use super::__suite::_3_starting_http2 as __group;
@@ -35,6 +270,9 @@ use super::__suite::_3_starting_http2 as __group;
 /// the server sends in the HTTP/2 connection.
 #[test]
 fn sends_client_connection_preface() {
+if !::httpwg::filter::Filter::from_env().matches("http2/3.4/1", "rfc9113", "3.4", "sends_client_connection_preface") {
+    return;
+}
 use __group::sends_client_connection_preface as test;
 $body
 }
@@ -43,9 +281,69 @@ $body
 /// a connection error (Section 5.4.1) of type PROTOCOL_ERROR.
 #[test]
 fn sends_invalid_connection_preface() {
+if !::httpwg::filter::Filter::from_env().matches("http2/3.4/2", "rfc9113", "3.4", "sends_invalid_connection_preface") {
+    return;
+}
 use __group::sends_invalid_connection_preface as test;
 $body
 }
+
+/// Clients and servers MUST treat an invalid connection preface as
+/// a connection error (Section 5.4.1) of type PROTOCOL_ERROR.
+///
+/// This sends all of the preface but its last byte, then the SETTINGS
+/// frame: a server that compares fewer than 24 bytes would take it.
+#[test]
+fn sends_truncated_connection_preface() {
+if !::httpwg::filter::Filter::from_env().matches("http2/3.4/3", "rfc9113", "3.4", "sends_truncated_connection_preface") {
+    return;
+}
+use __group::sends_truncated_connection_preface as test;
+$body
+}
+
+/// Clients and servers MUST treat an invalid connection preface as
+/// a connection error (Section 5.4.1) of type PROTOCOL_ERROR.
+///
+/// The preface is octets, not text: `pri * http/2.0` doesn't match it.
+#[test]
+fn sends_connection_preface_in_wrong_case() {
+if !::httpwg::filter::Filter::from_env().matches("http2/3.4/4", "rfc9113", "3.4", "sends_connection_preface_in_wrong_case") {
+    return;
+}
+use __group::sends_connection_preface_in_wrong_case as test;
+$body
+}
+
+/// That is, the connection preface starts with the string
+/// "PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n". This sequence MUST be followed
+/// by a SETTINGS frame (Section 6.5), which MAY be empty.
+///
+/// Here it's followed by a stray CRLF, which makes for a frame header
+/// announcing a frame larger than SETTINGS_MAX_FRAME_SIZE, so a
+/// FRAME_SIZE_ERROR is fine too.
+#[test]
+fn sends_connection_preface_with_extra_bytes() {
+if !::httpwg::filter::Filter::from_env().matches("http2/3.4/5", "rfc9113", "3.4", "sends_connection_preface_with_extra_bytes") {
+    return;
+}
+use __group::sends_connection_preface_with_extra_bytes as test;
+$body
+}
+
+/// The client connection preface starts with a sequence of 24 octets.
+///
+/// Nothing says those arrive in a single read: this sends the preface in
+/// two halves on one connection and a byte at a time on another, and
+/// expects both handshakes to go through and a request to get a response.
+#[test]
+fn sends_connection_preface_in_pieces() {
+if !::httpwg::filter::Filter::from_env().matches("http2/3.4/6", "rfc9113", "3.4", "sends_connection_preface_in_pieces") {
+    return;
+}
+use __group::sends_connection_preface_in_pieces as test;
+$body
+}
 }
 
 /// Section 4: HTTP Frames
@@ -55,6 +353,9 @@ use super::__suite::_4_http_frames as __group;
 /// Implementations MUST ignore and discard frames of unknown types.
 #[test]
 fn sends_frame_with_unknown_type() {
+if !::httpwg::filter::Filter::from_env().matches("http2/4.1/1", "rfc9113", "4.1", "sends_frame_with_unknown_type") {
+    return;
+}
 use __group::sends_frame_with_unknown_type as test;
 $body
 }
@@ -63,6 +364,9 @@ $body
 /// unset (0x00) when sending.
 #[test]
 fn sends_frame_with_unused_flags() {
+if !::httpwg::filter::Filter::from_env().matches("http2/4.1/2", "rfc9113", "4.1", "sends_frame_with_unused_flags") {
+    return;
+}
 use __group::sends_frame_with_unused_flags as test;
 $body
 }
@@ -72,12 +376,34 @@ $body
 /// MUST be ignored when receiving.
 #[test]
 fn sends_frame_with_reserved_bit_set() {
+if !::httpwg::filter::Filter::from_env().matches("http2/4.1/3", "rfc9113", "4.1", "sends_frame_with_reserved_bit_set") {
+    return;
+}
 use __group::sends_frame_with_reserved_bit_set as test;
 $body
 }
 
+/// All frames begin with a fixed 9-octet header followed by a
+/// variable-length frame payload.
+///
+/// Nothing says those bytes arrive in a single read: this sends a request
+/// split in the middle of its frame header, then one a byte at a time, then
+/// one in small writes with pauses in between, and expects a response to
+/// each of them.
+#[test]
+fn sends_frames_in_awkward_chunks() {
+if !::httpwg::filter::Filter::from_env().matches("http2/4.1/4", "rfc9113", "4.1", "sends_frames_in_awkward_chunks") {
+    return;
+}
+use __group::sends_frames_in_awkward_chunks as test;
+$body
+}
+
 #[test]
 fn data_frame_with_max_length() {
+if !::httpwg::filter::Filter::from_env().matches("http2/4.1/5", "rfc9113", "4.1", "data_frame_with_max_length") {
+    return;
+}
 use __group::data_frame_with_max_length as test;
 $body
 }
@@ -88,6 +414,9 @@ $body
 /// data
 #[test]
 fn frame_exceeding_max_size() {
+if !::httpwg::filter::Filter::from_env().matches("http2/4.1/6", "rfc9113", "4.1", "frame_exceeding_max_size") {
+    return;
+}
 use __group::frame_exceeding_max_size as test;
 $body
 }
@@ -99,6 +428,9 @@ $body
 /// a SETTINGS frame, and any frame with a stream identifier of 0.
 #[test]
 fn large_headers_frame_exceeding_max_size() {
+if !::httpwg::filter::Filter::from_env().matches("http2/4.1/7", "rfc9113", "4.1", "large_headers_frame_exceeding_max_size") {
+    return;
+}
 use __group::large_headers_frame_exceeding_max_size as test;
 $body
 }
@@ -107,24 +439,92 @@ $body
 /// (Section 5.4.1) of type COMPRESSION_ERROR.
 #[test]
 fn invalid_header_block_fragment() {
+if !::httpwg::filter::Filter::from_env().matches("http2/4.3/1", "rfc9113", "4.3", "invalid_header_block_fragment") {
+    return;
+}
 use __group::invalid_header_block_fragment as test;
 $body
 }
 
+/// A decoding error in a field block MUST be treated as a connection error
+/// (Section 5.4.1) of type COMPRESSION_ERROR.
+///
+/// This one refers to an index past the end of the static table, while
+/// the dynamic table is still empty.
+#[test]
+fn header_block_with_invalid_index() {
+if !::httpwg::filter::Filter::from_env().matches("http2/4.3/2", "rfc9113", "4.3", "header_block_with_invalid_index") {
+    return;
+}
+use __group::header_block_with_invalid_index as test;
+$body
+}
+
+/// A decoding error in a field block MUST be treated as a connection error
+/// (Section 5.4.1) of type COMPRESSION_ERROR.
+///
+/// This one has a Huffman-encoded value that claims more octets than the
+/// field block has left.
+#[test]
+fn header_block_with_truncated_huffman_string() {
+if !::httpwg::filter::Filter::from_env().matches("http2/4.3/3", "rfc9113", "4.3", "header_block_with_truncated_huffman_string") {
+    return;
+}
+use __group::header_block_with_truncated_huffman_string as test;
+$body
+}
+
+/// A decoding error in a field block MUST be treated as a connection error
+/// (Section 5.4.1) of type COMPRESSION_ERROR.
+///
+/// This one opens with a dynamic table size update to 4097 octets, past
+/// the SETTINGS_HEADER_TABLE_SIZE we left at its default of 4096, which
+/// RFC 7541, Section 6.3 calls a decoding error.
+#[test]
+fn dynamic_table_size_update_exceeding_max() {
+if !::httpwg::filter::Filter::from_env().matches("http2/4.3/4", "rfc9113", "4.3", "dynamic_table_size_update_exceeding_max") {
+    return;
+}
+use __group::dynamic_table_size_update_exceeding_max as test;
+$body
+}
+
 /// Each header block is processed as a discrete unit. Header blocks
 /// MUST be transmitted as a contiguous sequence of frames, with no
 /// interleaved frames of any other type or from any other stream.
 #[test]
 fn priority_frame_while_sending_headers() {
+if !::httpwg::filter::Filter::from_env().matches("http2/4.3/5", "rfc9113", "4.3", "priority_frame_while_sending_headers") {
+    return;
+}
 use __group::priority_frame_while_sending_headers as test;
 $body
 }
 
+/// Each field block is processed as a discrete unit. Field blocks MUST be
+/// transmitted as a contiguous sequence of frames, with no interleaved
+/// frames of any other type or from any other stream.
+///
+/// This goes for the server's responses too: with several of them in
+/// flight, each one's HEADERS frame must be followed by its own
+/// CONTINUATION frames, if any, and nothing else.
+#[test]
+fn response_field_blocks_are_contiguous() {
+if !::httpwg::filter::Filter::from_env().matches("http2/4.3/6", "rfc9113", "4.3", "response_field_blocks_are_contiguous") {
+    return;
+}
+use __group::response_field_blocks_are_contiguous as test;
+$body
+}
+
 /// Each header block is processed as a discrete unit. Header blocks
 /// MUST be transmitted as a contiguous sequence of frames, with no
 /// interleaved frames of any other type or from any other stream.
 #[test]
 fn headers_frame_to_another_stream() {
+if !::httpwg::filter::Filter::from_env().matches("http2/4.3/7", "rfc9113", "4.3", "headers_frame_to_another_stream") {
+    return;
+}
 use __group::headers_frame_to_another_stream as test;
 $body
 }
@@ -140,6 +540,9 @@ use super::__suite::_5_streams_and_multiplexing as __group;
 /// (Section 5.4.1) of type PROTOCOL_ERROR.
 #[test]
 fn idle_sends_data_frame() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.1/1", "rfc9113", "5.1", "idle_sends_data_frame") {
+    return;
+}
 use __group::idle_sends_data_frame as test;
 $body
 }
@@ -150,6 +553,9 @@ $body
 /// (Section 5.4.1) of type PROTOCOL_ERROR.
 #[test]
 fn idle_sends_rst_stream_frame() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.1/2", "rfc9113", "5.1", "idle_sends_rst_stream_frame") {
+    return;
+}
 use __group::idle_sends_rst_stream_frame as test;
 $body
 }
@@ -160,6 +566,9 @@ $body
 /// (Section 5.4.1) of type PROTOCOL_ERROR.
 #[test]
 fn idle_sends_window_update_frame() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.1/3", "rfc9113", "5.1", "idle_sends_window_update_frame") {
+    return;
+}
 use __group::idle_sends_window_update_frame as test;
 $body
 }
@@ -170,6 +579,9 @@ $body
 /// (Section 5.4.1) of type PROTOCOL_ERROR.
 #[test]
 fn idle_sends_continuation_frame() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.1/4", "rfc9113", "5.1", "idle_sends_continuation_frame") {
+    return;
+}
 use __group::idle_sends_continuation_frame as test;
 $body
 }
@@ -181,6 +593,9 @@ $body
 /// of type STREAM_CLOSED.
 #[test]
 fn half_closed_remote_sends_data_frame() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.1/5", "rfc9113", "5.1", "half_closed_remote_sends_data_frame") {
+    return;
+}
 use __group::half_closed_remote_sends_data_frame as test;
 $body
 }
@@ -192,6 +607,9 @@ $body
 /// of type STREAM_CLOSED.
 #[test]
 fn half_closed_remote_sends_headers_frame() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.1/6", "rfc9113", "5.1", "half_closed_remote_sends_headers_frame") {
+    return;
+}
 use __group::half_closed_remote_sends_headers_frame as test;
 $body
 }
@@ -203,16 +621,64 @@ $body
 /// of type STREAM_CLOSED.
 #[test]
 fn half_closed_remote_sends_continuation_frame() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.1/7", "rfc9113", "5.1", "half_closed_remote_sends_continuation_frame") {
+    return;
+}
 use __group::half_closed_remote_sends_continuation_frame as test;
 $body
 }
 
+/// half-closed (remote):
+/// If an endpoint receives additional frames, other than
+/// WINDOW_UPDATE, PRIORITY, or RST_STREAM, for a stream that is in
+/// this state, it MUST respond with a stream error (Section 5.4.2)
+/// of type STREAM_CLOSED.
+#[test]
+fn half_closed_remote_sends_window_update_frame() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.1/8", "rfc9113", "5.1", "half_closed_remote_sends_window_update_frame") {
+    return;
+}
+use __group::half_closed_remote_sends_window_update_frame as test;
+$body
+}
+
+/// half-closed (remote):
+/// If an endpoint receives additional frames, other than
+/// WINDOW_UPDATE, PRIORITY, or RST_STREAM, for a stream that is in
+/// this state, it MUST respond with a stream error (Section 5.4.2)
+/// of type STREAM_CLOSED.
+#[test]
+fn half_closed_remote_sends_priority_frame() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.1/9", "rfc9113", "5.1", "half_closed_remote_sends_priority_frame") {
+    return;
+}
+use __group::half_closed_remote_sends_priority_frame as test;
+$body
+}
+
+/// half-closed (remote):
+/// If an endpoint receives additional frames, other than
+/// WINDOW_UPDATE, PRIORITY, or RST_STREAM, for a stream that is in
+/// this state, it MUST respond with a stream error (Section 5.4.2)
+/// of type STREAM_CLOSED.
+#[test]
+fn half_closed_remote_sends_rst_stream_frame() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.1/10", "rfc9113", "5.1", "half_closed_remote_sends_rst_stream_frame") {
+    return;
+}
+use __group::half_closed_remote_sends_rst_stream_frame as test;
+$body
+}
+
 /// closed:
 /// An endpoint that receives any frame other than PRIORITY after
 /// receiving a RST_STREAM MUST treat that as a stream error
 /// (Section 5.4.2) of type STREAM_CLOSED.
 #[test]
 fn closed_sends_data_frame_after_rst_stream() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.1/11", "rfc9113", "5.1", "closed_sends_data_frame_after_rst_stream") {
+    return;
+}
 use __group::closed_sends_data_frame_after_rst_stream as test;
 $body
 }
@@ -223,6 +689,9 @@ $body
 /// (Section 5.4.2) of type STREAM_CLOSED.
 #[test]
 fn closed_sends_headers_frame_after_rst_stream() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.1/12", "rfc9113", "5.1", "closed_sends_headers_frame_after_rst_stream") {
+    return;
+}
 use __group::closed_sends_headers_frame_after_rst_stream as test;
 $body
 }
@@ -233,6 +702,9 @@ $body
 /// (Section 5.4.2) of type STREAM_CLOSED.
 #[test]
 fn closed_sends_continuation_frame_after_rst_stream() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.1/13", "rfc9113", "5.1", "closed_sends_continuation_frame_after_rst_stream") {
+    return;
+}
 use __group::closed_sends_continuation_frame_after_rst_stream as test;
 $body
 }
@@ -243,6 +715,9 @@ $body
 /// error (Section 6.4.1) of type STREAM_CLOSED.
 #[test]
 fn closed_sends_data_frame() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.1/14", "rfc9113", "5.1", "closed_sends_data_frame") {
+    return;
+}
 use __group::closed_sends_data_frame as test;
 $body
 }
@@ -253,6 +728,9 @@ $body
 /// error (Section 6.4.1) of type STREAM_CLOSED.
 #[test]
 fn closed_sends_headers_frame() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.1/15", "rfc9113", "5.1", "closed_sends_headers_frame") {
+    return;
+}
 use __group::closed_sends_headers_frame as test;
 $body
 }
@@ -263,6 +741,9 @@ $body
 /// error (Section 6.4.1) of type STREAM_CLOSED.
 #[test]
 fn closed_sends_continuation_frame() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.1/16", "rfc9113", "5.1", "closed_sends_continuation_frame") {
+    return;
+}
 use __group::closed_sends_continuation_frame as test;
 $body
 }
@@ -272,6 +753,9 @@ $body
 /// type PROTOCOL_ERROR.
 #[test]
 fn sends_even_numbered_stream_identifier() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.1/17", "rfc9113", "5.1", "sends_even_numbered_stream_identifier") {
+    return;
+}
 use __group::sends_even_numbered_stream_identifier as test;
 $body
 }
@@ -281,35 +765,86 @@ $body
 /// type PROTOCOL_ERROR.
 #[test]
 fn sends_smaller_stream_identifier() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.1/18", "rfc9113", "5.1", "sends_smaller_stream_identifier") {
+    return;
+}
 use __group::sends_smaller_stream_identifier as test;
 $body
 }
 
 #[test]
 fn exceeds_concurrent_stream_limit() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.1.2/1", "rfc9113", "5.1.2", "exceeds_concurrent_stream_limit") {
+    return;
+}
 use __group::exceeds_concurrent_stream_limit as test;
 $body
 }
 
+/// A peer can limit the number of concurrently active streams using the
+/// SETTINGS_MAX_CONCURRENT_STREAMS parameter (see Section 6.5.2) within a
+/// SETTINGS frame. [...]
+///
+/// Endpoints MUST NOT exceed the limit set by their peer. An endpoint
+/// that receives a HEADERS frame that causes its advertised concurrent
+/// stream limit to be exceeded MUST treat this as a stream error
+/// (Section 5.4.2) of type PROTOCOL_ERROR or REFUSED_STREAM.
+///
+/// This opens exactly as many streams as the server allows, then one
+/// more: only that last one may be refused, and once it is, the others
+/// must still get their responses.
+#[test]
+fn exceeds_concurrent_stream_limit_by_one() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.1.2/2", "rfc9113", "5.1.2", "exceeds_concurrent_stream_limit_by_one") {
+    return;
+}
+use __group::exceeds_concurrent_stream_limit_by_one as test;
+$body
+}
+
 /// After sending the GOAWAY frame for an error condition,
 /// the endpoint MUST close the TCP connection.
 #[test]
 fn invalid_ping_frame_for_connection_close() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.4.1/1", "rfc9113", "5.4.1", "invalid_ping_frame_for_connection_close") {
+    return;
+}
 use __group::invalid_ping_frame_for_connection_close as test;
 $body
 }
 
 #[test]
 fn test_invalid_ping_frame_for_goaway() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.4.1/2", "rfc9113", "5.4.1", "test_invalid_ping_frame_for_goaway") {
+    return;
+}
 use __group::test_invalid_ping_frame_for_goaway as test;
 $body
 }
 
+/// A connection error is any error that prevents further processing of
+/// the frame layer or corrupts any connection state.
+///
+/// That state is the connection's own: this opens a second connection,
+/// triggers a connection error on the first one, then checks that the
+/// second one still gets a response.
+#[test]
+fn connection_error_leaves_other_connections_alone() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.4.1/3", "rfc9113", "5.4.1", "connection_error_leaves_other_connections_alone") {
+    return;
+}
+use __group::connection_error_leaves_other_connections_alone as test;
+$body
+}
+
 /// Extension frames that appear in the middle of a header block
 /// (Section 4.3) are not permitted; these MUST be treated as
 /// a connection error (Section 5.4.1) of type PROTOCOL_ERROR.
 #[test]
 fn unknown_extension_frame_in_header_block() {
+if !::httpwg::filter::Filter::from_env().matches("http2/5.5/1", "rfc9113", "5.5", "unknown_extension_frame_in_header_block") {
+    return;
+}
 use __group::unknown_extension_frame_in_header_block as test;
 $body
 }
@@ -325,6 +860,9 @@ use super::__suite::_6_frame_definitions as __group;
 /// PROTOCOL_ERROR.
 #[test]
 fn sends_data_frame_with_zero_stream_id() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.1/1", "rfc9113", "6.1", "sends_data_frame_with_zero_stream_id") {
+    return;
+}
 use __group::sends_data_frame_with_zero_stream_id as test;
 $body
 }
@@ -336,6 +874,9 @@ $body
 /// Note: This test case is duplicated with 5.1.
 #[test]
 fn sends_data_frame_on_invalid_stream_state() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.1/2", "rfc9113", "6.1", "sends_data_frame_on_invalid_stream_state") {
+    return;
+}
 use __group::sends_data_frame_on_invalid_stream_state as test;
 $body
 }
@@ -345,16 +886,53 @@ $body
 /// (Section 5.4.1) of type PROTOCOL_ERROR.
 #[test]
 fn sends_data_frame_with_invalid_pad_length() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.1/3", "rfc9113", "6.1", "sends_data_frame_with_invalid_pad_length") {
+    return;
+}
 use __group::sends_data_frame_with_invalid_pad_length as test;
 $body
 }
 
+/// If the length of the padding is the length of the frame payload
+/// or greater, the recipient MUST treat this as a connection error
+/// (Section 5.4.1) of type PROTOCOL_ERROR.
+///
+/// This sends a pad length equal to the frame payload length, which
+/// counts the Pad Length field itself.
+#[test]
+fn sends_data_frame_with_pad_length_equal_to_payload_length() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.1/4", "rfc9113", "6.1", "sends_data_frame_with_pad_length_equal_to_payload_length") {
+    return;
+}
+use __group::sends_data_frame_with_pad_length_equal_to_payload_length as test;
+$body
+}
+
+/// If the length of the padding is the length of the frame payload
+/// or greater, the recipient MUST treat this as a connection error
+/// (Section 5.4.1) of type PROTOCOL_ERROR.
+///
+/// This sends valid padding: none at all, the most there can be, and
+/// enough to leave no data. It must be stripped, and must not count
+/// towards the content-length.
+#[test]
+fn sends_data_frames_with_valid_padding() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.1/5", "rfc9113", "6.1", "sends_data_frames_with_valid_padding") {
+    return;
+}
+use __group::sends_data_frames_with_valid_padding as test;
+$body
+}
+
 /// HEADERS frames MUST be associated with a stream. If a HEADERS
 /// frame is received whose stream identifier field is 0x0, the
 /// recipient MUST respond with a connection error (Section 5.4.1)
 /// of type PROTOCOL_ERROR.
 #[test]
 fn sends_headers_frame_with_zero_stream_id() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.2/1", "rfc9113", "6.2", "sends_headers_frame_with_zero_stream_id") {
+    return;
+}
 use __group::sends_headers_frame_with_zero_stream_id as test;
 $body
 }
@@ -365,16 +943,54 @@ $body
 /// fragment MUST be treated as a PROTOCOL_ERROR.
 #[test]
 fn sends_headers_frame_with_invalid_pad_length() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.2/2", "rfc9113", "6.2", "sends_headers_frame_with_invalid_pad_length") {
+    return;
+}
 use __group::sends_headers_frame_with_invalid_pad_length as test;
 $body
 }
 
+/// The HEADERS frame can include padding. Padding fields and flags
+/// are identical to those defined for DATA frames (Section 6.1).
+/// Padding that exceeds the size remaining for the header block
+/// fragment MUST be treated as a PROTOCOL_ERROR.
+///
+/// This sends a pad length equal to the frame payload length, which
+/// counts the Pad Length field itself.
+#[test]
+fn sends_headers_frame_with_pad_length_equal_to_payload_length() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.2/3", "rfc9113", "6.2", "sends_headers_frame_with_pad_length_equal_to_payload_length") {
+    return;
+}
+use __group::sends_headers_frame_with_pad_length_equal_to_payload_length as test;
+$body
+}
+
+/// The HEADERS frame can include padding. Padding fields and flags
+/// are identical to those defined for DATA frames (Section 6.1).
+/// Padding that exceeds the size remaining for the header block
+/// fragment MUST be treated as a PROTOCOL_ERROR.
+///
+/// This sends valid padding, which must be stripped before the header
+/// block fragment is decoded.
+#[test]
+fn sends_headers_frame_with_valid_padding() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.2/4", "rfc9113", "6.2", "sends_headers_frame_with_valid_padding") {
+    return;
+}
+use __group::sends_headers_frame_with_valid_padding as test;
+$body
+}
+
 /// The PRIORITY frame always identifies a stream. If a PRIORITY
 /// frame is received with a stream identifier of 0x0, the recipient
 /// MUST respond with a connection error (Section 5.4.1) of type
 /// PROTOCOL_ERROR.
 #[test]
 fn sends_priority_frame_with_zero_stream_id() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.3/1", "rfc9113", "6.3", "sends_priority_frame_with_zero_stream_id") {
+    return;
+}
 use __group::sends_priority_frame_with_zero_stream_id as test;
 $body
 }
@@ -384,6 +1000,9 @@ $body
 /// FRAME_SIZE_ERROR.
 #[test]
 fn sends_priority_frame_with_invalid_length() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.3/2", "rfc9113", "6.3", "sends_priority_frame_with_invalid_length") {
+    return;
+}
 use __group::sends_priority_frame_with_invalid_length as test;
 $body
 }
@@ -394,6 +1013,9 @@ $body
 /// (Section 5.4.1) of type PROTOCOL_ERROR.
 #[test]
 fn sends_rst_stream_frame_with_zero_stream_id() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.4/1", "rfc9113", "6.4", "sends_rst_stream_frame_with_zero_stream_id") {
+    return;
+}
 use __group::sends_rst_stream_frame_with_zero_stream_id as test;
 $body
 }
@@ -404,6 +1026,9 @@ $body
 /// (Section 5.4.1) of type PROTOCOL_ERROR.
 #[test]
 fn sends_rst_stream_frame_on_idle_stream() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.4/2", "rfc9113", "6.4", "sends_rst_stream_frame_on_idle_stream") {
+    return;
+}
 use __group::sends_rst_stream_frame_on_idle_stream as test;
 $body
 }
@@ -413,6 +1038,9 @@ $body
 /// FRAME_SIZE_ERROR.
 #[test]
 fn sends_rst_stream_frame_with_invalid_length() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.4/3", "rfc9113", "6.4", "sends_rst_stream_frame_with_invalid_length") {
+    return;
+}
 use __group::sends_rst_stream_frame_with_invalid_length as test;
 $body
 }
@@ -426,6 +1054,9 @@ $body
 /// of type FRAME_SIZE_ERROR.
 #[test]
 fn sends_settings_frame_with_ack_and_payload() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.5.1/1", "rfc9113", "6.5.1", "sends_settings_frame_with_ack_and_payload") {
+    return;
+}
 use __group::sends_settings_frame_with_ack_and_payload as test;
 $body
 }
@@ -438,6 +1069,9 @@ $body
 /// of type PROTOCOL_ERROR.
 #[test]
 fn sends_settings_frame_with_non_zero_stream_id() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.5.1/2", "rfc9113", "6.5.1", "sends_settings_frame_with_non_zero_stream_id") {
+    return;
+}
 use __group::sends_settings_frame_with_non_zero_stream_id as test;
 $body
 }
@@ -451,6 +1085,9 @@ $body
 /// FRAME_SIZE_ERROR.
 #[test]
 fn sends_settings_frame_with_invalid_length() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.5.1/3", "rfc9113", "6.5.1", "sends_settings_frame_with_invalid_length") {
+    return;
+}
 use __group::sends_settings_frame_with_invalid_length as test;
 $body
 }
@@ -461,20 +1098,86 @@ $body
 /// connection error (Section 5.4.1) of type PROTOCOL_ERROR.
 #[test]
 fn sends_settings_enable_push_with_invalid_value() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.5.2/1", "rfc9113", "6.5.2", "sends_settings_enable_push_with_invalid_value") {
+    return;
+}
 use __group::sends_settings_enable_push_with_invalid_value as test;
 $body
 }
 
+/// SETTINGS_ENABLE_PUSH (0x2):
+/// The initial value is 1, which indicates that server push is
+/// permitted. Any value other than 0 or 1 MUST be treated as a
+/// connection error (Section 5.4.1) of type PROTOCOL_ERROR.
+///
+/// This sends the largest value a setting can have.
+#[test]
+fn sends_settings_enable_push_with_max_value() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.5.2/2", "rfc9113", "6.5.2", "sends_settings_enable_push_with_max_value") {
+    return;
+}
+use __group::sends_settings_enable_push_with_max_value as test;
+$body
+}
+
+/// SETTINGS_ENABLE_PUSH (0x2):
+/// The initial value is 1, which indicates that server push is
+/// permitted. Any value other than 0 or 1 MUST be treated as a
+/// connection error (Section 5.4.1) of type PROTOCOL_ERROR.
+///
+/// This sends both valid values, which must not be treated as errors.
+#[test]
+fn sends_settings_enable_push_with_valid_values() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.5.2/3", "rfc9113", "6.5.2", "sends_settings_enable_push_with_valid_values") {
+    return;
+}
+use __group::sends_settings_enable_push_with_valid_values as test;
+$body
+}
+
 /// SETTINGS_INITIAL_WINDOW_SIZE (0x4):
 /// Values above the maximum flow-control window size of 2^31-1
 /// MUST be treated as a connection error (Section 5.4.1) of
 /// type FLOW_CONTROL_ERROR.
 #[test]
 fn sends_settings_initial_window_size_with_invalid_value() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.5.2/4", "rfc9113", "6.5.2", "sends_settings_initial_window_size_with_invalid_value") {
+    return;
+}
 use __group::sends_settings_initial_window_size_with_invalid_value as test;
 $body
 }
 
+/// SETTINGS_INITIAL_WINDOW_SIZE (0x4):
+/// Values above the maximum flow-control window size of 2^31-1
+/// MUST be treated as a connection error (Section 5.4.1) of
+/// type FLOW_CONTROL_ERROR.
+///
+/// This sends the largest value a setting can have.
+#[test]
+fn sends_settings_initial_window_size_with_max_value() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.5.2/5", "rfc9113", "6.5.2", "sends_settings_initial_window_size_with_max_value") {
+    return;
+}
+use __group::sends_settings_initial_window_size_with_max_value as test;
+$body
+}
+
+/// SETTINGS_INITIAL_WINDOW_SIZE (0x4):
+/// Values above the maximum flow-control window size of 2^31-1
+/// MUST be treated as a connection error (Section 5.4.1) of
+/// type FLOW_CONTROL_ERROR.
+///
+/// This sends exactly 2^31-1, which must not be treated as an error.
+#[test]
+fn sends_settings_initial_window_size_with_max_valid_value() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.5.2/6", "rfc9113", "6.5.2", "sends_settings_initial_window_size_with_max_valid_value") {
+    return;
+}
+use __group::sends_settings_initial_window_size_with_max_valid_value as test;
+$body
+}
+
 /// SETTINGS_MAX_FRAME_SIZE (0x5):
 /// The initial value is 2^14 (16,384) octets. The value advertised
 /// by an endpoint MUST be between this initial value and the
@@ -483,6 +1186,9 @@ $body
 /// connection error (Section 5.4.1) of type PROTOCOL_ERROR.
 #[test]
 fn sends_settings_max_frame_size_with_invalid_value_below_initial() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.5.2/7", "rfc9113", "6.5.2", "sends_settings_max_frame_size_with_invalid_value_below_initial") {
+    return;
+}
 use __group::sends_settings_max_frame_size_with_invalid_value_below_initial as test;
 $body
 }
@@ -495,14 +1201,71 @@ $body
 /// connection error (Section 5.4.1) of type PROTOCOL_ERROR.
 #[test]
 fn sends_settings_max_frame_size_with_invalid_value_above_max() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.5.2/8", "rfc9113", "6.5.2", "sends_settings_max_frame_size_with_invalid_value_above_max") {
+    return;
+}
 use __group::sends_settings_max_frame_size_with_invalid_value_above_max as test;
 $body
 }
 
+/// SETTINGS_MAX_FRAME_SIZE (0x5):
+/// The initial value is 2^14 (16,384) octets. The value advertised
+/// by an endpoint MUST be between this initial value and the
+/// maximum allowed frame size (2^24-1 or 16,777,215 octets),
+/// inclusive. Values outside this range MUST be treated as a
+/// connection error (Section 5.4.1) of type PROTOCOL_ERROR.
+///
+/// This sends zero, the smallest value a setting can have.
+#[test]
+fn sends_settings_max_frame_size_with_zero_value() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.5.2/9", "rfc9113", "6.5.2", "sends_settings_max_frame_size_with_zero_value") {
+    return;
+}
+use __group::sends_settings_max_frame_size_with_zero_value as test;
+$body
+}
+
+/// SETTINGS_MAX_FRAME_SIZE (0x5):
+/// The initial value is 2^14 (16,384) octets. The value advertised
+/// by an endpoint MUST be between this initial value and the
+/// maximum allowed frame size (2^24-1 or 16,777,215 octets),
+/// inclusive. Values outside this range MUST be treated as a
+/// connection error (Section 5.4.1) of type PROTOCOL_ERROR.
+///
+/// This sends the largest value a setting can have.
+#[test]
+fn sends_settings_max_frame_size_with_max_value() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.5.2/10", "rfc9113", "6.5.2", "sends_settings_max_frame_size_with_max_value") {
+    return;
+}
+use __group::sends_settings_max_frame_size_with_max_value as test;
+$body
+}
+
+/// SETTINGS_MAX_FRAME_SIZE (0x5):
+/// The initial value is 2^14 (16,384) octets. The value advertised
+/// by an endpoint MUST be between this initial value and the
+/// maximum allowed frame size (2^24-1 or 16,777,215 octets),
+/// inclusive. Values outside this range MUST be treated as a
+/// connection error (Section 5.4.1) of type PROTOCOL_ERROR.
+///
+/// This sends both ends of the range, which must not be treated as errors.
+#[test]
+fn sends_settings_max_frame_size_with_boundary_values() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.5.2/11", "rfc9113", "6.5.2", "sends_settings_max_frame_size_with_boundary_values") {
+    return;
+}
+use __group::sends_settings_max_frame_size_with_boundary_values as test;
+$body
+}
+
 /// An endpoint that receives a SETTINGS frame with any unknown
 /// or unsupported identifier MUST ignore that setting.
 #[test]
 fn sends_settings_frame_with_unknown_identifier() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.5.2/12", "rfc9113", "6.5.2", "sends_settings_frame_with_unknown_identifier") {
+    return;
+}
 use __group::sends_settings_frame_with_unknown_identifier as test;
 $body
 }
@@ -511,6 +1274,9 @@ $body
 /// they appear, with no other frame processing between values.
 #[test]
 fn sends_multiple_values_of_settings_initial_window_size() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.5.3/1", "rfc9113", "6.5.3", "sends_multiple_values_of_settings_initial_window_size") {
+    return;
+}
 use __group::sends_multiple_values_of_settings_initial_window_size as test;
 $body
 }
@@ -519,15 +1285,39 @@ $body
 /// immediately emit a SETTINGS frame with the ACK flag set.
 #[test]
 fn sends_settings_frame_without_ack_flag() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.5.3/2", "rfc9113", "6.5.3", "sends_settings_frame_without_ack_flag") {
+    return;
+}
 use __group::sends_settings_frame_without_ack_flag as test;
 $body
 }
 
+/// Once all values have been processed, the recipient MUST
+/// immediately emit a SETTINGS frame with the ACK flag set. [...] If the
+/// sender of a SETTINGS frame does not receive an acknowledgment within a
+/// reasonable amount of time, it MAY issue a connection error (Section
+/// 5.4.1) of type SETTINGS_TIMEOUT.
+///
+/// "Reasonable" is up to the client: this one sends the same settings as in
+/// the handshake, which take no work to apply, and gives the server
+/// [crate::Config::settings_ack_timeout] to acknowledge them.
+#[test]
+fn sends_settings_frame_and_expects_timely_ack() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.5.3/3", "rfc9113", "6.5.3", "sends_settings_frame_and_expects_timely_ack") {
+    return;
+}
+use __group::sends_settings_frame_and_expects_timely_ack as test;
+$body
+}
+
 /// Receivers of a PING frame that does not include an ACK flag MUST
 /// send a PING frame with the ACK flag set in response, with an
 /// identical payload.
 #[test]
 fn sends_ping_frame() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.7/1", "rfc9113", "6.7", "sends_ping_frame") {
+    return;
+}
 use __group::sends_ping_frame as test;
 $body
 }
@@ -539,15 +1329,53 @@ $body
 /// flag.
 #[test]
 fn sends_ping_frame_with_ack() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.7/2", "rfc9113", "6.7", "sends_ping_frame_with_ack") {
+    return;
+}
 use __group::sends_ping_frame_with_ack as test;
 $body
 }
 
+/// Receivers of a PING frame that does not include an ACK flag MUST
+/// send a PING frame with the ACK flag set in response, with an
+/// identical payload.
+///
+/// This sends several PING frames whose opaque data is all zeroes, all
+/// ones, or all different: each must come back as-is, in any order.
+#[test]
+fn sends_ping_frames_with_arbitrary_payloads() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.7/3", "rfc9113", "6.7", "sends_ping_frames_with_arbitrary_payloads") {
+    return;
+}
+use __group::sends_ping_frames_with_arbitrary_payloads as test;
+$body
+}
+
+/// ACK (0x1):
+/// When set, bit 0 indicates that this PING frame is a PING
+/// response. An endpoint MUST set this flag in PING responses.
+/// An endpoint MUST NOT respond to PING frames containing this
+/// flag.
+///
+/// This sends a burst of PING frames with the ACK flag set, then one
+/// without it: the only ACK the server sends back must be for the latter.
+#[test]
+fn sends_multiple_ping_frames_with_ack() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.7/4", "rfc9113", "6.7", "sends_multiple_ping_frames_with_ack") {
+    return;
+}
+use __group::sends_multiple_ping_frames_with_ack as test;
+$body
+}
+
 /// If a PING frame is received with a stream identifier field value
 /// other than 0x0, the recipient MUST respond with a connection
 /// error (Section 5.4.1) of type PROTOCOL_ERROR.
 #[test]
 fn sends_ping_frame_with_non_zero_stream_id() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.7/5", "rfc9113", "6.7", "sends_ping_frame_with_non_zero_stream_id") {
+    return;
+}
 use __group::sends_ping_frame_with_non_zero_stream_id as test;
 $body
 }
@@ -557,6 +1385,9 @@ $body
 /// FRAME_SIZE_ERROR.
 #[test]
 fn sends_ping_frame_with_invalid_length() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.7/6", "rfc9113", "6.7", "sends_ping_frame_with_invalid_length") {
+    return;
+}
 use __group::sends_ping_frame_with_invalid_length as test;
 $body
 }
@@ -566,10 +1397,48 @@ $body
 /// PROTOCOL_ERROR.
 #[test]
 fn sends_goaway_frame_with_non_zero_stream_id() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.8/1", "rfc9113", "6.8", "sends_goaway_frame_with_non_zero_stream_id") {
+    return;
+}
 use __group::sends_goaway_frame_with_non_zero_stream_id as test;
 $body
 }
 
+/// The last stream identifier in the GOAWAY frame contains the
+/// highest-numbered stream identifier for which the sender of the
+/// GOAWAY frame might have taken some action on or might yet take
+/// action on.
+///
+/// This opens three streams whose request bodies are still to come, asks
+/// the server to shut down gracefully on a fourth one, then sends the
+/// request bodies: all four streams are at or below the last stream ID of
+/// the GOAWAY frame, so all four should complete.
+#[test]
+fn sends_data_frames_on_streams_in_flight_during_graceful_shutdown() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.8/2", "rfc9113", "6.8", "sends_data_frames_on_streams_in_flight_during_graceful_shutdown") {
+    return;
+}
+use __group::sends_data_frames_on_streams_in_flight_during_graceful_shutdown as test;
+$body
+}
+
+/// Once sent, the sender will ignore frames sent on streams initiated
+/// by the receiver if the stream has an identifier higher than the
+/// included last stream identifier.
+///
+/// This keeps a stream open, asks the server to shut down gracefully on
+/// another one, then opens two more streams after the GOAWAY frame: the
+/// server should either reset them or ignore them, and complete the first
+/// two.
+#[test]
+fn sends_headers_frames_after_graceful_shutdown_goaway() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.8/3", "rfc9113", "6.8", "sends_headers_frames_after_graceful_shutdown_goaway") {
+    return;
+}
+use __group::sends_headers_frames_after_graceful_shutdown_goaway as test;
+$body
+}
+
 /// A receiver MUST treat the receipt of a WINDOW_UPDATE frame with
 /// a flow-control window increment of 0 as a stream error
 /// (Section 5.4.2) of type PROTOCOL_ERROR; errors on the connection
@@ -577,6 +1446,9 @@ $body
 /// (Section 5.4.1).
 #[test]
 fn sends_window_update_frame_with_zero_increment() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.9/1", "rfc9113", "6.9", "sends_window_update_frame_with_zero_increment") {
+    return;
+}
 use __group::sends_window_update_frame_with_zero_increment as test;
 $body
 }
@@ -588,6 +1460,9 @@ $body
 /// (Section 5.4.1).
 #[test]
 fn sends_window_update_frame_with_zero_increment_on_stream() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.9/2", "rfc9113", "6.9", "sends_window_update_frame_with_zero_increment_on_stream") {
+    return;
+}
 use __group::sends_window_update_frame_with_zero_increment_on_stream as test;
 $body
 }
@@ -597,6 +1472,9 @@ $body
 /// FRAME_SIZE_ERROR.
 #[test]
 fn sends_window_update_frame_with_invalid_length() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.9/3", "rfc9113", "6.9", "sends_window_update_frame_with_invalid_length") {
+    return;
+}
 use __group::sends_window_update_frame_with_invalid_length as test;
 $body
 }
@@ -606,6 +1484,9 @@ $body
 /// windows advertised by the receiver.
 #[test]
 fn sends_settings_frame_to_set_initial_window_size_to_1_and_sends_headers_frame() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.9.1/1", "rfc9113", "6.9.1", "sends_settings_frame_to_set_initial_window_size_to_1_and_sends_headers_frame") {
+    return;
+}
 use __group::sends_settings_frame_to_set_initial_window_size_to_1_and_sends_headers_frame as test;
 $body
 }
@@ -619,6 +1500,9 @@ $body
 /// an error code of FLOW_CONTROL_ERROR is sent.
 #[test]
 fn sends_multiple_window_update_frames_increasing_flow_control_window_above_max() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.9.1/2", "rfc9113", "6.9.1", "sends_multiple_window_update_frames_increasing_flow_control_window_above_max") {
+    return;
+}
 use __group::sends_multiple_window_update_frames_increasing_flow_control_window_above_max as test;
 $body
 }
@@ -632,16 +1516,68 @@ $body
 /// an error code of FLOW_CONTROL_ERROR is sent.
 #[test]
 fn sends_multiple_window_update_frames_increasing_flow_control_window_above_max_on_stream() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.9.1/3", "rfc9113", "6.9.1", "sends_multiple_window_update_frames_increasing_flow_control_window_above_max_on_stream") {
+    return;
+}
 use __group::sends_multiple_window_update_frames_increasing_flow_control_window_above_max_on_stream as test;
 $body
 }
 
+/// A sender MUST NOT allow a flow-control window to exceed 2^31-1
+/// octets.
+///
+/// This grows the connection window to exactly 2^31-1 octets, which
+/// is allowed.
+#[test]
+fn sends_window_update_frame_increasing_flow_control_window_to_max() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.9.1/4", "rfc9113", "6.9.1", "sends_window_update_frame_increasing_flow_control_window_to_max") {
+    return;
+}
+use __group::sends_window_update_frame_increasing_flow_control_window_to_max as test;
+$body
+}
+
+/// A sender MUST NOT allow a flow-control window to exceed 2^31-1
+/// octets. If a sender receives a WINDOW_UPDATE that causes a
+/// flow-control window to exceed this maximum, it MUST terminate
+/// either the stream or the connection, as appropriate.
+///
+/// This grows the connection window to 2^31 octets, one past the
+/// maximum, with a single WINDOW_UPDATE frame.
+#[test]
+fn sends_window_update_frame_increasing_flow_control_window_to_max_plus_one() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.9.1/5", "rfc9113", "6.9.1", "sends_window_update_frame_increasing_flow_control_window_to_max_plus_one") {
+    return;
+}
+use __group::sends_window_update_frame_increasing_flow_control_window_to_max_plus_one as test;
+$body
+}
+
+/// A sender MUST NOT allow a flow-control window to exceed 2^31-1
+/// octets. If a sender receives a WINDOW_UPDATE that causes a
+/// flow-control window to exceed this maximum, it MUST terminate
+/// either the stream or the connection, as appropriate.
+///
+/// This grows a stream window to 2^31 octets, one past the maximum,
+/// with a single WINDOW_UPDATE frame.
+#[test]
+fn sends_window_update_frame_increasing_flow_control_window_to_max_plus_one_on_stream() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.9.1/6", "rfc9113", "6.9.1", "sends_window_update_frame_increasing_flow_control_window_to_max_plus_one_on_stream") {
+    return;
+}
+use __group::sends_window_update_frame_increasing_flow_control_window_to_max_plus_one_on_stream as test;
+$body
+}
+
 /// When the value of SETTINGS_INITIAL_WINDOW_SIZE changes,
 /// a receiver MUST adjust the size of all stream flow-control
 /// windows that it maintains by the difference between the new
 /// value and the old value.
 #[test]
 fn changes_settings_initial_window_size_after_sending_headers_frame() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.9.2/1", "rfc9113", "6.9.2", "changes_settings_initial_window_size_after_sending_headers_frame") {
+    return;
+}
 use __group::changes_settings_initial_window_size_after_sending_headers_frame as test;
 $body
 }
@@ -652,6 +1588,9 @@ $body
 /// become positive.
 #[test]
 fn sends_settings_frame_for_window_size_to_be_negative() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.9.2/2", "rfc9113", "6.9.2", "sends_settings_frame_for_window_size_to_be_negative") {
+    return;
+}
 use __group::sends_settings_frame_for_window_size_to_be_negative as test;
 $body
 }
@@ -661,10 +1600,28 @@ $body
 /// as a connection error (Section 5.4.1) of type FLOW_CONTROL_ERROR.
 #[test]
 fn sends_settings_initial_window_size_with_exceeded_max_window_size_value() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.9.2/3", "rfc9113", "6.9.2", "sends_settings_initial_window_size_with_exceeded_max_window_size_value") {
+    return;
+}
 use __group::sends_settings_initial_window_size_with_exceeded_max_window_size_value as test;
 $body
 }
 
+/// An endpoint MUST treat a change to SETTINGS_INITIAL_WINDOW_SIZE
+/// that causes any flow-control window to exceed the maximum size
+/// as a connection error (Section 5.4.1) of type FLOW_CONTROL_ERROR.
+///
+/// This grows a stream window to exactly 2^31-1 octets with a
+/// WINDOW_UPDATE frame, then bumps SETTINGS_INITIAL_WINDOW_SIZE by one.
+#[test]
+fn sends_settings_initial_window_size_making_stream_window_exceed_max() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.9.2/4", "rfc9113", "6.9.2", "sends_settings_initial_window_size_making_stream_window_exceed_max") {
+    return;
+}
+use __group::sends_settings_initial_window_size_making_stream_window_exceed_max as test;
+$body
+}
+
 /// The CONTINUATION frame (type=0x9) is used to continue a sequence
 /// of header block fragments (Section 4.3). Any number of
 /// CONTINUATION frames can be sent, as long as the preceding frame
@@ -672,6 +1629,9 @@ $body
 /// or CONTINUATION frame without the END_HEADERS flag set.
 #[test]
 fn sends_multiple_continuation_frames_preceded_by_headers_frame() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.10/1", "rfc9113", "6.10", "sends_multiple_continuation_frames_preceded_by_headers_frame") {
+    return;
+}
 use __group::sends_multiple_continuation_frames_preceded_by_headers_frame as test;
 $body
 }
@@ -683,6 +1643,9 @@ $body
 /// a connection error (Section 5.4.1) of type PROTOCOL_ERROR.
 #[test]
 fn sends_continuation_frame_followed_by_non_continuation_frame() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.10/2", "rfc9113", "6.10", "sends_continuation_frame_followed_by_non_continuation_frame") {
+    return;
+}
 use __group::sends_continuation_frame_followed_by_non_continuation_frame as test;
 $body
 }
@@ -693,6 +1656,9 @@ $body
 /// (Section 5.4.1) of type PROTOCOL_ERROR.
 #[test]
 fn sends_continuation_frame_with_zero_stream_id() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.10/3", "rfc9113", "6.10", "sends_continuation_frame_with_zero_stream_id") {
+    return;
+}
 use __group::sends_continuation_frame_with_zero_stream_id as test;
 $body
 }
@@ -703,6 +1669,9 @@ $body
 /// with a connection error (Section 5.4.1) of type PROTOCOL_ERROR.
 #[test]
 fn sends_continuation_frame_preceded_by_headers_frame_with_end_headers_flag() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.10/4", "rfc9113", "6.10", "sends_continuation_frame_preceded_by_headers_frame_with_end_headers_flag") {
+    return;
+}
 use __group::sends_continuation_frame_preceded_by_headers_frame_with_end_headers_flag as test;
 $body
 }
@@ -713,6 +1682,9 @@ $body
 /// with a connection error (Section 5.4.1) of type PROTOCOL_ERROR.
 #[test]
 fn sends_continuation_frame_preceded_by_continuation_frame_with_end_headers_flag() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.10/5", "rfc9113", "6.10", "sends_continuation_frame_preceded_by_continuation_frame_with_end_headers_flag") {
+    return;
+}
 use __group::sends_continuation_frame_preceded_by_continuation_frame_with_end_headers_flag as test;
 $body
 }
@@ -723,6 +1695,9 @@ $body
 /// with a connection error (Section 5.4.1) of type PROTOCOL_ERROR.
 #[test]
 fn sends_continuation_frame_preceded_by_data_frame() {
+if !::httpwg::filter::Filter::from_env().matches("http2/6.10/6", "rfc9113", "6.10", "sends_continuation_frame_preceded_by_data_frame") {
+    return;
+}
 use __group::sends_continuation_frame_preceded_by_data_frame as test;
 $body
 }
@@ -737,6 +1712,9 @@ use super::__suite::_7_error_codes as __group;
 /// equivalent to INTERNAL_ERROR.
 #[test]
 fn sends_goaway_frame_with_unknown_error_code() {
+if !::httpwg::filter::Filter::from_env().matches("http2/7/1", "rfc9113", "7", "sends_goaway_frame_with_unknown_error_code") {
+    return;
+}
 use __group::sends_goaway_frame_with_unknown_error_code as test;
 $body
 }
@@ -746,6 +1724,9 @@ $body
 /// equivalent to INTERNAL_ERROR.
 #[test]
 fn sends_rst_stream_frame_with_unknown_error_code() {
+if !::httpwg::filter::Filter::from_env().matches("http2/7/2", "rfc9113", "7", "sends_rst_stream_frame_with_unknown_error_code") {
+    return;
+}
 use __group::sends_rst_stream_frame_with_unknown_error_code as test;
 $body
 }
@@ -757,22 +1738,85 @@ use super::__suite::_8_expressing_http_semantics_in_http2 as __group;
 
 #[test]
 fn sends_second_headers_frame_without_end_stream() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.1/1", "rfc9113", "8.1", "sends_second_headers_frame_without_end_stream") {
+    return;
+}
 use __group::sends_second_headers_frame_without_end_stream as test;
 $body
 }
 
+/// An HTTP message (request or response) consists of:
+///
+/// 1. one HEADERS frame (followed by zero or more CONTINUATION frames)
+///    containing the header section (see Section 6.3 of [HTTP]),
+/// 2. zero or more DATA frames containing the message content (see Section
+///    6.4 of [HTTP]), and
+/// 3. optionally, one HEADERS frame (followed by zero or more CONTINUATION
+///    frames) containing the trailer section, if present (see Section 6.5 of
+///    [HTTP]).
+///
+/// This sends a request with all three: its content spans several DATA
+/// frames, as far as the server's flow-control window allows, and the
+/// server should answer it.
+#[test]
+fn sends_request_with_content_and_trailers() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.1/2", "rfc9113", "8.1", "sends_request_with_content_and_trailers") {
+    return;
+}
+use __group::sends_request_with_content_and_trailers as test;
+$body
+}
+
+/// An HTTP message (request or response) consists of: [...] 2. zero or
+/// more DATA frames containing the message content [...] and 3.
+/// optionally, one HEADERS frame (followed by zero or more CONTINUATION
+/// frames) containing the trailer section.
+///
+/// This sends a request with no content at all, just a header section and
+/// a trailer section with a few fields, and the server should answer it.
+#[test]
+fn sends_request_with_trailers_and_no_content() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.1/3", "rfc9113", "8.1", "sends_request_with_trailers_and_no_content") {
+    return;
+}
+use __group::sends_request_with_trailers_and_no_content as test;
+$body
+}
+
 #[test]
 fn sends_headers_frame_with_incorrect_content_length_single_data_frame() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.1/4", "rfc9113", "8.1", "sends_headers_frame_with_incorrect_content_length_single_data_frame") {
+    return;
+}
 use __group::sends_headers_frame_with_incorrect_content_length_single_data_frame as test;
 $body
 }
 
 #[test]
 fn sends_headers_frame_with_incorrect_content_length_multiple_data_frames() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.1/5", "rfc9113", "8.1", "sends_headers_frame_with_incorrect_content_length_multiple_data_frames") {
+    return;
+}
 use __group::sends_headers_frame_with_incorrect_content_length_multiple_data_frames as test;
 $body
 }
 
+/// A request or response is also malformed if the value of a
+/// content-length header field does not equal the sum of the DATA frame
+/// payload lengths that form the content, unless the message is defined as
+/// having no content.
+///
+/// This checks the server's own response to a GET request, if it has a
+/// content-length header.
+#[test]
+fn response_content_length_matches_content() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.1/6", "rfc9113", "8.1", "response_content_length_matches_content") {
+    return;
+}
+use __group::response_content_length_matches_content as test;
+$body
+}
+
 /// A field name MUST NOT contain characters in the ranges 0x00-0x20, 0x41-0x5a,
 /// or 0x7f-0xff (all ranges inclusive). This specifically excludes all
 /// non-visible ASCII characters, ASCII SP (0x20), and uppercase characters ('A'
@@ -784,6 +1828,9 @@ $body
 /// cannot be sent (e.g., because the error occurs in a trailer field).
 #[test]
 fn sends_headers_frame_with_uppercase_field_name() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.1/7", "rfc9113", "8.1", "sends_headers_frame_with_uppercase_field_name") {
+    return;
+}
 use __group::sends_headers_frame_with_uppercase_field_name as test;
 $body
 }
@@ -799,6 +1846,9 @@ $body
 /// cannot be sent (e.g., because the error occurs in a trailer field).
 #[test]
 fn sends_headers_frame_with_space_in_field_name() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.1/8", "rfc9113", "8.1", "sends_headers_frame_with_space_in_field_name") {
+    return;
+}
 use __group::sends_headers_frame_with_space_in_field_name as test;
 $body
 }
@@ -814,6 +1864,9 @@ $body
 /// cannot be sent (e.g., because the error occurs in a trailer field).
 #[test]
 fn sends_headers_frame_with_non_visible_ascii() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.1/9", "rfc9113", "8.1", "sends_headers_frame_with_non_visible_ascii") {
+    return;
+}
 use __group::sends_headers_frame_with_non_visible_ascii as test;
 $body
 }
@@ -829,6 +1882,9 @@ $body
 /// cannot be sent (e.g., because the error occurs in a trailer field).
 #[test]
 fn sends_headers_frame_with_del_character() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.1/10", "rfc9113", "8.1", "sends_headers_frame_with_del_character") {
+    return;
+}
 use __group::sends_headers_frame_with_del_character as test;
 $body
 }
@@ -844,6 +1900,9 @@ $body
 /// cannot be sent (e.g., because the error occurs in a trailer field).
 #[test]
 fn sends_headers_frame_with_non_ascii_character() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.1/11", "rfc9113", "8.1", "sends_headers_frame_with_non_ascii_character") {
+    return;
+}
 use __group::sends_headers_frame_with_non_ascii_character as test;
 $body
 }
@@ -858,6 +1917,9 @@ $body
 /// cannot be sent (e.g., because the error occurs in a trailer field).
 #[test]
 fn sends_headers_frame_with_colon_in_field_name() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.1/12", "rfc9113", "8.1", "sends_headers_frame_with_colon_in_field_name") {
+    return;
+}
 use __group::sends_headers_frame_with_colon_in_field_name as test;
 $body
 }
@@ -871,6 +1933,9 @@ $body
 /// cannot be sent (e.g., because the error occurs in a trailer field).
 #[test]
 fn sends_headers_frame_with_lf_in_field_value() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.1/13", "rfc9113", "8.1", "sends_headers_frame_with_lf_in_field_value") {
+    return;
+}
 use __group::sends_headers_frame_with_lf_in_field_value as test;
 $body
 }
@@ -884,6 +1949,9 @@ $body
 /// cannot be sent (e.g., because the error occurs in a trailer field).
 #[test]
 fn sends_headers_frame_with_cr_in_field_value() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.1/14", "rfc9113", "8.1", "sends_headers_frame_with_cr_in_field_value") {
+    return;
+}
 use __group::sends_headers_frame_with_cr_in_field_value as test;
 $body
 }
@@ -897,6 +1965,9 @@ $body
 /// cannot be sent (e.g., because the error occurs in a trailer field).
 #[test]
 fn sends_headers_frame_with_nul_in_field_value() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.1/15", "rfc9113", "8.1", "sends_headers_frame_with_nul_in_field_value") {
+    return;
+}
 use __group::sends_headers_frame_with_nul_in_field_value as test;
 $body
 }
@@ -909,6 +1980,9 @@ $body
 /// cannot be sent (e.g., because the error occurs in a trailer field).
 #[test]
 fn sends_headers_frame_with_leading_space_in_field_value() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.1/16", "rfc9113", "8.1", "sends_headers_frame_with_leading_space_in_field_value") {
+    return;
+}
 use __group::sends_headers_frame_with_leading_space_in_field_value as test;
 $body
 }
@@ -921,6 +1995,9 @@ $body
 /// cannot be sent (e.g., because the error occurs in a trailer field).
 #[test]
 fn sends_headers_frame_with_trailing_tab_in_field_value() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.1/17", "rfc9113", "8.1", "sends_headers_frame_with_trailing_tab_in_field_value") {
+    return;
+}
 use __group::sends_headers_frame_with_trailing_tab_in_field_value as test;
 $body
 }
@@ -936,6 +2013,9 @@ $body
 /// (Section 8.1.1).
 #[test]
 fn sends_headers_frame_with_connection_header() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.2.2/1", "rfc9113", "8.2.2", "sends_headers_frame_with_connection_header") {
+    return;
+}
 use __group::sends_headers_frame_with_connection_header as test;
 $body
 }
@@ -952,6 +2032,9 @@ $body
 /// (Section 8.1.1).
 #[test]
 fn sends_headers_frame_with_proxy_connection_header() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.2.2/2", "rfc9113", "8.2.2", "sends_headers_frame_with_proxy_connection_header") {
+    return;
+}
 use __group::sends_headers_frame_with_proxy_connection_header as test;
 $body
 }
@@ -968,6 +2051,9 @@ $body
 /// (Section 8.1.1).
 #[test]
 fn sends_headers_frame_with_keep_alive_header() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.2.2/3", "rfc9113", "8.2.2", "sends_headers_frame_with_keep_alive_header") {
+    return;
+}
 use __group::sends_headers_frame_with_keep_alive_header as test;
 $body
 }
@@ -984,6 +2070,9 @@ $body
 /// (Section 8.1.1).
 #[test]
 fn sends_headers_frame_with_transfer_encoding_header() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.2.2/4", "rfc9113", "8.2.2", "sends_headers_frame_with_transfer_encoding_header") {
+    return;
+}
 use __group::sends_headers_frame_with_transfer_encoding_header as test;
 $body
 }
@@ -1000,6 +2089,9 @@ $body
 /// (Section 8.1.1).
 #[test]
 fn sends_headers_frame_with_upgrade_header() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.2.2/5", "rfc9113", "8.2.2", "sends_headers_frame_with_upgrade_header") {
+    return;
+}
 use __group::sends_headers_frame_with_upgrade_header as test;
 $body
 }
@@ -1009,6 +2101,9 @@ $body
 /// "trailers".
 #[test]
 fn sends_headers_frame_with_te_trailers() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.2.2/6", "rfc9113", "8.2.2", "sends_headers_frame_with_te_trailers") {
+    return;
+}
 use __group::sends_headers_frame_with_te_trailers as test;
 $body
 }
@@ -1018,6 +2113,9 @@ $body
 /// "trailers".
 #[test]
 fn sends_headers_frame_with_te_not_trailers() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.2.2/7", "rfc9113", "8.2.2", "sends_headers_frame_with_te_not_trailers") {
+    return;
+}
 use __group::sends_headers_frame_with_te_not_trailers as test;
 $body
 }
@@ -1027,6 +2125,9 @@ $body
 /// invalid pseudo-header fields as malformed (Section 8.1.1).
 #[test]
 fn sends_headers_frame_with_response_pseudo_header() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.3/1", "rfc9113", "8.3", "sends_headers_frame_with_response_pseudo_header") {
+    return;
+}
 use __group::sends_headers_frame_with_response_pseudo_header as test;
 $body
 }
@@ -1036,16 +2137,37 @@ $body
 /// pseudo-header fields as malformed (Section 8.1.1).
 #[test]
 fn sends_headers_frame_with_pseudo_header_in_trailer() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.3/2", "rfc9113", "8.3", "sends_headers_frame_with_pseudo_header_in_trailer") {
+    return;
+}
 use __group::sends_headers_frame_with_pseudo_header_in_trailer as test;
 $body
 }
 
+/// [...] Pseudo-header fields MUST NOT appear in a trailer section. Endpoints
+/// MUST treat a request or response that contains undefined or invalid
+/// pseudo-header fields as malformed (Section 8.1.1).
+///
+/// This one's trailer section has a valid field, then a `:status`
+/// pseudo-header, which has no business in a request either.
+#[test]
+fn sends_trailers_with_pseudo_header_after_regular_field() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.3/3", "rfc9113", "8.3", "sends_trailers_with_pseudo_header_after_regular_field") {
+    return;
+}
+use __group::sends_trailers_with_pseudo_header_after_regular_field as test;
+$body
+}
+
 /// The same pseudo-header field name MUST NOT appear more than once in a field
 /// block. A field block for an HTTP request or response that contains a
 /// repeated pseudo-header field name MUST be treated as malformed (Section
 /// 8.1.1).
 #[test]
 fn sends_headers_frame_with_duplicate_pseudo_headers() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.3/4", "rfc9113", "8.3", "sends_headers_frame_with_duplicate_pseudo_headers") {
+    return;
+}
 use __group::sends_headers_frame_with_duplicate_pseudo_headers as test;
 $body
 }
@@ -1060,6 +2182,9 @@ $body
 /// cf. <https://www.rfc-editor.org/rfc/rfc3986.html#section-6.2.3>
 #[test]
 fn sends_headers_frame_with_mismatched_host_authority() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.3/5", "rfc9113", "8.3", "sends_headers_frame_with_mismatched_host_authority") {
+    return;
+}
 use __group::sends_headers_frame_with_mismatched_host_authority as test;
 $body
 }
@@ -1074,6 +2199,9 @@ $body
 /// ":path" pseudo-header field is omitted.
 #[test]
 fn sends_headers_frame_with_empty_path_component() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.3/6", "rfc9113", "8.3", "sends_headers_frame_with_empty_path_component") {
+    return;
+}
 use __group::sends_headers_frame_with_empty_path_component as test;
 $body
 }
@@ -1084,24 +2212,36 @@ $body
 /// fields is malformed (Section 8.1.1).
 #[test]
 fn sends_headers_frame_without_method() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.3/7", "rfc9113", "8.3", "sends_headers_frame_without_method") {
+    return;
+}
 use __group::sends_headers_frame_without_method as test;
 $body
 }
 
 #[test]
 fn sends_headers_frame_without_scheme() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.3/8", "rfc9113", "8.3", "sends_headers_frame_without_scheme") {
+    return;
+}
 use __group::sends_headers_frame_without_scheme as test;
 $body
 }
 
 #[test]
 fn sends_headers_frame_without_path() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.3/9", "rfc9113", "8.3", "sends_headers_frame_without_path") {
+    return;
+}
 use __group::sends_headers_frame_without_path as test;
 $body
 }
 
 #[test]
 fn sends_headers_frame_without_status() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.3.2/1", "rfc9113", "8.3.2", "sends_headers_frame_without_status") {
+    return;
+}
 use __group::sends_headers_frame_without_status as test;
 $body
 }
@@ -1112,6 +2252,9 @@ $body
 /// Section 6.5.2).
 #[test]
 fn client_sends_push_promise_frame() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.3.2/2", "rfc9113", "8.3.2", "client_sends_push_promise_frame") {
+    return;
+}
 use __group::client_sends_push_promise_frame as test;
 $body
 }
@@ -1133,18 +2276,27 @@ $body
 /// requests; see Section 3.2.3 of [HTTP/1.1]).
 #[test]
 fn sends_connect_with_scheme() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.5/1", "rfc9113", "8.5", "sends_connect_with_scheme") {
+    return;
+}
 use __group::sends_connect_with_scheme as test;
 $body
 }
 
 #[test]
 fn sends_connect_with_path() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.5/2", "rfc9113", "8.5", "sends_connect_with_path") {
+    return;
+}
 use __group::sends_connect_with_path as test;
 $body
 }
 
 #[test]
 fn sends_connect_without_authority() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.5/3", "rfc9113", "8.5", "sends_connect_without_authority") {
+    return;
+}
 use __group::sends_connect_without_authority as test;
 $body
 }
@@ -1153,500 +2305,553 @@ $body
 /// field lines (RFC 9113, section 8.3)
 #[test]
 fn sends_headers_frame_with_pseudo_headers_after_regular_headers() {
+if !::httpwg::filter::Filter::from_env().matches("http2/8.5/4", "rfc9113", "8.5", "sends_headers_frame_with_pseudo_headers_after_regular_headers") {
+    return;
+}
 use __group::sends_headers_frame_with_pseudo_headers_after_regular_headers as test;
 $body
 }
 }
+
+/// Section 9: HTTP/2 Connections
+mod _9_http2_connections {
+use super::__suite::_9_http2_connections as __group;
+
+/// HTTP/2 connections are persistent. For best performance, it is
+/// expected that clients will not close connections until it is
+/// determined that no further communication with a server is necessary
+/// [...]
+///
+/// Servers are encouraged to maintain open connections for as long as
+/// possible but are permitted to terminate idle connections if
+/// necessary. When either endpoint chooses to close the transport-layer
+/// TCP connection, the terminating endpoint SHOULD first send a GOAWAY
+/// (Section 6.8) frame so that both endpoints can reliably determine
+/// whether previously sent frames have been processed and gracefully
+/// complete or terminate any necessary remaining tasks.
+///
+/// This keeps a connection open for [crate::Config::soak_duration], sending
+/// nothing but PINGs now and then, and answering the server's own PINGs.
+/// Round-trip times get logged. The server may close the connection, but
+/// only after sending a GOAWAY.
+#[test]
+fn sends_ping_frames_on_idle_connection() {
+if !::httpwg::filter::Filter::from_env().matches("http2/9.1/1", "rfc9113", "9.1", "sends_ping_frames_on_idle_connection") {
+    return;
+}
+use __group::sends_ping_frames_on_idle_connection as test;
+$body
+}
+
+/// Servers are encouraged to maintain open connections for as long as
+/// possible but are permitted to terminate idle connections if
+/// necessary. When either endpoint chooses to close the transport-layer
+/// TCP connection, the terminating endpoint SHOULD first send a GOAWAY
+/// (Section 6.8) frame so that both endpoints can reliably determine
+/// whether previously sent frames have been processed and gracefully
+/// complete or terminate any necessary remaining tasks.
+///
+/// This sends a request, then half-closes the TCP connection without a
+/// GOAWAY: the request is complete, so the server should still send the
+/// whole response before it closes its own half.
+#[test]
+fn sends_request_then_half_closes_connection() {
+if !::httpwg::filter::Filter::from_env().matches("http2/9.1/2", "rfc9113", "9.1", "sends_request_then_half_closes_connection") {
+    return;
+}
+use __group::sends_request_then_half_closes_connection as test;
+$body
+}
+}
+}
+
+/// RFC 9218 describes a scheme that allows an HTTP client to communicate
+/// its preferences for how the upstream server prioritizes responses to its
+/// requests, and also allows a server to hint to a downstream intermediary
+/// how its responses should be prioritized when they are forwarded.
+///
+/// It defines the Priority header field and, for HTTP/2, the
+/// PRIORITY_UPDATE frame. Servers are free to apply these signals or not,
+/// but none of them may get a request or a connection rejected.
+///
+/// cf. <https://httpwg.org/specs/rfc9218.html>
+#[cfg(test)]
+mod rfc9218 {
+use ::httpwg::rfc9218 as __suite;
+
+/// Section 4: Priority Parameters
+mod _4_priority_parameters {
+use super::__suite::_4_priority_parameters as __group;
+
+/// Unknown parameters, parameters with out-of-range values, or values
+/// of unexpected types MUST be ignored.
+///
+/// This sends a parameter nobody knows about, next to valid ones.
+#[test]
+fn sends_priority_header_with_unknown_parameters() {
+if !::httpwg::filter::Filter::from_env().matches("priority/4/1", "rfc9218", "4", "sends_priority_header_with_unknown_parameters") {
+    return;
+}
+use __group::sends_priority_header_with_unknown_parameters as test;
+$body
+}
+
+/// Unknown parameters, parameters with out-of-range values, or values
+/// of unexpected types MUST be ignored.
+///
+/// The urgency goes from 0 to 7 inclusive: this sends 8, then -1.
+#[test]
+fn sends_priority_header_with_out_of_range_urgency() {
+if !::httpwg::filter::Filter::from_env().matches("priority/4/2", "rfc9218", "4", "sends_priority_header_with_out_of_range_urgency") {
+    return;
+}
+use __group::sends_priority_header_with_out_of_range_urgency as test;
+$body
+}
+
+/// Unknown parameters, parameters with out-of-range values, or values
+/// of unexpected types MUST be ignored.
+///
+/// The urgency is an Integer and incremental is a Boolean: this sends a
+/// Token and an Integer instead.
+#[test]
+fn sends_priority_header_with_unexpected_types() {
+if !::httpwg::filter::Filter::from_env().matches("priority/4/3", "rfc9218", "4", "sends_priority_header_with_unexpected_types") {
+    return;
+}
+use __group::sends_priority_header_with_unexpected_types as test;
+$body
+}
+}
+
+/// Section 5: The Priority HTTP Header Field
+mod _5_the_priority_http_header_field {
+use super::__suite::_5_the_priority_http_header_field as __group;
+
+/// The Priority HTTP header field is a Dictionary that carries priority
+/// parameters (see Section 4). It can appear in requests and responses.
+#[test]
+fn sends_priority_header() {
+if !::httpwg::filter::Filter::from_env().matches("priority/5/1", "rfc9218", "5", "sends_priority_header") {
+    return;
+}
+use __group::sends_priority_header as test;
+$body
+}
+
+/// When receiving an HTTP request that does not carry these priority
+/// parameters, a server SHOULD act as if their default values were
+/// specified.
+///
+/// A Priority header field that can't be parsed as a Dictionary carries
+/// no parameters: the request must go through all the same.
+#[test]
+fn sends_unparseable_priority_header() {
+if !::httpwg::filter::Filter::from_env().matches("priority/5/2", "rfc9218", "5", "sends_unparseable_priority_header") {
+    return;
+}
+use __group::sends_unparseable_priority_header as test;
+$body
+}
+}
+
+/// Section 7: The PRIORITY_UPDATE Frame
+mod _7_the_priority_update_frame {
+use super::__suite::_7_the_priority_update_frame as __group;
+
+/// The HTTP/2 PRIORITY_UPDATE frame (type=0x10) is used by clients to
+/// signal the initial priority of a response, or to reprioritize a
+/// response or push stream.
+///
+/// This reprioritizes a request whose body is still being sent.
+#[test]
+fn sends_priority_update_frame_for_open_stream() {
+if !::httpwg::filter::Filter::from_env().matches("priority/7.1/1", "rfc9218", "7.1", "sends_priority_update_frame_for_open_stream") {
+    return;
+}
+use __group::sends_priority_update_frame_for_open_stream as test;
+$body
+}
+
+/// When the PRIORITY_UPDATE frame applies to a request stream, clients
+/// SHOULD provide a prioritized stream ID that refers to a stream in
+/// the "open", "half-closed (local)", or "idle" state.
+///
+/// This sends the initial priority of a request before the request
+/// itself, while its stream is still idle.
+#[test]
+fn sends_priority_update_frame_for_idle_stream() {
+if !::httpwg::filter::Filter::from_env().matches("priority/7.1/2", "rfc9218", "7.1", "sends_priority_update_frame_for_idle_stream") {
+    return;
+}
+use __group::sends_priority_update_frame_for_idle_stream as test;
+$body
+}
+
+/// When the PRIORITY_UPDATE frame applies to a request stream, clients
+/// SHOULD provide a prioritized stream ID that refers to a stream in
+/// the "open", "half-closed (local)", or "idle" state.
+///
+/// This prioritizes a stream that never gets opened, which must not get
+/// in the way of other requests.
+#[test]
+fn sends_priority_update_frame_for_nonexistent_stream() {
+if !::httpwg::filter::Filter::from_env().matches("priority/7.1/3", "rfc9218", "7.1", "sends_priority_update_frame_for_nonexistent_stream") {
+    return;
+}
+use __group::sends_priority_update_frame_for_nonexistent_stream as test;
+$body
+}
+
+/// Servers can discard frames where the Prioritized Stream ID refers to
+/// a stream in the "half-closed (local)" or "closed" state.
+///
+/// This reprioritizes a request that's already been responded to.
+#[test]
+fn sends_priority_update_frame_for_closed_stream() {
+if !::httpwg::filter::Filter::from_env().matches("priority/7.1/4", "rfc9218", "7.1", "sends_priority_update_frame_for_closed_stream") {
+    return;
+}
+use __group::sends_priority_update_frame_for_closed_stream as test;
+$body
+}
+
+/// The Priority Field Value is the priority update value in ASCII text,
+/// encoded using Structured Fields. This is the same representation as
+/// the Priority header field value.
+///
+/// A value that can't be parsed carries no parameters, and must be
+/// ignored like a Priority header field that can't be parsed.
+#[test]
+fn sends_priority_update_frame_with_unparseable_value() {
+if !::httpwg::filter::Filter::from_env().matches("priority/7.1/5", "rfc9218", "7.1", "sends_priority_update_frame_with_unparseable_value") {
+    return;
+}
+use __group::sends_priority_update_frame_with_unparseable_value as test;
+$body
+}
+}
 }
 }
 }
 
 /// This generates a function that returns a Catalog of type
+/// `HashMap<rfc, HashMap<group, HashMap<test name, Test<IO>>>>`, out of
+/// `httpwg::all_tests`
 #[macro_export]
 macro_rules! gen_catalog {
-  ($catalog_fn_name:ident) => {
-    use ::httpwg::BoxedTest;
-    pub fn $catalog_fn_name<IO: IntoHalves>() -> HashMap<&'static str, HashMap<&'static str, HashMap<&'static str, BoxedTest<IO>>>> {
-        let mut rfcs: HashMap<&'static str, HashMap<&'static str, HashMap<&'static str, BoxedTest<IO>>>> = Default::default();
-
-        {
-            let mut sections: HashMap<&'static str, _> = Default::default();
-
-            {
-                use ::httpwg::rfc9113::_3_starting_http2 as s;
-                let mut _3_starting_http2: HashMap<&'static str, BoxedTest<IO>> = Default::default();
-
-                _3_starting_http2.insert(
-                    "sends client connection preface",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_client_connection_preface(conn))),
-                );
-                _3_starting_http2.insert(
-                    "sends invalid connection preface",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_invalid_connection_preface(conn))),
-                );
-
-                sections.insert("3. starting http2", _3_starting_http2);
+    ($catalog_fn_name:ident) => {
+        use ::httpwg::Test;
+        pub fn $catalog_fn_name<IO: IntoHalves>(
+        ) -> HashMap<&'static str, HashMap<&'static str, HashMap<&'static str, Test<IO>>>> {
+            let mut rfcs: HashMap<
+                &'static str,
+                HashMap<&'static str, HashMap<&'static str, Test<IO>>>,
+            > = Default::default();
+            for suite in ::httpwg::all_tests::<IO>() {
+                let sections = rfcs.entry(suite.rfc).or_default();
+                for group in suite.groups {
+                    let tests = sections.entry(group.title).or_default();
+                    for test in group.tests {
+                        tests.insert(test.name, test);
+                    }
+                }
             }
-            {
-                use ::httpwg::rfc9113::_4_http_frames as s;
-                let mut _4_http_frames: HashMap<&'static str, BoxedTest<IO>> = Default::default();
-
-                _4_http_frames.insert(
-                    "sends frame with unknown type",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_frame_with_unknown_type(conn))),
-                );
-                _4_http_frames.insert(
-                    "sends frame with unused flags",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_frame_with_unused_flags(conn))),
-                );
-                _4_http_frames.insert(
-                    "sends frame with reserved bit set",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_frame_with_reserved_bit_set(conn))),
-                );
-                _4_http_frames.insert(
-                    "data frame with max length",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::data_frame_with_max_length(conn))),
-                );
-                _4_http_frames.insert(
-                    "frame exceeding max size",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::frame_exceeding_max_size(conn))),
-                );
-                _4_http_frames.insert(
-                    "large headers frame exceeding max size",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::large_headers_frame_exceeding_max_size(conn))),
-                );
-                _4_http_frames.insert(
-                    "invalid header block fragment",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::invalid_header_block_fragment(conn))),
-                );
-                _4_http_frames.insert(
-                    "priority frame while sending headers",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::priority_frame_while_sending_headers(conn))),
-                );
-                _4_http_frames.insert(
-                    "headers frame to another stream",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::headers_frame_to_another_stream(conn))),
-                );
-
-                sections.insert("4. http frames", _4_http_frames);
-            }
-            {
-                use ::httpwg::rfc9113::_5_streams_and_multiplexing as s;
-                let mut _5_streams_and_multiplexing: HashMap<&'static str, BoxedTest<IO>> = Default::default();
-
-                _5_streams_and_multiplexing.insert(
-                    "idle sends data frame",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::idle_sends_data_frame(conn))),
-                );
-                _5_streams_and_multiplexing.insert(
-                    "idle sends rst stream frame",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::idle_sends_rst_stream_frame(conn))),
-                );
-                _5_streams_and_multiplexing.insert(
-                    "idle sends window update frame",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::idle_sends_window_update_frame(conn))),
-                );
-                _5_streams_and_multiplexing.insert(
-                    "idle sends continuation frame",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::idle_sends_continuation_frame(conn))),
-                );
-                _5_streams_and_multiplexing.insert(
-                    "half closed remote sends data frame",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::half_closed_remote_sends_data_frame(conn))),
-                );
-                _5_streams_and_multiplexing.insert(
-                    "half closed remote sends headers frame",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::half_closed_remote_sends_headers_frame(conn))),
-                );
-                _5_streams_and_multiplexing.insert(
-                    "half closed remote sends continuation frame",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::half_closed_remote_sends_continuation_frame(conn))),
-                );
-                _5_streams_and_multiplexing.insert(
-                    "closed sends data frame after rst stream",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::closed_sends_data_frame_after_rst_stream(conn))),
-                );
-                _5_streams_and_multiplexing.insert(
-                    "closed sends headers frame after rst stream",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::closed_sends_headers_frame_after_rst_stream(conn))),
-                );
-                _5_streams_and_multiplexing.insert(
-                    "closed sends continuation frame after rst stream",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::closed_sends_continuation_frame_after_rst_stream(conn))),
-                );
-                _5_streams_and_multiplexing.insert(
-                    "closed sends data frame",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::closed_sends_data_frame(conn))),
-                );
-                _5_streams_and_multiplexing.insert(
-                    "closed sends headers frame",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::closed_sends_headers_frame(conn))),
-                );
-                _5_streams_and_multiplexing.insert(
-                    "closed sends continuation frame",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::closed_sends_continuation_frame(conn))),
-                );
-                _5_streams_and_multiplexing.insert(
-                    "sends even numbered stream identifier",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_even_numbered_stream_identifier(conn))),
-                );
-                _5_streams_and_multiplexing.insert(
-                    "sends smaller stream identifier",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_smaller_stream_identifier(conn))),
-                );
-                _5_streams_and_multiplexing.insert(
-                    "exceeds concurrent stream limit",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::exceeds_concurrent_stream_limit(conn))),
-                );
-                _5_streams_and_multiplexing.insert(
-                    "invalid ping frame for connection close",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::invalid_ping_frame_for_connection_close(conn))),
-                );
-                _5_streams_and_multiplexing.insert(
-                    "test invalid ping frame for goaway",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::test_invalid_ping_frame_for_goaway(conn))),
-                );
-                _5_streams_and_multiplexing.insert(
-                    "unknown extension frame in header block",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::unknown_extension_frame_in_header_block(conn))),
-                );
-
-                sections.insert("5. streams and multiplexing", _5_streams_and_multiplexing);
-            }
-            {
-                use ::httpwg::rfc9113::_6_frame_definitions as s;
-                let mut _6_frame_definitions: HashMap<&'static str, BoxedTest<IO>> = Default::default();
-
-                _6_frame_definitions.insert(
-                    "sends data frame with zero stream id",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_data_frame_with_zero_stream_id(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends data frame on invalid stream state",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_data_frame_on_invalid_stream_state(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends data frame with invalid pad length",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_data_frame_with_invalid_pad_length(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends headers frame with zero stream id",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_zero_stream_id(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends headers frame with invalid pad length",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_invalid_pad_length(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends priority frame with zero stream id",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_priority_frame_with_zero_stream_id(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends priority frame with invalid length",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_priority_frame_with_invalid_length(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends rst stream frame with zero stream id",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_rst_stream_frame_with_zero_stream_id(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends rst stream frame on idle stream",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_rst_stream_frame_on_idle_stream(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends rst stream frame with invalid length",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_rst_stream_frame_with_invalid_length(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends settings frame with ack and payload",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_settings_frame_with_ack_and_payload(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends settings frame with non zero stream id",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_settings_frame_with_non_zero_stream_id(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends settings frame with invalid length",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_settings_frame_with_invalid_length(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends settings enable push with invalid value",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_settings_enable_push_with_invalid_value(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends settings initial window size with invalid value",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_settings_initial_window_size_with_invalid_value(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends settings max frame size with invalid value below initial",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_settings_max_frame_size_with_invalid_value_below_initial(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends settings max frame size with invalid value above max",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_settings_max_frame_size_with_invalid_value_above_max(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends settings frame with unknown identifier",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_settings_frame_with_unknown_identifier(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends multiple values of settings initial window size",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_multiple_values_of_settings_initial_window_size(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends settings frame without ack flag",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_settings_frame_without_ack_flag(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends ping frame",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_ping_frame(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends ping frame with ack",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_ping_frame_with_ack(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends ping frame with non zero stream id",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_ping_frame_with_non_zero_stream_id(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends ping frame with invalid length",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_ping_frame_with_invalid_length(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends goaway frame with non zero stream id",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_goaway_frame_with_non_zero_stream_id(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends window update frame with zero increment",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_window_update_frame_with_zero_increment(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends window update frame with zero increment on stream",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_window_update_frame_with_zero_increment_on_stream(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends window update frame with invalid length",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_window_update_frame_with_invalid_length(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends settings frame to set initial window size to 1 and sends headers frame",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_settings_frame_to_set_initial_window_size_to_1_and_sends_headers_frame(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends multiple window update frames increasing flow control window above max",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_multiple_window_update_frames_increasing_flow_control_window_above_max(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends multiple window update frames increasing flow control window above max on stream",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_multiple_window_update_frames_increasing_flow_control_window_above_max_on_stream(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "changes settings initial window size after sending headers frame",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::changes_settings_initial_window_size_after_sending_headers_frame(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends settings frame for window size to be negative",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_settings_frame_for_window_size_to_be_negative(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends settings initial window size with exceeded max window size value",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_settings_initial_window_size_with_exceeded_max_window_size_value(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends multiple continuation frames preceded by headers frame",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_multiple_continuation_frames_preceded_by_headers_frame(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends continuation frame followed by non continuation frame",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_continuation_frame_followed_by_non_continuation_frame(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends continuation frame with zero stream id",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_continuation_frame_with_zero_stream_id(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends continuation frame preceded by headers frame with end headers flag",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_continuation_frame_preceded_by_headers_frame_with_end_headers_flag(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends continuation frame preceded by continuation frame with end headers flag",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_continuation_frame_preceded_by_continuation_frame_with_end_headers_flag(conn))),
-                );
-                _6_frame_definitions.insert(
-                    "sends continuation frame preceded by data frame",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_continuation_frame_preceded_by_data_frame(conn))),
-                );
-
-                sections.insert("6. frame definitions", _6_frame_definitions);
-            }
-            {
-                use ::httpwg::rfc9113::_7_error_codes as s;
-                let mut _7_error_codes: HashMap<&'static str, BoxedTest<IO>> = Default::default();
-
-                _7_error_codes.insert(
-                    "sends goaway frame with unknown error code",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_goaway_frame_with_unknown_error_code(conn))),
-                );
-                _7_error_codes.insert(
-                    "sends rst stream frame with unknown error code",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_rst_stream_frame_with_unknown_error_code(conn))),
-                );
-
-                sections.insert("7. error codes", _7_error_codes);
-            }
-            {
-                use ::httpwg::rfc9113::_8_expressing_http_semantics_in_http2 as s;
-                let mut _8_expressing_http_semantics_in_http2: HashMap<&'static str, BoxedTest<IO>> = Default::default();
-
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends second headers frame without end stream",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_second_headers_frame_without_end_stream(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with incorrect content length single data frame",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_incorrect_content_length_single_data_frame(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with incorrect content length multiple data frames",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_incorrect_content_length_multiple_data_frames(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with uppercase field name",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_uppercase_field_name(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with space in field name",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_space_in_field_name(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with non visible ascii",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_non_visible_ascii(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with del character",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_del_character(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with non ascii character",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_non_ascii_character(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with colon in field name",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_colon_in_field_name(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with lf in field value",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_lf_in_field_value(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with cr in field value",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_cr_in_field_value(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with nul in field value",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_nul_in_field_value(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with leading space in field value",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_leading_space_in_field_value(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with trailing tab in field value",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_trailing_tab_in_field_value(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with connection header",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_connection_header(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with proxy connection header",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_proxy_connection_header(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with keep alive header",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_keep_alive_header(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with transfer encoding header",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_transfer_encoding_header(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with upgrade header",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_upgrade_header(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with te trailers",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_te_trailers(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with te not trailers",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_te_not_trailers(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with response pseudo header",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_response_pseudo_header(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with pseudo header in trailer",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_pseudo_header_in_trailer(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with duplicate pseudo headers",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_duplicate_pseudo_headers(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with mismatched host authority",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_mismatched_host_authority(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with empty path component",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_empty_path_component(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame without method",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_without_method(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame without scheme",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_without_scheme(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame without path",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_without_path(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame without status",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_without_status(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "client sends push promise frame",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::client_sends_push_promise_frame(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends connect with scheme",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_connect_with_scheme(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends connect with path",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_connect_with_path(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends connect without authority",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_connect_without_authority(conn))),
-                );
-                _8_expressing_http_semantics_in_http2.insert(
-                    "sends headers frame with pseudo headers after regular headers",
-                    Box::new(|conn: Conn<IO>| Box::pin(s::sends_headers_frame_with_pseudo_headers_after_regular_headers(conn))),
-                );
-
-                sections.insert("8. expressing http semantics in http2", _8_expressing_http_semantics_in_http2);
-            }
-
-            rfcs.insert("RFC 9113", sections);
+            rfcs
         }
+    };
+}
 
-        rfcs
+/// This generates a function that lists every section of every RFC,
+/// with what their tests check, cf. `httpwg::Section`
+#[macro_export]
+macro_rules! gen_sections {
+  ($sections_fn_name:ident) => {
+    pub fn $sections_fn_name() -> Vec<::httpwg::Section> {
+        vec![
+            ::httpwg::Section {
+                rfc: "RFC 8441",
+                number: "3",
+                title: "The SETTINGS_ENABLE_CONNECT_PROTOCOL SETTINGS Parameter",
+                requirements: &["Upon receipt of SETTINGS_ENABLE_CONNECT_PROTOCOL with a value of 1,\na client MAY use the Extended CONNECT as defined in this document\nwhen creating new streams.", "Receipt of this parameter by a server does not have any impact."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 8441",
+                number: "4",
+                title: "The Extended CONNECT Method",
+                requirements: &["Upon receiving a CONNECT request bearing the :protocol pseudo-header\nfield, the server establishes a tunnel to another service of the\nprotocol type indicated by the pseudo-header field.", "On requests that contain the :protocol pseudo-header field, the\n:scheme and :path pseudo-header fields of the target URI (see\nSection 5) MUST also be included.", "A new pseudo-header field :protocol MAY be included on request\nHEADERS indicating the desired protocol to be spoken on the tunnel\ncreated by CONNECT."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 8441",
+                number: "5",
+                title: "Using Extended CONNECT to Bootstrap the WebSocket Protocol",
+                requirements: &["After successfully processing the opening handshake, the peers\nshould proceed with The WebSocket Protocol [RFC6455] using the\nHTTP/2 stream from the CONNECT transaction as if it were the TCP\nconnection referred to in [RFC6455].", "RST exceptions are represented with the stream error (Section 5.4.2\nof [RFC7540]) of type CANCEL (Section 7 of [RFC7540])."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "3",
+                title: "Starting HTTP/2",
+                requirements: &[],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "3.4",
+                title: "HTTP/2 connection preface",
+                requirements: &["The server connection preface consists of a potentially empty\nSETTINGS frame (Section 6.5) that MUST be the first frame\nthe server sends in the HTTP/2 connection.", "Clients and servers MUST treat an invalid connection preface as\na connection error (Section 5.4.1) of type PROTOCOL_ERROR.", "That is, the connection preface starts with the string\n\"PRI * HTTP/2.0\\r\\n\\r\\nSM\\r\\n\\r\\n\". This sequence MUST be followed\nby a SETTINGS frame (Section 6.5), which MAY be empty.", "The client connection preface starts with a sequence of 24 octets."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "4",
+                title: "HTTP Frames",
+                requirements: &[],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "4.1",
+                title: "Frame Format",
+                requirements: &["Implementations MUST ignore and discard frames of unknown types.", "Unused flags MUST be ignored on receipt and MUST be left\nunset (0x00) when sending.", "Reserved: A reserved 1-bit field. The semantics of this bit are\nundefined, and the bit MUST remain unset (0x00) when sending and\nMUST be ignored when receiving.", "All frames begin with a fixed 9-octet header followed by a\nvariable-length frame payload.", "An endpoint MUST send an error code of FRAME_SIZE_ERROR if a frame\nexceeds the size defined in SETTINGS_MAX_FRAME_SIZE, exceeds any\nlimit defined for the frame type, or is too small to contain mandatory frame\ndata", "A frame size error in a frame that could alter the state of\nthe entire connection MUST be treated as a connection error\n(Section 5.4.1); this includes any frame carrying a field block\n(Section 4.3) (that is, HEADERS, PUSH_PROMISE, and CONTINUATION),\na SETTINGS frame, and any frame with a stream identifier of 0."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "4.3",
+                title: "Header Compression and Decompression",
+                requirements: &["A decoding error in a header block MUST be treated as a connection error\n(Section 5.4.1) of type COMPRESSION_ERROR.", "A decoding error in a field block MUST be treated as a connection error\n(Section 5.4.1) of type COMPRESSION_ERROR.", "Each header block is processed as a discrete unit. Header blocks\nMUST be transmitted as a contiguous sequence of frames, with no\ninterleaved frames of any other type or from any other stream.", "Each field block is processed as a discrete unit. Field blocks MUST be\ntransmitted as a contiguous sequence of frames, with no interleaved\nframes of any other type or from any other stream."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "5",
+                title: "Streams and Multiplexing",
+                requirements: &[],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "5.1",
+                title: "Stream States",
+                requirements: &["idle:\nReceiving any frame other than HEADERS or PRIORITY on a stream\nin this state MUST be treated as a connection error\n(Section 5.4.1) of type PROTOCOL_ERROR.", "half-closed (remote):\nIf an endpoint receives additional frames, other than\nWINDOW_UPDATE, PRIORITY, or RST_STREAM, for a stream that is in\nthis state, it MUST respond with a stream error (Section 5.4.2)\nof type STREAM_CLOSED.", "closed:\nAn endpoint that receives any frame other than PRIORITY after\nreceiving a RST_STREAM MUST treat that as a stream error\n(Section 5.4.2) of type STREAM_CLOSED.", "closed:\nAn endpoint that receives any frames after receiving a frame\nwith the END_STREAM flag set MUST treat that as a connection\nerror (Section 6.4.1) of type STREAM_CLOSED.", "An endpoint that receives an unexpected stream identifier\nMUST respond with a connection error (Section 5.4.1) of\ntype PROTOCOL_ERROR."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "5.1.2",
+                title: "Stream Concurrency",
+                requirements: &["An endpoint that receives an unexpected stream identifier\nMUST respond with a connection error (Section 5.4.1) of\ntype PROTOCOL_ERROR.", "A peer can limit the number of concurrently active streams using the\nSETTINGS_MAX_CONCURRENT_STREAMS parameter (see Section 6.5.2) within a\nSETTINGS frame. [...]"],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "5.4.1",
+                title: "Connection Error Handling",
+                requirements: &["After sending the GOAWAY frame for an error condition,\nthe endpoint MUST close the TCP connection.", "A connection error is any error that prevents further processing of\nthe frame layer or corrupts any connection state."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "5.5",
+                title: "Extending HTTP/2",
+                requirements: &["Extension frames that appear in the middle of a header block\n(Section 4.3) are not permitted; these MUST be treated as\na connection error (Section 5.4.1) of type PROTOCOL_ERROR."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "6",
+                title: "Frame Definitions",
+                requirements: &[],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "6.1",
+                title: "DATA",
+                requirements: &["DATA frames MUST be associated with a stream. If a DATA frame is\nreceived whose stream identifier field is 0x0, the recipient\nMUST respond with a connection error (Section 5.4.1) of type\nPROTOCOL_ERROR.", "If a DATA frame is received whose stream is not in \"open\" or\n\"half-closed (local)\" state, the recipient MUST respond with\na stream error (Section 5.4.2) of type STREAM_CLOSED.", "If the length of the padding is the length of the frame payload\nor greater, the recipient MUST treat this as a connection error\n(Section 5.4.1) of type PROTOCOL_ERROR."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "6.2",
+                title: "HEADERS",
+                requirements: &["HEADERS frames MUST be associated with a stream. If a HEADERS\nframe is received whose stream identifier field is 0x0, the\nrecipient MUST respond with a connection error (Section 5.4.1)\nof type PROTOCOL_ERROR.", "The HEADERS frame can include padding. Padding fields and flags\nare identical to those defined for DATA frames (Section 6.1).\nPadding that exceeds the size remaining for the header block\nfragment MUST be treated as a PROTOCOL_ERROR."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "6.3",
+                title: "PRIORITY",
+                requirements: &["The PRIORITY frame always identifies a stream. If a PRIORITY\nframe is received with a stream identifier of 0x0, the recipient\nMUST respond with a connection error (Section 5.4.1) of type\nPROTOCOL_ERROR.", "A PRIORITY frame with a length other than 5 octets MUST be\ntreated as a stream error (Section 5.4.2) of type\nFRAME_SIZE_ERROR."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "6.4",
+                title: "RST_STREAM",
+                requirements: &["RST_STREAM frames MUST be associated with a stream. If a\nRST_STREAM frame is received with a stream identifier of 0x0,\nthe recipient MUST treat this as a connection error\n(Section 5.4.1) of type PROTOCOL_ERROR.", "RST_STREAM frames MUST NOT be sent for a stream in the \"idle\"\nstate. If a RST_STREAM frame identifying an idle stream is\nreceived, the recipient MUST treat this as a connection error\n(Section 5.4.1) of type PROTOCOL_ERROR.", "A RST_STREAM frame with a length other than 4 octets MUST be\ntreated as a connection error (Section 5.4.1) of type\nFRAME_SIZE_ERROR."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "6.5",
+                title: "SETTINGS",
+                requirements: &[],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "6.5.1",
+                title: "SETTINGS Format",
+                requirements: &["ACK (0x1):\nWhen set, bit 0 indicates that this frame acknowledges receipt\nand application of the peer's SETTINGS frame. When this bit is\nset, the payload of the SETTINGS frame MUST be empty. Receipt of\na SETTINGS frame with the ACK flag set and a length field value\nother than 0 MUST be treated as a connection error (Section 5.4.1)\nof type FRAME_SIZE_ERROR.", "SETTINGS frames always apply to a connection, never a single\nstream. The stream identifier for a SETTINGS frame MUST be\nzero (0x0). If an endpoint receives a SETTINGS frame whose\nstream identifier field is anything other than 0x0, the\nendpoint MUST respond with a connection error (Section 5.4.1)\nof type PROTOCOL_ERROR.", "The SETTINGS frame affects connection state. A badly formed or\nincomplete SETTINGS frame MUST be treated as a connection error\n(Section 5.4.1) of type PROTOCOL_ERROR."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "6.5.2",
+                title: "Defined SETTINGS Parameters",
+                requirements: &["SETTINGS_ENABLE_PUSH (0x2):\nThe initial value is 1, which indicates that server push is\npermitted. Any value other than 0 or 1 MUST be treated as a\nconnection error (Section 5.4.1) of type PROTOCOL_ERROR.", "SETTINGS_INITIAL_WINDOW_SIZE (0x4):\nValues above the maximum flow-control window size of 2^31-1\nMUST be treated as a connection error (Section 5.4.1) of\ntype FLOW_CONTROL_ERROR.", "SETTINGS_MAX_FRAME_SIZE (0x5):\nThe initial value is 2^14 (16,384) octets. The value advertised\nby an endpoint MUST be between this initial value and the\nmaximum allowed frame size (2^24-1 or 16,777,215 octets),\ninclusive. Values outside this range MUST be treated as a\nconnection error (Section 5.4.1) of type PROTOCOL_ERROR.", "An endpoint that receives a SETTINGS frame with any unknown\nor unsupported identifier MUST ignore that setting."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "6.5.3",
+                title: "Settings Synchronization",
+                requirements: &["The values in the SETTINGS frame MUST be processed in the order\nthey appear, with no other frame processing between values.", "Once all values have been processed, the recipient MUST\nimmediately emit a SETTINGS frame with the ACK flag set.", "Once all values have been processed, the recipient MUST\nimmediately emit a SETTINGS frame with the ACK flag set. [...] If the\nsender of a SETTINGS frame does not receive an acknowledgment within a\nreasonable amount of time, it MAY issue a connection error (Section\n5.4.1) of type SETTINGS_TIMEOUT."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "6.7",
+                title: "PING",
+                requirements: &["Receivers of a PING frame that does not include an ACK flag MUST\nsend a PING frame with the ACK flag set in response, with an\nidentical payload.", "ACK (0x1):\nWhen set, bit 0 indicates that this PING frame is a PING\nresponse. An endpoint MUST set this flag in PING responses.\nAn endpoint MUST NOT respond to PING frames containing this\nflag.", "If a PING frame is received with a stream identifier field value\nother than 0x0, the recipient MUST respond with a connection\nerror (Section 5.4.1) of type PROTOCOL_ERROR.", "Receipt of a PING frame with a length field value other than 8\nMUST be treated as a connection error (Section 5.4.1) of type\nFRAME_SIZE_ERROR."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "6.8",
+                title: "GOAWAY",
+                requirements: &["An endpoint MUST treat a GOAWAY frame with a stream identifier\nother than 0x0 as a connection error (Section 5.4.1) of type\nPROTOCOL_ERROR.", "The last stream identifier in the GOAWAY frame contains the\nhighest-numbered stream identifier for which the sender of the\nGOAWAY frame might have taken some action on or might yet take\naction on.", "Once sent, the sender will ignore frames sent on streams initiated\nby the receiver if the stream has an identifier higher than the\nincluded last stream identifier."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "6.9",
+                title: "WINDOW_UPDATE",
+                requirements: &["A receiver MUST treat the receipt of a WINDOW_UPDATE frame with\na flow-control window increment of 0 as a stream error\n(Section 5.4.2) of type PROTOCOL_ERROR; errors on the connection\nflow-control window MUST be treated as a connection error\n(Section 5.4.1).", "A WINDOW_UPDATE frame with a length other than 4 octets MUST\nbe treated as a connection error (Section 5.4.1) of type\nFRAME_SIZE_ERROR."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "6.9.1",
+                title: "The Flow-Control Window",
+                requirements: &["The sender MUST NOT send a flow-controlled frame with a length\nthat exceeds the space available in either of the flow-control\nwindows advertised by the receiver.", "A sender MUST NOT allow a flow-control window to exceed 2^31-1\noctets. If a sender receives a WINDOW_UPDATE that causes a\nflow-control window to exceed this maximum, it MUST terminate\neither the stream or the connection, as appropriate.\nFor streams, the sender sends a RST_STREAM with an error code\nof FLOW_CONTROL_ERROR; for the connection, a GOAWAY frame with\nan error code of FLOW_CONTROL_ERROR is sent.", "A sender MUST NOT allow a flow-control window to exceed 2^31-1\noctets.", "A sender MUST NOT allow a flow-control window to exceed 2^31-1\noctets. If a sender receives a WINDOW_UPDATE that causes a\nflow-control window to exceed this maximum, it MUST terminate\neither the stream or the connection, as appropriate."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "6.9.2",
+                title: "Initial Flow-Control Window Size",
+                requirements: &["When the value of SETTINGS_INITIAL_WINDOW_SIZE changes,\na receiver MUST adjust the size of all stream flow-control\nwindows that it maintains by the difference between the new\nvalue and the old value.", "A sender MUST track the negative flow-control window and\nMUST NOT send new flow-controlled frames until it receives\nWINDOW_UPDATE frames that cause the flow-control window to\nbecome positive.", "An endpoint MUST treat a change to SETTINGS_INITIAL_WINDOW_SIZE\nthat causes any flow-control window to exceed the maximum size\nas a connection error (Section 5.4.1) of type FLOW_CONTROL_ERROR."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "6.10",
+                title: "CONTINUATION",
+                requirements: &["The CONTINUATION frame (type=0x9) is used to continue a sequence\nof header block fragments (Section 4.3). Any number of\nCONTINUATION frames can be sent, as long as the preceding frame\nis on the same stream and is a HEADERS, PUSH_PROMISE,\nor CONTINUATION frame without the END_HEADERS flag set.", "END_HEADERS (0x4):\nIf the END_HEADERS bit is not set, this frame MUST be followed\nby another CONTINUATION frame. A receiver MUST treat the receipt\nof any other type of frame or a frame on a different stream as\na connection error (Section 5.4.1) of type PROTOCOL_ERROR.", "CONTINUATION frames MUST be associated with a stream. If a\nCONTINUATION frame is received whose stream identifier field is\n0x0, the recipient MUST respond with a connection error\n(Section 5.4.1) of type PROTOCOL_ERROR.", "A CONTINUATION frame MUST be preceded by a HEADERS, PUSH_PROMISE\nor CONTINUATION frame without the END_HEADERS flag set.\nA recipient that observes violation of this rule MUST respond\nwith a connection error (Section 5.4.1) of type PROTOCOL_ERROR."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "7",
+                title: "Error Codes",
+                requirements: &["Unknown or unsupported error codes MUST NOT trigger any special\nbehavior. These MAY be treated by an implementation as being\nequivalent to INTERNAL_ERROR."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "8",
+                title: "Expressing HTTP Semantics in HTTP/2",
+                requirements: &[],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "8.1",
+                title: "HTTP Message Framing",
+                requirements: &["", "An HTTP message (request or response) consists of:", "An HTTP message (request or response) consists of: [...] 2. zero or\nmore DATA frames containing the message content [...] and 3.\noptionally, one HEADERS frame (followed by zero or more CONTINUATION\nframes) containing the trailer section.", "A request or response is also malformed if the value of a\ncontent-length header field does not equal the sum of the DATA frame\npayload lengths that form the content, unless the message is defined as\nhaving no content.", "A field name MUST NOT contain characters in the ranges 0x00-0x20, 0x41-0x5a,\nor 0x7f-0xff (all ranges inclusive). This specifically excludes all\nnon-visible ASCII characters, ASCII SP (0x20), and uppercase characters ('A'\nto 'Z', ASCII 0x41 to 0x5a).", "With the exception of pseudo-header fields (Section 8.3), which have a name\nthat starts with a single colon, field names MUST NOT include a colon (ASCII\nCOLON, 0x3a).", "A field value MUST NOT contain the zero value (ASCII NUL, 0x00), line feed\n(ASCII LF, 0x0a), or carriage return (ASCII CR, 0x0d) at any position.", "A field value MUST NOT start or end with an ASCII whitespace character\n(ASCII SP or HTAB, 0x20 or 0x09).\nWhen a request message violates one of these requirements, an implementation\nSHOULD generate a 400 (Bad Request) status code (see Section 15.5.1 of\nHTTP), unless a more suitable status code is defined or the status code\ncannot be sent (e.g., because the error occurs in a trailer field)."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "8.2.2",
+                title: "Connection-Specific Header Fields",
+                requirements: &["HTTP/2 does not use the Connection header field (Section 7.6.1 of HTTP) to\nindicate connection-specific header fields; in this protocol,\nconnection-specific metadata is conveyed by other means. An endpoint MUST\nNOT generate an HTTP/2 message containing connection-specific header fields.\nThis includes the Connection header field and those listed as having\nconnection-specific semantics in Section 7.6.1 of HTTP (that is,\nProxy-Connection, Keep-Alive, Transfer-Encoding, and Upgrade). Any message\ncontaining connection-specific header fields MUST be treated as malformed\n(Section 8.1.1).", "HTTP/2 does not use the Connection header field (Section 7.6.1 of HTTP) to\nindicate connection-specific header fields; in this protocol,\nconnection-specific metadata is conveyed by other means. An endpoint MUST\nNOT generate an HTTP/2 message containing connection-specific header fields.", "The only exception to this is the TE header field, which MAY be present in\nan HTTP/2 request; when it is, it MUST NOT contain any value other than\n\"trailers\"."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "8.2.3",
+                title: "Compressing the Cookie Header Field",
+                requirements: &[],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "8.3",
+                title: "HTTP Control Data",
+                requirements: &["[...] pseudo-header fields defined for responses MUST NOT appear in requests\n[...] Endpoints MUST treat a request or response that contains undefined or\ninvalid pseudo-header fields as malformed (Section 8.1.1).", "[...] Pseudo-header fields MUST NOT appear in a trailer section. Endpoints\nMUST treat a request or response that contains undefined or invalid\npseudo-header fields as malformed (Section 8.1.1).", "The same pseudo-header field name MUST NOT appear more than once in a field\nblock. A field block for an HTTP request or response that contains a\nrepeated pseudo-header field name MUST be treated as malformed (Section\n8.1.1).", "A server SHOULD treat a request as malformed if it contains a Host header\nfield that identifies an entity that differs from the entity in the\n\":authority\" pseudo-header field. The values of fields need to be normalized\nto compare them (see Section 6.2 of RFC3986). An origin server can apply\nany normalization method, whereas other servers MUST perform scheme-based\nnormalization (see Section 6.2.3 of RFC3986) of the two fields.", "This pseudo-header field MUST NOT be empty for \"http\" or \"https\" URIs;\n\"http\" or \"https\" URIs that do not contain a path component MUST include a\nvalue of '/'. The exceptions to this rule are:", "All HTTP/2 requests MUST include exactly one valid value for the \":method\",\n\":scheme\", and \":path\" pseudo-header fields, unless they are CONNECT\nrequests (Section 8.5). An HTTP request that omits mandatory pseudo-header\nfields is malformed (Section 8.1.1)."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "8.3.2",
+                title: "Response Pseudo-Header Fields",
+                requirements: &["All HTTP/2 requests MUST include exactly one valid value for the \":method\",\n\":scheme\", and \":path\" pseudo-header fields, unless they are CONNECT\nrequests (Section 8.5). An HTTP request that omits mandatory pseudo-header\nfields is malformed (Section 8.1.1).", "A client cannot push. Thus, servers MUST treat the receipt of a PUSH_PROMISE\nframe as a connection error (Section 5.4.1) of type PROTOCOL_ERROR. A server\ncannot set the SETTINGS_ENABLE_PUSH setting to a value other than 0 (see\nSection 6.5.2)."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "8.5",
+                title: "The CONNECT Method",
+                requirements: &["The CONNECT method (Section 9.3.6 of HTTP) is used to convert an HTTP\nconnection into a tunnel to a remote host. CONNECT is primarily used with\nHTTP proxies to establish a TLS session with an origin server for the\npurposes of interacting with \"https\" resources.", "All pseudo-header fields MUST appear in a field block before all regular\nfield lines (RFC 9113, section 8.3)"],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "8.6",
+                title: "The Upgrade Header Field",
+                requirements: &[],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "8.7",
+                title: "Request Reliability",
+                requirements: &[],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "9",
+                title: "HTTP/2 Connections",
+                requirements: &[],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "9.1",
+                title: "Connection Management",
+                requirements: &["HTTP/2 connections are persistent. For best performance, it is\nexpected that clients will not close connections until it is\ndetermined that no further communication with a server is necessary\n[...]", "Servers are encouraged to maintain open connections for as long as\npossible but are permitted to terminate idle connections if\nnecessary. When either endpoint chooses to close the transport-layer\nTCP connection, the terminating endpoint SHOULD first send a GOAWAY\n(Section 6.8) frame so that both endpoints can reliably determine\nwhether previously sent frames have been processed and gracefully\ncomplete or terminate any necessary remaining tasks."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "10",
+                title: "Security Considerations",
+                requirements: &[],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "10.5",
+                title: "Denial-of-Service Considerations",
+                requirements: &["An endpoint that doesn't monitor use of these features exposes itself\nto a risk of denial of service. Implementations SHOULD track the use\nof these features and set limits on their use. An endpoint MAY treat\nactivity that is suspicious as a connection error (Section 5.4.1) of\ntype ENHANCE_YOUR_CALM."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9113",
+                number: "10.5.1",
+                title: "Limits on Field Block Size",
+                requirements: &["A large field block (Section 8.2) can cause an implementation to\ncommit a large amount of state. [...] A server that receives a larger\nfield block than it is willing to handle can send an HTTP 431 (Request\nHeader Fields Too Large) status code. [...] An endpoint can treat\nthis as a connection error (Section 5.4.1) of type\nENHANCE_YOUR_CALM."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9218",
+                number: "4",
+                title: "Priority Parameters",
+                requirements: &["Unknown parameters, parameters with out-of-range values, or values\nof unexpected types MUST be ignored."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9218",
+                number: "5",
+                title: "The Priority HTTP Header Field",
+                requirements: &["The Priority HTTP header field is a Dictionary that carries priority\nparameters (see Section 4). It can appear in requests and responses.", "When receiving an HTTP request that does not carry these priority\nparameters, a server SHOULD act as if their default values were\nspecified."],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9218",
+                number: "7",
+                title: "The PRIORITY_UPDATE Frame",
+                requirements: &[],
+            },
+            ::httpwg::Section {
+                rfc: "RFC 9218",
+                number: "7.1",
+                title: "HTTP/2 PRIORITY_UPDATE Frame",
+                requirements: &["The HTTP/2 PRIORITY_UPDATE frame (type=0x10) is used by clients to\nsignal the initial priority of a response, or to reprioritize a\nresponse or push stream.", "When the PRIORITY_UPDATE frame applies to a request stream, clients\nSHOULD provide a prioritized stream ID that refers to a stream in\nthe \"open\", \"half-closed (local)\", or \"idle\" state.", "Servers can discard frames where the Prioritized Stream ID refers to\na stream in the \"half-closed (local)\" or \"closed\" state.", "The Priority Field Value is the priority update value in ASCII text,\nencoded using Structured Fields. This is the same representation as\nthe Priority header field value."],
+            },
+        ]
     }
   }
 }
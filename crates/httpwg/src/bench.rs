@@ -0,0 +1,223 @@
+//! Not conformance, performance: drives a configurable request load against
+//! a server and reports requests per second and latency percentiles, to
+//! catch regressions in the server rather than to grade it.
+//!
+//! Requests go through [Conn::send_request], so the numbers include the
+//! harness's own overhead: they're good for comparing two builds of a server
+//! on the same machine, not for comparing servers with other clients.
+
+use std::{collections::HashMap, fmt, rc::Rc, time::Duration};
+
+use buffet::IntoHalves;
+use loona_h2::StreamId;
+use tokio::time::Instant;
+
+use crate::{Config, Conn, Connector, Request, Responses};
+
+/// The connection's flow-control window we keep topping up to: the one
+/// every connection starts out with
+pub(crate) const CONNECTION_WINDOW: i64 = 65_535;
+
+/// The load to put on the server
+#[derive(Debug, Clone)]
+pub struct Load {
+    /// how many connections to open, all driven at once
+    pub connections: u32,
+
+    /// how many requests each connection keeps in flight, at most: fewer if
+    /// the server's SETTINGS_MAX_CONCURRENT_STREAMS says so
+    pub streams: u32,
+
+    /// how many requests each connection sends in total
+    pub requests: u32,
+
+    /// the size of each request body: requests without one are GETs, the
+    /// others POSTs. Bodies go out regardless of the server's flow-control
+    /// windows, so keep them well below those.
+    pub body_len: usize,
+}
+
+impl Default for Load {
+    fn default() -> Self {
+        Self {
+            connections: 1,
+            streams: 10,
+            requests: 10_000,
+            body_len: 0,
+        }
+    }
+}
+
+impl Load {
+    fn request(&self) -> Request {
+        if self.body_len == 0 {
+            Request::get()
+        } else {
+            Request::post(crate::dummy_bytes(self.body_len))
+        }
+    }
+}
+
+/// How a [run] went
+#[derive(Debug, Default)]
+pub struct BenchReport {
+    /// requests that got a complete response
+    pub completed: u64,
+
+    /// requests that didn't, e.g. because their stream got reset
+    pub failed: u64,
+
+    /// from the first connection opening to the last response coming in
+    pub elapsed: Duration,
+
+    /// of completed requests, from sending them to reading their response,
+    /// sorted
+    pub latencies: Vec<Duration>,
+}
+
+impl BenchReport {
+    pub fn requests_per_sec(&self) -> f64 {
+        self.completed as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// The latency `p` percent of requests completed within, e.g. 99.0 for
+    /// the 99th percentile
+    pub fn percentile(&self, p: f64) -> Option<Duration> {
+        percentile(&self.latencies, p)
+    }
+}
+
+/// The latency `p` percent of `latencies` are within, `latencies` being
+/// sorted
+pub(crate) fn percentile(latencies: &[Duration], p: f64) -> Option<Duration> {
+    if latencies.is_empty() {
+        return None;
+    }
+    let rank = (p / 100.0 * latencies.len() as f64).ceil() as usize;
+    Some(latencies[rank.clamp(1, latencies.len()) - 1])
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} requests in {:.2?} ({:.0} req/s), {} failed",
+            self.completed,
+            self.elapsed,
+            self.requests_per_sec(),
+            self.failed
+        )?;
+        if let (Some(p50), Some(p90), Some(p99), Some(max)) = (
+            self.percentile(50.0),
+            self.percentile(90.0),
+            self.percentile(99.0),
+            self.latencies.last(),
+        ) {
+            write!(
+                f,
+                ", latency p50 {p50:.2?}, p90 {p90:.2?}, p99 {p99:.2?}, max {max:.2?}"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Puts `load` on the server `connect` reaches. Fails if a connection
+/// can't be opened, or if the server stops answering on one.
+pub async fn run<IO: IntoHalves>(
+    config: Rc<Config>,
+    connect: Connector<IO>,
+    load: &Load,
+) -> eyre::Result<BenchReport> {
+    let start = Instant::now();
+    let drivers = (0..load.connections).map(|_| {
+        let config = config.clone();
+        let connect = connect.clone();
+        async move {
+            let conn = Conn::new(config, connect().await?);
+            drive(conn, load).await
+        }
+    });
+    let results = futures_util::future::try_join_all(drivers).await?;
+
+    let mut report = BenchReport {
+        elapsed: start.elapsed(),
+        ..Default::default()
+    };
+    for result in results {
+        report.completed += result.completed;
+        report.failed += result.failed;
+        report.latencies.extend(result.latencies);
+    }
+    report.latencies.sort_unstable();
+    Ok(report)
+}
+
+/// Sends `load.requests` requests on `conn`, keeping up to `load.streams` of
+/// them in flight
+async fn drive<IO: IntoHalves>(mut conn: Conn<IO>, load: &Load) -> eyre::Result<BenchReport> {
+    conn.handshake().await?;
+
+    let max_in_flight = load
+        .streams
+        .min(conn.settings.max_concurrent_streams.unwrap_or(u32::MAX))
+        .max(1) as usize;
+    let mut report = BenchReport::default();
+    let mut in_flight: HashMap<StreamId, Instant> = Default::default();
+    let mut responses = Responses::default();
+    let mut sent = 0;
+
+    loop {
+        while in_flight.len() < max_in_flight && sent < load.requests {
+            let pending = conn.send_request(load.request()).await?;
+            in_flight.insert(pending.stream_id, Instant::now());
+            sent += 1;
+        }
+        if in_flight.is_empty() {
+            break;
+        }
+
+        conn.read_responses(&mut responses, |r| {
+            in_flight.keys().any(|&id| r.ended(id)) || r.goaway.is_some() || r.closed
+        })
+        .await?;
+
+        let ended: Vec<StreamId> = in_flight
+            .keys()
+            .copied()
+            .filter(|&id| responses.ended(id))
+            .collect();
+        if ended.is_empty() {
+            let why = match (&responses.goaway, responses.closed) {
+                (Some(goaway), _) => format!("sent GOAWAY with {:?}", goaway.error_code),
+                (None, true) => "hung up".to_owned(),
+                (None, false) => format!("went quiet for {:?}", conn.config.timeout),
+            };
+            eyre::bail!(
+                "server {why} after {} responses, with {} requests in flight",
+                report.completed + report.failed,
+                in_flight.len()
+            );
+        }
+        for stream_id in ended {
+            let sent_at = in_flight.remove(&stream_id).unwrap();
+            if responses.completed(stream_id) {
+                report.completed += 1;
+                report.latencies.push(sent_at.elapsed());
+            } else {
+                report.failed += 1;
+            }
+            responses.streams.remove(&stream_id);
+        }
+
+        // every stream gets a fresh window, but the connection's only grows
+        // back if we say so
+        let window = conn.granted_window(StreamId::CONNECTION);
+        if window < CONNECTION_WINDOW / 2 {
+            conn.write_window_update(StreamId::CONNECTION, (CONNECTION_WINDOW - window) as u32)
+                .await?;
+        }
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,316 @@
+//! Recording every frame a [crate::Conn] sends and receives, so that when a
+//! test fails, the whole exchange can be dumped and looked at offline.
+//!
+//! Recording is off unless [crate::Config::record_frames] is set. Grab the
+//! [Recorder] with [crate::Conn::recorder] before handing the connection to a
+//! test, then dump it if the test fails:
+//!
+//!   - [Recorder::to_json] is for humans: one object per frame, with the
+//!     frame's debug representation, and the header and payload in hex
+//!   - [Recorder::to_bin] is for tools, see below
+//!
+//! [read_bin] reads such a dump back, [render_trace] turns it into a
+//! human-readable trace, and [crate::replay] sends its client side to
+//! another server, e.g. to see whether it fails against that one too.
+//!
+//! The binary format is the magic `HTTPWG\0\x01`, then one record per
+//! frame, each made of:
+//!
+//!   - the direction, as a byte: 0 for sent, 1 for received
+//!   - microseconds since recording started, as a big-endian u64
+//!   - the length of what follows, as a big-endian u32
+//!   - the bytes as they went over the wire: the 9-byte frame header followed
+//!     by the payload, or whatever [crate::Conn::send] sent as-is (like the
+//!     connection preface, or deliberately malformed frames)
+
+use std::{cell::RefCell, fmt::Write, rc::Rc, time::Duration};
+
+use buffet::{Roll, RollMut};
+use eyre::bail;
+use loona_h2::{Frame, PREFACE};
+use serde::Serialize;
+use tokio::time::Instant;
+
+use crate::Payload;
+
+/// Magic bytes that start a dump made by [Recorder::to_bin]
+pub const BIN_MAGIC: &[u8; 8] = b"HTTPWG\0\x01";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// A frame (or some raw bytes) that went over the wire
+#[derive(Debug, Clone)]
+pub struct Captured {
+    pub direction: Direction,
+
+    /// since recording started
+    pub at: Duration,
+
+    /// `None` for bytes sent with [crate::Conn::send], which may or may not
+    /// be frames
+    pub frame: Option<Frame>,
+
+    /// the 9-byte frame header, empty for raw bytes
+    pub header: Vec<u8>,
+
+    pub payload: Vec<u8>,
+}
+
+impl Captured {
+    /// The bytes as they went over the wire
+    pub fn bytes(&self) -> Vec<u8> {
+        [&self.header[..], &self.payload[..]].concat()
+    }
+}
+
+/// A handle to the frames recorded on a connection: clones share them
+#[derive(Clone)]
+pub struct Recorder {
+    start: Instant,
+    frames: Rc<RefCell<Vec<Captured>>>,
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            frames: Default::default(),
+        }
+    }
+}
+
+impl Recorder {
+    pub(crate) fn record_frame(
+        &self,
+        direction: Direction,
+        frame: &Frame,
+        header: &[u8],
+        payload: &[u8],
+    ) {
+        self.push(direction, Some(*frame), header, payload);
+    }
+
+    pub(crate) fn record_raw(&self, bytes: &[u8]) {
+        self.push(Direction::Sent, None, &[], bytes);
+    }
+
+    fn push(&self, direction: Direction, frame: Option<Frame>, header: &[u8], payload: &[u8]) {
+        self.frames.borrow_mut().push(Captured {
+            direction,
+            at: self.start.elapsed(),
+            frame,
+            header: header.to_vec(),
+            payload: payload.to_vec(),
+        });
+    }
+
+    /// Everything recorded so far, in order
+    pub fn frames(&self) -> Vec<Captured> {
+        self.frames.borrow().clone()
+    }
+
+    pub fn to_json(&self) -> String {
+        #[derive(Serialize)]
+        struct JsonFrame {
+            direction: Direction,
+            at_us: u128,
+            frame: Option<String>,
+            header: String,
+            payload: String,
+        }
+
+        let frames: Vec<_> = self
+            .frames
+            .borrow()
+            .iter()
+            .map(|c| JsonFrame {
+                direction: c.direction,
+                at_us: c.at.as_micros(),
+                frame: c.frame.as_ref().map(|f| format!("{f:?}")),
+                header: hex(&c.header),
+                payload: hex(&c.payload),
+            })
+            .collect();
+        serde_json::to_string_pretty(&frames).expect("frames serialize")
+    }
+
+    pub fn to_bin(&self) -> Vec<u8> {
+        let frames = self.frames.borrow();
+        let mut out = BIN_MAGIC.to_vec();
+        for c in frames.iter() {
+            out.push(match c.direction {
+                Direction::Sent => 0,
+                Direction::Received => 1,
+            });
+            out.extend_from_slice(&(c.at.as_micros() as u64).to_be_bytes());
+            out.extend_from_slice(&((c.header.len() + c.payload.len()) as u32).to_be_bytes());
+            out.extend_from_slice(&c.header);
+            out.extend_from_slice(&c.payload);
+        }
+        out
+    }
+}
+
+/// Reads back a dump made by [Recorder::to_bin]. Records that hold exactly
+/// one frame come back with [Captured::frame] set, even if they were sent
+/// with [crate::Conn::send]; the others come back as raw bytes.
+pub fn read_bin(bin: &[u8]) -> eyre::Result<Vec<Captured>> {
+    let Some(mut rest) = bin.strip_prefix(&BIN_MAGIC[..]) else {
+        bail!("not an httpwg frame dump: it doesn't start with {BIN_MAGIC:?}");
+    };
+
+    let mut frames = vec![];
+    while !rest.is_empty() {
+        if rest.len() < 13 {
+            bail!("truncated record header after {} records", frames.len());
+        }
+        let direction = match rest[0] {
+            0 => Direction::Sent,
+            1 => Direction::Received,
+            other => bail!("unknown direction {other} in record {}", frames.len()),
+        };
+        let at = Duration::from_micros(u64::from_be_bytes(rest[1..9].try_into().unwrap()));
+        let len = u32::from_be_bytes(rest[9..13].try_into().unwrap()) as usize;
+        let Some(bytes) = rest.get(13..13 + len) else {
+            bail!("truncated record {}: wanted {len} bytes", frames.len());
+        };
+        rest = &rest[13 + len..];
+
+        frames.push(match parse_frame_header(bytes) {
+            Some(frame) if frame.len as usize == bytes.len() - 9 => Captured {
+                direction,
+                at,
+                frame: Some(frame),
+                header: bytes[..9].to_vec(),
+                payload: bytes[9..].to_vec(),
+            },
+            _ => Captured {
+                direction,
+                at,
+                frame: None,
+                header: vec![],
+                payload: bytes.to_vec(),
+            },
+        });
+    }
+    Ok(frames)
+}
+
+/// Renders `frames` as a trace, one line per frame: when it went over the
+/// wire, which way (`>` for sent, `<` for received) and what it was, with
+/// the payloads of SETTINGS, GOAWAY, RST_STREAM, WINDOW_UPDATE and PING
+/// frames decoded. Raw bytes are split into the connection preface and
+/// frames where they parse as such.
+pub fn render_trace(frames: &[Captured]) -> String {
+    let mut out = String::new();
+    for c in frames {
+        let arrow = match c.direction {
+            Direction::Sent => '>',
+            Direction::Received => '<',
+        };
+        let at = c.at.as_secs_f64() * 1000.0;
+        let lines = match c.frame {
+            Some(frame) => vec![describe_frame(&frame, &c.payload)],
+            None => describe_raw(&c.payload),
+        };
+        for line in lines {
+            // writing to a String can't fail
+            _ = writeln!(out, "{at:>10.3}ms {arrow} {line}");
+        }
+    }
+    out
+}
+
+/// Splits raw bytes into the connection preface, frames, and whatever's
+/// left that doesn't parse as either
+fn describe_raw(mut bytes: &[u8]) -> Vec<String> {
+    let mut lines = vec![];
+    if let Some(rest) = bytes.strip_prefix(PREFACE) {
+        lines.push("connection preface".to_owned());
+        bytes = rest;
+    }
+    while let Some(frame) = parse_frame_header(bytes) {
+        let Some(payload) = bytes.get(9..9 + frame.len as usize) else {
+            break;
+        };
+        lines.push(describe_frame(&frame, payload));
+        bytes = &bytes[9 + payload.len()..];
+    }
+    if !bytes.is_empty() {
+        lines.push(format!(
+            "{} bytes that aren't a frame: {}",
+            bytes.len(),
+            hex_preview(bytes)
+        ));
+    }
+    lines
+}
+
+fn describe_frame(frame: &Frame, payload: &[u8]) -> String {
+    let decoded = match Payload::decode(frame, to_roll(payload)) {
+        Ok(Payload::Settings(settings)) if !settings.is_empty() => format!(" {settings:?}"),
+        Ok(Payload::GoAway(goaway)) => {
+            let mut s = format!(
+                " last_stream_id={}, error_code={:?}",
+                goaway.last_stream_id.0, goaway.error_code
+            );
+            if !goaway.additional_debug_data.is_empty() {
+                _ = write!(
+                    s,
+                    ", debug_data={:?}",
+                    String::from_utf8_lossy(&goaway.additional_debug_data[..])
+                );
+            }
+            s
+        }
+        Ok(Payload::RstStream(rst_stream)) => format!(" error_code={:?}", rst_stream.error_code),
+        Ok(Payload::WindowUpdate(update)) => format!(" increment={}", update.increment),
+        Ok(Payload::Ping(data)) => format!(" data={}", hex(&data)),
+        Ok(Payload::Settings(_)) => String::new(),
+        Ok(Payload::Other(_)) if payload.is_empty() => String::new(),
+        Ok(Payload::Other(_)) => format!(" payload={}", hex_preview(payload)),
+        Err(e) => format!(" ({e})"),
+    };
+    format!("{frame:?}{decoded}")
+}
+
+/// Parses the 9-byte frame header `bytes` start with, if they do
+fn parse_frame_header(bytes: &[u8]) -> Option<Frame> {
+    let header = bytes.get(..9)?;
+    Frame::parse(to_roll(header)).ok().map(|(_, frame)| frame)
+}
+
+fn to_roll(bytes: &[u8]) -> Roll {
+    RollMut::alloc()
+        .and_then(|mut buf| {
+            buf.put_to_roll(bytes.len(), |slice| {
+                slice.copy_from_slice(bytes);
+                Ok(())
+            })
+        })
+        .expect("allocating a buffer for a captured frame")
+}
+
+/// The first few bytes, in hex, so that big payloads don't drown the trace
+fn hex_preview(bytes: &[u8]) -> String {
+    const MAX: usize = 32;
+    if bytes.len() <= MAX {
+        hex(bytes)
+    } else {
+        format!("{}… ({} bytes)", hex(&bytes[..MAX]), bytes.len())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        // writing to a String can't fail
+        _ = write!(s, "{b:02x}");
+    }
+    s
+}
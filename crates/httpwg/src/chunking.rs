@@ -0,0 +1,117 @@
+//! Sending bytes in deliberately awkward writes, to exercise servers'
+//! incremental parsers: a frame that shows up one byte at a time, or with
+//! its header split in two, must be handled just like one that arrives in a
+//! single read.
+//!
+//! [crate::Conn::send_chunked] sends a buffer that way. Setting
+//! [crate::Config::chunking] makes every frame a connection writes go out
+//! that way, whatever the test.
+
+use std::{str::FromStr, time::Duration};
+
+use loona_h2::PREFACE;
+
+/// How to split outgoing bytes into writes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Chunking {
+    /// One byte per write
+    ByteByByte,
+
+    /// Writes of this many bytes, the last one possibly shorter
+    Fixed(usize),
+
+    /// Splits every frame in the middle of its 9-byte header, between its
+    /// header and its payload, and in the middle of its payload. Bytes that
+    /// don't parse as frames (apart from the connection preface, which is
+    /// split in two) go out in a single write.
+    MidFrameHeader,
+
+    /// Writes of `size` bytes, with a `pause` after each but the last one,
+    /// for servers that only parse what a single read gave them
+    Paused { size: usize, pause: Duration },
+}
+
+impl Chunking {
+    /// Where writes of `buf` should end, in increasing order: the last one is
+    /// always `buf.len()`, unless `buf` is empty
+    pub fn split_points(&self, buf: &[u8]) -> Vec<usize> {
+        let mut points = match self {
+            Chunking::ByteByByte => (1..=buf.len()).collect(),
+            Chunking::Fixed(size) | Chunking::Paused { size, .. } => {
+                let size = (*size).max(1);
+                (size..buf.len()).step_by(size).collect()
+            }
+            Chunking::MidFrameHeader => mid_frame_header_points(buf),
+        };
+        if !buf.is_empty() && points.last() != Some(&buf.len()) {
+            points.push(buf.len());
+        }
+        points
+    }
+
+    /// How long to wait between two writes
+    pub fn pause(&self) -> Option<Duration> {
+        match self {
+            Chunking::Paused { pause, .. } => Some(*pause),
+            _ => None,
+        }
+    }
+}
+
+fn mid_frame_header_points(buf: &[u8]) -> Vec<usize> {
+    let mut points = vec![];
+    let mut offset = 0;
+    if buf.starts_with(PREFACE) {
+        points.push(PREFACE.len() / 2);
+        points.push(PREFACE.len());
+        offset = PREFACE.len();
+    }
+
+    while let Some(header) = buf.get(offset..offset + 9) {
+        let len = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+        let end = offset + 9 + len;
+        if end > buf.len() {
+            break;
+        }
+        points.push(offset + 4);
+        points.push(offset + 9);
+        if len > 1 {
+            points.push(offset + 9 + len / 2);
+        }
+        points.push(end);
+        offset = end;
+    }
+    points.retain(|&p| p < buf.len());
+    points
+}
+
+/// Parses `byte`, `mid-header`, `<SIZE>` or `<SIZE>:<PAUSE_MS>`, as taken
+/// by `httpwg --chunking`
+impl FromStr for Chunking {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_size = |size: &str| -> eyre::Result<usize> {
+            match size.parse()? {
+                0 => Err(eyre::eyre!("chunk size must be at least 1")),
+                size => Ok(size),
+            }
+        };
+
+        Ok(match s {
+            "byte" => Chunking::ByteByByte,
+            "mid-header" => Chunking::MidFrameHeader,
+            _ => match s.split_once(':') {
+                Some((size, pause)) => Chunking::Paused {
+                    size: parse_size(size)?,
+                    pause: Duration::from_millis(pause.parse()?),
+                },
+                None => Chunking::Fixed(parse_size(s).map_err(|e| {
+                    eyre::eyre!(
+                        "expected byte, mid-header, <SIZE> or <SIZE>:<PAUSE_MS>, got {s:?} ({e})"
+                    )
+                })?),
+            },
+        })
+    }
+}
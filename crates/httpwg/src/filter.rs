@@ -0,0 +1,71 @@
+//! Picking which tests to run, when iterating on a single server bug and
+//! the whole suite takes too long.
+
+/// The environment variable the generated harness reads its filter from
+pub const FILTER_ENV_VAR: &str = "HTTPWG_FILTER";
+
+/// A comma-separated list of patterns. A test runs if any of them matches,
+/// or if there are none.
+///
+/// A test is identified by its path, e.g. `rfc9113/6.7/sends_ping_frame`,
+/// and by its id, e.g. `http2/6.7/1`. A pattern matches:
+///
+///   - a prefix of either that ends at a component, so `rfc9113`,
+///     `rfc9113/6` and `http2/6.7` all match the test above, but
+///     `rfc9113/6.7` doesn't match tests from section 6.70, and
+///     `http2/6.7/1` doesn't match `http2/6.7/10`
+///   - any part of the test's name, so `ping` matches the test above
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    patterns: Vec<String>,
+}
+
+impl Filter {
+    pub fn parse(s: &str) -> Self {
+        Self {
+            patterns: s
+                .split(',')
+                .map(str::trim)
+                .filter(|p| !p.is_empty())
+                .map(|p| p.trim_end_matches('/').to_owned())
+                .collect(),
+        }
+    }
+
+    /// Reads [FILTER_ENV_VAR]: when it's not set, every test runs
+    pub fn from_env() -> Self {
+        std::env::var(FILTER_ENV_VAR)
+            .map(|s| Self::parse(&s))
+            .unwrap_or_default()
+    }
+
+    /// Whether the test `id`, a.k.a. `suite/section/test`, should run, e.g.
+    /// `("http2/6.7/1", "rfc9113", "6.7", "sends_ping_frame")`
+    pub fn matches(&self, id: &str, suite: &str, section: &str, test: &str) -> bool {
+        if self.patterns.is_empty() {
+            return true;
+        }
+
+        let path = format!("{suite}/{section}/{test}");
+        self.patterns.iter().any(|pattern| {
+            if test.contains(pattern.as_str()) {
+                return true;
+            }
+            [path.as_str(), id]
+                .into_iter()
+                .any(|full| match full.strip_prefix(pattern.as_str()) {
+                    Some(rest) => rest.is_empty() || rest.starts_with(['/', '.']),
+                    None => false,
+                })
+        })
+    }
+}
+
+/// The first of the features a test `needs` that's `unsupported` by the
+/// server, if any: tests that need one don't run, cf. `httpwg_macros::tests`
+pub fn unsupported(unsupported: &[&str], needs: &[&'static str]) -> Option<&'static str> {
+    needs
+        .iter()
+        .find(|feature| unsupported.contains(feature))
+        .copied()
+}
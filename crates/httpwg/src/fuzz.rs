@@ -0,0 +1,280 @@
+//! Structured-random sequences of mostly-malformed frames: frame headers
+//! that describe their payload correctly, with random types, flags and stream
+//! IDs, and payloads that start out plausible and then get corrupted.
+//!
+//! Whatever the server makes of them, it must not hang, and once it sends a
+//! GOAWAY for an error, it must close the connection without sending
+//! anything else (RFC 9113, Section 5.4.1).
+//!
+//! Every sequence comes from a seed, which failures mention: setting
+//! [SEED_ENV_VAR] to it sends the exact same frames again.
+
+use buffet::IntoHalves;
+use loona_h2::{EncodedFrameType, Frame, FrameType, KnownErrorCode, StreamId};
+use tokio::time::Instant;
+
+use crate::{Conn, Ev, NextEvent, Payload};
+
+/// Set this to a seed to replay it, cf. [seed_from_env_or_time]
+pub const SEED_ENV_VAR: &str = "HTTPWG_FUZZ_SEED";
+
+/// How many frames [run] sends, unless told otherwise
+pub const DEFAULT_NUM_FRAMES: usize = 32;
+
+/// Payloads never grow past the protocol's initial SETTINGS_MAX_FRAME_SIZE:
+/// frames that are too large are tested elsewhere, and they'd get every
+/// sequence rejected for the same reason.
+const MAX_PAYLOAD_LEN: usize = 16384;
+
+/// What we ping the server with once we're done, to check it's still there
+const PING_PAYLOAD: &[u8; 8] = b"fuzzfuzz";
+
+/// SplitMix64: tiny, and unlike the generators of the `rand` crate,
+/// guaranteed to produce the same numbers for the same seed in every version
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    /// True once every `n` times, on average
+    pub fn one_in(&mut self, n: u64) -> bool {
+        self.below(n) == 0
+    }
+
+    pub fn byte(&mut self) -> u8 {
+        self.next_u64() as u8
+    }
+
+    pub fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.byte()).collect()
+    }
+}
+
+/// The seed in [SEED_ENV_VAR], or one derived from the current time
+pub fn seed_from_env_or_time() -> eyre::Result<u64> {
+    match std::env::var(SEED_ENV_VAR) {
+        Ok(seed) => seed
+            .parse()
+            .map_err(|e| eyre::eyre!("{SEED_ENV_VAR}={seed:?} isn't a valid seed: {e}")),
+        Err(_) => Ok(std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_nanos() as u64),
+    }
+}
+
+/// A frame, exactly as it'll be sent
+pub struct FuzzFrame {
+    pub frame: Frame,
+    pub payload: Vec<u8>,
+}
+
+/// The frames for `seed`. HEADERS, PUSH_PROMISE and CONTINUATION payloads
+/// start out as `block_fragment`, which should be a valid request header
+/// block, so that some of them get past HPACK decoding.
+pub fn frames(seed: u64, num_frames: usize, block_fragment: &[u8]) -> Vec<FuzzFrame> {
+    let mut rng = Rng::new(seed);
+    (0..num_frames)
+        .map(|_| random_frame(&mut rng, block_fragment))
+        .collect()
+}
+
+fn random_frame(rng: &mut Rng, block_fragment: &[u8]) -> FuzzFrame {
+    // 0x0 through 0x9 are the frame types RFC 9113 defines, anything above
+    // is an extension frame the server must ignore
+    let ty = if rng.one_in(11) {
+        0xa + rng.below(0xf6) as u8
+    } else {
+        rng.below(0xa) as u8
+    };
+
+    let mut payload = match ty {
+        // DATA
+        0x0 => {
+            let len = rng.below(64) as usize;
+            rng.bytes(len)
+        }
+        // HEADERS, CONTINUATION
+        0x1 | 0x9 => block_fragment.to_vec(),
+        // PRIORITY
+        0x2 => rng.bytes(5),
+        // RST_STREAM: mostly known error codes
+        0x3 => (rng.below(0x10) as u32).to_be_bytes().to_vec(),
+        // SETTINGS: mostly known identifiers
+        0x4 => (0..rng.below(4))
+            .flat_map(|_| {
+                let mut setting = (rng.below(8) as u16).to_be_bytes().to_vec();
+                setting.extend_from_slice(&(rng.next_u64() as u32).to_be_bytes());
+                setting
+            })
+            .collect(),
+        // PUSH_PROMISE: a promised stream ID, then a header block
+        0x5 => {
+            let mut payload = rng.bytes(4);
+            payload.extend_from_slice(block_fragment);
+            payload
+        }
+        // PING
+        0x6 => rng.bytes(8),
+        // GOAWAY: last stream ID, error code, maybe some debug data
+        0x7 => {
+            let len = 8 + rng.below(8) as usize;
+            rng.bytes(len)
+        }
+        // WINDOW_UPDATE
+        0x8 => rng.bytes(4),
+        _ => {
+            let len = rng.below(32) as usize;
+            rng.bytes(len)
+        }
+    };
+    if rng.one_in(2) {
+        corrupt(rng, &mut payload);
+    }
+
+    // mostly no flags, or all of them, which sets END_STREAM and
+    // END_HEADERS when they exist
+    let flags = match rng.below(4) {
+        0 => 0,
+        1 => 0xff,
+        _ => rng.byte(),
+    };
+
+    let stream_id = match rng.below(6) {
+        0 => StreamId::CONNECTION,
+        1 => StreamId(rng.below(8) as u32 * 2 + 2),
+        2 => StreamId(rng.next_u64() as u32 & 0x7fff_ffff),
+        // mostly client-initiated streams, a few of them, so that frames
+        // end up on the same streams
+        _ => StreamId(rng.below(8) as u32 * 2 + 1),
+    };
+
+    let mut frame = Frame::new(
+        FrameType::Unknown(EncodedFrameType { ty, flags }),
+        stream_id,
+    );
+    if rng.one_in(16) {
+        frame.reserved = 1;
+    }
+
+    FuzzFrame { frame, payload }
+}
+
+/// Flips bits, truncates, extends, or plain replaces `payload`
+fn corrupt(rng: &mut Rng, payload: &mut Vec<u8>) {
+    match rng.below(4) {
+        0 if !payload.is_empty() => {
+            for _ in 0..1 + rng.below(4) {
+                let i = rng.below(payload.len() as u64) as usize;
+                payload[i] ^= 1 << rng.below(8);
+            }
+        }
+        1 if !payload.is_empty() => {
+            let len = rng.below(payload.len() as u64) as usize;
+            payload.truncate(len);
+        }
+        2 => {
+            let len = 1 + rng.below(16) as usize;
+            payload.extend(rng.bytes(len));
+        }
+        _ => {
+            let len = rng.below(64) as usize;
+            *payload = rng.bytes(len);
+        }
+    }
+    payload.truncate(MAX_PAYLOAD_LEN);
+}
+
+/// Does the handshake, sends the `num_frames` frames for `seed`, then sends
+/// a PING. Passes if the server acknowledges it or closes the connection
+/// within [crate::Config::timeout], and if it sends nothing after a GOAWAY
+/// for an error. Errors mention the seed.
+pub async fn run<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+    seed: u64,
+    num_frames: usize,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    let block_fragment = conn.encode_headers(&conn.common_headers("POST"))?;
+    let frames = frames(seed, num_frames, &block_fragment[..]);
+
+    check(&mut conn, frames).await.map_err(|e| {
+        e.wrap_err(format!(
+            "fuzz seed {seed} (replay with {SEED_ENV_VAR}={seed})"
+        ))
+    })
+}
+
+async fn check<IO: IntoHalves>(conn: &mut Conn<IO>, frames: Vec<FuzzFrame>) -> eyre::Result<()> {
+    for FuzzFrame { frame, payload } in frames {
+        if conn.write_frame(frame, payload).await.is_err() {
+            // the server hung up on us: let's see whether it said goodbye
+            break;
+        }
+    }
+    // this fails too if the server hung up, that's fine
+    _ = conn.write_ping(false, &PING_PAYLOAD[..]).await;
+
+    let timeout = conn.config.timeout;
+    let deadline = Instant::now() + timeout;
+    let mut error_goaway: Option<Frame> = None;
+    loop {
+        let (frame, payload) = match conn.next_event_with_deadline(deadline).await {
+            NextEvent::Timeout { .. } => match error_goaway {
+                Some(goaway) => eyre::bail!(
+                    "server sent {goaway:?} for an error, but didn't close the connection within {timeout:?}"
+                ),
+                None => eyre::bail!(
+                    "server hung: no PING ack, and the connection is still open after {timeout:?}"
+                ),
+            },
+            NextEvent::Eof | NextEvent::Ev(Ev::Eof | Ev::IoError { .. }) => return Ok(()),
+            NextEvent::Ev(Ev::ProtocolViolation { reason }) => {
+                eyre::bail!("server violated the protocol: {reason}")
+            }
+            NextEvent::Ev(Ev::Frame { frame, payload }) => (frame, payload),
+            NextEvent::Ev(Ev::Raw { .. }) => unreachable!("raw mode is never on here"),
+            NextEvent::Ev(Ev::Headers { stream_id, .. }) => match error_goaway {
+                Some(goaway) => eyre::bail!(
+                    "server sent a field block on stream {stream_id} after {goaway:?} for an error"
+                ),
+                None => continue,
+            },
+        };
+
+        if let Some(goaway) = error_goaway {
+            eyre::bail!("server sent {frame:?} after {goaway:?} for an error");
+        }
+        match Payload::decode(&frame, payload)? {
+            Payload::GoAway(goaway) => {
+                if !matches!(
+                    KnownErrorCode::try_from(goaway.error_code),
+                    Ok(KnownErrorCode::NoError)
+                ) {
+                    error_goaway = Some(frame);
+                }
+            }
+            Payload::Ping(payload) if frame.is_ack() && &payload == PING_PAYLOAD => {
+                return Ok(());
+            }
+            _ => {}
+        }
+    }
+}
@@ -1,25 +1,162 @@
+use base64::Engine;
 use eyre::eyre;
-use rfc9113::DEFAULT_FRAME_SIZE;
-use std::{collections::VecDeque, future::Future, pin::Pin, rc::Rc, time::Duration};
+use rfc9113::{DEFAULT_FRAME_SIZE, DEFAULT_WINDOW_SIZE, MAX_WINDOW_SIZE};
+use std::{
+    cell::Cell,
+    collections::{HashMap, VecDeque},
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    str::FromStr,
+    time::Duration,
+};
 
 use buffet::{IntoHalves, Piece, PieceList, Roll, RollMut, WriteOwned};
+use capture::{Direction, Recorder};
+use chunking::Chunking;
 use enumflags2::{bitflags, BitFlags};
 use loona_h2::{
     enumflags2,
     nom::{self, Finish},
-    ContinuationFlags, DataFlags, ErrorCode, Frame, FrameType, GoAway, HeadersFlags, IntoPiece,
-    KnownErrorCode, PingFlags, PrioritySpec, RstStream, SettingPairs, Settings, SettingsFlags,
-    StreamId, WindowUpdate, PREFACE,
+    ContinuationFlags, DataFlags, EncodedFrameType, ErrorCode, Frame, FrameType, GoAway,
+    HeadersFlags, IntoPiece, KnownErrorCode, Padded, PingFlags, PrioritySpec, PriorityUpdate,
+    RstStream, Setting, SettingPairs, Settings, SettingsFlags, StreamId, WindowUpdate, PREFACE,
+    PRIORITY_UPDATE_FRAME_TYPE,
 };
+use preface::Preface;
 use tokio::time::Instant;
 use tracing::{debug, trace};
 
-use crate::rfc9113::default_settings;
-
+pub mod bench;
+pub mod capture;
+pub mod chunking;
+pub mod filter;
+pub mod fuzz;
+pub mod preface;
+pub mod registry;
+pub mod replay;
+pub mod rfc8441;
 pub mod rfc9113;
+pub mod rfc9218;
+pub mod stress;
+pub mod target;
+
+pub use registry::all_tests;
+
+#[cfg(feature = "tls")]
+pub mod tls;
+
+/// What `httpwg_macros::tests` needs, so that downstream crates don't have
+/// to depend on buffet and eyre themselves
+#[doc(hidden)]
+pub mod macros {
+    pub use buffet::IntoHalves;
+    pub use eyre::Result;
+}
 
 pub type BoxedTest<IO> = Box<dyn Fn(Conn<IO>) -> Pin<Box<dyn Future<Output = eyre::Result<()>>>>>;
 
+/// Fails `test` if it takes longer than `timeout`, for tests the catalog
+/// gives a timeout of their own, cf. `httpwg_macros::tests`
+pub async fn with_timeout(
+    timeout: Duration,
+    test: impl Future<Output = eyre::Result<()>>,
+) -> eyre::Result<()> {
+    tokio::time::timeout(timeout, test)
+        .await
+        .map_err(|_| eyre!("test timed out after {timeout:?}"))?
+}
+
+/// Opens a fresh connection to the server under test, for tests that need
+/// more than one, cf. [Conn::with_connector]
+pub type Connector<IO> = Rc<dyn Fn() -> Pin<Box<dyn Future<Output = eyre::Result<IO>>>>>;
+
+/// The tests of an RFC, as listed by [all_tests]
+pub struct Suite<IO: IntoHalves> {
+    /// e.g. "RFC 9113"
+    pub rfc: &'static str,
+
+    pub groups: Vec<Group<IO>>,
+}
+
+/// The tests of a top-level section of an RFC, as listed by [all_tests]
+pub struct Group<IO: IntoHalves> {
+    /// e.g. "6. frame definitions"
+    pub title: &'static str,
+
+    pub tests: Vec<Test<IO>>,
+}
+
+/// A test, as listed by [all_tests] (or in the catalog generated by
+/// `httpwg_macros::gen_catalog`)
+pub struct Test<IO: IntoHalves> {
+    /// e.g. "sends ping frame"
+    pub name: &'static str,
+
+    /// What to refer to the test by, e.g. "http2/6.7/1": unlike its name or
+    /// the module it's in, that never changes, cf. httpwg-gen's catalog
+    pub id: &'static str,
+
+    /// How strongly the RFC words what the test checks
+    pub requirement: Requirement,
+
+    /// The section of the RFC it checks, e.g. "6.5.2"
+    pub section: &'static str,
+
+    /// What the RFC says there, as quoted in the test's docs
+    pub citation: &'static str,
+
+    pub run: BoxedTest<IO>,
+}
+
+/// A section of an RFC, as listed by `httpwg_macros::gen_sections`: every
+/// one the suite knows of, tested or not, in the order the RFC has them
+#[derive(Debug, Clone, Copy)]
+pub struct Section {
+    /// e.g. "RFC 9113", as in the catalog
+    pub rfc: &'static str,
+
+    /// e.g. "6.5.2"
+    pub number: &'static str,
+
+    /// e.g. "Defined Settings"
+    pub title: &'static str,
+
+    /// What the tests of this section check ([Test::citation]), in order
+    pub requirements: &'static [&'static str],
+}
+
+/// Whether a test checks a MUST (or MUST NOT), or merely a SHOULD: servers
+/// adopting the suite may want to start with the former.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Requirement {
+    Should,
+    Must,
+}
+
+impl Requirement {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Requirement::Should => "should",
+            Requirement::Must => "must",
+        }
+    }
+}
+
+impl FromStr for Requirement {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "should" => Ok(Requirement::Should),
+            "must" => Ok(Requirement::Must),
+            _ => Err(eyre!(
+                "unknown requirement level {s:?}, expected must or should"
+            )),
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct Headers {
     values: VecDeque<(Piece, Piece)>,
@@ -34,6 +171,29 @@ impl IntoIterator for Headers {
     }
 }
 
+/// Builds headers from (name, value) pairs, in order, e.g. to pass to
+/// [Conn::encode_and_write_headers]:
+///
+/// ```
+/// use httpwg::Headers;
+///
+/// let headers: Headers = [(":method", "GET"), (":path", "/")].into_iter().collect();
+/// assert_eq!(headers.len(), 2);
+/// ```
+impl<K, V> FromIterator<(K, V)> for Headers
+where
+    K: Into<Piece>,
+    V: Into<Piece>,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut headers = Headers::default();
+        for (k, v) in iter {
+            headers.append(k, v);
+        }
+        headers
+    }
+}
+
 impl Headers {
     /// Appends a key-value pair to the end of the headers.
     pub fn append(&mut self, key: impl Into<Piece>, value: impl Into<Piece>) {
@@ -82,12 +242,76 @@ impl Headers {
         self.values.extend(other);
     }
 
+    /// Gets the first value of `name`, if it's there and valid UTF-8, e.g.
+    /// `headers.get_str("content-type")`
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|(k, _)| &k[..] == name.as_bytes())
+            .and_then(|(_, v)| std::str::from_utf8(&v[..]).ok())
+    }
+
+    /// The `:status` pseudo-header of a response. Errors out if it's missing
+    /// or isn't a three-digit status code.
+    pub fn status(&self) -> eyre::Result<u16> {
+        let Some(status) = self.get_first(&":status".into()) else {
+            return Err(eyre!("response has no :status pseudo-header"));
+        };
+        match std::str::from_utf8(&status[..]) {
+            Ok(s) if s.len() == 3 && s.bytes().all(|b| b.is_ascii_digit()) => Ok(s.parse()?),
+            _ => Err(eyre!(
+                "response has an invalid :status pseudo-header: {:?}",
+                String::from_utf8_lossy(&status[..])
+            )),
+        }
+    }
+
+    /// The `content-length` header, if there is one. Errors out if it isn't
+    /// a number, or if there are several that disagree.
+    pub fn content_length(&self) -> eyre::Result<Option<u64>> {
+        let mut content_length = None;
+        for (k, v) in self.values.iter() {
+            if &k[..] != b"content-length" {
+                continue;
+            }
+            let value: u64 = std::str::from_utf8(&v[..])
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .ok_or_else(|| {
+                    eyre!(
+                        "invalid content-length: {:?}",
+                        String::from_utf8_lossy(&v[..])
+                    )
+                })?;
+            if content_length.is_some_and(|cl| cl != value) {
+                return Err(eyre!("conflicting content-length headers"));
+            }
+            content_length = Some(value);
+        }
+        Ok(content_length)
+    }
+
     /// Remove and return all key-value pairs matching the specified key
     pub fn remove(&mut self, key: &Piece) {
         self.values.retain(|(k, _)| k != key);
     }
 }
 
+/// Field lines, one per line, as (lossy) text
+impl std::fmt::Debug for Headers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.values.iter().map(|(k, v)| {
+                format!(
+                    "{}: {}",
+                    String::from_utf8_lossy(&k[..]),
+                    String::from_utf8_lossy(&v[..])
+                )
+            }))
+            .finish()
+    }
+}
+
 pub struct Conn<IO: IntoHalves> {
     w: <IO as IntoHalves>::Write,
     scratch: RollMut,
@@ -97,15 +321,234 @@ pub struct Conn<IO: IntoHalves> {
     hpack_dec: loona_hpack::Decoder<'static>,
     /// the peer's settings
     pub settings: Settings,
+    /// the flow-control windows we granted the peer
+    granted: GrantedWindows,
+    /// if [Config::record_frames] is set
+    recorder: Option<Recorder>,
+    /// the highest client-initiated stream we sent HEADERS on, cf.
+    /// [Conn::next_stream_id]
+    last_stream_id: StreamId,
+    /// cf. [Conn::connect_another]
+    connector: Option<Connector<IO>>,
+    /// cf. [Conn::assemble_headers], shared with the receive loop
+    assemble_headers: Rc<Cell<bool>>,
+    /// cf. [Conn::raw_mode], shared with the receive loop
+    raw_mode: Rc<Cell<bool>>,
+    /// cf. [Conn::shutdown_write]
+    write_shut_down: bool,
 
     // this field exists for the `Drop` impl
     #[allow(dead_code)]
     cancel_tx: tokio::sync::oneshot::Sender<()>,
 }
 
+/// Flow-control windows we granted the peer, cf. [Conn::granted_window]
+struct GrantedWindows {
+    connection: i64,
+    /// our SETTINGS_INITIAL_WINDOW_SIZE
+    initial: i64,
+    /// per-stream WINDOW_UPDATE increments minus DATA received, on top of
+    /// `initial`, so that changing the initial window size applies to
+    /// streams that are already open
+    streams: HashMap<StreamId, i64>,
+}
+
+impl Default for GrantedWindows {
+    fn default() -> Self {
+        // the protocol's initial window size, until we say otherwise
+        Self {
+            connection: 65535,
+            initial: 65535,
+            streams: Default::default(),
+        }
+    }
+}
+
+impl GrantedWindows {
+    fn grant(&mut self, stream_id: StreamId, increment: i64) {
+        if stream_id == StreamId::CONNECTION {
+            self.connection += increment;
+        } else {
+            *self.streams.entry(stream_id).or_default() += increment;
+        }
+    }
+
+    fn consume(&mut self, stream_id: StreamId, len: i64) {
+        self.connection -= len;
+        if stream_id != StreamId::CONNECTION {
+            *self.streams.entry(stream_id).or_default() -= len;
+        }
+    }
+}
+
 pub enum Ev {
-    Frame { frame: Frame, payload: Roll },
-    IoError { error: std::io::Error },
+    Frame {
+        frame: Frame,
+        payload: Roll,
+    },
+
+    /// A HEADERS frame and the CONTINUATION frames that followed it, with
+    /// their padding and priority fields stripped, cf.
+    /// [Conn::assemble_headers]. Stands in for all those frames.
+    Headers {
+        stream_id: StreamId,
+        block: Piece,
+        end_stream: bool,
+    },
+
+    IoError {
+        error: std::io::Error,
+    },
+
+    /// The peer sent something that doesn't parse as frames, e.g. it hung
+    /// up halfway through one. Nothing is read after that.
+    ProtocolViolation {
+        reason: String,
+    },
+
+    /// Bytes the peer sent, as they came in, without parsing them as
+    /// frames: only handed out while [Conn::raw_mode] is on
+    Raw {
+        bytes: Roll,
+    },
+
+    /// The peer hung up, between frames
+    Eof,
+}
+
+/// The field block the receive loop is putting together, cf.
+/// [Conn::assemble_headers]
+struct HeaderBlockAssembler {
+    max_size: usize,
+    pending: Option<PendingHeaderBlock>,
+}
+
+struct PendingHeaderBlock {
+    stream_id: StreamId,
+    end_stream: bool,
+    block: Vec<u8>,
+}
+
+impl HeaderBlockAssembler {
+    fn new(max_size: usize) -> Self {
+        Self {
+            max_size,
+            pending: None,
+        }
+    }
+
+    /// Returns the event `frame` makes for, if any yet, or how the peer
+    /// violated the protocol. HEADERS frames only start a field block if
+    /// `enabled`, but one that's started gets finished either way.
+    fn feed(&mut self, enabled: bool, frame: Frame, payload: Roll) -> Result<Option<Ev>, String> {
+        let (stream_id, end_stream, mut block) = match self.pending.take() {
+            Some(pending) => {
+                if !matches!(frame.frame_type, FrameType::Continuation(_))
+                    || frame.stream_id != pending.stream_id
+                {
+                    return Err(format!(
+                        "expected CONTINUATION frame on stream {}, got {frame:?}",
+                        pending.stream_id
+                    ));
+                }
+                (pending.stream_id, pending.end_stream, pending.block)
+            }
+            None => {
+                if !enabled || !matches!(frame.frame_type, FrameType::Headers(_)) {
+                    return Ok(Some(Ev::Frame { frame, payload }));
+                }
+                let fragment =
+                    header_block_fragment(&frame, &payload[..]).map_err(|e| e.to_string())?;
+                (frame.stream_id, frame.is_end_stream(), fragment.to_vec())
+            }
+        };
+        if matches!(frame.frame_type, FrameType::Continuation(_)) {
+            block.extend_from_slice(&payload[..]);
+        }
+
+        if block.len() > self.max_size {
+            return Err(format!(
+                "field block on stream {stream_id} is larger than {} bytes",
+                self.max_size
+            ));
+        }
+        if !frame.is_end_headers() {
+            self.pending = Some(PendingHeaderBlock {
+                stream_id,
+                end_stream,
+                block,
+            });
+            return Ok(None);
+        }
+        Ok(Some(Ev::Headers {
+            stream_id,
+            block: block.into(),
+            end_stream,
+        }))
+    }
+}
+
+/// The part of a HEADERS frame's payload that's a field block fragment:
+/// without padding or priority fields
+fn header_block_fragment<'a>(frame: &Frame, payload: &'a [u8]) -> eyre::Result<&'a [u8]> {
+    let FrameType::Headers(flags) = frame.frame_type else {
+        return Err(eyre!("expected a HEADERS frame, got {frame:?}"));
+    };
+    let mut fragment = payload;
+    if flags.contains(HeadersFlags::Padded) {
+        let Some((&pad_len, rest)) = fragment.split_first() else {
+            return Err(eyre!("padded HEADERS frame without a pad length"));
+        };
+        let Some(len) = rest.len().checked_sub(pad_len as usize) else {
+            return Err(eyre!("HEADERS frame padding exceeds its payload"));
+        };
+        fragment = &rest[..len];
+    }
+    if flags.contains(HeadersFlags::Priority) {
+        fragment = fragment
+            .get(5..)
+            .ok_or_else(|| eyre!("HEADERS frame too short for its priority fields"))?;
+    }
+    Ok(fragment)
+}
+
+/// What [Conn::next_event] got
+pub enum NextEvent {
+    Ev(Ev),
+
+    /// The peer hung up
+    Eof,
+
+    /// Nothing happened for [Config::timeout]
+    Timeout {
+        waited: Duration,
+    },
+}
+
+/// What [Conn::next_decoded_event] got
+enum DecodedEvent {
+    /// A field block, decoded
+    Headers {
+        stream_id: StreamId,
+        headers: Headers,
+        end_stream: bool,
+    },
+
+    /// Anything else: never [Ev::Headers], nor a HEADERS frame
+    Other(NextEvent),
+}
+
+impl NextEvent {
+    /// Returns the event, or an error saying why there's none
+    pub fn into_ev(self) -> eyre::Result<Ev> {
+        match self {
+            NextEvent::Ev(ev) => Ok(ev),
+            NextEvent::Eof => Err(eyre!("Wanted an event, peer hung up")),
+            NextEvent::Timeout { waited } => {
+                Err(eyre!("Wanted an event, timed out after {waited:?}"))
+            }
+        }
+    }
 }
 
 pub enum FrameWaitOutcome {
@@ -124,31 +567,305 @@ pub enum FrameWaitOutcome {
         last_frame: Option<Frame>,
         error: std::io::Error,
     },
+    ProtocolViolation {
+        wanted: BitFlags<FrameT>,
+        last_frame: Option<Frame>,
+        reason: String,
+    },
 }
 
 impl FrameWaitOutcome {
     pub fn unwrap(self) -> (Frame, Roll) {
+        match self.into_result() {
+            Ok(res) => res,
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    /// The frame we waited for, or an error saying why we didn't get it
+    pub fn into_result(self) -> eyre::Result<(Frame, Roll)> {
         match self {
-            FrameWaitOutcome::Success(frame, payload) => (frame, payload),
+            FrameWaitOutcome::Success(frame, payload) => Ok((frame, payload)),
             FrameWaitOutcome::Timeout {
                 wanted,
                 last_frame,
                 waited,
-            } => {
-                panic!(
-                    "Wanted ({wanted:?}), timed out after {waited:?}. Last frame: {last_frame:?}"
-                );
-            }
-            FrameWaitOutcome::Eof { wanted, last_frame } => {
-                panic!("Wanted ({wanted:?}), peer hung up. Last frame: {last_frame:?}");
-            }
+            } => Err(eyre!(
+                "Wanted ({wanted:?}), timed out after {waited:?}. Last frame: {last_frame:?}"
+            )),
+            FrameWaitOutcome::Eof { wanted, last_frame } => Err(eyre!(
+                "Wanted ({wanted:?}), peer hung up. Last frame: {last_frame:?}"
+            )),
             FrameWaitOutcome::IoError {
                 wanted,
                 last_frame,
                 error,
-            } => {
-                panic!("Wanted ({wanted:?}), got I/O error {error}. Last frame: {last_frame:?}")
+            } => Err(eyre!(
+                "Wanted ({wanted:?}), got I/O error {error}. Last frame: {last_frame:?}"
+            )),
+            FrameWaitOutcome::ProtocolViolation {
+                wanted,
+                last_frame,
+                reason,
+            } => Err(eyre!(
+                "Wanted ({wanted:?}), peer violated the protocol: {reason}. Last frame: {last_frame:?}"
+            )),
+        }
+    }
+}
+
+/// A frame payload, decoded according to the frame's type
+pub enum Payload {
+    /// The settings in a SETTINGS frame, in order. Unknown ones are left out.
+    Settings(Vec<(Setting, u32)>),
+    GoAway(GoAway),
+    RstStream(RstStream),
+    WindowUpdate(WindowUpdate),
+    Ping([u8; 8]),
+
+    /// Frames whose payload isn't decoded, e.g. DATA or HEADERS, as-is
+    Other(Roll),
+}
+
+impl Payload {
+    /// Decodes `payload` as whatever `frame` says it is. Errors out if it's
+    /// malformed, e.g. a PING payload that isn't 8 bytes long.
+    pub fn decode(frame: &Frame, payload: Roll) -> eyre::Result<Self> {
+        let malformed = |what: &str| eyre!("malformed {what} payload ({} bytes)", payload.len());
+
+        Ok(match frame.frame_type {
+            FrameType::Settings(_) => {
+                if payload.len() % 6 != 0 {
+                    return Err(malformed("SETTINGS"));
+                }
+                let mut settings = vec![];
+                Settings::parse(&payload[..], |k, v| {
+                    settings.push((k, v));
+                    Ok::<_, std::convert::Infallible>(())
+                })
+                .unwrap();
+                Payload::Settings(settings)
             }
+            FrameType::GoAway if payload.len() >= 8 => {
+                Payload::GoAway(GoAway::parse(payload).finish().unwrap().1)
+            }
+            FrameType::GoAway => return Err(malformed("GOAWAY")),
+            FrameType::RstStream if payload.len() == 4 => {
+                Payload::RstStream(RstStream::parse(payload).finish().unwrap().1)
+            }
+            FrameType::RstStream => return Err(malformed("RST_STREAM")),
+            FrameType::WindowUpdate if payload.len() == 4 => {
+                Payload::WindowUpdate(WindowUpdate::parse(payload).finish().unwrap().1)
+            }
+            FrameType::WindowUpdate => return Err(malformed("WINDOW_UPDATE")),
+            FrameType::Ping(_) => {
+                Payload::Ping(payload[..].try_into().map_err(|_| malformed("PING"))?)
+            }
+            _ => Payload::Other(payload),
+        })
+    }
+
+    /// The error code of a GOAWAY or RST_STREAM frame
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        match self {
+            Payload::GoAway(goaway) => Some(goaway.error_code),
+            Payload::RstStream(rst_stream) => Some(rst_stream.error_code),
+            _ => None,
+        }
+    }
+
+    /// Same as [Payload::error_code], if it's a code we know, e.g. to
+    /// `assert_eq!(payload.error_c(), Some(ErrorC::ProtocolError))`
+    pub fn error_c(&self) -> Option<ErrorC> {
+        KnownErrorCode::try_from(self.error_code()?)
+            .ok()
+            .map(Into::into)
+    }
+
+    /// The additional debug data of a GOAWAY frame, possibly empty
+    pub fn debug_data(&self) -> Option<&[u8]> {
+        match self {
+            Payload::GoAway(goaway) => Some(&goaway.additional_debug_data[..]),
+            _ => None,
+        }
+    }
+
+    /// Same as [Payload::debug_data], as (lossy) text
+    pub fn debug_data_str(&self) -> Option<String> {
+        self.debug_data()
+            .map(|data| String::from_utf8_lossy(data).into_owned())
+    }
+
+    /// Errors out unless this is a GOAWAY frame whose debug data contains
+    /// `needle`, e.g. to check that a server says why it closed the
+    /// connection, as RFC 9113, Section 6.8 lets it
+    pub fn assert_debug_contains(&self, needle: &str) -> eyre::Result<()> {
+        let Some(debug_data) = self.debug_data_str() else {
+            return Err(eyre!(
+                "expected a GOAWAY frame with debug data containing {needle:?}"
+            ));
+        };
+        eyre::ensure!(
+            debug_data.contains(needle),
+            "expected GOAWAY debug data containing {needle:?}, got {debug_data:?}"
+        );
+        Ok(())
+    }
+}
+
+/// Responses on any number of streams, as read by [Conn::read_responses]
+#[derive(Default)]
+pub struct Responses {
+    pub streams: HashMap<StreamId, StreamResponse>,
+
+    /// The last GOAWAY frame the peer sent, if any: a server shutting down
+    /// gracefully may send a second one, with a lower last stream ID.
+    pub goaway: Option<GoAway>,
+
+    /// Whether the peer hung up
+    pub closed: bool,
+
+    /// How much the peer grew the connection's flow-control window by, with
+    /// WINDOW_UPDATE frames, for callers keeping track of what they may send
+    pub connection_window_increments: u64,
+}
+
+impl Responses {
+    /// Whether `stream_id` got a final response, up to END_STREAM
+    pub fn completed(&self, stream_id: StreamId) -> bool {
+        self.streams
+            .get(&stream_id)
+            .is_some_and(|r| r.status.is_some() && matches!(r.end, Some(StreamEnd::EndStream)))
+    }
+
+    /// Whether `stream_id` ended, either with END_STREAM or RST_STREAM
+    pub fn ended(&self, stream_id: StreamId) -> bool {
+        self.streams
+            .get(&stream_id)
+            .is_some_and(|r| r.end.is_some())
+    }
+
+    /// The error code `stream_id` got reset with, if it did
+    pub fn reset_code(&self, stream_id: StreamId) -> Option<ErrorCode> {
+        match self.streams.get(&stream_id)?.end {
+            Some(StreamEnd::Reset(error_code)) => Some(error_code),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct StreamResponse {
+    /// The final status code, once the response headers are in: interim
+    /// (1xx) responses don't count
+    pub status: Option<u16>,
+
+    /// The fields of the final response, pseudo-headers included
+    pub headers: Option<Headers>,
+
+    pub end: Option<StreamEnd>,
+}
+
+#[derive(Debug)]
+pub enum StreamEnd {
+    /// The peer sent END_STREAM
+    EndStream,
+
+    /// The peer reset the stream
+    Reset(ErrorCode),
+}
+
+/// A request for [Conn::send_request]: `GET` for [Config::path] unless told
+/// otherwise, with the pseudo-headers filled in from [Config]
+pub struct Request {
+    pub method: &'static str,
+    /// Defaults to [Config::path]
+    pub path: Option<String>,
+    /// Regular fields, sent after the pseudo-headers
+    pub headers: Headers,
+    /// Sent in as many DATA frames as the peer's SETTINGS_MAX_FRAME_SIZE
+    /// calls for, or not at all if empty
+    pub body: Vec<u8>,
+    /// Sent in a HEADERS frame after the body, if any
+    pub trailers: Headers,
+}
+
+impl Request {
+    pub fn new(method: &'static str) -> Self {
+        Self {
+            method,
+            path: None,
+            headers: Default::default(),
+            body: Default::default(),
+            trailers: Default::default(),
+        }
+    }
+
+    pub fn get() -> Self {
+        Self::new("GET")
+    }
+
+    pub fn post(body: impl Into<Vec<u8>>) -> Self {
+        Self::new("POST").body(body)
+    }
+
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn header(mut self, key: impl Into<Piece>, value: impl Into<Piece>) -> Self {
+        self.headers.append(key, value);
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    pub fn trailer(mut self, key: impl Into<Piece>, value: impl Into<Piece>) -> Self {
+        self.trailers.append(key, value);
+        self
+    }
+}
+
+/// A request [Conn::send_request] sent, whose response is still to be read
+/// with [Conn::read_response]
+#[derive(Debug, Clone, Copy)]
+#[must_use = "the response should be read with Conn::read_response"]
+pub struct PendingResponse {
+    pub stream_id: StreamId,
+}
+
+/// A complete response, as read by [Conn::read_response]
+#[derive(Debug)]
+pub struct Response {
+    /// The final status code: interim (1xx) responses are skipped
+    pub status: u16,
+    /// All fields of the final response, pseudo-headers included
+    pub headers: Headers,
+    pub body: Vec<u8>,
+    /// The trailer section, if the response ended with one
+    pub trailers: Option<Headers>,
+}
+
+impl Response {
+    /// The first value of header `name`, cf. [Headers::get_str]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get_str(name)
+    }
+
+    /// Checks that the body is as long as the `content-length` header says,
+    /// if there is one. Don't call this for responses that are defined to
+    /// have no content, e.g. to HEAD requests, or with a 204 or 304 status.
+    pub fn verify_content_length(&self) -> eyre::Result<()> {
+        match self.headers.content_length()? {
+            Some(len) if len != self.body.len() as u64 => Err(eyre!(
+                "content-length says {len} bytes, but the body is {} bytes long",
+                self.body.len()
+            )),
+            _ => Ok(()),
         }
     }
 }
@@ -239,23 +956,188 @@ impl From<KnownErrorCode> for ErrorC {
 }
 
 impl<IO: IntoHalves> Conn<IO> {
+    /// Plays the client: call [Conn::handshake] next
     pub fn new(config: Rc<Config>, io: IO) -> Self {
+        let (r, w) = io.into_halves();
+        Self::from_halves(config, r, w, RollMut::alloc().unwrap())
+    }
+
+    /// Plays the server, to test HTTP/2 clients: reads the client connection
+    /// preface and the client's SETTINGS frame, then sends ours, and
+    /// acknowledges theirs. Whatever the client sends next is left for the
+    /// caller to read.
+    pub async fn accept(config: Rc<Config>, io: IO) -> eyre::Result<Self> {
         let (mut r, w) = io.into_halves();
 
+        let mut res_buf = RollMut::alloc()?;
+        let deadline = Instant::now() + config.timeout;
+        while res_buf.len() < PREFACE.len() {
+            res_buf.reserve()?;
+            let res;
+            (res, res_buf) = tokio::time::timeout_at(deadline, res_buf.read_into(16384, &mut r))
+                .await
+                .map_err(|_| eyre!("timed out waiting for the client connection preface"))?;
+            if res? == 0 {
+                eyre::bail!("client hung up before sending the whole connection preface");
+            }
+        }
+        let preface = res_buf.filled().slice(..PREFACE.len());
+        if preface[..] != PREFACE[..] {
+            eyre::bail!("invalid client connection preface: {:?}", &preface[..]);
+        }
+        res_buf.skip(PREFACE.len());
+
+        let mut conn = Self::from_halves(config, r, w, res_buf);
+
+        let settings = conn.config.settings();
+        conn.write_settings(&settings[..]).await?;
+        conn.read_and_ack_peer_settings("client").await?;
+
+        Ok(conn)
+    }
+
+    /// Plays a client that starts out speaking HTTP/1.1, cf. RFC 7540,
+    /// Section 3.2: sends a GET request for [Config::path] that asks to
+    /// upgrade to h2c, with our settings in `HTTP2-Settings`. Once the server
+    /// agrees with a 101 response, this sends the client connection preface,
+    /// and acknowledges the server's SETTINGS frame.
+    ///
+    /// The response to the upgrade request comes on stream 1, which is
+    /// half-closed (local): further requests start at stream 3.
+    pub async fn upgrade(config: Rc<Config>, io: IO) -> eyre::Result<Self> {
+        let (mut r, mut w) = io.into_halves();
+
+        let settings = config.settings();
+        let payload = SettingPairs::from(&settings[..]).into_piece(&mut RollMut::alloc()?)?;
+        let req = format!(
+            "GET {path} HTTP/1.1\r\n\
+             host: {authority}\r\n\
+             connection: Upgrade, HTTP2-Settings\r\n\
+             upgrade: h2c\r\n\
+             http2-settings: {payload}\r\n\
+             \r\n",
+            path = config.path,
+            authority = config.authority(),
+            payload = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(&payload[..]),
+        );
+        w.write_all_owned(req.into_bytes()).await?;
+
+        let mut res_buf = RollMut::alloc()?;
+        let deadline = Instant::now() + config.timeout;
+        let head_len = loop {
+            let mut headers = [httparse::EMPTY_HEADER; 32];
+            let mut res = httparse::Response::new(&mut headers);
+            let filled = res_buf.filled();
+            if let httparse::Status::Complete(head_len) = res.parse(&filled[..])? {
+                if res.code != Some(101) {
+                    eyre::bail!(
+                        "server didn't switch protocols: {:?} {:?}",
+                        res.code,
+                        res.reason
+                    );
+                }
+                let upgrade = res
+                    .headers
+                    .iter()
+                    .find(|h| h.name.eq_ignore_ascii_case("upgrade"))
+                    .map(|h| h.value);
+                if !upgrade.is_some_and(|v| v.eq_ignore_ascii_case(b"h2c")) {
+                    eyre::bail!(
+                        "server switched protocols, but not to h2c: {:?}",
+                        upgrade.map(String::from_utf8_lossy)
+                    );
+                }
+                break head_len;
+            }
+
+            res_buf.reserve()?;
+            let res;
+            (res, res_buf) = tokio::time::timeout_at(deadline, res_buf.read_into(16384, &mut r))
+                .await
+                .map_err(|_| eyre!("timed out waiting for the response to the upgrade request"))?;
+            if res? == 0 {
+                eyre::bail!("server hung up before responding to the upgrade request");
+            }
+        };
+        res_buf.skip(head_len);
+
+        let mut conn = Self::from_halves(config, r, w, res_buf);
+
+        conn.send(PREFACE).await?;
+        conn.write_settings(&settings[..]).await?;
+        conn.read_and_ack_peer_settings("server").await?;
+
+        Ok(conn)
+    }
+
+    /// Reads the SETTINGS frame that must open the peer's connection preface,
+    /// and acknowledges it
+    async fn read_and_ack_peer_settings(&mut self, peer: &str) -> eyre::Result<()> {
+        let (frame, payload) = match self.next_event().await.into_ev()? {
+            Ev::Frame { frame, payload } => (frame, payload),
+            Ev::IoError { error } => return Err(error.into()),
+            Ev::ProtocolViolation { reason } => {
+                eyre::bail!("{peer} violated the protocol during the handshake: {reason}")
+            }
+            Ev::Eof => eyre::bail!("{peer} hung up during the handshake"),
+            Ev::Headers { stream_id, .. } => {
+                eyre::bail!("{peer} should send their settings first thing, got a field block on stream {stream_id}")
+            }
+            Ev::Raw { .. } => eyre::bail!("raw mode is on, no settings to read"),
+        };
+        if !matches!(frame.frame_type, FrameType::Settings(_)) || frame.is_ack() {
+            eyre::bail!("{peer} should send their settings first thing, got {frame:?}");
+        }
+        Settings::parse(&payload[..], |k, v| self.settings.apply(k, v))?;
+
+        self.write_frame(
+            Frame::new(
+                FrameType::Settings(SettingsFlags::Ack.into()),
+                StreamId::CONNECTION,
+            ),
+            (),
+        )
+        .await
+    }
+
+    fn from_halves(
+        config: Rc<Config>,
+        mut r: <IO as IntoHalves>::Read,
+        w: <IO as IntoHalves>::Write,
+        mut res_buf: RollMut,
+    ) -> Self {
         let (ev_tx, ev_rx) = tokio::sync::mpsc::channel::<Ev>(1);
         let mut eof = false;
+        let recorder = config.record_frames.then(Recorder::default);
+        let assemble_headers = Rc::new(Cell::new(false));
+        let raw_mode = Rc::new(Cell::new(false));
 
         let ev_tx_unwrap = ev_tx.clone();
-        let mut res_buf = RollMut::alloc().unwrap();
 
         let recv_fut = {
             let config = config.clone();
+            let recorder = recorder.clone();
+            let assemble_headers = assemble_headers.clone();
+            let raw_mode = raw_mode.clone();
+            let mut assembler = HeaderBlockAssembler::new(config.max_header_block_size);
             async move {
                 'read: loop {
                     trace!("'read loop");
 
+                    if raw_mode.get() && !res_buf.is_empty() {
+                        let bytes = res_buf.take_all();
+                        debug!("< {} raw bytes", bytes.len());
+                        if ev_tx.send(Ev::Raw { bytes }).await.is_err() {
+                            break 'read;
+                        }
+                        continue;
+                    }
+
                     match Frame::parse(res_buf.filled()) {
                         Ok((rest, frame)) => {
+                            let header = recorder
+                                .is_some()
+                                .then(|| res_buf.filled()[..res_buf.len() - rest.len()].to_vec());
                             res_buf.keep(rest);
                             debug!("< {frame:?}");
 
@@ -287,12 +1169,12 @@ impl<IO: IntoHalves> Conn<IO> {
                                 trace!(%n, len = %res_buf.len(), "read bytes (reading frame payload)");
 
                                 if n == 0 {
-                                    eof = true;
-                                    if res_buf.len() < frame_len {
-                                        panic!(
-                                            "peer frame header, then incomplete payload, then hung up"
-                                        )
-                                    }
+                                    let reason = format!(
+                                        "peer sent frame header ({frame:?}), then {} bytes of payload, then hung up",
+                                        res_buf.len()
+                                    );
+                                    _ = ev_tx.send(Ev::ProtocolViolation { reason }).await;
+                                    break 'read;
                                 }
                             }
 
@@ -304,41 +1186,52 @@ impl<IO: IntoHalves> Conn<IO> {
                             assert_eq!(payload.len(), frame_len);
 
                             trace!(%frame_len, "got frame payload");
-                            if ev_tx.send(Ev::Frame { frame, payload }).await.is_err() {
+                            if let (Some(recorder), Some(header)) = (&recorder, &header) {
+                                recorder.record_frame(
+                                    Direction::Received,
+                                    &frame,
+                                    header,
+                                    &payload[..],
+                                );
+                            }
+                            let ev = match assembler.feed(assemble_headers.get(), frame, payload) {
+                                Ok(Some(ev)) => ev,
+                                Ok(None) => continue,
+                                Err(reason) => {
+                                    _ = ev_tx.send(Ev::ProtocolViolation { reason }).await;
+                                    break 'read;
+                                }
+                            };
+                            if ev_tx.send(ev).await.is_err() {
                                 // I guess we stopped consuming frames, sure.
                                 break 'read;
                             }
                         }
                         Err(nom::Err::Incomplete(_)) => {
                             if eof {
-                                if res_buf.is_empty() {
+                                let ev = if res_buf.is_empty() {
                                     // all good, that's eof!
-                                    break 'read;
+                                    Ev::Eof
                                 } else {
-                                    panic!(
-                                        "peer sent incomplete frame header then hung up (buf len: {})",
-                                        res_buf.len()
-                                    )
-                                }
+                                    Ev::ProtocolViolation {
+                                        reason: format!(
+                                            "peer sent incomplete frame header then hung up (buf len: {})",
+                                            res_buf.len()
+                                        ),
+                                    }
+                                };
+                                _ = ev_tx.send(ev).await;
+                                break 'read;
                             }
 
                             trace!("reserving");
                             res_buf.reserve().unwrap();
                             let res;
                             trace!("re-filling buffer");
-                            let deadline = Instant::now() + config.timeout;
-                            (res, res_buf) = match tokio::time::timeout_at(
-                                deadline,
-                                res_buf.read_into(16384, &mut r),
-                            )
-                            .await
-                            {
-                                Ok(res) => res,
-                                Err(_) => {
-                                    debug!("timed out reading frame header (re-filling)");
-                                    break 'read;
-                                }
-                            };
+                            // no deadline here: the peer not sending anything
+                            // for a while isn't the same as it hanging up, and
+                            // whoever waits for a frame has a deadline of their own
+                            (res, res_buf) = res_buf.read_into(16384, &mut r).await;
                             let n = res?;
                             if n == 0 {
                                 debug!("reached EOF");
@@ -351,7 +1244,13 @@ impl<IO: IntoHalves> Conn<IO> {
                         }
                         Err(nom::Err::Failure(err) | nom::Err::Error(err)) => {
                             debug!(?err, "got parse error");
-                            break;
+                            let header = &res_buf.filled()[..res_buf.len().min(9)];
+                            let reason = format!(
+                                "could not parse frame header {header:02x?}: {:?}",
+                                err.code
+                            );
+                            _ = ev_tx.send(Ev::ProtocolViolation { reason }).await;
+                            break 'read;
                         }
                     }
                 }
@@ -381,11 +1280,6 @@ impl<IO: IntoHalves> Conn<IO> {
             }
         });
 
-        let mut settings: Settings = Default::default();
-        for (code, value) in default_settings().0 {
-            settings.apply(*code, *value).unwrap();
-        }
-
         Self {
             w,
             scratch: RollMut::alloc().unwrap(),
@@ -398,21 +1292,205 @@ impl<IO: IntoHalves> Conn<IO> {
                 max_frame_size: DEFAULT_FRAME_SIZE,
                 ..Default::default()
             },
+            granted: Default::default(),
+            recorder,
+            last_stream_id: StreamId::CONNECTION,
+            connector: None,
+            assemble_headers,
+            raw_mode,
+            write_shut_down: false,
             cancel_tx,
         }
     }
 
+    /// Lets tests open more connections to the same server with
+    /// [Conn::connect_another]. Test runners that don't set this can only run
+    /// tests that make do with the one.
+    pub fn with_connector(mut self, connector: Connector<IO>) -> Self {
+        self.connector = Some(connector);
+        self
+    }
+
+    /// Opens another connection to the server, with the same config, for
+    /// tests that need several at once, e.g. to check that an error on one
+    /// leaves the others alone. Call [Conn::handshake] on it next.
+    pub async fn connect_another(&self) -> eyre::Result<Self> {
+        let Some(connector) = self.connector.clone() else {
+            return Err(eyre!(
+                "this test needs more than one connection, and the test runner can't open them"
+            ));
+        };
+        let io = connector().await?;
+        Ok(Self::new(self.config.clone(), io).with_connector(connector))
+    }
+
+    /// Whether the receive loop puts HEADERS frames and the CONTINUATION
+    /// frames after them together, up to [Config::max_header_block_size],
+    /// and hands out [Ev::Headers] events instead of the frames, for tests
+    /// that don't care how a field block is split up. A HEADERS frame
+    /// followed by anything but CONTINUATION frames on its stream is then a
+    /// protocol violation. Off by default; only applies to frames that
+    /// haven't been received yet.
+    ///
+    /// [Conn::read_headers], [Conn::read_response] and
+    /// [Conn::read_responses] work either way, but [Conn::wait_for_frame]
+    /// never sees a HEADERS frame while this is on.
+    pub fn assemble_headers(&mut self, enabled: bool) {
+        self.assemble_headers.set(enabled);
+    }
+
+    /// Whether the receive loop stops parsing frames, and hands out whatever
+    /// the peer sends as [Ev::Raw] events instead, for tests that need to
+    /// see bytes that may not be frames at all: what's left on the wire
+    /// after a GOAWAY, or what a server answers a bad preface with. Off by
+    /// default; a frame the receive loop already parsed still comes out as
+    /// one. Cf. [Conn::read_raw].
+    pub fn raw_mode(&mut self, enabled: bool) {
+        self.raw_mode.set(enabled);
+    }
+
+    /// Turns on [Conn::raw_mode], and reads bytes until there's at least
+    /// `len` of them, the peer hangs up, or [Config::timeout] passes: tests
+    /// can then assert on exactly what the peer sent, e.g. nothing at all.
+    pub async fn read_raw(&mut self, len: usize) -> eyre::Result<Vec<u8>> {
+        self.raw_mode(true);
+        let deadline = Instant::now() + self.config.timeout;
+        let mut out = vec![];
+        while out.len() < len {
+            match self.next_event_with_deadline(deadline).await {
+                NextEvent::Timeout { .. } | NextEvent::Eof => break,
+                NextEvent::Ev(Ev::Raw { bytes }) => out.extend_from_slice(&bytes[..]),
+                NextEvent::Ev(Ev::Frame { frame, .. }) => {
+                    debug!("skipping {frame:?}, parsed before raw mode was on");
+                }
+                NextEvent::Ev(Ev::Headers { stream_id, .. }) => {
+                    debug!(%stream_id, "skipping field block, assembled before raw mode was on");
+                }
+                NextEvent::Ev(Ev::IoError { error }) => {
+                    eyre::bail!("I/O error while reading raw bytes: {error}")
+                }
+                NextEvent::Ev(Ev::ProtocolViolation { reason }) => {
+                    eyre::bail!("Peer violated the protocol before raw mode was on: {reason}")
+                }
+                NextEvent::Ev(Ev::Eof) => unreachable!("next_event turns it into NextEvent::Eof"),
+            }
+        }
+        Ok(out)
+    }
+
+    /// The frames sent and received so far, if [Config::record_frames] is
+    /// set. The recorder is shared: grab it before handing the connection to
+    /// a test, and it'll still have everything once the test is done.
+    pub fn recorder(&self) -> Option<Recorder> {
+        self.recorder.clone()
+    }
+
+    /// How many bytes of DATA the peer may still send us on `stream_id` (or
+    /// on the connection as a whole, for [StreamId::CONNECTION]): what we
+    /// granted with SETTINGS_INITIAL_WINDOW_SIZE and WINDOW_UPDATE frames,
+    /// minus the DATA frames we've received so far.
+    pub fn granted_window(&self, stream_id: StreamId) -> i64 {
+        if stream_id == StreamId::CONNECTION {
+            self.granted.connection
+        } else {
+            self.granted.initial + self.granted.streams.get(&stream_id).unwrap_or(&0)
+        }
+    }
+
+    /// The WINDOW_UPDATE increment that takes the window for `stream_id`
+    /// exactly to 2^31-1: one more and it overflows.
+    pub fn max_window_increment(&self, stream_id: StreamId) -> u32 {
+        (MAX_WINDOW_SIZE - self.granted_window(stream_id))
+            .try_into()
+            .unwrap()
+    }
+
     pub async fn write_frame(&mut self, frame: Frame, payload: impl IntoPiece) -> eyre::Result<()> {
+        self.note_stream_opened(&frame);
         let payload = payload.into_piece(&mut self.scratch)?;
         let frame = frame.with_len(payload.len().try_into().unwrap());
 
         let header = frame.into_piece(&mut self.scratch)?;
+        if let Some(recorder) = &self.recorder {
+            recorder.record_frame(Direction::Sent, &frame, &header[..], &payload[..]);
+        }
+        if let Some(chunking) = self.config.chunking.clone() {
+            let buf = [&header[..], &payload[..]].concat();
+            return self.write_chunked(&buf, &chunking).await;
+        }
         self.w
             .writev_all_owned(PieceList::single(header).followed_by(payload))
             .await?;
         Ok(())
     }
 
+    /// Sends `buf` as-is, in as many writes as `chunking` says, e.g. frames
+    /// encoded with [Conn::encode_frame]. The server should make the same
+    /// of it as it would of a single write.
+    pub async fn send_chunked(
+        &mut self,
+        buf: impl Into<Piece>,
+        chunking: &Chunking,
+    ) -> eyre::Result<()> {
+        let buf = buf.into();
+        if let Some(recorder) = &self.recorder {
+            recorder.record_raw(&buf[..]);
+        }
+        self.write_chunked(&buf[..], chunking).await
+    }
+
+    async fn write_chunked(&mut self, buf: &[u8], chunking: &Chunking) -> eyre::Result<()> {
+        let mut start = 0;
+        for end in chunking.split_points(buf) {
+            if start > 0 {
+                if let Some(pause) = chunking.pause() {
+                    tokio::time::sleep(pause).await;
+                }
+            }
+            if let Err(e) = self.w.write_all_owned(buf[start..end].to_vec()).await {
+                if start == 0 {
+                    return Err(e.into());
+                }
+                // the peer may well have seen enough to hang up on us, what
+                // we read next will tell
+                debug!(
+                    "peer hung up {start} bytes into a {}-byte write: {e}",
+                    buf.len()
+                );
+                return Ok(());
+            }
+            start = end;
+        }
+        Ok(())
+    }
+
+    /// A frame's header and payload, as [Conn::write_frame] would send them,
+    /// to be sent later, e.g. with [Conn::send_chunked]
+    pub fn encode_frame(&mut self, frame: Frame, payload: impl IntoPiece) -> eyre::Result<Vec<u8>> {
+        let payload = payload.into_piece(&mut self.scratch)?;
+        let frame = frame.with_len(payload.len().try_into().unwrap());
+        let header = frame.into_piece(&mut self.scratch)?;
+        Ok([&header[..], &payload[..]].concat())
+    }
+
+    fn note_stream_opened(&mut self, frame: &Frame) {
+        if let FrameType::Headers(_) = frame.frame_type {
+            if frame.stream_id.0 % 2 == 1 && frame.stream_id > self.last_stream_id {
+                self.last_stream_id = frame.stream_id;
+            }
+        }
+    }
+
+    /// The client-initiated stream ID after the highest one we've sent
+    /// HEADERS on so far, however we did: 1 on a fresh connection
+    pub fn next_stream_id(&self) -> StreamId {
+        if self.last_stream_id == StreamId::CONNECTION {
+            StreamId(1)
+        } else {
+            StreamId(self.last_stream_id.0 + 2)
+        }
+    }
+
     pub async fn write_priority(
         &mut self,
         stream_id: StreamId,
@@ -456,9 +1534,16 @@ impl<IO: IntoHalves> Conn<IO> {
         &mut self,
         settings: impl Into<SettingPairs<'_>>,
     ) -> eyre::Result<()> {
+        let settings = settings.into();
+        for &(setting, value) in settings.0 {
+            if setting == Setting::InitialWindowSize {
+                self.granted.initial = value as i64;
+            }
+        }
+
         self.write_frame(
             FrameType::Settings(Default::default()).into_frame(StreamId::CONNECTION),
-            settings.into(),
+            settings,
         )
         .await
     }
@@ -469,6 +1554,32 @@ impl<IO: IntoHalves> Conn<IO> {
         self.wait_for_frame_with_deadline(types, deadline).await
     }
 
+    /// Waits for the next frame or I/O error, for at most [Config::timeout],
+    /// so that a peer that never answers fails the test instead of stalling
+    /// the suite.
+    pub async fn next_event(&mut self) -> NextEvent {
+        let deadline = Instant::now() + self.config.timeout;
+        self.next_event_with_deadline(deadline).await
+    }
+
+    /// Waits for the next frame or I/O error until `deadline`
+    pub async fn next_event_with_deadline(&mut self, deadline: Instant) -> NextEvent {
+        match tokio::time::timeout_at(deadline, self.ev_rx.recv()).await {
+            Err(_) => NextEvent::Timeout {
+                waited: self.config.timeout,
+            },
+            Ok(None | Some(Ev::Eof)) => NextEvent::Eof,
+            Ok(Some(ev)) => {
+                if let Ev::Frame { frame, .. } = &ev {
+                    if let FrameType::Data(_) = frame.frame_type {
+                        self.granted.consume(frame.stream_id, frame.len as i64);
+                    }
+                }
+                NextEvent::Ev(ev)
+            }
+        }
+    }
+
     /// Waits for a certain kind of frame with a specified deadline
     pub async fn wait_for_frame_with_deadline(
         &mut self,
@@ -479,38 +1590,191 @@ impl<IO: IntoHalves> Conn<IO> {
         let mut last_frame: Option<Frame> = None;
 
         loop {
-            match tokio::time::timeout_at(deadline, self.ev_rx.recv()).await {
-                Err(_) => {
+            match self.next_event_with_deadline(deadline).await {
+                NextEvent::Timeout { waited } => {
                     return FrameWaitOutcome::Timeout {
                         wanted: types,
                         last_frame,
-                        waited: self.config.timeout,
+                        waited,
                     };
                 }
-                Ok(maybe_ev) => match maybe_ev {
-                    None => {
-                        return FrameWaitOutcome::Eof {
-                            wanted: types,
-                            last_frame,
-                        }
+                NextEvent::Eof => {
+                    return FrameWaitOutcome::Eof {
+                        wanted: types,
+                        last_frame,
                     }
-                    Some(ev) => match ev {
-                        Ev::Frame { frame, payload } => {
-                            if types.contains(FrameT::from(frame.frame_type)) {
-                                return FrameWaitOutcome::Success(frame, payload);
-                            } else {
-                                last_frame = Some(frame)
-                            }
-                        }
-                        Ev::IoError { error } => {
-                            return FrameWaitOutcome::IoError {
-                                wanted: types,
-                                last_frame,
-                                error,
-                            }
-                        }
-                    },
-                },
+                }
+                NextEvent::Ev(Ev::Headers { stream_id, .. }) => {
+                    debug!(%stream_id, "skipping assembled field block while waiting for a frame");
+                }
+                NextEvent::Ev(Ev::Raw { bytes }) => {
+                    debug!(
+                        "skipping {} raw bytes while waiting for a frame",
+                        bytes.len()
+                    );
+                }
+                NextEvent::Ev(Ev::Frame { frame, payload }) => {
+                    if types.contains(FrameT::from(frame.frame_type)) {
+                        return FrameWaitOutcome::Success(frame, payload);
+                    } else {
+                        last_frame = Some(frame)
+                    }
+                }
+                NextEvent::Ev(Ev::IoError { error }) => {
+                    return FrameWaitOutcome::IoError {
+                        wanted: types,
+                        last_frame,
+                        error,
+                    }
+                }
+                NextEvent::Ev(Ev::ProtocolViolation { reason }) => {
+                    return FrameWaitOutcome::ProtocolViolation {
+                        wanted: types,
+                        last_frame,
+                        reason,
+                    }
+                }
+                NextEvent::Ev(Ev::Eof) => unreachable!("next_event turns it into NextEvent::Eof"),
+            }
+        }
+    }
+
+    /// Waits up to `timeout` for a frame that matches `pred`, e.g.
+    /// `|f| matches!(f.frame_type, FrameType::GoAway)`, and returns it along
+    /// with its decoded payload.
+    ///
+    /// Frames a peer may send at any time (WINDOW_UPDATE, PING, PRIORITY,
+    /// SETTINGS and unknown extension frames) are skipped, unless they match.
+    /// Any other frame is an error, and so is the peer hanging up: unlike
+    /// [Conn::wait_for_frame], this doesn't skip over a response we weren't
+    /// expecting.
+    pub async fn expect_frame(
+        &mut self,
+        pred: impl Fn(&Frame) -> bool,
+        timeout: Duration,
+    ) -> eyre::Result<(Frame, Payload)> {
+        let deadline = Instant::now() + timeout;
+        let mut last_frame: Option<Frame> = None;
+
+        loop {
+            let ev = match self.next_event_with_deadline(deadline).await {
+                NextEvent::Timeout { .. } => {
+                    eyre::bail!(
+                        "Timed out after {timeout:?} waiting for the expected frame, last frame: ({last_frame:?})"
+                    )
+                }
+                NextEvent::Eof => {
+                    eyre::bail!(
+                        "Peer hung up while we waited for the expected frame, last frame: ({last_frame:?})"
+                    )
+                }
+                NextEvent::Ev(ev) => ev,
+            };
+
+            match ev {
+                Ev::Frame { frame, payload } => {
+                    if pred(&frame) {
+                        let payload = Payload::decode(&frame, payload)?;
+                        return Ok((frame, payload));
+                    }
+                    if !matches!(
+                        frame.frame_type,
+                        FrameType::WindowUpdate
+                            | FrameType::Ping(_)
+                            | FrameType::Priority
+                            | FrameType::Settings(_)
+                            | FrameType::Unknown(_)
+                    ) {
+                        eyre::bail!("Got {frame:?} while we waited for the expected frame");
+                    }
+                    debug!("skipping {frame:?} while waiting for the expected frame");
+                    last_frame = Some(frame);
+                }
+                Ev::Headers { stream_id, .. } => {
+                    eyre::bail!("Got a field block on stream {stream_id} while we waited for the expected frame");
+                }
+                Ev::Raw { bytes } => {
+                    eyre::bail!(
+                        "Got {} raw bytes while we waited for the expected frame",
+                        bytes.len()
+                    );
+                }
+                Ev::IoError { error } => {
+                    eyre::bail!("I/O error while waiting for the expected frame: {error}, last frame: ({last_frame:?})")
+                }
+                Ev::ProtocolViolation { reason } => {
+                    eyre::bail!("Peer violated the protocol while we waited for the expected frame: {reason}")
+                }
+                Ev::Eof => unreachable!("next_event turns it into NextEvent::Eof"),
+            }
+        }
+    }
+
+    /// Waits `duration`, and errors out if the peer sends anything in the
+    /// meantime, or hangs up: for requirements like "implementations MUST
+    /// ignore X". Cf. [Conn::expect_no_frame], to only mind some frames.
+    pub async fn expect_silence(&mut self, duration: Duration) -> eyre::Result<()> {
+        self.expect_no_frame(BitFlags::all(), duration).await
+    }
+
+    /// Waits `duration`, and errors out if the peer sends a frame of one of
+    /// `types` in the meantime, e.g. `FrameT::GoAway | FrameT::RstStream` to
+    /// check it didn't take offense, or hangs up. Other frames are skipped.
+    pub async fn expect_no_frame(
+        &mut self,
+        types: impl Into<BitFlags<FrameT>>,
+        duration: Duration,
+    ) -> eyre::Result<()> {
+        let types = types.into();
+        let deadline = Instant::now() + duration;
+
+        loop {
+            let ev = match self.next_event_with_deadline(deadline).await {
+                NextEvent::Timeout { .. } => return Ok(()),
+                NextEvent::Eof => {
+                    eyre::bail!(
+                        "Peer hung up within {duration:?}, while it should have stayed quiet"
+                    )
+                }
+                NextEvent::Ev(ev) => ev,
+            };
+
+            match ev {
+                Ev::Frame { frame, payload } => {
+                    if !types.contains(FrameT::from(frame.frame_type)) {
+                        debug!("skipping {frame:?} while making sure the peer stays quiet");
+                        continue;
+                    }
+                    if let Payload::GoAway(goaway) = Payload::decode(&frame, payload)? {
+                        eyre::bail!(
+                            "Got GOAWAY with {:?} within {duration:?}, while the peer should have stayed quiet",
+                            goaway.error_code
+                        );
+                    }
+                    eyre::bail!(
+                        "Got {frame:?} within {duration:?}, while the peer should have stayed quiet"
+                    );
+                }
+                Ev::Headers { stream_id, .. } => {
+                    if types.contains(FrameT::Headers) {
+                        eyre::bail!("Got a field block on stream {stream_id} within {duration:?}, while the peer should have stayed quiet");
+                    }
+                }
+                Ev::Raw { bytes } => {
+                    eyre::bail!(
+                        "Got {:02x?} within {duration:?}, while the peer should have stayed quiet",
+                        &bytes[..]
+                    );
+                }
+                Ev::IoError { error } => {
+                    eyre::bail!("I/O error while making sure the peer stays quiet: {error}")
+                }
+                Ev::ProtocolViolation { reason } => {
+                    eyre::bail!(
+                        "Peer violated the protocol while it should have stayed quiet: {reason}"
+                    )
+                }
+                Ev::Eof => unreachable!("next_event turns it into NextEvent::Eof"),
             }
         }
     }
@@ -528,10 +1792,17 @@ impl<IO: IntoHalves> Conn<IO> {
     }
 
     pub async fn handshake(&mut self) -> eyre::Result<()> {
-        // perform an HTTP/2 handshake as a client
-        self.w.write_all_owned(PREFACE).await?;
+        self.handshake_with_preface(&Preface::Valid).await
+    }
+
+    /// Performs an HTTP/2 handshake as a client, like [Conn::handshake], but
+    /// opening with `preface`: for prefaces a server should accept, e.g. one
+    /// split across writes
+    pub async fn handshake_with_preface(&mut self, preface: &Preface) -> eyre::Result<()> {
+        self.send_preface(preface).await?;
 
-        self.write_settings(default_settings()).await?;
+        let settings = self.config.settings();
+        self.write_settings(&settings[..]).await?;
 
         let (frame, payload) = self.wait_for_frame(FrameT::Settings).await.unwrap();
         assert!(
@@ -551,53 +1822,108 @@ impl<IO: IntoHalves> Conn<IO> {
         .await?;
 
         // and wait until the server acknowledges our settings
-        let (frame, _payload) = self.wait_for_frame(FrameT::Settings).await.unwrap();
-        assert!(frame.is_ack(), "server should acknowledge our settings");
+        self.verify_settings_ack().await?;
 
         Ok(())
     }
 
-    pub async fn send(&mut self, buf: impl Into<Piece>) -> eyre::Result<()> {
-        self.w.write_all_owned(buf.into()).await?;
-        Ok(())
+    /// Waits for the peer to acknowledge the SETTINGS frame we sent last,
+    /// for at most [Config::settings_ack_timeout], and returns how long that
+    /// took. Other frames are skipped, SETTINGS frames without ACK included.
+    pub async fn verify_settings_ack(&mut self) -> eyre::Result<Duration> {
+        let start = Instant::now();
+        let timeout = self.config.settings_ack_timeout;
+        let deadline = start + timeout;
+
+        loop {
+            match self
+                .wait_for_frame_with_deadline(FrameT::Settings, deadline)
+                .await
+            {
+                FrameWaitOutcome::Success(frame, _) if frame.is_ack() => {
+                    return Ok(start.elapsed())
+                }
+                FrameWaitOutcome::Success(frame, _) => {
+                    debug!(?frame, "skipping SETTINGS frame while waiting for an ACK");
+                }
+                FrameWaitOutcome::Timeout { last_frame, .. } => {
+                    return Err(eyre!(
+                        "peer didn't acknowledge our SETTINGS within {timeout:?}, which we could treat as a connection error of type SETTINGS_TIMEOUT. Last frame: {last_frame:?}"
+                    ))
+                }
+                outcome => {
+                    return Err(outcome
+                        .into_result()
+                        .unwrap_err()
+                        .wrap_err("while waiting for the peer to acknowledge our SETTINGS"))
+                }
+            }
+        }
+    }
+
+    /// Sends `preface`, split into writes if it says so
+    pub async fn send_preface(&mut self, preface: &Preface) -> eyre::Result<()> {
+        let bytes = preface.bytes();
+        match preface.chunking() {
+            Some(chunking) => self.send_chunked(bytes, chunking).await,
+            None => self.send(bytes).await,
+        }
     }
 
-    async fn verify_connection_error(
+    /// Sends `preface` and our SETTINGS frame, and expects the server to
+    /// treat that as a connection error with one of `codes`, cf.
+    /// [Conn::verify_connection_error]
+    pub async fn verify_preface_rejected(
         &mut self,
+        preface: &Preface,
         codes: impl Into<BitFlags<ErrorC>>,
     ) -> eyre::Result<()> {
-        let codes = codes.into();
+        eyre::ensure!(
+            !preface.is_valid(),
+            "{preface:?} is a valid preface, servers have no reason to reject it"
+        );
+        self.send_preface(preface).await?;
 
-        match self.wait_for_frame(FrameT::GoAway).await {
-            FrameWaitOutcome::Success(_frame, payload) => {
-                let (_, goaway) = GoAway::parse(payload).finish().unwrap();
-                let error_c: ErrorC = KnownErrorCode::try_from(goaway.error_code)
-                    .map_err(|_| eyre::eyre!(
-                        "Expected GOAWAY with one of {codes:?}, but got unknown error code {} (0x{:x})",
-                        goaway.error_code.as_repr(), goaway.error_code.as_repr()
-                    ))?
-                    .into();
+        // the server may have hung up already
+        let settings = self.config.settings();
+        _ = self.write_settings(&settings[..]).await;
 
-                if codes.contains(error_c) {
-                    // that's what we expected!
-                    return Ok(());
-                }
-                Err(eyre::eyre!(
-                    "Expected GOAWAY with one of {codes:?}, but got {error_c:?}"
-                ))
-            }
-            FrameWaitOutcome::Timeout { last_frame, .. } => Err(eyre!(
-                "Timed out while waiting for connection error, last frame: ({last_frame:?})"
-            )),
-            FrameWaitOutcome::Eof { .. } => {
-                // that's fine
-                Ok(())
-            }
-            FrameWaitOutcome::IoError { .. } => {
-                // TODO: that's fine if it's a connection reset, we should probably check
-                Ok(())
-            }
+        self.verify_connection_error(codes).await
+    }
+
+    pub async fn send(&mut self, buf: impl Into<Piece>) -> eyre::Result<()> {
+        let buf = buf.into();
+        if let Some(recorder) = &self.recorder {
+            recorder.record_raw(&buf[..]);
         }
+        if let Some(chunking) = self.config.chunking.clone() {
+            return self.write_chunked(&buf[..], &chunking).await;
+        }
+        self.w.write_all_owned(buf).await?;
+        Ok(())
+    }
+
+    /// Shuts down our half of the connection, i.e. half-closes TCP: the
+    /// peer reads EOF once it's read everything we sent, but can still send
+    /// us frames. Writing anything after that fails, and
+    /// [Conn::read_response] stops sending WINDOW_UPDATE frames, so
+    /// responses have to fit in the windows granted so far.
+    pub async fn shutdown_write(&mut self) -> eyre::Result<()> {
+        self.w.shutdown().await?;
+        self.write_shut_down = true;
+        Ok(())
+    }
+
+    /// Waits for the peer to signal a connection error with one of `codes`,
+    /// cf. h2spec's `VerifyConnectionError`: either a GOAWAY frame, or the
+    /// connection closing (some peers hang up without saying why). Other
+    /// frames that arrive in the meantime are skipped.
+    pub async fn verify_connection_error(
+        &mut self,
+        codes: impl Into<BitFlags<ErrorC>>,
+    ) -> eyre::Result<()> {
+        self.verify_error(FrameT::GoAway.into(), codes.into(), "connection error")
+            .await
     }
 
     pub async fn verify_connection_close(&mut self) -> eyre::Result<()> {
@@ -617,6 +1943,11 @@ impl<IO: IntoHalves> Conn<IO> {
                 // TODO: that's fine if it's a connection reset, we should probably check
                 Ok(())
             }
+            FrameWaitOutcome::ProtocolViolation { reason, .. } => {
+                // it did close the connection, if not gracefully
+                debug!(%reason, "peer hung up mid-frame");
+                Ok(())
+            }
         }
     }
 
@@ -679,6 +2010,11 @@ impl<IO: IntoHalves> Conn<IO> {
                     // TODO: that's fine if it's a connection reset, we should probably check
                     return Ok(());
                 }
+                FrameWaitOutcome::ProtocolViolation { reason, .. } => {
+                    return Err(eyre!(
+                        "Peer violated the protocol while we waited for stream close: {reason}"
+                    ));
+                }
             }
         }
     }
@@ -691,98 +2027,147 @@ impl<IO: IntoHalves> Conn<IO> {
         Ok(())
     }
 
+    /// Waits for a complete response on `stream_id`: a final status, up to
+    /// END_STREAM. Fails if the stream gets reset, or if the peer sends a
+    /// GOAWAY or hangs up first.
+    pub async fn verify_response(&mut self, stream_id: StreamId) -> eyre::Result<()> {
+        self.verify_responses(&[stream_id]).await
+    }
+
+    /// Like [Conn::verify_response], for all of `stream_ids`, in any order
+    pub async fn verify_responses(&mut self, stream_ids: &[StreamId]) -> eyre::Result<()> {
+        let mut responses = Responses::default();
+        self.read_responses(&mut responses, |r| {
+            stream_ids.iter().all(|&id| r.ended(id)) || r.goaway.is_some() || r.closed
+        })
+        .await?;
+
+        for &stream_id in stream_ids {
+            if responses.completed(stream_id) {
+                continue;
+            }
+            if let Some(goaway) = &responses.goaway {
+                return Err(eyre!(
+                    "expected a response on stream {stream_id}, got GOAWAY with {:?}",
+                    goaway.error_code
+                ));
+            }
+            return match responses.streams.get(&stream_id) {
+                Some(response) => Err(eyre!(
+                    "expected a complete response on stream {stream_id}, got {response:?}"
+                )),
+                None => Err(eyre!("expected a response on stream {stream_id}, got none")),
+            };
+        }
+        Ok(())
+    }
+
+    /// Waits for the peer to signal a stream error with one of `codes`, cf.
+    /// h2spec's `VerifyStreamError`: either an RST_STREAM frame, or a
+    /// connection error (a GOAWAY frame, or the connection closing). Other
+    /// frames that arrive in the meantime are skipped.
     pub async fn verify_stream_error(
         &mut self,
         codes: impl Into<BitFlags<ErrorC>>,
     ) -> eyre::Result<()> {
-        let codes = codes.into();
-
-        tracing::debug!("waiting for frame GoAaway | RstStream");
-        let frame_wait_outcome = self
-            .wait_for_frame(FrameT::GoAway | FrameT::RstStream)
-            .await;
-        tracing::debug!("waiting for frame GoAaway | RstStream.. got an outcome");
+        self.verify_error(
+            FrameT::GoAway | FrameT::RstStream,
+            codes.into(),
+            "stream error",
+        )
+        .await
+    }
 
-        match frame_wait_outcome {
+    async fn verify_error(
+        &mut self,
+        types: BitFlags<FrameT>,
+        codes: BitFlags<ErrorC>,
+        what: &str,
+    ) -> eyre::Result<()> {
+        debug!("waiting for {what} ({types:?} with one of {codes:?})");
+        match self.wait_for_frame(types).await {
             FrameWaitOutcome::Success(frame, payload) => {
-                match frame.frame_type {
-                    FrameType::GoAway => {
-                        let (_, goaway) = GoAway::parse(payload).finish().unwrap();
-                        let error_c: ErrorC = KnownErrorCode::try_from(goaway.error_code)
-                            .map_err(|_| eyre::eyre!(
-                                "Expected GOAWAY or RSTSTREAM with one of {codes:?}, but got unknown error code {} (0x{:x})",
-                                goaway.error_code.as_repr(), goaway.error_code.as_repr()
-                            ))?
-                            .into();
-
-                        if codes.contains(error_c) {
-                            // that's what we expected!
-                            Ok(())
-                        } else {
-                            Err(eyre::eyre!(
-                                "Expected GOAWAY or RSTSTREAM with one of {codes:?}, but got {error_c:?}"
-                            ))
-                        }
+                let payload = Payload::decode(&frame, payload)?;
+                let error_code = payload
+                    .error_code()
+                    .expect("GOAWAY and RST_STREAM frames have an error code");
+                // whatever the server says about why can help whoever's
+                // debugging it, whether or not it's the error we expected
+                let debug_data = match payload.debug_data_str() {
+                    Some(data) if !data.is_empty() => {
+                        debug!(%data, "GOAWAY debug data");
+                        format!(" (debug data: {data:?})")
                     }
-                    FrameType::RstStream => {
-                        tracing::debug!("waiting for frame GoAaway | RstStream.. got RstStream");
-
-                        let (_, rststream) = RstStream::parse(payload).finish().unwrap();
-                        let error_code = KnownErrorCode::try_from(rststream.error_code)
-                            .map_err(|_| eyre::eyre!(
-                                "Expected GOAWAY or RSTSTREAM with one of {codes:?}, but got unknown error code {} (0x{:x})",
-                                rststream.error_code.as_repr(), rststream.error_code.as_repr()
-                            ))?;
-
-                        let error_c: ErrorC = error_code.into();
+                    _ => String::new(),
+                };
+                let error_c: ErrorC = KnownErrorCode::try_from(error_code)
+                    .map_err(|_| {
+                        eyre!(
+                            "Expected {types:?} with one of {codes:?}, but got unknown error code {} (0x{:x})",
+                            error_code.as_repr(),
+                            error_code.as_repr()
+                        )
+                    })?
+                    .into();
 
-                        if codes.contains(error_c) {
-                            // that's what we expected!
-                            Ok(())
-                        } else {
-                            Err(eyre::eyre!(
-                                "Expected GOAWAY or RSTSTREAM with one of {codes:?}, but got {error_c:?}"
-                            ))
-                        }
-                    }
-                    _ => unreachable!(),
+                if codes.contains(error_c) {
+                    // that's what we expected!
+                    Ok(())
+                } else {
+                    Err(eyre!(
+                        "Expected {types:?} with one of {codes:?}, but got {:?} with {error_c:?}{debug_data}",
+                        frame.frame_type
+                    ))
                 }
             }
             FrameWaitOutcome::Timeout { last_frame, .. } => Err(eyre!(
-                "Timed out while waiting for stream error, last frame: ({last_frame:?})"
+                "Timed out while waiting for {what}, last frame: ({last_frame:?})"
             )),
             FrameWaitOutcome::Eof { .. } => {
                 // that's fine
                 Ok(())
             }
-            FrameWaitOutcome::IoError { .. } => {
-                // TODO: that's fine if it's a connection reset, we should probably check
-                Ok(())
-            }
+            FrameWaitOutcome::IoError {
+                error, last_frame, ..
+            } => match error.kind() {
+                // the peer hung up without reading everything we sent
+                std::io::ErrorKind::ConnectionReset
+                | std::io::ErrorKind::ConnectionAborted
+                | std::io::ErrorKind::BrokenPipe => Ok(()),
+                _ => Err(eyre!(
+                    "I/O error while waiting for {what}: {error}, last frame: ({last_frame:?})"
+                )),
+            },
+            FrameWaitOutcome::ProtocolViolation {
+                reason, last_frame, ..
+            } => Err(eyre!(
+                "Peer violated the protocol while we waited for {what}: {reason}, last frame: ({last_frame:?})"
+            )),
         }
     }
 
     fn common_headers(&self, method: &'static str) -> Headers {
-        let (scheme, default_port) = if self.config.tls {
-            ("https", self.config.port == 443)
-        } else {
-            ("http", self.config.port == 80)
-        };
-
-        let authority = if default_port {
-            self.config.host.clone()
-        } else {
-            format!("{}:{}", self.config.host, self.config.port)
-        };
+        let scheme = if self.config.tls { "https" } else { "http" };
 
         let mut headers = Headers::default();
         headers.append(":method", method);
         headers.append(":scheme", scheme);
         headers.append(":path", self.config.path.clone().into_bytes());
-        headers.append(":authority", authority.into_bytes());
+        headers.append(":authority", self.config.authority().into_bytes());
+        headers
+    }
+
+    /// Pseudo-headers for an extended CONNECT request (RFC 8441), to open a
+    /// tunnel for `protocol`: those of a regular request, plus `:protocol`
+    fn extended_connect_headers(&self, protocol: &'static str) -> Headers {
+        let mut headers = self.common_headers("CONNECT");
+        headers.append(":protocol", protocol);
         headers
     }
 
+    /// HPACK-encodes `headers` into a header block fragment, with the
+    /// connection's encoder: entries it adds to its dynamic table stay there
+    /// for the following blocks.
     pub fn encode_headers(&mut self, headers: &Headers) -> eyre::Result<Piece> {
         // wasteful, but we're doing tests so shrug.
         let mut fragment = Vec::new();
@@ -819,6 +2204,257 @@ impl<IO: IntoHalves> Conn<IO> {
         Ok(headers)
     }
 
+    /// Waits for a HEADERS frame on `stream_id` and any CONTINUATION frames
+    /// that follow it, then decodes the whole field block. Padding and
+    /// priority fields are stripped. Other frames are skipped, and so are
+    /// field blocks on other streams, once decoded.
+    pub async fn read_headers(&mut self, stream_id: StreamId) -> eyre::Result<Headers> {
+        let mut last_frame: Option<Frame> = None;
+
+        loop {
+            let ev = match self.next_decoded_event().await? {
+                DecodedEvent::Headers {
+                    stream_id: id,
+                    headers,
+                    ..
+                } => {
+                    if id == stream_id {
+                        return Ok(headers);
+                    }
+                    debug!(%id, "skipping field block for another stream");
+                    continue;
+                }
+                DecodedEvent::Other(ev) => ev,
+            };
+            match ev {
+                NextEvent::Ev(Ev::Frame { frame, .. }) => last_frame = Some(frame),
+                NextEvent::Timeout { waited } => {
+                    return Err(eyre!(
+                        "Wanted HEADERS on stream {stream_id}, timed out after {waited:?}. Last frame: {last_frame:?}"
+                    ))
+                }
+                NextEvent::Eof | NextEvent::Ev(Ev::Eof) => {
+                    return Err(eyre!(
+                        "Wanted HEADERS on stream {stream_id}, peer hung up. Last frame: {last_frame:?}"
+                    ))
+                }
+                NextEvent::Ev(Ev::IoError { error }) => {
+                    return Err(eyre!(
+                        "Wanted HEADERS on stream {stream_id}, got I/O error {error}. Last frame: {last_frame:?}"
+                    ))
+                }
+                NextEvent::Ev(Ev::ProtocolViolation { reason }) => {
+                    return Err(eyre!(
+                        "Wanted HEADERS on stream {stream_id}, peer violated the protocol: {reason}. Last frame: {last_frame:?}"
+                    ))
+                }
+                NextEvent::Ev(Ev::Headers { .. }) => unreachable!("next_decoded_event decodes those"),
+                NextEvent::Ev(Ev::Raw { .. }) => {
+                    return Err(eyre!(
+                        "Wanted HEADERS on stream {stream_id}, but raw mode is on"
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Like [Conn::next_event], but with field blocks decoded, whether they
+    /// came in as [Ev::Headers], or as a HEADERS frame and the CONTINUATION
+    /// frames after it
+    async fn next_decoded_event(&mut self) -> eyre::Result<DecodedEvent> {
+        Ok(match self.next_event().await {
+            NextEvent::Ev(Ev::Headers {
+                stream_id,
+                block,
+                end_stream,
+            }) => DecodedEvent::Headers {
+                stream_id,
+                headers: self.decode_headers(block)?,
+                end_stream,
+            },
+            NextEvent::Ev(Ev::Frame { frame, payload })
+                if matches!(frame.frame_type, FrameType::Headers(_)) =>
+            {
+                let stream_id = frame.stream_id;
+                let end_stream = frame.is_end_stream();
+                DecodedEvent::Headers {
+                    stream_id,
+                    headers: self.read_header_block(frame, payload).await?,
+                    end_stream,
+                }
+            }
+            ev => DecodedEvent::Other(ev),
+        })
+    }
+
+    /// Checks that the peer advertised SETTINGS_ENABLE_CONNECT_PROTOCOL
+    /// (RFC 8441), without which there's no extended CONNECT
+    pub fn verify_enable_connect_protocol(&self) -> eyre::Result<()> {
+        if !self.settings.enable_connect_protocol {
+            return Err(eyre!(
+                "peer didn't advertise SETTINGS_ENABLE_CONNECT_PROTOCOL"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Waits for the response to an extended CONNECT request on
+    /// `stream_id`, and checks that it opens the tunnel: any 2xx status does
+    pub async fn verify_tunnel_established(&mut self, stream_id: StreamId) -> eyre::Result<()> {
+        let headers = self.read_headers(stream_id).await?;
+        match headers.status()? {
+            200..=299 => Ok(()),
+            status => Err(eyre!("expected a 2xx status, got {status}")),
+        }
+    }
+
+    /// Reads the response headers on `stream_id`, skipping interim (1xx)
+    /// responses, and checks their `:status`. Returns them, for tests that
+    /// have more to check.
+    pub async fn verify_status(
+        &mut self,
+        stream_id: StreamId,
+        expected: u16,
+    ) -> eyre::Result<Headers> {
+        loop {
+            let headers = self.read_headers(stream_id).await?;
+            let status = headers.status()?;
+            if (100..200).contains(&status) && expected >= 200 {
+                continue;
+            }
+            if status != expected {
+                return Err(eyre!(
+                    "expected status {expected} on stream {stream_id}, got {status}: {headers:?}"
+                ));
+            }
+            return Ok(headers);
+        }
+    }
+
+    /// Reads DATA frames on `stream_id` until `len` bytes came in, or until
+    /// one has END_STREAM, e.g. to read back what went through a tunnel.
+    /// Fails if any other frame than those [Conn::expect_frame] skips comes
+    /// in first.
+    pub async fn read_data(&mut self, stream_id: StreamId, len: usize) -> eyre::Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(len);
+        while data.len() < len {
+            let (frame, payload) = self
+                .expect_frame(
+                    |f| matches!(f.frame_type, FrameType::Data(_)) && f.stream_id == stream_id,
+                    self.config.timeout,
+                )
+                .await?;
+            if let Payload::Other(payload) = payload {
+                data.extend_from_slice(&payload[..]);
+            }
+            if frame.is_end_stream() {
+                break;
+            }
+        }
+        Ok(data)
+    }
+
+    /// Decodes the field block that starts with `frame`, a HEADERS frame,
+    /// reading the CONTINUATION frames that follow it if needed
+    async fn read_header_block(
+        &mut self,
+        mut frame: Frame,
+        payload: Roll,
+    ) -> eyre::Result<Headers> {
+        let stream_id = frame.stream_id;
+        let mut block = header_block_fragment(&frame, &payload[..])?.to_vec();
+
+        while !frame.is_end_headers() {
+            let payload;
+            (frame, payload) = self
+                .wait_for_frame(FrameT::Continuation)
+                .await
+                .into_result()?;
+            if frame.stream_id != stream_id {
+                return Err(eyre!(
+                    "expected CONTINUATION frame on stream {stream_id}, got {frame:?}"
+                ));
+            }
+            block.extend_from_slice(&payload[..]);
+        }
+
+        self.decode_headers(block.into())
+    }
+
+    /// Reads frames into `responses` until `done` says so, the peer hangs up,
+    /// or nothing happens for [Config::timeout]. The last two aren't errors:
+    /// `responses` says how far things got. All field blocks are decoded, on
+    /// whichever stream, so that the HPACK decoder stays in sync.
+    pub async fn read_responses(
+        &mut self,
+        responses: &mut Responses,
+        done: impl Fn(&Responses) -> bool,
+    ) -> eyre::Result<()> {
+        while !done(responses) {
+            let ev = match self.next_decoded_event().await? {
+                DecodedEvent::Headers {
+                    stream_id,
+                    headers,
+                    end_stream,
+                } => {
+                    let response = responses.streams.entry(stream_id).or_default();
+                    // trailers don't have a status
+                    if response.status.is_none() {
+                        let status = headers.status()?;
+                        if status >= 200 {
+                            response.status = Some(status);
+                            response.headers = Some(headers);
+                        }
+                    }
+                    if end_stream {
+                        response.end = Some(StreamEnd::EndStream);
+                    }
+                    continue;
+                }
+                DecodedEvent::Other(ev) => ev,
+            };
+            let (frame, payload) = match ev {
+                NextEvent::Ev(Ev::Frame { frame, payload }) => (frame, payload),
+                NextEvent::Timeout { .. } => break,
+                NextEvent::Eof | NextEvent::Ev(Ev::Eof | Ev::IoError { .. }) => {
+                    responses.closed = true;
+                    break;
+                }
+                NextEvent::Ev(Ev::ProtocolViolation { reason }) => {
+                    return Err(eyre!("peer violated the protocol: {reason}"));
+                }
+                NextEvent::Ev(Ev::Headers { .. }) => {
+                    unreachable!("next_decoded_event decodes those")
+                }
+                NextEvent::Ev(Ev::Raw { .. }) => {
+                    return Err(eyre!("can't read responses while raw mode is on"));
+                }
+            };
+
+            let stream_id = frame.stream_id;
+            match frame.frame_type {
+                FrameType::Data(_) => {
+                    if frame.is_end_stream() {
+                        let response = responses.streams.entry(stream_id).or_default();
+                        response.end = Some(StreamEnd::EndStream);
+                    }
+                }
+                _ => match Payload::decode(&frame, payload)? {
+                    Payload::RstStream(rst) => {
+                        let response = responses.streams.entry(stream_id).or_default();
+                        response.end = Some(StreamEnd::Reset(rst.error_code));
+                    }
+                    Payload::GoAway(goaway) => responses.goaway = Some(goaway),
+                    Payload::WindowUpdate(update) if stream_id == StreamId::CONNECTION => {
+                        responses.connection_window_increments += update.increment as u64;
+                    }
+                    _ => {}
+                },
+            }
+        }
+        Ok(())
+    }
+
     pub async fn send_empty_post_to_root(&mut self, stream_id: StreamId) -> eyre::Result<()> {
         self.encode_and_write_headers(
             stream_id,
@@ -828,6 +2464,257 @@ impl<IO: IntoHalves> Conn<IO> {
         .await
     }
 
+    /// Opens `stream_id` with a `method` request for `path`. Unless
+    /// `end_stream` is set, the request body is still to come, cf.
+    /// [Conn::write_data]: the stream stays open, which is how several
+    /// requests can be in flight at once.
+    pub async fn write_request(
+        &mut self,
+        stream_id: StreamId,
+        method: &'static str,
+        path: &str,
+        end_stream: bool,
+    ) -> eyre::Result<()> {
+        let mut headers = self.common_headers(method);
+        headers.replace(":path", path.to_owned().into_bytes());
+
+        let mut flags = BitFlags::from(HeadersFlags::EndHeaders);
+        if end_stream {
+            flags |= HeadersFlags::EndStream;
+        }
+        self.encode_and_write_headers(stream_id, flags, &headers)
+            .await
+    }
+
+    /// Opens `count` streams with [Conn::write_request], starting at `first`
+    /// and going up by two, none of them with END_STREAM: they all stay open
+    /// until we end or reset them. Returns their IDs, in order.
+    pub async fn open_streams(
+        &mut self,
+        first: StreamId,
+        count: u32,
+    ) -> eyre::Result<Vec<StreamId>> {
+        let path = self.config.path.clone();
+        let mut stream_ids = Vec::with_capacity(count as usize);
+        for i in 0..count {
+            let stream_id = StreamId(first.0 + i * 2);
+            self.write_request(stream_id, "POST", &path, false).await?;
+            stream_ids.push(stream_id);
+        }
+        Ok(stream_ids)
+    }
+
+    /// Sends `req` on the stream after the highest one opened so far, cf.
+    /// [Conn::next_stream_id]: its header block, in CONTINUATION frames if
+    /// it doesn't fit in one frame, then its body and its trailers, if any.
+    /// Frames never exceed the peer's SETTINGS_MAX_FRAME_SIZE, but flow
+    /// control is left to the caller: bodies that don't fit in the peer's
+    /// windows will stall.
+    pub async fn send_request(&mut self, req: Request) -> eyre::Result<PendingResponse> {
+        let stream_id = self.next_stream_id();
+
+        let mut headers = self.common_headers(req.method);
+        if let Some(path) = req.path {
+            headers.replace(":path", path.into_bytes());
+        }
+        headers.extend(req.headers);
+
+        let end_stream = req.body.is_empty() && req.trailers.is_empty();
+        self.write_header_block(stream_id, &headers, end_stream)
+            .await?;
+
+        let max_frame_size = self.settings.max_frame_size as usize;
+        let num_chunks = req.body.len().div_ceil(max_frame_size);
+        for (i, chunk) in req.body.chunks(max_frame_size).enumerate() {
+            let end_stream = i + 1 == num_chunks && req.trailers.is_empty();
+            self.write_data(stream_id, end_stream, chunk.to_vec())
+                .await?;
+        }
+
+        if !req.trailers.is_empty() {
+            self.write_trailers(stream_id, &req.trailers).await?;
+        }
+
+        Ok(PendingResponse { stream_id })
+    }
+
+    /// Ends `stream_id` with a trailer section: a HEADERS frame with
+    /// END_STREAM, followed by CONTINUATION frames if `trailers` don't fit in
+    /// one frame. Nothing checks that they're valid trailers, so that tests
+    /// can send ones with pseudo-headers in them.
+    pub async fn write_trailers(
+        &mut self,
+        stream_id: StreamId,
+        trailers: &Headers,
+    ) -> eyre::Result<()> {
+        self.write_header_block(stream_id, trailers, true).await
+    }
+
+    /// Encodes `headers` and sends them on `stream_id`: in a single HEADERS
+    /// frame if they fit in the peer's SETTINGS_MAX_FRAME_SIZE, followed by
+    /// CONTINUATION frames otherwise
+    async fn write_header_block(
+        &mut self,
+        stream_id: StreamId,
+        headers: &Headers,
+        end_stream: bool,
+    ) -> eyre::Result<()> {
+        let block = self.encode_headers(headers)?;
+        let mut fragments = block.chunks(self.settings.max_frame_size as usize);
+        let first = fragments.next().unwrap_or_default();
+        let mut rest = fragments.peekable();
+
+        let mut flags = BitFlags::<HeadersFlags>::empty();
+        if end_stream {
+            flags |= HeadersFlags::EndStream;
+        }
+        if rest.peek().is_none() {
+            flags |= HeadersFlags::EndHeaders;
+        }
+        self.write_headers(stream_id, flags, first.to_vec().into())
+            .await?;
+
+        while let Some(fragment) = rest.next() {
+            let flags = if rest.peek().is_none() {
+                ContinuationFlags::EndHeaders.into()
+            } else {
+                BitFlags::empty()
+            };
+            self.write_continuation(stream_id, flags, fragment.to_vec().into())
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Reads the response to `pending`, up to END_STREAM, trailers
+    /// included. Field blocks on other streams are decoded too, to keep the
+    /// HPACK decoder in sync, but otherwise skipped. The flow-control
+    /// window the response body takes up is granted back as it comes in, so
+    /// that bodies of any size make it through.
+    ///
+    /// Fails if the stream gets reset, if the peer sends a GOAWAY that
+    /// leaves it out, hangs up, or goes quiet for [Config::timeout] first.
+    pub async fn read_response(&mut self, pending: PendingResponse) -> eyre::Result<Response> {
+        let stream_id = pending.stream_id;
+        let mut final_headers: Option<(u16, Headers)> = None;
+        let mut body = Vec::new();
+        let mut trailers = None;
+
+        loop {
+            let ev = match self.next_decoded_event().await? {
+                DecodedEvent::Headers {
+                    stream_id: id,
+                    headers,
+                    end_stream,
+                } => {
+                    if id != stream_id {
+                        continue;
+                    }
+                    if final_headers.is_some() {
+                        eyre::ensure!(
+                            end_stream,
+                            "trailers on stream {stream_id} don't have END_STREAM set"
+                        );
+                        trailers = Some(headers);
+                    } else {
+                        let status = headers.status()?;
+                        if status >= 200 {
+                            final_headers = Some((status, headers));
+                        }
+                    }
+                    if end_stream {
+                        break;
+                    }
+                    continue;
+                }
+                DecodedEvent::Other(ev) => ev,
+            };
+            let (frame, payload) = match ev {
+                NextEvent::Ev(Ev::Frame { frame, payload }) => (frame, payload),
+                NextEvent::Timeout { waited } => {
+                    return Err(eyre!(
+                        "no response on stream {stream_id} after waiting {waited:?}"
+                    ))
+                }
+                NextEvent::Eof | NextEvent::Ev(Ev::Eof | Ev::IoError { .. }) => {
+                    return Err(eyre!(
+                        "peer hung up before completing the response on stream {stream_id}"
+                    ))
+                }
+                NextEvent::Ev(Ev::ProtocolViolation { reason }) => {
+                    return Err(eyre!("peer violated the protocol: {reason}"));
+                }
+                NextEvent::Ev(Ev::Headers { .. }) => {
+                    unreachable!("next_decoded_event decodes those")
+                }
+                NextEvent::Ev(Ev::Raw { .. }) => {
+                    return Err(eyre!(
+                        "can't read the response on stream {stream_id} while raw mode is on"
+                    ));
+                }
+            };
+
+            let end_stream = frame.is_end_stream() && frame.stream_id == stream_id;
+            match frame.frame_type {
+                FrameType::Data(flags) if frame.stream_id == stream_id => {
+                    eyre::ensure!(
+                        final_headers.is_some(),
+                        "got DATA on stream {stream_id} before the response headers"
+                    );
+                    let mut data = &payload[..];
+                    if flags.contains(DataFlags::Padded) {
+                        let Some((&pad_len, rest)) = data.split_first() else {
+                            return Err(eyre!("padded DATA frame without a pad length"));
+                        };
+                        let Some(len) = rest.len().checked_sub(pad_len as usize) else {
+                            return Err(eyre!("DATA frame padding exceeds its payload"));
+                        };
+                        data = &rest[..len];
+                    }
+                    body.extend_from_slice(data);
+
+                    if frame.len > 0 && !self.write_shut_down {
+                        self.write_window_update(StreamId::CONNECTION, frame.len)
+                            .await?;
+                        if !end_stream {
+                            self.write_window_update(stream_id, frame.len).await?;
+                        }
+                    }
+                }
+                _ => match Payload::decode(&frame, payload)? {
+                    Payload::RstStream(rst) if frame.stream_id == stream_id => {
+                        return Err(eyre!(
+                            "stream {stream_id} got reset with {:?}",
+                            rst.error_code
+                        ));
+                    }
+                    Payload::GoAway(goaway) if goaway.last_stream_id < stream_id => {
+                        return Err(eyre!(
+                            "expected a response on stream {stream_id}, got GOAWAY with {:?} and last stream {}",
+                            goaway.error_code,
+                            goaway.last_stream_id
+                        ));
+                    }
+                    _ => {}
+                },
+            }
+
+            if end_stream {
+                break;
+            }
+        }
+
+        let Some((status, headers)) = final_headers else {
+            return Err(eyre!("stream {stream_id} ended without a final response"));
+        };
+        Ok(Response {
+            status,
+            headers,
+            body,
+            trailers,
+        })
+    }
+
     pub async fn encode_and_write_headers(
         &mut self,
         stream_id: StreamId,
@@ -852,6 +2739,25 @@ impl<IO: IntoHalves> Conn<IO> {
         Ok(())
     }
 
+    /// Like [Self::write_headers], but with the PADDED flag set and
+    /// `pad_length` bytes of padding after the block fragment
+    pub async fn write_headers_padded(
+        &mut self,
+        stream_id: StreamId,
+        flags: impl Into<BitFlags<HeadersFlags>>,
+        block_fragment: Piece,
+        pad_length: u8,
+    ) -> eyre::Result<()> {
+        let flags = flags.into() | HeadersFlags::Padded;
+        let frame = Frame::new(FrameType::Headers(flags), stream_id);
+        let payload = Padded {
+            pad_length,
+            payload: block_fragment,
+        };
+        self.write_frame(frame, payload).await?;
+        Ok(())
+    }
+
     pub async fn write_headers_with_priority(
         &mut self,
         stream_id: StreamId,
@@ -861,6 +2767,7 @@ impl<IO: IntoHalves> Conn<IO> {
     ) -> eyre::Result<()> {
         let flags = flags.into() | HeadersFlags::Priority;
         let frame = Frame::new(FrameType::Headers(flags), stream_id);
+        self.note_stream_opened(&frame);
 
         let payload = block_fragment.into_piece(&mut self.scratch)?;
         let frame = frame.with_len(payload.len().try_into().unwrap());
@@ -868,6 +2775,14 @@ impl<IO: IntoHalves> Conn<IO> {
         let priority_spec_piece = priority_spec.into_piece(&mut self.scratch)?;
 
         let header = frame.into_piece(&mut self.scratch)?;
+        if let Some(recorder) = &self.recorder {
+            let payload = [&priority_spec_piece[..], &payload[..]].concat();
+            recorder.record_frame(Direction::Sent, &frame, &header[..], &payload);
+        }
+        if let Some(chunking) = self.config.chunking.clone() {
+            let buf = [&header[..], &priority_spec_piece[..], &payload[..]].concat();
+            return self.write_chunked(&buf, &chunking).await;
+        }
         self.w
             .writev_all_owned(
                 PieceList::single(header)
@@ -909,6 +2824,28 @@ impl<IO: IntoHalves> Conn<IO> {
         Ok(())
     }
 
+    /// Like [Self::write_data], but with the PADDED flag set and
+    /// `pad_length` bytes of padding after the data
+    pub async fn write_data_padded(
+        &mut self,
+        stream_id: StreamId,
+        end_stream: bool,
+        data: impl Into<Piece>,
+        pad_length: u8,
+    ) -> eyre::Result<()> {
+        let mut flags: BitFlags<DataFlags> = DataFlags::Padded.into();
+        if end_stream {
+            flags |= DataFlags::EndStream;
+        }
+        let frame = Frame::new(FrameType::Data(flags), stream_id);
+        let payload = Padded {
+            pad_length,
+            payload: data.into(),
+        };
+        self.write_frame(frame, payload).await?;
+        Ok(())
+    }
+
     /// Generates a set of dummy headers.
     ///
     /// # Parameters
@@ -947,6 +2884,25 @@ impl<IO: IntoHalves> Conn<IO> {
             .await
     }
 
+    /// Sends a PRIORITY_UPDATE frame (RFC 9218, Section 7.1) for
+    /// `prioritized_stream_id`, on stream 0 where it belongs
+    pub async fn write_priority_update(
+        &mut self,
+        prioritized_stream_id: StreamId,
+        field_value: &str,
+    ) -> eyre::Result<()> {
+        let frame = FrameType::Unknown(EncodedFrameType {
+            ty: PRIORITY_UPDATE_FRAME_TYPE,
+            flags: 0,
+        })
+        .into_frame(StreamId::CONNECTION);
+        let update = PriorityUpdate {
+            prioritized_stream_id,
+            field_value: field_value.to_owned().into_bytes().into(),
+        };
+        self.write_frame(frame, update).await
+    }
+
     async fn write_window_update(
         &mut self,
         stream_id: StreamId,
@@ -959,6 +2915,7 @@ impl<IO: IntoHalves> Conn<IO> {
         let mut rm = RollMut::alloc().unwrap();
         let piece = update.into_piece(&mut rm).unwrap();
         tracing::debug!(?update, "writing window_update, bytes = {:x?}", &piece[..]);
+        self.granted.grant(stream_id, increment as i64);
 
         self.write_frame(FrameType::WindowUpdate.into_frame(stream_id), update)
             .await
@@ -967,8 +2924,7 @@ impl<IO: IntoHalves> Conn<IO> {
     // verify_settings_frame_with_ack verifies whether a SETTINGS frame with
     // ACK flag was received.
     async fn verify_settings_frame_with_ack(&mut self) -> eyre::Result<()> {
-        let (frame, _payload) = self.wait_for_frame(FrameT::Settings).await.unwrap();
-        assert!(frame.is_ack());
+        self.verify_settings_ack().await?;
         Ok(())
     }
 
@@ -985,21 +2941,7 @@ impl<IO: IntoHalves> Conn<IO> {
         )
         .await?;
 
-        let (frame, payload) = self.wait_for_frame(FrameT::Headers).await.unwrap();
-        assert!(
-            frame.is_end_headers(),
-            "the server is free to answer with headers in several frames but this breaks that test"
-        );
-
-        let headers = self.decode_headers(payload.into())?;
-        let status = headers
-            .get_first(&":status".into())
-            .expect("response should contain :status");
-        let status = std::str::from_utf8(&status[..])
-            .expect("status should be valid utf-8")
-            .parse::<u16>()
-            .expect("status should be a u16");
-        assert_eq!(status, expected_status);
+        self.verify_status(stream_id, expected_status).await?;
 
         Ok(())
     }
@@ -1026,6 +2968,12 @@ impl<IO: IntoHalves> Conn<IO> {
     }
 }
 
+/// Tests that need the server to shut a connection down gracefully send a
+/// request for this path: servers under test should answer it, send a
+/// GOAWAY frame, then close the connection once the streams in flight are
+/// done, cf. RFC 9113, Section 6.8.
+pub const GRACEFUL_SHUTDOWN_PATH: &str = "/goaway";
+
 /// Parameters for tests
 pub struct Config {
     /// which host to connect to
@@ -1040,11 +2988,76 @@ pub struct Config {
     /// whether to use TLS
     pub tls: bool,
 
+    /// how long to wait for the server to accept a connection
+    pub connect_timeout: Duration,
+
     /// how long to wait for a frame
     pub timeout: Duration,
 
+    /// how long the peer has to acknowledge our SETTINGS frames, cf.
+    /// [Conn::verify_settings_ack]
+    pub settings_ack_timeout: Duration,
+
     /// maximum length of a header
     pub max_header_len: usize,
+
+    /// SETTINGS_MAX_FRAME_SIZE we advertise in the handshake
+    pub max_frame_size: u32,
+
+    /// SETTINGS_INITIAL_WINDOW_SIZE we advertise in the handshake
+    pub initial_window_size: u32,
+
+    /// SETTINGS_MAX_HEADER_LIST_SIZE we advertise in the handshake, if any
+    pub max_header_list_size: Option<u32>,
+
+    /// whether to record every frame sent and received, cf. [Conn::recorder]
+    pub record_frames: bool,
+
+    /// how long soak tests keep a connection idle (apart from PINGs)
+    pub soak_duration: Duration,
+
+    /// how many streams the rapid reset test opens and resets right away
+    pub rapid_reset_streams: u32,
+
+    /// how many streams per second the rapid reset test opens and resets,
+    /// or `None` to go as fast as possible
+    pub rapid_reset_rate: Option<u32>,
+
+    /// if set, everything we send goes out in writes split this way, cf.
+    /// [chunking]
+    pub chunking: Option<Chunking>,
+
+    /// the largest field block [Conn::assemble_headers] puts together: a
+    /// peer that sends more is a protocol violation
+    pub max_header_block_size: usize,
+
+    /// how many tests a runner runs at once, each over a connection of its
+    /// own, or `None` for as many as there are
+    pub concurrency: Option<usize>,
+}
+
+impl Config {
+    /// `host:port`, or just `host` for the scheme's default port
+    fn authority(&self) -> String {
+        let default_port = if self.tls { 443 } else { 80 };
+        if self.port == default_port {
+            self.host.clone()
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+
+    /// The SETTINGS we send in the handshake
+    pub fn settings(&self) -> Vec<(Setting, u32)> {
+        let mut settings = vec![
+            (Setting::InitialWindowSize, self.initial_window_size),
+            (Setting::MaxFrameSize, self.max_frame_size),
+        ];
+        if let Some(max_header_list_size) = self.max_header_list_size {
+            settings.push((Setting::MaxHeaderListSize, max_header_list_size));
+        }
+        settings
+    }
 }
 
 impl Default for Config {
@@ -1056,8 +3069,26 @@ impl Default for Config {
             tls: false,
 
             max_header_len: 4000,
+            max_frame_size: DEFAULT_FRAME_SIZE,
+            initial_window_size: DEFAULT_WINDOW_SIZE,
+            max_header_list_size: None,
 
+            connect_timeout: Duration::from_millis(250),
             timeout: Duration::from_millis(100),
+            settings_ack_timeout: Duration::from_secs(1),
+
+            record_frames: false,
+
+            soak_duration: Duration::from_secs(1),
+
+            rapid_reset_streams: 10_000,
+            rapid_reset_rate: None,
+
+            chunking: None,
+
+            max_header_block_size: 64 * 1024,
+
+            concurrency: None,
         }
     }
 }
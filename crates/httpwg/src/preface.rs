@@ -0,0 +1,70 @@
+//! Client connection prefaces, the real one and broken ones, so that tests
+//! for RFC 9113, Section 3.4 can say which one to send instead of writing
+//! raw bytes: [crate::Conn::handshake_with_preface] sends one and expects
+//! the handshake to go through, [crate::Conn::verify_preface_rejected]
+//! expects the server to refuse it.
+
+use loona_h2::PREFACE;
+
+use crate::chunking::Chunking;
+
+/// What to send in place of the client connection preface, before our
+/// SETTINGS frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Preface {
+    /// The real thing, in a single write
+    Valid,
+
+    /// The real thing, in writes split this way
+    Split(Chunking),
+
+    /// Only this many bytes of the real thing
+    Truncated(usize),
+
+    /// The real thing with the case of its letters flipped, e.g.
+    /// `pri * http/2.0`
+    WrongCase,
+
+    /// The real thing followed by these bytes, so that the first frame
+    /// doesn't start where it should
+    ExtraBytes(Vec<u8>),
+
+    /// These bytes instead
+    Custom(Vec<u8>),
+}
+
+impl Preface {
+    /// The bytes to send
+    pub fn bytes(&self) -> Vec<u8> {
+        match self {
+            Preface::Valid | Preface::Split(_) => PREFACE.to_vec(),
+            Preface::Truncated(len) => PREFACE[..(*len).min(PREFACE.len())].to_vec(),
+            Preface::WrongCase => PREFACE
+                .iter()
+                .map(|b| match b {
+                    b'a'..=b'z' => b.to_ascii_uppercase(),
+                    b'A'..=b'Z' => b.to_ascii_lowercase(),
+                    _ => *b,
+                })
+                .collect(),
+            Preface::ExtraBytes(extra) => [PREFACE, &extra[..]].concat(),
+            Preface::Custom(bytes) => bytes.clone(),
+        }
+    }
+
+    /// How to split [Preface::bytes] into writes, if at all
+    pub fn chunking(&self) -> Option<&Chunking> {
+        match self {
+            Preface::Split(chunking) => Some(chunking),
+            _ => None,
+        }
+    }
+
+    /// Whether a server should accept it
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Preface::Valid | Preface::Split(_) => true,
+            other => other.bytes() == PREFACE,
+        }
+    }
+}
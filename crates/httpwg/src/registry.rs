@@ -0,0 +1,1437 @@
+//! Every test of every suite, for runners to discover at runtime,
+//! cf. [all_tests]
+
+// This file is automatically @generated by httpwg-gen
+// It is not intended for manual editing
+
+use buffet::IntoHalves;
+
+use crate::{Conn, Group, Requirement, Suite, Test};
+
+/// Every test of every suite, grouped like the `httpwg_macros::tests`
+/// tree, in catalog order
+pub fn all_tests<IO: IntoHalves>() -> Vec<Suite<IO>> {
+    vec![
+        Suite {
+            rfc: "RFC 8441",
+            groups: vec![
+                Group {
+                    title: "3. the settings enable connect protocol settings parameter",
+                    tests: vec![
+                        Test {
+                            name: "advertises settings enable connect protocol",
+                            id: "extended-connect/3/1",
+                            requirement: Requirement::Should,
+                            section: "3",
+                            citation: "Upon receipt of SETTINGS_ENABLE_CONNECT_PROTOCOL with a value of 1,\na client MAY use the Extended CONNECT as defined in this document\nwhen creating new streams.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc8441::_3_the_settings_enable_connect_protocol_settings_parameter::advertises_settings_enable_connect_protocol(conn))),
+                        },
+                        Test {
+                            name: "sends settings enable connect protocol",
+                            id: "extended-connect/3/2",
+                            requirement: Requirement::Should,
+                            section: "3",
+                            citation: "Receipt of this parameter by a server does not have any impact.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc8441::_3_the_settings_enable_connect_protocol_settings_parameter::sends_settings_enable_connect_protocol(conn))),
+                        },
+                    ],
+                },
+                Group {
+                    title: "4. the extended connect method",
+                    tests: vec![
+                        Test {
+                            name: "sends extended connect request",
+                            id: "extended-connect/4/1",
+                            requirement: Requirement::Should,
+                            section: "4",
+                            citation: "Upon receiving a CONNECT request bearing the :protocol pseudo-header\nfield, the server establishes a tunnel to another service of the\nprotocol type indicated by the pseudo-header field.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc8441::_4_the_extended_connect_method::sends_extended_connect_request(conn))),
+                        },
+                        Test {
+                            name: "sends extended connect request without scheme",
+                            id: "extended-connect/4/2",
+                            requirement: Requirement::Must,
+                            section: "4",
+                            citation: "On requests that contain the :protocol pseudo-header field, the\n:scheme and :path pseudo-header fields of the target URI (see\nSection 5) MUST also be included.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc8441::_4_the_extended_connect_method::sends_extended_connect_request_without_scheme(conn))),
+                        },
+                        Test {
+                            name: "sends extended connect request without path",
+                            id: "extended-connect/4/3",
+                            requirement: Requirement::Must,
+                            section: "4",
+                            citation: "On requests that contain the :protocol pseudo-header field, the\n:scheme and :path pseudo-header fields of the target URI (see\nSection 5) MUST also be included.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc8441::_4_the_extended_connect_method::sends_extended_connect_request_without_path(conn))),
+                        },
+                        Test {
+                            name: "sends protocol pseudo header on non connect request",
+                            id: "extended-connect/4/4",
+                            requirement: Requirement::Should,
+                            section: "4",
+                            citation: "A new pseudo-header field :protocol MAY be included on request\nHEADERS indicating the desired protocol to be spoken on the tunnel\ncreated by CONNECT.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc8441::_4_the_extended_connect_method::sends_protocol_pseudo_header_on_non_connect_request(conn))),
+                        },
+                    ],
+                },
+                Group {
+                    title: "5. using extended connect to bootstrap the websocket protocol",
+                    tests: vec![
+                        Test {
+                            name: "sends websocket handshake",
+                            id: "extended-connect/5/1",
+                            requirement: Requirement::Should,
+                            section: "5",
+                            citation: "After successfully processing the opening handshake, the peers\nshould proceed with The WebSocket Protocol [RFC6455] using the\nHTTP/2 stream from the CONNECT transaction as if it were the TCP\nconnection referred to in [RFC6455].",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc8441::_5_using_extended_connect_to_bootstrap_the_websocket_protocol::sends_websocket_handshake(conn))),
+                        },
+                        Test {
+                            name: "sends rst stream cancel on websocket stream",
+                            id: "extended-connect/5/2",
+                            requirement: Requirement::Should,
+                            section: "5",
+                            citation: "RST exceptions are represented with the stream error (Section 5.4.2\nof [RFC7540]) of type CANCEL (Section 7 of [RFC7540]).",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc8441::_5_using_extended_connect_to_bootstrap_the_websocket_protocol::sends_rst_stream_cancel_on_websocket_stream(conn))),
+                        },
+                    ],
+                },
+            ],
+        },
+        Suite {
+            rfc: "RFC 9113",
+            groups: vec![
+                Group {
+                    title: "10. security considerations",
+                    tests: vec![
+                        Test {
+                            name: "sends rapid resets",
+                            id: "http2/10.5/1",
+                            requirement: Requirement::Should,
+                            section: "10.5",
+                            citation: "An endpoint that doesn't monitor use of these features exposes itself\nto a risk of denial of service. Implementations SHOULD track the use\nof these features and set limits on their use. An endpoint MAY treat\nactivity that is suspicious as a connection error (Section 5.4.1) of\ntype ENHANCE_YOUR_CALM.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_10_security_considerations::sends_rapid_resets(conn))),
+                        },
+                        Test {
+                            name: "sends endless continuation frames",
+                            id: "http2/10.5.1/1",
+                            requirement: Requirement::Should,
+                            section: "10.5.1",
+                            citation: "A large field block (Section 8.2) can cause an implementation to\ncommit a large amount of state. [...] A server that receives a larger\nfield block than it is willing to handle can send an HTTP 431 (Request\nHeader Fields Too Large) status code. [...] An endpoint can treat\nthis as a connection error (Section 5.4.1) of type\nENHANCE_YOUR_CALM.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_10_security_considerations::sends_endless_continuation_frames(conn))),
+                        },
+                    ],
+                },
+                Group {
+                    title: "3. starting http2",
+                    tests: vec![
+                        Test {
+                            name: "sends client connection preface",
+                            id: "http2/3.4/1",
+                            requirement: Requirement::Must,
+                            section: "3.4",
+                            citation: "The server connection preface consists of a potentially empty\nSETTINGS frame (Section 6.5) that MUST be the first frame\nthe server sends in the HTTP/2 connection.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_3_starting_http2::sends_client_connection_preface(conn))),
+                        },
+                        Test {
+                            name: "sends invalid connection preface",
+                            id: "http2/3.4/2",
+                            requirement: Requirement::Must,
+                            section: "3.4",
+                            citation: "Clients and servers MUST treat an invalid connection preface as\na connection error (Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_3_starting_http2::sends_invalid_connection_preface(conn))),
+                        },
+                        Test {
+                            name: "sends truncated connection preface",
+                            id: "http2/3.4/3",
+                            requirement: Requirement::Must,
+                            section: "3.4",
+                            citation: "Clients and servers MUST treat an invalid connection preface as\na connection error (Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_3_starting_http2::sends_truncated_connection_preface(conn))),
+                        },
+                        Test {
+                            name: "sends connection preface in wrong case",
+                            id: "http2/3.4/4",
+                            requirement: Requirement::Must,
+                            section: "3.4",
+                            citation: "Clients and servers MUST treat an invalid connection preface as\na connection error (Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_3_starting_http2::sends_connection_preface_in_wrong_case(conn))),
+                        },
+                        Test {
+                            name: "sends connection preface with extra bytes",
+                            id: "http2/3.4/5",
+                            requirement: Requirement::Must,
+                            section: "3.4",
+                            citation: "That is, the connection preface starts with the string\n\"PRI * HTTP/2.0\\r\\n\\r\\nSM\\r\\n\\r\\n\". This sequence MUST be followed\nby a SETTINGS frame (Section 6.5), which MAY be empty.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_3_starting_http2::sends_connection_preface_with_extra_bytes(conn))),
+                        },
+                        Test {
+                            name: "sends connection preface in pieces",
+                            id: "http2/3.4/6",
+                            requirement: Requirement::Should,
+                            section: "3.4",
+                            citation: "The client connection preface starts with a sequence of 24 octets.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_3_starting_http2::sends_connection_preface_in_pieces(conn))),
+                        },
+                    ],
+                },
+                Group {
+                    title: "4. http frames",
+                    tests: vec![
+                        Test {
+                            name: "sends frame with unknown type",
+                            id: "http2/4.1/1",
+                            requirement: Requirement::Must,
+                            section: "4.1",
+                            citation: "Implementations MUST ignore and discard frames of unknown types.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_4_http_frames::sends_frame_with_unknown_type(conn))),
+                        },
+                        Test {
+                            name: "sends frame with unused flags",
+                            id: "http2/4.1/2",
+                            requirement: Requirement::Must,
+                            section: "4.1",
+                            citation: "Unused flags MUST be ignored on receipt and MUST be left\nunset (0x00) when sending.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_4_http_frames::sends_frame_with_unused_flags(conn))),
+                        },
+                        Test {
+                            name: "sends frame with reserved bit set",
+                            id: "http2/4.1/3",
+                            requirement: Requirement::Must,
+                            section: "4.1",
+                            citation: "Reserved: A reserved 1-bit field. The semantics of this bit are\nundefined, and the bit MUST remain unset (0x00) when sending and\nMUST be ignored when receiving.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_4_http_frames::sends_frame_with_reserved_bit_set(conn))),
+                        },
+                        Test {
+                            name: "sends frames in awkward chunks",
+                            id: "http2/4.1/4",
+                            requirement: Requirement::Should,
+                            section: "4.1",
+                            citation: "All frames begin with a fixed 9-octet header followed by a\nvariable-length frame payload.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_4_http_frames::sends_frames_in_awkward_chunks(conn))),
+                        },
+                        Test {
+                            name: "data frame with max length",
+                            id: "http2/4.1/5",
+                            requirement: Requirement::Should,
+                            section: "4.1",
+                            citation: "All frames begin with a fixed 9-octet header followed by a\nvariable-length frame payload.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_4_http_frames::data_frame_with_max_length(conn))),
+                        },
+                        Test {
+                            name: "frame exceeding max size",
+                            id: "http2/4.1/6",
+                            requirement: Requirement::Must,
+                            section: "4.1",
+                            citation: "An endpoint MUST send an error code of FRAME_SIZE_ERROR if a frame\nexceeds the size defined in SETTINGS_MAX_FRAME_SIZE, exceeds any\nlimit defined for the frame type, or is too small to contain mandatory frame\ndata",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_4_http_frames::frame_exceeding_max_size(conn))),
+                        },
+                        Test {
+                            name: "large headers frame exceeding max size",
+                            id: "http2/4.1/7",
+                            requirement: Requirement::Must,
+                            section: "4.1",
+                            citation: "A frame size error in a frame that could alter the state of\nthe entire connection MUST be treated as a connection error\n(Section 5.4.1); this includes any frame carrying a field block\n(Section 4.3) (that is, HEADERS, PUSH_PROMISE, and CONTINUATION),\na SETTINGS frame, and any frame with a stream identifier of 0.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_4_http_frames::large_headers_frame_exceeding_max_size(conn))),
+                        },
+                        Test {
+                            name: "invalid header block fragment",
+                            id: "http2/4.3/1",
+                            requirement: Requirement::Must,
+                            section: "4.3",
+                            citation: "A decoding error in a header block MUST be treated as a connection error\n(Section 5.4.1) of type COMPRESSION_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_4_http_frames::invalid_header_block_fragment(conn))),
+                        },
+                        Test {
+                            name: "header block with invalid index",
+                            id: "http2/4.3/2",
+                            requirement: Requirement::Must,
+                            section: "4.3",
+                            citation: "A decoding error in a field block MUST be treated as a connection error\n(Section 5.4.1) of type COMPRESSION_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_4_http_frames::header_block_with_invalid_index(conn))),
+                        },
+                        Test {
+                            name: "header block with truncated huffman string",
+                            id: "http2/4.3/3",
+                            requirement: Requirement::Must,
+                            section: "4.3",
+                            citation: "A decoding error in a field block MUST be treated as a connection error\n(Section 5.4.1) of type COMPRESSION_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_4_http_frames::header_block_with_truncated_huffman_string(conn))),
+                        },
+                        Test {
+                            name: "dynamic table size update exceeding max",
+                            id: "http2/4.3/4",
+                            requirement: Requirement::Must,
+                            section: "4.3",
+                            citation: "A decoding error in a field block MUST be treated as a connection error\n(Section 5.4.1) of type COMPRESSION_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_4_http_frames::dynamic_table_size_update_exceeding_max(conn))),
+                        },
+                        Test {
+                            name: "priority frame while sending headers",
+                            id: "http2/4.3/5",
+                            requirement: Requirement::Must,
+                            section: "4.3",
+                            citation: "Each header block is processed as a discrete unit. Header blocks\nMUST be transmitted as a contiguous sequence of frames, with no\ninterleaved frames of any other type or from any other stream.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_4_http_frames::priority_frame_while_sending_headers(conn))),
+                        },
+                        Test {
+                            name: "response field blocks are contiguous",
+                            id: "http2/4.3/6",
+                            requirement: Requirement::Must,
+                            section: "4.3",
+                            citation: "Each field block is processed as a discrete unit. Field blocks MUST be\ntransmitted as a contiguous sequence of frames, with no interleaved\nframes of any other type or from any other stream.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_4_http_frames::response_field_blocks_are_contiguous(conn))),
+                        },
+                        Test {
+                            name: "headers frame to another stream",
+                            id: "http2/4.3/7",
+                            requirement: Requirement::Must,
+                            section: "4.3",
+                            citation: "Each header block is processed as a discrete unit. Header blocks\nMUST be transmitted as a contiguous sequence of frames, with no\ninterleaved frames of any other type or from any other stream.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_4_http_frames::headers_frame_to_another_stream(conn))),
+                        },
+                    ],
+                },
+                Group {
+                    title: "5. streams and multiplexing",
+                    tests: vec![
+                        Test {
+                            name: "idle sends data frame",
+                            id: "http2/5.1/1",
+                            requirement: Requirement::Must,
+                            section: "5.1",
+                            citation: "idle:\nReceiving any frame other than HEADERS or PRIORITY on a stream\nin this state MUST be treated as a connection error\n(Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::idle_sends_data_frame(conn))),
+                        },
+                        Test {
+                            name: "idle sends rst stream frame",
+                            id: "http2/5.1/2",
+                            requirement: Requirement::Must,
+                            section: "5.1",
+                            citation: "idle:\nReceiving any frame other than HEADERS or PRIORITY on a stream\nin this state MUST be treated as a connection error\n(Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::idle_sends_rst_stream_frame(conn))),
+                        },
+                        Test {
+                            name: "idle sends window update frame",
+                            id: "http2/5.1/3",
+                            requirement: Requirement::Must,
+                            section: "5.1",
+                            citation: "idle:\nReceiving any frame other than HEADERS or PRIORITY on a stream\nin this state MUST be treated as a connection error\n(Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::idle_sends_window_update_frame(conn))),
+                        },
+                        Test {
+                            name: "idle sends continuation frame",
+                            id: "http2/5.1/4",
+                            requirement: Requirement::Must,
+                            section: "5.1",
+                            citation: "idle:\nReceiving any frame other than HEADERS or PRIORITY on a stream\nin this state MUST be treated as a connection error\n(Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::idle_sends_continuation_frame(conn))),
+                        },
+                        Test {
+                            name: "half closed remote sends data frame",
+                            id: "http2/5.1/5",
+                            requirement: Requirement::Must,
+                            section: "5.1",
+                            citation: "half-closed (remote):\nIf an endpoint receives additional frames, other than\nWINDOW_UPDATE, PRIORITY, or RST_STREAM, for a stream that is in\nthis state, it MUST respond with a stream error (Section 5.4.2)\nof type STREAM_CLOSED.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::half_closed_remote_sends_data_frame(conn))),
+                        },
+                        Test {
+                            name: "half closed remote sends headers frame",
+                            id: "http2/5.1/6",
+                            requirement: Requirement::Must,
+                            section: "5.1",
+                            citation: "half-closed (remote):\nIf an endpoint receives additional frames, other than\nWINDOW_UPDATE, PRIORITY, or RST_STREAM, for a stream that is in\nthis state, it MUST respond with a stream error (Section 5.4.2)\nof type STREAM_CLOSED.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::half_closed_remote_sends_headers_frame(conn))),
+                        },
+                        Test {
+                            name: "half closed remote sends continuation frame",
+                            id: "http2/5.1/7",
+                            requirement: Requirement::Must,
+                            section: "5.1",
+                            citation: "half-closed (remote):\nIf an endpoint receives additional frames, other than\nWINDOW_UPDATE, PRIORITY, or RST_STREAM, for a stream that is in\nthis state, it MUST respond with a stream error (Section 5.4.2)\nof type STREAM_CLOSED.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::half_closed_remote_sends_continuation_frame(conn))),
+                        },
+                        Test {
+                            name: "half closed remote sends window update frame",
+                            id: "http2/5.1/8",
+                            requirement: Requirement::Must,
+                            section: "5.1",
+                            citation: "half-closed (remote):\nIf an endpoint receives additional frames, other than\nWINDOW_UPDATE, PRIORITY, or RST_STREAM, for a stream that is in\nthis state, it MUST respond with a stream error (Section 5.4.2)\nof type STREAM_CLOSED.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::half_closed_remote_sends_window_update_frame(conn))),
+                        },
+                        Test {
+                            name: "half closed remote sends priority frame",
+                            id: "http2/5.1/9",
+                            requirement: Requirement::Must,
+                            section: "5.1",
+                            citation: "half-closed (remote):\nIf an endpoint receives additional frames, other than\nWINDOW_UPDATE, PRIORITY, or RST_STREAM, for a stream that is in\nthis state, it MUST respond with a stream error (Section 5.4.2)\nof type STREAM_CLOSED.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::half_closed_remote_sends_priority_frame(conn))),
+                        },
+                        Test {
+                            name: "half closed remote sends rst stream frame",
+                            id: "http2/5.1/10",
+                            requirement: Requirement::Must,
+                            section: "5.1",
+                            citation: "half-closed (remote):\nIf an endpoint receives additional frames, other than\nWINDOW_UPDATE, PRIORITY, or RST_STREAM, for a stream that is in\nthis state, it MUST respond with a stream error (Section 5.4.2)\nof type STREAM_CLOSED.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::half_closed_remote_sends_rst_stream_frame(conn))),
+                        },
+                        Test {
+                            name: "closed sends data frame after rst stream",
+                            id: "http2/5.1/11",
+                            requirement: Requirement::Must,
+                            section: "5.1",
+                            citation: "closed:\nAn endpoint that receives any frame other than PRIORITY after\nreceiving a RST_STREAM MUST treat that as a stream error\n(Section 5.4.2) of type STREAM_CLOSED.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::closed_sends_data_frame_after_rst_stream(conn))),
+                        },
+                        Test {
+                            name: "closed sends headers frame after rst stream",
+                            id: "http2/5.1/12",
+                            requirement: Requirement::Must,
+                            section: "5.1",
+                            citation: "closed:\nAn endpoint that receives any frame other than PRIORITY after\nreceiving a RST_STREAM MUST treat that as a stream error\n(Section 5.4.2) of type STREAM_CLOSED.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::closed_sends_headers_frame_after_rst_stream(conn))),
+                        },
+                        Test {
+                            name: "closed sends continuation frame after rst stream",
+                            id: "http2/5.1/13",
+                            requirement: Requirement::Must,
+                            section: "5.1",
+                            citation: "closed:\nAn endpoint that receives any frame other than PRIORITY after\nreceiving a RST_STREAM MUST treat that as a stream error\n(Section 5.4.2) of type STREAM_CLOSED.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::closed_sends_continuation_frame_after_rst_stream(conn))),
+                        },
+                        Test {
+                            name: "closed sends data frame",
+                            id: "http2/5.1/14",
+                            requirement: Requirement::Must,
+                            section: "5.1",
+                            citation: "closed:\nAn endpoint that receives any frames after receiving a frame\nwith the END_STREAM flag set MUST treat that as a connection\nerror (Section 6.4.1) of type STREAM_CLOSED.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::closed_sends_data_frame(conn))),
+                        },
+                        Test {
+                            name: "closed sends headers frame",
+                            id: "http2/5.1/15",
+                            requirement: Requirement::Must,
+                            section: "5.1",
+                            citation: "closed:\nAn endpoint that receives any frames after receiving a frame\nwith the END_STREAM flag set MUST treat that as a connection\nerror (Section 6.4.1) of type STREAM_CLOSED.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::closed_sends_headers_frame(conn))),
+                        },
+                        Test {
+                            name: "closed sends continuation frame",
+                            id: "http2/5.1/16",
+                            requirement: Requirement::Must,
+                            section: "5.1",
+                            citation: "closed:\nAn endpoint that receives any frames after receiving a frame\nwith the END_STREAM flag set MUST treat that as a connection\nerror (Section 6.4.1) of type STREAM_CLOSED.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::closed_sends_continuation_frame(conn))),
+                        },
+                        Test {
+                            name: "sends even numbered stream identifier",
+                            id: "http2/5.1/17",
+                            requirement: Requirement::Must,
+                            section: "5.1",
+                            citation: "An endpoint that receives an unexpected stream identifier\nMUST respond with a connection error (Section 5.4.1) of\ntype PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::sends_even_numbered_stream_identifier(conn))),
+                        },
+                        Test {
+                            name: "sends smaller stream identifier",
+                            id: "http2/5.1/18",
+                            requirement: Requirement::Must,
+                            section: "5.1",
+                            citation: "An endpoint that receives an unexpected stream identifier\nMUST respond with a connection error (Section 5.4.1) of\ntype PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::sends_smaller_stream_identifier(conn))),
+                        },
+                        Test {
+                            name: "exceeds concurrent stream limit",
+                            id: "http2/5.1.2/1",
+                            requirement: Requirement::Must,
+                            section: "5.1.2",
+                            citation: "An endpoint that receives an unexpected stream identifier\nMUST respond with a connection error (Section 5.4.1) of\ntype PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::exceeds_concurrent_stream_limit(conn))),
+                        },
+                        Test {
+                            name: "exceeds concurrent stream limit by one",
+                            id: "http2/5.1.2/2",
+                            requirement: Requirement::Must,
+                            section: "5.1.2",
+                            citation: "A peer can limit the number of concurrently active streams using the\nSETTINGS_MAX_CONCURRENT_STREAMS parameter (see Section 6.5.2) within a\nSETTINGS frame. [...]",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::exceeds_concurrent_stream_limit_by_one(conn))),
+                        },
+                        Test {
+                            name: "invalid ping frame for connection close",
+                            id: "http2/5.4.1/1",
+                            requirement: Requirement::Must,
+                            section: "5.4.1",
+                            citation: "After sending the GOAWAY frame for an error condition,\nthe endpoint MUST close the TCP connection.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::invalid_ping_frame_for_connection_close(conn))),
+                        },
+                        Test {
+                            name: "test invalid ping frame for goaway",
+                            id: "http2/5.4.1/2",
+                            requirement: Requirement::Must,
+                            section: "5.4.1",
+                            citation: "After sending the GOAWAY frame for an error condition,\nthe endpoint MUST close the TCP connection.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::test_invalid_ping_frame_for_goaway(conn))),
+                        },
+                        Test {
+                            name: "connection error leaves other connections alone",
+                            id: "http2/5.4.1/3",
+                            requirement: Requirement::Should,
+                            section: "5.4.1",
+                            citation: "A connection error is any error that prevents further processing of\nthe frame layer or corrupts any connection state.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::connection_error_leaves_other_connections_alone(conn))),
+                        },
+                        Test {
+                            name: "unknown extension frame in header block",
+                            id: "http2/5.5/1",
+                            requirement: Requirement::Must,
+                            section: "5.5",
+                            citation: "Extension frames that appear in the middle of a header block\n(Section 4.3) are not permitted; these MUST be treated as\na connection error (Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_5_streams_and_multiplexing::unknown_extension_frame_in_header_block(conn))),
+                        },
+                    ],
+                },
+                Group {
+                    title: "6. frame definitions",
+                    tests: vec![
+                        Test {
+                            name: "sends data frame with zero stream id",
+                            id: "http2/6.1/1",
+                            requirement: Requirement::Must,
+                            section: "6.1",
+                            citation: "DATA frames MUST be associated with a stream. If a DATA frame is\nreceived whose stream identifier field is 0x0, the recipient\nMUST respond with a connection error (Section 5.4.1) of type\nPROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_data_frame_with_zero_stream_id(conn))),
+                        },
+                        Test {
+                            name: "sends data frame on invalid stream state",
+                            id: "http2/6.1/2",
+                            requirement: Requirement::Must,
+                            section: "6.1",
+                            citation: "If a DATA frame is received whose stream is not in \"open\" or\n\"half-closed (local)\" state, the recipient MUST respond with\na stream error (Section 5.4.2) of type STREAM_CLOSED.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_data_frame_on_invalid_stream_state(conn))),
+                        },
+                        Test {
+                            name: "sends data frame with invalid pad length",
+                            id: "http2/6.1/3",
+                            requirement: Requirement::Must,
+                            section: "6.1",
+                            citation: "If the length of the padding is the length of the frame payload\nor greater, the recipient MUST treat this as a connection error\n(Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_data_frame_with_invalid_pad_length(conn))),
+                        },
+                        Test {
+                            name: "sends data frame with pad length equal to payload length",
+                            id: "http2/6.1/4",
+                            requirement: Requirement::Must,
+                            section: "6.1",
+                            citation: "If the length of the padding is the length of the frame payload\nor greater, the recipient MUST treat this as a connection error\n(Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_data_frame_with_pad_length_equal_to_payload_length(conn))),
+                        },
+                        Test {
+                            name: "sends data frames with valid padding",
+                            id: "http2/6.1/5",
+                            requirement: Requirement::Must,
+                            section: "6.1",
+                            citation: "If the length of the padding is the length of the frame payload\nor greater, the recipient MUST treat this as a connection error\n(Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_data_frames_with_valid_padding(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with zero stream id",
+                            id: "http2/6.2/1",
+                            requirement: Requirement::Must,
+                            section: "6.2",
+                            citation: "HEADERS frames MUST be associated with a stream. If a HEADERS\nframe is received whose stream identifier field is 0x0, the\nrecipient MUST respond with a connection error (Section 5.4.1)\nof type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_headers_frame_with_zero_stream_id(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with invalid pad length",
+                            id: "http2/6.2/2",
+                            requirement: Requirement::Must,
+                            section: "6.2",
+                            citation: "The HEADERS frame can include padding. Padding fields and flags\nare identical to those defined for DATA frames (Section 6.1).\nPadding that exceeds the size remaining for the header block\nfragment MUST be treated as a PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_headers_frame_with_invalid_pad_length(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with pad length equal to payload length",
+                            id: "http2/6.2/3",
+                            requirement: Requirement::Must,
+                            section: "6.2",
+                            citation: "The HEADERS frame can include padding. Padding fields and flags\nare identical to those defined for DATA frames (Section 6.1).\nPadding that exceeds the size remaining for the header block\nfragment MUST be treated as a PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_headers_frame_with_pad_length_equal_to_payload_length(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with valid padding",
+                            id: "http2/6.2/4",
+                            requirement: Requirement::Must,
+                            section: "6.2",
+                            citation: "The HEADERS frame can include padding. Padding fields and flags\nare identical to those defined for DATA frames (Section 6.1).\nPadding that exceeds the size remaining for the header block\nfragment MUST be treated as a PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_headers_frame_with_valid_padding(conn))),
+                        },
+                        Test {
+                            name: "sends priority frame with zero stream id",
+                            id: "http2/6.3/1",
+                            requirement: Requirement::Must,
+                            section: "6.3",
+                            citation: "The PRIORITY frame always identifies a stream. If a PRIORITY\nframe is received with a stream identifier of 0x0, the recipient\nMUST respond with a connection error (Section 5.4.1) of type\nPROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_priority_frame_with_zero_stream_id(conn))),
+                        },
+                        Test {
+                            name: "sends priority frame with invalid length",
+                            id: "http2/6.3/2",
+                            requirement: Requirement::Must,
+                            section: "6.3",
+                            citation: "A PRIORITY frame with a length other than 5 octets MUST be\ntreated as a stream error (Section 5.4.2) of type\nFRAME_SIZE_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_priority_frame_with_invalid_length(conn))),
+                        },
+                        Test {
+                            name: "sends rst stream frame with zero stream id",
+                            id: "http2/6.4/1",
+                            requirement: Requirement::Must,
+                            section: "6.4",
+                            citation: "RST_STREAM frames MUST be associated with a stream. If a\nRST_STREAM frame is received with a stream identifier of 0x0,\nthe recipient MUST treat this as a connection error\n(Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_rst_stream_frame_with_zero_stream_id(conn))),
+                        },
+                        Test {
+                            name: "sends rst stream frame on idle stream",
+                            id: "http2/6.4/2",
+                            requirement: Requirement::Must,
+                            section: "6.4",
+                            citation: "RST_STREAM frames MUST NOT be sent for a stream in the \"idle\"\nstate. If a RST_STREAM frame identifying an idle stream is\nreceived, the recipient MUST treat this as a connection error\n(Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_rst_stream_frame_on_idle_stream(conn))),
+                        },
+                        Test {
+                            name: "sends rst stream frame with invalid length",
+                            id: "http2/6.4/3",
+                            requirement: Requirement::Must,
+                            section: "6.4",
+                            citation: "A RST_STREAM frame with a length other than 4 octets MUST be\ntreated as a connection error (Section 5.4.1) of type\nFRAME_SIZE_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_rst_stream_frame_with_invalid_length(conn))),
+                        },
+                        Test {
+                            name: "sends settings frame with ack and payload",
+                            id: "http2/6.5.1/1",
+                            requirement: Requirement::Must,
+                            section: "6.5.1",
+                            citation: "ACK (0x1):\nWhen set, bit 0 indicates that this frame acknowledges receipt\nand application of the peer's SETTINGS frame. When this bit is\nset, the payload of the SETTINGS frame MUST be empty. Receipt of\na SETTINGS frame with the ACK flag set and a length field value\nother than 0 MUST be treated as a connection error (Section 5.4.1)\nof type FRAME_SIZE_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_settings_frame_with_ack_and_payload(conn))),
+                        },
+                        Test {
+                            name: "sends settings frame with non zero stream id",
+                            id: "http2/6.5.1/2",
+                            requirement: Requirement::Must,
+                            section: "6.5.1",
+                            citation: "SETTINGS frames always apply to a connection, never a single\nstream. The stream identifier for a SETTINGS frame MUST be\nzero (0x0). If an endpoint receives a SETTINGS frame whose\nstream identifier field is anything other than 0x0, the\nendpoint MUST respond with a connection error (Section 5.4.1)\nof type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_settings_frame_with_non_zero_stream_id(conn))),
+                        },
+                        Test {
+                            name: "sends settings frame with invalid length",
+                            id: "http2/6.5.1/3",
+                            requirement: Requirement::Must,
+                            section: "6.5.1",
+                            citation: "The SETTINGS frame affects connection state. A badly formed or\nincomplete SETTINGS frame MUST be treated as a connection error\n(Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_settings_frame_with_invalid_length(conn))),
+                        },
+                        Test {
+                            name: "sends settings enable push with invalid value",
+                            id: "http2/6.5.2/1",
+                            requirement: Requirement::Must,
+                            section: "6.5.2",
+                            citation: "SETTINGS_ENABLE_PUSH (0x2):\nThe initial value is 1, which indicates that server push is\npermitted. Any value other than 0 or 1 MUST be treated as a\nconnection error (Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_settings_enable_push_with_invalid_value(conn))),
+                        },
+                        Test {
+                            name: "sends settings enable push with max value",
+                            id: "http2/6.5.2/2",
+                            requirement: Requirement::Must,
+                            section: "6.5.2",
+                            citation: "SETTINGS_ENABLE_PUSH (0x2):\nThe initial value is 1, which indicates that server push is\npermitted. Any value other than 0 or 1 MUST be treated as a\nconnection error (Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_settings_enable_push_with_max_value(conn))),
+                        },
+                        Test {
+                            name: "sends settings enable push with valid values",
+                            id: "http2/6.5.2/3",
+                            requirement: Requirement::Must,
+                            section: "6.5.2",
+                            citation: "SETTINGS_ENABLE_PUSH (0x2):\nThe initial value is 1, which indicates that server push is\npermitted. Any value other than 0 or 1 MUST be treated as a\nconnection error (Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_settings_enable_push_with_valid_values(conn))),
+                        },
+                        Test {
+                            name: "sends settings initial window size with invalid value",
+                            id: "http2/6.5.2/4",
+                            requirement: Requirement::Must,
+                            section: "6.5.2",
+                            citation: "SETTINGS_INITIAL_WINDOW_SIZE (0x4):\nValues above the maximum flow-control window size of 2^31-1\nMUST be treated as a connection error (Section 5.4.1) of\ntype FLOW_CONTROL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_settings_initial_window_size_with_invalid_value(conn))),
+                        },
+                        Test {
+                            name: "sends settings initial window size with max value",
+                            id: "http2/6.5.2/5",
+                            requirement: Requirement::Must,
+                            section: "6.5.2",
+                            citation: "SETTINGS_INITIAL_WINDOW_SIZE (0x4):\nValues above the maximum flow-control window size of 2^31-1\nMUST be treated as a connection error (Section 5.4.1) of\ntype FLOW_CONTROL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_settings_initial_window_size_with_max_value(conn))),
+                        },
+                        Test {
+                            name: "sends settings initial window size with max valid value",
+                            id: "http2/6.5.2/6",
+                            requirement: Requirement::Must,
+                            section: "6.5.2",
+                            citation: "SETTINGS_INITIAL_WINDOW_SIZE (0x4):\nValues above the maximum flow-control window size of 2^31-1\nMUST be treated as a connection error (Section 5.4.1) of\ntype FLOW_CONTROL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_settings_initial_window_size_with_max_valid_value(conn))),
+                        },
+                        Test {
+                            name: "sends settings max frame size with invalid value below initial",
+                            id: "http2/6.5.2/7",
+                            requirement: Requirement::Must,
+                            section: "6.5.2",
+                            citation: "SETTINGS_MAX_FRAME_SIZE (0x5):\nThe initial value is 2^14 (16,384) octets. The value advertised\nby an endpoint MUST be between this initial value and the\nmaximum allowed frame size (2^24-1 or 16,777,215 octets),\ninclusive. Values outside this range MUST be treated as a\nconnection error (Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_settings_max_frame_size_with_invalid_value_below_initial(conn))),
+                        },
+                        Test {
+                            name: "sends settings max frame size with invalid value above max",
+                            id: "http2/6.5.2/8",
+                            requirement: Requirement::Must,
+                            section: "6.5.2",
+                            citation: "SETTINGS_MAX_FRAME_SIZE (0x5):\nThe initial value is 2^14 (16,384) octets. The value advertised\nby an endpoint MUST be between this initial value and the\nmaximum allowed frame size (2^24-1 or 16,777,215 octets),\ninclusive. Values outside this range MUST be treated as a\nconnection error (Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_settings_max_frame_size_with_invalid_value_above_max(conn))),
+                        },
+                        Test {
+                            name: "sends settings max frame size with zero value",
+                            id: "http2/6.5.2/9",
+                            requirement: Requirement::Must,
+                            section: "6.5.2",
+                            citation: "SETTINGS_MAX_FRAME_SIZE (0x5):\nThe initial value is 2^14 (16,384) octets. The value advertised\nby an endpoint MUST be between this initial value and the\nmaximum allowed frame size (2^24-1 or 16,777,215 octets),\ninclusive. Values outside this range MUST be treated as a\nconnection error (Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_settings_max_frame_size_with_zero_value(conn))),
+                        },
+                        Test {
+                            name: "sends settings max frame size with max value",
+                            id: "http2/6.5.2/10",
+                            requirement: Requirement::Must,
+                            section: "6.5.2",
+                            citation: "SETTINGS_MAX_FRAME_SIZE (0x5):\nThe initial value is 2^14 (16,384) octets. The value advertised\nby an endpoint MUST be between this initial value and the\nmaximum allowed frame size (2^24-1 or 16,777,215 octets),\ninclusive. Values outside this range MUST be treated as a\nconnection error (Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_settings_max_frame_size_with_max_value(conn))),
+                        },
+                        Test {
+                            name: "sends settings max frame size with boundary values",
+                            id: "http2/6.5.2/11",
+                            requirement: Requirement::Must,
+                            section: "6.5.2",
+                            citation: "SETTINGS_MAX_FRAME_SIZE (0x5):\nThe initial value is 2^14 (16,384) octets. The value advertised\nby an endpoint MUST be between this initial value and the\nmaximum allowed frame size (2^24-1 or 16,777,215 octets),\ninclusive. Values outside this range MUST be treated as a\nconnection error (Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_settings_max_frame_size_with_boundary_values(conn))),
+                        },
+                        Test {
+                            name: "sends settings frame with unknown identifier",
+                            id: "http2/6.5.2/12",
+                            requirement: Requirement::Must,
+                            section: "6.5.2",
+                            citation: "An endpoint that receives a SETTINGS frame with any unknown\nor unsupported identifier MUST ignore that setting.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_settings_frame_with_unknown_identifier(conn))),
+                        },
+                        Test {
+                            name: "sends multiple values of settings initial window size",
+                            id: "http2/6.5.3/1",
+                            requirement: Requirement::Must,
+                            section: "6.5.3",
+                            citation: "The values in the SETTINGS frame MUST be processed in the order\nthey appear, with no other frame processing between values.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_multiple_values_of_settings_initial_window_size(conn))),
+                        },
+                        Test {
+                            name: "sends settings frame without ack flag",
+                            id: "http2/6.5.3/2",
+                            requirement: Requirement::Must,
+                            section: "6.5.3",
+                            citation: "Once all values have been processed, the recipient MUST\nimmediately emit a SETTINGS frame with the ACK flag set.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_settings_frame_without_ack_flag(conn))),
+                        },
+                        Test {
+                            name: "sends settings frame and expects timely ack",
+                            id: "http2/6.5.3/3",
+                            requirement: Requirement::Must,
+                            section: "6.5.3",
+                            citation: "Once all values have been processed, the recipient MUST\nimmediately emit a SETTINGS frame with the ACK flag set. [...] If the\nsender of a SETTINGS frame does not receive an acknowledgment within a\nreasonable amount of time, it MAY issue a connection error (Section\n5.4.1) of type SETTINGS_TIMEOUT.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_settings_frame_and_expects_timely_ack(conn))),
+                        },
+                        Test {
+                            name: "sends ping frame",
+                            id: "http2/6.7/1",
+                            requirement: Requirement::Must,
+                            section: "6.7",
+                            citation: "Receivers of a PING frame that does not include an ACK flag MUST\nsend a PING frame with the ACK flag set in response, with an\nidentical payload.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_ping_frame(conn))),
+                        },
+                        Test {
+                            name: "sends ping frame with ack",
+                            id: "http2/6.7/2",
+                            requirement: Requirement::Must,
+                            section: "6.7",
+                            citation: "ACK (0x1):\nWhen set, bit 0 indicates that this PING frame is a PING\nresponse. An endpoint MUST set this flag in PING responses.\nAn endpoint MUST NOT respond to PING frames containing this\nflag.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_ping_frame_with_ack(conn))),
+                        },
+                        Test {
+                            name: "sends ping frames with arbitrary payloads",
+                            id: "http2/6.7/3",
+                            requirement: Requirement::Must,
+                            section: "6.7",
+                            citation: "Receivers of a PING frame that does not include an ACK flag MUST\nsend a PING frame with the ACK flag set in response, with an\nidentical payload.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_ping_frames_with_arbitrary_payloads(conn))),
+                        },
+                        Test {
+                            name: "sends multiple ping frames with ack",
+                            id: "http2/6.7/4",
+                            requirement: Requirement::Must,
+                            section: "6.7",
+                            citation: "ACK (0x1):\nWhen set, bit 0 indicates that this PING frame is a PING\nresponse. An endpoint MUST set this flag in PING responses.\nAn endpoint MUST NOT respond to PING frames containing this\nflag.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_multiple_ping_frames_with_ack(conn))),
+                        },
+                        Test {
+                            name: "sends ping frame with non zero stream id",
+                            id: "http2/6.7/5",
+                            requirement: Requirement::Must,
+                            section: "6.7",
+                            citation: "If a PING frame is received with a stream identifier field value\nother than 0x0, the recipient MUST respond with a connection\nerror (Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_ping_frame_with_non_zero_stream_id(conn))),
+                        },
+                        Test {
+                            name: "sends ping frame with invalid length",
+                            id: "http2/6.7/6",
+                            requirement: Requirement::Must,
+                            section: "6.7",
+                            citation: "Receipt of a PING frame with a length field value other than 8\nMUST be treated as a connection error (Section 5.4.1) of type\nFRAME_SIZE_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_ping_frame_with_invalid_length(conn))),
+                        },
+                        Test {
+                            name: "sends goaway frame with non zero stream id",
+                            id: "http2/6.8/1",
+                            requirement: Requirement::Must,
+                            section: "6.8",
+                            citation: "An endpoint MUST treat a GOAWAY frame with a stream identifier\nother than 0x0 as a connection error (Section 5.4.1) of type\nPROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_goaway_frame_with_non_zero_stream_id(conn))),
+                        },
+                        Test {
+                            name: "sends data frames on streams in flight during graceful shutdown",
+                            id: "http2/6.8/2",
+                            requirement: Requirement::Should,
+                            section: "6.8",
+                            citation: "The last stream identifier in the GOAWAY frame contains the\nhighest-numbered stream identifier for which the sender of the\nGOAWAY frame might have taken some action on or might yet take\naction on.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_data_frames_on_streams_in_flight_during_graceful_shutdown(conn))),
+                        },
+                        Test {
+                            name: "sends headers frames after graceful shutdown goaway",
+                            id: "http2/6.8/3",
+                            requirement: Requirement::Should,
+                            section: "6.8",
+                            citation: "Once sent, the sender will ignore frames sent on streams initiated\nby the receiver if the stream has an identifier higher than the\nincluded last stream identifier.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_headers_frames_after_graceful_shutdown_goaway(conn))),
+                        },
+                        Test {
+                            name: "sends window update frame with zero increment",
+                            id: "http2/6.9/1",
+                            requirement: Requirement::Must,
+                            section: "6.9",
+                            citation: "A receiver MUST treat the receipt of a WINDOW_UPDATE frame with\na flow-control window increment of 0 as a stream error\n(Section 5.4.2) of type PROTOCOL_ERROR; errors on the connection\nflow-control window MUST be treated as a connection error\n(Section 5.4.1).",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_window_update_frame_with_zero_increment(conn))),
+                        },
+                        Test {
+                            name: "sends window update frame with zero increment on stream",
+                            id: "http2/6.9/2",
+                            requirement: Requirement::Must,
+                            section: "6.9",
+                            citation: "A receiver MUST treat the receipt of a WINDOW_UPDATE frame with\na flow-control window increment of 0 as a stream error\n(Section 5.4.2) of type PROTOCOL_ERROR; errors on the connection\nflow-control window MUST be treated as a connection error\n(Section 5.4.1).",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_window_update_frame_with_zero_increment_on_stream(conn))),
+                        },
+                        Test {
+                            name: "sends window update frame with invalid length",
+                            id: "http2/6.9/3",
+                            requirement: Requirement::Must,
+                            section: "6.9",
+                            citation: "A WINDOW_UPDATE frame with a length other than 4 octets MUST\nbe treated as a connection error (Section 5.4.1) of type\nFRAME_SIZE_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_window_update_frame_with_invalid_length(conn))),
+                        },
+                        Test {
+                            name: "sends settings frame to set initial window size to 1 and sends headers frame",
+                            id: "http2/6.9.1/1",
+                            requirement: Requirement::Must,
+                            section: "6.9.1",
+                            citation: "The sender MUST NOT send a flow-controlled frame with a length\nthat exceeds the space available in either of the flow-control\nwindows advertised by the receiver.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_settings_frame_to_set_initial_window_size_to_1_and_sends_headers_frame(conn))),
+                        },
+                        Test {
+                            name: "sends multiple window update frames increasing flow control window above max",
+                            id: "http2/6.9.1/2",
+                            requirement: Requirement::Must,
+                            section: "6.9.1",
+                            citation: "A sender MUST NOT allow a flow-control window to exceed 2^31-1\noctets. If a sender receives a WINDOW_UPDATE that causes a\nflow-control window to exceed this maximum, it MUST terminate\neither the stream or the connection, as appropriate.\nFor streams, the sender sends a RST_STREAM with an error code\nof FLOW_CONTROL_ERROR; for the connection, a GOAWAY frame with\nan error code of FLOW_CONTROL_ERROR is sent.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_multiple_window_update_frames_increasing_flow_control_window_above_max(conn))),
+                        },
+                        Test {
+                            name: "sends multiple window update frames increasing flow control window above max on stream",
+                            id: "http2/6.9.1/3",
+                            requirement: Requirement::Must,
+                            section: "6.9.1",
+                            citation: "A sender MUST NOT allow a flow-control window to exceed 2^31-1\noctets. If a sender receives a WINDOW_UPDATE that causes a\nflow-control window to exceed this maximum, it MUST terminate\neither the stream or the connection, as appropriate.\nFor streams, the sender sends a RST_STREAM with an error code\nof FLOW_CONTROL_ERROR; for the connection, a GOAWAY frame with\nan error code of FLOW_CONTROL_ERROR is sent.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_multiple_window_update_frames_increasing_flow_control_window_above_max_on_stream(conn))),
+                        },
+                        Test {
+                            name: "sends window update frame increasing flow control window to max",
+                            id: "http2/6.9.1/4",
+                            requirement: Requirement::Must,
+                            section: "6.9.1",
+                            citation: "A sender MUST NOT allow a flow-control window to exceed 2^31-1\noctets.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_window_update_frame_increasing_flow_control_window_to_max(conn))),
+                        },
+                        Test {
+                            name: "sends window update frame increasing flow control window to max plus one",
+                            id: "http2/6.9.1/5",
+                            requirement: Requirement::Must,
+                            section: "6.9.1",
+                            citation: "A sender MUST NOT allow a flow-control window to exceed 2^31-1\noctets. If a sender receives a WINDOW_UPDATE that causes a\nflow-control window to exceed this maximum, it MUST terminate\neither the stream or the connection, as appropriate.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_window_update_frame_increasing_flow_control_window_to_max_plus_one(conn))),
+                        },
+                        Test {
+                            name: "sends window update frame increasing flow control window to max plus one on stream",
+                            id: "http2/6.9.1/6",
+                            requirement: Requirement::Must,
+                            section: "6.9.1",
+                            citation: "A sender MUST NOT allow a flow-control window to exceed 2^31-1\noctets. If a sender receives a WINDOW_UPDATE that causes a\nflow-control window to exceed this maximum, it MUST terminate\neither the stream or the connection, as appropriate.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_window_update_frame_increasing_flow_control_window_to_max_plus_one_on_stream(conn))),
+                        },
+                        Test {
+                            name: "changes settings initial window size after sending headers frame",
+                            id: "http2/6.9.2/1",
+                            requirement: Requirement::Must,
+                            section: "6.9.2",
+                            citation: "When the value of SETTINGS_INITIAL_WINDOW_SIZE changes,\na receiver MUST adjust the size of all stream flow-control\nwindows that it maintains by the difference between the new\nvalue and the old value.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::changes_settings_initial_window_size_after_sending_headers_frame(conn))),
+                        },
+                        Test {
+                            name: "sends settings frame for window size to be negative",
+                            id: "http2/6.9.2/2",
+                            requirement: Requirement::Must,
+                            section: "6.9.2",
+                            citation: "A sender MUST track the negative flow-control window and\nMUST NOT send new flow-controlled frames until it receives\nWINDOW_UPDATE frames that cause the flow-control window to\nbecome positive.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_settings_frame_for_window_size_to_be_negative(conn))),
+                        },
+                        Test {
+                            name: "sends settings initial window size with exceeded max window size value",
+                            id: "http2/6.9.2/3",
+                            requirement: Requirement::Must,
+                            section: "6.9.2",
+                            citation: "An endpoint MUST treat a change to SETTINGS_INITIAL_WINDOW_SIZE\nthat causes any flow-control window to exceed the maximum size\nas a connection error (Section 5.4.1) of type FLOW_CONTROL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_settings_initial_window_size_with_exceeded_max_window_size_value(conn))),
+                        },
+                        Test {
+                            name: "sends settings initial window size making stream window exceed max",
+                            id: "http2/6.9.2/4",
+                            requirement: Requirement::Must,
+                            section: "6.9.2",
+                            citation: "An endpoint MUST treat a change to SETTINGS_INITIAL_WINDOW_SIZE\nthat causes any flow-control window to exceed the maximum size\nas a connection error (Section 5.4.1) of type FLOW_CONTROL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_settings_initial_window_size_making_stream_window_exceed_max(conn))),
+                        },
+                        Test {
+                            name: "sends multiple continuation frames preceded by headers frame",
+                            id: "http2/6.10/1",
+                            requirement: Requirement::Should,
+                            section: "6.10",
+                            citation: "The CONTINUATION frame (type=0x9) is used to continue a sequence\nof header block fragments (Section 4.3). Any number of\nCONTINUATION frames can be sent, as long as the preceding frame\nis on the same stream and is a HEADERS, PUSH_PROMISE,\nor CONTINUATION frame without the END_HEADERS flag set.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_multiple_continuation_frames_preceded_by_headers_frame(conn))),
+                        },
+                        Test {
+                            name: "sends continuation frame followed by non continuation frame",
+                            id: "http2/6.10/2",
+                            requirement: Requirement::Must,
+                            section: "6.10",
+                            citation: "END_HEADERS (0x4):\nIf the END_HEADERS bit is not set, this frame MUST be followed\nby another CONTINUATION frame. A receiver MUST treat the receipt\nof any other type of frame or a frame on a different stream as\na connection error (Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_continuation_frame_followed_by_non_continuation_frame(conn))),
+                        },
+                        Test {
+                            name: "sends continuation frame with zero stream id",
+                            id: "http2/6.10/3",
+                            requirement: Requirement::Must,
+                            section: "6.10",
+                            citation: "CONTINUATION frames MUST be associated with a stream. If a\nCONTINUATION frame is received whose stream identifier field is\n0x0, the recipient MUST respond with a connection error\n(Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_continuation_frame_with_zero_stream_id(conn))),
+                        },
+                        Test {
+                            name: "sends continuation frame preceded by headers frame with end headers flag",
+                            id: "http2/6.10/4",
+                            requirement: Requirement::Must,
+                            section: "6.10",
+                            citation: "A CONTINUATION frame MUST be preceded by a HEADERS, PUSH_PROMISE\nor CONTINUATION frame without the END_HEADERS flag set.\nA recipient that observes violation of this rule MUST respond\nwith a connection error (Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_continuation_frame_preceded_by_headers_frame_with_end_headers_flag(conn))),
+                        },
+                        Test {
+                            name: "sends continuation frame preceded by continuation frame with end headers flag",
+                            id: "http2/6.10/5",
+                            requirement: Requirement::Must,
+                            section: "6.10",
+                            citation: "A CONTINUATION frame MUST be preceded by a HEADERS, PUSH_PROMISE\nor CONTINUATION frame without the END_HEADERS flag set.\nA recipient that observes violation of this rule MUST respond\nwith a connection error (Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_continuation_frame_preceded_by_continuation_frame_with_end_headers_flag(conn))),
+                        },
+                        Test {
+                            name: "sends continuation frame preceded by data frame",
+                            id: "http2/6.10/6",
+                            requirement: Requirement::Must,
+                            section: "6.10",
+                            citation: "A CONTINUATION frame MUST be preceded by a HEADERS, PUSH_PROMISE\nor CONTINUATION frame without the END_HEADERS flag set.\nA recipient that observes violation of this rule MUST respond\nwith a connection error (Section 5.4.1) of type PROTOCOL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_6_frame_definitions::sends_continuation_frame_preceded_by_data_frame(conn))),
+                        },
+                    ],
+                },
+                Group {
+                    title: "7. error codes",
+                    tests: vec![
+                        Test {
+                            name: "sends goaway frame with unknown error code",
+                            id: "http2/7/1",
+                            requirement: Requirement::Must,
+                            section: "7",
+                            citation: "Unknown or unsupported error codes MUST NOT trigger any special\nbehavior. These MAY be treated by an implementation as being\nequivalent to INTERNAL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_7_error_codes::sends_goaway_frame_with_unknown_error_code(conn))),
+                        },
+                        Test {
+                            name: "sends rst stream frame with unknown error code",
+                            id: "http2/7/2",
+                            requirement: Requirement::Must,
+                            section: "7",
+                            citation: "Unknown or unsupported error codes MUST NOT trigger any special\nbehavior. These MAY be treated by an implementation as being\nequivalent to INTERNAL_ERROR.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_7_error_codes::sends_rst_stream_frame_with_unknown_error_code(conn))),
+                        },
+                    ],
+                },
+                Group {
+                    title: "8. expressing http semantics in http2",
+                    tests: vec![
+                        Test {
+                            name: "sends second headers frame without end stream",
+                            id: "http2/8.1/1",
+                            requirement: Requirement::Must,
+                            section: "8.1",
+                            citation: "",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_second_headers_frame_without_end_stream(conn))),
+                        },
+                        Test {
+                            name: "sends request with content and trailers",
+                            id: "http2/8.1/2",
+                            requirement: Requirement::Should,
+                            section: "8.1",
+                            citation: "An HTTP message (request or response) consists of:",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_request_with_content_and_trailers(conn))),
+                        },
+                        Test {
+                            name: "sends request with trailers and no content",
+                            id: "http2/8.1/3",
+                            requirement: Requirement::Should,
+                            section: "8.1",
+                            citation: "An HTTP message (request or response) consists of: [...] 2. zero or\nmore DATA frames containing the message content [...] and 3.\noptionally, one HEADERS frame (followed by zero or more CONTINUATION\nframes) containing the trailer section.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_request_with_trailers_and_no_content(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with incorrect content length single data frame",
+                            id: "http2/8.1/4",
+                            requirement: Requirement::Should,
+                            section: "8.1",
+                            citation: "An HTTP message (request or response) consists of: [...] 2. zero or\nmore DATA frames containing the message content [...] and 3.\noptionally, one HEADERS frame (followed by zero or more CONTINUATION\nframes) containing the trailer section.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_incorrect_content_length_single_data_frame(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with incorrect content length multiple data frames",
+                            id: "http2/8.1/5",
+                            requirement: Requirement::Should,
+                            section: "8.1",
+                            citation: "An HTTP message (request or response) consists of: [...] 2. zero or\nmore DATA frames containing the message content [...] and 3.\noptionally, one HEADERS frame (followed by zero or more CONTINUATION\nframes) containing the trailer section.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_incorrect_content_length_multiple_data_frames(conn))),
+                        },
+                        Test {
+                            name: "response content length matches content",
+                            id: "http2/8.1/6",
+                            requirement: Requirement::Should,
+                            section: "8.1",
+                            citation: "A request or response is also malformed if the value of a\ncontent-length header field does not equal the sum of the DATA frame\npayload lengths that form the content, unless the message is defined as\nhaving no content.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::response_content_length_matches_content(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with uppercase field name",
+                            id: "http2/8.1/7",
+                            requirement: Requirement::Must,
+                            section: "8.1",
+                            citation: "A field name MUST NOT contain characters in the ranges 0x00-0x20, 0x41-0x5a,\nor 0x7f-0xff (all ranges inclusive). This specifically excludes all\nnon-visible ASCII characters, ASCII SP (0x20), and uppercase characters ('A'\nto 'Z', ASCII 0x41 to 0x5a).",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_uppercase_field_name(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with space in field name",
+                            id: "http2/8.1/8",
+                            requirement: Requirement::Must,
+                            section: "8.1",
+                            citation: "A field name MUST NOT contain characters in the ranges 0x00-0x20, 0x41-0x5a,\nor 0x7f-0xff (all ranges inclusive). This specifically excludes all\nnon-visible ASCII characters, ASCII SP (0x20), and uppercase characters ('A'\nto 'Z', ASCII 0x41 to 0x5a).",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_space_in_field_name(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with non visible ascii",
+                            id: "http2/8.1/9",
+                            requirement: Requirement::Must,
+                            section: "8.1",
+                            citation: "A field name MUST NOT contain characters in the ranges 0x00-0x20, 0x41-0x5a,\nor 0x7f-0xff (all ranges inclusive). This specifically excludes all\nnon-visible ASCII characters, ASCII SP (0x20), and uppercase characters ('A'\nto 'Z', ASCII 0x41 to 0x5a).",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_non_visible_ascii(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with del character",
+                            id: "http2/8.1/10",
+                            requirement: Requirement::Must,
+                            section: "8.1",
+                            citation: "A field name MUST NOT contain characters in the ranges 0x00-0x20, 0x41-0x5a,\nor 0x7f-0xff (all ranges inclusive). This specifically excludes all\nnon-visible ASCII characters, ASCII SP (0x20), and uppercase characters ('A'\nto 'Z', ASCII 0x41 to 0x5a).",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_del_character(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with non ascii character",
+                            id: "http2/8.1/11",
+                            requirement: Requirement::Must,
+                            section: "8.1",
+                            citation: "A field name MUST NOT contain characters in the ranges 0x00-0x20, 0x41-0x5a,\nor 0x7f-0xff (all ranges inclusive). This specifically excludes all\nnon-visible ASCII characters, ASCII SP (0x20), and uppercase characters ('A'\nto 'Z', ASCII 0x41 to 0x5a).",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_non_ascii_character(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with colon in field name",
+                            id: "http2/8.1/12",
+                            requirement: Requirement::Must,
+                            section: "8.1",
+                            citation: "With the exception of pseudo-header fields (Section 8.3), which have a name\nthat starts with a single colon, field names MUST NOT include a colon (ASCII\nCOLON, 0x3a).",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_colon_in_field_name(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with lf in field value",
+                            id: "http2/8.1/13",
+                            requirement: Requirement::Must,
+                            section: "8.1",
+                            citation: "A field value MUST NOT contain the zero value (ASCII NUL, 0x00), line feed\n(ASCII LF, 0x0a), or carriage return (ASCII CR, 0x0d) at any position.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_lf_in_field_value(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with cr in field value",
+                            id: "http2/8.1/14",
+                            requirement: Requirement::Must,
+                            section: "8.1",
+                            citation: "A field value MUST NOT contain the zero value (ASCII NUL, 0x00), line feed\n(ASCII LF, 0x0a), or carriage return (ASCII CR, 0x0d) at any position.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_cr_in_field_value(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with nul in field value",
+                            id: "http2/8.1/15",
+                            requirement: Requirement::Must,
+                            section: "8.1",
+                            citation: "A field value MUST NOT contain the zero value (ASCII NUL, 0x00), line feed\n(ASCII LF, 0x0a), or carriage return (ASCII CR, 0x0d) at any position.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_nul_in_field_value(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with leading space in field value",
+                            id: "http2/8.1/16",
+                            requirement: Requirement::Must,
+                            section: "8.1",
+                            citation: "A field value MUST NOT start or end with an ASCII whitespace character\n(ASCII SP or HTAB, 0x20 or 0x09).\nWhen a request message violates one of these requirements, an implementation\nSHOULD generate a 400 (Bad Request) status code (see Section 15.5.1 of\nHTTP), unless a more suitable status code is defined or the status code\ncannot be sent (e.g., because the error occurs in a trailer field).",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_leading_space_in_field_value(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with trailing tab in field value",
+                            id: "http2/8.1/17",
+                            requirement: Requirement::Must,
+                            section: "8.1",
+                            citation: "A field value MUST NOT start or end with an ASCII whitespace character\n(ASCII SP or HTAB, 0x20 or 0x09).\nWhen a request message violates one of these requirements, an implementation\nSHOULD generate a 400 (Bad Request) status code (see Section 15.5.1 of\nHTTP), unless a more suitable status code is defined or the status code\ncannot be sent (e.g., because the error occurs in a trailer field).",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_trailing_tab_in_field_value(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with connection header",
+                            id: "http2/8.2.2/1",
+                            requirement: Requirement::Must,
+                            section: "8.2.2",
+                            citation: "HTTP/2 does not use the Connection header field (Section 7.6.1 of HTTP) to\nindicate connection-specific header fields; in this protocol,\nconnection-specific metadata is conveyed by other means. An endpoint MUST\nNOT generate an HTTP/2 message containing connection-specific header fields.\nThis includes the Connection header field and those listed as having\nconnection-specific semantics in Section 7.6.1 of HTTP (that is,\nProxy-Connection, Keep-Alive, Transfer-Encoding, and Upgrade). Any message\ncontaining connection-specific header fields MUST be treated as malformed\n(Section 8.1.1).",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_connection_header(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with proxy connection header",
+                            id: "http2/8.2.2/2",
+                            requirement: Requirement::Must,
+                            section: "8.2.2",
+                            citation: "HTTP/2 does not use the Connection header field (Section 7.6.1 of HTTP) to\nindicate connection-specific header fields; in this protocol,\nconnection-specific metadata is conveyed by other means. An endpoint MUST\nNOT generate an HTTP/2 message containing connection-specific header fields.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_proxy_connection_header(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with keep alive header",
+                            id: "http2/8.2.2/3",
+                            requirement: Requirement::Must,
+                            section: "8.2.2",
+                            citation: "HTTP/2 does not use the Connection header field (Section 7.6.1 of HTTP) to\nindicate connection-specific header fields; in this protocol,\nconnection-specific metadata is conveyed by other means. An endpoint MUST\nNOT generate an HTTP/2 message containing connection-specific header fields.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_keep_alive_header(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with transfer encoding header",
+                            id: "http2/8.2.2/4",
+                            requirement: Requirement::Must,
+                            section: "8.2.2",
+                            citation: "HTTP/2 does not use the Connection header field (Section 7.6.1 of HTTP) to\nindicate connection-specific header fields; in this protocol,\nconnection-specific metadata is conveyed by other means. An endpoint MUST\nNOT generate an HTTP/2 message containing connection-specific header fields.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_transfer_encoding_header(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with upgrade header",
+                            id: "http2/8.2.2/5",
+                            requirement: Requirement::Must,
+                            section: "8.2.2",
+                            citation: "HTTP/2 does not use the Connection header field (Section 7.6.1 of HTTP) to\nindicate connection-specific header fields; in this protocol,\nconnection-specific metadata is conveyed by other means. An endpoint MUST\nNOT generate an HTTP/2 message containing connection-specific header fields.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_upgrade_header(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with te trailers",
+                            id: "http2/8.2.2/6",
+                            requirement: Requirement::Must,
+                            section: "8.2.2",
+                            citation: "The only exception to this is the TE header field, which MAY be present in\nan HTTP/2 request; when it is, it MUST NOT contain any value other than\n\"trailers\".",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_te_trailers(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with te not trailers",
+                            id: "http2/8.2.2/7",
+                            requirement: Requirement::Must,
+                            section: "8.2.2",
+                            citation: "The only exception to this is the TE header field, which MAY be present in\nan HTTP/2 request; when it is, it MUST NOT contain any value other than\n\"trailers\".",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_te_not_trailers(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with response pseudo header",
+                            id: "http2/8.3/1",
+                            requirement: Requirement::Must,
+                            section: "8.3",
+                            citation: "[...] pseudo-header fields defined for responses MUST NOT appear in requests\n[...] Endpoints MUST treat a request or response that contains undefined or\ninvalid pseudo-header fields as malformed (Section 8.1.1).",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_response_pseudo_header(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with pseudo header in trailer",
+                            id: "http2/8.3/2",
+                            requirement: Requirement::Must,
+                            section: "8.3",
+                            citation: "[...] Pseudo-header fields MUST NOT appear in a trailer section. Endpoints\nMUST treat a request or response that contains undefined or invalid\npseudo-header fields as malformed (Section 8.1.1).",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_pseudo_header_in_trailer(conn))),
+                        },
+                        Test {
+                            name: "sends trailers with pseudo header after regular field",
+                            id: "http2/8.3/3",
+                            requirement: Requirement::Must,
+                            section: "8.3",
+                            citation: "[...] Pseudo-header fields MUST NOT appear in a trailer section. Endpoints\nMUST treat a request or response that contains undefined or invalid\npseudo-header fields as malformed (Section 8.1.1).",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_trailers_with_pseudo_header_after_regular_field(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with duplicate pseudo headers",
+                            id: "http2/8.3/4",
+                            requirement: Requirement::Must,
+                            section: "8.3",
+                            citation: "The same pseudo-header field name MUST NOT appear more than once in a field\nblock. A field block for an HTTP request or response that contains a\nrepeated pseudo-header field name MUST be treated as malformed (Section\n8.1.1).",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_duplicate_pseudo_headers(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with mismatched host authority",
+                            id: "http2/8.3/5",
+                            requirement: Requirement::Must,
+                            section: "8.3",
+                            citation: "A server SHOULD treat a request as malformed if it contains a Host header\nfield that identifies an entity that differs from the entity in the\n\":authority\" pseudo-header field. The values of fields need to be normalized\nto compare them (see Section 6.2 of RFC3986). An origin server can apply\nany normalization method, whereas other servers MUST perform scheme-based\nnormalization (see Section 6.2.3 of RFC3986) of the two fields.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_mismatched_host_authority(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with empty path component",
+                            id: "http2/8.3/6",
+                            requirement: Requirement::Must,
+                            section: "8.3",
+                            citation: "This pseudo-header field MUST NOT be empty for \"http\" or \"https\" URIs;\n\"http\" or \"https\" URIs that do not contain a path component MUST include a\nvalue of '/'. The exceptions to this rule are:",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_empty_path_component(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame without method",
+                            id: "http2/8.3/7",
+                            requirement: Requirement::Must,
+                            section: "8.3",
+                            citation: "All HTTP/2 requests MUST include exactly one valid value for the \":method\",\n\":scheme\", and \":path\" pseudo-header fields, unless they are CONNECT\nrequests (Section 8.5). An HTTP request that omits mandatory pseudo-header\nfields is malformed (Section 8.1.1).",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_without_method(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame without scheme",
+                            id: "http2/8.3/8",
+                            requirement: Requirement::Must,
+                            section: "8.3",
+                            citation: "All HTTP/2 requests MUST include exactly one valid value for the \":method\",\n\":scheme\", and \":path\" pseudo-header fields, unless they are CONNECT\nrequests (Section 8.5). An HTTP request that omits mandatory pseudo-header\nfields is malformed (Section 8.1.1).",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_without_scheme(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame without path",
+                            id: "http2/8.3/9",
+                            requirement: Requirement::Must,
+                            section: "8.3",
+                            citation: "All HTTP/2 requests MUST include exactly one valid value for the \":method\",\n\":scheme\", and \":path\" pseudo-header fields, unless they are CONNECT\nrequests (Section 8.5). An HTTP request that omits mandatory pseudo-header\nfields is malformed (Section 8.1.1).",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_without_path(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame without status",
+                            id: "http2/8.3.2/1",
+                            requirement: Requirement::Must,
+                            section: "8.3.2",
+                            citation: "All HTTP/2 requests MUST include exactly one valid value for the \":method\",\n\":scheme\", and \":path\" pseudo-header fields, unless they are CONNECT\nrequests (Section 8.5). An HTTP request that omits mandatory pseudo-header\nfields is malformed (Section 8.1.1).",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_without_status(conn))),
+                        },
+                        Test {
+                            name: "client sends push promise frame",
+                            id: "http2/8.3.2/2",
+                            requirement: Requirement::Must,
+                            section: "8.3.2",
+                            citation: "A client cannot push. Thus, servers MUST treat the receipt of a PUSH_PROMISE\nframe as a connection error (Section 5.4.1) of type PROTOCOL_ERROR. A server\ncannot set the SETTINGS_ENABLE_PUSH setting to a value other than 0 (see\nSection 6.5.2).",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::client_sends_push_promise_frame(conn))),
+                        },
+                        Test {
+                            name: "sends connect with scheme",
+                            id: "http2/8.5/1",
+                            requirement: Requirement::Must,
+                            section: "8.5",
+                            citation: "The CONNECT method (Section 9.3.6 of HTTP) is used to convert an HTTP\nconnection into a tunnel to a remote host. CONNECT is primarily used with\nHTTP proxies to establish a TLS session with an origin server for the\npurposes of interacting with \"https\" resources.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_connect_with_scheme(conn))),
+                        },
+                        Test {
+                            name: "sends connect with path",
+                            id: "http2/8.5/2",
+                            requirement: Requirement::Must,
+                            section: "8.5",
+                            citation: "The CONNECT method (Section 9.3.6 of HTTP) is used to convert an HTTP\nconnection into a tunnel to a remote host. CONNECT is primarily used with\nHTTP proxies to establish a TLS session with an origin server for the\npurposes of interacting with \"https\" resources.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_connect_with_path(conn))),
+                        },
+                        Test {
+                            name: "sends connect without authority",
+                            id: "http2/8.5/3",
+                            requirement: Requirement::Must,
+                            section: "8.5",
+                            citation: "The CONNECT method (Section 9.3.6 of HTTP) is used to convert an HTTP\nconnection into a tunnel to a remote host. CONNECT is primarily used with\nHTTP proxies to establish a TLS session with an origin server for the\npurposes of interacting with \"https\" resources.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_connect_without_authority(conn))),
+                        },
+                        Test {
+                            name: "sends headers frame with pseudo headers after regular headers",
+                            id: "http2/8.5/4",
+                            requirement: Requirement::Must,
+                            section: "8.5",
+                            citation: "All pseudo-header fields MUST appear in a field block before all regular\nfield lines (RFC 9113, section 8.3)",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_pseudo_headers_after_regular_headers(conn))),
+                        },
+                    ],
+                },
+                Group {
+                    title: "9. http2 connections",
+                    tests: vec![
+                        Test {
+                            name: "sends ping frames on idle connection",
+                            id: "http2/9.1/1",
+                            requirement: Requirement::Should,
+                            section: "9.1",
+                            citation: "HTTP/2 connections are persistent. For best performance, it is\nexpected that clients will not close connections until it is\ndetermined that no further communication with a server is necessary\n[...]",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_9_http2_connections::sends_ping_frames_on_idle_connection(conn))),
+                        },
+                        Test {
+                            name: "sends request then half closes connection",
+                            id: "http2/9.1/2",
+                            requirement: Requirement::Should,
+                            section: "9.1",
+                            citation: "Servers are encouraged to maintain open connections for as long as\npossible but are permitted to terminate idle connections if\nnecessary. When either endpoint chooses to close the transport-layer\nTCP connection, the terminating endpoint SHOULD first send a GOAWAY\n(Section 6.8) frame so that both endpoints can reliably determine\nwhether previously sent frames have been processed and gracefully\ncomplete or terminate any necessary remaining tasks.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9113::_9_http2_connections::sends_request_then_half_closes_connection(conn))),
+                        },
+                    ],
+                },
+            ],
+        },
+        Suite {
+            rfc: "RFC 9218",
+            groups: vec![
+                Group {
+                    title: "4. priority parameters",
+                    tests: vec![
+                        Test {
+                            name: "sends priority header with unknown parameters",
+                            id: "priority/4/1",
+                            requirement: Requirement::Must,
+                            section: "4",
+                            citation: "Unknown parameters, parameters with out-of-range values, or values\nof unexpected types MUST be ignored.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9218::_4_priority_parameters::sends_priority_header_with_unknown_parameters(conn))),
+                        },
+                        Test {
+                            name: "sends priority header with out of range urgency",
+                            id: "priority/4/2",
+                            requirement: Requirement::Must,
+                            section: "4",
+                            citation: "Unknown parameters, parameters with out-of-range values, or values\nof unexpected types MUST be ignored.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9218::_4_priority_parameters::sends_priority_header_with_out_of_range_urgency(conn))),
+                        },
+                        Test {
+                            name: "sends priority header with unexpected types",
+                            id: "priority/4/3",
+                            requirement: Requirement::Must,
+                            section: "4",
+                            citation: "Unknown parameters, parameters with out-of-range values, or values\nof unexpected types MUST be ignored.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9218::_4_priority_parameters::sends_priority_header_with_unexpected_types(conn))),
+                        },
+                    ],
+                },
+                Group {
+                    title: "5. the priority http header field",
+                    tests: vec![
+                        Test {
+                            name: "sends priority header",
+                            id: "priority/5/1",
+                            requirement: Requirement::Should,
+                            section: "5",
+                            citation: "The Priority HTTP header field is a Dictionary that carries priority\nparameters (see Section 4). It can appear in requests and responses.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9218::_5_the_priority_http_header_field::sends_priority_header(conn))),
+                        },
+                        Test {
+                            name: "sends unparseable priority header",
+                            id: "priority/5/2",
+                            requirement: Requirement::Should,
+                            section: "5",
+                            citation: "When receiving an HTTP request that does not carry these priority\nparameters, a server SHOULD act as if their default values were\nspecified.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9218::_5_the_priority_http_header_field::sends_unparseable_priority_header(conn))),
+                        },
+                    ],
+                },
+                Group {
+                    title: "7. the priority update frame",
+                    tests: vec![
+                        Test {
+                            name: "sends priority update frame for open stream",
+                            id: "priority/7.1/1",
+                            requirement: Requirement::Should,
+                            section: "7.1",
+                            citation: "The HTTP/2 PRIORITY_UPDATE frame (type=0x10) is used by clients to\nsignal the initial priority of a response, or to reprioritize a\nresponse or push stream.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9218::_7_the_priority_update_frame::sends_priority_update_frame_for_open_stream(conn))),
+                        },
+                        Test {
+                            name: "sends priority update frame for idle stream",
+                            id: "priority/7.1/2",
+                            requirement: Requirement::Should,
+                            section: "7.1",
+                            citation: "When the PRIORITY_UPDATE frame applies to a request stream, clients\nSHOULD provide a prioritized stream ID that refers to a stream in\nthe \"open\", \"half-closed (local)\", or \"idle\" state.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9218::_7_the_priority_update_frame::sends_priority_update_frame_for_idle_stream(conn))),
+                        },
+                        Test {
+                            name: "sends priority update frame for nonexistent stream",
+                            id: "priority/7.1/3",
+                            requirement: Requirement::Should,
+                            section: "7.1",
+                            citation: "When the PRIORITY_UPDATE frame applies to a request stream, clients\nSHOULD provide a prioritized stream ID that refers to a stream in\nthe \"open\", \"half-closed (local)\", or \"idle\" state.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9218::_7_the_priority_update_frame::sends_priority_update_frame_for_nonexistent_stream(conn))),
+                        },
+                        Test {
+                            name: "sends priority update frame for closed stream",
+                            id: "priority/7.1/4",
+                            requirement: Requirement::Should,
+                            section: "7.1",
+                            citation: "Servers can discard frames where the Prioritized Stream ID refers to\na stream in the \"half-closed (local)\" or \"closed\" state.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9218::_7_the_priority_update_frame::sends_priority_update_frame_for_closed_stream(conn))),
+                        },
+                        Test {
+                            name: "sends priority update frame with unparseable value",
+                            id: "priority/7.1/5",
+                            requirement: Requirement::Should,
+                            section: "7.1",
+                            citation: "The Priority Field Value is the priority update value in ASCII text,\nencoded using Structured Fields. This is the same representation as\nthe Priority header field value.",
+                            run: Box::new(|conn: Conn<IO>| Box::pin(crate::rfc9218::_7_the_priority_update_frame::sends_priority_update_frame_with_unparseable_value(conn))),
+                        },
+                    ],
+                },
+            ],
+        },
+    ]
+}
@@ -0,0 +1,96 @@
+//! Sending the client side of a recorded session (cf. [crate::capture]) to a
+//! server, for "it only fails against server X" reports: record the
+//! exchange against X, replay it against Y, and compare the two traces.
+//!
+//! What we sent goes out byte for byte, connection preface included, without
+//! a handshake of our own. Timing isn't kept: before each write, we wait
+//! until the server has sent as many frames as it had at that point of the
+//! recording, or until it goes quiet for [crate::Config::timeout], so the
+//! replay follows the conversation rather than the clock.
+
+use buffet::IntoHalves;
+use eyre::bail;
+use tracing::debug;
+
+use crate::{
+    capture::{Captured, Direction},
+    Conn, Ev,
+};
+
+/// How a [replay] went
+#[derive(Debug)]
+pub struct Replayed {
+    /// everything sent and received while replaying, cf.
+    /// [crate::capture::render_trace]
+    pub frames: Vec<Captured>,
+
+    /// why the connection ended, if it did before the server went quiet,
+    /// e.g. because the server hung up
+    pub ended: Option<String>,
+}
+
+/// Sends what `recorded` says we sent over `conn`, which must have
+/// [crate::Config::record_frames] set, then keeps reading until the server
+/// goes quiet or hangs up.
+pub async fn replay<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+    recorded: &[Captured],
+) -> eyre::Result<Replayed> {
+    let Some(recorder) = conn.recorder() else {
+        bail!("replaying needs Config::record_frames, to record what the server sends back");
+    };
+
+    let mut received = 0;
+    let mut wanted = 0;
+    let mut ended = None;
+    for c in recorded {
+        if c.direction == Direction::Received {
+            wanted += 1;
+            continue;
+        }
+        ended = wait_for_frames(&mut conn, wanted, &mut received).await;
+        if ended.is_some() {
+            break;
+        }
+        if let Err(e) = conn.send(c.bytes()).await {
+            ended = Some(format!("could not write: {e}"));
+            break;
+        }
+    }
+    if ended.is_none() {
+        ended = wait_for_frames(&mut conn, usize::MAX, &mut received).await;
+    }
+
+    Ok(Replayed {
+        frames: recorder.frames(),
+        ended,
+    })
+}
+
+/// Reads frames until `received` reaches `wanted`, or the server goes quiet
+/// for [crate::Config::timeout]. Returns why the connection ended, if it did.
+async fn wait_for_frames<IO: IntoHalves>(
+    conn: &mut Conn<IO>,
+    wanted: usize,
+    received: &mut usize,
+) -> Option<String> {
+    while *received < wanted {
+        let ev = match tokio::time::timeout(conn.config.timeout, conn.ev_rx.recv()).await {
+            Ok(ev) => ev,
+            Err(_) => {
+                debug!(%received, "server went quiet");
+                return None;
+            }
+        };
+        match ev {
+            Some(Ev::Frame { .. } | Ev::Headers { .. }) => *received += 1,
+            Some(Ev::Raw { .. }) => unreachable!("raw mode is never on here"),
+            Some(Ev::Eof) | None => return Some("the server hung up".to_owned()),
+            Some(Ev::IoError { error }) => return Some(format!("I/O error: {error}")),
+            Some(Ev::ProtocolViolation { reason }) => {
+                return Some(format!("the server violated the protocol: {reason}"))
+            }
+        }
+    }
+    None
+}
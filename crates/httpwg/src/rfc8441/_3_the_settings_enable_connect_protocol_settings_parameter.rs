@@ -0,0 +1,35 @@
+//! Section 3: The SETTINGS_ENABLE_CONNECT_PROTOCOL SETTINGS Parameter
+
+use buffet::IntoHalves;
+use loona_h2::Setting;
+
+use crate::Conn;
+
+/// Upon receipt of SETTINGS_ENABLE_CONNECT_PROTOCOL with a value of 1,
+/// a client MAY use the Extended CONNECT as defined in this document
+/// when creating new streams.
+///
+/// The rest of this suite relies on the server advertising it.
+pub async fn advertises_settings_enable_connect_protocol<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    conn.verify_enable_connect_protocol()?;
+
+    Ok(())
+}
+
+/// Receipt of this parameter by a server does not have any impact.
+pub async fn sends_settings_enable_connect_protocol<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    conn.write_and_ack_settings(&[(Setting::EnableConnectProtocol, 1)])
+        .await?;
+
+    conn.verify_connection_still_alive().await?;
+
+    Ok(())
+}
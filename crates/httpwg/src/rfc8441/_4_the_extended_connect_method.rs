@@ -0,0 +1,97 @@
+//! Section 4: The Extended CONNECT Method
+
+use buffet::IntoHalves;
+use loona_h2::{HeadersFlags, StreamId};
+
+use crate::{Conn, ErrorC};
+
+/// Upon receiving a CONNECT request bearing the :protocol pseudo-header
+/// field, the server establishes a tunnel to another service of the
+/// protocol type indicated by the pseudo-header field.
+///
+/// This sends data through the tunnel, which the server echoes back, then
+/// closes it.
+pub async fn sends_extended_connect_request<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    let stream_id = StreamId(1);
+
+    conn.handshake().await?;
+    conn.verify_enable_connect_protocol()?;
+
+    let headers = conn.extended_connect_headers("websocket");
+    conn.encode_and_write_headers(stream_id, HeadersFlags::EndHeaders, &headers)
+        .await?;
+
+    conn.verify_tunnel_established(stream_id).await?;
+
+    conn.write_data(stream_id, false, b"hello").await?;
+    let data = conn.read_data(stream_id, 5).await?;
+    assert_eq!(&data[..], b"hello", "unexpected data out of the tunnel");
+
+    conn.write_data(stream_id, true, b"").await?;
+    conn.verify_stream_close(stream_id).await?;
+
+    Ok(())
+}
+
+/// On requests that contain the :protocol pseudo-header field, the
+/// :scheme and :path pseudo-header fields of the target URI (see
+/// Section 5) MUST also be included.
+pub async fn sends_extended_connect_request_without_scheme<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    let mut headers = conn.extended_connect_headers("websocket");
+    headers.remove(&":scheme".into());
+    conn.encode_and_write_headers(StreamId(1), HeadersFlags::EndHeaders, &headers)
+        .await?;
+
+    conn.verify_stream_error(ErrorC::ProtocolError).await?;
+
+    Ok(())
+}
+
+/// On requests that contain the :protocol pseudo-header field, the
+/// :scheme and :path pseudo-header fields of the target URI (see
+/// Section 5) MUST also be included.
+pub async fn sends_extended_connect_request_without_path<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    let mut headers = conn.extended_connect_headers("websocket");
+    headers.remove(&":path".into());
+    conn.encode_and_write_headers(StreamId(1), HeadersFlags::EndHeaders, &headers)
+        .await?;
+
+    conn.verify_stream_error(ErrorC::ProtocolError).await?;
+
+    Ok(())
+}
+
+/// A new pseudo-header field :protocol MAY be included on request
+/// HEADERS indicating the desired protocol to be spoken on the tunnel
+/// created by CONNECT.
+///
+/// There's no tunnel without CONNECT: this sends `:protocol` on a GET
+/// request, which makes it malformed.
+pub async fn sends_protocol_pseudo_header_on_non_connect_request<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    let mut headers = conn.common_headers("GET");
+    headers.append(":protocol", "websocket");
+    conn.encode_and_write_headers(
+        StreamId(1),
+        HeadersFlags::EndStream | HeadersFlags::EndHeaders,
+        &headers,
+    )
+    .await?;
+
+    conn.verify_stream_error(ErrorC::ProtocolError).await?;
+
+    Ok(())
+}
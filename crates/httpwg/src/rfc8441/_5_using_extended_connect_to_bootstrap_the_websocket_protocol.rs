@@ -0,0 +1,77 @@
+//! Section 5: Using Extended CONNECT to Bootstrap the WebSocket Protocol
+
+use buffet::IntoHalves;
+use loona_h2::{HeadersFlags, KnownErrorCode, StreamId};
+
+use crate::{Conn, Headers};
+
+/// The request from Section 5.1, an example of a WebSocket handshake
+/// over HTTP/2
+fn websocket_headers<IO: IntoHalves>(conn: &Conn<IO>) -> Headers {
+    let mut headers = conn.extended_connect_headers("websocket");
+    headers.replace(":path", "/chat");
+    headers.append("sec-websocket-protocol", "chat, superchat");
+    headers.append("sec-websocket-extensions", "permessage-deflate");
+    headers.append("sec-websocket-version", "13");
+    headers.append("origin", "http://www.example.com");
+    headers
+}
+
+/// A masked WebSocket text frame saying "Hello", cf. RFC 6455, Section 5.7
+const MASKED_HELLO: &[u8] = &[
+    0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+];
+
+/// After successfully processing the opening handshake, the peers
+/// should proceed with The WebSocket Protocol [RFC6455] using the
+/// HTTP/2 stream from the CONNECT transaction as if it were the TCP
+/// connection referred to in [RFC6455].
+///
+/// This sends a WebSocket frame over the stream, which the server echoes
+/// back, then closes it, which is how orderly TCP-level closures are
+/// represented.
+pub async fn sends_websocket_handshake<IO: IntoHalves>(mut conn: Conn<IO>) -> eyre::Result<()> {
+    let stream_id = StreamId(1);
+
+    conn.handshake().await?;
+    conn.verify_enable_connect_protocol()?;
+
+    let headers = websocket_headers(&conn);
+    conn.encode_and_write_headers(stream_id, HeadersFlags::EndHeaders, &headers)
+        .await?;
+
+    conn.verify_tunnel_established(stream_id).await?;
+
+    conn.write_data(stream_id, false, MASKED_HELLO).await?;
+    let data = conn.read_data(stream_id, MASKED_HELLO.len()).await?;
+    assert_eq!(&data[..], MASKED_HELLO, "unexpected data out of the tunnel");
+
+    conn.write_data(stream_id, true, b"").await?;
+    conn.verify_stream_close(stream_id).await?;
+
+    Ok(())
+}
+
+/// RST exceptions are represented with the stream error (Section 5.4.2
+/// of [RFC7540]) of type CANCEL (Section 7 of [RFC7540]).
+///
+/// This cancels a WebSocket stream, which must not affect the connection.
+pub async fn sends_rst_stream_cancel_on_websocket_stream<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    let stream_id = StreamId(1);
+
+    conn.handshake().await?;
+
+    let headers = websocket_headers(&conn);
+    conn.encode_and_write_headers(stream_id, HeadersFlags::EndHeaders, &headers)
+        .await?;
+    conn.write_data(stream_id, false, MASKED_HELLO).await?;
+
+    conn.write_rst_stream(stream_id, KnownErrorCode::Cancel)
+        .await?;
+
+    conn.verify_connection_still_alive().await?;
+
+    Ok(())
+}
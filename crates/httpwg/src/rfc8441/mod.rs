@@ -0,0 +1,13 @@
+//! RFC 8441 provides a mechanism for running the WebSocket Protocol
+//! (RFC 6455) over a single stream of an HTTP/2 connection: the extended
+//! CONNECT method, which servers opt into with the
+//! SETTINGS_ENABLE_CONNECT_PROTOCOL parameter.
+//!
+//! The tests that open tunnels expect the server to accept extended CONNECT
+//! requests on any path, and to echo back whatever goes through them.
+//!
+//! cf. <https://httpwg.org/specs/rfc8441.html>
+
+pub mod _3_the_settings_enable_connect_protocol_settings_parameter;
+pub mod _4_the_extended_connect_method;
+pub mod _5_using_extended_connect_to_bootstrap_the_websocket_protocol;
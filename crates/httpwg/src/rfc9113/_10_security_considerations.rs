@@ -0,0 +1,192 @@
+//! Section 10: Security Considerations
+
+use std::time::Duration;
+
+use buffet::IntoHalves;
+use enumflags2::BitFlags;
+use loona_h2::{ContinuationFlags, GoAway, HeadersFlags, KnownErrorCode, StreamId};
+use tokio::time::Instant;
+use tracing::debug;
+
+use crate::{Conn, Ev, NextEvent, Payload};
+
+//---- Section 10.5: Denial-of-Service Considerations
+
+/// What we ping the server with once we're done resetting streams
+const RAPID_RESET_PING: &[u8; 8] = b"rapidrst";
+
+/// An endpoint that doesn't monitor use of these features exposes itself
+/// to a risk of denial of service. Implementations SHOULD track the use
+/// of these features and set limits on their use. An endpoint MAY treat
+/// activity that is suspicious as a connection error (Section 5.4.1) of
+/// type ENHANCE_YOUR_CALM.
+///
+/// This opens [crate::Config::rapid_reset_streams] streams and resets each
+/// of them right away, at [crate::Config::rapid_reset_rate], cf. the 2023
+/// "rapid reset" attack (CVE-2023-44487): it costs the client next to
+/// nothing, and the server has to start on a request every time. The
+/// server must keep up, and push back at some point: with a GOAWAY, or by
+/// closing the connection (which can make us miss the GOAWAY, since we're
+/// still writing when it does).
+pub async fn sends_rapid_resets<IO: IntoHalves>(mut conn: Conn<IO>) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    let streams = conn.config.rapid_reset_streams;
+    let rate = conn.config.rapid_reset_rate;
+    let timeout = conn.config.timeout;
+    let path = conn.config.path.clone();
+
+    let mut outcome = RapidResetOutcome::default();
+    let start = Instant::now();
+    let mut sent = 0;
+    // once the server pushes back, we stop: writing to a connection it
+    // closed could have its GOAWAY discarded along with the connection
+    while sent < streams && !outcome.closed && outcome.goaway.is_none() {
+        if let Some(due) = rate.and_then(|rate| Duration::from_secs(sent as u64).checked_div(rate))
+        {
+            tokio::time::sleep_until(start + due).await;
+        }
+
+        let stream_id = StreamId(1 + sent * 2);
+        let write = async {
+            conn.write_request(stream_id, "GET", &path, true).await?;
+            conn.write_rst_stream(stream_id, KnownErrorCode::Cancel)
+                .await
+        };
+        match tokio::time::timeout(timeout, write).await {
+            Ok(Ok(())) => {}
+            // the server hung up on us: let's see whether it said why
+            Ok(Err(_)) => break,
+            Err(_) => eyre::bail!(
+                "server stopped reading after {sent} rapid resets, but kept the connection open"
+            ),
+        }
+        sent += 1;
+
+        // don't let responses pile up while we're busy writing
+        tokio::task::yield_now().await;
+        while let Ok(ev) = conn.ev_rx.try_recv() {
+            outcome.note(ev)?;
+        }
+    }
+
+    // this fails too if the server hung up, that's fine
+    _ = conn.write_ping(false, &RAPID_RESET_PING[..]).await;
+    while !outcome.closed && !outcome.ping_acked {
+        match conn.next_event().await {
+            NextEvent::Ev(ev) => outcome.note(ev)?,
+            NextEvent::Eof => outcome.closed = true,
+            NextEvent::Timeout { waited } => eyre::bail!(
+                "server stopped responding after {sent} rapid resets: no PING ack, and the connection is still open after {waited:?}"
+            ),
+        }
+    }
+
+    match outcome.goaway {
+        Some(goaway) => {
+            debug!(
+                "server sent a GOAWAY with {:?} after {sent} rapid resets, in {:?}",
+                goaway.error_code,
+                start.elapsed()
+            );
+            Ok(())
+        }
+        None if outcome.closed => {
+            debug!(
+                "server closed the connection after {sent} rapid resets, in {:?}",
+                start.elapsed()
+            );
+            Ok(())
+        }
+        None => eyre::bail!(
+            "server let the client open and reset {sent} streams in {:?} without pushing back",
+            start.elapsed()
+        ),
+    }
+}
+
+/// What [sends_rapid_resets] heard back
+#[derive(Default)]
+struct RapidResetOutcome {
+    goaway: Option<GoAway>,
+    ping_acked: bool,
+    closed: bool,
+}
+
+impl RapidResetOutcome {
+    fn note(&mut self, ev: Ev) -> eyre::Result<()> {
+        match ev {
+            Ev::Frame { frame, payload } => match Payload::decode(&frame, payload)? {
+                Payload::GoAway(goaway) => self.goaway = Some(goaway),
+                Payload::Ping(payload) if frame.is_ack() && &payload == RAPID_RESET_PING => {
+                    self.ping_acked = true
+                }
+                _ => {}
+            },
+            Ev::Headers { .. } => {}
+            Ev::Raw { .. } => unreachable!("raw mode is never on here"),
+            Ev::Eof | Ev::IoError { .. } => self.closed = true,
+            Ev::ProtocolViolation { reason } => {
+                eyre::bail!("server violated the protocol: {reason}")
+            }
+        }
+        Ok(())
+    }
+}
+
+//---- Section 10.5.1: Limits on Field Block Size
+
+/// How much header block we're prepared to send before deciding the server
+/// buffers all of it: more than any server should accept.
+const CONTINUATION_FLOOD_LEN: usize = 32 * 1024 * 1024;
+
+/// A large field block (Section 8.2) can cause an implementation to
+/// commit a large amount of state. [...] A server that receives a larger
+/// field block than it is willing to handle can send an HTTP 431 (Request
+/// Header Fields Too Large) status code. [...] An endpoint can treat
+/// this as a connection error (Section 5.4.1) of type
+/// ENHANCE_YOUR_CALM.
+///
+/// This sends CONTINUATION frames that never set END_HEADERS, cf. the
+/// 2024 "CONTINUATION flood" advisories: the server must stop buffering
+/// them at some point, and close the connection.
+pub async fn sends_endless_continuation_frames<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    let stream_id = StreamId(1);
+
+    conn.handshake().await?;
+
+    let block_fragment = conn.encode_headers(&conn.common_headers("POST"))?;
+    conn.write_headers(stream_id, HeadersFlags::EndStream, block_fragment)
+        .await?;
+
+    let timeout = conn.config.timeout;
+    let mut sent = 0;
+    while sent < CONTINUATION_FLOOD_LEN {
+        // valid HPACK, so that the block is too large, rather than malformed
+        let block_fragment = conn.encode_headers(&conn.dummy_headers(3))?;
+        sent += block_fragment.len();
+        match tokio::time::timeout(
+            timeout,
+            conn.write_continuation(
+                stream_id,
+                BitFlags::<ContinuationFlags>::empty(),
+                block_fragment,
+            ),
+        )
+        .await
+        {
+            Ok(Ok(())) => {}
+            // the server hung up on us: that's what we want
+            Ok(Err(_)) => break,
+            Err(_) => eyre::bail!(
+                "server stopped reading after {sent} bytes, but kept the connection open"
+            ),
+        }
+    }
+
+    conn.verify_connection_close().await?;
+
+    Ok(())
+}
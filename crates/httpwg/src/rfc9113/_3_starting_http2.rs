@@ -3,7 +3,7 @@
 use buffet::IntoHalves;
 use loona_h2::PREFACE;
 
-use crate::{rfc9113::default_settings, Conn, ErrorC, FrameT};
+use crate::{chunking::Chunking, preface::Preface, Conn, ErrorC, FrameT, Request};
 
 //---- Section 3.4: HTTP/2 connection preface
 
@@ -15,8 +15,8 @@ pub async fn sends_client_connection_preface<IO: IntoHalves>(
 ) -> eyre::Result<()> {
     conn.send(PREFACE).await?;
 
-    let settings = default_settings();
-    conn.write_settings(settings).await?;
+    let settings = conn.config.settings();
+    conn.write_settings(&settings[..]).await?;
 
     let (frame, _) = conn.wait_for_frame(FrameT::Settings).await.unwrap();
     assert!(!frame.is_ack(), "The server connection preface MUST be the first frame the server sends in the HTTP/2 connection.");
@@ -29,8 +29,81 @@ pub async fn sends_client_connection_preface<IO: IntoHalves>(
 pub async fn sends_invalid_connection_preface<IO: IntoHalves>(
     mut conn: Conn<IO>,
 ) -> eyre::Result<()> {
-    conn.send("INVALID CONNECTION PREFACE\r\n\r\n").await?;
-    conn.verify_connection_error(ErrorC::ProtocolError).await?;
+    let preface = Preface::Custom(b"INVALID CONNECTION PREFACE\r\n\r\n".to_vec());
+    conn.verify_preface_rejected(&preface, ErrorC::ProtocolError)
+        .await?;
+
+    Ok(())
+}
+
+/// Clients and servers MUST treat an invalid connection preface as
+/// a connection error (Section 5.4.1) of type PROTOCOL_ERROR.
+///
+/// This sends all of the preface but its last byte, then the SETTINGS
+/// frame: a server that compares fewer than 24 bytes would take it.
+pub async fn sends_truncated_connection_preface<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    let preface = Preface::Truncated(PREFACE.len() - 1);
+    conn.verify_preface_rejected(&preface, ErrorC::ProtocolError)
+        .await?;
+
+    Ok(())
+}
+
+/// Clients and servers MUST treat an invalid connection preface as
+/// a connection error (Section 5.4.1) of type PROTOCOL_ERROR.
+///
+/// The preface is octets, not text: `pri * http/2.0` doesn't match it.
+pub async fn sends_connection_preface_in_wrong_case<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.verify_preface_rejected(&Preface::WrongCase, ErrorC::ProtocolError)
+        .await?;
+
+    Ok(())
+}
+
+/// That is, the connection preface starts with the string
+/// "PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n". This sequence MUST be followed
+/// by a SETTINGS frame (Section 6.5), which MAY be empty.
+///
+/// Here it's followed by a stray CRLF, which makes for a frame header
+/// announcing a frame larger than SETTINGS_MAX_FRAME_SIZE, so a
+/// FRAME_SIZE_ERROR is fine too.
+pub async fn sends_connection_preface_with_extra_bytes<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    let preface = Preface::ExtraBytes(b"\r\n".to_vec());
+    conn.verify_preface_rejected(&preface, ErrorC::ProtocolError | ErrorC::FrameSizeError)
+        .await?;
+
+    Ok(())
+}
+
+/// The client connection preface starts with a sequence of 24 octets.
+///
+/// Nothing says those arrive in a single read: this sends the preface in
+/// two halves on one connection and a byte at a time on another, and
+/// expects both handshakes to go through and a request to get a response.
+pub async fn sends_connection_preface_in_pieces<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    let mut other = conn.connect_another().await?;
+
+    for (conn, chunking) in [
+        (&mut conn, Chunking::MidFrameHeader),
+        (&mut other, Chunking::ByteByByte),
+    ] {
+        conn.handshake_with_preface(&Preface::Split(chunking.clone()))
+            .await
+            .map_err(|e| e.wrap_err(format!("with {chunking:?}")))?;
+
+        let pending = conn.send_request(Request::get()).await?;
+        conn.read_response(pending)
+            .await
+            .map_err(|e| e.wrap_err(format!("with {chunking:?}")))?;
+    }
 
     Ok(())
 }
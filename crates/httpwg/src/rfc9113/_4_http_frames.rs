@@ -1,10 +1,11 @@
 //! Section 4: HTTP Frames
 
-use crate::{dummy_bytes, Conn, ErrorC};
+use crate::{chunking::Chunking, dummy_bytes, Conn, ErrorC, PendingResponse, Request, Responses};
 use buffet::IntoHalves;
 use enumflags2::BitFlags;
 use loona_h2::{
-    ContinuationFlags, EncodedFrameType, Frame, FrameType, HeadersFlags, PrioritySpec, StreamId,
+    ContinuationFlags, EncodedFrameType, Frame, FrameType, HeadersFlags, KnownErrorCode,
+    PrioritySpec, StreamId,
 };
 
 //---- Section 4.1: Frame Format
@@ -23,6 +24,8 @@ pub async fn sends_frame_with_unknown_type<IO: IntoHalves>(mut conn: Conn<IO>) -
     )
     .await?;
 
+    // not even a PING or a WINDOW_UPDATE: it means nothing
+    conn.expect_silence(conn.config.timeout).await?;
     conn.verify_connection_still_alive().await?;
 
     Ok(())
@@ -71,6 +74,44 @@ pub async fn sends_frame_with_reserved_bit_set<IO: IntoHalves>(
     Ok(())
 }
 
+/// All frames begin with a fixed 9-octet header followed by a
+/// variable-length frame payload.
+///
+/// Nothing says those bytes arrive in a single read: this sends a request
+/// split in the middle of its frame header, then one a byte at a time, then
+/// one in small writes with pauses in between, and expects a response to
+/// each of them.
+pub async fn sends_frames_in_awkward_chunks<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    let strategies = [
+        Chunking::MidFrameHeader,
+        Chunking::ByteByByte,
+        Chunking::Paused {
+            size: 3,
+            pause: conn.config.timeout / 10,
+        },
+    ];
+    for (i, chunking) in strategies.into_iter().enumerate() {
+        let stream_id = StreamId(i as u32 * 2 + 1);
+        let block_fragment = conn.encode_headers(&conn.common_headers("GET"))?;
+        let buf = conn.encode_frame(
+            FrameType::Headers(HeadersFlags::EndHeaders | HeadersFlags::EndStream)
+                .into_frame(stream_id),
+            block_fragment,
+        )?;
+        conn.send_chunked(buf, &chunking).await?;
+
+        conn.read_response(PendingResponse { stream_id })
+            .await
+            .map_err(|e| e.wrap_err(format!("with {chunking:?}")))?;
+    }
+
+    Ok(())
+}
+
 //--- Section 4.2: Frame Size
 
 // All implementations MUST be capable of receiving and minimally
@@ -170,6 +211,109 @@ pub async fn invalid_header_block_fragment<IO: IntoHalves>(mut conn: Conn<IO>) -
     Ok(())
 }
 
+/// A decoding error in a field block MUST be treated as a connection error
+/// (Section 5.4.1) of type COMPRESSION_ERROR.
+///
+/// This one refers to an index past the end of the static table, while
+/// the dynamic table is still empty.
+pub async fn header_block_with_invalid_index<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    // Indexed Header Field, index 70
+    verify_compression_error(&mut conn, b"\xc6").await
+}
+
+/// A decoding error in a field block MUST be treated as a connection error
+/// (Section 5.4.1) of type COMPRESSION_ERROR.
+///
+/// This one has a Huffman-encoded value that claims more octets than the
+/// field block has left.
+pub async fn header_block_with_truncated_huffman_string<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    // Literal Header Field without Indexing, name `:path` (index 4), then a
+    // 10-octet Huffman-encoded value that stops after 2 octets
+    verify_compression_error(&mut conn, b"\x04\x8a\x60\x7f").await
+}
+
+/// A decoding error in a field block MUST be treated as a connection error
+/// (Section 5.4.1) of type COMPRESSION_ERROR.
+///
+/// This one opens with a dynamic table size update to 4097 octets, past
+/// the SETTINGS_HEADER_TABLE_SIZE we left at its default of 4096, which
+/// RFC 7541, Section 6.3 calls a decoding error.
+pub async fn dynamic_table_size_update_exceeding_max<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    // Dynamic Table Size Update: 31 in the 5-bit prefix, then 4066
+    verify_compression_error(&mut conn, b"\x3f\xe2\x1f").await
+}
+
+/// Sends `block` as the field block of a request on stream 1, then a valid
+/// request on stream 3, and expects a COMPRESSION_ERROR: the server can't
+/// decode field blocks after one it failed to decode, so it mustn't answer
+/// stream 3 either.
+async fn verify_compression_error<IO: IntoHalves>(
+    conn: &mut Conn<IO>,
+    block: &'static [u8],
+) -> eyre::Result<()> {
+    conn.write_frame(
+        FrameType::Headers(HeadersFlags::EndStream | HeadersFlags::EndHeaders)
+            .into_frame(StreamId(1)),
+        block,
+    )
+    .await?;
+
+    // this may fail, if the server hung up already
+    let next = StreamId(3);
+    let path = conn.config.path.clone();
+    _ = conn.write_request(next, "GET", &path, true).await;
+
+    let mut responses = Responses::default();
+    conn.read_responses(&mut responses, |r| {
+        r.goaway.is_some() || r.closed || r.streams.contains_key(&next)
+    })
+    .await?;
+
+    eyre::ensure!(
+        !responses.streams.contains_key(&next),
+        "server kept processing frames after a field block it couldn't decode, and answered stream {next}"
+    );
+    let Some(goaway) = responses.goaway else {
+        eyre::ensure!(
+            responses.closed,
+            "server neither sent a GOAWAY nor hung up within {:?}",
+            conn.config.timeout
+        );
+        return Ok(());
+    };
+    let error_c = KnownErrorCode::try_from(goaway.error_code)
+        .map(ErrorC::from)
+        .map_err(|_| {
+            eyre::eyre!(
+                "expected COMPRESSION_ERROR, got unknown error code {:?}",
+                goaway.error_code
+            )
+        })?;
+    eyre::ensure!(
+        error_c == ErrorC::CompressionError,
+        "expected COMPRESSION_ERROR, got {error_c:?}"
+    );
+    eyre::ensure!(
+        goaway.last_stream_id < next,
+        "server's GOAWAY says it processed stream {}, after a field block it couldn't decode",
+        goaway.last_stream_id
+    );
+
+    Ok(())
+}
+
 /// Each header block is processed as a discrete unit. Header blocks
 /// MUST be transmitted as a contiguous sequence of frames, with no
 /// interleaved frames of any other type or from any other stream.
@@ -210,6 +354,30 @@ pub async fn priority_frame_while_sending_headers<IO: IntoHalves>(
     Ok(())
 }
 
+/// Each field block is processed as a discrete unit. Field blocks MUST be
+/// transmitted as a contiguous sequence of frames, with no interleaved
+/// frames of any other type or from any other stream.
+///
+/// This goes for the server's responses too: with several of them in
+/// flight, each one's HEADERS frame must be followed by its own
+/// CONTINUATION frames, if any, and nothing else.
+pub async fn response_field_blocks_are_contiguous<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+    conn.assemble_headers(true);
+
+    let mut pending = vec![];
+    for _ in 0..3 {
+        pending.push(conn.send_request(Request::get()).await?);
+    }
+    for pending in pending {
+        conn.read_response(pending).await?;
+    }
+
+    Ok(())
+}
+
 /// Each header block is processed as a discrete unit. Header blocks
 /// MUST be transmitted as a contiguous sequence of frames, with no
 /// interleaved frames of any other type or from any other stream.
@@ -2,9 +2,12 @@
 
 use buffet::IntoHalves;
 use enumflags2::BitFlags;
-use loona_h2::{ContinuationFlags, EncodedFrameType, FrameType, HeadersFlags, Setting, StreamId};
+use loona_h2::{
+    ContinuationFlags, EncodedFrameType, FrameType, HeadersFlags, KnownErrorCode, PrioritySpec,
+    Setting, StreamId,
+};
 
-use crate::{dummy_bytes, Conn, ErrorC};
+use crate::{dummy_bytes, Conn, ErrorC, Request, Responses};
 
 //---- Section 5.1: Stream States
 
@@ -123,6 +126,70 @@ pub async fn half_closed_remote_sends_continuation_frame<IO: IntoHalves>(
     Ok(())
 }
 
+/// half-closed (remote):
+/// If an endpoint receives additional frames, other than
+/// WINDOW_UPDATE, PRIORITY, or RST_STREAM, for a stream that is in
+/// this state, it MUST respond with a stream error (Section 5.4.2)
+/// of type STREAM_CLOSED.
+pub async fn half_closed_remote_sends_window_update_frame<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    let stream_id = StreamId(1);
+    conn.handshake().await?;
+    conn.send_empty_post_to_root(stream_id).await?;
+
+    // the server may have closed the stream by now, but WINDOW_UPDATE frames
+    // can still be received for a short while (section 5.1, "closed")
+    conn.write_window_update(stream_id, 100).await?;
+    conn.verify_connection_still_alive().await?;
+
+    Ok(())
+}
+
+/// half-closed (remote):
+/// If an endpoint receives additional frames, other than
+/// WINDOW_UPDATE, PRIORITY, or RST_STREAM, for a stream that is in
+/// this state, it MUST respond with a stream error (Section 5.4.2)
+/// of type STREAM_CLOSED.
+pub async fn half_closed_remote_sends_priority_frame<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    let stream_id = StreamId(1);
+    conn.handshake().await?;
+    conn.send_empty_post_to_root(stream_id).await?;
+
+    conn.write_priority(
+        stream_id,
+        PrioritySpec {
+            stream_dependency: StreamId(0),
+            exclusive: false,
+            weight: 255,
+        },
+    )
+    .await?;
+    conn.verify_connection_still_alive().await?;
+
+    Ok(())
+}
+
+/// half-closed (remote):
+/// If an endpoint receives additional frames, other than
+/// WINDOW_UPDATE, PRIORITY, or RST_STREAM, for a stream that is in
+/// this state, it MUST respond with a stream error (Section 5.4.2)
+/// of type STREAM_CLOSED.
+pub async fn half_closed_remote_sends_rst_stream_frame<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    let stream_id = StreamId(1);
+    conn.handshake().await?;
+    conn.send_empty_post_to_root(stream_id).await?;
+
+    conn.write_rst_stream(stream_id, ErrorC::Cancel).await?;
+    conn.verify_connection_still_alive().await?;
+
+    Ok(())
+}
+
 /// closed:
 /// An endpoint that receives any frame other than PRIORITY after
 /// receiving a RST_STREAM MUST treat that as a stream error
@@ -319,6 +386,84 @@ pub async fn exceeds_concurrent_stream_limit<IO: IntoHalves>(
     Ok(())
 }
 
+/// A peer can limit the number of concurrently active streams using the
+/// SETTINGS_MAX_CONCURRENT_STREAMS parameter (see Section 6.5.2) within a
+/// SETTINGS frame. [...]
+///
+/// Endpoints MUST NOT exceed the limit set by their peer. An endpoint
+/// that receives a HEADERS frame that causes its advertised concurrent
+/// stream limit to be exceeded MUST treat this as a stream error
+/// (Section 5.4.2) of type PROTOCOL_ERROR or REFUSED_STREAM.
+///
+/// This opens exactly as many streams as the server allows, then one
+/// more: only that last one may be refused, and once it is, the others
+/// must still get their responses.
+pub async fn exceeds_concurrent_stream_limit_by_one<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    // Skip this test case when SETTINGS_MAX_CONCURRENT_STREAMS is unlimited.
+    let max_streams = match conn.settings.max_concurrent_streams {
+        Some(value) => value,
+        None => return Ok(()),
+    };
+
+    let open = conn.open_streams(StreamId(1), max_streams).await?;
+    let excess = StreamId(1 + max_streams * 2);
+    let path = conn.config.path.clone();
+    conn.write_request(excess, "POST", &path, false).await?;
+
+    let mut responses = Responses::default();
+    conn.read_responses(&mut responses, |r| {
+        r.ended(excess) || r.goaway.is_some() || r.closed
+    })
+    .await?;
+
+    for &stream_id in &open {
+        if let Some(error_code) = responses.reset_code(stream_id) {
+            eyre::bail!(
+                "stream {stream_id} was within the limit of {max_streams} concurrent streams, \
+                 but it got reset with {error_code:?}"
+            );
+        }
+    }
+
+    let refused = ErrorC::ProtocolError | ErrorC::RefusedStream;
+    let is_refusal = |error_code| {
+        KnownErrorCode::try_from(error_code).is_ok_and(|code| refused.contains(ErrorC::from(code)))
+    };
+    if let Some(goaway) = &responses.goaway {
+        // treating it as a connection error is fine too
+        eyre::ensure!(
+            is_refusal(goaway.error_code),
+            "expected the excess stream {excess} to be refused, got GOAWAY with {:?}",
+            goaway.error_code
+        );
+        return Ok(());
+    }
+    if responses.closed {
+        return Ok(());
+    }
+    match responses.reset_code(excess) {
+        Some(error_code) if is_refusal(error_code) => {}
+        Some(error_code) => eyre::bail!(
+            "expected the excess stream {excess} to be reset with one of {refused:?}, got {error_code:?}"
+        ),
+        None => eyre::bail!(
+            "expected the excess stream {excess} to be refused, got {:?}",
+            responses.streams.get(&excess)
+        ),
+    }
+
+    for &stream_id in &open {
+        conn.write_data(stream_id, true, b"").await?;
+    }
+    conn.verify_responses(&open).await?;
+
+    Ok(())
+}
+
 // Note: In RFC9113, Section 5.3 mostly describes how prioritization in HTTP/2
 // was a failure, and is now deprecated. RFC9218 describes another scheme, cf.
 // https://www.rfc-editor.org/rfc/rfc9218.html
@@ -363,6 +508,33 @@ pub async fn test_invalid_ping_frame_for_goaway<IO: IntoHalves>(
     Ok(())
 }
 
+/// A connection error is any error that prevents further processing of
+/// the frame layer or corrupts any connection state.
+///
+/// That state is the connection's own: this opens a second connection,
+/// triggers a connection error on the first one, then checks that the
+/// second one still gets a response.
+pub async fn connection_error_leaves_other_connections_alone<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+    let mut other = conn.connect_another().await?;
+    other.handshake().await?;
+
+    // PING frame with invalid stream ID
+    conn.write_frame(
+        FrameType::Ping(BitFlags::default()).into_frame(StreamId(3)),
+        dummy_bytes(8),
+    )
+    .await?;
+    conn.verify_connection_error(ErrorC::ProtocolError).await?;
+
+    let pending = other.send_request(Request::get()).await?;
+    other.read_response(pending).await?;
+
+    Ok(())
+}
+
 //---- Section 5.5: Extending HTTP/2
 
 /// Extension frames that appear in the middle of a header block
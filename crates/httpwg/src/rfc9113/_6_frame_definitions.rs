@@ -3,11 +3,12 @@
 use buffet::{IntoHalves, Piece};
 use enumflags2::BitFlags;
 use loona_h2::{
-    ContinuationFlags, Frame, FrameType, GoAway, HeadersFlags, IntoPiece, KnownErrorCode,
+    ContinuationFlags, DataFlags, Frame, FrameType, GoAway, HeadersFlags, KnownErrorCode,
     PrioritySpec, Setting, SettingPairs, SettingsFlags, StreamId,
 };
+use tracing::debug;
 
-use crate::{dummy_bytes, Conn, ErrorC, FrameT};
+use crate::{dummy_bytes, Conn, ErrorC, FrameT, Responses, GRACEFUL_SHUTDOWN_PATH};
 
 //---- Section 6.1: DATA
 
@@ -71,16 +72,80 @@ pub async fn sends_data_frame_with_invalid_pad_length<IO: IntoHalves>(
     conn.write_headers(stream_id, HeadersFlags::EndHeaders, block_fragment)
         .await?;
 
-    // DATA frame:
-    // frame length: 5, pad length: 6
-    conn.send(b"\x00\x00\x05\x00\x09\x00\x00\x00\x01").await?;
-    conn.send(b"\x06\x54\x65\x73\x74").await?;
+    // pad length: 6, but only 4 bytes of data follow
+    conn.write_frame(
+        FrameType::Data(DataFlags::Padded | DataFlags::EndStream).into_frame(stream_id),
+        b"\x06Test",
+    )
+    .await?;
+
+    conn.verify_connection_error(ErrorC::ProtocolError).await?;
+
+    Ok(())
+}
+
+/// If the length of the padding is the length of the frame payload
+/// or greater, the recipient MUST treat this as a connection error
+/// (Section 5.4.1) of type PROTOCOL_ERROR.
+///
+/// This sends a pad length equal to the frame payload length, which
+/// counts the Pad Length field itself.
+pub async fn sends_data_frame_with_pad_length_equal_to_payload_length<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    let stream_id = StreamId(1);
+
+    conn.handshake().await?;
+
+    let mut headers = conn.common_headers("POST");
+    headers.append("content-length", "4");
+    let block_fragment = conn.encode_headers(&headers)?;
+
+    conn.write_headers(stream_id, HeadersFlags::EndHeaders, block_fragment)
+        .await?;
+
+    // pad length: 5, the frame payload is 5 bytes long
+    conn.write_frame(
+        FrameType::Data(DataFlags::Padded | DataFlags::EndStream).into_frame(stream_id),
+        b"\x05Test",
+    )
+    .await?;
 
     conn.verify_connection_error(ErrorC::ProtocolError).await?;
 
     Ok(())
 }
 
+/// If the length of the padding is the length of the frame payload
+/// or greater, the recipient MUST treat this as a connection error
+/// (Section 5.4.1) of type PROTOCOL_ERROR.
+///
+/// This sends valid padding: none at all, the most there can be, and
+/// enough to leave no data. It must be stripped, and must not count
+/// towards the content-length.
+pub async fn sends_data_frames_with_valid_padding<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    let stream_id = StreamId(1);
+
+    conn.handshake().await?;
+
+    let mut headers = conn.common_headers("POST");
+    headers.append("content-length", "4");
+    let block_fragment = conn.encode_headers(&headers)?;
+
+    conn.write_headers(stream_id, HeadersFlags::EndHeaders, block_fragment)
+        .await?;
+
+    conn.write_data_padded(stream_id, false, b"te", 0).await?;
+    conn.write_data_padded(stream_id, false, b"st", 255).await?;
+    conn.write_data_padded(stream_id, true, b"", 8).await?;
+
+    conn.verify_headers_frame(stream_id).await?;
+
+    Ok(())
+}
+
 //---- Section 6.2: HEADERS
 
 /// HEADERS frames MUST be associated with a stream. If a HEADERS
@@ -117,24 +182,82 @@ pub async fn sends_headers_frame_with_invalid_pad_length<IO: IntoHalves>(
 
     let block_fragment = conn.encode_headers(&conn.common_headers("POST"))?;
 
-    let frame = Frame::new(
+    // the pad length goes past the end of the frame
+    let mut payload = vec![(block_fragment.len() + 2) as u8];
+    payload.extend_from_slice(&block_fragment[..]);
+    conn.write_frame(
         FrameType::Headers(
             HeadersFlags::Padded | HeadersFlags::EndHeaders | HeadersFlags::EndStream,
-        ),
-        StreamId(1),
+        )
+        .into_frame(StreamId(1)),
+        payload,
     )
-    .with_len((block_fragment.len() + 1) as _);
-    let frame_header = frame.into_piece(&mut conn.scratch)?;
+    .await?;
+
+    conn.verify_connection_error(ErrorC::ProtocolError).await?;
+
+    Ok(())
+}
+
+/// The HEADERS frame can include padding. Padding fields and flags
+/// are identical to those defined for DATA frames (Section 6.1).
+/// Padding that exceeds the size remaining for the header block
+/// fragment MUST be treated as a PROTOCOL_ERROR.
+///
+/// This sends a pad length equal to the frame payload length, which
+/// counts the Pad Length field itself.
+pub async fn sends_headers_frame_with_pad_length_equal_to_payload_length<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
 
-    conn.send(frame_header).await?;
-    conn.send(vec![(block_fragment.len() + 2) as u8]).await?;
-    conn.send(block_fragment).await?;
+    let block_fragment = conn.encode_headers(&conn.common_headers("POST"))?;
+
+    // the pad length is the length of the whole frame payload
+    let mut payload = vec![(block_fragment.len() + 1) as u8];
+    payload.extend_from_slice(&block_fragment[..]);
+    conn.write_frame(
+        FrameType::Headers(
+            HeadersFlags::Padded | HeadersFlags::EndHeaders | HeadersFlags::EndStream,
+        )
+        .into_frame(StreamId(1)),
+        payload,
+    )
+    .await?;
 
     conn.verify_connection_error(ErrorC::ProtocolError).await?;
 
     Ok(())
 }
 
+/// The HEADERS frame can include padding. Padding fields and flags
+/// are identical to those defined for DATA frames (Section 6.1).
+/// Padding that exceeds the size remaining for the header block
+/// fragment MUST be treated as a PROTOCOL_ERROR.
+///
+/// This sends valid padding, which must be stripped before the header
+/// block fragment is decoded.
+pub async fn sends_headers_frame_with_valid_padding<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    let stream_id = StreamId(1);
+
+    conn.handshake().await?;
+
+    let block_fragment = conn.encode_headers(&conn.common_headers("POST"))?;
+    conn.write_headers_padded(
+        stream_id,
+        HeadersFlags::EndHeaders | HeadersFlags::EndStream,
+        block_fragment,
+        16,
+    )
+    .await?;
+
+    conn.verify_headers_frame(stream_id).await?;
+
+    Ok(())
+}
+
 //---- Section 6.3: PRIORITY
 
 /// The PRIORITY frame always identifies a stream. If a PRIORITY
@@ -244,10 +367,8 @@ pub async fn sends_rst_stream_frame_with_invalid_length<IO: IntoHalves>(
     )
     .await?;
 
-    let frame = Frame::new(FrameType::RstStream, StreamId(1)).with_len(3);
-    let frame_header = frame.into_piece(&mut conn.scratch)?;
-    conn.send(frame_header).await?;
-    conn.send(b"\x00\x00\x00").await?;
+    conn.write_frame(FrameType::RstStream.into_frame(stream_id), b"\x00\x00\x00")
+        .await?;
 
     conn.verify_stream_error(ErrorC::FrameSizeError).await?;
 
@@ -349,6 +470,44 @@ pub async fn sends_settings_enable_push_with_invalid_value<IO: IntoHalves>(
     Ok(())
 }
 
+/// SETTINGS_ENABLE_PUSH (0x2):
+/// The initial value is 1, which indicates that server push is
+/// permitted. Any value other than 0 or 1 MUST be treated as a
+/// connection error (Section 5.4.1) of type PROTOCOL_ERROR.
+///
+/// This sends the largest value a setting can have.
+pub async fn sends_settings_enable_push_with_max_value<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    conn.write_settings(&[(Setting::EnablePush, u32::MAX)])
+        .await?;
+
+    conn.verify_connection_error(ErrorC::ProtocolError).await?;
+
+    Ok(())
+}
+
+/// SETTINGS_ENABLE_PUSH (0x2):
+/// The initial value is 1, which indicates that server push is
+/// permitted. Any value other than 0 or 1 MUST be treated as a
+/// connection error (Section 5.4.1) of type PROTOCOL_ERROR.
+///
+/// This sends both valid values, which must not be treated as errors.
+pub async fn sends_settings_enable_push_with_valid_values<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    conn.write_settings(&[(Setting::EnablePush, 1), (Setting::EnablePush, 0)])
+        .await?;
+
+    conn.verify_connection_still_alive().await?;
+
+    Ok(())
+}
+
 /// SETTINGS_INITIAL_WINDOW_SIZE (0x4):
 /// Values above the maximum flow-control window size of 2^31-1
 /// MUST be treated as a connection error (Section 5.4.1) of
@@ -367,6 +526,45 @@ pub async fn sends_settings_initial_window_size_with_invalid_value<IO: IntoHalve
     Ok(())
 }
 
+/// SETTINGS_INITIAL_WINDOW_SIZE (0x4):
+/// Values above the maximum flow-control window size of 2^31-1
+/// MUST be treated as a connection error (Section 5.4.1) of
+/// type FLOW_CONTROL_ERROR.
+///
+/// This sends the largest value a setting can have.
+pub async fn sends_settings_initial_window_size_with_max_value<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    conn.write_settings(&[(Setting::InitialWindowSize, u32::MAX)])
+        .await?;
+
+    conn.verify_connection_error(ErrorC::FlowControlError)
+        .await?;
+
+    Ok(())
+}
+
+/// SETTINGS_INITIAL_WINDOW_SIZE (0x4):
+/// Values above the maximum flow-control window size of 2^31-1
+/// MUST be treated as a connection error (Section 5.4.1) of
+/// type FLOW_CONTROL_ERROR.
+///
+/// This sends exactly 2^31-1, which must not be treated as an error.
+pub async fn sends_settings_initial_window_size_with_max_valid_value<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    conn.write_settings(&[(Setting::InitialWindowSize, (1 << 31) - 1)])
+        .await?;
+
+    conn.verify_connection_still_alive().await?;
+
+    Ok(())
+}
+
 /// SETTINGS_MAX_FRAME_SIZE (0x5):
 /// The initial value is 2^14 (16,384) octets. The value advertised
 /// by an endpoint MUST be between this initial value and the
@@ -405,6 +603,71 @@ pub async fn sends_settings_max_frame_size_with_invalid_value_above_max<IO: Into
     Ok(())
 }
 
+/// SETTINGS_MAX_FRAME_SIZE (0x5):
+/// The initial value is 2^14 (16,384) octets. The value advertised
+/// by an endpoint MUST be between this initial value and the
+/// maximum allowed frame size (2^24-1 or 16,777,215 octets),
+/// inclusive. Values outside this range MUST be treated as a
+/// connection error (Section 5.4.1) of type PROTOCOL_ERROR.
+///
+/// This sends zero, the smallest value a setting can have.
+pub async fn sends_settings_max_frame_size_with_zero_value<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    conn.write_settings(&[(Setting::MaxFrameSize, 0)]).await?;
+
+    conn.verify_connection_error(ErrorC::ProtocolError).await?;
+
+    Ok(())
+}
+
+/// SETTINGS_MAX_FRAME_SIZE (0x5):
+/// The initial value is 2^14 (16,384) octets. The value advertised
+/// by an endpoint MUST be between this initial value and the
+/// maximum allowed frame size (2^24-1 or 16,777,215 octets),
+/// inclusive. Values outside this range MUST be treated as a
+/// connection error (Section 5.4.1) of type PROTOCOL_ERROR.
+///
+/// This sends the largest value a setting can have.
+pub async fn sends_settings_max_frame_size_with_max_value<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    conn.write_settings(&[(Setting::MaxFrameSize, u32::MAX)])
+        .await?;
+
+    conn.verify_connection_error(ErrorC::ProtocolError).await?;
+
+    Ok(())
+}
+
+/// SETTINGS_MAX_FRAME_SIZE (0x5):
+/// The initial value is 2^14 (16,384) octets. The value advertised
+/// by an endpoint MUST be between this initial value and the
+/// maximum allowed frame size (2^24-1 or 16,777,215 octets),
+/// inclusive. Values outside this range MUST be treated as a
+/// connection error (Section 5.4.1) of type PROTOCOL_ERROR.
+///
+/// This sends both ends of the range, which must not be treated as errors.
+pub async fn sends_settings_max_frame_size_with_boundary_values<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    conn.write_settings(&[
+        (Setting::MaxFrameSize, (1 << 24) - 1),
+        (Setting::MaxFrameSize, 1 << 14),
+    ])
+    .await?;
+
+    conn.verify_connection_still_alive().await?;
+
+    Ok(())
+}
+
 /// An endpoint that receives a SETTINGS frame with any unknown
 /// or unsupported identifier MUST ignore that setting.
 pub async fn sends_settings_frame_with_unknown_identifier<IO: IntoHalves>(
@@ -423,6 +686,9 @@ pub async fn sends_settings_frame_with_unknown_identifier<IO: IntoHalves>(
     )
     .await?;
 
+    // it gets acknowledged like any other SETTINGS frame
+    conn.expect_no_frame(FrameT::GoAway | FrameT::RstStream, conn.config.timeout)
+        .await?;
     conn.verify_connection_still_alive().await?;
 
     Ok(())
@@ -488,6 +754,29 @@ pub async fn sends_settings_frame_without_ack_flag<IO: IntoHalves>(
     Ok(())
 }
 
+/// Once all values have been processed, the recipient MUST
+/// immediately emit a SETTINGS frame with the ACK flag set. [...] If the
+/// sender of a SETTINGS frame does not receive an acknowledgment within a
+/// reasonable amount of time, it MAY issue a connection error (Section
+/// 5.4.1) of type SETTINGS_TIMEOUT.
+///
+/// "Reasonable" is up to the client: this one sends the same settings as in
+/// the handshake, which take no work to apply, and gives the server
+/// [crate::Config::settings_ack_timeout] to acknowledge them.
+pub async fn sends_settings_frame_and_expects_timely_ack<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    let settings = conn.config.settings();
+    conn.write_settings(&settings[..]).await?;
+
+    let took = conn.verify_settings_ack().await?;
+    debug!("server acknowledged our SETTINGS in {took:?}");
+
+    Ok(())
+}
+
 // (Note: Section 6.6 is skipped: push promise is discouraged nowadays)
 
 //---- Section 6.7: PING
@@ -524,6 +813,83 @@ pub async fn sends_ping_frame_with_ack<IO: IntoHalves>(mut conn: Conn<IO>) -> ey
     Ok(())
 }
 
+/// Receivers of a PING frame that does not include an ACK flag MUST
+/// send a PING frame with the ACK flag set in response, with an
+/// identical payload.
+///
+/// This sends several PING frames whose opaque data is all zeroes, all
+/// ones, or all different: each must come back as-is, in any order.
+pub async fn sends_ping_frames_with_arbitrary_payloads<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    let payloads: [[u8; 8]; 4] = [
+        [0x00; 8],
+        [0xff; 8],
+        [0x00, 0x01, 0x02, 0x03, 0xfc, 0xfd, 0xfe, 0xff],
+        *b"\r\n\0PING\0",
+    ];
+    for payload in &payloads {
+        conn.write_ping(false, payload.to_vec()).await?;
+    }
+
+    let mut unacked = payloads.to_vec();
+    while !unacked.is_empty() {
+        let (frame, payload) = conn.wait_for_frame(FrameT::Ping).await.into_result()?;
+        if !frame.is_ack() {
+            continue;
+        }
+        match unacked.iter().position(|p| p[..] == payload[..]) {
+            Some(i) => {
+                unacked.remove(i);
+            }
+            None => eyre::bail!(
+                "PING ack echoed {:02x?}, which isn't the payload of any unacked PING",
+                &payload[..]
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+/// ACK (0x1):
+/// When set, bit 0 indicates that this PING frame is a PING
+/// response. An endpoint MUST set this flag in PING responses.
+/// An endpoint MUST NOT respond to PING frames containing this
+/// flag.
+///
+/// This sends a burst of PING frames with the ACK flag set, then one
+/// without it: the only ACK the server sends back must be for the latter.
+pub async fn sends_multiple_ping_frames_with_ack<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    for i in 0..8u64 {
+        conn.write_ping(true, (0xacac_acac_0000_0000 | i).to_be_bytes().to_vec())
+            .await?;
+    }
+    let expected_data = b"h2spec\0\0";
+    conn.write_ping(false, expected_data.to_vec()).await?;
+
+    loop {
+        let (frame, payload) = conn.wait_for_frame(FrameT::Ping).await.into_result()?;
+        if !frame.is_ack() {
+            continue;
+        }
+        eyre::ensure!(
+            &payload[..] == expected_data,
+            "server responded to a PING frame with the ACK flag set (payload {:02x?})",
+            &payload[..]
+        );
+        break;
+    }
+
+    Ok(())
+}
+
 /// If a PING frame is received with a stream identifier field value
 /// other than 0x0, the recipient MUST respond with a connection
 /// error (Section 5.4.1) of type PROTOCOL_ERROR.
@@ -587,6 +953,133 @@ pub async fn sends_goaway_frame_with_non_zero_stream_id<IO: IntoHalves>(
     Ok(())
 }
 
+/// The last stream identifier in the GOAWAY frame contains the
+/// highest-numbered stream identifier for which the sender of the
+/// GOAWAY frame might have taken some action on or might yet take
+/// action on.
+///
+/// This opens three streams whose request bodies are still to come, asks
+/// the server to shut down gracefully on a fourth one, then sends the
+/// request bodies: all four streams are at or below the last stream ID of
+/// the GOAWAY frame, so all four should complete.
+pub async fn sends_data_frames_on_streams_in_flight_during_graceful_shutdown<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    let path = conn.config.path.clone();
+    let in_flight = [StreamId(1), StreamId(3), StreamId(5)];
+    for stream_id in in_flight {
+        conn.write_request(stream_id, "POST", &path, false).await?;
+    }
+    conn.write_request(StreamId(7), "GET", GRACEFUL_SHUTDOWN_PATH, true)
+        .await?;
+
+    let mut responses = Responses::default();
+    conn.read_responses(&mut responses, |r| r.goaway.is_some())
+        .await?;
+    let Some(goaway) = &responses.goaway else {
+        eyre::bail!("expected a GOAWAY frame after requesting {GRACEFUL_SHUTDOWN_PATH}");
+    };
+    if KnownErrorCode::try_from(goaway.error_code) != Ok(KnownErrorCode::NoError) {
+        eyre::bail!(
+            "expected a GOAWAY frame with NO_ERROR, got {:?}",
+            goaway.error_code
+        );
+    }
+    if goaway.last_stream_id < StreamId(7) {
+        eyre::bail!(
+            "the last stream ID of the GOAWAY frame is {}, but the server processed stream 7",
+            goaway.last_stream_id
+        );
+    }
+
+    for stream_id in in_flight {
+        conn.write_data(stream_id, true, "hello").await?;
+    }
+
+    let stream_ids = [StreamId(1), StreamId(3), StreamId(5), StreamId(7)];
+    conn.read_responses(&mut responses, |r| {
+        stream_ids.iter().all(|&stream_id| r.ended(stream_id))
+    })
+    .await?;
+    for stream_id in stream_ids {
+        if !responses.completed(stream_id) {
+            eyre::bail!(
+                "stream {stream_id} should have completed during the graceful shutdown, got {:?}",
+                responses.streams.get(&stream_id)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Once sent, the sender will ignore frames sent on streams initiated
+/// by the receiver if the stream has an identifier higher than the
+/// included last stream identifier.
+///
+/// This keeps a stream open, asks the server to shut down gracefully on
+/// another one, then opens two more streams after the GOAWAY frame: the
+/// server should either reset them or ignore them, and complete the first
+/// two.
+pub async fn sends_headers_frames_after_graceful_shutdown_goaway<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    let path = conn.config.path.clone();
+    conn.write_request(StreamId(1), "POST", &path, false)
+        .await?;
+    conn.write_request(StreamId(3), "GET", GRACEFUL_SHUTDOWN_PATH, true)
+        .await?;
+
+    // a server may start with a GOAWAY frame whose last stream ID is 2^31-1,
+    // and only send the actual one a round-trip later.
+    let mut responses = Responses::default();
+    conn.read_responses(&mut responses, |r| {
+        r.goaway
+            .as_ref()
+            .is_some_and(|g| g.last_stream_id < StreamId((1 << 31) - 1))
+    })
+    .await?;
+    if responses.goaway.is_none() {
+        eyre::bail!("expected a GOAWAY frame after requesting {GRACEFUL_SHUTDOWN_PATH}");
+    }
+
+    conn.write_request(StreamId(5), "GET", &path, true).await?;
+    conn.write_request(StreamId(7), "GET", &path, true).await?;
+    conn.write_data(StreamId(1), true, "hello").await?;
+
+    let stream_ids = [StreamId(1), StreamId(3), StreamId(5), StreamId(7)];
+    conn.read_responses(&mut responses, |r| {
+        stream_ids.iter().all(|&stream_id| r.ended(stream_id))
+    })
+    .await?;
+
+    // the server may have lowered the last stream ID with another GOAWAY
+    let last_stream_id = responses
+        .goaway
+        .as_ref()
+        .map_or(StreamId::CONNECTION, |g| g.last_stream_id);
+    for stream_id in stream_ids {
+        let response = responses.streams.get(&stream_id);
+        if stream_id <= last_stream_id {
+            if !responses.completed(stream_id) {
+                eyre::bail!(
+                    "stream {stream_id} is at or below the last stream ID of the GOAWAY frame ({last_stream_id}), so it should have completed, got {response:?}"
+                );
+            }
+        } else if response.is_some_and(|r| r.status.is_some()) {
+            eyre::bail!(
+                "stream {stream_id} is above the last stream ID of the GOAWAY frame ({last_stream_id}), so it shouldn't have been processed, got {response:?}"
+            );
+        }
+    }
+
+    Ok(())
+}
+
 //---- Section 6.9: WINDOW_UPDATE
 
 /// A receiver MUST treat the receipt of a WINDOW_UPDATE frame with
@@ -698,8 +1191,10 @@ pub async fn sends_multiple_window_update_frames_increasing_flow_control_window_
 
     conn.write_window_update(StreamId::CONNECTION, (1 << 31) - 1)
         .await?;
-    conn.write_window_update(StreamId::CONNECTION, (1 << 31) - 1)
-        .await?;
+    // that write might fail: the window may already have exceeded the max
+    _ = conn
+        .write_window_update(StreamId::CONNECTION, (1 << 31) - 1)
+        .await;
 
     conn.verify_connection_error(ErrorC::FlowControlError)
         .await?;
@@ -739,6 +1234,80 @@ pub async fn sends_multiple_window_update_frames_increasing_flow_control_window_
     Ok(())
 }
 
+/// A sender MUST NOT allow a flow-control window to exceed 2^31-1
+/// octets.
+///
+/// This grows the connection window to exactly 2^31-1 octets, which
+/// is allowed.
+pub async fn sends_window_update_frame_increasing_flow_control_window_to_max<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    let increment = conn.max_window_increment(StreamId::CONNECTION);
+    conn.write_window_update(StreamId::CONNECTION, increment)
+        .await?;
+
+    conn.verify_connection_still_alive().await?;
+
+    Ok(())
+}
+
+/// A sender MUST NOT allow a flow-control window to exceed 2^31-1
+/// octets. If a sender receives a WINDOW_UPDATE that causes a
+/// flow-control window to exceed this maximum, it MUST terminate
+/// either the stream or the connection, as appropriate.
+///
+/// This grows the connection window to 2^31 octets, one past the
+/// maximum, with a single WINDOW_UPDATE frame.
+pub async fn sends_window_update_frame_increasing_flow_control_window_to_max_plus_one<
+    IO: IntoHalves,
+>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    let increment = conn.max_window_increment(StreamId::CONNECTION) + 1;
+    conn.write_window_update(StreamId::CONNECTION, increment)
+        .await?;
+
+    conn.verify_connection_error(ErrorC::FlowControlError)
+        .await?;
+
+    Ok(())
+}
+
+/// A sender MUST NOT allow a flow-control window to exceed 2^31-1
+/// octets. If a sender receives a WINDOW_UPDATE that causes a
+/// flow-control window to exceed this maximum, it MUST terminate
+/// either the stream or the connection, as appropriate.
+///
+/// This grows a stream window to 2^31 octets, one past the maximum,
+/// with a single WINDOW_UPDATE frame.
+pub async fn sends_window_update_frame_increasing_flow_control_window_to_max_plus_one_on_stream<
+    IO: IntoHalves,
+>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    let stream_id = StreamId(1);
+
+    conn.handshake().await?;
+
+    conn.encode_and_write_headers(
+        stream_id,
+        HeadersFlags::EndHeaders,
+        &conn.common_headers("POST"),
+    )
+    .await?;
+
+    let increment = conn.max_window_increment(stream_id) + 1;
+    conn.write_window_update(stream_id, increment).await?;
+
+    conn.verify_stream_error(ErrorC::FlowControlError).await?;
+
+    Ok(())
+}
+
 //---- Section 6.9.2: Initial Flow-Control Window Size
 
 /// When the value of SETTINGS_INITIAL_WINDOW_SIZE changes,
@@ -830,6 +1399,42 @@ pub async fn sends_settings_initial_window_size_with_exceeded_max_window_size_va
     Ok(())
 }
 
+/// An endpoint MUST treat a change to SETTINGS_INITIAL_WINDOW_SIZE
+/// that causes any flow-control window to exceed the maximum size
+/// as a connection error (Section 5.4.1) of type FLOW_CONTROL_ERROR.
+///
+/// This grows a stream window to exactly 2^31-1 octets with a
+/// WINDOW_UPDATE frame, then bumps SETTINGS_INITIAL_WINDOW_SIZE by one.
+pub async fn sends_settings_initial_window_size_making_stream_window_exceed_max<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    let stream_id = StreamId(1);
+
+    conn.handshake().await?;
+
+    conn.encode_and_write_headers(
+        stream_id,
+        HeadersFlags::EndHeaders,
+        &conn.common_headers("POST"),
+    )
+    .await?;
+
+    let increment = conn.max_window_increment(stream_id);
+    conn.write_window_update(stream_id, increment).await?;
+    conn.verify_connection_still_alive().await?;
+
+    let settings = [(
+        Setting::InitialWindowSize,
+        conn.config.initial_window_size + 1,
+    )];
+    conn.write_settings(&settings[..]).await?;
+
+    conn.verify_connection_error(ErrorC::FlowControlError)
+        .await?;
+
+    Ok(())
+}
+
 // Note: 6.9.3 (reducing the stream window size) is really tricky to test.
 
 //---- Section 6.10: CONTINUATION
@@ -5,7 +5,7 @@ use std::io::Write;
 use buffet::IntoHalves;
 use loona_h2::{pack_bit_and_u31, FrameType, HeadersFlags, StreamId};
 
-use crate::{Conn, ErrorC, FrameT, Headers};
+use crate::{dummy_bytes, Conn, ErrorC, Headers, Request};
 
 //---- Section 8.1: HTTP Message Framing
 
@@ -35,6 +35,71 @@ pub async fn sends_second_headers_frame_without_end_stream<IO: IntoHalves>(
     Ok(())
 }
 
+/// An HTTP message (request or response) consists of:
+///
+/// 1. one HEADERS frame (followed by zero or more CONTINUATION frames)
+///    containing the header section (see Section 6.3 of [HTTP]),
+/// 2. zero or more DATA frames containing the message content (see Section
+///    6.4 of [HTTP]), and
+/// 3. optionally, one HEADERS frame (followed by zero or more CONTINUATION
+///    frames) containing the trailer section, if present (see Section 6.5 of
+///    [HTTP]).
+///
+/// This sends a request with all three: its content spans several DATA
+/// frames, as far as the server's flow-control window allows, and the
+/// server should answer it.
+pub async fn sends_request_with_content_and_trailers<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    // the connection's flow-control window starts out at 65,535 bytes, no
+    // matter what the settings say
+    let len = (2 * conn.settings.max_frame_size + 1)
+        .min(conn.settings.initial_window_size)
+        .min(65_535);
+    let req = Request::post(dummy_bytes(len as usize))
+        .header("content-length", len.to_string().into_bytes())
+        .trailer("x-checksum", "ok");
+    let pending = conn.send_request(req).await?;
+
+    let res = conn.read_response(pending).await?;
+    eyre::ensure!(
+        res.status < 400,
+        "expected a successful response, got status {}",
+        res.status
+    );
+
+    Ok(())
+}
+
+/// An HTTP message (request or response) consists of: [...] 2. zero or
+/// more DATA frames containing the message content [...] and 3.
+/// optionally, one HEADERS frame (followed by zero or more CONTINUATION
+/// frames) containing the trailer section.
+///
+/// This sends a request with no content at all, just a header section and
+/// a trailer section with a few fields, and the server should answer it.
+pub async fn sends_request_with_trailers_and_no_content<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    let req = Request::post(vec![])
+        .trailer("x-checksum", "ok")
+        .trailer("x-trailer-count", "2");
+    let pending = conn.send_request(req).await?;
+
+    let res = conn.read_response(pending).await?;
+    eyre::ensure!(
+        res.status < 400,
+        "expected a successful response, got status {}",
+        res.status
+    );
+
+    Ok(())
+}
+
 //--- Section 8.1.1: Malformed Messages
 
 // A request or response that includes message content can include a
@@ -92,6 +157,29 @@ pub async fn sends_headers_frame_with_incorrect_content_length_multiple_data_fra
     Ok(())
 }
 
+/// A request or response is also malformed if the value of a
+/// content-length header field does not equal the sum of the DATA frame
+/// payload lengths that form the content, unless the message is defined as
+/// having no content.
+///
+/// This checks the server's own response to a GET request, if it has a
+/// content-length header.
+pub async fn response_content_length_matches_content<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    let pending = conn.send_request(Request::get()).await?;
+    let res = conn.read_response(pending).await?;
+    // 204 (No Content) and 304 (Not Modified) responses have no content,
+    // whatever content-length says
+    if !matches!(res.status, 204 | 304) {
+        res.verify_content_length()?;
+    }
+
+    Ok(())
+}
+
 //--- Section 8.2.1: Field Validity
 
 /// A field name MUST NOT contain characters in the ranges 0x00-0x20, 0x41-0x5a,
@@ -512,13 +600,28 @@ pub async fn sends_headers_frame_with_pseudo_header_in_trailer<IO: IntoHalves>(
 
     let mut trailers = Headers::default();
     trailers.append(":method", "POST");
-    let trailers_fragment = conn.encode_headers(&trailers)?;
-    conn.write_headers(
-        stream_id,
-        HeadersFlags::EndHeaders | HeadersFlags::EndStream,
-        trailers_fragment,
-    )
-    .await?;
+    conn.write_trailers(stream_id, &trailers).await?;
+
+    conn.verify_stream_error(ErrorC::ProtocolError).await?;
+
+    Ok(())
+}
+
+/// [...] Pseudo-header fields MUST NOT appear in a trailer section. Endpoints
+/// MUST treat a request or response that contains undefined or invalid
+/// pseudo-header fields as malformed (Section 8.1.1).
+///
+/// This one's trailer section has a valid field, then a `:status`
+/// pseudo-header, which has no business in a request either.
+pub async fn sends_trailers_with_pseudo_header_after_regular_field<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    let req = Request::post(b"test".to_vec())
+        .trailer("x-checksum", "ok")
+        .trailer(":status", "200");
+    _ = conn.send_request(req).await?;
 
     conn.verify_stream_error(ErrorC::ProtocolError).await?;
 
@@ -657,19 +760,23 @@ pub async fn sends_headers_frame_without_status<IO: IntoHalves>(
     .await?;
 
     // wait for the response
-    let (frame, payload) = conn.wait_for_frame(FrameT::Headers).await.unwrap();
-    assert!(frame.is_end_headers(), "the test makes that assumption");
-    let headers = conn.decode_headers(payload.into())?;
+    let headers = conn.read_headers(StreamId(1)).await?;
 
     let mut found_status = false;
+    let mut seen_regular = false;
     for (name, _) in headers.iter() {
         if name == b":status" {
+            assert!(
+                !seen_regular,
+                ":status must come before regular header fields"
+            );
             found_status = true;
         } else {
             assert!(
                 !name.starts_with(b":"),
                 "no header name should start with ':'"
             );
+            seen_regular = true;
         }
     }
     assert!(found_status, "the :status pseudo-header must be present");
@@ -0,0 +1,135 @@
+//! Section 9: HTTP/2 Connections
+
+use std::time::Duration;
+
+use buffet::IntoHalves;
+use loona_h2::Frame;
+use tokio::time::Instant;
+use tracing::debug;
+
+use crate::{Conn, Ev, NextEvent, Payload, Request};
+
+//---- Section 9.1: Connection Management
+
+/// How many PINGs a soak test sends, spread over [crate::Config::soak_duration]
+const SOAK_PINGS: u32 = 10;
+
+/// HTTP/2 connections are persistent. For best performance, it is
+/// expected that clients will not close connections until it is
+/// determined that no further communication with a server is necessary
+/// [...]
+///
+/// Servers are encouraged to maintain open connections for as long as
+/// possible but are permitted to terminate idle connections if
+/// necessary. When either endpoint chooses to close the transport-layer
+/// TCP connection, the terminating endpoint SHOULD first send a GOAWAY
+/// (Section 6.8) frame so that both endpoints can reliably determine
+/// whether previously sent frames have been processed and gracefully
+/// complete or terminate any necessary remaining tasks.
+///
+/// This keeps a connection open for [crate::Config::soak_duration], sending
+/// nothing but PINGs now and then, and answering the server's own PINGs.
+/// Round-trip times get logged. The server may close the connection, but
+/// only after sending a GOAWAY.
+pub async fn sends_ping_frames_on_idle_connection<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    let start = Instant::now();
+    let interval = conn.config.soak_duration / SOAK_PINGS;
+    let timeout = conn.config.timeout;
+    let mut goaway: Option<Frame> = None;
+    let mut rtts = Vec::new();
+
+    for i in 0..SOAK_PINGS {
+        tokio::time::sleep(interval).await;
+
+        let ping_payload = (i as u64).to_be_bytes();
+        // this fails if the server hung up, we'll find out why below
+        _ = conn.write_ping(false, ping_payload.to_vec()).await;
+        let sent_at = Instant::now();
+
+        let deadline = sent_at + timeout;
+        loop {
+            let (frame, payload) = match conn.next_event_with_deadline(deadline).await {
+                NextEvent::Timeout { .. } => {
+                    eyre::bail!(
+                        "no ack for PING #{i} within {timeout:?}, {:?} into the connection",
+                        sent_at - start
+                    )
+                }
+                NextEvent::Eof | NextEvent::Ev(Ev::Eof | Ev::IoError { .. }) => match goaway {
+                    Some(goaway) => {
+                        debug!(
+                            "server closed the connection after {:?} and {goaway:?}",
+                            start.elapsed()
+                        );
+                        return Ok(());
+                    }
+                    None => eyre::bail!(
+                        "server closed the idle connection after {:?} without sending a GOAWAY first",
+                        start.elapsed()
+                    ),
+                },
+                NextEvent::Ev(Ev::ProtocolViolation { reason }) => {
+                    eyre::bail!("server violated the protocol: {reason}")
+                }
+                NextEvent::Ev(Ev::Frame { frame, payload }) => (frame, payload),
+                NextEvent::Ev(Ev::Headers { .. }) => continue,
+                NextEvent::Ev(Ev::Raw { .. }) => unreachable!("raw mode is never on here"),
+            };
+
+            match Payload::decode(&frame, payload)? {
+                Payload::GoAway(_) => goaway = Some(frame),
+                Payload::Ping(payload) if frame.is_ack() => {
+                    eyre::ensure!(
+                        payload == ping_payload,
+                        "PING ack echoed {payload:02x?}, expected {ping_payload:02x?}"
+                    );
+                    rtts.push(sent_at.elapsed());
+                    break;
+                }
+                // the server checking on us: it may hang up if we don't answer
+                Payload::Ping(payload) => _ = conn.write_ping(true, payload.to_vec()).await,
+                _ => {}
+            }
+        }
+    }
+
+    if let (Some(min), Some(max)) = (rtts.iter().min(), rtts.iter().max()) {
+        let avg = rtts.iter().sum::<Duration>() / rtts.len() as u32;
+        debug!(
+            "{} PING round trips over {:?}: min {min:?}, avg {avg:?}, max {max:?}",
+            rtts.len(),
+            start.elapsed()
+        );
+    }
+
+    Ok(())
+}
+
+/// Servers are encouraged to maintain open connections for as long as
+/// possible but are permitted to terminate idle connections if
+/// necessary. When either endpoint chooses to close the transport-layer
+/// TCP connection, the terminating endpoint SHOULD first send a GOAWAY
+/// (Section 6.8) frame so that both endpoints can reliably determine
+/// whether previously sent frames have been processed and gracefully
+/// complete or terminate any necessary remaining tasks.
+///
+/// This sends a request, then half-closes the TCP connection without a
+/// GOAWAY: the request is complete, so the server should still send the
+/// whole response before it closes its own half.
+pub async fn sends_request_then_half_closes_connection<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    let pending = conn.send_request(Request::get()).await?;
+    conn.shutdown_write().await?;
+
+    let res = conn.read_response(pending).await?;
+    debug!(status = %res.status, body_len = %res.body.len(), "got response after half-close");
+
+    Ok(())
+}
@@ -10,24 +10,16 @@
 //!
 //! cf. <https://httpwg.org/specs/rfc9113.html>
 
-use loona_h2::{Setting, SettingPairs};
-
 pub const DEFAULT_WINDOW_SIZE: u32 = 65536;
 pub const DEFAULT_FRAME_SIZE: u32 = 16384;
+/// No flow-control window may grow past this, cf. Section 6.9.1
+pub const MAX_WINDOW_SIZE: i64 = (1 << 31) - 1;
 
-pub fn default_settings() -> SettingPairs<'static> {
-    SettingPairs::from(
-        [
-            (Setting::InitialWindowSize, DEFAULT_WINDOW_SIZE),
-            (Setting::MaxFrameSize, DEFAULT_FRAME_SIZE),
-        ]
-        .as_ref(),
-    )
-}
-
+pub mod _10_security_considerations;
 pub mod _3_starting_http2;
 pub mod _4_http_frames;
 pub mod _5_streams_and_multiplexing;
 pub mod _6_frame_definitions;
 pub mod _7_error_codes;
 pub mod _8_expressing_http_semantics_in_http2;
+pub mod _9_http2_connections;
@@ -0,0 +1,82 @@
+//! Section 4: Priority Parameters
+
+use buffet::IntoHalves;
+use loona_h2::{HeadersFlags, StreamId};
+
+use crate::Conn;
+
+/// Unknown parameters, parameters with out-of-range values, or values
+/// of unexpected types MUST be ignored.
+///
+/// This sends a parameter nobody knows about, next to valid ones.
+pub async fn sends_priority_header_with_unknown_parameters<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    let stream_id = StreamId(1);
+
+    conn.handshake().await?;
+
+    let mut headers = conn.common_headers("GET");
+    headers.append("priority", "u=2, foo=bar, i");
+    conn.encode_and_write_headers(
+        stream_id,
+        HeadersFlags::EndStream | HeadersFlags::EndHeaders,
+        &headers,
+    )
+    .await?;
+
+    conn.verify_response(stream_id).await?;
+
+    Ok(())
+}
+
+/// Unknown parameters, parameters with out-of-range values, or values
+/// of unexpected types MUST be ignored.
+///
+/// The urgency goes from 0 to 7 inclusive: this sends 8, then -1.
+pub async fn sends_priority_header_with_out_of_range_urgency<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    for (stream_id, priority) in [(StreamId(1), "u=8"), (StreamId(3), "u=-1")] {
+        let mut headers = conn.common_headers("GET");
+        headers.append("priority", priority);
+        conn.encode_and_write_headers(
+            stream_id,
+            HeadersFlags::EndStream | HeadersFlags::EndHeaders,
+            &headers,
+        )
+        .await?;
+
+        conn.verify_response(stream_id).await?;
+    }
+
+    Ok(())
+}
+
+/// Unknown parameters, parameters with out-of-range values, or values
+/// of unexpected types MUST be ignored.
+///
+/// The urgency is an Integer and incremental is a Boolean: this sends a
+/// Token and an Integer instead.
+pub async fn sends_priority_header_with_unexpected_types<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    let stream_id = StreamId(1);
+
+    conn.handshake().await?;
+
+    let mut headers = conn.common_headers("GET");
+    headers.append("priority", "u=high, i=3");
+    conn.encode_and_write_headers(
+        stream_id,
+        HeadersFlags::EndStream | HeadersFlags::EndHeaders,
+        &headers,
+    )
+    .await?;
+
+    conn.verify_response(stream_id).await?;
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+//! Section 5: The Priority HTTP Header Field
+
+use buffet::IntoHalves;
+use loona_h2::{HeadersFlags, StreamId};
+
+use crate::Conn;
+
+/// The Priority HTTP header field is a Dictionary that carries priority
+/// parameters (see Section 4). It can appear in requests and responses.
+pub async fn sends_priority_header<IO: IntoHalves>(mut conn: Conn<IO>) -> eyre::Result<()> {
+    let stream_id = StreamId(1);
+
+    conn.handshake().await?;
+
+    let mut headers = conn.common_headers("GET");
+    headers.append("priority", "u=0, i");
+    conn.encode_and_write_headers(
+        stream_id,
+        HeadersFlags::EndStream | HeadersFlags::EndHeaders,
+        &headers,
+    )
+    .await?;
+
+    conn.verify_response(stream_id).await?;
+
+    Ok(())
+}
+
+/// When receiving an HTTP request that does not carry these priority
+/// parameters, a server SHOULD act as if their default values were
+/// specified.
+///
+/// A Priority header field that can't be parsed as a Dictionary carries
+/// no parameters: the request must go through all the same.
+pub async fn sends_unparseable_priority_header<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    let stream_id = StreamId(1);
+
+    conn.handshake().await?;
+
+    let mut headers = conn.common_headers("GET");
+    headers.append("priority", "u=0,, ;;=");
+    conn.encode_and_write_headers(
+        stream_id,
+        HeadersFlags::EndStream | HeadersFlags::EndHeaders,
+        &headers,
+    )
+    .await?;
+
+    conn.verify_response(stream_id).await?;
+
+    Ok(())
+}
@@ -0,0 +1,114 @@
+//! Section 7: The PRIORITY_UPDATE Frame
+
+use buffet::IntoHalves;
+use loona_h2::{HeadersFlags, StreamId};
+
+use crate::Conn;
+
+//---- Section 7.1: HTTP/2 PRIORITY_UPDATE Frame
+
+/// The HTTP/2 PRIORITY_UPDATE frame (type=0x10) is used by clients to
+/// signal the initial priority of a response, or to reprioritize a
+/// response or push stream.
+///
+/// This reprioritizes a request whose body is still being sent.
+pub async fn sends_priority_update_frame_for_open_stream<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    let stream_id = StreamId(1);
+
+    conn.handshake().await?;
+
+    let headers = conn.common_headers("POST");
+    conn.encode_and_write_headers(stream_id, HeadersFlags::EndHeaders, &headers)
+        .await?;
+
+    conn.write_priority_update(stream_id, "u=0").await?;
+    conn.write_data(stream_id, true, b"test").await?;
+
+    conn.verify_response(stream_id).await?;
+
+    Ok(())
+}
+
+/// When the PRIORITY_UPDATE frame applies to a request stream, clients
+/// SHOULD provide a prioritized stream ID that refers to a stream in
+/// the "open", "half-closed (local)", or "idle" state.
+///
+/// This sends the initial priority of a request before the request
+/// itself, while its stream is still idle.
+pub async fn sends_priority_update_frame_for_idle_stream<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    let stream_id = StreamId(1);
+
+    conn.handshake().await?;
+
+    conn.write_priority_update(stream_id, "u=5, i").await?;
+    conn.send_empty_post_to_root(stream_id).await?;
+
+    conn.verify_response(stream_id).await?;
+
+    Ok(())
+}
+
+/// When the PRIORITY_UPDATE frame applies to a request stream, clients
+/// SHOULD provide a prioritized stream ID that refers to a stream in
+/// the "open", "half-closed (local)", or "idle" state.
+///
+/// This prioritizes a stream that never gets opened, which must not get
+/// in the way of other requests.
+pub async fn sends_priority_update_frame_for_nonexistent_stream<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    conn.write_priority_update(StreamId(101), "u=1").await?;
+    conn.verify_connection_still_alive().await?;
+
+    conn.send_empty_post_to_root(StreamId(1)).await?;
+    conn.verify_response(StreamId(1)).await?;
+
+    Ok(())
+}
+
+/// Servers can discard frames where the Prioritized Stream ID refers to
+/// a stream in the "half-closed (local)" or "closed" state.
+///
+/// This reprioritizes a request that's already been responded to.
+pub async fn sends_priority_update_frame_for_closed_stream<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    let stream_id = StreamId(1);
+
+    conn.handshake().await?;
+
+    conn.send_empty_post_to_root(stream_id).await?;
+    conn.verify_response(stream_id).await?;
+
+    conn.write_priority_update(stream_id, "u=7").await?;
+    conn.verify_connection_still_alive().await?;
+
+    Ok(())
+}
+
+/// The Priority Field Value is the priority update value in ASCII text,
+/// encoded using Structured Fields. This is the same representation as
+/// the Priority header field value.
+///
+/// A value that can't be parsed carries no parameters, and must be
+/// ignored like a Priority header field that can't be parsed.
+pub async fn sends_priority_update_frame_with_unparseable_value<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+) -> eyre::Result<()> {
+    let stream_id = StreamId(1);
+
+    conn.handshake().await?;
+
+    conn.write_priority_update(stream_id, "u=0,, ;;=").await?;
+    conn.send_empty_post_to_root(stream_id).await?;
+
+    conn.verify_response(stream_id).await?;
+
+    Ok(())
+}
@@ -0,0 +1,14 @@
+//! RFC 9218 describes a scheme that allows an HTTP client to communicate
+//! its preferences for how the upstream server prioritizes responses to its
+//! requests, and also allows a server to hint to a downstream intermediary
+//! how its responses should be prioritized when they are forwarded.
+//!
+//! It defines the Priority header field and, for HTTP/2, the
+//! PRIORITY_UPDATE frame. Servers are free to apply these signals or not,
+//! but none of them may get a request or a connection rejected.
+//!
+//! cf. <https://httpwg.org/specs/rfc9218.html>
+
+pub mod _4_priority_parameters;
+pub mod _5_the_priority_http_header_field;
+pub mod _7_the_priority_update_frame;
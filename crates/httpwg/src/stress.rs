@@ -0,0 +1,381 @@
+//! Not conformance, endurance: keeps a server busy for minutes, over many
+//! connections and with bodies of mixed sizes, and samples its latency (and
+//! memory use, when we know its process) along the way. Leaks and slow
+//! degradation only show up after thousands of streams, which quick
+//! functional tests never get to.
+//!
+//! Like [crate::bench], requests go through [Conn::send_request]: what to
+//! look at is how the numbers drift from one sample to the next, not the
+//! numbers themselves.
+
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc, time::Duration};
+
+use buffet::IntoHalves;
+use loona_h2::{KnownErrorCode, StreamId};
+use tokio::time::Instant;
+
+use crate::{bench::CONNECTION_WINDOW, Config, Conn, Connector, Request, Responses};
+
+/// The load to keep the server under
+#[derive(Debug, Clone)]
+pub struct Stress {
+    /// how many connections to keep open, all driven at once
+    pub connections: u32,
+
+    /// how many requests each connection keeps in flight, at most: fewer if
+    /// the server's SETTINGS_MAX_CONCURRENT_STREAMS says so
+    pub streams: u32,
+
+    /// how many requests a connection sends before it's closed and replaced
+    /// by a fresh one, so connection setup and teardown get exercised too
+    pub requests_per_connection: u32,
+
+    /// the sizes of request bodies, taken in turn: 0 makes for a GET, the
+    /// others for POSTs. Sizes above the server's initial stream window get
+    /// capped to it.
+    pub body_sizes: Vec<usize>,
+
+    /// how long to keep the server busy
+    pub duration: Duration,
+
+    /// how many times to sample latency and memory use over `duration`
+    pub samples: u32,
+}
+
+impl Default for Stress {
+    fn default() -> Self {
+        Self {
+            connections: 8,
+            streams: 20,
+            requests_per_connection: 1_000,
+            body_sizes: vec![0, 100, 1_000, 10_000],
+            duration: Duration::from_secs(5 * 60),
+            samples: 10,
+        }
+    }
+}
+
+/// How the server fared over one sampling period
+#[derive(Debug, Clone)]
+pub struct Sample {
+    /// since the run started
+    pub at: Duration,
+
+    /// requests that got a complete response during the period
+    pub completed: u64,
+
+    /// requests that didn't, e.g. because their stream got reset or their
+    /// connection dropped
+    pub failed: u64,
+
+    pub p50: Option<Duration>,
+    pub p99: Option<Duration>,
+
+    /// the server's resident memory at the end of the period, in bytes, if
+    /// we know how to measure it
+    pub rss: Option<u64>,
+}
+
+impl fmt::Display for Sample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:>6.1}s: {} requests, {} failed",
+            self.at.as_secs_f64(),
+            self.completed,
+            self.failed
+        )?;
+        if let (Some(p50), Some(p99)) = (self.p50, self.p99) {
+            write!(f, ", latency p50 {p50:.2?}, p99 {p99:.2?}")?;
+        }
+        if let Some(rss) = self.rss {
+            write!(f, ", rss {:.1} MiB", rss as f64 / (1024.0 * 1024.0))?;
+        }
+        Ok(())
+    }
+}
+
+/// How a [run] went
+#[derive(Debug, Default)]
+pub struct StressReport {
+    pub samples: Vec<Sample>,
+
+    /// how many connections got opened, replacements included
+    pub connections: u64,
+
+    /// how many connections the server dropped, reset or stopped answering
+    /// on, and what happened to the last one
+    pub dropped: u64,
+    pub last_drop: Option<String>,
+}
+
+impl StressReport {
+    pub fn completed(&self) -> u64 {
+        self.samples.iter().map(|s| s.completed).sum()
+    }
+
+    pub fn failed(&self) -> u64 {
+        self.samples.iter().map(|s| s.failed).sum()
+    }
+
+    /// How much the p99 latency grew from the first sample to the last, in
+    /// percent
+    pub fn latency_growth(&self) -> Option<f64> {
+        self.growth(|s| s.p99.map(|d| d.as_secs_f64()))
+    }
+
+    /// How much the server's resident memory grew from the first sample to
+    /// the last, in percent
+    pub fn memory_growth(&self) -> Option<f64> {
+        self.growth(|s| s.rss.map(|rss| rss as f64))
+    }
+
+    fn growth(&self, metric: impl Fn(&Sample) -> Option<f64>) -> Option<f64> {
+        let (first, last) = match self.samples.as_slice() {
+            [first, .., last] => (metric(first)?, metric(last)?),
+            _ => return None,
+        };
+        if first == 0.0 {
+            return None;
+        }
+        Some((last / first - 1.0) * 100.0)
+    }
+}
+
+impl fmt::Display for StressReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} requests over {} connections, {} failed, {} connections dropped",
+            self.completed(),
+            self.connections,
+            self.failed(),
+            self.dropped
+        )?;
+        if let Some(growth) = self.latency_growth() {
+            write!(f, ", p99 latency {growth:+.1}%")?;
+        }
+        if let Some(growth) = self.memory_growth() {
+            write!(f, ", memory {growth:+.1}%")?;
+        }
+        Ok(())
+    }
+}
+
+/// What the connections have gotten through since the last sample
+#[derive(Default)]
+struct Tally {
+    completed: u64,
+    failed: u64,
+    latencies: Vec<Duration>,
+    connections: u64,
+    dropped: u64,
+    last_drop: Option<String>,
+}
+
+impl Tally {
+    fn sample(&mut self, at: Duration, rss: Option<u64>) -> Sample {
+        let mut latencies = std::mem::take(&mut self.latencies);
+        latencies.sort_unstable();
+        Sample {
+            at,
+            completed: std::mem::take(&mut self.completed),
+            failed: std::mem::take(&mut self.failed),
+            p50: crate::bench::percentile(&latencies, 50.0),
+            p99: crate::bench::percentile(&latencies, 99.0),
+            rss,
+        }
+    }
+}
+
+/// Keeps the server `connect` reaches under `stress`, calling `rss` for its
+/// memory use and `on_sample` with every sample as it's taken. A connection
+/// the server drops gets replaced, and counted in the report: the run only
+/// fails if a connection can't be opened at all.
+pub async fn run<IO: IntoHalves>(
+    config: Rc<Config>,
+    connect: Connector<IO>,
+    stress: &Stress,
+    rss: impl Fn() -> Option<u64>,
+    mut on_sample: impl FnMut(&Sample),
+) -> eyre::Result<StressReport> {
+    if stress.body_sizes.is_empty() {
+        eyre::bail!("stress runs need at least one body size");
+    }
+
+    let start = Instant::now();
+    let deadline = start + stress.duration;
+    let tally = RefCell::new(Tally::default());
+    let mut samples = vec![];
+
+    let lanes = (0..stress.connections).map(|lane| {
+        let config = config.clone();
+        let connect = connect.clone();
+        let tally = &tally;
+        async move {
+            // so that connections don't all send the same sizes in lockstep
+            let mut next_body = lane as usize;
+            while Instant::now() < deadline {
+                let conn = Conn::new(config.clone(), connect().await?);
+                tally.borrow_mut().connections += 1;
+                if let Err(e) = drive(conn, stress, deadline, tally, &mut next_body).await {
+                    tracing::debug!("stress connection dropped: {e}");
+                    let mut tally = tally.borrow_mut();
+                    tally.dropped += 1;
+                    tally.last_drop = Some(e.to_string());
+                }
+            }
+            Ok::<_, eyre::Report>(())
+        }
+    });
+    let sampler = async {
+        // the last sample gets taken once every connection is done
+        let period = stress.duration / stress.samples.max(1);
+        for i in 1..stress.samples {
+            tokio::time::sleep_until(start + period * i).await;
+            let sample = tally.borrow_mut().sample(start.elapsed(), rss());
+            on_sample(&sample);
+            samples.push(sample);
+        }
+        Ok(())
+    };
+    futures_util::future::try_join(futures_util::future::try_join_all(lanes), sampler).await?;
+
+    let mut tally = tally.into_inner();
+    let sample = tally.sample(start.elapsed(), rss());
+    on_sample(&sample);
+    samples.push(sample);
+
+    Ok(StressReport {
+        samples,
+        connections: tally.connections,
+        dropped: tally.dropped,
+        last_drop: tally.last_drop,
+    })
+}
+
+/// Sends requests on `conn` until `deadline`, or until it's sent
+/// `stress.requests_per_connection` of them, keeping up to `stress.streams`
+/// in flight
+async fn drive<IO: IntoHalves>(
+    mut conn: Conn<IO>,
+    stress: &Stress,
+    deadline: Instant,
+    tally: &RefCell<Tally>,
+    next_body: &mut usize,
+) -> eyre::Result<()> {
+    conn.handshake().await?;
+
+    let max_in_flight = stress
+        .streams
+        .min(conn.settings.max_concurrent_streams.unwrap_or(u32::MAX))
+        .max(1) as usize;
+    // bodies go out whole, so they have to fit in a stream's window, and
+    // only go out once the connection's has room for them
+    let max_body = (conn.settings.initial_window_size as usize).min(CONNECTION_WINDOW as usize);
+    let mut send_window = CONNECTION_WINDOW;
+    let mut in_flight: HashMap<StreamId, Instant> = Default::default();
+    let mut responses = Responses::default();
+    let mut sent = 0;
+    let mut skipped = 0;
+
+    loop {
+        while in_flight.len() < max_in_flight
+            && sent < stress.requests_per_connection
+            && responses.goaway.is_none()
+            && Instant::now() < deadline
+        {
+            let body_len = stress.body_sizes[*next_body % stress.body_sizes.len()].min(max_body);
+            if body_len as i64 > send_window {
+                if !in_flight.is_empty() {
+                    break;
+                }
+                // with nothing in flight, there's nothing to wait on: servers
+                // may hold back WINDOW_UPDATEs until more of the window is
+                // used up, so move on to a smaller body
+                skipped += 1;
+                if skipped == stress.body_sizes.len() {
+                    eyre::bail!(
+                        "none of the body sizes fit in the connection's flow-control window, after {sent} requests"
+                    );
+                }
+                *next_body += 1;
+                continue;
+            }
+            skipped = 0;
+            let req = if body_len == 0 {
+                Request::get()
+            } else {
+                Request::post(crate::dummy_bytes(body_len))
+            };
+            let pending = conn.send_request(req).await?;
+            in_flight.insert(pending.stream_id, Instant::now());
+            send_window -= body_len as i64;
+            *next_body += 1;
+            sent += 1;
+        }
+        if in_flight.is_empty() {
+            // a graceful shutdown is no drop, but any other GOAWAY is
+            if let Some(goaway) = &responses.goaway {
+                if !matches!(
+                    KnownErrorCode::try_from(goaway.error_code),
+                    Ok(KnownErrorCode::NoError)
+                ) {
+                    eyre::bail!("server sent GOAWAY with {:?}", goaway.error_code);
+                }
+            }
+            break;
+        }
+
+        conn.read_responses(&mut responses, |r| {
+            in_flight.keys().any(|&id| r.ended(id)) || r.goaway.is_some() || r.closed
+        })
+        .await?;
+        send_window += std::mem::take(&mut responses.connection_window_increments) as i64;
+
+        // streams past the GOAWAY's last stream ID weren't processed, and
+        // never will be: they're neither a success nor a failure
+        if let Some(goaway) = &responses.goaway {
+            in_flight.retain(|&id, _| id <= goaway.last_stream_id);
+        }
+
+        let ended: Vec<StreamId> = in_flight
+            .keys()
+            .copied()
+            .filter(|&id| responses.ended(id))
+            .collect();
+        if ended.is_empty() && !in_flight.is_empty() {
+            tally.borrow_mut().failed += in_flight.len() as u64;
+            let why = match (&responses.goaway, responses.closed) {
+                (Some(goaway), _) => format!("sent GOAWAY with {:?}", goaway.error_code),
+                (None, true) => "hung up".to_owned(),
+                (None, false) => format!("went quiet for {:?}", conn.config.timeout),
+            };
+            eyre::bail!(
+                "server {why} after {sent} requests, with {} in flight",
+                in_flight.len()
+            );
+        }
+        for stream_id in ended {
+            let sent_at = in_flight.remove(&stream_id).unwrap();
+            let mut tally = tally.borrow_mut();
+            if responses.completed(stream_id) {
+                tally.completed += 1;
+                tally.latencies.push(sent_at.elapsed());
+            } else {
+                tally.failed += 1;
+            }
+            responses.streams.remove(&stream_id);
+        }
+
+        // every stream gets a fresh window, but the connection's only grows
+        // back if we say so
+        let window = conn.granted_window(StreamId::CONNECTION);
+        if window < CONNECTION_WINDOW / 2 {
+            conn.write_window_update(StreamId::CONNECTION, (CONNECTION_WINDOW - window) as u32)
+                .await?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,279 @@
+//! The server under test, as something the harness can start, check on and
+//! stop: with a fresh server for every test, or one that gets restarted
+//! once a test crashes it, one crash doesn't fail every test after it.
+//!
+//! [Subprocess] runs a server binary as a child process:
+//!
+//! ```no_run
+//! # async fn example() -> eyre::Result<()> {
+//! use httpwg::target::{Readiness, Subprocess, Target};
+//!
+//! let addr = "127.0.0.1:8001".parse()?;
+//! let mut server = Subprocess::new("./my_server", Readiness::Tcp(addr))
+//!     .args(["--port", "8001"])
+//!     .env("RUST_LOG", "error");
+//! server.start().await?;
+//! // run a test, then...
+//! if server.check().is_err() {
+//!     server.restart().await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    ffi::OsString,
+    future::Future,
+    net::SocketAddr,
+    path::PathBuf,
+    process::{Child, Command},
+    time::Duration,
+};
+
+use buffet::net::{TcpStream, UnixStream};
+use eyre::eyre;
+use tokio::time::Instant;
+use tracing::debug;
+
+/// A server tests run against, cf. [Subprocess]
+#[allow(async_fn_in_trait)] // we never require Send
+pub trait Target {
+    /// Starts the server, and waits until it's ready for connections
+    async fn start(&mut self) -> eyre::Result<()>;
+
+    /// Stops the server, if it's running
+    async fn stop(&mut self) -> eyre::Result<()>;
+
+    /// Errors out if the server isn't running, e.g. because it crashed,
+    /// saying why if it can
+    fn check(&mut self) -> eyre::Result<()>;
+
+    /// Stops the server if it's running, then starts it again
+    async fn restart(&mut self) -> eyre::Result<()> {
+        self.stop().await?;
+        self.start().await
+    }
+}
+
+/// How to tell a server is ready for connections
+#[derive(Debug, Clone)]
+pub enum Readiness {
+    /// Once it accepts TCP connections on this address
+    Tcp(SocketAddr),
+
+    /// Once it accepts connections on the Unix socket at this path
+    Unix(PathBuf),
+
+    /// After this long, e.g. for servers that don't listen on TCP
+    Delay(Duration),
+}
+
+/// How often a [Readiness::Tcp] or [Readiness::Unix] probe tries to connect
+const PROBE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A server that runs as a child process, with its output going wherever
+/// ours goes. It's killed when dropped, and on Linux, when we die.
+pub struct Subprocess {
+    program: OsString,
+    args: Vec<OsString>,
+    env: Vec<(OsString, OsString)>,
+    readiness: Readiness,
+    startup_timeout: Duration,
+    child: Option<Child>,
+}
+
+impl Subprocess {
+    /// A server started by running `program`, with no arguments, and
+    /// the same environment as ours
+    pub fn new(program: impl Into<OsString>, readiness: Readiness) -> Self {
+        Self {
+            program: program.into(),
+            args: Default::default(),
+            env: Default::default(),
+            readiness,
+            startup_timeout: Duration::from_secs(1),
+            child: None,
+        }
+    }
+
+    pub fn arg(mut self, arg: impl Into<OsString>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn args(mut self, args: impl IntoIterator<Item = impl Into<OsString>>) -> Self {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets an environment variable for the server, on top of ours
+    pub fn env(mut self, key: impl Into<OsString>, value: impl Into<OsString>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// How long the server has to become ready, cf. [Readiness]. Defaults to
+    /// a second.
+    pub fn startup_timeout(mut self, timeout: Duration) -> Self {
+        self.startup_timeout = timeout;
+        self
+    }
+
+    /// The process ID of the server, if it's been started
+    pub fn id(&self) -> Option<u32> {
+        self.child.as_ref().map(Child::id)
+    }
+
+    /// How much memory the server has resident, in bytes: only known on
+    /// Linux, and once it's been started
+    pub fn rss(&self) -> Option<u64> {
+        let status = std::fs::read_to_string(format!("/proc/{}/status", self.id()?)).ok()?;
+        let kib: u64 = status
+            .lines()
+            .find_map(|line| line.strip_prefix("VmRSS:"))?
+            .trim()
+            .strip_suffix("kB")?
+            .trim()
+            .parse()
+            .ok()?;
+        Some(kib * 1024)
+    }
+
+    fn spawn(&self) -> eyre::Result<Child> {
+        let mut cmd = Command::new(&self.program);
+        cmd.args(&self.args);
+        cmd.envs(self.env.iter().map(|(k, v)| (k, v)));
+
+        #[cfg(target_os = "linux")]
+        unsafe {
+            // avoid zombie children: no matter how the test runner dies, the
+            // server will die with it.
+            use std::os::unix::process::CommandExt;
+            cmd.pre_exec(|| {
+                let ret = libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGTERM);
+                if ret != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+
+        cmd.spawn()
+            .map_err(|e| eyre!("could not launch {:?}: {e}", self.program))
+    }
+
+    async fn wait_until_ready(&mut self) -> eyre::Result<()> {
+        let deadline = Instant::now() + self.startup_timeout;
+        match self.readiness.clone() {
+            Readiness::Tcp(addr) => {
+                self.probe(deadline, &addr.to_string(), || TcpStream::connect(addr))
+                    .await
+            }
+            Readiness::Unix(path) => {
+                self.probe(deadline, &path.display().to_string(), || {
+                    UnixStream::connect(&path)
+                })
+                .await
+            }
+            Readiness::Delay(delay) => {
+                tokio::time::sleep(delay).await;
+                self.check()
+                    .map_err(|e| e.wrap_err("server didn't become ready"))
+            }
+        }
+    }
+
+    /// Tries to `connect` until it works, or until `deadline`
+    async fn probe<T, Fut>(
+        &mut self,
+        deadline: Instant,
+        what: &str,
+        connect: impl Fn() -> Fut,
+    ) -> eyre::Result<()>
+    where
+        Fut: Future<Output = std::io::Result<T>>,
+    {
+        loop {
+            self.check()
+                .map_err(|e| e.wrap_err("server didn't become ready"))?;
+            if let Ok(Ok(_)) = tokio::time::timeout(PROBE_INTERVAL, connect()).await {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                eyre::bail!(
+                    "server didn't start listening on {what} within {:?}",
+                    self.startup_timeout
+                );
+            }
+            tokio::time::sleep(PROBE_INTERVAL).await;
+        }
+    }
+
+    /// A killed server's listening socket can outlive it for a bit, and since
+    /// servers often bind with SO_REUSEPORT, the next one would share the
+    /// address with it, and get some of its connections reset.
+    async fn wait_until_gone(&self) -> eyre::Result<()> {
+        let Readiness::Tcp(addr) = self.readiness else {
+            return Ok(());
+        };
+        let deadline = Instant::now() + self.startup_timeout;
+        while let Ok(Ok(_)) = tokio::time::timeout(PROBE_INTERVAL, TcpStream::connect(addr)).await {
+            if Instant::now() >= deadline {
+                eyre::bail!(
+                    "something still listens on {addr} {:?} after the server exited",
+                    self.startup_timeout
+                );
+            }
+            tokio::time::sleep(PROBE_INTERVAL / 10).await;
+        }
+        Ok(())
+    }
+}
+
+impl Target for Subprocess {
+    async fn start(&mut self) -> eyre::Result<()> {
+        if self.check().is_ok() {
+            eyre::bail!("server is already running");
+        }
+
+        let child = self.spawn()?;
+        debug!("launched {:?} (pid {})", self.program, child.id());
+        self.child = Some(child);
+        self.wait_until_ready().await
+    }
+
+    async fn stop(&mut self) -> eyre::Result<()> {
+        let Some(mut child) = self.child.take() else {
+            return Ok(());
+        };
+        if child.try_wait()?.is_none() {
+            child.kill()?;
+        }
+        let status = child.wait()?;
+        debug!(
+            "{:?} (pid {}) exited with {status}",
+            self.program,
+            child.id()
+        );
+        self.wait_until_gone().await
+    }
+
+    fn check(&mut self) -> eyre::Result<()> {
+        let Some(child) = self.child.as_mut() else {
+            eyre::bail!("server isn't running");
+        };
+        match child.try_wait()? {
+            None => Ok(()),
+            Some(status) => Err(eyre!("server exited with {status}")),
+        }
+    }
+}
+
+impl Drop for Subprocess {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            _ = child.kill();
+            _ = child.wait();
+        }
+    }
+}
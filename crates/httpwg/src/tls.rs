@@ -0,0 +1,295 @@
+//! Testing servers over TLS: rustls does the handshake and the record layer
+//! in userspace, on top of any [IntoHalves] transport, and the server has
+//! to pick `h2` with ALPN.
+
+use std::{
+    cell::RefCell,
+    io::{Read, Write},
+    rc::Rc,
+    sync::Arc,
+};
+
+use buffet::{
+    bufpool::{BufResult, IoBufMut},
+    IntoHalves, Piece, ReadOwned, WriteOwned,
+};
+use eyre::eyre;
+use rustls::{
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{aws_lc_rs, CryptoProvider},
+    pki_types::{CertificateDer, ServerName, UnixTime},
+    ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+use tracing::debug;
+
+/// How many TLS bytes to read from the transport at once
+const TLS_READ_BUF_SIZE: usize = 16 * 1024;
+
+/// How to check the certificate the server presents
+pub enum Verify {
+    /// It must chain up to one of these
+    Roots(RootCertStore),
+
+    /// Anything goes, e.g. for self-signed test certificates
+    Insecure,
+}
+
+/// A client configuration that offers `h2` (and only `h2`) with ALPN
+pub fn client_config(verify: Verify) -> eyre::Result<Arc<ClientConfig>> {
+    let provider = Arc::new(aws_lc_rs::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?;
+    let mut config = match verify {
+        Verify::Roots(roots) => builder.with_root_certificates(roots),
+        Verify::Insecure => builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoVerifier { provider })),
+    }
+    .with_no_client_auth();
+    config.alpn_protocols = vec![b"h2".to_vec()];
+    Ok(Arc::new(config))
+}
+
+/// Does the TLS handshake over `io`, and makes sure the server picked `h2`.
+/// The result can be passed to [crate::Conn::new].
+pub async fn connect<IO: IntoHalves>(
+    io: IO,
+    server_name: &str,
+    config: Arc<ClientConfig>,
+) -> eyre::Result<TlsStream<IO>> {
+    let server_name = ServerName::try_from(server_name.to_owned())
+        .map_err(|e| eyre!("invalid server name {server_name:?}: {e}"))?;
+    let (mut r, mut w) = io.into_halves();
+    let conn = Rc::new(RefCell::new(ClientConnection::new(config, server_name)?));
+
+    while conn.borrow().is_handshaking() {
+        flush(&conn, &mut w).await?;
+        if !conn.borrow().wants_read() {
+            continue;
+        }
+        if !read_tls(&conn, &mut r, &mut w).await? {
+            return Err(eyre!("server hung up during the TLS handshake"));
+        }
+    }
+    // our Finished message, for one
+    flush(&conn, &mut w).await?;
+
+    let alpn = conn.borrow().alpn_protocol().map(|p| p.to_vec());
+    debug!(alpn = ?alpn.as_deref().map(String::from_utf8_lossy), "TLS handshake done");
+    if alpn.as_deref() != Some(b"h2") {
+        return Err(eyre!(
+            "server didn't pick h2 with ALPN (picked {:?})",
+            alpn.as_deref().map(String::from_utf8_lossy)
+        ));
+    }
+
+    Ok(TlsStream { r, w, conn })
+}
+
+/// Sends whatever TLS records rustls has queued up
+async fn flush(conn: &RefCell<ClientConnection>, w: &mut impl WriteOwned) -> std::io::Result<()> {
+    loop {
+        let mut out = vec![];
+        {
+            let mut conn = conn.borrow_mut();
+            while conn.wants_write() {
+                conn.write_tls(&mut out)?;
+            }
+        }
+        if out.is_empty() {
+            return Ok(());
+        }
+        w.write_all_owned(out).await?;
+    }
+}
+
+/// Reads TLS records from the transport and has rustls process them,
+/// sending any alert it has to send on error. Returns false on EOF.
+async fn read_tls(
+    conn: &RefCell<ClientConnection>,
+    r: &mut impl ReadOwned,
+    w: &mut impl WriteOwned,
+) -> eyre::Result<bool> {
+    let buf = vec![0u8; TLS_READ_BUF_SIZE];
+    let (res, buf) = r.read_owned(buf).await;
+    let n = res?;
+    if n == 0 {
+        return Ok(false);
+    }
+
+    let res = {
+        let mut conn = conn.borrow_mut();
+        let mut tls = &buf[..n];
+        let mut res = Ok(());
+        while !tls.is_empty() {
+            conn.read_tls(&mut tls)?;
+            if let Err(e) = conn.process_new_packets() {
+                res = Err(e);
+                break;
+            }
+        }
+        res
+    };
+    if let Err(e) = res {
+        _ = flush(conn, w).await;
+        return Err(e.into());
+    }
+    Ok(true)
+}
+
+/// A TLS connection on top of `IO`, once the handshake is done
+pub struct TlsStream<IO: IntoHalves> {
+    r: IO::Read,
+    w: IO::Write,
+    conn: Rc<RefCell<ClientConnection>>,
+}
+
+impl<IO: IntoHalves> IntoHalves for TlsStream<IO> {
+    type Read = TlsRead<IO>;
+    type Write = TlsWrite<IO>;
+
+    fn into_halves(self) -> (Self::Read, Self::Write) {
+        (
+            TlsRead {
+                r: self.r,
+                conn: self.conn.clone(),
+            },
+            TlsWrite {
+                w: self.w,
+                conn: self.conn,
+            },
+        )
+    }
+}
+
+/// Decrypts what the server sends
+pub struct TlsRead<IO: IntoHalves> {
+    r: IO::Read,
+    conn: Rc<RefCell<ClientConnection>>,
+}
+
+impl<IO: IntoHalves> ReadOwned for TlsRead<IO> {
+    async fn read_owned<B: IoBufMut>(&mut self, mut buf: B) -> BufResult<usize, B> {
+        loop {
+            let res = {
+                let dst = unsafe { buf.slice_mut() };
+                self.conn.borrow_mut().reader().read(dst)
+            };
+            match res {
+                Ok(n) => return (Ok(n), buf),
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
+                // the server hung up without a close_notify
+                Err(e) => return (Err(e), buf),
+            }
+
+            // rustls wants more records, and alerts it sends on error go
+            // nowhere: the write half is the one that writes.
+            let mut sink = NoWrite;
+            match read_tls(&self.conn, &mut self.r, &mut sink).await {
+                Ok(true) => {}
+                Ok(false) => return (Ok(0), buf),
+                Err(e) => {
+                    return (
+                        Err(std::io::Error::new(std::io::ErrorKind::InvalidData, e)),
+                        buf,
+                    )
+                }
+            }
+        }
+    }
+}
+
+/// Encrypts what we send to the server
+pub struct TlsWrite<IO: IntoHalves> {
+    w: IO::Write,
+    conn: Rc<RefCell<ClientConnection>>,
+}
+
+impl<IO: IntoHalves> WriteOwned for TlsWrite<IO> {
+    async fn write_owned(&mut self, buf: impl Into<Piece>) -> BufResult<usize, Piece> {
+        let buf = buf.into();
+        let res = self.conn.borrow_mut().writer().write(&buf[..]);
+        let n = match res {
+            Ok(n) => n,
+            Err(e) => return (Err(e), buf),
+        };
+        if let Err(e) = flush(&self.conn, &mut self.w).await {
+            return (Err(e), buf);
+        }
+        (Ok(n), buf)
+    }
+
+    async fn shutdown(&mut self) -> std::io::Result<()> {
+        self.conn.borrow_mut().send_close_notify();
+        flush(&self.conn, &mut self.w).await?;
+        self.w.shutdown().await
+    }
+}
+
+/// Where [TlsRead] sends alerts: nowhere
+struct NoWrite;
+
+impl WriteOwned for NoWrite {
+    async fn write_owned(&mut self, buf: impl Into<Piece>) -> BufResult<usize, Piece> {
+        let buf = buf.into();
+        (Ok(buf.len()), buf)
+    }
+
+    async fn shutdown(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Accepts any certificate, cf. [Verify::Insecure]. Signatures are still
+/// checked, so the handshake itself is sound.
+#[derive(Debug)]
+struct NoVerifier {
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for NoVerifier {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
@@ -0,0 +1,32 @@
+//! Serves [axum] routers with loona, over HTTP/1.1 and HTTP/2, so that
+//! applications can switch to loona's server without leaving their
+//! framework.
+//!
+//! A [Router] is a tower service, so [AxumDriver] is a
+//! [loona_tower::TowerDriver] underneath: handlers get a
+//! [loona_tower::RequestBody] (as an [axum::body::Body]), and everything
+//! else works as it does with hyper.
+//!
+//! The exception is upgrades: hyper's `OnUpgrade` can't be built outside
+//! of hyper, so axum's `WebSocketUpgrade` extractor won't work here. What
+//! loona upgrades (HTTP/1.1 CONNECT requests, and HTTP/2 extended CONNECT
+//! requests, cf. [loona::h2::ServerConf::enable_connect_protocol]) is
+//! available through the [Tunnel] extractor instead.
+
+use axum::Router;
+use loona_tower::TowerDriver;
+
+mod tunnel;
+pub use tunnel::*;
+
+/// A [loona::ServerDriver] that serves an axum [Router]
+pub type AxumDriver = TowerDriver<Router>;
+
+/// Returns a driver for `router`, to pass to e.g. [loona::h1::serve],
+/// [loona::h2::serve] or [loona::server::Server::new].
+///
+/// Routers with state need to be given it first, with
+/// [Router::with_state].
+pub fn driver(router: Router) -> AxumDriver {
+    TowerDriver::new(router)
+}
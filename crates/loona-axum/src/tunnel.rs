@@ -0,0 +1,160 @@
+//! An extractor for requests that loona turns into byte streams: CONNECT
+//! requests, cf. [loona::tunnel].
+
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use axum::{
+    body::Body,
+    extract::{FromRequest, Request},
+    response::{IntoResponse, Response},
+};
+use http::{uri::Authority, Method, StatusCode};
+use http_body_util::BodyExt;
+use loona::ConnectProtocol;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, DuplexStream, ReadBuf};
+use tokio_util::io::ReaderStream;
+use tracing::debug;
+
+/// How much of the stream is buffered in each direction, between the
+/// connection and the [TunnelStream]
+const TUNNEL_BUF_SIZE: usize = 64 * 1024;
+
+/// Extracts a CONNECT request, whose request and response bodies become the
+/// two directions of a byte stream once it's accepted with
+/// [Tunnel::on_open].
+///
+/// That's either a plain CONNECT request (HTTP/1.1 or HTTP/2), whose target
+/// is in [Tunnel::authority], or an HTTP/2 extended CONNECT request, with a
+/// [Tunnel::protocol], e.g. `websocket`, cf.
+/// <https://www.rfc-editor.org/rfc/rfc8441>.
+///
+/// Note that an HTTP/1.1 CONNECT request target has no path, so those only
+/// ever reach a router's fallback.
+///
+/// Since it consumes the request body, this must be the last extractor.
+#[derive(Debug)]
+pub struct Tunnel {
+    authority: Option<Authority>,
+    protocol: Option<String>,
+    body: Body,
+}
+
+/// Rejection for [Tunnel], when the request isn't a CONNECT request
+#[derive(Debug, thiserror::Error)]
+#[error("expected a CONNECT request")]
+pub struct TunnelRejection;
+
+impl IntoResponse for TunnelRejection {
+    fn into_response(self) -> Response {
+        (StatusCode::METHOD_NOT_ALLOWED, self.to_string()).into_response()
+    }
+}
+
+impl<S> FromRequest<S> for Tunnel
+where
+    S: Send + Sync,
+{
+    type Rejection = TunnelRejection;
+
+    async fn from_request(req: Request, _state: &S) -> Result<Self, Self::Rejection> {
+        if req.method() != Method::CONNECT {
+            return Err(TunnelRejection);
+        }
+
+        let (mut parts, body) = req.into_parts();
+        Ok(Self {
+            authority: parts.uri.authority().cloned(),
+            protocol: parts.extensions.remove::<ConnectProtocol>().map(|p| p.0),
+            body,
+        })
+    }
+}
+
+impl Tunnel {
+    /// The target of the CONNECT request, e.g. `example.org:443`
+    pub fn authority(&self) -> Option<&Authority> {
+        self.authority.as_ref()
+    }
+
+    /// HTTP/2 only: the protocol of an extended CONNECT request, e.g.
+    /// `websocket`
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+
+    /// Accepts the tunnel: returns a 200 response, whose body is what gets
+    /// written to the [TunnelStream] handed to `callback`, which runs in a
+    /// task of its own. Reads from the [TunnelStream] return what the client
+    /// sends.
+    ///
+    /// The tunnel is torn down when the connection goes away, or when both
+    /// the client and `callback` are done writing.
+    pub fn on_open<F, Fut>(self, callback: F) -> Response
+    where
+        F: FnOnce(TunnelStream) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (ours, theirs) = tokio::io::duplex(TUNNEL_BUF_SIZE);
+        let (their_r, their_w) = tokio::io::split(theirs);
+
+        tokio::spawn(copy_from_client(self.body, their_w));
+        tokio::spawn(callback(TunnelStream(ours)));
+        Response::new(Body::from_stream(ReaderStream::new(their_r)))
+    }
+}
+
+async fn copy_from_client(mut body: Body, mut w: impl AsyncWrite + Unpin) {
+    while let Some(frame) = body.frame().await {
+        let frame = match frame {
+            Ok(frame) => frame,
+            Err(e) => {
+                debug!(%e, "error reading from tunnel client");
+                break;
+            }
+        };
+        if let Ok(data) = frame.into_data() {
+            if w.write_all(&data).await.is_err() {
+                debug!("tunnel stream dropped, not reading from client anymore");
+                return;
+            }
+        }
+    }
+    _ = w.shutdown().await;
+}
+
+/// The server's end of a [Tunnel]
+#[derive(Debug)]
+pub struct TunnelStream(DuplexStream);
+
+impl AsyncRead for TunnelStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for TunnelStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.0).poll_shutdown(cx)
+    }
+}
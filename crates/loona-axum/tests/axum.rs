@@ -0,0 +1,156 @@
+use std::rc::Rc;
+
+use axum::{
+    extract::Path,
+    response::Response,
+    routing::{any, get},
+    Router,
+};
+use b_x::BX;
+use loona::{
+    buffet::{IntoHalves, ReadOwned, RollMut, WriteOwned},
+    h1, h2,
+};
+use loona_axum::{Tunnel, TunnelStream};
+use tokio::io::AsyncWriteExt;
+
+async fn hello(Path(name): Path<String>) -> String {
+    format!("hello, {name}")
+}
+
+async fn echo(stream: TunnelStream) {
+    let (mut r, mut w) = tokio::io::split(stream);
+    tokio::io::copy(&mut r, &mut w).await.unwrap();
+    AsyncWriteExt::shutdown(&mut w).await.unwrap();
+}
+
+async fn connect(tunnel: Tunnel) -> Response {
+    assert_eq!(tunnel.authority().unwrap().as_str(), "example.org:443");
+    assert_eq!(tunnel.protocol(), None);
+    tunnel.on_open(echo)
+}
+
+async fn chat(tunnel: Tunnel) -> Response {
+    assert_eq!(tunnel.protocol(), Some("websocket"));
+    tunnel.on_open(echo)
+}
+
+fn router() -> Router {
+    Router::new()
+        .route("/hello/{name}", get(hello))
+        .route("/chat", any(chat))
+        .fallback(connect)
+}
+
+#[test]
+fn router_over_h1() {
+    loona::buffet::start(async move {
+        let (server_write, mut client_read) = loona::buffet::pipe();
+        let (mut client_write, server_read) = loona::buffet::pipe();
+        loona::buffet::spawn(async move {
+            h1::serve(
+                (server_read, server_write),
+                Rc::new(Default::default()),
+                RollMut::alloc()?,
+                loona_axum::driver(router()),
+            )
+            .await?;
+            Ok::<_, BX>(())
+        });
+
+        // a regular request, then a CONNECT request whose tunnel echoes
+        // everything back, until we hang up.
+        client_write
+            .write_all_owned(
+                "GET /hello/loona HTTP/1.1\r\n\r\n\
+                 CONNECT example.org:443 HTTP/1.1\r\nhost: example.org:443\r\n\r\nping",
+            )
+            .await
+            .unwrap();
+        drop(client_write);
+
+        let mut out = vec![];
+        let mut buf = vec![0u8; 1024];
+        loop {
+            let res;
+            (res, buf) = client_read.read_owned(buf).await;
+            let n = res.unwrap();
+            if n == 0 {
+                break;
+            }
+            out.extend_from_slice(&buf[..n]);
+        }
+        let res = String::from_utf8(out).unwrap();
+
+        let (first, second) = res.split_at(res.rfind("HTTP/1.1 200 ").expect(&res));
+        assert!(first.starts_with("HTTP/1.1 200 "), "{res}");
+        assert!(first.ends_with("\r\n\r\nhello, loona"), "{res}");
+        assert!(second.ends_with("\r\n\r\nping"), "{res}");
+    })
+}
+
+/// Lets an [httpwg::Conn] talk to a server over a pair of pipes
+struct TwoHalves<W, R>(W, R);
+
+impl<W: WriteOwned + 'static, R: ReadOwned + 'static> IntoHalves for TwoHalves<W, R> {
+    type Read = R;
+    type Write = W;
+
+    fn into_halves(self) -> (Self::Read, Self::Write) {
+        (self.1, self.0)
+    }
+}
+
+#[test]
+fn tunnel_over_h2() {
+    loona::buffet::start(async move {
+        let (server_write, client_read) = loona::buffet::pipe();
+        let (client_write, server_read) = loona::buffet::pipe();
+        loona::buffet::spawn(async move {
+            let conf = Rc::new(h2::ServerConf {
+                enable_connect_protocol: true,
+                ..Default::default()
+            });
+            h2::serve(
+                (server_read, server_write),
+                conf,
+                RollMut::alloc()?,
+                Rc::new(loona_axum::driver(router())),
+            )
+            .await?;
+            Ok::<_, BX>(())
+        });
+
+        let mut conn = httpwg::Conn::new(
+            Rc::new(httpwg::Config::default()),
+            TwoHalves(client_write, client_read),
+        );
+        conn.handshake().await.unwrap();
+
+        let mut headers = httpwg::Headers::default();
+        headers.append(":method", "CONNECT");
+        headers.append(":protocol", "websocket");
+        headers.append(":scheme", "http");
+        headers.append(":path", "/chat");
+        headers.append(":authority", "example.org");
+        let stream_id = loona_h2::StreamId(1);
+        conn.encode_and_write_headers(stream_id, loona_h2::HeadersFlags::EndHeaders, &headers)
+            .await
+            .unwrap();
+
+        let (_, payload) = conn.wait_for_frame(httpwg::FrameT::Headers).await.unwrap();
+        let res_headers = conn.decode_headers(payload.into()).unwrap();
+        assert_eq!(
+            res_headers.get_first(&":status".into()).map(|v| &v[..]),
+            Some(&b"200"[..])
+        );
+
+        conn.write_data(stream_id, false, "hello").await.unwrap();
+        let (_, payload) = conn.wait_for_frame(httpwg::FrameT::Data).await.unwrap();
+        assert_eq!(&payload[..], b"hello");
+
+        conn.write_data(stream_id, true, "").await.unwrap();
+        let (frame, _) = conn.wait_for_frame(httpwg::FrameT::Data).await.unwrap();
+        assert!(frame.is_end_stream());
+    })
+}
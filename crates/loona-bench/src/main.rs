@@ -0,0 +1,347 @@
+use std::{
+    cell::{Cell, RefCell},
+    ffi::OsString,
+    rc::Rc,
+    time::Duration,
+};
+
+use b_x::{BxForResults, BX};
+use loona::{
+    buffet::PieceStr,
+    client::{Client, ClientConf, TcpConnector},
+    http::{StatusCode, Uri},
+    Body, BodyChunk, ClientDriver, Method, Request, Response,
+};
+use tokio::time::Instant;
+use tracing::Level;
+use tracing_subscriber::{filter::Targets, layer::SubscriberExt, util::SubscriberInitExt};
+
+#[derive(Debug)]
+struct Args {
+    /// the URIs to request, each with a weight
+    targets: Vec<(usize, Uri)>,
+
+    /// the method of every request
+    method: Method,
+
+    /// how many clients to run, each with its own connection(s)
+    connections: usize,
+
+    /// how many requests each client keeps in flight
+    streams: usize,
+
+    /// how long to measure for
+    duration: Duration,
+
+    /// how long to send requests for before measuring
+    warmup: Duration,
+
+    /// speak HTTP/2 with prior knowledge
+    h2: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            targets: Default::default(),
+            method: Method::Get,
+            connections: 1,
+            streams: 1,
+            duration: Duration::from_secs(10),
+            warmup: Duration::ZERO,
+            h2: false,
+        }
+    }
+}
+
+trait IntoStringResult {
+    fn into_string_result(self) -> eyre::Result<String>;
+}
+
+impl IntoStringResult for OsString {
+    fn into_string_result(self) -> eyre::Result<String> {
+        self.into_string()
+            .map_err(|_| eyre::eyre!("OsString contained invalid UTF-8"))
+    }
+}
+
+fn parse_args() -> eyre::Result<Args> {
+    fn parse<T>(parser: &mut lexopt::Parser, what: &str) -> eyre::Result<T>
+    where
+        T: std::str::FromStr,
+        T::Err: std::fmt::Display,
+    {
+        parser
+            .value()?
+            .into_string_result()?
+            .parse()
+            .map_err(|e| eyre::eyre!("Failed to parse {what}: {e}"))
+    }
+
+    let mut args: Args = Default::default();
+    let mut parser = lexopt::Parser::from_env();
+    while let Some(arg) = parser.next()? {
+        match arg {
+            lexopt::Arg::Long("connections") | lexopt::Arg::Short('c') => {
+                args.connections = parse(&mut parser, "connections")?;
+            }
+            lexopt::Arg::Long("streams") | lexopt::Arg::Short('m') => {
+                args.streams = parse(&mut parser, "streams")?;
+            }
+            lexopt::Arg::Long("duration") | lexopt::Arg::Short('D') => {
+                args.duration = Duration::from_secs_f64(parse(&mut parser, "duration")?);
+            }
+            lexopt::Arg::Long("warmup") => {
+                args.warmup = Duration::from_secs_f64(parse(&mut parser, "warmup")?);
+            }
+            lexopt::Arg::Long("method") | lexopt::Arg::Short('X') => {
+                let method = parser.value()?.into_string_result()?;
+                args.method = PieceStr::from(method.to_ascii_uppercase()).into();
+            }
+            lexopt::Arg::Long("h2") => {
+                args.h2 = true;
+            }
+            lexopt::Arg::Value(value) => {
+                let value = value.into_string_result()?;
+                // URIs start with a scheme, so a leading number is a weight
+                let (weight, uri) = match value.split_once(':') {
+                    Some((weight, uri)) if weight.bytes().all(|b| b.is_ascii_digit()) => {
+                        (weight.parse()?, uri)
+                    }
+                    _ => (1, &value[..]),
+                };
+                let uri: Uri = uri
+                    .parse()
+                    .map_err(|e| eyre::eyre!("Invalid URI {uri}: {e}"))?;
+                args.targets.push((weight, uri));
+            }
+            _ => return Err(arg.unexpected().into()),
+        }
+    }
+
+    if args.targets.is_empty() {
+        eyre::bail!("No URI given");
+    }
+    if args.connections == 0 || args.streams == 0 {
+        eyre::bail!("Need at least one connection and one stream");
+    }
+    if args.targets.iter().all(|(weight, _)| *weight == 0) {
+        eyre::bail!("Every URI has a weight of zero");
+    }
+    Ok(args)
+}
+
+fn print_usage() {
+    eprintln!(
+        "Usage: loona-bench [OPTIONS] [WEIGHT:]URI...
+
+Options:
+    -c, --connections <N>      How many clients to run, each with its own connection (default: 1)
+    -m, --streams <N>          How many requests each client keeps in flight (default: 1)
+    -D, --duration <SECS>      How long to measure for (default: 10)
+    --warmup <SECS>            How long to send requests for before measuring (default: 0)
+    -X, --method <METHOD>      The method of every request (default: GET)
+    --h2                       Speak HTTP/2 with prior knowledge, instead of HTTP/1.1
+
+Arguments:
+    URI                        What to request. With several, requests go to each in
+                               turn, WEIGHT times as often as the others (default: 1)
+
+Over HTTP/1.1, every request in flight needs its own connection, so each client
+opens up to --streams connections.
+
+Examples:
+    loona-bench -c 8 -m 16 -D 10 --h2 http://127.0.0.1:8000/
+    loona-bench -c 4 --warmup 2 3:http://127.0.0.1:8000/small http://127.0.0.1:8000/large
+"
+    );
+}
+
+fn main() -> eyre::Result<()> {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("Failed to parse arguments: {e}");
+            print_usage();
+            std::process::exit(1);
+        }
+    };
+    setup_tracing();
+    buffet::start(async move { async_main(args).await })
+}
+
+/// What happened while measuring
+#[derive(Default)]
+struct Stats {
+    latencies: Vec<Duration>,
+    statuses: [u64; 10],
+    failed: u64,
+    body_bytes: u64,
+}
+
+impl Stats {
+    fn record(&mut self, latency: Duration, res: Result<(StatusCode, u64), BX>) {
+        match res {
+            Ok((status, body_bytes)) => {
+                self.latencies.push(latency);
+                self.statuses[(status.as_u16() / 100) as usize] += 1;
+                self.body_bytes += body_bytes;
+            }
+            Err(e) => {
+                tracing::debug!("request failed: {e}");
+                self.failed += 1;
+            }
+        }
+    }
+}
+
+async fn async_main(args: Args) -> eyre::Result<()> {
+    let schedule: Rc<[(Method, Uri)]> = args
+        .targets
+        .iter()
+        .flat_map(|(weight, uri)| {
+            std::iter::repeat((args.method.clone(), uri.clone())).take(*weight)
+        })
+        .collect();
+    let sent = Rc::new(Cell::new(0usize));
+    let stats: Rc<RefCell<Stats>> = Default::default();
+
+    let conf = ClientConf {
+        h2_prior_knowledge: args.h2,
+        max_requests_per_origin: None,
+        ..Default::default()
+    };
+    let started = Instant::now();
+    let measure_from = started + args.warmup;
+    let deadline = measure_from + args.duration;
+
+    eprintln!(
+        "Running {} clients with {} streams each, for {:?} (after {:?} of warmup)",
+        args.connections, args.streams, args.duration, args.warmup
+    );
+
+    let mut workers = vec![];
+    for _ in 0..args.connections {
+        let mut conf = conf.clone();
+        conf.pool.max_streams_per_conn = args.streams as u32;
+        conf.pool.max_idle_per_authority = args.streams;
+        let client = Rc::new(Client::new(TcpConnector::new(), conf));
+
+        for _ in 0..args.streams {
+            let client = client.clone();
+            let schedule = schedule.clone();
+            let sent = sent.clone();
+            let stats = stats.clone();
+            workers.push(buffet::spawn(async move {
+                while Instant::now() < deadline {
+                    let n = sent.get();
+                    sent.set(n + 1);
+                    let (method, uri) = schedule[n % schedule.len()].clone();
+                    let req = Request {
+                        method,
+                        uri,
+                        ..Default::default()
+                    };
+
+                    let start = Instant::now();
+                    let res = client.request(req, &mut (), Drain).await.map_err(BX::from);
+                    let end = Instant::now();
+                    if start >= measure_from && end <= deadline {
+                        stats.borrow_mut().record(end - start, res);
+                    }
+                }
+            }));
+        }
+    }
+    for worker in workers {
+        worker.await?;
+    }
+
+    let mut stats = stats.take();
+    print_report(&mut stats, args.duration);
+    Ok(())
+}
+
+fn print_report(stats: &mut Stats, duration: Duration) {
+    let succeeded = stats.latencies.len() as u64;
+    let secs = duration.as_secs_f64();
+    println!();
+    println!(
+        "requests: {} total, {succeeded} succeeded, {} failed",
+        succeeded + stats.failed,
+        stats.failed
+    );
+    println!(
+        "status codes: {} 2xx, {} 3xx, {} 4xx, {} 5xx",
+        stats.statuses[2], stats.statuses[3], stats.statuses[4], stats.statuses[5]
+    );
+    println!(
+        "throughput: {:.2} req/s, {:.2} MiB/s of response bodies",
+        succeeded as f64 / secs,
+        stats.body_bytes as f64 / secs / (1024.0 * 1024.0)
+    );
+
+    if stats.latencies.is_empty() {
+        return;
+    }
+    stats.latencies.sort_unstable();
+    let latencies = &stats.latencies;
+    let mean = latencies.iter().sum::<Duration>() / latencies.len() as u32;
+    println!(
+        "latency: min {:?}, mean {mean:?}, max {:?}",
+        latencies[0],
+        latencies[latencies.len() - 1]
+    );
+    for p in [50.0, 75.0, 90.0, 99.0, 99.9] {
+        println!("  p{p:<5} {:?}", percentile(latencies, p));
+    }
+}
+
+/// The `p`th percentile of sorted, non-empty `samples`, nearest-rank style
+fn percentile(samples: &[Duration], p: f64) -> Duration {
+    let rank = (p / 100.0 * samples.len() as f64).ceil() as usize;
+    samples[rank.clamp(1, samples.len()) - 1]
+}
+
+/// Reads (and discards) response bodies, counting their bytes
+struct Drain;
+
+impl ClientDriver for Drain {
+    type Return = (StatusCode, u64);
+    type Error = BX;
+
+    async fn on_informational_response(&mut self, _res: Response) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    async fn on_final_response(
+        self,
+        res: Response,
+        body: &mut impl Body,
+    ) -> Result<Self::Return, Self::Error> {
+        let mut bytes = 0;
+        while let BodyChunk::Chunk(chunk) = body.next_chunk().await.bx()? {
+            bytes += chunk.len() as u64;
+        }
+        Ok((res.status, bytes))
+    }
+}
+
+fn setup_tracing() {
+    let targets = if let Ok(rust_log) = std::env::var("RUST_LOG") {
+        rust_log.parse::<Targets>().unwrap()
+    } else {
+        Targets::new().with_default(Level::WARN)
+    };
+
+    let fmt_layer = tracing_subscriber::fmt::layer()
+        .with_ansi(true)
+        .with_file(false)
+        .with_line_number(false)
+        .without_time();
+
+    tracing_subscriber::registry()
+        .with(targets)
+        .with(fmt_layer)
+        .init();
+}
@@ -0,0 +1,14 @@
+//! Arbitrary bytes as the body of a chunked request, to the chunked
+//! transfer coding decoder, cf. <https://httpwg.org/specs/rfc9112.html#chunked.encoding>
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+const HEAD: &[u8] = b"POST / HTTP/1.1\r\nhost: example.org\r\ntransfer-encoding: chunked\r\n\r\n";
+
+fuzz_target!(|data: &[u8]| {
+    let mut input = HEAD.to_vec();
+    input.extend_from_slice(data);
+    loona_fuzz::serve_h1(&input);
+});
@@ -0,0 +1,10 @@
+//! Arbitrary bytes from a client, to the HTTP/1.1 request parser (and
+//! whatever body reader the request asks for)
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    loona_fuzz::serve_h1(data);
+});
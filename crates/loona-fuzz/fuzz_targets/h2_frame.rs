@@ -0,0 +1,55 @@
+//! Arbitrary bytes as a sequence of HTTP/2 frames, to the frame header
+//! parser and the parsers for frame payloads
+
+#![no_main]
+
+use buffet::{Roll, RollMut};
+use libfuzzer_sys::fuzz_target;
+use loona_h2::{
+    Frame, FrameType, GoAway, PrioritySpec, PriorityUpdate, RstStream, Settings, WindowUpdate,
+    PRIORITY_UPDATE_FRAME_TYPE,
+};
+
+fuzz_target!(|data: &[u8]| {
+    buffet::bufpool::initialize_allocator_with_num_bufs(64).unwrap();
+
+    // frames are parsed out of the same kind of buffers the server reads into
+    let mut buf = RollMut::alloc().unwrap();
+    let data = &data[..data.len().min(buf.cap())];
+    buf.put(data).unwrap();
+    let mut rest = buf.filled();
+
+    loop {
+        let Ok((after_header, frame)) = Frame::parse(rest) else {
+            return;
+        };
+        if after_header.len() < frame.len as usize {
+            return;
+        }
+        let (payload, after_payload) = after_header.split_at(frame.len as usize);
+        parse_payload(&frame, payload);
+        rest = after_payload;
+    }
+});
+
+fn parse_payload(frame: &Frame, payload: Roll) {
+    match frame.frame_type {
+        FrameType::Headers(flags) if flags.contains(loona_h2::HeadersFlags::Priority) => {
+            _ = PrioritySpec::parse(payload);
+        }
+        FrameType::Priority => _ = PrioritySpec::parse(payload),
+        FrameType::RstStream => _ = RstStream::parse(payload),
+        FrameType::Settings(_) => {
+            if payload.len() % 6 == 0 {
+                let mut settings = Settings::default();
+                _ = Settings::parse(&payload[..], |code, value| settings.apply(code, value));
+            }
+        }
+        FrameType::GoAway => _ = GoAway::parse(payload),
+        FrameType::WindowUpdate => _ = WindowUpdate::parse(payload),
+        FrameType::Unknown(ft) if ft.ty == PRIORITY_UPDATE_FRAME_TYPE => {
+            _ = PriorityUpdate::parse(payload);
+        }
+        _ => {}
+    }
+}
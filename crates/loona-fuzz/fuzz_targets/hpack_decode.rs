@@ -0,0 +1,20 @@
+//! Arbitrary bytes as a sequence of header blocks, to the HPACK decoder. The
+//! first byte of each block says how long it is, so that blocks can share a
+//! dynamic table.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut decoder = loona_hpack::Decoder::new();
+    let mut rest = data;
+    while let Some((&len, tail)) = rest.split_first() {
+        let (block, tail) = tail.split_at((len as usize).min(tail.len()));
+        if decoder.decode(block).is_err() {
+            // the connection would be closed
+            return;
+        }
+        rest = tail;
+    }
+});
@@ -0,0 +1,228 @@
+//! Writes seed corpora for the fuzz targets to `seeds/<target>/`.
+//!
+//! HTTP/2 seeds are transcripts of the httpwg conformance suite: every byte
+//! each test sends, recorded while running it against loona. The frames in
+//! them seed `h2_frame`, and their header blocks seed `hpack_decode`.
+//! HTTP/1.1 seeds are written by hand.
+
+use std::{cell::RefCell, collections::HashMap, path::Path, rc::Rc, time::Duration};
+
+use buffet::{IntoHalves, Piece, PipeRead, PipeWrite, RollMut, WriteOwned};
+use httpwg::{Config, Conn};
+use loona_fuzz::DrainDriver;
+
+const H1_REQUESTS: &[&[u8]] = &[
+    b"GET / HTTP/1.1\r\nhost: example.org\r\n\r\n",
+    b"GET /a?b=c HTTP/1.0\r\n\r\n",
+    b"POST /upload HTTP/1.1\r\nhost: example.org\r\ncontent-length: 5\r\n\r\nhello",
+    b"POST / HTTP/1.1\r\ntransfer-encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n",
+    b"GET http://example.org/abs HTTP/1.1\r\nhost: example.org\r\n\r\n",
+    b"CONNECT example.org:443 HTTP/1.1\r\nhost: example.org:443\r\n\r\n",
+    b"OPTIONS * HTTP/1.1\r\nhost: example.org\r\n\r\n",
+    b"GET / HTTP/1.1\r\nhost: a\r\nexpect: 100-continue\r\nconnection: close\r\n\r\n",
+    b"GET /1 HTTP/1.1\r\nhost: a\r\n\r\nGET /2 HTTP/1.1\r\nhost: a\r\n\r\n",
+];
+
+const CHUNKED_BODIES: &[&[u8]] = &[
+    b"0\r\n\r\n",
+    b"5\r\nhello\r\n0\r\n\r\n",
+    b"5;ext=value\r\nhello\r\n6\r\n world\r\n0\r\n\r\n",
+    b"A\r\n0123456789\r\n0\r\nx-trailer: yes\r\n\r\n",
+    b"fffffffffffffffff\r\n",
+];
+
+fn main() -> eyre::Result<()> {
+    let seeds = Path::new(env!("CARGO_MANIFEST_DIR")).join("seeds");
+
+    write_seeds(&seeds.join("h1_request"), H1_REQUESTS.iter().copied())?;
+    write_seeds(&seeds.join("h1_chunked"), CHUNKED_BODIES.iter().copied())?;
+
+    let transcripts = buffet::start(record_httpwg_transcripts())?;
+    let mut frame_seeds = vec![];
+    let mut hpack_seeds = vec![];
+    for transcript in transcripts.values() {
+        let frames = transcript
+            .strip_prefix(loona_h2::PREFACE)
+            .unwrap_or(transcript);
+        frame_seeds.push(frames.to_vec());
+        let blocks = header_blocks(frames);
+        if !blocks.is_empty() {
+            // as hpack_decode expects: each block prefixed by its length
+            let mut seed = vec![];
+            for block in blocks.iter().filter(|block| block.len() <= 255) {
+                seed.push(block.len() as u8);
+                seed.extend_from_slice(block);
+            }
+            hpack_seeds.push(seed);
+        }
+    }
+    frame_seeds.sort();
+    frame_seeds.dedup();
+    hpack_seeds.sort();
+    hpack_seeds.dedup();
+    write_seeds(&seeds.join("h2_frame"), frame_seeds.iter().map(|s| &s[..]))?;
+    write_seeds(
+        &seeds.join("hpack_decode"),
+        hpack_seeds.iter().map(|s| &s[..]),
+    )?;
+
+    Ok(())
+}
+
+fn write_seeds<'a>(dir: &Path, seeds: impl Iterator<Item = &'a [u8]>) -> eyre::Result<()> {
+    if dir.exists() {
+        std::fs::remove_dir_all(dir)?;
+    }
+    std::fs::create_dir_all(dir)?;
+    let mut count = 0;
+    for (i, seed) in seeds.enumerate() {
+        std::fs::write(dir.join(format!("seed-{i:03}")), seed)?;
+        count += 1;
+    }
+    eprintln!("Wrote {count} seeds to {}", dir.display());
+    Ok(())
+}
+
+/// Runs every httpwg test against a loona server, returning what each test
+/// sent, by test name
+async fn record_httpwg_transcripts() -> eyre::Result<HashMap<String, Vec<u8>>> {
+    buffet::bufpool::initialize_allocator()?;
+
+    let config = Rc::new(Config {
+        timeout: Duration::from_millis(100),
+        ..Default::default()
+    });
+    let mut transcripts = HashMap::new();
+    for (rfc, sections) in catalog::<RecordingIo>() {
+        for (section, tests) in sections {
+            for (test, Test { run: boxed_test, .. }) in tests {
+                let (server_write, client_read) = buffet::pipe();
+                let (client_write, server_read) = buffet::pipe();
+                let server = buffet::spawn(async move {
+                    let conf = Rc::new(loona::h2::ServerConf::conformance_test());
+                    let client_buf = RollMut::alloc().unwrap();
+                    _ = loona::h2::serve(
+                        (server_read, server_write),
+                        conf,
+                        client_buf,
+                        Rc::new(DrainDriver),
+                    )
+                    .await;
+                });
+
+                let recorded: Rc<RefCell<Vec<u8>>> = Default::default();
+                let io = RecordingIo {
+                    read: client_read,
+                    write: RecordingWrite {
+                        inner: client_write,
+                        recorded: recorded.clone(),
+                    },
+                };
+                let conn = Conn::new(config.clone(), io);
+                // we're after what the test sends, not whether loona passes
+                // it: failures (and panics) are fine
+                let run = buffet::spawn(boxed_test(conn));
+                let abort = run.abort_handle();
+                _ = tokio::time::timeout(Duration::from_secs(2), run).await;
+                abort.abort();
+                server.abort();
+
+                transcripts.insert(format!("{rfc} :: {section} :: {test}"), recorded.take());
+            }
+        }
+    }
+    eprintln!("Recorded {} httpwg transcripts", transcripts.len());
+    Ok(transcripts)
+}
+
+/// Extracts the header blocks of HEADERS frames (and their CONTINUATION
+/// frames) from a sequence of frames
+fn header_blocks(mut frames: &[u8]) -> Vec<Vec<u8>> {
+    const PADDED: u8 = 0x8;
+    const PRIORITY: u8 = 0x20;
+    const END_HEADERS: u8 = 0x4;
+
+    let mut blocks = vec![];
+    let mut current: Option<Vec<u8>> = None;
+    while frames.len() >= 9 {
+        let len = u32::from_be_bytes([0, frames[0], frames[1], frames[2]]) as usize;
+        let (ty, flags) = (frames[3], frames[4]);
+        let Some(payload) = frames.get(9..9 + len) else {
+            break;
+        };
+        frames = &frames[9 + len..];
+
+        let fragment = match ty {
+            // HEADERS
+            0x1 => {
+                let mut fragment = payload;
+                if flags & PADDED != 0 {
+                    let Some((&pad_len, rest)) = fragment.split_first() else {
+                        continue;
+                    };
+                    let Some(end) = rest.len().checked_sub(pad_len as usize) else {
+                        continue;
+                    };
+                    fragment = &rest[..end];
+                }
+                if flags & PRIORITY != 0 {
+                    let Some(rest) = fragment.get(5..) else {
+                        continue;
+                    };
+                    fragment = rest;
+                }
+                current = Some(vec![]);
+                fragment
+            }
+            // CONTINUATION
+            0x9 if current.is_some() => payload,
+            _ => continue,
+        };
+        let block = current.as_mut().unwrap();
+        block.extend_from_slice(fragment);
+        if flags & END_HEADERS != 0 {
+            blocks.extend(current.take());
+        }
+    }
+    blocks
+}
+
+struct RecordingIo {
+    read: PipeRead,
+    write: RecordingWrite,
+}
+
+impl IntoHalves for RecordingIo {
+    type Read = PipeRead;
+    type Write = RecordingWrite;
+
+    fn into_halves(self) -> (Self::Read, Self::Write) {
+        (self.read, self.write)
+    }
+}
+
+/// Writes to a pipe, keeping a copy of everything that goes through
+struct RecordingWrite {
+    inner: PipeWrite,
+    recorded: Rc<RefCell<Vec<u8>>>,
+}
+
+impl WriteOwned for RecordingWrite {
+    async fn write_owned(
+        &mut self,
+        buf: impl Into<Piece>,
+    ) -> buffet::bufpool::BufResult<usize, Piece> {
+        let buf = buf.into();
+        let (res, buf) = self.inner.write_owned(buf).await;
+        if let Ok(n) = res {
+            self.recorded.borrow_mut().extend_from_slice(&buf[..n]);
+        }
+        (res, buf)
+    }
+
+    async fn shutdown(&mut self) -> std::io::Result<()> {
+        self.inner.shutdown().await
+    }
+}
+
+httpwg_macros::gen_catalog!(catalog);
@@ -0,0 +1,69 @@
+//! Helpers shared by the fuzz targets: feeding bytes to a loona server as if
+//! a client had sent them, cf. [serve_h1]
+
+use std::rc::Rc;
+
+use b_x::{BxForResults, BX};
+use buffet::{Piece, RollMut, WriteOwned};
+use loona::{
+    Body, BodyChunk, Encoder, ExpectResponseHeaders, Responder, Response, ResponseDone,
+    ServerDriver,
+};
+
+/// Serves `input` as an HTTP/1.1 connection, until the server is done with
+/// it. Responses are discarded.
+pub fn serve_h1(input: &[u8]) {
+    let input = input.to_vec();
+    buffet::start(async move {
+        let (mut client_write, server_read) = buffet::pipe();
+        buffet::spawn(async move {
+            // fails once the server stops reading, which is fine
+            _ = client_write.write_all_owned(input).await;
+        });
+
+        let conf = Rc::new(loona::h1::ServerConf::default());
+        let client_buf = RollMut::alloc().unwrap();
+        // errors are expected: inputs are mostly garbage
+        _ = loona::h1::serve((server_read, Discard), conf, client_buf, DrainDriver).await;
+    });
+}
+
+/// Answers every request with a 200 and a short body, once it's read the
+/// whole request body
+pub struct DrainDriver;
+
+impl<OurEncoder> ServerDriver<OurEncoder> for DrainDriver
+where
+    OurEncoder: Encoder,
+{
+    type Error = BX;
+
+    async fn handle(
+        &self,
+        _req: loona::Request,
+        req_body: &mut impl Body,
+        res: Responder<OurEncoder, ExpectResponseHeaders>,
+    ) -> Result<Responder<OurEncoder, ResponseDone>, BX> {
+        while let BodyChunk::Chunk(_) = req_body.next_chunk().await.bx()? {}
+        let mut res = res.write_final_response(Response::default()).await?;
+        res.write_chunk("ok".into()).await?;
+        Ok(res.finish_body(None).await?)
+    }
+}
+
+/// Accepts (and drops) everything written to it
+pub struct Discard;
+
+impl WriteOwned for Discard {
+    async fn write_owned(
+        &mut self,
+        buf: impl Into<Piece>,
+    ) -> buffet::bufpool::BufResult<usize, Piece> {
+        let buf = buf.into();
+        (Ok(buf.len()), buf)
+    }
+
+    async fn shutdown(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,354 @@
+//! Walks a byte dump frame by frame, printing what it finds
+
+use std::io::Write;
+
+use buffet::RollMut;
+use loona_h2::{
+    ContinuationFlags, DataFlags, ErrorCode, Frame, FrameType, HeadersFlags, Setting, StreamId,
+    PREFACE, PRIORITY_UPDATE_FRAME_TYPE,
+};
+use pretty_hex::PrettyHex;
+
+const FRAME_HEADER_LEN: usize = 9;
+
+/// Decodes frames from `input`, writing a description of each one to `out`.
+/// Stops at the first frame that's cut short.
+pub fn decode(input: &[u8], out: &mut impl Write) -> eyre::Result<()> {
+    let mut decoder = Decoder {
+        hpack: loona_hpack::Decoder::new(),
+        header_block: None,
+        scratch: RollMut::alloc()?,
+    };
+
+    let mut offset = 0;
+    if input.starts_with(PREFACE) {
+        writeln!(out, "@{offset}: client connection preface")?;
+        offset += PREFACE.len();
+    }
+
+    while offset < input.len() {
+        let rest = &input[offset..];
+        if rest.len() < FRAME_HEADER_LEN {
+            writeln!(
+                out,
+                "@{offset}: truncated frame header, only {} bytes left",
+                rest.len()
+            )?;
+            break;
+        }
+
+        let frame = decoder.parse_header(&rest[..FRAME_HEADER_LEN])?;
+        let payload = &rest[FRAME_HEADER_LEN..];
+        if payload.len() < frame.len as usize {
+            writeln!(
+                out,
+                "@{offset}: {frame:?} is truncated, only {} bytes of payload left",
+                payload.len()
+            )?;
+            break;
+        }
+        let payload = &payload[..frame.len as usize];
+
+        writeln!(out, "@{offset}: {frame:?}")?;
+        // the flags byte, for frame types loona-h2 doesn't type them for
+        let raw_flags = rest[4];
+        decoder.describe_payload(&frame, raw_flags, payload, out)?;
+        offset += FRAME_HEADER_LEN + payload.len();
+    }
+
+    if let Some(block) = &decoder.header_block {
+        writeln!(
+            out,
+            "header block for stream {} was never completed ({} bytes)",
+            block.stream_id,
+            block.fragment.len()
+        )?;
+    }
+    Ok(())
+}
+
+struct Decoder<'a> {
+    hpack: loona_hpack::Decoder<'a>,
+
+    /// a header block we're waiting on CONTINUATION frames for
+    header_block: Option<HeaderBlock>,
+
+    /// where frame headers are copied to, to be parsed
+    scratch: RollMut,
+}
+
+struct HeaderBlock {
+    stream_id: StreamId,
+    fragment: Vec<u8>,
+}
+
+impl Decoder<'_> {
+    fn parse_header(&mut self, header: &[u8]) -> eyre::Result<Frame> {
+        self.scratch.put(header)?;
+        let (_, frame) = Frame::parse(self.scratch.take_all())
+            .map_err(|e| eyre::eyre!("could not parse frame header: {e}"))?;
+        Ok(frame)
+    }
+
+    fn describe_payload(
+        &mut self,
+        frame: &Frame,
+        raw_flags: u8,
+        payload: &[u8],
+        out: &mut impl Write,
+    ) -> eyre::Result<()> {
+        match frame.frame_type {
+            FrameType::Data(flags) => {
+                let Some(data) = strip_padding(payload, flags.contains(DataFlags::Padded), out)?
+                else {
+                    return Ok(());
+                };
+                if !data.is_empty() {
+                    writeln!(out, "{:?}", data.hex_dump())?;
+                }
+            }
+            FrameType::Headers(flags) => {
+                let Some(mut fragment) =
+                    strip_padding(payload, flags.contains(HeadersFlags::Padded), out)?
+                else {
+                    return Ok(());
+                };
+                if flags.contains(HeadersFlags::Priority) {
+                    let Some(priority) = fragment.get(..5) else {
+                        writeln!(out, "  priority fields don't fit")?;
+                        return Ok(());
+                    };
+                    describe_priority(priority, out)?;
+                    fragment = &fragment[5..];
+                }
+                self.header_fragment(
+                    frame.stream_id,
+                    fragment,
+                    flags.contains(HeadersFlags::EndHeaders),
+                    out,
+                )?;
+            }
+            FrameType::Continuation(flags) => {
+                self.header_fragment(
+                    frame.stream_id,
+                    payload,
+                    flags.contains(ContinuationFlags::EndHeaders),
+                    out,
+                )?;
+            }
+            FrameType::PushPromise => {
+                // the PADDED flag has the same value as for HEADERS frames,
+                // and so has END_HEADERS
+                let Some(fragment) =
+                    strip_padding(payload, raw_flags & HeadersFlags::Padded as u8 != 0, out)?
+                else {
+                    return Ok(());
+                };
+                let Some(promised) = fragment.get(..4) else {
+                    writeln!(out, "  promised stream ID doesn't fit")?;
+                    return Ok(());
+                };
+                let promised = u32::from_be_bytes(promised.try_into().unwrap()) & 0x7FFF_FFFF;
+                writeln!(out, "  promised stream: {promised}")?;
+                self.header_fragment(
+                    frame.stream_id,
+                    &fragment[4..],
+                    raw_flags & HeadersFlags::EndHeaders as u8 != 0,
+                    out,
+                )?;
+            }
+            FrameType::Priority => {
+                let Ok(priority) = <[u8; 5]>::try_from(payload) else {
+                    writeln!(out, "  invalid length for a PRIORITY frame")?;
+                    return Ok(());
+                };
+                describe_priority(&priority, out)?;
+            }
+            FrameType::RstStream => match <[u8; 4]>::try_from(payload) {
+                Ok(code) => writeln!(out, "  error: {:?}", ErrorCode(u32::from_be_bytes(code)))?,
+                Err(_) => writeln!(out, "  invalid length for a RST_STREAM frame")?,
+            },
+            FrameType::Settings(_) => {
+                if payload.len() % 6 != 0 {
+                    writeln!(out, "  invalid length for a SETTINGS frame")?;
+                    return Ok(());
+                }
+                for pair in payload.chunks_exact(6) {
+                    let id = u16::from_be_bytes([pair[0], pair[1]]);
+                    let value = u32::from_be_bytes(pair[2..].try_into().unwrap());
+                    match Setting::from_repr(id) {
+                        Some(setting) => writeln!(out, "  {setting:?} = {value}")?,
+                        None => writeln!(out, "  unknown setting {id:#x} = {value}")?,
+                    }
+                }
+            }
+            FrameType::Ping(_) => writeln!(out, "  opaque data: {:02x?}", payload)?,
+            FrameType::GoAway => {
+                if payload.len() < 8 {
+                    writeln!(out, "  invalid length for a GOAWAY frame")?;
+                    return Ok(());
+                }
+                let last_stream_id = u32::from_be_bytes(payload[..4].try_into().unwrap());
+                let code = u32::from_be_bytes(payload[4..8].try_into().unwrap());
+                writeln!(
+                    out,
+                    "  last stream: {}, error: {:?}",
+                    last_stream_id & 0x7FFF_FFFF,
+                    ErrorCode(code)
+                )?;
+                if payload.len() > 8 {
+                    writeln!(
+                        out,
+                        "  debug data: {:?}",
+                        String::from_utf8_lossy(&payload[8..])
+                    )?;
+                }
+            }
+            FrameType::WindowUpdate => match <[u8; 4]>::try_from(payload) {
+                Ok(increment) => writeln!(
+                    out,
+                    "  increment: {}",
+                    u32::from_be_bytes(increment) & 0x7FFF_FFFF
+                )?,
+                Err(_) => writeln!(out, "  invalid length for a WINDOW_UPDATE frame")?,
+            },
+            FrameType::Unknown(ft) if ft.ty == PRIORITY_UPDATE_FRAME_TYPE && payload.len() >= 4 => {
+                let prioritized = u32::from_be_bytes(payload[..4].try_into().unwrap());
+                writeln!(
+                    out,
+                    "  PRIORITY_UPDATE for stream {}: {:?}",
+                    prioritized & 0x7FFF_FFFF,
+                    String::from_utf8_lossy(&payload[4..])
+                )?;
+            }
+            FrameType::Unknown(_) => {
+                if !payload.is_empty() {
+                    writeln!(out, "{:?}", payload.hex_dump())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Accumulates a header block fragment, and decodes the block once it's
+    /// complete
+    fn header_fragment(
+        &mut self,
+        stream_id: StreamId,
+        fragment: &[u8],
+        end_headers: bool,
+        out: &mut impl Write,
+    ) -> eyre::Result<()> {
+        let block = self.header_block.get_or_insert_with(|| HeaderBlock {
+            stream_id,
+            fragment: Vec::new(),
+        });
+        if block.stream_id != stream_id {
+            writeln!(
+                out,
+                "  expected a CONTINUATION frame for stream {}",
+                block.stream_id
+            )?;
+        }
+        block.fragment.extend_from_slice(fragment);
+        if !end_headers {
+            writeln!(out, "  (header block continues)")?;
+            return Ok(());
+        }
+
+        let block = self.header_block.take().unwrap();
+        let mut lines = vec![];
+        let res = self.hpack.decode_with_cb(&block.fragment, |name, value| {
+            lines.push(format!(
+                "  {}: {}",
+                String::from_utf8_lossy(&name),
+                String::from_utf8_lossy(&value)
+            ));
+        });
+        for line in lines {
+            writeln!(out, "{line}")?;
+        }
+        if let Err(e) = res {
+            writeln!(out, "  HPACK decoding failed: {e:?}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Describes the fields of PRIORITY frames, and of HEADERS frames with the
+/// PRIORITY flag, cf. <https://httpwg.org/specs/rfc9113.html#PRIORITY>
+fn describe_priority(fields: &[u8], out: &mut impl Write) -> eyre::Result<()> {
+    let dependency = u32::from_be_bytes(fields[..4].try_into().unwrap());
+    writeln!(
+        out,
+        "  priority: exclusive={}, depends on {}, weight {}",
+        dependency >> 31 == 1,
+        dependency & 0x7FFF_FFFF,
+        fields[4] as u32 + 1
+    )?;
+    Ok(())
+}
+
+/// Returns what's left of `payload` once its padding is removed, if it's
+/// padded. Returns `None` (after saying why) if the padding is invalid.
+fn strip_padding<'a>(
+    payload: &'a [u8],
+    padded: bool,
+    out: &mut impl Write,
+) -> eyre::Result<Option<&'a [u8]>> {
+    if !padded {
+        return Ok(Some(payload));
+    }
+    let Some((&pad_len, rest)) = payload.split_first() else {
+        writeln!(out, "  padded, but has no pad length")?;
+        return Ok(None);
+    };
+    if pad_len as usize > rest.len() {
+        writeln!(
+            out,
+            "  pad length {pad_len} exceeds the {} bytes left",
+            rest.len()
+        )?;
+        return Ok(None);
+    }
+    writeln!(out, "  padding: {pad_len} bytes")?;
+    Ok(Some(&rest[..rest.len() - pad_len as usize]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decode;
+
+    #[test]
+    fn decodes_frames_and_header_blocks() {
+        buffet::bufpool::initialize_allocator_with_num_bufs(16).unwrap();
+
+        let mut input = loona_h2::PREFACE.to_vec();
+        // SETTINGS: MAX_CONCURRENT_STREAMS = 100
+        input.extend([0, 0, 6, 0x4, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 100]);
+        // HEADERS without END_HEADERS, then CONTINUATION with it
+        let block = loona_hpack::Encoder::new()
+            .encode([(&b":method"[..], &b"GET"[..]), (b":path", b"/hello")]);
+        let (first, second) = block.split_at(2);
+        input.extend([0, 0, first.len() as u8, 0x1, 0x1, 0, 0, 0, 1]);
+        input.extend(first);
+        input.extend([0, 0, second.len() as u8, 0x9, 0x4, 0, 0, 0, 1]);
+        input.extend(second);
+        // a truncated GOAWAY
+        input.extend([0, 0, 8, 0x7, 0, 0, 0, 0, 0, 0, 0]);
+
+        let mut out = vec![];
+        decode(&input, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        for expected in [
+            "client connection preface",
+            "MaxConcurrentStreams = 100",
+            "(header block continues)",
+            ":method: GET",
+            ":path: /hello",
+            "only 2 bytes of payload left",
+        ] {
+            assert!(out.contains(expected), "missing {expected:?} in:\n{out}");
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::{ffi::OsString, io::Read, path::PathBuf};
+
+use base64::Engine;
+
+mod decode;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Format {
+    #[default]
+    Hex,
+    Base64,
+    Raw,
+}
+
+#[derive(Default, Debug)]
+struct Args {
+    /// how the input is encoded
+    format: Format,
+
+    /// how many (decoded) bytes to skip before the first frame
+    offset: usize,
+
+    /// where to read from, stdin if `None`
+    input: Option<PathBuf>,
+}
+
+trait IntoStringResult {
+    fn into_string_result(self) -> eyre::Result<String>;
+}
+
+impl IntoStringResult for OsString {
+    fn into_string_result(self) -> eyre::Result<String> {
+        self.into_string()
+            .map_err(|_| eyre::eyre!("OsString contained invalid UTF-8"))
+    }
+}
+
+fn parse_args() -> eyre::Result<Args> {
+    let mut args: Args = Default::default();
+    let mut parser = lexopt::Parser::from_env();
+    while let Some(arg) = parser.next()? {
+        match arg {
+            lexopt::Arg::Long("hex") => args.format = Format::Hex,
+            lexopt::Arg::Long("base64") => args.format = Format::Base64,
+            lexopt::Arg::Long("raw") => args.format = Format::Raw,
+            lexopt::Arg::Long("offset") | lexopt::Arg::Short('o') => {
+                args.offset = parser
+                    .value()?
+                    .into_string_result()?
+                    .parse()
+                    .map_err(|e| eyre::eyre!("Failed to parse offset: {e}"))?;
+            }
+            lexopt::Arg::Value(value) if args.input.is_none() => {
+                args.input = Some(value.into());
+            }
+            _ => return Err(arg.unexpected().into()),
+        }
+    }
+    Ok(args)
+}
+
+fn print_usage() {
+    eprintln!(
+        "Usage: loona-h2-decode [OPTIONS] [FILE]
+
+Options:
+    --hex                  The input is hex (the default): whitespace, `0x` prefixes
+                           and `pretty-hex` offsets and ASCII columns are ignored
+    --base64               The input is base64
+    --raw                  The input is raw bytes
+    -o, --offset <BYTES>   How many (decoded) bytes to skip before the first frame
+
+Arguments:
+    FILE                   Where to read the dump from, stdin by default
+
+Examples:
+    echo '000004080000000000000f0001' | loona-h2-decode
+    loona-h2-decode --raw --offset 120 capture.bin
+"
+    );
+}
+
+fn main() -> eyre::Result<()> {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("Failed to parse arguments: {e}");
+            print_usage();
+            std::process::exit(1);
+        }
+    };
+
+    let mut input = vec![];
+    match &args.input {
+        Some(path) => input = std::fs::read(path)?,
+        None => {
+            std::io::stdin().read_to_end(&mut input)?;
+        }
+    }
+    let bytes = match args.format {
+        Format::Raw => input,
+        Format::Hex => parse_hex(std::str::from_utf8(&input)?)?,
+        Format::Base64 => {
+            let text: String = std::str::from_utf8(&input)?
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect();
+            base64::engine::general_purpose::STANDARD.decode(text)?
+        }
+    };
+    let Some(bytes) = bytes.get(args.offset..) else {
+        eyre::bail!(
+            "Offset {} is past the end of the input ({} bytes)",
+            args.offset,
+            bytes.len()
+        );
+    };
+
+    buffet::bufpool::initialize_allocator_with_num_bufs(16)?;
+    decode::decode(bytes, &mut std::io::stdout().lock())
+}
+
+/// Parses a hex dump, either plain (with any whitespace, and optional `0x`
+/// prefixes), or as formatted by `pretty-hex`, e.g.
+/// `0000:   50 52 49 20  2a 20 48 54   PRI * HT`
+fn parse_hex(text: &str) -> eyre::Result<Vec<u8>> {
+    let mut digits = String::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with("Length:") {
+            // pretty-hex's header
+            continue;
+        }
+        let line = match line.split_once(':') {
+            Some((offset, rest)) if u64::from_str_radix(offset, 16).is_ok() => {
+                // the ASCII column comes after three spaces
+                rest.trim_start().split("   ").next().unwrap_or_default()
+            }
+            _ => line,
+        };
+        for word in line.split_whitespace() {
+            digits.push_str(word.trim_start_matches("0x"));
+        }
+    }
+
+    if digits.len() % 2 != 0 {
+        eyre::bail!("Odd number of hex digits ({})", digits.len());
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&digits[i..i + 2], 16)
+                .map_err(|e| eyre::eyre!("Invalid hex {:?}: {e}", &digits[i..i + 2]))
+        })
+        .collect()
+}
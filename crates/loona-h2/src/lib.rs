@@ -792,6 +792,15 @@ pub struct Settings {
     /// For any given request, a lower limit than what is advertised MAY be
     /// enforced. The initial value of this setting is unlimited.
     pub max_header_list_size: u32,
+
+    /// Upon receipt of SETTINGS_ENABLE_CONNECT_PROTOCOL with a value of 1, a
+    /// client MAY use the Extended CONNECT as defined in this document when
+    /// creating new streams. Receipt of this parameter by a server does not
+    /// have any impact.
+    ///
+    /// The initial value is 0. Any value other than 0 or 1 MUST be treated as
+    /// a connection error of type PROTOCOL_ERROR, cf. <https://www.rfc-editor.org/rfc/rfc8441#section-3>
+    pub enable_connect_protocol: bool,
 }
 
 impl Default for Settings {
@@ -804,6 +813,7 @@ impl Default for Settings {
             initial_window_size: (1 << 16) - 1,
             max_frame_size: (1 << 14),
             max_header_list_size: 0,
+            enable_connect_protocol: false,
         }
     }
 }
@@ -839,6 +849,13 @@ impl Settings {
             Setting::MaxHeaderListSize => {
                 self.max_header_list_size = value;
             }
+            Setting::EnableConnectProtocol => match value {
+                0 => self.enable_connect_protocol = false,
+                1 => self.enable_connect_protocol = true,
+                _ => {
+                    return Err(SettingsError::InvalidEnableConnectProtocolValue { actual: value })
+                }
+            },
         }
 
         Ok(())
@@ -858,6 +875,9 @@ pub enum SettingsError {
         "bad SETTINGS_MAX_FRAME_SIZE value {actual}, should be between 2^14 and 2^24-1 inclusive"
     )]
     SettingsMaxFrameSizeInvalid { actual: u32 },
+
+    #[error("ENABLE_CONNECT_PROTOCOL setting is supposed to be either 0 or 1, got {actual}")]
+    InvalidEnableConnectProtocolValue { actual: u32 },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -868,6 +888,7 @@ pub enum Setting {
     InitialWindowSize = 0x04,
     MaxFrameSize = 0x05,
     MaxHeaderListSize = 0x06,
+    EnableConnectProtocol = 0x08,
 }
 
 impl Setting {
@@ -883,6 +904,7 @@ impl Setting {
             0x04 => Some(Setting::InitialWindowSize),
             0x05 => Some(Setting::MaxFrameSize),
             0x06 => Some(Setting::MaxHeaderListSize),
+            0x08 => Some(Setting::EnableConnectProtocol),
             _ => None,
         }
     }
@@ -897,6 +919,7 @@ fn test_setting_roundtrip() {
         Setting::InitialWindowSize,
         Setting::MaxFrameSize,
         Setting::MaxHeaderListSize,
+        Setting::EnableConnectProtocol,
     ];
 
     for &setting in &settings {
@@ -972,6 +995,29 @@ impl<'a> IntoPiece for SettingPairs<'a> {
     }
 }
 
+/// Payload for a DATA or HEADERS frame that has the PADDED flag set: the pad
+/// length, then `payload` (data, or a header block fragment), then
+/// `pad_length` bytes of padding, all zero.
+pub struct Padded {
+    pub pad_length: u8,
+    pub payload: Piece,
+}
+
+impl IntoPiece for Padded {
+    fn into_piece(self, scratch: &mut RollMut) -> std::io::Result<Piece> {
+        let pad_length = self.pad_length as usize;
+        let roll = scratch
+            .put_to_roll(1 + self.payload.len() + pad_length, |mut slice| {
+                slice.write_u8(self.pad_length)?;
+                slice.write_all(&self.payload[..])?;
+                slice.write_all(&[0u8; 255][..pad_length])?;
+                Ok(())
+            })
+            .unwrap();
+        Ok(roll.into())
+    }
+}
+
 /// Payload for a GOAWAY frame
 pub struct GoAway {
     pub last_stream_id: StreamId,
@@ -1039,6 +1085,48 @@ impl RstStream {
     }
 }
 
+/// Frame type of PRIORITY_UPDATE frames, which aren't part of RFC 9113 (and
+/// so are parsed as [FrameType::Unknown]), cf. <https://www.rfc-editor.org/rfc/rfc9218#section-7.1>
+pub const PRIORITY_UPDATE_FRAME_TYPE: u8 = 0x10;
+
+/// Payload for a PRIORITY_UPDATE frame
+#[derive(Clone)]
+pub struct PriorityUpdate {
+    /// The stream whose priority is being updated
+    pub prioritized_stream_id: StreamId,
+
+    /// The new priority, as a Priority Field Value, e.g. `u=1, i`
+    pub field_value: Piece,
+}
+
+impl IntoPiece for PriorityUpdate {
+    fn into_piece(self, scratch: &mut RollMut) -> std::io::Result<Piece> {
+        let roll = scratch
+            .put_to_roll(4 + self.field_value.len(), |mut slice| {
+                slice.write_all(&pack_bit_and_u31(0, self.prioritized_stream_id.0))?;
+                slice.write_all(&self.field_value[..])?;
+                Ok(())
+            })
+            .unwrap();
+        Ok(roll.into())
+    }
+}
+
+impl PriorityUpdate {
+    pub fn parse(i: Roll) -> IResult<Roll, Self> {
+        let (i, (_reserved, prioritized_stream_id)) = parse_reserved_and_stream_id(i)?;
+        let len = i.len();
+        let (rest, field_value) = nom::bytes::streaming::take(len)(i)?;
+        Ok((
+            rest,
+            Self {
+                prioritized_stream_id,
+                field_value: field_value.into(),
+            },
+        ))
+    }
+}
+
 /// Payload for a WINDOW_UPDATE frame
 #[derive(Debug, Clone, Copy)]
 pub struct WindowUpdate {
@@ -196,6 +196,23 @@ impl<'a> Encoder<'a> {
             .set_max_table_size(new_max_size);
     }
 
+    /// Returns how many entries the dynamic table holds
+    pub fn dynamic_table_len(&self) -> usize {
+        self.header_table.dynamic_table.len()
+    }
+
+    /// Returns the size of the dynamic table in octets, as defined by the
+    /// HPACK spec (each entry counts for 32 octets on top of its name and
+    /// value)
+    pub fn dynamic_table_size(&self) -> usize {
+        self.header_table.dynamic_table.get_size()
+    }
+
+    /// Returns the maximum size of the dynamic table in octets
+    pub fn dynamic_table_max_size(&self) -> usize {
+        self.header_table.dynamic_table.get_max_table_size()
+    }
+
     /// Encodes the given headers using the HPACK rules and returns a newly
     /// allocated `Vec` containing the bytes representing the encoded header
     /// set.
@@ -0,0 +1,102 @@
+//! The header rewriting [crate::ProxyDriver] does on the way through
+
+use std::net::SocketAddr;
+
+use http::{header, HeaderName};
+use loona::{Headers, Request};
+
+/// Headers that only make sense for a single connection, which proxies
+/// must not forward, cf. <https://httpwg.org/specs/rfc9110.html#field.connection>
+pub static HOP_BY_HOP: [HeaderName; 9] = [
+    header::CONNECTION,
+    HeaderName::from_static("keep-alive"),
+    HeaderName::from_static("proxy-connection"),
+    header::PROXY_AUTHENTICATE,
+    header::PROXY_AUTHORIZATION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+pub const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+pub const X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+pub const X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+
+/// Removes [HOP_BY_HOP] headers, along with the ones the `connection`
+/// header lists.
+pub fn strip_hop_by_hop(headers: &mut Headers) {
+    let listed: Vec<HeaderName> = headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .flat_map(|value| value.split(|&b| b == b','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim_ascii()).ok())
+        .collect();
+
+    for name in listed.iter().chain(&HOP_BY_HOP) {
+        headers.remove(name);
+    }
+}
+
+/// Tells the upstream who the request is really from, with a `forwarded`
+/// header (cf. <https://www.rfc-editor.org/rfc/rfc7239>) and the
+/// `x-forwarded-*` headers that predate it.
+///
+/// `peer` is the address of the client, appended to `x-forwarded-for`, or
+/// `unknown` in `forwarded` if it's `None`. `x-forwarded-host` and
+/// `x-forwarded-proto` are overwritten: they're about the request as we
+/// received it. The protocol is the request URI's scheme (which HTTP/2
+/// requests always have), `http` otherwise.
+pub fn add_forwarding_headers(req: &mut Request, peer: Option<SocketAddr>) {
+    let host = req
+        .headers
+        .get(header::HOST)
+        .and_then(|host| std::str::from_utf8(host).ok())
+        .map(|host| host.to_owned())
+        .or_else(|| req.uri.authority().map(|a| a.as_str().to_owned()));
+    let proto = req.uri.scheme_str().unwrap_or("http").to_owned();
+
+    let mut forwarded = match peer {
+        Some(peer) => format!("for=\"{peer}\""),
+        None => "for=unknown".to_string(),
+    };
+    if let Some(host) = &host {
+        forwarded.push_str(&format!(";host={}", quoted(host)));
+    }
+    forwarded.push_str(&format!(";proto={proto}"));
+    req.headers
+        .append(header::FORWARDED, forwarded.into_bytes().into());
+
+    if let Some(peer) = peer {
+        let mut xff = Vec::new();
+        for value in req.headers.get_all(&X_FORWARDED_FOR) {
+            xff.extend_from_slice(value);
+            xff.extend_from_slice(b", ");
+        }
+        xff.extend_from_slice(peer.ip().to_string().as_bytes());
+        req.headers.insert(X_FORWARDED_FOR, xff.into());
+    }
+
+    match host {
+        Some(host) => req
+            .headers
+            .insert(X_FORWARDED_HOST, host.into_bytes().into()),
+        None => req.headers.remove(X_FORWARDED_HOST),
+    };
+    req.headers
+        .insert(X_FORWARDED_PROTO, proto.into_bytes().into());
+}
+
+/// Formats `s` as a quoted-string, cf. <https://httpwg.org/specs/rfc9110.html#quoted.strings>
+fn quoted(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        if c == '"' || c == '\\' {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,386 @@
+//! A reverse proxy: [ProxyDriver] forwards the requests loona serves, over
+//! HTTP/1.1 or HTTP/2, to an upstream server.
+//!
+//! The upstream is spoken to over HTTP/1.1 (loona doesn't have an HTTP/2
+//! client yet). Connections to it are kept in a [Pool] and reused once a
+//! response has been read in full; one that went stale while idle is retried
+//! once on a fresh connection, if the request had no body. Request and
+//! response bodies are streamed chunk by chunk, never buffered whole: a
+//! slow reader on either side slows down the writer on the other. Hop-by-hop
+//! headers are stripped both ways, and forwarding headers are added to
+//! requests, cf. [headers].
+//!
+//! Failing to reach the upstream, or to get a response out of it, turns into
+//! a `502 Bad Gateway` or, for timeouts, a `504 Gateway Timeout`, cf.
+//! [ProxyConf]. Once the response has started, all we can do is abort it.
+//! An upstream we couldn't connect to is marked unhealthy in the pool, and
+//! requests fail fast with a 502 until [loona::pool::PoolConf::unhealthy_for] elapses.
+
+use std::{cell::RefCell, net::SocketAddr, rc::Rc, time::Duration};
+
+use b_x::BX;
+use http::{header, StatusCode, Version};
+use loona::{
+    buffet::{net::TcpStream, IntoHalves},
+    cancel::CancelReason,
+    h1,
+    pool::Pool,
+    Body, BodyChunk, ConnectionInfo, Encoder, ExpectResponseHeaders, HeadersExt, Method, Request,
+    Responder, ResponderError, Response, ResponseDone, ServerDriver,
+};
+use tracing::debug;
+
+pub mod headers;
+
+/// Timeouts for [ProxyDriver]
+#[derive(Debug, Clone)]
+pub struct ProxyConf {
+    /// How long to wait for the connection to the upstream to be
+    /// established
+    pub connect_timeout: Duration,
+
+    /// How long to wait for the upstream's response headers. Once they've
+    /// arrived, the response body can take as long as it needs.
+    pub response_timeout: Duration,
+}
+
+impl Default for ProxyConf {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(10),
+            response_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Both halves of an upstream connection, as [h1::request_with_conf] takes and
+/// returns them
+pub type UpstreamTransport = (
+    <TcpStream as IntoHalves>::Read,
+    <TcpStream as IntoHalves>::Write,
+);
+
+/// A [ServerDriver] that forwards every request to an upstream
+#[derive(Clone)]
+pub struct ProxyDriver {
+    upstream: SocketAddr,
+    conf: Rc<ProxyConf>,
+    pool: Rc<Pool<SocketAddr, UpstreamTransport>>,
+    peer_addr: Option<SocketAddr>,
+}
+
+impl ProxyDriver {
+    pub fn new(upstream: SocketAddr) -> Self {
+        Self {
+            upstream,
+            conf: Default::default(),
+            pool: Rc::new(Pool::new(Default::default())),
+            peer_addr: None,
+        }
+    }
+
+    pub fn with_conf(mut self, conf: ProxyConf) -> Self {
+        self.conf = Rc::new(conf);
+        self
+    }
+
+    /// Uses `pool` for upstream connections, e.g. to share them between
+    /// several drivers. Every driver gets its own pool otherwise, shared
+    /// with its clones.
+    pub fn with_pool(mut self, pool: Rc<Pool<SocketAddr, UpstreamTransport>>) -> Self {
+        self.pool = pool;
+        self
+    }
+
+    pub fn pool(&self) -> &Rc<Pool<SocketAddr, UpstreamTransport>> {
+        &self.pool
+    }
+
+    /// Returns a driver for the requests of the connection `info` is about,
+    /// so that the client's address makes it into the forwarding headers.
+    /// Without it (e.g. when a single driver serves every connection of a
+    /// [loona::server::Server]), the client is `unknown`.
+    pub fn for_connection(&self, info: &ConnectionInfo) -> Self {
+        Self {
+            peer_addr: info.peer_addr,
+            ..self.clone()
+        }
+    }
+}
+
+/// Something went wrong between us and the upstream
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum UpstreamError {
+    #[error("could not connect to upstream: {0}")]
+    Connect(#[source] std::io::Error),
+
+    #[error("timed out connecting to upstream")]
+    ConnectTimeout,
+
+    /// Connecting failed recently, cf. [Pool::mark_unhealthy]
+    #[error("upstream is unhealthy")]
+    Unhealthy,
+
+    #[error("timed out waiting for the upstream response")]
+    ResponseTimeout,
+
+    /// The upstream didn't send a valid response, or went away while
+    /// sending it
+    #[error("upstream request failed: {0}")]
+    Request(#[source] BX),
+}
+
+impl UpstreamError {
+    /// What the client is told, if the response hasn't started yet
+    pub fn status(&self) -> StatusCode {
+        match self {
+            UpstreamError::ConnectTimeout | UpstreamError::ResponseTimeout => {
+                StatusCode::GATEWAY_TIMEOUT
+            }
+            _ => StatusCode::BAD_GATEWAY,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum ProxyError<EncoderError> {
+    /// The upstream failed after the response started, so the client
+    /// couldn't be told with a 502 or 504
+    #[error("upstream error: {0}")]
+    Upstream(#[from] UpstreamError),
+
+    /// Error writing the response to the client
+    #[error("error writing response: {0}")]
+    Responder(#[from] ResponderError<EncoderError>),
+
+    /// The request was cancelled (cf. [loona::cancel]), so the upstream
+    /// request was dropped
+    #[error("request cancelled: {0:?}")]
+    Cancelled(CancelReason),
+}
+
+/// Where the responder waits until the upstream response arrives, so that
+/// we can still send an error response if it doesn't.
+type ResponderSlot<OurEncoder> = RefCell<Option<Responder<OurEncoder, ExpectResponseHeaders>>>;
+
+impl<OurEncoder> ServerDriver<OurEncoder> for ProxyDriver
+where
+    OurEncoder: Encoder,
+{
+    type Error = ProxyError<OurEncoder::Error>;
+
+    async fn handle(
+        &self,
+        mut req: Request,
+        req_body: &mut impl Body,
+        mut respond: Responder<OurEncoder, ExpectResponseHeaders>,
+    ) -> Result<Responder<OurEncoder, ResponseDone>, Self::Error> {
+        if req.method == Method::Connect {
+            debug!("not proxying CONNECT request");
+            return Ok(error_response(respond, StatusCode::NOT_IMPLEMENTED).await?);
+        }
+
+        if req.headers.expects_100_continue() {
+            // the body gets read as it's forwarded, whatever the upstream
+            // would have said about it.
+            respond
+                .write_interim_response(Response {
+                    status: StatusCode::CONTINUE,
+                    ..Default::default()
+                })
+                .await?;
+            req.headers.remove(header::EXPECT);
+        }
+
+        headers::strip_hop_by_hop(&mut req.headers);
+        if !req.headers.contains_key(header::HOST) {
+            if let Some(authority) = req.uri.authority() {
+                let host = authority.as_str().to_owned().into_bytes();
+                req.headers.insert(header::HOST, host.into());
+            }
+        }
+        headers::add_forwarding_headers(&mut req, self.peer_addr);
+        req.version = Version::HTTP_11;
+
+        let cancellation = respond.cancellation().clone();
+        let slot: ResponderSlot<OurEncoder> = RefCell::new(Some(respond));
+        let res = tokio::select! {
+            res = self.forward(req, req_body, &slot) => res,
+            reason = cancellation.cancelled() => {
+                debug!(?reason, "request cancelled, dropping upstream request");
+                Err(ProxyError::Cancelled(reason))
+            }
+        };
+
+        match res {
+            Err(ProxyError::Upstream(e)) => match slot.take() {
+                Some(respond) => {
+                    debug!(%e, "upstream error, responding with {}", e.status());
+                    Ok(error_response(respond, e.status()).await?)
+                }
+                None => Err(e.into()),
+            },
+            res => res,
+        }
+    }
+}
+
+impl ProxyDriver {
+    async fn forward<OurEncoder>(
+        &self,
+        req: Request,
+        req_body: &mut impl Body,
+        slot: &ResponderSlot<OurEncoder>,
+    ) -> Result<Responder<OurEncoder, ResponseDone>, ProxyError<OurEncoder::Error>>
+    where
+        OurEncoder: Encoder,
+    {
+        if let Some(transport) = self.pool.checkout(&self.upstream) {
+            // only requests without a body can be retried: the body may
+            // have been partially read already.
+            let retryable = req_body.content_len() == Some(0);
+            match self.request(transport, req.clone(), req_body, slot).await {
+                Err(ProxyError::Upstream(UpstreamError::Request(e)))
+                    if retryable && slot.borrow().is_some() =>
+                {
+                    debug!(%e, "pooled connection failed, retrying on a fresh one");
+                }
+                res => return res,
+            }
+            return self
+                .request(self.connect().await?, req, &mut (), slot)
+                .await;
+        }
+
+        let transport = self.connect().await?;
+        self.request(transport, req, req_body, slot).await
+    }
+
+    async fn connect(&self) -> Result<UpstreamTransport, UpstreamError> {
+        if !self.pool.is_healthy(&self.upstream) {
+            return Err(UpstreamError::Unhealthy);
+        }
+
+        let connect = TcpStream::connect(self.upstream);
+        let res = match tokio::time::timeout(self.conf.connect_timeout, connect).await {
+            Ok(res) => res.map_err(UpstreamError::Connect),
+            Err(_) => Err(UpstreamError::ConnectTimeout),
+        };
+        match res {
+            Ok(transport) => Ok(transport.into_halves()),
+            Err(e) => {
+                self.pool.mark_unhealthy(self.upstream);
+                Err(e)
+            }
+        }
+    }
+
+    async fn request<OurEncoder>(
+        &self,
+        transport: UpstreamTransport,
+        req: Request,
+        req_body: &mut impl Body,
+        slot: &ResponderSlot<OurEncoder>,
+    ) -> Result<Responder<OurEncoder, ResponseDone>, ProxyError<OurEncoder::Error>>
+    where
+        OurEncoder: Encoder,
+    {
+        let conf = h1::ClientConf {
+            // once the response has started, it takes as long as it takes
+            response_timeout: Some(self.conf.response_timeout),
+            ..Default::default()
+        };
+        match h1::request_with_conf(transport, &conf, req, req_body, UpstreamDriver { slot }).await
+        {
+            Ok((transport, respond)) => {
+                if let Some(transport) = transport {
+                    self.pool.checkin(self.upstream, transport);
+                }
+                Ok(respond)
+            }
+            Err(h1::Http1ClientError::DriverError(e)) => Err(e),
+            Err(h1::Http1ClientError::ResponseTimeout) => {
+                Err(UpstreamError::ResponseTimeout.into())
+            }
+            Err(e) => Err(UpstreamError::Request(BX::from_err(e)).into()),
+        }
+    }
+}
+
+/// Relays the upstream's response to the client
+struct UpstreamDriver<'a, OurEncoder>
+where
+    OurEncoder: Encoder,
+{
+    slot: &'a ResponderSlot<OurEncoder>,
+}
+
+impl<OurEncoder> h1::ClientDriver for UpstreamDriver<'_, OurEncoder>
+where
+    OurEncoder: Encoder,
+{
+    type Return = Responder<OurEncoder, ResponseDone>;
+    type Error = ProxyError<OurEncoder::Error>;
+
+    async fn on_informational_response(&mut self, mut res: Response) -> Result<(), Self::Error> {
+        if res.status == StatusCode::CONTINUE {
+            // we took care of `expect: 100-continue` ourselves
+            return Ok(());
+        }
+
+        if let Some(mut respond) = self.slot.take() {
+            headers::strip_hop_by_hop(&mut res.headers);
+            let written = respond.write_interim_response(res).await;
+            self.slot.replace(Some(respond));
+            written?;
+        }
+        Ok(())
+    }
+
+    async fn on_final_response(
+        self,
+        mut res: Response,
+        body: &mut impl Body,
+    ) -> Result<Self::Return, Self::Error> {
+        let respond = self
+            .slot
+            .take()
+            .expect("the responder is only taken for the final response");
+
+        headers::strip_hop_by_hop(&mut res.headers);
+        res.version = Version::HTTP_11;
+        let mut respond = respond.write_final_response(res).await?;
+
+        loop {
+            match body
+                .next_chunk()
+                .await
+                .map_err(|e| UpstreamError::Request(BX::from_err(e)))?
+            {
+                BodyChunk::Chunk(chunk) => respond.write_chunk(chunk).await?,
+                BodyChunk::Done { trailers } => return Ok(respond.finish_body(trailers).await?),
+            }
+        }
+    }
+}
+
+async fn error_response<OurEncoder>(
+    respond: Responder<OurEncoder, ExpectResponseHeaders>,
+    status: StatusCode,
+) -> Result<Responder<OurEncoder, ResponseDone>, ResponderError<OurEncoder::Error>>
+where
+    OurEncoder: Encoder,
+{
+    let mut res = Response {
+        status,
+        ..Default::default()
+    };
+    res.headers.insert(header::CONTENT_LENGTH, "0".into());
+    respond
+        .write_final_response(res)
+        .await?
+        .finish_body(None)
+        .await
+}
@@ -0,0 +1,196 @@
+use std::{net::SocketAddr, rc::Rc, time::Duration};
+
+use b_x::{BxForResults, BX};
+use http::{header, StatusCode};
+use loona::{
+    buffet::{ReadOwned, RollMut, WriteOwned},
+    h1,
+    server::{Proto, Server},
+    Body, BodyChunk, Encoder, ExpectResponseHeaders, Headers, Responder, Response, ResponseDone,
+    ServerDriver,
+};
+use loona_proxy::{ProxyConf, ProxyDriver};
+
+/// Answers with the request headers it got, one per line, followed by the
+/// request body.
+struct EchoDriver;
+
+impl<OurEncoder> ServerDriver<OurEncoder> for EchoDriver
+where
+    OurEncoder: Encoder,
+{
+    type Error = BX;
+
+    async fn handle(
+        &self,
+        req: loona::Request,
+        req_body: &mut impl Body,
+        res: Responder<OurEncoder, ExpectResponseHeaders>,
+    ) -> b_x::Result<Responder<OurEncoder, ResponseDone>> {
+        let mut out = vec![];
+        for (name, value) in req.headers.iter() {
+            out.extend_from_slice(name.as_str().as_bytes());
+            out.extend_from_slice(b": ");
+            out.extend_from_slice(value);
+            out.extend_from_slice(b"\n");
+        }
+        while let BodyChunk::Chunk(chunk) = req_body.next_chunk().await.bx()? {
+            out.extend_from_slice(&chunk[..]);
+        }
+
+        let mut headers = Headers::default();
+        headers.insert(header::CONNECTION, "x-upstream-secret".into());
+        headers.insert("x-upstream-secret", "1".into());
+        let mut res = res
+            .write_final_response(Response {
+                status: StatusCode::OK,
+                headers,
+                ..Default::default()
+            })
+            .await?;
+        res.write_chunk(out.into()).await?;
+        Ok(res.finish_body(None).await?)
+    }
+}
+
+/// Sends `req` through `driver` over HTTP/1.1, hangs up and returns
+/// everything that came back.
+async fn roundtrip(driver: ProxyDriver, req: &'static str) -> String {
+    let (server_write, mut client_read) = loona::buffet::pipe();
+    let (mut client_write, server_read) = loona::buffet::pipe();
+    loona::buffet::spawn(async move {
+        h1::serve(
+            (server_read, server_write),
+            Rc::new(Default::default()),
+            RollMut::alloc()?,
+            driver,
+        )
+        .await?;
+        Ok::<_, BX>(())
+    });
+
+    client_write.write_all_owned(req).await.unwrap();
+    drop(client_write);
+
+    let mut out = vec![];
+    let mut buf = vec![0u8; 1024];
+    loop {
+        let res;
+        (res, buf) = client_read.read_owned(buf).await;
+        let n = res.unwrap();
+        if n == 0 {
+            break;
+        }
+        out.extend_from_slice(&buf[..n]);
+    }
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn forwards_to_upstream() {
+    loona::buffet::start(async move {
+        let ln = loona::buffet::net::TcpListener::bind("127.0.0.1:0".parse()?).await?;
+        let upstream = ln.local_addr()?;
+        let mut server = Server::new(EchoDriver);
+        server.listen(ln, Proto::H1);
+        let handle = server.handle();
+        let server_fut = loona::buffet::spawn(server.run());
+
+        let res = roundtrip(
+            ProxyDriver::new(upstream),
+            "POST /echo?q=1 HTTP/1.1\r\n\
+             host: example.org\r\n\
+             connection: x-secret\r\n\
+             x-secret: 1\r\n\
+             transfer-encoding: chunked\r\n\
+             \r\n\
+             5\r\nhello\r\n0\r\n\r\n",
+        )
+        .await;
+
+        assert!(res.starts_with("HTTP/1.1 200 "), "{res}");
+        let (res_head, res_body) = res.split_once("\r\n\r\n").expect(&res);
+        assert!(!res_head.contains("x-upstream-secret"), "{res}");
+        assert!(res_body.contains("host: example.org\n"), "{res}");
+        assert!(
+            res_body.contains("forwarded: for=unknown;host=\"example.org\";proto=http\n"),
+            "{res}"
+        );
+        assert!(
+            res_body.contains("x-forwarded-host: example.org\n"),
+            "{res}"
+        );
+        assert!(res_body.contains("x-forwarded-proto: http\n"), "{res}");
+        assert!(!res_body.contains("x-secret"), "{res}");
+        assert!(res_body.contains("hello"), "{res}");
+
+        handle.shutdown(Duration::from_secs(1));
+        tokio::time::timeout(Duration::from_secs(5), server_fut)
+            .await
+            .bx()?
+            .bx()?;
+
+        Ok::<_, BX>(())
+    })
+    .unwrap()
+}
+
+#[test]
+fn reuses_upstream_connections() {
+    loona::buffet::start(async move {
+        let ln = loona::buffet::net::TcpListener::bind("127.0.0.1:0".parse()?).await?;
+        let upstream = ln.local_addr()?;
+        let mut server = Server::new(EchoDriver);
+        server.listen(ln, Proto::H1);
+        let handle = server.handle();
+        let server_fut = loona::buffet::spawn(server.run());
+
+        let driver = ProxyDriver::new(upstream);
+        for _ in 0..3 {
+            let res = roundtrip(
+                driver.clone(),
+                "GET / HTTP/1.1\r\nhost: example.org\r\n\r\n",
+            )
+            .await;
+            assert!(res.starts_with("HTTP/1.1 200 "), "{res}");
+        }
+        assert_eq!(handle.stats().connections_accepted, 1);
+        assert_eq!(driver.pool().idle_count(&upstream), 1);
+
+        handle.shutdown(Duration::from_secs(1));
+        tokio::time::timeout(Duration::from_secs(5), server_fut)
+            .await
+            .bx()?
+            .bx()?;
+
+        Ok::<_, BX>(())
+    })
+    .unwrap()
+}
+
+#[test]
+fn unreachable_upstream_is_bad_gateway() {
+    loona::buffet::start(async move {
+        // bind then drop, so nothing listens there
+        let upstream: SocketAddr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let driver = ProxyDriver::new(upstream).with_conf(ProxyConf {
+            connect_timeout: Duration::from_secs(5),
+            ..Default::default()
+        });
+        let res = roundtrip(
+            driver.clone(),
+            "GET / HTTP/1.1\r\nhost: example.org\r\n\r\n",
+        )
+        .await;
+        assert!(res.starts_with("HTTP/1.1 502 "), "{res}");
+
+        // no more connection attempts for a while
+        assert!(!driver.pool().is_healthy(&upstream));
+        let res = roundtrip(driver, "GET / HTTP/1.1\r\nhost: example.org\r\n\r\n").await;
+        assert!(res.starts_with("HTTP/1.1 502 "), "{res}");
+    })
+}
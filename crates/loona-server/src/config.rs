@@ -0,0 +1,895 @@
+//! The configuration file: which listeners to open, what they speak, and
+//! where their requests go.
+//!
+//! ```toml
+//! shutdown_grace_period = "30s"
+//!
+//! [log]
+//! filter = "info,loona=debug"
+//!
+//! [[listener]]
+//! address = "0.0.0.0:443"
+//! proto = "h2"
+//! preset = "edge"
+//!
+//! [listener.tls]
+//! cert_chain = "/etc/tls/fullchain.pem"
+//! private_key = "/etc/tls/key.pem"
+//!
+//! [listener.h2]
+//! max_streams = 200
+//! request_timeout = "30s"
+//!
+//! [listener.proxy]
+//! upstream = "127.0.0.1:8080"
+//! response_timeout = "10s"
+//!
+//! [[listener]]
+//! address = "unix:/run/loona/static.sock"
+//!
+//! [listener.files]
+//! root = "/srv/www"
+//! ```
+//!
+//! Every listener has an `address`, either `ip:port` or `unix:/path`, and
+//! either a `[listener.proxy]` table (requests are forwarded to an upstream,
+//! cf. [loona_proxy]) or a `[listener.files]` table (files are served from a
+//! directory, cf. [loona::files]).
+//!
+//! `proto` (`h1`, the default, or `h2` with prior knowledge) is what
+//! connections speak unless they negotiated something else with TLS ALPN.
+//! `preset` (`default`, `edge` or `internal`) picks the HTTP/1.1 and HTTP/2
+//! settings to start from, cf. [loona::h1::ServerConf::edge], and keys in
+//! `[listener.h1]` and `[listener.h2]` override them: they're named after
+//! the fields of [loona::h1::ServerConf] and [loona::h2::ServerConf].
+//!
+//! Durations are a number of seconds, or a string with a unit: `"500ms"`,
+//! `"10s"`, `"5m"`, `"1h"`. Optional timeouts can be turned off with
+//! `false`. Sizes are a number of bytes, or a string with a unit: `"16KiB"`,
+//! `"1MiB"`, `"1GiB"`. Relative paths are relative to the configuration
+//! file.
+//!
+//! Mistakes are reported with the key they're about, e.g.
+//! `listener[0].h2.max_frame_size`, and so are keys that don't mean
+//! anything: a typo doesn't silently fall back to the default.
+
+use std::{
+    fmt, io,
+    net::{SocketAddr, ToSocketAddrs},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use loona::{
+    error::ConfError, h1, h2, memory::MemoryLimits, server::Proto, tls::CertFiles,
+    validation::Validation,
+};
+use loona_proxy::ProxyConf;
+use toml_edit::{Document, Item, TableLike, Value};
+
+/// A whole server: one [loona::server::Server] per listener
+pub struct Config {
+    /// Which logs to print, in `RUST_LOG` syntax, cf.
+    /// [tracing_subscriber::filter::Targets]. `RUST_LOG` wins if it's set.
+    pub log_filter: Option<String>,
+
+    /// How long connections get to finish on their own once we're asked to
+    /// shut down, cf. [loona::server::ServerHandle::shutdown]
+    pub shutdown_grace_period: Duration,
+
+    pub listeners: Vec<ListenerConfig>,
+}
+
+pub struct ListenerConfig {
+    pub address: Address,
+
+    /// What connections speak, unless they negotiated something else with
+    /// ALPN
+    pub proto: Proto,
+
+    /// If set, connections are TLS (over kTLS, so Linux only)
+    pub tls: Option<TlsConfig>,
+
+    pub h1: h1::ServerConf,
+    pub h2: h2::ServerConf,
+
+    /// For this listener's connections: `global` is for all of them
+    /// together, cf. [loona::memory]
+    pub memory: MemoryLimits,
+
+    pub handler: Handler,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Address {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Address::Tcp(addr) => write!(f, "{addr}"),
+            Address::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub files: CertFiles,
+
+    /// How often to check whether certificate files changed, cf.
+    /// [loona::tls::CertResolver::watch]
+    pub reload_interval: Duration,
+
+    /// How long clients get to complete the handshake before we hang up
+    pub handshake_timeout: Duration,
+
+    /// How much TLS 1.3 early data we accept from clients that resume a
+    /// session, cf. [loona::ConnectionInfo::early_data_len]. Zero disables
+    /// it.
+    pub max_early_data: u32,
+}
+
+/// Where a listener's requests go
+#[derive(Debug, Clone)]
+pub enum Handler {
+    Proxy {
+        upstream: SocketAddr,
+        conf: ProxyConf,
+    },
+    Files {
+        root: PathBuf,
+        prefix: String,
+        index_file: Option<String>,
+    },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("could not read {}: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+
+    #[error("{0}")]
+    Syntax(#[from] toml_edit::TomlError),
+
+    /// `key` is where the value is, e.g. `listener[0].h2.max_streams`
+    #[error("{key}: {message}")]
+    Invalid { key: String, message: String },
+}
+
+type Result<T> = std::result::Result<T, ConfigError>;
+
+impl Config {
+    /// Reads and checks the configuration file at `path`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_owned(),
+            source,
+        })?;
+        Self::parse(&text, path.parent().unwrap_or(Path::new(".")))
+    }
+
+    /// Parses and checks a configuration, whose relative paths are relative
+    /// to `base_dir`
+    pub fn parse(text: &str, base_dir: &Path) -> Result<Self> {
+        let doc: Document = text.parse()?;
+        let mut root = Section::new(String::new(), doc.as_table());
+
+        let log_filter = match root.section("log")? {
+            Some(mut log) => {
+                let filter = log.string("filter")?.map(str::to_owned);
+                log.finish()?;
+                filter
+            }
+            None => None,
+        };
+        let shutdown_grace_period = root
+            .duration("shutdown_grace_period")?
+            .unwrap_or(Duration::from_secs(30));
+
+        let mut listeners = vec![];
+        for (i, table) in root.tables("listener")?.into_iter().enumerate() {
+            let section = Section::new(format!("listener[{i}]"), table);
+            listeners.push(listener(section, base_dir)?);
+        }
+        if listeners.is_empty() {
+            return Err(root.invalid("listener", "at least one listener is needed"));
+        }
+        root.finish()?;
+
+        Ok(Self {
+            log_filter,
+            shutdown_grace_period,
+            listeners,
+        })
+    }
+}
+
+fn listener(mut s: Section<'_>, base_dir: &Path) -> Result<ListenerConfig> {
+    let address = s.required_string("address")?;
+    let address = match address.strip_prefix("unix:") {
+        Some(path) => Address::Unix(base_dir.join(path)),
+        None => Address::Tcp(
+            address
+                .parse()
+                .map_err(|_| s.invalid("address", "expected `ip:port` or `unix:/path`"))?,
+        ),
+    };
+
+    let proto = match s.string("proto")? {
+        None | Some("h1") => Proto::H1,
+        Some("h2") => Proto::H2,
+        Some(_) => return Err(s.invalid("proto", "expected \"h1\" or \"h2\"")),
+    };
+
+    let (h1_preset, h2_preset) = match s.string("preset")? {
+        None | Some("default") => (h1::ServerConf::default(), h2::ServerConf::default()),
+        Some("edge") => (h1::ServerConf::edge(), h2::ServerConf::edge()),
+        Some("internal") => (h1::ServerConf::internal(), h2::ServerConf::internal()),
+        Some(_) => {
+            return Err(s.invalid("preset", "expected \"default\", \"edge\" or \"internal\""))
+        }
+    };
+    let h1 = match s.section("h1")? {
+        Some(section) => h1_conf(section, h1_preset)?,
+        None => h1_preset,
+    };
+    let h2 = match s.section("h2")? {
+        Some(section) => h2_conf(section, h2_preset)?,
+        None => h2_preset,
+    };
+
+    let tls = match s.section("tls")? {
+        Some(_) if matches!(address, Address::Unix(_)) => {
+            return Err(s.invalid("tls", "TLS is only supported on TCP listeners"))
+        }
+        Some(section) => Some(tls_config(section, base_dir)?),
+        None => None,
+    };
+
+    let mut memory = MemoryLimits::default();
+    if let Some(mut section) = s.section("memory")? {
+        if let Some(limit) = section.size("per_connection")? {
+            memory.per_connection = limit;
+        }
+        if let Some(limit) = section.size("global")? {
+            memory.global = limit;
+        }
+        section.finish()?;
+    }
+
+    let (proxy, files) = (s.section("proxy")?, s.section("files")?);
+    // a typo in a table name is better reported as such
+    s.finish()?;
+    let handler = match (proxy, files) {
+        (Some(section), None) => proxy_handler(section)?,
+        (None, Some(section)) => files_handler(section, base_dir)?,
+        (Some(_), Some(_)) => return Err(s.invalid("files", "conflicts with `proxy`")),
+        (None, None) => return Err(s.invalid_here("expected a `proxy` or a `files` table")),
+    };
+
+    Ok(ListenerConfig {
+        address,
+        proto,
+        tls,
+        h1,
+        h2,
+        memory,
+        handler,
+    })
+}
+
+fn h1_conf(mut s: Section<'_>, preset: h1::ServerConf) -> Result<h1::ServerConf> {
+    let mut b = preset.into_builder();
+    if let Some(len) = s.size("max_http_header_len")? {
+        b = b.max_http_header_len(len);
+    }
+    if let Some(len) = s.size("max_header_record_len")? {
+        b = b.max_header_record_len(len);
+    }
+    if let Some(records) = s.integer("max_header_records")? {
+        b = b.max_header_records(records);
+    }
+    if let Some(enabled) = s.bool("date_header")? {
+        b = b.date_header(enabled);
+    }
+    if let Some(value) = s.string("server_header")? {
+        b = b.server_header(Some(value.as_bytes().to_vec().into()));
+    }
+    if let Some(validation) = s.validation("validation")? {
+        b = b.validation(validation);
+    }
+    if let Some(enabled) = s.bool("reject_unsafe_early_data")? {
+        b = b.reject_unsafe_early_data(enabled);
+    }
+    if let Some(enabled) = s.bool("preserve_header_case")? {
+        b = b.preserve_header_case(enabled);
+    }
+    if let Some(timeout) = s.optional_duration("request_timeout")? {
+        b = b.request_timeout(timeout);
+    }
+    s.finish()?;
+    b.build().map_err(|e| s.conf_error(e))
+}
+
+fn h2_conf(mut s: Section<'_>, preset: h2::ServerConf) -> Result<h2::ServerConf> {
+    let mut b = preset.into_builder();
+    match s.get("max_streams") {
+        None => {}
+        Some(item) if item.as_bool() == Some(false) => b = b.max_streams(None),
+        Some(_) => b = b.max_streams(s.integer("max_streams")?),
+    }
+    if let Some(enabled) = s.bool("date_header")? {
+        b = b.date_header(enabled);
+    }
+    if let Some(value) = s.string("server_header")? {
+        b = b.server_header(Some(value.as_bytes().to_vec().into()));
+    }
+    if let Some(enabled) = s.bool("enable_connect_protocol")? {
+        b = b.enable_connect_protocol(enabled);
+    }
+    if let Some(interval) = s.optional_duration("keepalive_interval")? {
+        b = b.keepalive_interval(interval);
+    }
+    if let Some(timeout) = s.duration("keepalive_timeout")? {
+        b = b.keepalive_timeout(timeout);
+    }
+    if let Some(timeout) = s.optional_duration("idle_timeout")? {
+        b = b.idle_timeout(timeout);
+    }
+    if let Some(timeout) = s.optional_duration("request_timeout")? {
+        b = b.request_timeout(timeout);
+    }
+    if let Some(size) = s.size("initial_stream_window_size")? {
+        b = b.initial_stream_window_size(size);
+    }
+    if let Some(size) = s.size("connection_window_size")? {
+        b = b.connection_window_size(size);
+    }
+    if let Some(size) = s.size("max_frame_size")? {
+        b = b.max_frame_size(size);
+    }
+    if let Some(size) = s.size("max_header_list_size")? {
+        b = b.max_header_list_size(size);
+    }
+    match s.get("max_resets") {
+        None => {}
+        Some(item) if item.as_bool() == Some(false) => b = b.max_resets(None),
+        Some(_) => b = b.max_resets(s.integer("max_resets")?),
+    }
+    if let Some(window) = s.duration("reset_window")? {
+        b = b.reset_window(window);
+    }
+    if let Some(enabled) = s.bool("goaway_debug_data")? {
+        b = b.goaway_debug_data(enabled);
+    }
+    if let Some(validation) = s.validation("validation")? {
+        b = b.validation(validation);
+    }
+    if let Some(enabled) = s.bool("reject_unsafe_early_data")? {
+        b = b.reject_unsafe_early_data(enabled);
+    }
+    s.finish()?;
+    b.build().map_err(|e| s.conf_error(e))
+}
+
+fn tls_config(mut s: Section<'_>, base_dir: &Path) -> Result<TlsConfig> {
+    let mut files = CertFiles::new(
+        base_dir.join(s.required_string("cert_chain")?),
+        base_dir.join(s.required_string("private_key")?),
+    );
+    if let Some(path) = s.string("ocsp_response")? {
+        files = files.with_ocsp_response(base_dir.join(path));
+    }
+    let conf = TlsConfig {
+        files,
+        reload_interval: s
+            .duration("reload_interval")?
+            .unwrap_or(Duration::from_secs(60)),
+        handshake_timeout: s
+            .duration("handshake_timeout")?
+            .unwrap_or(Duration::from_secs(10)),
+        max_early_data: s.size("max_early_data")?.unwrap_or(0),
+    };
+    for (key, value) in [
+        ("reload_interval", conf.reload_interval),
+        ("handshake_timeout", conf.handshake_timeout),
+    ] {
+        if value.is_zero() {
+            return Err(s.invalid(key, "must not be zero"));
+        }
+    }
+    s.finish()?;
+    Ok(conf)
+}
+
+fn proxy_handler(mut s: Section<'_>) -> Result<Handler> {
+    let upstream = s.required_string("upstream")?;
+    // resolved once: the proxy connects to a single address
+    let upstream = upstream
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .ok_or_else(|| s.invalid("upstream", format!("could not resolve {upstream:?}")))?;
+
+    let mut conf = ProxyConf::default();
+    if let Some(timeout) = s.duration("connect_timeout")? {
+        conf.connect_timeout = timeout;
+    }
+    if let Some(timeout) = s.duration("response_timeout")? {
+        conf.response_timeout = timeout;
+    }
+    s.finish()?;
+    Ok(Handler::Proxy { upstream, conf })
+}
+
+fn files_handler(mut s: Section<'_>, base_dir: &Path) -> Result<Handler> {
+    let root = base_dir.join(s.required_string("root")?);
+    let prefix = s.string("prefix")?.unwrap_or("/").to_owned();
+    if !prefix.starts_with('/') {
+        return Err(s.invalid("prefix", "must start with `/`"));
+    }
+    let index_file = match s.get("index_file") {
+        Some(item) if item.as_bool() == Some(false) => None,
+        Some(_) => s.string("index_file")?.map(str::to_owned),
+        None => Some("index.html".to_owned()),
+    };
+    s.finish()?;
+    Ok(Handler::Files {
+        root,
+        prefix,
+        index_file,
+    })
+}
+
+/// A table being read: remembers which keys were looked at, so that the
+/// others can be reported, and where it is, for errors.
+struct Section<'a> {
+    path: String,
+    table: &'a dyn TableLike,
+    known: Vec<&'static str>,
+}
+
+impl<'a> Section<'a> {
+    fn new(path: String, table: &'a dyn TableLike) -> Self {
+        Self {
+            path,
+            table,
+            known: vec![],
+        }
+    }
+
+    fn key(&self, name: &str) -> String {
+        if self.path.is_empty() {
+            name.to_owned()
+        } else {
+            format!("{}.{name}", self.path)
+        }
+    }
+
+    fn invalid(&self, name: &str, message: impl fmt::Display) -> ConfigError {
+        ConfigError::Invalid {
+            key: self.key(name),
+            message: message.to_string(),
+        }
+    }
+
+    fn invalid_here(&self, message: impl fmt::Display) -> ConfigError {
+        ConfigError::Invalid {
+            key: self.path.clone(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Blames the setting a [ConfError] is about, since keys are named
+    /// after settings
+    fn conf_error(&self, e: ConfError) -> ConfigError {
+        match &e {
+            ConfError::OutOfRange { setting, .. }
+            | ConfError::Zero { setting }
+            | ConfError::Exceeds { setting, .. } => self.invalid(setting, e),
+            _ => self.invalid_here(e),
+        }
+    }
+
+    fn get(&mut self, name: &'static str) -> Option<&'a Item> {
+        self.known.push(name);
+        self.table.get(name)
+    }
+
+    fn value(&mut self, name: &'static str, what: &str) -> Result<Option<&'a Value>> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(item) => match item.as_value() {
+                Some(value) => Ok(Some(value)),
+                None => Err(self.invalid(name, format!("expected {what}"))),
+            },
+        }
+    }
+
+    fn string(&mut self, name: &'static str) -> Result<Option<&'a str>> {
+        match self.value(name, "a string")? {
+            None => Ok(None),
+            Some(value) => match value.as_str() {
+                Some(s) => Ok(Some(s)),
+                None => Err(self.invalid(name, "expected a string")),
+            },
+        }
+    }
+
+    fn required_string(&mut self, name: &'static str) -> Result<&'a str> {
+        self.string(name)?
+            .ok_or_else(|| self.invalid(name, "missing"))
+    }
+
+    fn bool(&mut self, name: &'static str) -> Result<Option<bool>> {
+        match self.value(name, "a boolean")? {
+            None => Ok(None),
+            Some(value) => match value.as_bool() {
+                Some(b) => Ok(Some(b)),
+                None => Err(self.invalid(name, "expected a boolean")),
+            },
+        }
+    }
+
+    fn integer<T: TryFrom<i64>>(&mut self, name: &'static str) -> Result<Option<T>> {
+        match self.value(name, "an integer")? {
+            None => Ok(None),
+            Some(value) => match value.as_integer() {
+                Some(n) => T::try_from(n)
+                    .map(Some)
+                    .map_err(|_| self.invalid(name, format!("{n} is out of range"))),
+                None => Err(self.invalid(name, "expected an integer")),
+            },
+        }
+    }
+
+    /// A number of seconds, or a string with a unit
+    fn duration(&mut self, name: &'static str) -> Result<Option<Duration>> {
+        const EXPECTED: &str = "expected a duration, e.g. 10 or \"500ms\", \"10s\", \"5m\", \"1h\"";
+        match self.value(name, EXPECTED)? {
+            None => Ok(None),
+            Some(value) => {
+                let duration = match (value.as_integer(), value.as_str()) {
+                    (Some(secs), _) => u64::try_from(secs).ok().map(Duration::from_secs),
+                    (_, Some(s)) => parse_duration(s),
+                    _ => None,
+                };
+                duration
+                    .map(Some)
+                    .ok_or_else(|| self.invalid(name, EXPECTED))
+            }
+        }
+    }
+
+    /// Like [Section::duration], `false` turning it off
+    fn optional_duration(&mut self, name: &'static str) -> Result<Option<Option<Duration>>> {
+        match self.get(name) {
+            Some(item) if item.as_bool() == Some(false) => Ok(Some(None)),
+            _ => Ok(self.duration(name)?.map(Some)),
+        }
+    }
+
+    /// A number of bytes, or a string with a unit
+    fn size<T: TryFrom<u64>>(&mut self, name: &'static str) -> Result<Option<T>> {
+        const EXPECTED: &str = "expected a size, e.g. 16384 or \"16KiB\", \"1MiB\", \"1GiB\"";
+        match self.value(name, EXPECTED)? {
+            None => Ok(None),
+            Some(value) => {
+                let size = match (value.as_integer(), value.as_str()) {
+                    (Some(n), _) => u64::try_from(n).ok(),
+                    (_, Some(s)) => parse_size(s),
+                    _ => None,
+                }
+                .ok_or_else(|| self.invalid(name, EXPECTED))?;
+                T::try_from(size)
+                    .map(Some)
+                    .map_err(|_| self.invalid(name, format!("{size} is out of range")))
+            }
+        }
+    }
+
+    /// `"strict"` or `"lenient"`, or a table of rules to override, on top
+    /// of the `profile` it names (strict by default)
+    fn validation(&mut self, name: &'static str) -> Result<Option<Validation>> {
+        let profile = |s: &Section<'_>, key: &str, profile: &str| match profile {
+            "strict" => Ok(Validation::strict()),
+            "lenient" => Ok(Validation::lenient()),
+            _ => Err(s.invalid(key, "expected \"strict\" or \"lenient\"")),
+        };
+
+        let Some(item) = self.get(name) else {
+            return Ok(None);
+        };
+        if let Some(s) = item.as_str() {
+            return profile(self, name, s).map(Some);
+        }
+        let Some(table) = item.as_table_like() else {
+            return Err(self.invalid(name, "expected a string or a table"));
+        };
+
+        let mut s = Section::new(self.key(name), table);
+        let mut validation = match s.string("profile")? {
+            Some(p) => profile(&s, "profile", p)?,
+            None => Validation::strict(),
+        };
+        for (rule, value) in [
+            ("allow_obs_fold", &mut validation.allow_obs_fold),
+            (
+                "allow_content_length_with_transfer_encoding",
+                &mut validation.allow_content_length_with_transfer_encoding,
+            ),
+            (
+                "allow_spaces_in_target",
+                &mut validation.allow_spaces_in_target,
+            ),
+            (
+                "allow_uppercase_header_names",
+                &mut validation.allow_uppercase_header_names,
+            ),
+        ] {
+            if let Some(allowed) = s.bool(rule)? {
+                *value = allowed;
+            }
+        }
+        s.finish()?;
+        Ok(Some(validation))
+    }
+
+    fn section(&mut self, name: &'static str) -> Result<Option<Section<'a>>> {
+        match self.get(name) {
+            None => Ok(None),
+            Some(item) => match item.as_table_like() {
+                Some(table) => Ok(Some(Section::new(self.key(name), table))),
+                None => Err(self.invalid(name, "expected a table")),
+            },
+        }
+    }
+
+    /// `[[name]]` tables, or an array of inline tables
+    fn tables(&mut self, name: &'static str) -> Result<Vec<&'a dyn TableLike>> {
+        let Some(item) = self.get(name) else {
+            return Ok(vec![]);
+        };
+        if let Some(tables) = item.as_array_of_tables() {
+            return Ok(tables.iter().map(|t| t as &dyn TableLike).collect());
+        }
+        item.as_array()
+            .and_then(|array| {
+                array
+                    .iter()
+                    .map(|v| v.as_inline_table().map(|t| t as &dyn TableLike))
+                    .collect()
+            })
+            .ok_or_else(|| self.invalid(name, "expected an array of tables"))
+    }
+
+    /// Errors out if there are keys nothing looked at
+    fn finish(&self) -> Result<()> {
+        match self.table.iter().find(|(key, _)| !self.known.contains(key)) {
+            Some((key, _)) => Err(self.invalid(key, "unknown key")),
+            None => Ok(()),
+        }
+    }
+}
+
+fn split_unit(s: &str) -> Option<(u64, &str)> {
+    let s = s.trim();
+    let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let n = s[..digits].parse().ok()?;
+    Some((n, s[digits..].trim_start()))
+}
+
+fn parse_duration(s: &str) -> Option<Duration> {
+    let (n, unit) = split_unit(s)?;
+    let secs = match unit {
+        "ms" => return Some(Duration::from_millis(n)),
+        "s" | "" => n,
+        "m" => n.checked_mul(60)?,
+        "h" => n.checked_mul(60 * 60)?,
+        _ => return None,
+    };
+    Some(Duration::from_secs(secs))
+}
+
+fn parse_size(s: &str) -> Option<u64> {
+    let (n, unit) = split_unit(s)?;
+    let shift = match unit {
+        "B" | "" => 0,
+        "KiB" => 10,
+        "MiB" => 20,
+        "GiB" => 30,
+        _ => return None,
+    };
+    n.checked_mul(1 << shift)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, time::Duration};
+
+    use loona::server::Proto;
+
+    use super::{Address, Config, ConfigError, Handler};
+
+    fn parse(text: &str) -> Result<Config, ConfigError> {
+        Config::parse(text, Path::new("/etc/loona"))
+    }
+
+    /// The key an invalid configuration is blamed on
+    fn blamed(text: &str) -> String {
+        match parse(text) {
+            Err(ConfigError::Invalid { key, .. }) => key,
+            Err(e) => panic!("expected an invalid key, got {e}"),
+            Ok(_) => panic!("expected an invalid key, got a valid configuration"),
+        }
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config = parse(
+            r#"
+            shutdown_grace_period = "5s"
+
+            [log]
+            filter = "debug"
+
+            [[listener]]
+            address = "127.0.0.1:8443"
+            proto = "h2"
+            preset = "edge"
+
+            [listener.tls]
+            cert_chain = "tls/cert.pem"
+            private_key = "/secrets/key.pem"
+            max_early_data = "16KiB"
+
+            [listener.h1]
+            validation = { profile = "lenient", allow_obs_fold = false }
+
+            [listener.h2]
+            max_streams = false
+            idle_timeout = false
+            request_timeout = "1m"
+            initial_stream_window_size = "1MiB"
+            connection_window_size = "16MiB"
+            max_frame_size = "64KiB"
+            max_resets = 100
+            reset_window = "1m"
+
+            [listener.memory]
+            per_connection = "4MiB"
+
+            [listener.proxy]
+            upstream = "127.0.0.1:8080"
+            connect_timeout = "500ms"
+
+            [[listener]]
+            address = "unix:sock/static.sock"
+
+            [listener.files]
+            root = "/srv/www"
+            prefix = "/static"
+            index_file = false
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.log_filter.as_deref(), Some("debug"));
+        assert_eq!(config.shutdown_grace_period, Duration::from_secs(5));
+        assert_eq!(config.listeners.len(), 2);
+
+        let edge = &config.listeners[0];
+        assert_eq!(
+            edge.address,
+            Address::Tcp("127.0.0.1:8443".parse().unwrap())
+        );
+        assert_eq!(edge.proto, Proto::H2);
+        let tls = edge.tls.as_ref().unwrap();
+        assert_eq!(tls.files.cert_chain, Path::new("/etc/loona/tls/cert.pem"));
+        assert_eq!(tls.files.private_key, Path::new("/secrets/key.pem"));
+        assert_eq!(tls.max_early_data, 16 * 1024);
+        // from the preset
+        assert_eq!(edge.h1.max_http_header_len, 16 * 1024);
+        assert!(edge.h1.validation.allow_spaces_in_target);
+        assert!(!edge.h1.validation.allow_obs_fold);
+        assert_eq!(edge.h2.max_streams, None);
+        assert_eq!(edge.h2.keepalive_interval, Some(Duration::from_secs(60)));
+        assert_eq!(edge.h2.idle_timeout, None);
+        assert_eq!(edge.h2.request_timeout, Some(Duration::from_secs(60)));
+        assert_eq!(edge.h2.max_frame_size, 64 * 1024);
+        assert_eq!(edge.h2.max_resets, Some(100));
+        assert_eq!(edge.h2.reset_window, Duration::from_secs(60));
+        assert_eq!(edge.memory.per_connection, 4 * 1024 * 1024);
+        assert_eq!(edge.memory.global, usize::MAX);
+        match &edge.handler {
+            Handler::Proxy { upstream, conf } => {
+                assert_eq!(upstream.port(), 8080);
+                assert_eq!(conf.connect_timeout, Duration::from_millis(500));
+                assert_eq!(conf.response_timeout, Duration::from_secs(60));
+            }
+            _ => panic!("expected a proxy"),
+        }
+
+        let files = &config.listeners[1];
+        assert_eq!(
+            files.address,
+            Address::Unix("/etc/loona/sock/static.sock".into())
+        );
+        assert_eq!(files.proto, Proto::H1);
+        assert!(files.tls.is_none());
+        match &files.handler {
+            Handler::Files {
+                root,
+                prefix,
+                index_file,
+            } => {
+                assert_eq!(root, Path::new("/srv/www"));
+                assert_eq!(prefix, "/static");
+                assert_eq!(index_file, &None);
+            }
+            _ => panic!("expected files"),
+        }
+    }
+
+    #[test]
+    fn test_config_errors_point_at_keys() {
+        let listener = |extra: &str| {
+            format!(
+                "[[listener]]\naddress = \"127.0.0.1:8080\"\n[listener.files]\nroot = \"www\"\n{extra}"
+            )
+        };
+
+        assert!(matches!(parse("[[listener]"), Err(ConfigError::Syntax(_))));
+        assert_eq!(blamed(""), "listener");
+        assert_eq!(
+            blamed("shutdown_grace_period = \"soon\""),
+            "shutdown_grace_period"
+        );
+        assert_eq!(
+            blamed("[[listener]]\naddress = \"localhost\"\n[listener.files]\nroot = \"www\""),
+            "listener[0].address"
+        );
+        assert_eq!(
+            blamed("[[listener]]\naddress = \"127.0.0.1:8080\""),
+            "listener[0]"
+        );
+        assert_eq!(
+            blamed(&listener("indx_file = \"a.html\"")),
+            "listener[0].files.indx_file"
+        );
+        assert_eq!(
+            blamed(&listener("[listener.h2]\nmax_streams = -1")),
+            "listener[0].h2.max_streams"
+        );
+        assert_eq!(
+            blamed(&listener("[listener.h1]\nrequest_timeout = \"10 years\"")),
+            "listener[0].h1.request_timeout"
+        );
+        assert_eq!(
+            blamed(&listener(
+                "[listener.h1]\nvalidation = { allow_all = true }"
+            )),
+            "listener[0].h1.validation.allow_all"
+        );
+
+        // caught by loona's own checks
+        let err = parse(&listener("[listener.h2]\nmax_frame_size = \"1MiB\""))
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "listener[0].h2.max_frame_size: max_frame_size (1048576) must not exceed initial_stream_window_size (65535)"
+        );
+
+        let tls = "[listener.tls]\ncert_chain = \"cert.pem\"\nprivate_key = \"key.pem\"";
+        assert!(parse(&listener(tls)).is_ok());
+        assert_eq!(
+            blamed(&listener(tls).replace("127.0.0.1:8080", "unix:/tmp/a.sock")),
+            "listener[0].tls"
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! A standalone server: listeners, what they speak and where their requests
+//! go all come from a configuration file, cf. [config].
+//!
+//! Every listener gets a [loona::server::Server] of its own, with its own
+//! HTTP/1.1 and HTTP/2 settings, memory limits, and handler: either a
+//! reverse proxy ([loona_proxy::ProxyDriver]) or a file server
+//! ([loona::files::FileServer]). [Servers] runs them all, and shuts them all
+//! down together.
+
+use std::{future::Future, net::SocketAddr, time::Duration};
+
+use b_x::BX;
+use buffet::net::{TcpListener, UnixListener};
+use config::{Address, Config, Handler};
+use loona::{
+    files::FileServer,
+    server::{Server, ServerHandle},
+    Body, Encoder, ExpectResponseHeaders, Request, Responder, ResponseDone, ServerDriver,
+};
+use loona_proxy::ProxyDriver;
+use tracing::debug;
+
+pub mod config;
+
+#[cfg(target_os = "linux")]
+mod tls;
+
+/// What a listener hands its requests to, cf. [Handler]
+enum Driver {
+    Proxy(ProxyDriver),
+    Files(FileServer),
+}
+
+impl<OurEncoder> ServerDriver<OurEncoder> for Driver
+where
+    OurEncoder: Encoder,
+{
+    type Error = BX;
+
+    async fn handle(
+        &self,
+        req: Request,
+        req_body: &mut impl Body,
+        respond: Responder<OurEncoder, ExpectResponseHeaders>,
+    ) -> Result<Responder<OurEncoder, ResponseDone>, Self::Error> {
+        match self {
+            Driver::Proxy(driver) => driver
+                .handle(req, req_body, respond)
+                .await
+                .map_err(BX::from_err),
+            Driver::Files(driver) => driver
+                .handle(req, req_body, respond)
+                .await
+                .map_err(BX::from_err),
+        }
+    }
+}
+
+/// A listener that couldn't be set up
+#[derive(Debug, thiserror::Error)]
+#[error("listener[{index}] ({address}): {source}")]
+pub struct BindError {
+    pub index: usize,
+    pub address: Address,
+    pub source: BX,
+}
+
+/// Every listener of a [Config], bound and ready to serve
+pub struct Servers {
+    servers: Vec<Server<Driver>>,
+    local_addrs: Vec<Option<SocketAddr>>,
+    shutdown_grace_period: Duration,
+}
+
+impl Servers {
+    /// Binds every listener. Must be called from within a buffet runtime.
+    pub async fn bind(config: Config) -> Result<Self, BindError> {
+        let mut servers = Self {
+            servers: vec![],
+            local_addrs: vec![],
+            shutdown_grace_period: config.shutdown_grace_period,
+        };
+
+        for (index, listener) in config.listeners.into_iter().enumerate() {
+            let driver = match listener.handler {
+                Handler::Proxy { upstream, conf } => {
+                    Driver::Proxy(ProxyDriver::new(upstream).with_conf(conf))
+                }
+                Handler::Files {
+                    root,
+                    prefix,
+                    index_file,
+                } => Driver::Files(
+                    FileServer::new(root)
+                        .with_prefix(prefix)
+                        .with_index_file(index_file),
+                ),
+            };
+            let mut server = Server::new(driver)
+                .with_h1_conf(listener.h1)
+                .with_h2_conf(listener.h2)
+                .with_memory_limits(listener.memory);
+
+            let bind_error = |source: BX| BindError {
+                index,
+                address: listener.address.clone(),
+                source,
+            };
+            let local_addr = match (&listener.address, &listener.tls) {
+                (Address::Tcp(addr), None) => {
+                    let tcp = TcpListener::bind(*addr)
+                        .await
+                        .map_err(|e| bind_error(e.into()))?;
+                    let local_addr = tcp.local_addr().ok();
+                    server.listen(tcp, listener.proto);
+                    local_addr
+                }
+                #[cfg(target_os = "linux")]
+                (Address::Tcp(addr), Some(tls)) => {
+                    let tls = tls::TlsListener::bind(*addr, tls)
+                        .map_err(|e| bind_error(BX::from_err(e)))?;
+                    let local_addr = tls.local_addr();
+                    server.listen(tls, listener.proto);
+                    local_addr
+                }
+                #[cfg(not(target_os = "linux"))]
+                (Address::Tcp(_), Some(_)) => {
+                    return Err(bind_error(BX::from_string(
+                        "TLS listeners need kTLS, which is Linux-only".to_owned(),
+                    )))
+                }
+                (Address::Unix(path), _) => {
+                    // what a previous run left behind
+                    _ = std::fs::remove_file(path);
+                    let unix = UnixListener::bind(path)
+                        .await
+                        .map_err(|e| bind_error(e.into()))?;
+                    server.listen(unix, listener.proto);
+                    None
+                }
+            };
+            debug!(%index, address = %listener.address, ?local_addr, "listening");
+
+            servers.servers.push(server);
+            servers.local_addrs.push(local_addr);
+        }
+        Ok(servers)
+    }
+
+    /// The address each TCP listener is bound to, in the order of the
+    /// configuration, e.g. to find out which port `127.0.0.1:0` got.
+    /// `None` for unix listeners.
+    pub fn local_addrs(&self) -> &[Option<SocketAddr>] {
+        &self.local_addrs
+    }
+
+    /// Handles to every server, e.g. to look at their stats
+    pub fn handles(&self) -> Vec<ServerHandle> {
+        self.servers.iter().map(Server::handle).collect()
+    }
+
+    /// Serves until `shutdown` completes, then gives connections the
+    /// configured grace period to finish, cf. [ServerHandle::shutdown]
+    pub async fn run_until(self, shutdown: impl Future<Output = ()>) {
+        let handles = self.handles();
+        let grace_period = self.shutdown_grace_period;
+        let servers: Vec<_> = self
+            .servers
+            .into_iter()
+            .map(|server| buffet::spawn(server.run()))
+            .collect();
+
+        shutdown.await;
+        debug!(?grace_period, "shutting down");
+        for handle in &handles {
+            handle.shutdown(grace_period);
+        }
+        for server in servers {
+            _ = server.await;
+        }
+    }
+}
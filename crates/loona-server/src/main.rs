@@ -0,0 +1,141 @@
+use std::{ffi::OsString, path::PathBuf};
+
+use loona_server::{config::Config, Servers};
+use tracing::Level;
+use tracing_subscriber::{filter::Targets, layer::SubscriberExt, util::SubscriberInitExt};
+
+#[derive(Debug)]
+struct Args {
+    /// the configuration file
+    config: PathBuf,
+
+    /// check the configuration, then exit
+    check: bool,
+}
+
+trait IntoStringResult {
+    fn into_string_result(self) -> eyre::Result<String>;
+}
+
+impl IntoStringResult for OsString {
+    fn into_string_result(self) -> eyre::Result<String> {
+        self.into_string()
+            .map_err(|_| eyre::eyre!("OsString contained invalid UTF-8"))
+    }
+}
+
+fn parse_args() -> eyre::Result<Args> {
+    let mut config = None;
+    let mut check = false;
+    let mut parser = lexopt::Parser::from_env();
+    while let Some(arg) = parser.next()? {
+        match arg {
+            lexopt::Arg::Long("config") | lexopt::Arg::Short('c') => {
+                config = Some(PathBuf::from(parser.value()?.into_string_result()?));
+            }
+            lexopt::Arg::Long("check") => {
+                check = true;
+            }
+            lexopt::Arg::Value(value) if config.is_none() => {
+                config = Some(PathBuf::from(value.into_string_result()?));
+            }
+            _ => return Err(arg.unexpected().into()),
+        }
+    }
+
+    let Some(config) = config else {
+        eyre::bail!("No configuration file given");
+    };
+    Ok(Args { config, check })
+}
+
+fn print_usage() {
+    eprintln!(
+        "Usage: loona-server [OPTIONS] <CONFIG>
+
+Options:
+    -c, --config <CONFIG>      The configuration file (TOML)
+    --check                    Check the configuration, then exit
+
+Logs are filtered with the configuration's [log] filter, or RUST_LOG if it's
+set. SIGINT or SIGTERM shut the server down gracefully.
+
+Examples:
+    loona-server /etc/loona/server.toml
+    loona-server --check server.toml
+"
+    );
+}
+
+fn main() -> eyre::Result<()> {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("Failed to parse arguments: {e}");
+            print_usage();
+            std::process::exit(1);
+        }
+    };
+
+    let config = match Config::load(&args.config) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Invalid configuration: {e}");
+            std::process::exit(1);
+        }
+    };
+    if args.check {
+        eprintln!("{}: OK", args.config.display());
+        return Ok(());
+    }
+
+    setup_tracing(config.log_filter.as_deref())?;
+    buffet::start(async move {
+        // BX isn't Send, eyre wants it to be
+        let servers = Servers::bind(config)
+            .await
+            .map_err(|e| eyre::eyre!("Could not listen: {e}"))?;
+        servers.run_until(shutdown_signal()).await;
+        Ok(())
+    })
+}
+
+/// Completes on SIGINT or SIGTERM
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = sigterm.recv() => {}
+                }
+                return;
+            }
+            Err(e) => tracing::warn!("can't listen for SIGTERM: {e}"),
+        }
+    }
+    _ = tokio::signal::ctrl_c().await;
+}
+
+fn setup_tracing(filter: Option<&str>) -> eyre::Result<()> {
+    let targets = match std::env::var("RUST_LOG").ok().as_deref().or(filter) {
+        Some(filter) => filter
+            .parse::<Targets>()
+            .map_err(|e| eyre::eyre!("Invalid log filter {filter:?}: {e}"))?,
+        None => Targets::new().with_default(Level::INFO),
+    };
+
+    let fmt_layer = tracing_subscriber::fmt::layer()
+        .with_ansi(true)
+        .with_file(false)
+        .with_line_number(false);
+
+    tracing_subscriber::registry()
+        .with(targets)
+        .with(fmt_layer)
+        .init();
+    Ok(())
+}
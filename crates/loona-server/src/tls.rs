@@ -0,0 +1,256 @@
+//! TLS listeners: rustls does the handshake, then hands the connection over
+//! to kTLS, so that it's a regular TCP socket as far as io_uring is
+//! concerned.
+
+use std::{
+    io::{self, Read},
+    mem::ManuallyDrop,
+    net::SocketAddr,
+    os::fd::{AsRawFd, FromRawFd},
+    sync::Arc,
+};
+
+use b_x::BxForResults;
+use buffet::{
+    bufpool::{BufResult, IoBufMut},
+    net::TcpStream,
+    IntoHalves, ReadOwned,
+};
+use ktls::CorkStream;
+use loona::{
+    client::{AlpnProtocol, ALPN_PROTOCOLS},
+    server::Listener,
+    tls::{CertError, CertResolver},
+    ConnectionInfo,
+};
+use rustls::{crypto::aws_lc_rs, ServerConfig};
+use tokio::{
+    sync::{mpsc, Mutex},
+    task::AbortHandle,
+};
+use tokio_rustls::TlsAcceptor;
+use tracing::debug;
+
+use crate::config::TlsConfig;
+
+/// How many handshaken connections can wait for the server to pick them up
+const ACCEPT_BACKLOG: usize = 128;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TlsError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+
+    #[error("{0}")]
+    Cert(#[from] CertError),
+
+    #[error("{0}")]
+    Rustls(#[from] rustls::Error),
+}
+
+type Accepted = io::Result<(TlsStream, ConnectionInfo)>;
+
+/// Accepts TCP connections and does their handshake in a task of its own,
+/// so that slow clients don't hold up the others. Certificates are
+/// reloaded when their files change.
+pub(crate) struct TlsListener {
+    accepted: Mutex<mpsc::Receiver<Accepted>>,
+    local_addr: Option<SocketAddr>,
+
+    /// The accept loop and the certificate watcher
+    tasks: Vec<AbortHandle>,
+}
+
+impl TlsListener {
+    pub(crate) fn bind(addr: SocketAddr, conf: &TlsConfig) -> Result<Self, TlsError> {
+        let provider = Arc::new(aws_lc_rs::default_provider());
+        let resolver = Arc::new(CertResolver::new(provider.clone()));
+        resolver.load(&conf.files)?;
+
+        let mut server_config = ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_cert_resolver(resolver.clone());
+        server_config.alpn_protocols = ALPN_PROTOCOLS.iter().map(|p| p.to_vec()).collect();
+        // kTLS needs the session keys
+        server_config.enable_secret_extraction = true;
+        server_config.max_early_data_size = conf.max_early_data;
+        let acceptor = TlsAcceptor::from(Arc::new(server_config));
+
+        let listener = std::net::TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let listener = tokio::net::TcpListener::from_std(listener)?;
+        let local_addr = listener.local_addr().ok();
+
+        let (tx, rx) = mpsc::channel(ACCEPT_BACKLOG);
+        let handshake_timeout = conf.handshake_timeout;
+        let accept_loop = buffet::spawn(async move {
+            loop {
+                let (stream, peer_addr) = match listener.accept().await {
+                    Ok(t) => t,
+                    Err(e) => {
+                        if tx.send(Err(e)).await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                };
+                let info = ConnectionInfo {
+                    peer_addr: Some(peer_addr),
+                    local_addr,
+                    ..Default::default()
+                };
+                let acceptor = acceptor.clone();
+                let tx = tx.clone();
+                buffet::spawn(async move {
+                    match tokio::time::timeout(handshake_timeout, handshake(acceptor, stream, info))
+                        .await
+                    {
+                        Ok(Ok(accepted)) => {
+                            _ = tx.send(Ok(accepted)).await;
+                        }
+                        Ok(Err(e)) => debug!(%peer_addr, %e, "TLS handshake failed"),
+                        Err(_) => debug!(%peer_addr, "TLS handshake timed out"),
+                    }
+                });
+            }
+        });
+
+        let files = conf.files.clone();
+        let reload_interval = conf.reload_interval;
+        let watcher = buffet::spawn(async move { resolver.watch(files, reload_interval).await });
+
+        Ok(Self {
+            accepted: Mutex::new(rx),
+            local_addr,
+            tasks: vec![accept_loop.abort_handle(), watcher.abort_handle()],
+        })
+    }
+
+    pub(crate) fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+}
+
+impl Drop for TlsListener {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+impl Listener for TlsListener {
+    type Stream = TlsStream;
+
+    async fn accept(&self) -> io::Result<(Self::Stream, ConnectionInfo)> {
+        match self.accepted.lock().await.recv().await {
+            Some(accepted) => accepted,
+            // the accept loop is gone
+            None => Err(io::ErrorKind::NotConnected.into()),
+        }
+    }
+}
+
+async fn handshake(
+    acceptor: TlsAcceptor,
+    stream: tokio::net::TcpStream,
+    info: ConnectionInfo,
+) -> b_x::Result<(TlsStream, ConnectionInfo)> {
+    let mut stream = acceptor.accept(CorkStream::new(stream)).await?;
+
+    // rustls keeps early data apart from the rest, and kTLS won't know
+    // about it: it goes first.
+    let mut prefix = vec![];
+    if let Some(mut early_data) = stream.get_mut().1.early_data() {
+        early_data.read_to_end(&mut prefix)?;
+    }
+    let early_data_len = prefix.len();
+    let alpn = stream
+        .get_ref()
+        .1
+        .alpn_protocol()
+        .and_then(AlpnProtocol::from_alpn);
+
+    let stream = ktls::config_ktls_server(stream).await.bx()?;
+    let (drained, stream) = stream.into_raw();
+    prefix.extend(drained.unwrap_or_default());
+    debug!(
+        ?alpn,
+        early_data_len,
+        prefix_len = prefix.len(),
+        "TLS handshake done, set up kTLS"
+    );
+
+    Ok((
+        TlsStream {
+            stream: to_uring_tcp_stream(stream)?,
+            prefix,
+        },
+        ConnectionInfo {
+            early_data_len,
+            alpn,
+            ..info
+        },
+    ))
+}
+
+/// tokio needs the socket to be "non-blocking" (as in: return EAGAIN),
+/// buffet needs it to be "blocking" (as in: let io_uring do the op async)
+fn to_uring_tcp_stream(stream: tokio::net::TcpStream) -> io::Result<TcpStream> {
+    {
+        let sock = ManuallyDrop::new(unsafe { socket2::Socket::from_raw_fd(stream.as_raw_fd()) });
+        sock.set_nonblocking(false)?;
+    }
+    let uring_stream = unsafe { TcpStream::from_raw_fd(stream.as_raw_fd()) };
+    std::mem::forget(stream);
+    Ok(uring_stream)
+}
+
+/// A kTLS connection, and what rustls read off of it before handing it over
+pub(crate) struct TlsStream {
+    stream: TcpStream,
+    prefix: Vec<u8>,
+}
+
+impl IntoHalves for TlsStream {
+    type Read = PrefixedRead;
+    type Write = <TcpStream as IntoHalves>::Write;
+
+    fn into_halves(self) -> (Self::Read, Self::Write) {
+        let (r, w) = self.stream.into_halves();
+        (
+            PrefixedRead {
+                prefix: self.prefix,
+                pos: 0,
+                inner: r,
+            },
+            w,
+        )
+    }
+}
+
+/// Reads `prefix`, then from `inner`
+pub(crate) struct PrefixedRead {
+    prefix: Vec<u8>,
+    pos: usize,
+    inner: <TcpStream as IntoHalves>::Read,
+}
+
+impl ReadOwned for PrefixedRead {
+    async fn read_owned<B: IoBufMut>(&mut self, mut buf: B) -> BufResult<usize, B> {
+        let rest = &self.prefix[self.pos..];
+        if rest.is_empty() {
+            return self.inner.read_owned(buf).await;
+        }
+
+        let n = rest.len().min(buf.io_buf_mut_capacity());
+        unsafe { buf.slice_mut()[..n].copy_from_slice(&rest[..n]) };
+        self.pos += n;
+        if self.pos == self.prefix.len() {
+            self.prefix = Default::default();
+            self.pos = 0;
+        }
+        (Ok(n), buf)
+    }
+}
@@ -0,0 +1,165 @@
+//! Bridges between loona bodies and [http_body] bodies, in both directions
+
+use std::{
+    fmt,
+    future::poll_fn,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::{Buf, Bytes};
+use http_body::{Frame, SizeHint};
+use loona::{from_http_headers, to_http_headers, Body, BodyChunk};
+use tokio::sync::mpsc;
+use tracing::debug;
+
+use crate::BoxError;
+
+/// Error reading the loona request body, as seen by the service
+#[derive(Debug, thiserror::Error)]
+#[error("error reading request body: {0}")]
+pub struct RequestBodyError(String);
+
+/// The request body handed to services: an [http_body::Body] fed from the
+/// loona request body, one chunk at a time, as it's polled.
+pub struct RequestBody {
+    rx: mpsc::Receiver<Result<Frame<Bytes>, RequestBodyError>>,
+    content_len: Option<u64>,
+}
+
+impl http_body::Body for RequestBody {
+    type Data = Bytes;
+    type Error = RequestBodyError;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        self.rx.poll_recv(cx)
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        match self.content_len {
+            Some(len) => SizeHint::with_exact(len),
+            None => SizeHint::default(),
+        }
+    }
+}
+
+/// Returns a [RequestBody], and the future that feeds it from `req_body`:
+/// it copies chunks over until the body is done or the [RequestBody] is
+/// dropped, and must be polled alongside whatever reads the [RequestBody].
+pub fn request_body(req_body: &mut impl Body) -> (RequestBody, impl std::future::Future + '_) {
+    let (tx, rx) = mpsc::channel(1);
+    let body = RequestBody {
+        rx,
+        content_len: req_body.content_len(),
+    };
+    (body, feed_request_body(req_body, tx))
+}
+
+async fn feed_request_body(
+    req_body: &mut impl Body,
+    tx: mpsc::Sender<Result<Frame<Bytes>, RequestBodyError>>,
+) {
+    loop {
+        let (frame, done) = match req_body.next_chunk().await {
+            Ok(BodyChunk::Chunk(chunk)) => (Ok(Frame::data(Bytes::copy_from_slice(&chunk))), false),
+            Ok(BodyChunk::Done { trailers }) => match trailers {
+                None => return,
+                Some(trailers) => match to_http_headers(&trailers) {
+                    Ok(trailers) => (Ok(Frame::trailers(trailers)), true),
+                    Err(e) => (Err(RequestBodyError(e.to_string())), true),
+                },
+            },
+            Err(e) => (Err(RequestBodyError(e.to_string())), true),
+        };
+
+        if tx.send(frame).await.is_err() {
+            debug!("service dropped the request body, not reading the rest");
+            return;
+        }
+        if done {
+            return;
+        }
+    }
+}
+
+/// Error reading an [http_body::Body] through [HttpBody]
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct HttpBodyError(BoxError);
+
+impl HttpBodyError {
+    pub fn into_inner(self) -> BoxError {
+        self.0
+    }
+}
+
+/// Any [http_body::Body] (a hyper or axum body, for example) as a loona
+/// [Body], for use with e.g. [loona::Responder::write_final_response_with_body]
+pub struct HttpBody<B> {
+    body: Pin<Box<B>>,
+    eof: bool,
+}
+
+impl<B> HttpBody<B> {
+    pub fn new(body: B) -> Self {
+        Self {
+            body: Box::pin(body),
+            eof: false,
+        }
+    }
+}
+
+impl<B> fmt::Debug for HttpBody<B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpBody").field("eof", &self.eof).finish()
+    }
+}
+
+impl<B> Body for HttpBody<B>
+where
+    B: http_body::Body,
+    B::Error: Into<BoxError>,
+{
+    type Error = HttpBodyError;
+
+    fn content_len(&self) -> Option<u64> {
+        self.body.size_hint().exact()
+    }
+
+    fn eof(&self) -> bool {
+        self.eof || self.body.is_end_stream()
+    }
+
+    async fn next_chunk(&mut self) -> Result<BodyChunk, Self::Error> {
+        while !self.eof {
+            let frame = match poll_fn(|cx| self.body.as_mut().poll_frame(cx)).await {
+                Some(frame) => frame.map_err(|e| HttpBodyError(e.into()))?,
+                None => break,
+            };
+
+            match frame.into_data() {
+                Ok(mut data) => {
+                    if data.has_remaining() {
+                        let data = data.copy_to_bytes(data.remaining());
+                        return Ok(BodyChunk::Chunk(Vec::from(data).into()));
+                    }
+                }
+                Err(frame) => {
+                    // frames that are neither data nor trailers are skipped
+                    if let Ok(trailers) = frame.into_trailers() {
+                        self.eof = true;
+                        return Ok(BodyChunk::Done {
+                            trailers: Some(Box::new(from_http_headers(trailers))),
+                        });
+                    }
+                }
+            }
+        }
+
+        self.eof = true;
+        Ok(BodyChunk::Done { trailers: None })
+    }
+}
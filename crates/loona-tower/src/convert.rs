@@ -0,0 +1,32 @@
+//! Conversions between loona's requests and responses and the [http]
+//! crate's (which are also hyper's and tower's), bodies included.
+//!
+//! These build on loona's own conversions for request and response heads,
+//! cf. [loona::from_http_headers].
+
+use loona::{Request, Response};
+
+/// Turns a loona request into an [http::Request] with the given body.
+///
+/// Fails if a header value is invalid as far as the `http` crate is
+/// concerned.
+pub fn to_http_request<B>(req: Request, body: B) -> Result<http::Request<B>, http::Error> {
+    Ok(http::Request::from_parts(req.try_into()?, body))
+}
+
+/// Turns an [http::Request] into a loona request and its body
+pub fn from_http_request<B>(req: http::Request<B>) -> (Request, B) {
+    let (parts, body) = req.into_parts();
+    (parts.into(), body)
+}
+
+/// Turns a loona response into an [http::Response] with the given body
+pub fn to_http_response<B>(res: Response, body: B) -> Result<http::Response<B>, http::Error> {
+    Ok(http::Response::from_parts(res.try_into()?, body))
+}
+
+/// Turns an [http::Response] into a loona response and its body
+pub fn from_http_response<B>(res: http::Response<B>) -> (Response, B) {
+    let (parts, body) = res.into_parts();
+    (parts.into(), body)
+}
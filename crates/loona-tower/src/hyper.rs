@@ -0,0 +1,42 @@
+//! Serves [hyper services](hyper::service::Service) with loona
+//!
+//! hyper 1.x deals in `http` requests and responses, with [http_body]
+//! bodies, so this is mostly the same as [crate::TowerDriver]: services just
+//! get a [RequestBody] instead of `hyper::body::Incoming`, so they need to be
+//! generic over the request body (or take a [RequestBody] outright).
+
+use loona::{Body, Encoder, ExpectResponseHeaders, Request, Responder, ResponseDone, ServerDriver};
+
+use crate::{serve_http, BoxError, RequestBody, TowerDriverError};
+
+/// A [ServerDriver] that hands requests to a hyper service
+#[derive(Clone)]
+pub struct HyperDriver<S> {
+    service: S,
+}
+
+impl<S> HyperDriver<S> {
+    pub fn new(service: S) -> Self {
+        Self { service }
+    }
+}
+
+impl<OurEncoder, S, ResBody> ServerDriver<OurEncoder> for HyperDriver<S>
+where
+    OurEncoder: Encoder,
+    S: hyper::service::Service<http::Request<RequestBody>, Response = http::Response<ResBody>>,
+    S::Error: Into<BoxError>,
+    ResBody: http_body::Body,
+    ResBody::Error: Into<BoxError>,
+{
+    type Error = TowerDriverError<OurEncoder::Error>;
+
+    async fn handle(
+        &self,
+        req: Request,
+        req_body: &mut impl Body,
+        respond: Responder<OurEncoder, ExpectResponseHeaders>,
+    ) -> Result<Responder<OurEncoder, ResponseDone>, Self::Error> {
+        serve_http(req, req_body, respond, |req| self.service.call(req)).await
+    }
+}
@@ -0,0 +1,175 @@
+//! Drives [tower services](tower_service::Service) with loona, so that the
+//! existing middleware ecosystem (timeouts, auth, rate limiting, etc.) can be
+//! used unchanged. With the `hyper` feature, hyper services can be served
+//! the same way, cf. [hyper::HyperDriver].
+//!
+//! [TowerDriver] turns loona requests into [http::Request]s, whose body
+//! ([RequestBody]) is fed from the loona request body as the service reads
+//! it, and streams the service's response body back through the loona
+//! responder. Body chunks get copied along the way: loona's buffers aren't
+//! `Send`, and tower's bodies usually have to be.
+//!
+//! For code that deals with both loona and `http` types, [convert] and
+//! [HttpBody] go back and forth between the two.
+
+use std::future::{poll_fn, Future};
+
+use http::header;
+use loona::{
+    cancel::CancelReason, Body, BodyChunk, Encoder, ExpectResponseHeaders, Request, Responder,
+    ResponderError, ResponseDone, ServerDriver,
+};
+use tower_service::Service;
+use tracing::debug;
+
+mod body;
+pub use body::*;
+
+pub mod convert;
+
+#[cfg(feature = "hyper")]
+pub mod hyper;
+
+/// The error type tower services and bodies usually deal in
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A [ServerDriver] that hands requests to a tower service.
+///
+/// The service is cloned for every request, and waited on until it's ready,
+/// the way tower services are meant to be driven.
+#[derive(Clone)]
+pub struct TowerDriver<S> {
+    service: S,
+}
+
+impl<S> TowerDriver<S> {
+    pub fn new(service: S) -> Self {
+        Self { service }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum TowerDriverError<EncoderError> {
+    /// The loona request couldn't be turned into an [http::Request]
+    #[error("invalid request: {0}")]
+    InvalidRequest(#[from] http::Error),
+
+    /// The service wasn't ready, or failed to produce a response
+    #[error("service error: {0}")]
+    Service(BoxError),
+
+    /// The service's response body errored out
+    #[error("error reading response body: {0}")]
+    ResponseBody(BoxError),
+
+    /// Error writing the response to the client
+    #[error("error writing response: {0}")]
+    Responder(#[from] ResponderError<EncoderError>),
+
+    /// The request was cancelled (cf. [loona::cancel]), so the service's
+    /// future was dropped, which is how tower services are cancelled.
+    #[error("request cancelled: {0:?}")]
+    Cancelled(CancelReason),
+}
+
+impl<OurEncoder, S, ResBody> ServerDriver<OurEncoder> for TowerDriver<S>
+where
+    OurEncoder: Encoder,
+    S: Service<http::Request<RequestBody>, Response = http::Response<ResBody>> + Clone,
+    S::Error: Into<BoxError>,
+    ResBody: http_body::Body,
+    ResBody::Error: Into<BoxError>,
+{
+    type Error = TowerDriverError<OurEncoder::Error>;
+
+    async fn handle(
+        &self,
+        req: Request,
+        req_body: &mut impl Body,
+        respond: Responder<OurEncoder, ExpectResponseHeaders>,
+    ) -> Result<Responder<OurEncoder, ResponseDone>, Self::Error> {
+        let mut service = self.service.clone();
+        serve_http(req, req_body, respond, |req| async move {
+            poll_fn(|cx| service.poll_ready(cx)).await?;
+            service.call(req).await
+        })
+        .await
+    }
+}
+
+/// Hands `req` to `call` as an [http::Request], feeding its body along the
+/// way, then writes the response it returns. Gives up if the request is
+/// cancelled.
+async fn serve_http<OurEncoder, F, Fut, ResBody, E>(
+    req: Request,
+    req_body: &mut impl Body,
+    respond: Responder<OurEncoder, ExpectResponseHeaders>,
+    call: F,
+) -> Result<Responder<OurEncoder, ResponseDone>, TowerDriverError<OurEncoder::Error>>
+where
+    OurEncoder: Encoder,
+    F: FnOnce(http::Request<RequestBody>) -> Fut,
+    Fut: Future<Output = Result<http::Response<ResBody>, E>>,
+    E: Into<BoxError>,
+    ResBody: http_body::Body,
+    ResBody::Error: Into<BoxError>,
+{
+    let (body, feed) = request_body(req_body);
+    let req = convert::to_http_request(req, body)?;
+
+    let cancellation = respond.cancellation().clone();
+    let serve = async move {
+        let res = call(req)
+            .await
+            .map_err(|e| TowerDriverError::Service(e.into()))?;
+        write_response(res, respond).await
+    };
+    tokio::pin!(serve);
+    tokio::pin!(feed);
+    let mut feeding = true;
+
+    loop {
+        tokio::select! {
+            res = &mut serve => return res,
+            _ = &mut feed, if feeding => feeding = false,
+            reason = cancellation.cancelled() => {
+                debug!(?reason, "request cancelled, dropping service future");
+                return Err(TowerDriverError::Cancelled(reason));
+            }
+        }
+    }
+}
+
+async fn write_response<OurEncoder, ResBody>(
+    res: http::Response<ResBody>,
+    respond: Responder<OurEncoder, ExpectResponseHeaders>,
+) -> Result<Responder<OurEncoder, ResponseDone>, TowerDriverError<OurEncoder::Error>>
+where
+    OurEncoder: Encoder,
+    ResBody: http_body::Body,
+    ResBody::Error: Into<BoxError>,
+{
+    let (mut res, body) = convert::from_http_response(res);
+    let mut body = HttpBody::new(body);
+
+    if let Some(len) = body.content_len() {
+        if !res.means_empty_body() && !res.headers.contains_key(header::TRANSFER_ENCODING) {
+            res.headers
+                .entry(header::CONTENT_LENGTH)
+                .or_insert_with(|| len.to_string().into_bytes().into());
+        }
+    }
+
+    let mut respond = respond.write_final_response(res).await?;
+    loop {
+        match body
+            .next_chunk()
+            .await
+            .map_err(|e| TowerDriverError::ResponseBody(e.into_inner()))?
+        {
+            BodyChunk::Chunk(chunk) => respond.write_chunk(chunk).await?,
+            BodyChunk::Done { trailers } => return Ok(respond.finish_body(trailers).await?),
+        }
+    }
+}
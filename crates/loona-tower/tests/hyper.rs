@@ -0,0 +1,74 @@
+use std::{convert::Infallible, rc::Rc};
+
+use b_x::BX;
+use bytes::Bytes;
+use http_body_util::{BodyExt, StreamBody};
+use loona::{
+    buffet::{ReadOwned, RollMut, WriteOwned},
+    h1,
+};
+use loona_tower::{hyper::HyperDriver, RequestBody};
+
+/// Replies with the request body, twice, in a response body that doesn't
+/// know its size. Shows that hyper services just need to be generic over the
+/// request body.
+async fn echo<B>(
+    req: http::Request<B>,
+) -> Result<http::Response<impl http_body::Body<Data = Bytes, Error = Infallible>>, Infallible>
+where
+    B: http_body::Body,
+    B::Error: std::fmt::Debug,
+{
+    let body = req.into_body().collect().await.unwrap().to_bytes();
+    let frames = futures_util::stream::iter([
+        Ok::<_, Infallible>(http_body::Frame::data(body.clone())),
+        Ok(http_body::Frame::data(body)),
+    ]);
+    Ok(http::Response::new(StreamBody::new(frames)))
+}
+
+#[test]
+fn hyper_service_over_h1() {
+    loona::buffet::start(async move {
+        let service = hyper::service::service_fn(echo::<RequestBody>);
+
+        let (server_write, mut client_read) = loona::buffet::pipe();
+        let (mut client_write, server_read) = loona::buffet::pipe();
+        loona::buffet::spawn(async move {
+            h1::serve(
+                (server_read, server_write),
+                Rc::new(Default::default()),
+                RollMut::alloc()?,
+                HyperDriver::new(service),
+            )
+            .await?;
+            Ok::<_, BX>(())
+        });
+
+        client_write
+            .write_all_owned("POST / HTTP/1.1\r\ncontent-length: 5\r\n\r\nhello")
+            .await
+            .unwrap();
+        drop(client_write);
+
+        let mut out = vec![];
+        let mut buf = vec![0u8; 1024];
+        loop {
+            let res;
+            (res, buf) = client_read.read_owned(buf).await;
+            let n = res.unwrap();
+            if n == 0 {
+                break;
+            }
+            out.extend_from_slice(&buf[..n]);
+        }
+        let res = String::from_utf8(out).unwrap();
+
+        assert!(res.starts_with("HTTP/1.1 200 "), "{res}");
+        assert!(res.contains("transfer-encoding: chunked\r\n"), "{res}");
+        assert!(
+            res.ends_with("\r\n5\r\nhello\r\n5\r\nhello\r\n0\r\n\r\n"),
+            "{res}"
+        );
+    })
+}
@@ -0,0 +1,78 @@
+use std::{rc::Rc, time::Duration};
+
+use b_x::BX;
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use loona::{
+    buffet::{ReadOwned, RollMut, WriteOwned},
+    h1,
+};
+use loona_tower::{BoxError, RequestBody, TowerDriver};
+use tower::ServiceBuilder;
+
+async fn echo(req: http::Request<RequestBody>) -> Result<http::Response<Full<Bytes>>, BoxError> {
+    if req.uri().path() == "/slow" {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    let header = req.headers().get("x-foo").cloned();
+    let body = req.into_body().collect().await?.to_bytes();
+    let mut res = http::Response::new(Full::new(body));
+    if let Some(header) = header {
+        res.headers_mut().insert("x-foo", header);
+    }
+    Ok(res)
+}
+
+#[test]
+fn tower_service_over_h1() {
+    loona::buffet::start(async move {
+        let service = ServiceBuilder::new()
+            .timeout(Duration::from_millis(50))
+            .service_fn(echo);
+
+        let (server_write, mut client_read) = loona::buffet::pipe();
+        let (mut client_write, server_read) = loona::buffet::pipe();
+        loona::buffet::spawn(async move {
+            h1::serve(
+                (server_read, server_write),
+                Rc::new(Default::default()),
+                RollMut::alloc()?,
+                TowerDriver::new(service),
+            )
+            .await?;
+            Ok::<_, BX>(())
+        });
+
+        // the body makes it to the service and back, and so do headers. the
+        // timeout middleware then makes the second request fail.
+        client_write
+            .write_all_owned(
+                "POST /echo HTTP/1.1\r\nx-foo: bar\r\ncontent-length: 5\r\n\r\nhello\
+                 GET /slow HTTP/1.1\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        drop(client_write);
+
+        let mut out = vec![];
+        let mut buf = vec![0u8; 1024];
+        loop {
+            let res;
+            (res, buf) = client_read.read_owned(buf).await;
+            let n = res.unwrap();
+            if n == 0 {
+                break;
+            }
+            out.extend_from_slice(&buf[..n]);
+        }
+        let res = String::from_utf8(out).unwrap();
+
+        let (first, second) = res.split_at(res.find("HTTP/1.1 500 ").expect(&res));
+        assert!(first.starts_with("HTTP/1.1 200 "), "{res}");
+        assert!(first.contains("x-foo: bar\r\n"), "{res}");
+        assert!(first.contains("content-length: 5\r\n"), "{res}");
+        assert!(first.ends_with("\r\n\r\nhello"), "{res}");
+        assert!(second.starts_with("HTTP/1.1 500 "), "{res}");
+    })
+}
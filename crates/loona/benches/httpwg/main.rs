@@ -0,0 +1,53 @@
+//! Benchmarks some of httpwg's tests, the ones marked with `bench` in
+//! httpwg-gen's catalog: every iteration runs the test against a
+//! fresh server, from `server::start_server`
+
+// This file is automatically @generated by httpwg-gen
+// It is not intended for manual editing
+
+use std::future::Future;
+
+use codspeed_criterion_compat::{
+    async_executor::AsyncExecutor, criterion_group, criterion_main, Criterion, Throughput,
+};
+
+mod server;
+
+/// Runs benchmarks on buffet, like the tests
+struct Buffet;
+
+impl AsyncExecutor for Buffet {
+    fn block_on<T>(&self, future: impl Future<Output = T>) -> T {
+        buffet::start(future)
+    }
+}
+
+fn rfc9113(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rfc9113");
+    group.throughput(Throughput::Elements(1));
+
+    // http2/3.4/1
+    group.bench_function("handshake", |b| {
+        b.to_async(Buffet).iter(|| async {
+            httpwg::rfc9113::_3_starting_http2::sends_client_connection_preface(
+                server::start_server(),
+            )
+            .await
+            .unwrap()
+        })
+    });
+
+    // http2/8.2.2/6
+    group.bench_function("headers", |b| {
+        b.to_async(Buffet).iter(|| async {
+            httpwg::rfc9113::_8_expressing_http_semantics_in_http2::sends_headers_frame_with_te_trailers(server::start_server())
+                .await
+                .unwrap()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, rfc9113);
+criterion_main!(benches);
@@ -0,0 +1,71 @@
+//! What the generated harness benchmarks: loona serving HTTP/2 over pipes,
+//! like in `tests/httpwg.rs`, minus the logging
+
+use std::error::Error as StdError;
+use std::rc::Rc;
+
+use b_x::{BxForResults, BX};
+use buffet::{IntoHalves, PipeRead, PipeWrite, ReadOwned, RollMut, WriteOwned};
+use http::StatusCode;
+use loona::{
+    Body, BodyChunk, Encoder, ExpectResponseHeaders, Responder, Response, ResponseDone,
+    ServerDriver,
+};
+
+struct BenchDriver;
+
+impl<OurEncoder> ServerDriver<OurEncoder> for BenchDriver
+where
+    OurEncoder: Encoder,
+    <OurEncoder as Encoder>::Error: AsRef<dyn StdError>,
+{
+    type Error = BX;
+
+    async fn handle(
+        &self,
+        _req: loona::Request,
+        req_body: &mut impl Body,
+        res: Responder<OurEncoder, ExpectResponseHeaders>,
+    ) -> Result<Responder<OurEncoder, ResponseDone>, BX> {
+        while let BodyChunk::Chunk(_) = req_body.next_chunk().await.bx()? {}
+
+        let mut res = res
+            .write_final_response(Response {
+                status: StatusCode::OK,
+                ..Default::default()
+            })
+            .await?;
+        res.write_chunk("it's less dire to lose, than to lose oneself".into())
+            .await?;
+        Ok(res.finish_body(None).await?)
+    }
+}
+
+pub struct TwoHalves<W, R>(W, R);
+impl<W: WriteOwned + 'static, R: ReadOwned + 'static> IntoHalves for TwoHalves<W, R> {
+    type Read = R;
+    type Write = W;
+
+    fn into_halves(self) -> (Self::Read, Self::Write) {
+        (self.1, self.0)
+    }
+}
+
+/// A client connection to a fresh server, served on the current buffet
+/// runtime
+pub fn start_server() -> httpwg::Conn<TwoHalves<PipeWrite, PipeRead>> {
+    let (server_write, client_read) = loona::buffet::pipe();
+    let (client_write, server_read) = loona::buffet::pipe();
+
+    buffet::spawn(async move {
+        let server_conf = Rc::new(loona::h2::ServerConf::conformance_test());
+        let client_buf = RollMut::alloc().unwrap();
+        let io = (server_read, server_write);
+        // the scenarios are well-behaved, but the server may still see the
+        // client hang up mid-way
+        _ = loona::h2::serve(io, server_conf, client_buf, Rc::new(BenchDriver)).await;
+    });
+
+    let config = Rc::new(httpwg::Config::default());
+    httpwg::Conn::new(config, TwoHalves(client_write, client_read))
+}
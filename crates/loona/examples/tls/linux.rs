@@ -344,6 +344,9 @@ async fn sample_http_request() -> b_x::Result<()> {
         uri: "http://httpbingo.org/image/jpeg".parse().unwrap(),
         version: Version::HTTP_11,
         headers: Default::default(),
+        protocol: None,
+        header_case: None,
+        connection: None,
     };
 
     let (transport, _) = h1::request(transport.into_halves(), req, &mut (), driver).await?;
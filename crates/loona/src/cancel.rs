@@ -0,0 +1,151 @@
+//! Lets handlers find out when nobody is waiting for a response anymore
+//!
+//! Every [crate::Responder] carries a [Cancellation]: handlers doing
+//! expensive work (database queries, upstream requests) can race it against
+//! [Cancellation::cancelled] and bail out early.
+
+use std::{cell::Cell, rc::Rc};
+
+use tokio::{sync::Notify, time::Instant};
+
+/// Why a request was cancelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CancelReason {
+    /// HTTP/2 only: the stream was reset, by the client (with RST_STREAM) or
+    /// by us because of a protocol error
+    StreamReset,
+
+    /// The connection is gone, or can't be used anymore. For HTTP/1.1, this
+    /// is only noticed when reading the request body fails: a client hanging
+    /// up while the handler isn't reading won't cancel the request.
+    ConnectionClosed,
+
+    /// The request took longer than the configured request timeout
+    Timeout,
+}
+
+/// A signal that a request isn't worth finishing anymore. Cloning it is
+/// cheap, and all clones get cancelled together.
+#[derive(Clone, Default)]
+pub struct Cancellation {
+    // `None` for requests that can't be cancelled, so that those don't cost
+    // an allocation.
+    inner: Option<Rc<Inner>>,
+}
+
+struct Inner {
+    reason: Cell<Option<CancelReason>>,
+    notify: Notify,
+    deadline: Option<Instant>,
+}
+
+impl Cancellation {
+    /// A cancellation that fires when [Cancellation::cancel] is called, or
+    /// once `deadline` is reached, if set.
+    pub(crate) fn new(deadline: Option<Instant>) -> Self {
+        Self {
+            inner: Some(Rc::new(Inner {
+                reason: Default::default(),
+                notify: Default::default(),
+                deadline,
+            })),
+        }
+    }
+
+    /// Why the request was cancelled, if it was
+    pub fn reason(&self) -> Option<CancelReason> {
+        let inner = self.inner.as_ref()?;
+        inner.reason.get().or_else(|| {
+            inner
+                .deadline
+                .filter(|deadline| Instant::now() >= *deadline)
+                .map(|_| CancelReason::Timeout)
+        })
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.reason().is_some()
+    }
+
+    /// Resolves when the request is cancelled, which might be never.
+    pub async fn cancelled(&self) -> CancelReason {
+        let inner = match &self.inner {
+            Some(inner) => inner,
+            None => std::future::pending().await,
+        };
+
+        loop {
+            let notified = inner.notify.notified();
+            tokio::pin!(notified);
+            // register interest before checking, so we can't miss a `cancel`
+            // that happens in between.
+            notified.as_mut().enable();
+
+            if let Some(reason) = self.reason() {
+                return reason;
+            }
+
+            match inner.deadline {
+                Some(deadline) => {
+                    tokio::select! {
+                        _ = notified => {},
+                        _ = tokio::time::sleep_until(deadline) => {},
+                    }
+                }
+                None => notified.await,
+            }
+        }
+    }
+
+    /// Cancels the request, unless it was already cancelled: the first reason
+    /// sticks.
+    pub(crate) fn cancel(&self, reason: CancelReason) {
+        if let Some(inner) = &self.inner {
+            if inner.reason.get().is_none() {
+                inner.reason.set(Some(reason));
+                inner.notify.notify_waiters();
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Cancellation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cancellation")
+            .field("reason", &self.reason())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::Instant;
+
+    use super::{CancelReason, Cancellation};
+
+    #[tokio::test]
+    async fn test_cancellation() {
+        let never = Cancellation::default();
+        assert!(!never.is_cancelled());
+        never.cancel(CancelReason::StreamReset);
+        assert_eq!(never.reason(), None);
+
+        // the first reason sticks, and clones see it
+        let c = Cancellation::new(None);
+        let c2 = c.clone();
+        let (reason, _) = tokio::join!(c2.cancelled(), async {
+            tokio::task::yield_now().await;
+            c.cancel(CancelReason::StreamReset);
+            c.cancel(CancelReason::ConnectionClosed);
+        });
+        assert_eq!(reason, CancelReason::StreamReset);
+        assert_eq!(c.reason(), Some(CancelReason::StreamReset));
+
+        let c = Cancellation::new(Some(Instant::now() + Duration::from_millis(10)));
+        assert!(!c.is_cancelled());
+        assert_eq!(c.cancelled().await, CancelReason::Timeout);
+    }
+}
@@ -0,0 +1,1083 @@
+//! A high-level HTTP client, which connects to origins as needed and keeps
+//! connections around between requests, cf. [Client]
+//!
+//! Connections are made by a [Connector]: [TcpConnector] for plain TCP, or
+//! one of your own, e.g. to do TLS. Connectors report the protocol that was
+//! negotiated with ALPN, which decides whether a connection speaks HTTP/2
+//! (and serves many requests at once) or HTTP/1.1 (one at a time).
+//!
+//! [TcpConnector] resolves names with a [Resolve]r, the system's by default,
+//! cf. [crate::dns].
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{hash_map::Entry, HashMap},
+    fmt,
+    future::Future,
+    rc::Rc,
+    time::Duration,
+};
+
+use b_x::BX;
+use buffet::{ReadOwned, WriteOwned};
+use http::{header, StatusCode, Uri};
+use tokio::sync::{Notify, Semaphore};
+use tracing::debug;
+
+use crate::{
+    dns::{happy_eyeballs, HappyEyeballs, Resolve, SystemResolver},
+    h1::{self, Http1ClientError},
+    h2::{self, H2ClientError},
+    pool::{Pool, PoolConf, Shared},
+    util::ReadAndParseError,
+    Body, BodyChunk, ClientDriver, Method, Request, Response,
+};
+
+/// The protocols to offer with ALPN, most preferred first, e.g. for the
+/// `alpn_protocols` of a rustls `ClientConfig`
+pub const ALPN_PROTOCOLS: [&[u8]; 2] = [b"h2", b"http/1.1"];
+
+/// A protocol negotiated with ALPN
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlpnProtocol {
+    /// `http/1.1`
+    Http11,
+
+    /// `h2`
+    H2,
+}
+
+impl AlpnProtocol {
+    /// Parses an ALPN protocol ID, e.g. what a TLS library says the server
+    /// picked. Returns `None` for protocols we don't speak.
+    pub fn from_alpn(id: &[u8]) -> Option<Self> {
+        match id {
+            b"http/1.1" => Some(Self::Http11),
+            b"h2" => Some(Self::H2),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scheme {
+    Http,
+    Https,
+}
+
+/// Where requests go: connections are pooled per origin
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Origin {
+    pub scheme: Scheme,
+
+    /// a domain name (lowercase) or an IP address. IPv6 addresses are
+    /// enclosed in brackets, as in URIs.
+    pub host: String,
+
+    pub port: u16,
+}
+
+impl Origin {
+    /// The origin of an absolute URI, with the scheme's default port if it
+    /// doesn't specify one. Returns `None` for relative URIs, and for
+    /// schemes other than `http` and `https`.
+    pub fn from_uri(uri: &Uri) -> Option<Self> {
+        let scheme = match uri.scheme_str()? {
+            s if s.eq_ignore_ascii_case("http") => Scheme::Http,
+            s if s.eq_ignore_ascii_case("https") => Scheme::Https,
+            _ => return None,
+        };
+        let host = uri.host().filter(|host| !host.is_empty())?;
+        let port = uri.port_u16().unwrap_or(match scheme {
+            Scheme::Http => 80,
+            Scheme::Https => 443,
+        });
+        Some(Self {
+            scheme,
+            host: host.to_ascii_lowercase(),
+            port,
+        })
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scheme = match self.scheme {
+            Scheme::Http => "http",
+            Scheme::Https => "https",
+        };
+        write!(f, "{scheme}://{}:{}", self.host, self.port)
+    }
+}
+
+/// A connection made by a [Connector]
+pub struct Connected<R, W> {
+    pub transport: (R, W),
+
+    /// What the server picked with ALPN, if anything was negotiated. Without
+    /// it, connections speak HTTP/1.1, unless
+    /// [ClientConf::h2_prior_knowledge] says otherwise.
+    pub alpn: Option<AlpnProtocol>,
+}
+
+/// Connects to origins for a [Client], in two steps (which time out
+/// separately, cf. [ClientConf::connect_timeout] and
+/// [ClientConf::handshake_timeout]): opening a transport, e.g. a TCP
+/// connection, then setting it up, e.g. with a TLS handshake.
+///
+/// For `https` origins, the TLS handshake takes the server name from
+/// [Origin::host], and offers [ALPN_PROTOCOLS].
+#[allow(async_fn_in_trait)] // we never require Send
+pub trait Connector {
+    /// What [Connector::connect] opens
+    type Transport;
+    type Read: ReadOwned + 'static;
+    type Write: WriteOwned + 'static;
+    type Error: std::error::Error + 'static;
+
+    async fn connect(&self, origin: &Origin) -> Result<Self::Transport, Self::Error>;
+
+    async fn handshake(
+        &self,
+        origin: &Origin,
+        transport: Self::Transport,
+    ) -> Result<Connected<Self::Read, Self::Write>, Self::Error>;
+}
+
+/// Connects over plain TCP, to the addresses its [Resolve]r finds for the
+/// host, racing attempts as [HappyEyeballs] says. It doesn't do TLS, so
+/// `https` origins are refused.
+#[derive(Debug, Clone, Default)]
+pub struct TcpConnector<R = SystemResolver> {
+    pub resolver: R,
+    pub happy_eyeballs: HappyEyeballs,
+}
+
+impl TcpConnector {
+    /// A connector that resolves names with the system's resolver
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<R> TcpConnector<R>
+where
+    R: Resolve,
+{
+    /// A connector that resolves names with `resolver`
+    pub fn with_resolver(resolver: R) -> Self {
+        Self {
+            resolver,
+            happy_eyeballs: Default::default(),
+        }
+    }
+}
+
+impl<R> Connector for TcpConnector<R>
+where
+    R: Resolve,
+{
+    type Transport = buffet::net::TcpStream;
+    type Read = buffet::net::TcpReadHalf;
+    type Write = buffet::net::TcpWriteHalf;
+    type Error = std::io::Error;
+
+    async fn connect(&self, origin: &Origin) -> Result<Self::Transport, Self::Error> {
+        if origin.scheme == Scheme::Https {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "TcpConnector doesn't do TLS, https origins need a connector that does",
+            ));
+        }
+
+        let host = origin.host.trim_start_matches('[').trim_end_matches(']');
+        let addrs = self.resolver.resolve(host, origin.port).await?;
+        if addrs.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{host} did not resolve to any address"),
+            ));
+        }
+        debug!(%origin, ?addrs, "resolved");
+        happy_eyeballs(self.happy_eyeballs, addrs, buffet::net::TcpStream::connect).await
+    }
+
+    async fn handshake(
+        &self,
+        _origin: &Origin,
+        transport: Self::Transport,
+    ) -> Result<Connected<Self::Read, Self::Write>, Self::Error> {
+        use buffet::IntoHalves;
+
+        Ok(Connected {
+            transport: transport.into_halves(),
+            alpn: None,
+        })
+    }
+}
+
+/// Connects to `host:port` on a simulated network, cf. [buffet::sim]. Like
+/// [TcpConnector], it doesn't do TLS.
+#[cfg(feature = "sim")]
+#[derive(Clone)]
+pub struct SimConnector {
+    pub net: buffet::sim::Net,
+}
+
+#[cfg(feature = "sim")]
+impl Connector for SimConnector {
+    type Transport = buffet::sim::SimStream;
+    type Read = buffet::PipeRead;
+    type Write = buffet::PipeWrite;
+    type Error = std::io::Error;
+
+    async fn connect(&self, origin: &Origin) -> Result<Self::Transport, Self::Error> {
+        if origin.scheme == Scheme::Https {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "SimConnector doesn't do TLS",
+            ));
+        }
+        self.net
+            .connect(&format!("{}:{}", origin.host, origin.port))
+            .await
+    }
+
+    async fn handshake(
+        &self,
+        _origin: &Origin,
+        transport: Self::Transport,
+    ) -> Result<Connected<Self::Read, Self::Write>, Self::Error> {
+        use buffet::IntoHalves;
+
+        Ok(Connected {
+            transport: transport.into_halves(),
+            alpn: None,
+        })
+    }
+}
+
+/// Settings for a [Client]
+#[derive(Clone)]
+pub struct ClientConf {
+    /// How many connections are kept around, and for how long
+    pub pool: PoolConf,
+
+    /// How many requests can be in flight to the same origin at once, across
+    /// all connections to it. Requests past that wait their turn. `None`
+    /// for no limit.
+    pub max_requests_per_origin: Option<usize>,
+
+    /// How long opening a connection can take, cf. [Connector::connect].
+    /// `None` waits forever.
+    pub connect_timeout: Option<Duration>,
+
+    /// How long setting up a connection can take once it's open, e.g. the
+    /// TLS handshake, cf. [Connector::handshake]. `None` waits forever.
+    pub handshake_timeout: Option<Duration>,
+
+    /// How long a request can take, from the moment it's made until the
+    /// driver is done with the response: redirects and retries included.
+    /// `None` waits forever.
+    pub request_timeout: Option<Duration>,
+
+    /// Speak HTTP/2 right away on connections to `http` origins that didn't
+    /// negotiate anything with ALPN
+    pub h2_prior_knowledge: bool,
+
+    /// Which redirects to follow
+    pub redirect: RedirectPolicy,
+
+    /// Which failed requests to try again
+    pub retry: RetryPolicy,
+
+    /// Settings for HTTP/1.1 connections
+    pub h1: h1::ClientConf,
+
+    /// Settings for HTTP/2 connections
+    pub h2: h2::ClientConf,
+}
+
+impl Default for ClientConf {
+    fn default() -> Self {
+        Self {
+            pool: Default::default(),
+            max_requests_per_origin: Some(256),
+            connect_timeout: Some(Duration::from_secs(10)),
+            handshake_timeout: Some(Duration::from_secs(10)),
+            request_timeout: None,
+            h2_prior_knowledge: false,
+            redirect: Default::default(),
+            retry: Default::default(),
+            h1: Default::default(),
+            h2: h2::ClientConf {
+                // same as pooled HTTP/1.1 connections
+                idle_timeout: Some(PoolConf::default().idle_timeout),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// How a [Client] follows redirects (3xx responses with a `location`)
+///
+/// 303 responses, and 301 or 302 responses to POST requests, are followed
+/// with a GET request (HEAD stays HEAD), without a body. Other redirects
+/// are followed with the same method and body, which is only possible for
+/// requests with an empty body: otherwise, the redirect response goes to
+/// the driver. Credentials (`authorization`, `proxy-authorization` and
+/// `cookie` headers) are dropped when following a redirect to another
+/// origin.
+#[derive(Clone)]
+pub struct RedirectPolicy {
+    /// How many redirects to follow for a single request, at most: the
+    /// request fails with [ClientError::TooManyRedirects] past that. Zero
+    /// hands every redirect response to the driver.
+    pub max_redirects: usize,
+
+    /// Decides whether to follow each redirect: if it returns false, the
+    /// redirect response goes to the driver.
+    pub hook: RedirectHook,
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        Self {
+            max_redirects: 10,
+            hook: Rc::new(default_redirect_hook),
+        }
+    }
+}
+
+/// A redirect a [Client] is about to follow, cf. [RedirectPolicy::hook]
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct Redirect<'a> {
+    pub status: StatusCode,
+
+    /// the URI that was requested
+    pub from: &'a Uri,
+
+    /// where the response points to, resolved against [Redirect::from]
+    pub to: &'a Uri,
+
+    /// the method of the next request
+    pub method: &'a Method,
+
+    /// how many redirects were followed before this one
+    pub redirects: usize,
+}
+
+pub type RedirectHook = Rc<dyn Fn(&Redirect<'_>) -> bool>;
+
+/// The default [RedirectHook]: follows every redirect, except from `https`
+/// to `http`
+pub fn default_redirect_hook(redirect: &Redirect<'_>) -> bool {
+    !(redirect.from.scheme() == Some(&http::uri::Scheme::HTTPS)
+        && redirect.to.scheme() == Some(&http::uri::Scheme::HTTP))
+}
+
+/// How a [Client] retries requests that failed before a response arrived
+///
+/// That's only possible if none of the request body was read yet.
+#[derive(Clone)]
+pub struct RetryPolicy {
+    /// How many times to retry a single request, at most
+    pub max_retries: usize,
+
+    /// Decides whether to retry after each failure
+    pub hook: RetryHook,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            hook: Rc::new(default_retry_hook),
+        }
+    }
+}
+
+/// Why a request failed in a way that may be worth retrying
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RetryReason {
+    /// We couldn't connect to the origin
+    ConnectFailed,
+
+    /// The server said it didn't process the request (HTTP/2): it went away
+    /// before getting to it, or refused its stream
+    NotProcessed,
+
+    /// The connection failed before the response arrived, e.g. because the
+    /// server closed a pooled connection just as we sent the request. The
+    /// server may have processed it.
+    ConnectionFailed,
+}
+
+/// A failed request a [Client] could retry, cf. [RetryPolicy::hook]
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct Retry<'a> {
+    pub reason: RetryReason,
+    pub method: &'a Method,
+    pub uri: &'a Uri,
+
+    /// how many times the request was retried already
+    pub retries: usize,
+}
+
+pub type RetryHook = Rc<dyn Fn(&Retry<'_>) -> bool>;
+
+/// The default [RetryHook]: retries requests the server didn't process, and
+/// idempotent requests (cf. [Method::is_idempotent]) that failed because of
+/// the connection.
+pub fn default_retry_hook(retry: &Retry<'_>) -> bool {
+    match retry.reason {
+        RetryReason::NotProcessed => true,
+        RetryReason::ConnectFailed | RetryReason::ConnectionFailed => retry.method.is_idempotent(),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum ClientError<DriverError> {
+    #[error("Requests need an absolute URI, with an http or https scheme")]
+    InvalidUri,
+
+    #[error("Could not connect: {0}")]
+    Connect(#[source] BX),
+
+    #[error("Timed out connecting")]
+    ConnectTimeout,
+
+    #[error("Timed out setting up the connection")]
+    HandshakeTimeout,
+
+    #[error("Timed out waiting for the request to complete")]
+    RequestTimeout,
+
+    #[error("Allocation error: {0}")]
+    Alloc(#[from] buffet::bufpool::Error),
+
+    #[error("HTTP/1.1 request failed: {0}")]
+    H1(#[from] Http1ClientError<DriverError>),
+
+    #[error("HTTP/2 request failed: {0}")]
+    H2(#[from] H2ClientError<DriverError>),
+
+    #[error("Gave up after following {max} redirects")]
+    TooManyRedirects { max: usize },
+}
+
+impl<DriverError> ClientError<DriverError> {
+    /// Whether this failure may be fixed by retrying, and why
+    pub fn retry_reason(&self) -> Option<RetryReason> {
+        match self {
+            ClientError::Connect(_)
+            | ClientError::ConnectTimeout
+            | ClientError::HandshakeTimeout => Some(RetryReason::ConnectFailed),
+            ClientError::H2(e) if e.is_retryable() => Some(RetryReason::NotProcessed),
+            ClientError::H2(H2ClientError::ConnectionClosed) => Some(RetryReason::ConnectionFailed),
+            ClientError::H1(
+                Http1ClientError::WhileWritingRequestHeaders(_)
+                | Http1ClientError::ErrorReadingResponseHeaders(ReadAndParseError::ReadError(_))
+                | Http1ClientError::ServerWentAwayBeforeSendingResponseHeaders,
+            ) => Some(RetryReason::ConnectionFailed),
+            _ => None,
+        }
+    }
+}
+
+impl<DriverError> From<ClientError<DriverError>> for BX
+where
+    DriverError: std::error::Error + 'static,
+{
+    fn from(e: ClientError<DriverError>) -> Self {
+        BX::from_err(e)
+    }
+}
+
+/// Makes requests to any origin, over connections it makes with its
+/// [Connector] and keeps in a [Pool].
+///
+/// HTTP/2 connections serve every request to their origin, up to the
+/// number of streams the server allows at once. HTTP/1.1 connections serve
+/// one request at a time, and go back to the pool once the response has
+/// been read in full.
+pub struct Client<C>
+where
+    C: Connector,
+{
+    conf: ClientConf,
+    h2_conf: Rc<h2::ClientConf>,
+    connector: C,
+    h1_pool: Pool<Origin, (C::Read, C::Write)>,
+    h2_pool: Pool<Origin, h2::ClientHandle>,
+
+    /// for [ClientConf::max_requests_per_origin], with an entry for each
+    /// origin that has requests in flight
+    limits: RefCell<HashMap<Origin, Rc<Semaphore>>>,
+
+    /// origins we're connecting to: requests to them wait for the
+    /// connection, which they can share if it turns out to speak HTTP/2
+    connecting: RefCell<HashMap<Origin, Rc<Notify>>>,
+}
+
+impl<C> Client<C>
+where
+    C: Connector,
+{
+    pub fn new(connector: C, conf: ClientConf) -> Self {
+        Self {
+            h2_conf: Rc::new(conf.h2.clone()),
+            h1_pool: Pool::new(conf.pool.clone()),
+            h2_pool: Pool::new(conf.pool.clone()),
+            connector,
+            conf,
+            limits: Default::default(),
+            connecting: Default::default(),
+        }
+    }
+
+    pub fn conf(&self) -> &ClientConf {
+        &self.conf
+    }
+
+    pub fn connector(&self) -> &C {
+        &self.connector
+    }
+
+    /// Closes connections that sat unused for longer than
+    /// [PoolConf::idle_timeout]. Connections to an origin are evicted
+    /// whenever a request is made to it anyway: call this periodically to
+    /// close connections to origins that aren't used anymore.
+    pub fn evict_idle(&self) {
+        self.h1_pool.evict_idle();
+        self.h2_pool.evict_idle();
+    }
+
+    /// Performs a request to the origin of its URI, which must be absolute,
+    /// over a pooled connection if there's one, or over a new one.
+    ///
+    /// Redirects are followed, and requests that failed before a response
+    /// arrived are retried, as [ClientConf::redirect] and
+    /// [ClientConf::retry] say.
+    ///
+    /// Dropping the returned future cancels the request: its HTTP/2 stream
+    /// is reset, or its HTTP/1.1 connection closed.
+    pub async fn request<D>(
+        &self,
+        req: Request,
+        body: &mut impl Body,
+        driver: D,
+    ) -> Result<D::Return, ClientError<D::Error>>
+    where
+        D: ClientDriver,
+    {
+        with_timeout(
+            self.conf.request_timeout,
+            self.follow(req, body, driver),
+            || ClientError::RequestTimeout,
+        )
+        .await?
+    }
+
+    /// Makes requests until one gets a response that's not a redirect we
+    /// follow, or fails in a way we don't retry
+    async fn follow<D>(
+        &self,
+        mut req: Request,
+        body: &mut impl Body,
+        driver: D,
+    ) -> Result<D::Return, ClientError<D::Error>>
+    where
+        D: ClientDriver,
+    {
+        let mut driver = Some(driver);
+        let started = Cell::new(false);
+        let mut body = ReplayableBody {
+            body: Some(body),
+            started: &started,
+        };
+        let mut redirects = 0;
+        let mut retries = 0;
+
+        loop {
+            let empty_body = !started.get() && (body.content_len() == Some(0) || body.eof());
+            let policy_driver = PolicyDriver {
+                driver: &mut driver,
+                follow: |res: &Response| self.redirect_target(&req, res, empty_body, redirects),
+            };
+            match self.send(req.clone(), &mut body, policy_driver).await {
+                Ok(Followed::Done(ret)) => return Ok(ret),
+                Ok(Followed::Redirect { status, to }) => {
+                    if redirects == self.conf.redirect.max_redirects {
+                        return Err(ClientError::TooManyRedirects {
+                            max: self.conf.redirect.max_redirects,
+                        });
+                    }
+                    redirects += 1;
+                    debug!(%status, from = %req.uri, %to, "following redirect");
+                    req = redirect_request(req, status, to, &mut body);
+                }
+                Err(e) => {
+                    let Some(reason) = e.retry_reason() else {
+                        return Err(e);
+                    };
+                    // the driver is gone once it's seen the final response
+                    let can_retry = driver.is_some()
+                        && !started.get()
+                        && retries < self.conf.retry.max_retries
+                        && (self.conf.retry.hook)(&Retry {
+                            reason,
+                            method: &req.method,
+                            uri: &req.uri,
+                            retries,
+                        });
+                    if !can_retry {
+                        return Err(e);
+                    }
+                    retries += 1;
+                    debug!(?reason, uri = %req.uri, "retrying request: {e}");
+                }
+            }
+        }
+    }
+
+    /// Where to go next, if `res` is a redirect we follow
+    fn redirect_target(
+        &self,
+        req: &Request,
+        res: &Response,
+        empty_body: bool,
+        redirects: usize,
+    ) -> Option<Uri> {
+        let policy = &self.conf.redirect;
+        if policy.max_redirects == 0 {
+            return None;
+        }
+        if !matches!(res.status.as_u16(), 301 | 302 | 303 | 307 | 308) {
+            return None;
+        }
+        let drops_body = drops_body(res.status, &req.method);
+        if !drops_body && !empty_body {
+            // it may be on its way already, and can't be sent again
+            return None;
+        }
+        let location = res.headers.get(header::LOCATION)?;
+        let to = resolve_location(&req.uri, std::str::from_utf8(location).ok()?)?;
+        Origin::from_uri(&to)?;
+
+        let method = if drops_body && req.method != Method::Head {
+            Method::Get
+        } else {
+            req.method.clone()
+        };
+        (policy.hook)(&Redirect {
+            status: res.status,
+            from: &req.uri,
+            to: &to,
+            method: &method,
+            redirects,
+        })
+        .then_some(to)
+    }
+
+    /// Sends a request once
+    async fn send<D>(
+        &self,
+        req: Request,
+        body: &mut impl Body,
+        driver: D,
+    ) -> Result<D::Return, ClientError<D::Error>>
+    where
+        D: ClientDriver,
+    {
+        let origin = Origin::from_uri(&req.uri).ok_or(ClientError::InvalidUri)?;
+
+        let limit = self.conf.max_requests_per_origin.map(|max| {
+            self.limits
+                .borrow_mut()
+                .entry(origin.clone())
+                .or_insert_with(|| Rc::new(Semaphore::new(max)))
+                .clone()
+        });
+        let res = {
+            let _permit = match &limit {
+                Some(limit) => Some(limit.acquire().await.expect("never closed")),
+                None => None,
+            };
+            self.dispatch(&origin, req, body, driver).await
+        };
+        if let Some(limit) = limit {
+            // nobody else is waiting on it or holding a permit
+            if Rc::strong_count(&limit) == 2 {
+                self.limits.borrow_mut().remove(&origin);
+            }
+        }
+        res
+    }
+
+    async fn dispatch<D>(
+        &self,
+        origin: &Origin,
+        req: Request,
+        body: &mut impl Body,
+        driver: D,
+    ) -> Result<D::Return, ClientError<D::Error>>
+    where
+        D: ClientDriver,
+    {
+        let mut waited = false;
+        loop {
+            if let Some(handle) = self.h2_pool.checkout_shared(origin) {
+                if !handle.is_closed() {
+                    return self.h2_request(handle, req, body, driver).await;
+                }
+                handle.close();
+            }
+            if let Some(transport) = self.h1_pool.checkout(origin) {
+                return self.h1_request(origin, transport, req, body, driver).await;
+            }
+
+            let pending = self.connecting.borrow().get(origin).cloned();
+            match pending {
+                Some(pending) if !waited => {
+                    debug!(%origin, "waiting for a connection in progress");
+                    pending.notified().await;
+                    waited = true;
+                }
+                _ => break,
+            }
+        }
+
+        let connecting = Connecting::start(&self.connecting, origin);
+        let transport = with_timeout(
+            self.conf.connect_timeout,
+            self.connector.connect(origin),
+            || ClientError::ConnectTimeout,
+        )
+        .await?
+        .map_err(|e| ClientError::Connect(BX::from_err(e)))?;
+        let connected = with_timeout(
+            self.conf.handshake_timeout,
+            self.connector.handshake(origin, transport),
+            || ClientError::HandshakeTimeout,
+        )
+        .await?
+        .map_err(|e| ClientError::Connect(BX::from_err(e)))?;
+        let use_h2 = match connected.alpn {
+            Some(AlpnProtocol::H2) => true,
+            Some(AlpnProtocol::Http11) => false,
+            None => self.conf.h2_prior_knowledge && origin.scheme == Scheme::Http,
+        };
+        debug!(%origin, alpn = ?connected.alpn, %use_h2, "connected");
+
+        if use_h2 {
+            let (handle, conn) = h2::handshake(connected.transport, self.h2_conf.clone())?;
+            let conn_origin = origin.clone();
+            buffet::spawn(async move {
+                if let Err(e) = conn.await {
+                    debug!(origin = %conn_origin, "h2 client connection failed: {e}");
+                }
+            });
+            let handle = self.h2_pool.insert_shared(origin.clone(), handle);
+            drop(connecting);
+            self.h2_request(handle, req, body, driver).await
+        } else {
+            // nothing to share
+            drop(connecting);
+            self.h1_request(origin, connected.transport, req, body, driver)
+                .await
+        }
+    }
+
+    async fn h1_request<D>(
+        &self,
+        origin: &Origin,
+        transport: (C::Read, C::Write),
+        req: Request,
+        body: &mut impl Body,
+        driver: D,
+    ) -> Result<D::Return, ClientError<D::Error>>
+    where
+        D: ClientDriver,
+    {
+        let (transport, ret) =
+            h1::request_with_conf(transport, &self.conf.h1, req, body, driver).await?;
+        if let Some(transport) = transport {
+            self.h1_pool.checkin(origin.clone(), transport);
+        }
+        Ok(ret)
+    }
+
+    async fn h2_request<D>(
+        &self,
+        handle: Shared<h2::ClientHandle>,
+        req: Request,
+        body: &mut impl Body,
+        driver: D,
+    ) -> Result<D::Return, ClientError<D::Error>>
+    where
+        D: ClientDriver,
+    {
+        let res = handle.request(req, body, driver).await;
+        // the server's settings have arrived by now
+        if let Some(max_streams) = handle.max_concurrent_streams() {
+            handle.set_max_streams(max_streams);
+        }
+        if handle.is_closed() {
+            handle.close();
+        }
+        Ok(res?)
+    }
+}
+
+/// Runs `fut`, giving up with `on_timeout` after `timeout`, if set
+async fn with_timeout<T, E>(
+    timeout: Option<Duration>,
+    fut: impl Future<Output = T>,
+    on_timeout: impl FnOnce() -> E,
+) -> Result<T, E> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, fut)
+            .await
+            .map_err(|_| on_timeout()),
+        None => Ok(fut.await),
+    }
+}
+
+/// Whether following a redirect means sending a GET (or HEAD) request
+/// without a body, cf. <https://httpwg.org/specs/rfc9110.html#status.3xx>
+fn drops_body(status: StatusCode, method: &Method) -> bool {
+    match status.as_u16() {
+        303 => true,
+        301 | 302 => *method == Method::Post,
+        _ => false,
+    }
+}
+
+/// The request to send when following a redirect
+fn redirect_request<B: Body>(
+    mut req: Request,
+    status: StatusCode,
+    to: Uri,
+    body: &mut ReplayableBody<'_, B>,
+) -> Request {
+    if drops_body(status, &req.method) {
+        if req.method != Method::Head {
+            req.method = Method::Get;
+        }
+        body.body = None;
+        for name in [
+            header::CONTENT_LENGTH,
+            header::CONTENT_TYPE,
+            header::CONTENT_ENCODING,
+            header::TRANSFER_ENCODING,
+        ] {
+            req.headers.remove(name);
+        }
+    }
+
+    if Origin::from_uri(&req.uri) != Origin::from_uri(&to) {
+        for name in [
+            header::HOST,
+            header::AUTHORIZATION,
+            header::PROXY_AUTHORIZATION,
+            header::COOKIE,
+        ] {
+            req.headers.remove(name);
+        }
+    }
+    req.uri = to;
+    req
+}
+
+/// Resolves a `location` header value against the URI that was requested,
+/// cf. <https://www.rfc-editor.org/rfc/rfc3986#section-5.2>. Dot segments
+/// are left for the server to deal with, fragments are dropped.
+fn resolve_location(base: &Uri, location: &str) -> Option<Uri> {
+    let location = location.split('#').next().unwrap_or_default();
+    if location.contains("://") {
+        return location.parse().ok();
+    }
+
+    let scheme = base.scheme_str()?;
+    if let Some(rest) = location.strip_prefix("//") {
+        return format!("{scheme}://{rest}").parse().ok();
+    }
+
+    let authority = base.authority()?;
+    let path_and_query = if location.starts_with('/') {
+        location.to_string()
+    } else if location.is_empty() || location.starts_with('?') {
+        format!("{}{location}", base.path())
+    } else {
+        // relative to the base's "directory"
+        let path = base.path();
+        let dir = &path[..path.rfind('/').map_or(0, |i| i + 1)];
+        let dir = if dir.is_empty() { "/" } else { dir };
+        format!("{dir}{location}")
+    };
+    format!("{scheme}://{authority}{path_and_query}")
+        .parse()
+        .ok()
+}
+
+/// A request body that remembers whether any of it was read, and so whether
+/// it can be sent again
+#[derive(Debug)]
+struct ReplayableBody<'a, B> {
+    /// `None` once it's been dropped for a redirect
+    body: Option<&'a mut B>,
+    started: &'a Cell<bool>,
+}
+
+impl<B: Body> Body for ReplayableBody<'_, B> {
+    type Error = B::Error;
+
+    fn content_len(&self) -> Option<u64> {
+        match &self.body {
+            Some(body) => body.content_len(),
+            None => Some(0),
+        }
+    }
+
+    fn eof(&self) -> bool {
+        self.body.as_ref().map_or(true, |body| body.eof())
+    }
+
+    async fn next_chunk(&mut self) -> Result<BodyChunk, Self::Error> {
+        let Some(body) = &mut self.body else {
+            return Ok(BodyChunk::Done { trailers: None });
+        };
+        let chunk = body.next_chunk().await?;
+        if matches!(chunk, BodyChunk::Chunk(_)) {
+            self.started.set(true);
+        }
+        Ok(chunk)
+    }
+}
+
+enum Followed<T> {
+    Done(T),
+    Redirect { status: StatusCode, to: Uri },
+}
+
+/// Lends the caller's driver for one attempt at a request: it stays with
+/// the caller until the final response arrives, unless that's a redirect we
+/// follow.
+struct PolicyDriver<'a, D, F> {
+    driver: &'a mut Option<D>,
+    follow: F,
+}
+
+impl<D, F> ClientDriver for PolicyDriver<'_, D, F>
+where
+    D: ClientDriver,
+    F: Fn(&Response) -> Option<Uri>,
+{
+    type Return = Followed<D::Return>;
+    type Error = D::Error;
+
+    async fn on_informational_response(&mut self, res: Response) -> Result<(), Self::Error> {
+        self.driver
+            .as_mut()
+            .expect("driver is only taken for the final response")
+            .on_informational_response(res)
+            .await
+    }
+
+    async fn on_final_response(
+        self,
+        res: Response,
+        body: &mut impl Body,
+    ) -> Result<Self::Return, Self::Error> {
+        if let Some(to) = (self.follow)(&res) {
+            // read (and discard) the body, so the connection can be reused
+            let mut drained = 0;
+            while drained < MAX_DRAINED_REDIRECT_BODY {
+                match body.next_chunk().await {
+                    Ok(BodyChunk::Chunk(chunk)) => drained += chunk.len(),
+                    _ => break,
+                }
+            }
+            return Ok(Followed::Redirect {
+                status: res.status,
+                to,
+            });
+        }
+
+        let driver = self
+            .driver
+            .take()
+            .expect("final response only arrives once");
+        driver
+            .on_final_response(res, body)
+            .await
+            .map(Followed::Done)
+    }
+}
+
+/// Redirect response bodies larger than this are abandoned, along with their
+/// connection
+const MAX_DRAINED_REDIRECT_BODY: usize = 64 * 1024;
+
+/// Marks an origin as being connected to, until dropped
+struct Connecting<'a> {
+    connecting: &'a RefCell<HashMap<Origin, Rc<Notify>>>,
+    origin: &'a Origin,
+    notify: Option<Rc<Notify>>,
+}
+
+impl<'a> Connecting<'a> {
+    fn start(connecting: &'a RefCell<HashMap<Origin, Rc<Notify>>>, origin: &'a Origin) -> Self {
+        // if we waited on another connection that wasn't for sharing, it's
+        // still marked as in progress
+        let notify = match connecting.borrow_mut().entry(origin.clone()) {
+            Entry::Occupied(_) => None,
+            Entry::Vacant(entry) => Some(entry.insert(Default::default()).clone()),
+        };
+        Self {
+            connecting,
+            origin,
+            notify,
+        }
+    }
+}
+
+impl Drop for Connecting<'_> {
+    fn drop(&mut self) {
+        if let Some(notify) = self.notify.take() {
+            self.connecting.borrow_mut().remove(self.origin);
+            notify.notify_waiters();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::Uri;
+
+    use super::resolve_location;
+
+    #[test]
+    fn resolve_locations() {
+        let base: Uri = "https://example.org/a/b?c".parse().unwrap();
+        for (location, resolved) in [
+            ("http://other.org/x", "http://other.org/x"),
+            ("//other.org/x", "https://other.org/x"),
+            ("/x?y", "https://example.org/x?y"),
+            ("x", "https://example.org/a/x"),
+            ("?y", "https://example.org/a/b?y"),
+            ("/x#fragment", "https://example.org/x"),
+        ] {
+            assert_eq!(
+                resolve_location(&base, location).unwrap().to_string(),
+                resolved,
+                "{location}"
+            );
+        }
+    }
+}
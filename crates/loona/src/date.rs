@@ -0,0 +1,88 @@
+//! A coarse clock for the `date` response header, cf. <https://httpwg.org/specs/rfc9110.html#field.date>
+//!
+//! The `date` header only has second granularity, so there's no point in
+//! formatting it for every single response: we format it at most once per
+//! second, per thread, and hand out cheap clones of the resulting [Piece].
+
+use std::{
+    cell::RefCell,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use buffet::Piece;
+use http::header;
+
+use crate::Headers;
+
+struct CachedDate {
+    /// Seconds since the unix epoch `piece` was formatted for
+    secs: u64,
+    piece: Piece,
+}
+
+thread_local! {
+    static CACHED_DATE: RefCell<Option<CachedDate>> = const { RefCell::new(None) };
+}
+
+/// Returns the current date, formatted as an IMF-fixdate, e.g.
+/// `Sun, 06 Nov 1994 08:49:37 GMT`
+pub fn http_date() -> Piece {
+    let now = SystemTime::now();
+    let secs = now
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs();
+
+    CACHED_DATE.with_borrow_mut(|cached| match cached {
+        Some(cached) if cached.secs == secs => cached.piece.clone(),
+        _ => {
+            let piece: Piece = httpdate::fmt_http_date(now).into_bytes().into();
+            *cached = Some(CachedDate {
+                secs,
+                piece: piece.clone(),
+            });
+            piece
+        }
+    })
+}
+
+/// Adds the `date` and `server` headers to a response, unless the driver
+/// already set them.
+pub(crate) fn add_response_headers(
+    headers: &mut Headers,
+    date_header: bool,
+    server_header: Option<&Piece>,
+) {
+    if date_header && !headers.contains_key(header::DATE) {
+        headers.insert(header::DATE, http_date());
+    }
+    if let Some(server) = server_header {
+        if !headers.contains_key(header::SERVER) {
+            headers.insert(header::SERVER, server.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn http_date_is_valid_and_cached() {
+        let a = http_date();
+        let b = http_date();
+
+        let a_str = std::str::from_utf8(&a[..]).unwrap();
+        let parsed = httpdate::parse_http_date(a_str).unwrap();
+        let delta = SystemTime::now()
+            .duration_since(parsed)
+            .unwrap_or(Duration::ZERO);
+        assert!(delta < Duration::from_secs(2));
+
+        // unless we crossed a second boundary, the second call is served
+        // from the cache
+        if a[..] == b[..] {
+            assert_eq!(a.as_ptr(), b.as_ptr());
+        }
+    }
+}
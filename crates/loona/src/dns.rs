@@ -0,0 +1,199 @@
+//! Name resolution and connection racing for the client, cf. [Resolve] and
+//! [happy_eyeballs]
+//!
+//! Hosts often resolve to several addresses, of both families. Trying them
+//! one after the other means waiting for each dead address to time out
+//! before moving on: instead, we follow "Happy Eyeballs"
+//! (<https://www.rfc-editor.org/rfc/rfc8305>) and start a new attempt every
+//! [HappyEyeballs::attempt_delay] until one succeeds, alternating between
+//! IPv6 and IPv4.
+
+use std::{future::Future, io, net::SocketAddr, pin::Pin, time::Duration};
+
+use futures_util::{stream::FuturesUnordered, StreamExt};
+use tracing::debug;
+
+/// Resolves host names to addresses, for [crate::client::TcpConnector]
+#[allow(async_fn_in_trait)] // we never require Send
+pub trait Resolve {
+    /// Returns the addresses `host` resolves to, in order of preference.
+    /// `host` is a domain name or an IP address, without brackets.
+    async fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>>;
+}
+
+/// Resolves names with the system's resolver (`getaddrinfo`), on a blocking
+/// thread
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl Resolve for SystemResolver {
+    async fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        if let Ok(ip) = host.parse() {
+            return Ok(vec![SocketAddr::new(ip, port)]);
+        }
+        Ok(tokio::net::lookup_host((host, port)).await?.collect())
+    }
+}
+
+impl<R: Resolve> Resolve for std::rc::Rc<R> {
+    async fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        (**self).resolve(host, port).await
+    }
+}
+
+/// Settings for [happy_eyeballs]
+#[derive(Debug, Clone, Copy)]
+pub struct HappyEyeballs {
+    /// How long to give an attempt before starting the next one, if it
+    /// hasn't failed by then: the "Connection Attempt Delay" of RFC 8305.
+    /// Zero tries every address at once.
+    pub attempt_delay: Duration,
+}
+
+impl Default for HappyEyeballs {
+    fn default() -> Self {
+        Self {
+            // what RFC 8305 recommends
+            attempt_delay: Duration::from_millis(250),
+        }
+    }
+}
+
+/// Connects to one of `addrs` with `connect`, racing attempts as
+/// [HappyEyeballs] says: the first connection made wins, and the other
+/// attempts are dropped. Returns the last error if they all fail.
+pub async fn happy_eyeballs<T, F>(
+    conf: HappyEyeballs,
+    addrs: Vec<SocketAddr>,
+    connect: impl Fn(SocketAddr) -> F,
+) -> io::Result<T>
+where
+    F: Future<Output = io::Result<T>>,
+{
+    let mut pending = interleave_families(addrs).into_iter();
+    let mut attempts: FuturesUnordered<Pin<Box<dyn Future<Output = io::Result<T>>>>> =
+        Default::default();
+    let mut last_err = None;
+
+    loop {
+        if attempts.is_empty() {
+            let Some(addr) = pending.next() else {
+                return Err(last_err.unwrap_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, "no addresses to connect to")
+                }));
+            };
+            debug!(%addr, "connecting");
+            attempts.push(Box::pin(connect(addr)));
+        }
+
+        let delay = tokio::time::sleep(conf.attempt_delay);
+        tokio::select! {
+            res = attempts.next() => match res.expect("attempts is not empty") {
+                Ok(conn) => return Ok(conn),
+                Err(e) => {
+                    debug!("connection attempt failed: {e}");
+                    last_err = Some(e);
+                    // the next attempt starts right away, cf. RFC 8305
+                    // section 5
+                    if let Some(addr) = pending.next() {
+                        debug!(%addr, "connecting");
+                        attempts.push(Box::pin(connect(addr)));
+                    }
+                }
+            },
+            _ = delay, if pending.len() > 0 => {
+                let addr = pending.next().expect("checked above");
+                debug!(%addr, "connecting, previous attempts are taking a while");
+                attempts.push(Box::pin(connect(addr)));
+            }
+        }
+    }
+}
+
+/// Orders addresses so that families alternate, starting with the family of
+/// the first address, and keeping the order within each family, cf. RFC 8305
+/// section 4
+fn interleave_families(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let Some(first) = addrs.first() else {
+        return addrs;
+    };
+    let first_is_v6 = first.is_ipv6();
+    let mut out = Vec::with_capacity(addrs.len());
+    let (preferred, other): (Vec<_>, Vec<_>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == first_is_v6);
+    let (mut preferred, mut other) = (preferred.into_iter(), other.into_iter());
+    loop {
+        match (preferred.next(), other.next()) {
+            (None, None) => return out,
+            (a, b) => out.extend(a.into_iter().chain(b)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, net::SocketAddr, time::Duration};
+
+    use super::{happy_eyeballs, interleave_families, HappyEyeballs};
+
+    #[test]
+    fn interleaves_families() {
+        let addrs: Vec<SocketAddr> = [
+            "[::1]:80",
+            "[::2]:80",
+            "[::3]:80",
+            "127.0.0.1:80",
+            "127.0.0.2:80",
+        ]
+        .iter()
+        .map(|addr| addr.parse().unwrap())
+        .collect();
+        let ordered: Vec<String> = interleave_families(addrs)
+            .iter()
+            .map(|addr| addr.to_string())
+            .collect();
+        assert_eq!(
+            ordered,
+            [
+                "[::1]:80",
+                "127.0.0.1:80",
+                "[::2]:80",
+                "127.0.0.2:80",
+                "[::3]:80"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn stalled_attempts_dont_hold_up_others() {
+        let conf = HappyEyeballs {
+            attempt_delay: Duration::from_millis(20),
+        };
+        let stalled: SocketAddr = "[::1]:1".parse().unwrap();
+        let reachable: SocketAddr = "127.0.0.1:2".parse().unwrap();
+
+        // an attempt that hangs doesn't hold up the others for longer than
+        // the attempt delay
+        let winner = tokio::time::timeout(
+            Duration::from_secs(1),
+            happy_eyeballs(conf, vec![stalled, reachable], |addr| async move {
+                if addr == stalled {
+                    std::future::pending::<()>().await;
+                }
+                Ok(addr)
+            }),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(winner, reachable);
+
+        // when they all fail, the last error is returned
+        let res = happy_eyeballs(conf, vec![stalled, reachable], |addr| async move {
+            Err::<(), _>(io::Error::other(addr.to_string()))
+        })
+        .await;
+        assert_eq!(res.unwrap_err().to_string(), "127.0.0.1:2");
+    }
+}
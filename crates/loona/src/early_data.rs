@@ -0,0 +1,133 @@
+//! TLS 1.3 early data (0-RTT): requests a client sends along with its
+//! ClientHello, before the handshake completes. That saves a round-trip, but
+//! whoever recorded the connection can replay early data, cf.
+//! <https://www.rfc-editor.org/rfc/rfc8470>.
+//!
+//! Requests that came in early data carry an `early-data: 1` header, cf.
+//! [Request::is_early_data]: either a TLS-terminating proxy in front of us
+//! added it, or their head (the request line and headers, or a HEADERS frame
+//! and its CONTINUATIONs) ended within the early data of the connection, cf.
+//! [crate::ConnectionInfo::early_data_len]. A request whose head only starts
+//! there isn't marked: replaying the early data alone doesn't get it
+//! processed.
+//!
+//! By default, marked requests with methods that aren't idempotent are
+//! answered with 425 Too Early rather than handed to the driver, cf.
+//! [crate::h1::ServerConf::reject_unsafe_early_data]. Clients retry them
+//! once the handshake is done.
+
+use std::{cell::Cell, os::fd::RawFd, rc::Rc};
+
+use buffet::{
+    bufpool::{BufResult, IoBufMut},
+    ReadOwned,
+};
+use http::{HeaderName, StatusCode};
+
+use crate::Request;
+
+/// cf. <https://www.rfc-editor.org/rfc/rfc8470#section-5.1>
+pub(crate) static EARLY_DATA: HeaderName = HeaderName::from_static("early-data");
+
+/// 425 Too Early, cf. <https://www.rfc-editor.org/rfc/rfc8470#section-5.2>
+pub(crate) fn too_early() -> StatusCode {
+    StatusCode::from_u16(425).unwrap()
+}
+
+/// Whether `req` should be answered with 425 Too Early instead of being
+/// processed: it came in early data, and sending it twice could do harm.
+pub(crate) fn is_unsafe(req: &Request) -> bool {
+    req.is_early_data() && !req.method.is_idempotent()
+}
+
+/// Keeps track of how far into a connection we've read, to tell which
+/// requests came in its early data
+#[derive(Clone)]
+pub(crate) struct EarlyData {
+    inner: Rc<EarlyDataInner>,
+}
+
+struct EarlyDataInner {
+    /// How many bytes at the start of the connection came in early data
+    len: u64,
+
+    /// How many bytes of the connection we've read so far
+    read: Cell<u64>,
+
+    /// HTTP/2: the last stream whose request headers ended within early
+    /// data. Stream IDs only go up, so every stream up to that one did too.
+    last_stream_id: Cell<u32>,
+}
+
+impl EarlyData {
+    /// For a connection whose first `len` bytes came in early data, of which
+    /// `already_read` were read before serving it (e.g. they're in
+    /// `client_buf`)
+    pub(crate) fn new(len: usize, already_read: usize) -> Self {
+        Self {
+            inner: Rc::new(EarlyDataInner {
+                len: len as u64,
+                read: Cell::new(already_read as u64),
+                last_stream_id: Default::default(),
+            }),
+        }
+    }
+
+    /// For connections without early data
+    pub(crate) fn none() -> Self {
+        Self::new(0, 0)
+    }
+
+    /// Counts bytes read through `inner`
+    pub(crate) fn wrap<R: ReadOwned>(&self, inner: R) -> EarlyDataRead<R> {
+        EarlyDataRead {
+            inner,
+            early_data: self.clone(),
+        }
+    }
+
+    /// Whether everything read so far, but the last `unconsumed` bytes, came
+    /// in early data
+    pub(crate) fn covers(&self, unconsumed: usize) -> bool {
+        let inner = &self.inner;
+        inner.len > 0 && inner.read.get() - unconsumed as u64 <= inner.len
+    }
+
+    /// Records that the request headers of `stream_id` ended within early
+    /// data
+    pub(crate) fn mark_stream(&self, stream_id: u32) {
+        let last = &self.inner.last_stream_id;
+        last.set(last.get().max(stream_id));
+    }
+
+    /// Whether the request headers of `stream_id` ended within early data
+    pub(crate) fn is_stream_marked(&self, stream_id: u32) -> bool {
+        stream_id <= self.inner.last_stream_id.get()
+    }
+}
+
+/// Reads through to a transport, cf. [EarlyData::wrap]
+pub(crate) struct EarlyDataRead<R> {
+    inner: R,
+    early_data: EarlyData,
+}
+
+impl<R: ReadOwned> ReadOwned for EarlyDataRead<R> {
+    async fn read_owned<B: IoBufMut>(&mut self, buf: B) -> BufResult<usize, B> {
+        let (res, buf) = self.inner.read_owned(buf).await;
+        if let Ok(n) = &res {
+            let read = &self.early_data.inner.read;
+            read.set(read.get() + *n as u64);
+        }
+        (res, buf)
+    }
+
+    fn raw_fd(&self) -> Option<RawFd> {
+        // past early data, there's nothing left to keep count of
+        let inner = &self.early_data.inner;
+        if inner.read.get() < inner.len {
+            return None;
+        }
+        self.inner.raw_fd()
+    }
+}
@@ -1,9 +1,11 @@
 use std::error::Error as StdError;
-use std::fmt;
+use std::{fmt, rc::Rc};
 
 use b_x::BX;
+use buffet::Piece;
+use http::{header, StatusCode};
 
-use crate::h2::types::H2ConnectionError;
+use crate::{h2::types::H2ConnectionError, Response};
 
 #[non_exhaustive]
 #[derive(Debug, thiserror::Error)]
@@ -28,6 +30,68 @@ pub enum ServeError<DriverError> {
     /// An error occurred during memory allocation
     #[error("Memory allocation error: {0}")]
     Alloc(#[from] buffet::bufpool::Error),
+
+    /// HTTP/1.1 only: the server driver panicked after it started sending a
+    /// response (or while handling a CONNECT request), so the connection had
+    /// to be closed
+    #[error("Server driver panicked mid-response: {}", message.as_deref().unwrap_or("(no message)"))]
+    DriverPanicked { message: Option<String> },
+}
+
+/// A server configuration whose settings don't hold together, cf.
+/// [crate::h1::ServerConfBuilder::build] and
+/// [crate::h2::ServerConfBuilder::build]
+#[non_exhaustive]
+#[derive(Debug, thiserror::Error)]
+pub enum ConfError {
+    #[error("{setting} must be between {min} and {max}, got {actual}")]
+    OutOfRange {
+        setting: &'static str,
+        min: u64,
+        max: u64,
+        actual: u64,
+    },
+
+    #[error("{setting} must not be zero")]
+    Zero { setting: &'static str },
+
+    #[error("{setting} ({actual}) must not exceed {other} ({limit})")]
+    Exceeds {
+        setting: &'static str,
+        actual: u64,
+        other: &'static str,
+        limit: u64,
+    },
+}
+
+/// Why a handler didn't finish its response
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum HandlerFailure<'a> {
+    /// The driver returned an error
+    Error(&'a (dyn StdError + 'static)),
+
+    /// The driver panicked, with this message (if the panic payload was a
+    /// string)
+    Panic(Option<&'a str>),
+}
+
+/// Called when a handler fails before it sent the final response headers,
+/// to build the response (and body) to send instead. This is also a good
+/// place to log failures.
+///
+/// If it fails after that, there's nothing to do but to reset the stream
+/// (HTTP/2) or close the connection (HTTP/1.1).
+pub type ErrorHook = Rc<dyn Fn(&HandlerFailure<'_>) -> (Response, Piece)>;
+
+/// The default [ErrorHook]: an empty 500 response
+pub fn default_error_response(_failure: &HandlerFailure<'_>) -> (Response, Piece) {
+    let mut res = Response {
+        status: StatusCode::INTERNAL_SERVER_ERROR,
+        ..Default::default()
+    };
+    res.headers.insert(header::CONTENT_LENGTH, "0".into());
+    (res, Piece::empty())
 }
 
 impl<DriverError> From<ServeError<DriverError>> for BX
@@ -0,0 +1,579 @@
+//! Serving files from a directory
+//!
+//! [FileServer] is a [ServerDriver] that maps request paths onto a root
+//! directory, and answers GET and HEAD requests with the files it finds
+//! there (or their `index.html`, for directories). Responses carry an
+//! `etag` and a `last-modified` header, so that clients can revalidate with
+//! `if-none-match` / `if-modified-since` and get a `304 Not Modified`, and
+//! single byte ranges are honored, cf. <https://httpwg.org/specs/rfc9110.html#range.requests>.
+//!
+//! Files are opened and read with [buffet::fs], into pooled buffers, one
+//! buffer's worth at a time: a client that reads slowly doesn't make us read
+//! ahead.
+
+use std::{
+    ffi::OsStr,
+    fmt,
+    fs::Metadata,
+    io,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use buffet::{
+    bufpool::{BufResult, IoBufMut},
+    fs::File,
+    ReadOwned, RollMut,
+};
+use http::{header, StatusCode};
+use tracing::debug;
+
+use crate::{
+    Body, BodyChunk, Encoder, ExpectResponseHeaders, Headers, Method, Request, Responder,
+    ResponderError, ResponderOrBodyError, Response, ResponseDone, ServerDriver,
+};
+
+/// A [ServerDriver] that serves the files under a directory
+#[derive(Debug, Clone)]
+pub struct FileServer {
+    root: PathBuf,
+    /// `root` with every symlink resolved: files whose canonical path isn't
+    /// under it are not served
+    canonical_root: PathBuf,
+    prefix: String,
+    index_file: Option<String>,
+}
+
+impl FileServer {
+    /// Serves the files under `root`: `/a/b.txt` is `root/a/b.txt`
+    ///
+    /// Symlinks are followed, but only to files that are under `root` too.
+    /// `root` should exist by the time this is called, since that's when
+    /// its own symlinks get resolved.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        Self {
+            canonical_root: std::fs::canonicalize(&root).unwrap_or_else(|_| root.clone()),
+            root,
+            prefix: "/".to_string(),
+            index_file: Some("index.html".to_string()),
+        }
+    }
+
+    /// Only serves requests whose path starts with `prefix`, which gets
+    /// stripped: with a prefix of `/static`, `/static/a/b.txt` is
+    /// `root/a/b.txt`, and `/a/b.txt` is not found.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        let mut prefix = prefix.into();
+        if !prefix.ends_with('/') {
+            prefix.push('/');
+        }
+        self.prefix = prefix;
+        self
+    }
+
+    /// Sets the file to serve for directories, `index.html` by default.
+    /// With `None`, directories are not found.
+    pub fn with_index_file(mut self, index_file: Option<String>) -> Self {
+        self.index_file = index_file;
+        self
+    }
+
+    /// Where the file for `path` would be, if `path` is under our prefix
+    /// and doesn't try to escape the root. Returns whether `path` named a
+    /// directory (with a trailing slash) along with it.
+    fn resolve(&self, path: &str) -> Option<(PathBuf, bool)> {
+        let rest = match path.strip_prefix(&self.prefix) {
+            Some(rest) => rest,
+            // `/static` for a `/static/` prefix
+            None if path == self.prefix.trim_end_matches('/') => "",
+            None => return None,
+        };
+
+        let mut resolved = self.root.clone();
+        for segment in rest.split('/') {
+            let segment = percent_decode(segment)?;
+            match &segment[..] {
+                b"" | b"." => continue,
+                b".." => return None,
+                _ if segment.contains(&b'/') || segment.contains(&0) => return None,
+                _ => resolved.push(OsStr::from_bytes(&segment)),
+            }
+        }
+        Some((resolved, rest.is_empty() || rest.ends_with('/')))
+    }
+
+    /// Opens `path`, which [FileServer::resolve] returned, as long as it
+    /// doesn't lead out of the root once symlinks are resolved.
+    async fn open(&self, path: &Path) -> io::Result<(Metadata, File)> {
+        let file = File::open(path).await?;
+        let canonical = file.canonical_path()?;
+        if !canonical.starts_with(&self.canonical_root) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{} is outside of the root", canonical.display()),
+            ));
+        }
+        Ok((file.metadata()?, file))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum FileServerError<EncoderError> {
+    #[error("error writing response: {0}")]
+    Responder(#[from] ResponderError<EncoderError>),
+
+    /// Reading the file failed after the response started
+    #[error("error reading file: {0}")]
+    Read(#[source] io::Error),
+}
+
+impl<E> From<ResponderOrBodyError<E, io::Error>> for FileServerError<E> {
+    fn from(e: ResponderOrBodyError<E, io::Error>) -> Self {
+        match e {
+            ResponderOrBodyError::Responder(e) => Self::Responder(e),
+            ResponderOrBodyError::Body(e) => Self::Read(e),
+        }
+    }
+}
+
+impl<OurEncoder> ServerDriver<OurEncoder> for FileServer
+where
+    OurEncoder: Encoder,
+{
+    type Error = FileServerError<OurEncoder::Error>;
+
+    async fn handle(
+        &self,
+        req: Request,
+        _req_body: &mut impl Body,
+        respond: Responder<OurEncoder, ExpectResponseHeaders>,
+    ) -> Result<Responder<OurEncoder, ResponseDone>, Self::Error> {
+        if !matches!(req.method, Method::Get | Method::Head) {
+            let mut headers = Headers::default();
+            headers.insert(header::ALLOW, "GET, HEAD".into());
+            return empty_response(respond, StatusCode::METHOD_NOT_ALLOWED, headers).await;
+        }
+
+        let Some((mut path, is_dir_path)) = self.resolve(req.uri.path()) else {
+            return not_found(respond).await;
+        };
+
+        let (mut meta, mut file) = match self.open(&path).await {
+            Ok(opened) => opened,
+            Err(e) => {
+                debug!(?path, %e, "can't serve file");
+                return not_found(respond).await;
+            }
+        };
+        if meta.is_dir() {
+            if !is_dir_path {
+                // so that relative links in the index file work
+                let mut location = format!("{}/", req.uri.path());
+                if let Some(query) = req.uri.query() {
+                    location.push('?');
+                    location.push_str(query);
+                }
+                let mut headers = Headers::default();
+                headers.insert(header::LOCATION, location.into_bytes().into());
+                return empty_response(respond, StatusCode::MOVED_PERMANENTLY, headers).await;
+            }
+            let Some(index_file) = &self.index_file else {
+                return not_found(respond).await;
+            };
+            path.push(index_file);
+            (meta, file) = match self.open(&path).await {
+                Ok(opened) => opened,
+                Err(e) => {
+                    debug!(?path, %e, "can't serve file");
+                    return not_found(respond).await;
+                }
+            };
+        }
+        if !meta.is_file() {
+            return not_found(respond).await;
+        }
+
+        let len = meta.len();
+        let modified = meta.modified().ok();
+        let etag = etag(len, modified);
+
+        let mut headers = Headers::default();
+        headers.insert(header::ETAG, etag.clone().into_bytes().into());
+        if let Some(modified) = modified {
+            let last_modified = httpdate::fmt_http_date(modified);
+            headers.insert(header::LAST_MODIFIED, last_modified.into_bytes().into());
+        }
+
+        if is_not_modified(&req.headers, &etag, modified) {
+            return empty_response(respond, StatusCode::NOT_MODIFIED, headers).await;
+        }
+
+        headers.insert(header::ACCEPT_RANGES, "bytes".into());
+        headers.insert(header::CONTENT_TYPE, mime_type(&path).into());
+
+        let mut status = StatusCode::OK;
+        let mut range = 0..len;
+        if let Some(value) = req.headers.get(header::RANGE) {
+            if if_range_matches(&req.headers, &etag, modified) {
+                match parse_range(value, len) {
+                    Ok(Some(parsed)) => {
+                        let content_range =
+                            format!("bytes {}-{}/{len}", parsed.start, parsed.end - 1);
+                        headers.insert(header::CONTENT_RANGE, content_range.into_bytes().into());
+                        status = StatusCode::PARTIAL_CONTENT;
+                        range = parsed;
+                    }
+                    Ok(None) => {}
+                    Err(Unsatisfiable) => {
+                        let content_range = format!("bytes */{len}");
+                        headers.insert(header::CONTENT_RANGE, content_range.into_bytes().into());
+                        return empty_response(respond, StatusCode::RANGE_NOT_SATISFIABLE, headers)
+                            .await;
+                    }
+                }
+            }
+        }
+
+        let content_len = range.end - range.start;
+        headers.insert(
+            header::CONTENT_LENGTH,
+            content_len.to_string().into_bytes().into(),
+        );
+        let res = Response {
+            status,
+            headers,
+            ..Default::default()
+        };
+
+        if req.method == Method::Head {
+            return Ok(respond.write_final_response_without_body(res).await?);
+        }
+        let mut body = FileBody::new(file, range.start, content_len);
+        Ok(respond
+            .write_final_response_with_body(res, &mut body)
+            .await?)
+    }
+}
+
+async fn not_found<OurEncoder>(
+    respond: Responder<OurEncoder, ExpectResponseHeaders>,
+) -> Result<Responder<OurEncoder, ResponseDone>, FileServerError<OurEncoder::Error>>
+where
+    OurEncoder: Encoder,
+{
+    empty_response(respond, StatusCode::NOT_FOUND, Default::default()).await
+}
+
+async fn empty_response<OurEncoder>(
+    respond: Responder<OurEncoder, ExpectResponseHeaders>,
+    status: StatusCode,
+    headers: Headers,
+) -> Result<Responder<OurEncoder, ResponseDone>, FileServerError<OurEncoder::Error>>
+where
+    OurEncoder: Encoder,
+{
+    let mut res = Response {
+        status,
+        headers,
+        ..Default::default()
+    };
+    if !res.means_empty_body() {
+        res.headers.insert(header::CONTENT_LENGTH, "0".into());
+    }
+    Ok(respond.write_final_response_without_body(res).await?)
+}
+
+/// Changes whenever the file's size or modification time does
+fn etag(len: u64, modified: Option<SystemTime>) -> String {
+    let modified = modified
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default();
+    format!(
+        "\"{:x}.{:x}-{len:x}\"",
+        modified.as_secs(),
+        modified.subsec_nanos()
+    )
+}
+
+/// Whether the client's cached copy is still fresh, cf. <https://httpwg.org/specs/rfc9110.html#field.if-none-match>
+/// and <https://httpwg.org/specs/rfc9110.html#field.if-modified-since>
+fn is_not_modified(headers: &Headers, etag: &str, modified: Option<SystemTime>) -> bool {
+    if headers.contains_key(header::IF_NONE_MATCH) {
+        // weak comparison: a `W/` prefix doesn't matter
+        return headers
+            .get_all(header::IF_NONE_MATCH)
+            .iter()
+            .flat_map(|value| value.split(|&b| b == b','))
+            .map(|tag| tag.trim_ascii())
+            .any(|tag| tag == b"*" || tag.strip_prefix(b"W/").unwrap_or(tag) == etag.as_bytes());
+    }
+
+    match (header_date(headers, header::IF_MODIFIED_SINCE), modified) {
+        (Some(since), Some(modified)) => truncate_to_secs(modified) <= since,
+        _ => false,
+    }
+}
+
+/// Whether a `range` header should be honored, cf. <https://httpwg.org/specs/rfc9110.html#field.if-range>.
+/// If-Range needs a strong match, which ours are.
+fn if_range_matches(headers: &Headers, etag: &str, modified: Option<SystemTime>) -> bool {
+    let Some(value) = headers.get(header::IF_RANGE) else {
+        return true;
+    };
+    if value.starts_with(b"\"") {
+        return &value[..] == etag.as_bytes();
+    }
+    match (header_date(headers, header::IF_RANGE), modified) {
+        (Some(date), Some(modified)) => truncate_to_secs(modified) == date,
+        _ => false,
+    }
+}
+
+fn header_date(headers: &Headers, name: header::HeaderName) -> Option<SystemTime> {
+    let value = std::str::from_utf8(headers.get(name)?).ok()?;
+    httpdate::parse_http_date(value).ok()
+}
+
+/// HTTP dates only have a one-second resolution
+fn truncate_to_secs(time: SystemTime) -> SystemTime {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => UNIX_EPOCH + std::time::Duration::from_secs(d.as_secs()),
+        Err(_) => time,
+    }
+}
+
+struct Unsatisfiable;
+
+/// Parses a `range` header into the byte range to serve, cf. <https://httpwg.org/specs/rfc9110.html#field.range>.
+/// Returns `None` when the whole file should be served instead: for
+/// multiple ranges (which we don't do), other units, or a header we can't
+/// make sense of.
+fn parse_range(value: &[u8], len: u64) -> Result<Option<std::ops::Range<u64>>, Unsatisfiable> {
+    let Some(spec) = value.strip_prefix(b"bytes=") else {
+        return Ok(None);
+    };
+    let Ok(spec) = std::str::from_utf8(spec) else {
+        return Ok(None);
+    };
+    if spec.contains(',') {
+        return Ok(None);
+    }
+    let Some((first, last)) = spec.trim().split_once('-') else {
+        return Ok(None);
+    };
+
+    let range = match (first, last) {
+        // the last `n` bytes
+        ("", n) => {
+            let Ok(n) = n.parse::<u64>() else {
+                return Ok(None);
+            };
+            if n == 0 {
+                return Err(Unsatisfiable);
+            }
+            len.saturating_sub(n)..len
+        }
+        (first, last) => {
+            let Ok(first) = first.parse::<u64>() else {
+                return Ok(None);
+            };
+            let last = match last {
+                "" => len.saturating_sub(1),
+                last => match last.parse::<u64>() {
+                    Ok(last) if last >= first => last.min(len.saturating_sub(1)),
+                    _ => return Ok(None),
+                },
+            };
+            if first >= len {
+                return Err(Unsatisfiable);
+            }
+            first..last + 1
+        }
+    };
+    Ok(Some(range))
+}
+
+/// Decodes `%xx` escapes, cf. <https://www.rfc-editor.org/rfc/rfc3986#section-2.1>.
+/// Returns `None` for malformed ones.
+fn percent_decode(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len());
+    let mut bytes = s.bytes();
+    while let Some(b) = bytes.next() {
+        if b != b'%' {
+            out.push(b);
+            continue;
+        }
+        let hi = (bytes.next()? as char).to_digit(16)?;
+        let lo = (bytes.next()? as char).to_digit(16)?;
+        out.push((hi * 16 + lo) as u8);
+    }
+    Some(out)
+}
+
+/// Guesses a `content-type` from the file extension
+pub fn mime_type(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+    match ext.as_deref() {
+        Some("html" | "htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js" | "mjs") => "text/javascript; charset=utf-8",
+        Some("json" | "map") => "application/json",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("md") => "text/markdown; charset=utf-8",
+        Some("csv") => "text/csv; charset=utf-8",
+        Some("xml") => "application/xml",
+        Some("wasm") => "application/wasm",
+        Some("pdf") => "application/pdf",
+        Some("zip") => "application/zip",
+        Some("gz") => "application/gzip",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("avif") => "image/avif",
+        Some("ico") => "image/x-icon",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("ttf") => "font/ttf",
+        Some("otf") => "font/otf",
+        Some("mp4") => "video/mp4",
+        Some("webm") => "video/webm",
+        Some("mp3") => "audio/mpeg",
+        Some("ogg") => "audio/ogg",
+        Some("wav") => "audio/wav",
+        _ => "application/octet-stream",
+    }
+}
+
+/// `len` bytes of a file, from `offset` on
+pub struct FileBody {
+    file: File,
+    offset: u64,
+    remaining: u64,
+    content_len: u64,
+    buf: Option<RollMut>,
+}
+
+impl fmt::Debug for FileBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileBody")
+            .field("offset", &self.offset)
+            .field("remaining", &self.remaining)
+            .finish()
+    }
+}
+
+impl FileBody {
+    pub fn new(file: File, offset: u64, len: u64) -> Self {
+        Self {
+            file,
+            offset,
+            remaining: len,
+            content_len: len,
+            buf: None,
+        }
+    }
+}
+
+impl Body for FileBody {
+    type Error = io::Error;
+
+    fn content_len(&self) -> Option<u64> {
+        Some(self.content_len)
+    }
+
+    fn eof(&self) -> bool {
+        self.remaining == 0
+    }
+
+    async fn next_chunk(&mut self) -> Result<BodyChunk, Self::Error> {
+        if self.remaining == 0 {
+            return Ok(BodyChunk::Done { trailers: None });
+        }
+
+        let mut buf = match self.buf.take() {
+            Some(buf) => buf,
+            None => RollMut::alloc().map_err(io::Error::other)?,
+        };
+        buf.reserve().map_err(io::Error::other)?;
+
+        let limit = usize::try_from(self.remaining).unwrap_or(usize::MAX);
+        let mut reader = ReadAt {
+            file: &self.file,
+            offset: self.offset,
+        };
+        let (res, mut buf) = buf.read_into(limit, &mut reader).await;
+        let n = res? as u64;
+        if n == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "file got shorter while serving it",
+            ));
+        }
+        self.offset += n;
+        self.remaining -= n;
+
+        let chunk = buf.take_all();
+        self.buf = Some(buf);
+        Ok(BodyChunk::Chunk(chunk.into()))
+    }
+}
+
+/// Reads a file from a given offset
+struct ReadAt<'a> {
+    file: &'a File,
+    offset: u64,
+}
+
+impl ReadOwned for ReadAt<'_> {
+    async fn read_owned<B: IoBufMut>(&mut self, buf: B) -> BufResult<usize, B> {
+        self.file.read_at(buf, self.offset).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_range, percent_decode, FileServer};
+
+    #[test]
+    fn test_parse_range() {
+        let parse = |value: &str| parse_range(value.as_bytes(), 100).map_err(|_| ());
+        assert_eq!(parse("bytes=0-9"), Ok(Some(0..10)));
+        assert_eq!(parse("bytes=90-"), Ok(Some(90..100)));
+        assert_eq!(parse("bytes=90-1000"), Ok(Some(90..100)));
+        assert_eq!(parse("bytes=-10"), Ok(Some(90..100)));
+        assert_eq!(parse("bytes=-1000"), Ok(Some(0..100)));
+        assert_eq!(parse("bytes=0-1,5-6"), Ok(None));
+        assert_eq!(parse("bytes=9-0"), Ok(None));
+        assert_eq!(parse("items=0-9"), Ok(None));
+        assert_eq!(parse("bytes=100-"), Err(()));
+        assert_eq!(parse("bytes=-0"), Err(()));
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(percent_decode("a%20b%2F").as_deref(), Some(&b"a b/"[..]));
+        assert_eq!(percent_decode("a%2"), None);
+
+        let server = FileServer::new("/srv").with_prefix("/static");
+        let resolve = |path| server.resolve(path).map(|(p, d)| (p.into_os_string(), d));
+        assert_eq!(
+            resolve("/static/a/./b.txt"),
+            Some(("/srv/a/b.txt".into(), false))
+        );
+        assert_eq!(resolve("/static/a/"), Some(("/srv/a".into(), true)));
+        assert_eq!(resolve("/static"), Some(("/srv".into(), true)));
+        assert_eq!(resolve("/staticky"), None);
+        assert_eq!(resolve("/a/b.txt"), None);
+        assert_eq!(resolve("/static/../etc/passwd"), None);
+        assert_eq!(resolve("/static/%2e%2e/etc/passwd"), None);
+        assert_eq!(resolve("/static/a%2Fb"), None);
+    }
+}
@@ -1,21 +1,39 @@
-use std::fmt;
+use std::{fmt, os::fd::RawFd, time::Duration};
 
 use tracing::debug;
 
-use crate::{util::read_and_parse, Body, BodyChunk, BodyError};
+use crate::{
+    cancel::{CancelReason, Cancellation},
+    util::read_and_parse,
+    Body, BodyChunk, BodyError,
+};
 use buffet::{Piece, PieceList, ReadOwned, RollMut, WriteOwned};
 
-/// An HTTP/1.1 body, either chunked or content-length.
+/// How long a chunk size line can be, chunk extensions included
+const MAX_CHUNK_HEADER_LEN: usize = 4 * 1024;
+
+/// How large the trailer section of a chunked body can be
+const MAX_TRAILERS_LEN: usize = 64 * 1024;
+
+/// An HTTP/1.1 body, either chunked, content-length, or the raw bytes of a
+/// CONNECT tunnel.
 pub(crate) struct H1Body<T> {
     transport_r: T,
     buf: Option<RollMut>,
     state: Decoder,
+
+    /// fired if we fail to read the body: the connection is unusable after that
+    cancellation: Cancellation,
+
+    /// how long a single `next_chunk` call can take, if limited
+    read_timeout: Option<Duration>,
 }
 
 #[derive(Debug)]
 enum Decoder {
     Chunked(ChunkedDecoder),
     ContentLength(ContentLengthDecoder),
+    Tunnel(TunnelDecoder),
 }
 
 #[derive(Debug)]
@@ -33,10 +51,17 @@ struct ContentLengthDecoder {
     read: u64,
 }
 
+#[derive(Debug)]
+struct TunnelDecoder {
+    done: bool,
+}
+
 #[derive(Debug)]
 pub(crate) enum H1BodyKind {
     Chunked,
     ContentLength(u64),
+    /// Everything the client sends until it closes the connection
+    Tunnel,
 }
 
 impl<T> fmt::Debug for H1Body<T> {
@@ -54,14 +79,29 @@ impl<T: ReadOwned> H1Body<T> {
             H1BodyKind::ContentLength(len) => {
                 Decoder::ContentLength(ContentLengthDecoder { len, read: 0 })
             }
+            H1BodyKind::Tunnel => Decoder::Tunnel(TunnelDecoder { done: false }),
         };
         H1Body {
             transport_r,
             buf: Some(buf),
             state,
+            cancellation: Default::default(),
+            read_timeout: None,
         }
     }
 
+    pub(crate) fn with_cancellation(mut self, cancellation: Cancellation) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Makes `next_chunk` fail with [BodyError::ReadTimeout] if it takes
+    /// longer than `timeout`, e.g. because the peer stopped sending.
+    pub(crate) fn with_read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
     /// Returns the inner buffer and transport, but only if the body has been
     /// fully read.
     pub(crate) fn into_inner(self) -> Option<(RollMut, T)> {
@@ -78,7 +118,7 @@ impl<OurReadOwned: ReadOwned> Body for H1Body<OurReadOwned> {
 
     fn content_len(&self) -> Option<u64> {
         match &self.state {
-            Decoder::Chunked(_) => None,
+            Decoder::Chunked(_) | Decoder::Tunnel(_) => None,
             Decoder::ContentLength(state) => Some(state.len),
         }
     }
@@ -88,18 +128,48 @@ impl<OurReadOwned: ReadOwned> Body for H1Body<OurReadOwned> {
             return Ok(BodyChunk::Done { trailers: None });
         }
 
-        match &mut self.state {
-            Decoder::Chunked(state) => state.next_chunk(&mut self.buf, &mut self.transport_r).await,
-            Decoder::ContentLength(state) => {
-                state.next_chunk(&mut self.buf, &mut self.transport_r).await
+        let read_timeout = self.read_timeout;
+        let read = async {
+            match &mut self.state {
+                Decoder::Chunked(state) => {
+                    state.next_chunk(&mut self.buf, &mut self.transport_r).await
+                }
+                Decoder::ContentLength(state) => {
+                    state.next_chunk(&mut self.buf, &mut self.transport_r).await
+                }
+                Decoder::Tunnel(state) => {
+                    state.next_chunk(&mut self.buf, &mut self.transport_r).await
+                }
             }
+        };
+        let res = match read_timeout {
+            // the decoders hold on to the buffer while reading: if we give up
+            // halfway, `buf` stays empty and the body can't be used anymore.
+            Some(timeout) => tokio::time::timeout(timeout, read)
+                .await
+                .unwrap_or(Err(BodyError::ReadTimeout)),
+            None => read.await,
+        };
+        if res.is_err() {
+            self.cancellation.cancel(CancelReason::ConnectionClosed);
         }
+        res
     }
 
     fn eof(&self) -> bool {
         match &self.state {
             Decoder::Chunked(state) => state.eof(),
             Decoder::ContentLength(state) => state.eof(),
+            Decoder::Tunnel(state) => state.done,
+        }
+    }
+
+    fn raw_fd(&self) -> Option<RawFd> {
+        match (&self.state, &self.buf) {
+            (Decoder::Tunnel(state), Some(buf)) if !state.done && buf.is_empty() => {
+                self.transport_r.raw_fd()
+            }
+            _ => None,
         }
     }
 }
@@ -142,6 +212,40 @@ impl ContentLengthDecoder {
     }
 }
 
+impl TunnelDecoder {
+    async fn next_chunk(
+        &mut self,
+        buf_slot: &mut Option<RollMut>,
+        transport: &mut impl ReadOwned,
+    ) -> Result<BodyChunk, BodyError> {
+        if self.done {
+            return Ok(BodyChunk::Done { trailers: None });
+        }
+
+        let mut buf = buf_slot
+            .take()
+            .ok_or(BodyError::CalledNextChunkAfterError)?;
+
+        if buf.is_empty() {
+            buf.reserve()?;
+
+            let res;
+            (res, buf) = buf.read_into(usize::MAX, transport).await;
+            let n = res.map_err(BodyError::ErrorWhileReadingChunkData)?;
+            if n == 0 {
+                debug!("peer closed its end of the tunnel");
+                self.done = true;
+                buf_slot.replace(buf);
+                return Ok(BodyChunk::Done { trailers: None });
+            }
+        }
+
+        let chunk = buf.take_all();
+        buf_slot.replace(buf);
+        Ok(BodyChunk::Chunk(chunk.into()))
+    }
+}
+
 impl ChunkedDecoder {
     async fn next_chunk(
         &mut self,
@@ -166,7 +270,7 @@ impl ChunkedDecoder {
                     super::parse::chunk_size,
                     transport,
                     buf,
-                    16,
+                    MAX_CHUNK_HEADER_LEN,
                 )
                 .await
                 .map_err(|_| BodyError::InvalidChunkSize)?
@@ -174,23 +278,24 @@ impl ChunkedDecoder {
                 buf = next_buf;
 
                 if chunk_size == 0 {
-                    // that's the final chunk, look for the final CRLF
-                    let (next_buf, _) = read_and_parse(
-                        "Http1BodyChunkFinalTerminator",
-                        super::parse::crlf,
+                    // that's the final chunk, then come trailers (if any)
+                    // and the final CRLF, cf. <https://httpwg.org/specs/rfc9112.html#chunked.trailer.section>
+                    let (next_buf, (trailers, _)) = read_and_parse(
+                        "Http1BodyTrailers",
+                        |i| super::parse::headers_and_crlf(i, false, false),
                         transport,
                         buf,
-                        2,
+                        MAX_TRAILERS_LEN,
                     )
                     .await
-                    .map_err(BodyError::InvalidChunkTerminator)?
+                    .map_err(BodyError::InvalidTrailers)?
                     .ok_or(BodyError::ClosedWhileReadingChunkTerminator)?;
                     buf = next_buf;
                     *self = ChunkedDecoder::Done;
                     buf_slot.replace(buf);
 
-                    // TODO: trailers
-                    return Ok(BodyChunk::Done { trailers: None });
+                    let trailers = (!trailers.is_empty()).then(|| Box::new(trailers));
+                    return Ok(BodyChunk::Done { trailers });
                 }
 
                 *self = ChunkedDecoder::ReadingChunk { remain: chunk_size }
@@ -256,6 +361,10 @@ pub enum BodyWriteMode {
     // we didn't set a content-length and we're not doing chunked transfer
     // encoding, so we're not sending a body at all.
     Empty,
+
+    // we approved a CONNECT request: the body is written as-is, and ending
+    // it shuts down our side of the connection.
+    Tunnel,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -313,7 +422,7 @@ pub(crate) async fn write_h1_body_chunk(
                 .await
                 .map_err(BodyError::WriteError)?;
         }
-        BodyWriteMode::ContentLength(_) => {
+        BodyWriteMode::ContentLength(_) | BodyWriteMode::Tunnel => {
             transport
                 .write_all_owned(chunk)
                 .await
@@ -344,6 +453,9 @@ pub(crate) async fn write_h1_body_end(
         BodyWriteMode::Empty => {
             // nothing to do
         }
+        BodyWriteMode::Tunnel => {
+            transport.shutdown().await.map_err(BodyError::WriteError)?;
+        }
     }
     Ok(())
 }
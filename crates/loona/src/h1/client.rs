@@ -1,34 +1,56 @@
+use std::time::Duration;
+
 use b_x::BX;
-use http::header;
+use http::{header, StatusCode, Version};
 use tracing::debug;
 
 use crate::{
     types::Request,
     util::{read_and_parse, ReadAndParseError},
-    Body, HeadersExt, Response,
+    Body, HeadersExt, Method, Response,
 };
 use buffet::{
     PieceList, RollMut, {ReadOwned, WriteOwned},
 };
 
+pub use crate::ClientDriver;
+
 use super::{
     body::{write_h1_body, BodyWriteMode, H1Body, H1BodyKind},
     encode::encode_request,
 };
 
-pub struct ClientConf {}
+/// Settings for [request_with_conf]
+#[derive(Debug, Clone)]
+pub struct ClientConf {
+    /// Max length of the response status line and headers, informational
+    /// responses included
+    pub max_response_header_len: usize,
 
-#[allow(async_fn_in_trait)] // we never require Send
-pub trait ClientDriver {
-    type Return;
-    type Error: std::error::Error + 'static;
+    /// How long to wait for the final response headers, once the request
+    /// headers have been written. `None` waits forever.
+    pub response_timeout: Option<Duration>,
 
-    async fn on_informational_response(&mut self, res: Response) -> Result<(), Self::Error>;
-    async fn on_final_response(
-        self,
-        res: Response,
-        body: &mut impl Body,
-    ) -> Result<Self::Return, Self::Error>;
+    /// How long a single read of the response body can take: reading the
+    /// body fails with [crate::BodyError::ReadTimeout] after that. `None`
+    /// waits forever.
+    pub body_read_timeout: Option<Duration>,
+
+    /// Whether to record how response header names were spelled, in
+    /// [Response::header_case]. Requests are encoded with
+    /// [Request::header_case] either way.
+    pub preserve_header_case: bool,
+}
+
+impl Default for ClientConf {
+    fn default() -> Self {
+        Self {
+            max_response_header_len: 64 * 1024,
+            response_timeout: None,
+            body_read_timeout: None,
+            preserve_header_case: false,
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -40,12 +62,18 @@ pub enum Http1ClientError<DriverError> {
     #[error("Could not write the request headers")]
     WhileWritingRequestHeaders(#[source] std::io::Error),
 
+    #[error("Could not write the request body: {0}")]
+    WhileWritingRequestBody(#[source] BX),
+
     #[error("Could not read / receive the response headers")]
     ErrorReadingResponseHeaders(#[from] ReadAndParseError),
 
     #[error("Server went away before sending response headers")]
     ServerWentAwayBeforeSendingResponseHeaders,
 
+    #[error("Timed out waiting for the response headers")]
+    ResponseTimeout,
+
     #[error("Allocation failed")]
     Alloc(#[from] buffet::bufpool::Error),
 }
@@ -59,21 +87,60 @@ where
     }
 }
 
-/// Perform an HTTP/1.1 request against an HTTP/1.1 server
+/// Perform an HTTP/1.1 request against an HTTP/1.1 server, with the default
+/// [ClientConf]
 ///
-/// The transport halves will be returned unless the server requested connection
-/// close or the request body wasn't fully drained
+/// The transport halves will be returned if the connection can be reused
+/// for another request, cf. [request_with_conf]
 pub async fn request<R, W, D>(
+    transport: (R, W),
+    req: Request,
+    body: &mut impl Body,
+    driver: D,
+) -> Result<(Option<(R, W)>, D::Return), Http1ClientError<D::Error>>
+where
+    R: ReadOwned,
+    W: WriteOwned,
+    D: ClientDriver,
+{
+    request_with_conf(transport, &ClientConf::default(), req, body, driver).await
+}
+
+/// Perform an HTTP/1.1 request against an HTTP/1.1 server
+///
+/// If the request has no `host` header, it's taken from the URI's
+/// authority. The request body is sent while the response is being read, and
+/// the driver reads the response body as it arrives.
+///
+/// The transport halves will be returned if the connection can be reused
+/// for another request: not if either side asked for it to be closed, if the
+/// response body is delimited by the connection closing, or if the driver
+/// didn't read the response body in full.
+pub async fn request_with_conf<R, W, D>(
     (mut transport_r, mut transport_w): (R, W),
+    conf: &ClientConf,
     mut req: Request,
     body: &mut impl Body,
-    driver: D,
+    mut driver: D,
 ) -> Result<(Option<(R, W)>, D::Return), Http1ClientError<D::Error>>
 where
     R: ReadOwned,
     W: WriteOwned,
     D: ClientDriver,
 {
+    // responses to HEAD requests announce a length but have no body
+    let is_head = req.method == Method::Head;
+    let req_conn_close = req.headers.is_connection_close() || req.version == Version::HTTP_10;
+
+    if !req.headers.contains_key(header::HOST) {
+        if let Some(authority) = req.uri.authority() {
+            req.headers.insert(
+                header::HOST,
+                authority.as_str().to_owned().into_bytes().into(),
+            );
+        }
+    }
+
     let mode = match body.content_len() {
         Some(0) => BodyWriteMode::Empty,
         Some(len) => {
@@ -83,7 +150,12 @@ where
                 .insert(header::CONTENT_LENGTH, len.to_string().into_bytes().into());
             BodyWriteMode::ContentLength(len)
         }
-        None => BodyWriteMode::Chunked,
+        None => {
+            req.headers.remove(header::CONTENT_LENGTH);
+            req.headers
+                .insert(header::TRANSFER_ENCODING, "chunked".into());
+            BodyWriteMode::Chunked
+        }
     };
 
     let mut buf = RollMut::alloc()?;
@@ -101,58 +173,30 @@ where
 
     let send_body_fut = {
         async move {
-            match write_h1_body(&mut transport_w, body, mode).await {
-                Err(err) => {
-                    // TODO: find way to report this error to the driver without
-                    // spawning, without ref-counting the driver, etc.
-                    panic!("error writing request body: {err}");
-                }
-                Ok(_) => {
-                    debug!("done writing request body");
-                    Ok::<_, Http1ClientError<D::Error>>(transport_w)
-                }
-            }
+            write_h1_body(&mut transport_w, body, mode)
+                .await
+                .map_err(|e| Http1ClientError::WhileWritingRequestBody(BX::from_err(e)))?;
+            debug!("done writing request body");
+            Ok::<_, Http1ClientError<D::Error>>(transport_w)
         }
     };
 
     let recv_res_fut = {
         async move {
-            let (buf, res) = read_and_parse(
-                "Http1Response",
-                super::parse::response,
-                &mut transport_r,
-                buf,
-                // TODO: make this configurable
-                64 * 1024,
-            )
-            .await
-            .map_err(Http1ClientError::ErrorReadingResponseHeaders)?
-            .ok_or(Http1ClientError::ServerWentAwayBeforeSendingResponseHeaders)?;
-            debug!("client received response");
-            res.debug_print();
-
-            if res.status.is_informational() {
-                todo!("handle informational responses");
-            }
-
-            let chunked = res.headers.is_chunked_transfer_encoding();
-
-            // TODO: handle 204/304 separately
-            let content_len = res.headers.content_length().unwrap_or_default();
-
-            let mut res_body = H1Body::new(
-                transport_r,
-                buf,
-                if chunked {
-                    // TODO: even with chunked transfer-encoding, we can announce
-                    // a content length - we should probably detect errors there?
-                    H1BodyKind::Chunked
-                } else {
-                    H1BodyKind::ContentLength(content_len)
-                },
-            );
+            let read_head = read_response_head(&mut transport_r, buf, conf, &mut driver);
+            let (buf, res) = match conf.response_timeout {
+                Some(timeout) => tokio::time::timeout(timeout, read_head)
+                    .await
+                    .map_err(|_| Http1ClientError::ResponseTimeout)??,
+                None => read_head.await?,
+            };
+
+            let (body_kind, must_close) = response_body_kind(&res, is_head);
+            let conn_close =
+                must_close || res.headers.is_connection_close() || res.version == Version::HTTP_10;
 
-            let conn_close = res.headers.is_connection_close();
+            let mut res_body =
+                H1Body::new(transport_r, buf, body_kind).with_read_timeout(conf.body_read_timeout);
 
             let ret = driver
                 .on_final_response(res, &mut res_body)
@@ -160,8 +204,9 @@ where
                 .map_err(Http1ClientError::DriverError)?;
 
             let transport_r = match (conn_close, res_body.into_inner()) {
-                // can only re-use the body if conn_close is false and the body was fully draided
-                (false, Some((_buf, transport_r))) => Some(transport_r),
+                // can only re-use the connection if the body was fully
+                // drained, and the server didn't send anything past it
+                (false, Some((buf, transport_r))) if buf.is_empty() => Some(transport_r),
                 _ => None,
             };
 
@@ -174,6 +219,68 @@ where
     let transport_w = send_res;
     let (transport_r, ret) = recv_res;
 
-    let transport = transport_r.map(|transport_r| (transport_r, transport_w));
+    let transport = transport_r
+        .filter(|_| !req_conn_close)
+        .map(|transport_r| (transport_r, transport_w));
     Ok((transport, ret))
 }
+
+/// Reads responses until the final one, passing informational ones to the
+/// driver
+async fn read_response_head<D: ClientDriver>(
+    transport_r: &mut impl ReadOwned,
+    mut buf: RollMut,
+    conf: &ClientConf,
+    driver: &mut D,
+) -> Result<(RollMut, Response), Http1ClientError<D::Error>> {
+    loop {
+        let res;
+        (buf, res) = read_and_parse(
+            "Http1Response",
+            |i| super::parse::response(i, conf.preserve_header_case),
+            transport_r,
+            buf,
+            conf.max_response_header_len,
+        )
+        .await
+        .map_err(Http1ClientError::ErrorReadingResponseHeaders)?
+        .ok_or(Http1ClientError::ServerWentAwayBeforeSendingResponseHeaders)?;
+        debug!("client received response");
+        res.debug_print();
+
+        // 101 is informational, but it's the last response we'll
+        // get on this connection.
+        if !res.status.is_informational() || res.status == StatusCode::SWITCHING_PROTOCOLS {
+            return Ok((buf, res));
+        }
+        driver
+            .on_informational_response(res)
+            .await
+            .map_err(Http1ClientError::DriverError)?;
+    }
+}
+
+/// How the response body is delimited, and whether the connection must be
+/// closed after it, cf. <https://httpwg.org/specs/rfc9112.html#message.body.length>
+fn response_body_kind(res: &Response, is_head: bool) -> (H1BodyKind, bool) {
+    if res.status == StatusCode::SWITCHING_PROTOCOLS {
+        // whatever follows is the other protocol's
+        return (H1BodyKind::Tunnel, true);
+    }
+    if is_head || res.means_empty_body() {
+        return (H1BodyKind::ContentLength(0), false);
+    }
+    if res.headers.is_chunked_transfer_encoding() {
+        // chunked wins over content-length, but a server sending both
+        // can't be trusted with the next response
+        return (
+            H1BodyKind::Chunked,
+            res.headers.contains_key(header::CONTENT_LENGTH),
+        );
+    }
+    match res.headers.content_length() {
+        Some(len) => (H1BodyKind::ContentLength(len), false),
+        // the body ends when the server closes the connection
+        None => (H1BodyKind::Tunnel, true),
+    }
+}
@@ -1,14 +1,18 @@
-use std::io::Write;
+use std::{cell::Cell, io::Write, os::fd::RawFd, rc::Rc};
 
 use http::{header, StatusCode, Version};
 
 use crate::{
-    types::{Headers, Request, Response},
+    types::{HeaderCase, Headers, Request, Response},
     BodyError, Encoder, HeadersExt,
 };
 use buffet::{Piece, PieceList, RollMut, WriteOwned};
+use tracing::debug;
 
-use super::body::{write_h1_body_chunk, write_h1_body_end, BodyWriteMode};
+use super::{
+    body::{write_h1_body_chunk, write_h1_body_end, BodyWriteMode},
+    ServerConf,
+};
 
 pub(crate) fn encode_request(
     req: Request,
@@ -19,7 +23,8 @@ pub(crate) fn encode_request(
     list.push_back(" ");
 
     assert_eq!(out_scratch.len(), 0);
-    out_scratch.write_all(req.uri.path().as_bytes())?;
+    let target = req.uri.path_and_query().map_or("/", |pq| pq.as_str());
+    out_scratch.write_all(target.as_bytes())?;
     list.push_back(out_scratch.take_all());
 
     match req.version {
@@ -33,7 +38,7 @@ pub(crate) fn encode_request(
 
     // TODO: if `host` isn't set, set from request uri? which should
     // take precedence here?
-    encode_headers(req.headers, list)?;
+    encode_headers(req.headers, req.header_case.as_deref(), list)?;
     list.push_back("\r\n");
     Ok(())
 }
@@ -52,26 +57,42 @@ fn encode_response(res: Response, list: &mut PieceList) -> Result<(), std::io::E
     list.push_back(" ");
     list.push_back(res.status.canonical_reason().unwrap_or("Unknown"));
     list.push_back("\r\n");
-    encode_headers(res.headers, list)?;
+    encode_headers(res.headers, res.header_case.as_deref(), list)?;
     list.push_back("\r\n");
     Ok(())
 }
 
-pub(crate) fn encode_headers(headers: Headers, list: &mut PieceList) -> Result<(), std::io::Error> {
+/// Encodes header lines, spelling names as `header_case` says, when it
+/// says something that's the same name
+pub(crate) fn encode_headers(
+    headers: Headers,
+    header_case: Option<&HeaderCase>,
+    list: &mut PieceList,
+) -> Result<(), std::io::Error> {
     let mut last_header_name = None;
+    // which occurrence of `last_header_name` this is
+    let mut index = 0;
     for (name, value) in headers {
-        match name {
+        let name = match name {
             Some(name) => {
                 last_header_name = Some(name.clone());
-                list.push_back(name);
+                index = 0;
+                name
             }
             None => {
-                let name = match last_header_name {
+                index += 1;
+                match last_header_name {
                     Some(ref name) => name.clone(),
                     None => unreachable!("HeaderMap's IntoIter violated its contract"),
-                };
-                list.push_back(name);
+                }
             }
+        };
+        match header_case
+            .and_then(|case| case.get(&name, index))
+            .filter(|spelled| spelled.eq_ignore_ascii_case(name.as_str().as_bytes()))
+        {
+            Some(spelled) => list.push_back(spelled.clone()),
+            None => list.push_back(name),
         }
         list.push_back(": ");
         list.push_back(value);
@@ -153,20 +174,69 @@ pub struct H1Encoder<OurWriteOwned>
 where
     OurWriteOwned: WriteOwned,
 {
-    pub(crate) transport_w: OurWriteOwned,
+    // only `None` once it's been handed back, cf. [H1Encoder::handing_back_transport]
+    transport_w: Option<OurWriteOwned>,
     mode: BodyWriteMode,
+    server_conf: Option<Rc<ServerConf>>,
+    connect: bool,
+    wrote_final_response: bool,
+    hand_back: Option<TransportSlot<OurWriteOwned>>,
 }
 
+/// Where an [H1Encoder] puts its transport when it's dropped, along with
+/// whether it had written a final response (and so whether it's still
+/// possible to send one).
+pub(crate) type TransportSlot<W> = Rc<Cell<Option<(W, bool)>>>;
+
 impl<OurWriteOwned> H1Encoder<OurWriteOwned>
 where
     OurWriteOwned: WriteOwned,
 {
     pub fn new(transport_w: OurWriteOwned) -> Self {
         Self {
-            transport_w,
+            transport_w: Some(transport_w),
             mode: BodyWriteMode::Empty,
+            server_conf: None,
+            connect: false,
+            wrote_final_response: false,
+            hand_back: None,
         }
     }
+
+    /// If the encoder is dropped without [H1Encoder::into_transport] being
+    /// called (e.g. because the handler errored out or panicked), its
+    /// transport ends up in the returned slot, so a response can still be
+    /// sent on it.
+    pub(crate) fn handing_back_transport(mut self) -> (Self, TransportSlot<OurWriteOwned>) {
+        let slot: TransportSlot<OurWriteOwned> = Default::default();
+        self.hand_back = Some(slot.clone());
+        (self, slot)
+    }
+
+    pub(crate) fn into_transport(mut self) -> OurWriteOwned {
+        self.transport_w
+            .take()
+            .expect("transport was already handed back")
+    }
+
+    fn transport(&mut self) -> &mut OurWriteOwned {
+        self.transport_w
+            .as_mut()
+            .expect("transport was already handed back")
+    }
+
+    /// Add the `date` and `server` headers to responses, as configured
+    pub(crate) fn with_server_conf(mut self, server_conf: Rc<ServerConf>) -> Self {
+        self.server_conf = Some(server_conf);
+        self
+    }
+
+    /// We're responding to a CONNECT request: a 2xx response turns the
+    /// connection into a tunnel, cf. <https://httpwg.org/specs/rfc9110.html#CONNECT>
+    pub(crate) fn for_connect_request(mut self) -> Self {
+        self.connect = true;
+        self
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -196,7 +266,13 @@ where
     type Error = H1EncoderError;
 
     async fn write_response(&mut self, mut res: Response) -> Result<(), Self::Error> {
-        if !res.status.is_informational() && !res.means_empty_body() {
+        if self.connect && res.status.is_success() {
+            // A server MUST NOT send any Transfer-Encoding or Content-Length
+            // header fields in a 2xx (Successful) response to CONNECT.
+            res.headers.remove(header::CONTENT_LENGTH);
+            res.headers.remove(header::TRANSFER_ENCODING);
+            self.mode = BodyWriteMode::Tunnel;
+        } else if !res.status.is_informational() && !res.means_empty_body() {
             self.mode = match res.headers.content_length() {
                 Some(0) => BodyWriteMode::Empty,
                 Some(length) => BodyWriteMode::ContentLength(length),
@@ -208,10 +284,24 @@ where
             };
         }
 
+        if let Some(conf) = self.server_conf.as_deref() {
+            if !res.status.is_informational() {
+                crate::date::add_response_headers(
+                    &mut res.headers,
+                    conf.date_header,
+                    conf.server_header.as_ref(),
+                );
+            }
+        }
+
+        if !res.status.is_informational() {
+            self.wrote_final_response = true;
+        }
+
         let mut list = PieceList::default();
         encode_response(res, &mut list)?;
 
-        self.transport_w
+        self.transport()
             .writev_all_owned(list)
             .await
             .map_err(H1EncoderError::from)?;
@@ -223,26 +313,55 @@ where
         // note: we don't check content length here, because it's done by the Responder,
         // note by encoders.
 
-        write_h1_body_chunk(&mut self.transport_w, chunk, self.mode)
+        let mode = self.mode;
+        write_h1_body_chunk(self.transport(), chunk, mode)
             .await
             .map_err(H1EncoderError::from)
     }
 
     async fn write_body_end(&mut self) -> Result<(), Self::Error> {
-        write_h1_body_end(&mut self.transport_w, self.mode)
+        let mode = self.mode;
+        write_h1_body_end(self.transport(), mode)
             .await
             .map_err(H1EncoderError::from)
     }
 
     async fn write_trailers(&mut self, trailers: Box<Headers>) -> Result<(), Self::Error> {
+        if self.mode != BodyWriteMode::Chunked {
+            // there's nowhere to put trailers in other bodies
+            debug!(mode = ?self.mode, "dropping trailers");
+            return self.write_body_end().await;
+        }
+
+        // the last chunk, then the trailer section, cf. <https://httpwg.org/specs/rfc9112.html#chunked.trailer.section>
         let mut list = PieceList::default();
-        encode_headers(*trailers, &mut list)?;
+        list.push_back("0\r\n");
+        encode_headers(*trailers, None, &mut list)?;
+        list.push_back("\r\n");
 
-        self.transport_w
+        self.transport()
             .writev_all_owned(list)
             .await
             .map_err(H1EncoderError::from)?;
 
         Ok(())
     }
+
+    fn raw_fd(&self) -> Option<RawFd> {
+        if self.mode != BodyWriteMode::Tunnel {
+            return None;
+        }
+        self.transport_w.as_ref()?.raw_fd()
+    }
+}
+
+impl<OurWriteOwned> Drop for H1Encoder<OurWriteOwned>
+where
+    OurWriteOwned: WriteOwned,
+{
+    fn drop(&mut self) {
+        if let (Some(slot), Some(transport_w)) = (self.hand_back.take(), self.transport_w.take()) {
+            slot.set(Some((transport_w, self.wrote_final_response)));
+        }
+    }
 }
@@ -9,5 +9,6 @@ pub use server::*;
 
 pub(crate) mod body;
 pub(crate) mod parse;
+mod scan;
 
 pub mod encode;
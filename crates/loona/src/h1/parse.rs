@@ -3,25 +3,32 @@
 //! HTTP/1.1 <https://httpwg.org/specs/rfc9112.html>
 //! HTTP semantics <https://httpwg.org/specs/rfc9110.html>
 
-use http::{header::HeaderName, StatusCode, Version};
+use http::{header::HeaderName, StatusCode, Uri, Version};
 use nom::{
-    bytes::streaming::{tag, take, take_until, take_while1},
+    bytes::streaming::{tag, take, take_until, take_while, take_while1},
     combinator::{map_res, opt},
     sequence::{preceded, terminated},
     IResult,
 };
 
+use super::scan::{StopBytes, TOKEN_END, URI_END, VALUE_END};
 use crate::{
-    types::{Headers, Request, Response},
+    types::{HeaderCase, Headers, Request, Response},
+    validation::Validation,
     Method,
 };
-use buffet::{PieceStr, Roll, RollStr};
+use buffet::{Piece, PieceStr, Roll, RollStr};
 
 const CRLF: &[u8] = b"\r\n";
 
-/// Parses a chunked transfer coding chunk size (hex text followed by CRLF)
+/// Parses a chunked transfer coding chunk size: hex text, then CRLF. Chunk
+/// extensions are skipped, cf. <https://httpwg.org/specs/rfc9112.html#chunked.extension>
 pub fn chunk_size(i: Roll) -> IResult<Roll, u64> {
-    terminated(u64_text_hex, tag(CRLF))(i)
+    let (i, size) = u64_text_hex(i)?;
+    let (i, _) = take_while(|c| c == b' ' || c == b'\t')(i)?;
+    let (i, _) = opt(preceded(tag(&b";"[..]), take_until(CRLF)))(i)?;
+    let (i, _) = tag(CRLF)(i)?;
+    Ok((i, size))
 }
 
 pub fn crlf(i: Roll) -> IResult<Roll, ()> {
@@ -29,20 +36,38 @@ pub fn crlf(i: Roll) -> IResult<Roll, ()> {
     Ok((i, ()))
 }
 
-// Looks like `GET /path HTTP/1.1\r\n`, then headers
-pub fn request(i: Roll) -> IResult<Roll, Request> {
+// Looks like `GET /path HTTP/1.1\r\n`, then headers.
+//
+// What `validation` allows is accepted and normalized, cf. [Validation].
+// With `preserve_header_case`, how header names were spelled is recorded in
+// [Request::header_case].
+pub fn request(
+    i: Roll,
+    validation: &Validation,
+    preserve_header_case: bool,
+) -> IResult<Roll, Request> {
     let (i, method) = terminated(method, space1)(i)?;
-    let (i, path) = terminated(path, space1)(i)?;
+    // origin-form, absolute-form, authority-form or asterisk-form: the
+    // server checks which is allowed
+    let (i, uri) = if validation.allow_spaces_in_target {
+        map_res(path_with_spaces, |path: String| path.parse::<Uri>())(i)?
+    } else {
+        map_res(terminated(path, space1), |path: RollStr| {
+            path.parse::<Uri>()
+        })(i)?
+    };
     let (i, version) = terminated(http_version, tag(CRLF))(i)?;
-    let (i, headers) = headers_and_crlf(i)?;
+    let (i, (headers, header_case)) =
+        headers_and_crlf(i, validation.allow_obs_fold, preserve_header_case)?;
 
     let request = Request {
         method,
-        // TODO: should this take the host header into account?
-        // check what hyper does.
-        uri: path.parse().unwrap(),
+        uri,
         version,
         headers,
+        protocol: None,
+        header_case,
+        connection: None,
     };
     Ok((i, request))
 }
@@ -56,49 +81,51 @@ pub fn method(i: Roll) -> IResult<Roll, Method> {
 /// A short textual identifier that does not include whitspace or delimiters,
 /// cf. <https://httpwg.org/specs/rfc9110.html#rule.token.separators>
 pub fn token(i: Roll) -> IResult<Roll, RollStr> {
-    let (i, token) = take_while1(is_tchar)(i)?;
+    let (i, token) = take_until_stop(&TOKEN_END)(i)?;
     let token = unsafe { token.to_string_unchecked() };
     Ok((i, token))
 }
 
-/// cf. <https://httpwg.org/specs/rfc9110.html#rule.token.separators>
-fn is_tchar(c: u8) -> bool {
-    c.is_ascii_graphic() && !is_delimiter(c)
-}
-
-/// cf. <https://httpwg.org/specs/rfc9110.html#rule.token.separators>
-fn is_delimiter(c: u8) -> bool {
-    memchr::memchr(c, br#"(),/:;<=>?@[\]{}""#).is_some()
-}
-
 fn path(i: Roll) -> IResult<Roll, RollStr> {
-    let (i, path) = take_while1(is_uri_char)(i)?;
+    let (i, path) = take_until_stop(&URI_END)(i)?;
     let path = unsafe { path.to_string_unchecked() };
     Ok((i, path))
 }
 
-/// Returns true if `c` is a character that can be found in an URI
-/// cf. <https://stackoverflow.com/a/7109208>
-fn is_uri_char(c: u8) -> bool {
-    memchr::memchr(
-        c,
-        br#"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789-._~:/?#[]@!$&'()*+,;%="#,
-    )
-    .is_some()
+/// Like [path] followed by [space1], except spaces followed by anything other
+/// than the HTTP version are part of the path, percent-encoded.
+fn path_with_spaces(i: Roll) -> IResult<Roll, String> {
+    let (mut i, first) = path(i)?;
+    let mut out = first.to_string();
+    loop {
+        let (rest, spaces) = take_while1(|c| c == b' ')(i)?;
+        // streaming: if we can't tell yet, we need more
+        if let (_, Some(_)) = opt(tag(&b"HTTP/"[..]))(rest.clone())? {
+            return Ok((rest, out));
+        }
+
+        let (rest, more) = path(rest)?;
+        out.extend(std::iter::repeat("%20").take(spaces.len()));
+        out.push_str(&more);
+        i = rest;
+    }
 }
 
 // Looks like `HTTP/1.1 200 OK\r\n` or `HTTP/1.1 404 Not Found\r\n`, then
-// headers
-pub fn response(i: Roll) -> IResult<Roll, Response> {
+// headers. With `preserve_header_case`, how header names were spelled is
+// recorded in [Response::header_case].
+pub fn response(i: Roll, preserve_header_case: bool) -> IResult<Roll, Response> {
     let (i, version) = terminated(http_version, space1)(i)?;
     let (i, code) = terminated(status_code, space1)(i)?;
     let (i, _reason) = terminated(take_until(CRLF), tag(CRLF))(i)?;
-    let (i, headers) = headers_and_crlf(i)?;
+    // user agents must unfold obsolete line folding in responses
+    let (i, (headers, header_case)) = headers_and_crlf(i, true, preserve_header_case)?;
 
     let response = Response {
         version,
         status: code,
         headers,
+        header_case,
     };
     Ok((i, response))
 }
@@ -137,28 +164,67 @@ pub fn http_version(i: Roll) -> IResult<Roll, Version> {
     Ok((i, version))
 }
 
-pub fn headers_and_crlf(mut i: Roll) -> IResult<Roll, Headers> {
+/// Parses headers until an empty line. With `preserve_header_case`, also
+/// returns how their names were spelled, unless they were all lowercase.
+pub fn headers_and_crlf(
+    mut i: Roll,
+    allow_obs_fold: bool,
+    preserve_header_case: bool,
+) -> IResult<Roll, (Headers, Option<Box<HeaderCase>>)> {
     let mut headers = Headers::default();
+    let mut header_case = HeaderCase::default();
+    let mut any_uppercase = false;
     loop {
         if let (i, Some(_)) = opt(tag(CRLF))(i.clone())? {
             // end of headers
-            return Ok((i, headers));
+            let header_case = any_uppercase.then(|| Box::new(header_case));
+            return Ok((i, (headers, header_case)));
         }
 
-        let (i_next, (name, value)) = header(i)?;
-        headers.append(name, value.into());
+        let (i_next, (spelled, name, value)) = header(i, allow_obs_fold)?;
+        if preserve_header_case {
+            any_uppercase |= spelled.iter().any(|b| b.is_ascii_uppercase());
+            header_case.append(name.clone(), spelled);
+        }
+        headers.append(name, value);
         i = i_next;
     }
 }
 
-/// Parse a single header line
-fn header(i: Roll) -> IResult<Roll, (HeaderName, Roll)> {
-    let (i, name) = map_res(take_until_and_consume(b":"), |s: Roll| {
-        HeaderName::from_bytes(&s[..])
-    })(i)?;
-    let (i, value) = preceded(space1, take_until_and_consume(CRLF))(i)?;
+/// Parse a single header line. If `allow_obs_fold` is false, header values
+/// continued on the next line (which then starts with whitespace) are an
+/// error, cf. <https://httpwg.org/specs/rfc9112.html#line.folding>
+fn header(i: Roll, allow_obs_fold: bool) -> IResult<Roll, (Roll, HeaderName, Piece)> {
+    let (i, (spelled, name)) = map_res(
+        terminated(take_until_stop(&TOKEN_END), tag(&b":"[..])),
+        |s: Roll| HeaderName::from_bytes(&s[..]).map(|name| (s, name)),
+    )(i)?;
+    let (mut i, value) = preceded(space1, field_value_and_crlf)(i)?;
+    let mut value: Piece = value.into();
 
-    Ok((i, (name, value)))
+    loop {
+        match i.first().copied() {
+            // we need to see the start of the next line to know whether the
+            // value continues there
+            None => return Err(nom::Err::Incomplete(nom::Needed::new(1))),
+            Some(b' ' | b'\t') => {
+                if !allow_obs_fold {
+                    return Err(nom::Err::Error(nom::error::Error::new(
+                        i,
+                        nom::error::ErrorKind::Verify,
+                    )));
+                }
+
+                let (i_next, continuation) = field_value_and_crlf(i)?;
+                let mut unfolded = value.to_vec();
+                unfolded.push(b' ');
+                unfolded.extend_from_slice(continuation.trim_ascii_start());
+                value = unfolded.into();
+                i = i_next;
+            }
+            Some(_) => return Ok((i, (spelled, name, value))),
+        }
+    }
 }
 
 /// Parse at least one SP character
@@ -167,20 +233,183 @@ fn space1(i: Roll) -> IResult<Roll, ()> {
     Ok((i, ()))
 }
 
-/// Parse until the given tag, then skip the tag
-fn take_until_and_consume(needle: &[u8]) -> impl FnMut(Roll) -> IResult<Roll, Roll> + '_ {
-    terminated(take_until(needle), tag(needle))
+/// Parse a header value, up to CRLF, which is skipped. Values may be empty,
+/// but control characters (other than HTAB) are an error, cf.
+/// <https://httpwg.org/specs/rfc9110.html#fields.values>
+fn field_value_and_crlf(i: Roll) -> IResult<Roll, Roll> {
+    let (i, value) = take_until_stop0(&VALUE_END)(i)?;
+    let (i, _) = tag(CRLF)(i)?;
+    Ok((i, value))
+}
+
+/// Parse at least one byte, up to (not including) the first one in `stop`
+fn take_until_stop(stop: &'static StopBytes) -> impl Fn(Roll) -> IResult<Roll, Roll> {
+    move |i: Roll| {
+        let (rest, taken) = take_until_stop0(stop)(i)?;
+        if taken.is_empty() {
+            return Err(nom::Err::Error(nom::error::Error::new(
+                rest,
+                nom::error::ErrorKind::TakeUntil,
+            )));
+        }
+        Ok((rest, taken))
+    }
+}
+
+/// Parse up to (not including) the first byte in `stop`, which may be the
+/// first one
+fn take_until_stop0(stop: &'static StopBytes) -> impl Fn(Roll) -> IResult<Roll, Roll> {
+    move |i: Roll| match stop.find(&i[..]) {
+        // we need to see where it ends
+        None => Err(nom::Err::Incomplete(nom::Needed::new(1))),
+        Some(n) => {
+            let (taken, rest) = i.split_at(n);
+            Ok((rest, taken))
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::h1::parse::is_delimiter;
+    use buffet::{PieceList, Roll, RollMut};
+    use http::header;
+
+    use crate::{
+        h1::{encode::encode_request, scan::TOKEN_END},
+        validation::Validation,
+    };
+
+    fn roll(input: &[u8]) -> Roll {
+        buffet::bufpool::initialize_allocator().unwrap();
+        let mut buf = RollMut::alloc().unwrap();
+        buf.put(input).unwrap();
+        buf.filled()
+    }
 
     #[test]
     fn test_h1_parse_various_lowlevel_functions() {
-        assert!(is_delimiter(b'('));
-        assert!(is_delimiter(b'"'));
-        assert!(is_delimiter(b'\\'));
-        assert!(!is_delimiter(b'B'));
+        assert!(TOKEN_END.contains(b'('));
+        assert!(TOKEN_END.contains(b'"'));
+        assert!(TOKEN_END.contains(b'\\'));
+        assert!(!TOKEN_END.contains(b'B'));
+    }
+
+    #[test]
+    fn test_chunk_size() {
+        let (rest, size) = super::chunk_size(roll(b"1a\r\ndata")).unwrap();
+        assert_eq!(size, 0x1a);
+        assert_eq!(&rest[..], b"data");
+
+        // extensions are skipped
+        let (_, size) = super::chunk_size(roll(b"ff ; name=\"value\";other\r\n")).unwrap();
+        assert_eq!(size, 0xff);
+
+        assert!(super::chunk_size(roll(b"1a")).unwrap_err().is_incomplete());
+        assert!(super::chunk_size(roll(b"zz\r\n")).is_err());
+        assert!(super::chunk_size(roll(b"1a x\r\n")).is_err());
+        assert!(super::chunk_size(roll(b"fffffffffffffffff\r\n")).is_err());
+    }
+
+    #[test]
+    fn test_header_value_bytes() {
+        let long = "x".repeat(100);
+        let mut input = format!("GET / HTTP/1.1\r\nx-long: {long}\tend\r\nx-obs: caf").into_bytes();
+        input.extend_from_slice(b"\xe9\r\n\r\n");
+        let (rest, req) = super::request(roll(&input), &Validation::strict(), false).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(
+            &req.headers.get("x-long").unwrap()[..],
+            format!("{long}\tend").as_bytes()
+        );
+        assert_eq!(&req.headers.get("x-obs").unwrap()[..], b"caf\xe9");
+
+        // empty values are fine
+        let input = b"GET / HTTP/1.1\r\nx-empty: \r\nhost: a\r\n\r\n";
+        let (rest, req) = super::request(roll(input), &Validation::strict(), false).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(&req.headers.get("x-empty").unwrap()[..], b"");
+        assert_eq!(&req.headers.get("host").unwrap()[..], b"a");
+
+        // control characters, and line endings that aren't CRLF
+        for value in [&b"a\0b"[..], b"a\nb", b"a\rb", b"a\x7fb"] {
+            let mut input = b"GET / HTTP/1.1\r\nx-bad: ".to_vec();
+            input.extend_from_slice(value);
+            input.extend_from_slice(b"\r\n\r\n");
+            assert!(
+                super::request(roll(&input), &Validation::strict(), false).is_err(),
+                "{value:?}"
+            );
+        }
+
+        // not enough to tell yet
+        let input = format!("GET /{long} HTTP/1.1\r\nx-long: {long}");
+        assert!(
+            super::request(roll(input.as_bytes()), &Validation::strict(), false)
+                .unwrap_err()
+                .is_incomplete()
+        );
+    }
+
+    #[test]
+    fn test_spaces_in_target() {
+        let lenient = Validation::lenient();
+        let input = b"GET /a b  c?d e HTTP/1.1\r\n\r\n";
+        assert!(super::request(roll(input), &Validation::strict(), false).is_err());
+        let (rest, req) = super::request(roll(input), &lenient, false).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(req.uri, "/a%20b%20%20c?d%20e");
+
+        // the request line ends with the version, wherever the spaces are
+        let (_, req) = super::request(roll(b"GET /a  HTTP/1.1\r\n\r\n"), &lenient, false).unwrap();
+        assert_eq!(req.uri, "/a");
+        assert!(super::request(roll(b"GET /a b \r\n\r\n"), &lenient, false).is_err());
+        assert!(super::request(roll(b"GET /a b HTT"), &lenient, false)
+            .unwrap_err()
+            .is_incomplete());
+    }
+
+    #[test]
+    fn test_preserve_header_case() {
+        let input = b"GET / HTTP/1.1\r\nX-Custom: a\r\nx-CUSTOM: b\r\nhost: c\r\n\r\n";
+
+        let (_, req) = super::request(roll(input), &Validation::strict(), false).unwrap();
+        assert!(req.header_case.is_none());
+        let (_, req) = super::request(
+            roll(b"GET / HTTP/1.1\r\nhost: c\r\n\r\n"),
+            &Validation::strict(),
+            true,
+        )
+        .unwrap();
+        assert!(
+            req.header_case.is_none(),
+            "all lowercase, nothing to preserve"
+        );
+
+        let (_, mut req) = super::request(roll(input), &Validation::strict(), true).unwrap();
+        let mut list = PieceList::default();
+        encode_request(req.clone(), &mut list, &mut RollMut::alloc().unwrap()).unwrap();
+        let encoded: Vec<u8> = list
+            .into_vec_deque()
+            .iter()
+            .flat_map(|p| p.to_vec())
+            .collect();
+        assert_eq!(&encoded[..], &input[..]);
+
+        // spellings can be set, but not to other names
+        let case = req.header_case_mut();
+        case.insert(header::HOST, "HOST");
+        case.insert(header::ACCEPT, "Content-Type");
+        req.headers.insert(header::ACCEPT, "*/*".into());
+        let mut list = PieceList::default();
+        encode_request(req, &mut list, &mut RollMut::alloc().unwrap()).unwrap();
+        let encoded: Vec<u8> = list
+            .into_vec_deque()
+            .iter()
+            .flat_map(|p| p.to_vec())
+            .collect();
+        assert_eq!(
+            std::str::from_utf8(&encoded).unwrap(),
+            "GET / HTTP/1.1\r\nX-Custom: a\r\nx-CUSTOM: b\r\nHOST: c\r\naccept: */*\r\n\r\n"
+        );
     }
 }
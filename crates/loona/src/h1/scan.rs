@@ -0,0 +1,215 @@
+//! Finding where HTTP/1.1 tokens, request targets and header values end, 16
+//! or 32 bytes at a time, in the style of picohttpparser's `findchar_fast`.
+//!
+//! Each scan looks for the first byte that falls in one of a few ranges:
+//! that's either the delimiter we're after, or a byte that's not allowed
+//! there. On x86_64, SSE2 is always there and AVX2 is used if the CPU has
+//! it. Elsewhere, and for the last few bytes, it's a table lookup.
+
+/// A set of bytes, as inclusive ranges
+pub(crate) struct StopBytes {
+    ranges: &'static [(u8, u8)],
+    table: [bool; 256],
+}
+
+impl StopBytes {
+    const fn new(ranges: &'static [(u8, u8)]) -> Self {
+        let mut table = [false; 256];
+        let mut i = 0;
+        while i < ranges.len() {
+            let (lo, hi) = ranges[i];
+            let mut b = lo as usize;
+            while b <= hi as usize {
+                table[b] = true;
+                b += 1;
+            }
+            i += 1;
+        }
+        Self { ranges, table }
+    }
+
+    #[inline]
+    pub(crate) fn contains(&self, b: u8) -> bool {
+        self.table[b as usize]
+    }
+
+    /// Returns the index of the first byte of `buf` that's in the set
+    #[inline]
+    pub(crate) fn find(&self, buf: &[u8]) -> Option<usize> {
+        #[cfg(target_arch = "x86_64")]
+        {
+            let (skipped, found) = x86::find(self.ranges, buf);
+            if found.is_some() {
+                return found;
+            }
+            self.find_scalar(&buf[skipped..]).map(|i| skipped + i)
+        }
+
+        #[cfg(not(target_arch = "x86_64"))]
+        self.find_scalar(buf)
+    }
+
+    #[inline]
+    fn find_scalar(&self, buf: &[u8]) -> Option<usize> {
+        buf.iter().position(|&b| self.contains(b))
+    }
+}
+
+/// Bytes that end a token (methods, header names): anything that's not a
+/// `tchar`, cf. <https://httpwg.org/specs/rfc9110.html#rule.token.separators>
+pub(crate) static TOKEN_END: StopBytes = StopBytes::new(&[
+    (0x00, b' '),
+    (b'"', b'"'),
+    (b'(', b')'),
+    (b',', b','),
+    (b'/', b'/'),
+    (b':', b'@'),
+    (b'[', b']'),
+    (b'{', b'{'),
+    (b'}', b'}'),
+    (0x7f, 0xff),
+]);
+
+/// Bytes that end a request target: anything that's not allowed in a URI,
+/// cf. <https://www.rfc-editor.org/rfc/rfc3986#appendix-A>
+pub(crate) static URI_END: StopBytes = StopBytes::new(&[
+    (0x00, b' '),
+    (b'"', b'"'),
+    (b'<', b'<'),
+    (b'>', b'>'),
+    (b'\\', b'\\'),
+    (b'^', b'^'),
+    (b'`', b'`'),
+    (b'{', b'}'),
+    (0x7f, 0xff),
+]);
+
+/// Bytes that end a header value: control characters other than HTAB
+/// (which includes CR and LF), and DEL. obs-text is fine, cf.
+/// <https://httpwg.org/specs/rfc9110.html#fields.values>
+pub(crate) static VALUE_END: StopBytes =
+    StopBytes::new(&[(0x00, 0x08), (0x0a, 0x1f), (0x7f, 0x7f)]);
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    /// Scans as much of `buf` as fits in whole vectors. Returns how many
+    /// bytes were scanned, and the index of the first byte in `ranges`, if
+    /// any of those was.
+    #[inline]
+    pub(super) fn find(ranges: &[(u8, u8)], buf: &[u8]) -> (usize, Option<usize>) {
+        if buf.len() >= 32 && std::is_x86_feature_detected!("avx2") {
+            // SAFETY: we just checked the CPU has AVX2
+            unsafe { find_avx2(ranges, buf) }
+        } else {
+            // SAFETY: SSE2 is part of the x86_64 baseline
+            unsafe { find_sse2(ranges, buf) }
+        }
+    }
+
+    #[target_feature(enable = "sse2")]
+    unsafe fn find_sse2(ranges: &[(u8, u8)], buf: &[u8]) -> (usize, Option<usize>) {
+        let mut offset = 0;
+        while offset + 16 <= buf.len() {
+            let v = _mm_loadu_si128(buf.as_ptr().add(offset) as *const __m128i);
+            let mut hits = _mm_setzero_si128();
+            for &(lo, hi) in ranges {
+                // unsigned lo <= v <= hi, as: clamp(v, lo, hi) == v
+                let (lo, hi) = (_mm_set1_epi8(lo as i8), _mm_set1_epi8(hi as i8));
+                let clamped = _mm_min_epu8(_mm_max_epu8(v, lo), hi);
+                hits = _mm_or_si128(hits, _mm_cmpeq_epi8(clamped, v));
+            }
+            let mask = _mm_movemask_epi8(hits) as u32;
+            if mask != 0 {
+                return (offset, Some(offset + mask.trailing_zeros() as usize));
+            }
+            offset += 16;
+        }
+        (offset, None)
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn find_avx2(ranges: &[(u8, u8)], buf: &[u8]) -> (usize, Option<usize>) {
+        let mut offset = 0;
+        while offset + 32 <= buf.len() {
+            let v = _mm256_loadu_si256(buf.as_ptr().add(offset) as *const __m256i);
+            let mut hits = _mm256_setzero_si256();
+            for &(lo, hi) in ranges {
+                let (lo, hi) = (_mm256_set1_epi8(lo as i8), _mm256_set1_epi8(hi as i8));
+                let clamped = _mm256_min_epu8(_mm256_max_epu8(v, lo), hi);
+                hits = _mm256_or_si256(hits, _mm256_cmpeq_epi8(clamped, v));
+            }
+            let mask = _mm256_movemask_epi8(hits) as u32;
+            if mask != 0 {
+                return (offset, Some(offset + mask.trailing_zeros() as usize));
+            }
+            offset += 32;
+        }
+
+        // whatever's left might still fill a 16-byte vector
+        let (scanned, found) = find_sse2(ranges, &buf[offset..]);
+        (offset + scanned, found.map(|i| offset + i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StopBytes, TOKEN_END, URI_END, VALUE_END};
+
+    /// Checks the vectorized scan against the table, with the interesting
+    /// byte at every position, so that every code path gets exercised
+    fn check(stop: &StopBytes, filler: u8) {
+        assert!(!stop.contains(filler));
+        let one_stop = stop.ranges[0].0;
+        for len in 0..70 {
+            let mut buf = vec![filler; len];
+            assert_eq!(stop.find(&buf), None, "len {len}");
+            for pos in 0..len {
+                // every byte, for a buffer that takes all code paths
+                let bytes = if len == 69 {
+                    0..=255
+                } else {
+                    one_stop..=one_stop
+                };
+                for b in bytes {
+                    buf[pos] = b;
+                    let expected = stop.contains(b).then_some(pos);
+                    assert_eq!(stop.find(&buf), expected, "len {len}, byte {b:#x} at {pos}");
+                }
+                buf[pos] = filler;
+            }
+        }
+    }
+
+    #[test]
+    fn test_scan_matches_table() {
+        check(&TOKEN_END, b'a');
+        check(&URI_END, b'/');
+        check(&VALUE_END, b' ');
+    }
+
+    #[test]
+    fn test_stop_bytes() {
+        for b in b"!#$%&'*+-.^_`|~09azAZ" {
+            assert!(!TOKEN_END.contains(*b), "{}", *b as char);
+        }
+        for b in b"\"(),/:;<=>?@[\\]{} \t\r\n\x7f\x80" {
+            assert!(TOKEN_END.contains(*b), "{}", *b as char);
+        }
+
+        for b in b"azAZ09-._~:/?#[]@!$&'()*+,;%=" {
+            assert!(!URI_END.contains(*b), "{}", *b as char);
+        }
+        for b in b" \"<>\\^`{|}\r\x7f\xff" {
+            assert!(URI_END.contains(*b), "{}", *b as char);
+        }
+
+        for b in b"\t !~\x80\xff" {
+            assert!(!VALUE_END.contains(*b), "{}", *b as char);
+        }
+        for b in b"\r\n\0\x7f" {
+            assert!(VALUE_END.contains(*b), "{}", *b as char);
+        }
+    }
+}
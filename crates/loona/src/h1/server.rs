@@ -1,17 +1,29 @@
-use std::rc::Rc;
+use std::{rc::Rc, time::Duration};
 
+use http::{header, uri::Authority, StatusCode, Version};
+use nom::IResult;
+use tokio::time::Instant;
 use tracing::debug;
 
 use crate::{
-    error::ServeError,
+    cancel::Cancellation,
+    early_data::{self, EarlyData, EARLY_DATA},
+    error::{default_error_response, ConfError, ErrorHook, HandlerFailure, ServeError},
     h1::body::{H1Body, H1BodyKind},
-    util::{read_and_parse, ReadAndParseError},
-    HeadersExt, Responder, ServeOutcome, ServerDriver,
+    tunnel::connect_target,
+    types::from_digits,
+    util::{catch_unwind, panic_message, read_and_parse, ReadAndParseError},
+    validation::Validation,
+    Body, ConnectionInfo, HeadersExt, Method, Request, RequestTargetForm, Responder, ServeOutcome,
+    ServerDriver, SinglePieceBody,
 };
-use buffet::{ReadOwned, RollMut, WriteOwned};
+use buffet::{Piece, ReadOwned, Roll, RollMut, WriteOwned};
 
 use super::encode::H1Encoder;
 
+/// HTTP/1.1 server configuration. [ServerConf::builder] and the presets
+/// check that settings hold together, struct literals don't: cf.
+/// [ServerConf::validate].
 pub struct ServerConf {
     /// Max length of the request line + HTTP headers
     pub max_http_header_len: usize,
@@ -21,6 +33,36 @@ pub struct ServerConf {
 
     /// Max number of header records
     pub max_header_records: usize,
+
+    /// Whether to add a `date` header to responses that don't have one
+    pub date_header: bool,
+
+    /// The `server` header to add to responses that don't have one, if any
+    pub server_header: Option<Piece>,
+
+    /// Which requests that RFC 9112 lets us reject are processed anyway. By
+    /// default, none are: some of them are a common vector for request
+    /// smuggling.
+    pub validation: Validation,
+
+    /// Whether requests that came in TLS early data, and aren't idempotent,
+    /// get a 425 Too Early (after which we hang up) instead of being handed
+    /// to the driver, cf. [crate::early_data]
+    pub reject_unsafe_early_data: bool,
+
+    /// Whether to record how request header names were spelled, in
+    /// [Request::header_case], e.g. to send them on as-is when proxying.
+    /// Responses are encoded with [crate::Response::header_case] either way.
+    pub preserve_header_case: bool,
+
+    /// If set, requests that take longer than this are cancelled, cf.
+    /// [crate::Responder::cancellation]. It's up to the handler to give up.
+    pub request_timeout: Option<Duration>,
+
+    /// Builds the response sent when a handler errors out or panics before
+    /// sending a final response. The connection is kept open if the request
+    /// body had been read in full.
+    pub error_hook: ErrorHook,
 }
 
 impl Default for ServerConf {
@@ -29,14 +71,198 @@ impl Default for ServerConf {
             max_http_header_len: 64 * 1024,
             max_header_record_len: 4 * 1024,
             max_header_records: 128,
+            date_header: true,
+            server_header: None,
+            validation: Validation::strict(),
+            reject_unsafe_early_data: true,
+            preserve_header_case: false,
+            request_timeout: None,
+            error_hook: Rc::new(default_error_response),
+        }
+    }
+}
+
+impl ServerConf {
+    pub fn builder() -> ServerConfBuilder {
+        Self::default().into_builder()
+    }
+
+    /// Starts a builder from this configuration, e.g. to tweak a preset
+    pub fn into_builder(self) -> ServerConfBuilder {
+        ServerConfBuilder { conf: self }
+    }
+
+    /// For servers facing the internet: smaller request headers, and strict
+    /// parsing.
+    pub fn edge() -> Self {
+        Self {
+            max_http_header_len: 16 * 1024,
+            max_header_record_len: 8 * 1024,
+            max_header_records: 100,
+            ..Default::default()
+        }
+    }
+
+    /// For servers behind a load balancer or another proxy that already
+    /// vetted requests: lenient parsing, and header names passed on as they
+    /// were spelled. Requests with both `content-length` and
+    /// `transfer-encoding` are still rejected: the proxy and this server
+    /// could disagree on where they end.
+    pub fn internal() -> Self {
+        Self {
+            validation: Validation {
+                allow_content_length_with_transfer_encoding: false,
+                ..Validation::lenient()
+            },
+            preserve_header_case: true,
+            ..Default::default()
+        }
+    }
+
+    /// For running conformance suites against: strict parsing, and nothing
+    /// added to responses that a test could trip on.
+    pub fn conformance_test() -> Self {
+        Self {
+            date_header: false,
+            server_header: None,
+            ..Default::default()
+        }
+    }
+
+    /// Checks that settings hold together: limits aren't zero, and a single
+    /// header record isn't allowed to be longer than all headers combined.
+    pub fn validate(&self) -> Result<(), ConfError> {
+        for (setting, value) in [
+            ("max_http_header_len", self.max_http_header_len),
+            ("max_header_record_len", self.max_header_record_len),
+            ("max_header_records", self.max_header_records),
+        ] {
+            if value == 0 {
+                return Err(ConfError::Zero { setting });
+            }
+        }
+        if self.max_header_record_len > self.max_http_header_len {
+            return Err(ConfError::Exceeds {
+                setting: "max_header_record_len",
+                actual: self.max_header_record_len as u64,
+                other: "max_http_header_len",
+                limit: self.max_http_header_len as u64,
+            });
         }
+        if self.request_timeout == Some(Duration::ZERO) {
+            return Err(ConfError::Zero {
+                setting: "request_timeout",
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Builds a [ServerConf], checked by [ServerConfBuilder::build]. Setters are
+/// documented on the matching [ServerConf] fields.
+pub struct ServerConfBuilder {
+    conf: ServerConf,
+}
+
+impl ServerConfBuilder {
+    pub fn max_http_header_len(mut self, len: usize) -> Self {
+        self.conf.max_http_header_len = len;
+        self
+    }
+
+    pub fn max_header_record_len(mut self, len: usize) -> Self {
+        self.conf.max_header_record_len = len;
+        self
+    }
+
+    pub fn max_header_records(mut self, records: usize) -> Self {
+        self.conf.max_header_records = records;
+        self
+    }
+
+    pub fn date_header(mut self, enabled: bool) -> Self {
+        self.conf.date_header = enabled;
+        self
+    }
+
+    pub fn server_header(mut self, value: Option<Piece>) -> Self {
+        self.conf.server_header = value;
+        self
+    }
+
+    pub fn validation(mut self, validation: Validation) -> Self {
+        self.conf.validation = validation;
+        self
+    }
+
+    pub fn reject_unsafe_early_data(mut self, enabled: bool) -> Self {
+        self.conf.reject_unsafe_early_data = enabled;
+        self
+    }
+
+    pub fn preserve_header_case(mut self, enabled: bool) -> Self {
+        self.conf.preserve_header_case = enabled;
+        self
+    }
+
+    pub fn request_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.conf.request_timeout = timeout;
+        self
+    }
+
+    pub fn error_hook(mut self, hook: ErrorHook) -> Self {
+        self.conf.error_hook = hook;
+        self
+    }
+
+    /// Errors out if settings don't hold together, cf. [ServerConf::validate]
+    pub fn build(self) -> Result<ServerConf, ConfError> {
+        self.conf.validate()?;
+        Ok(self.conf)
     }
 }
 
 pub async fn serve<OurDriver, OurReadOwned, OurWriteOwned>(
-    (mut transport_r, mut transport_w): (OurReadOwned, OurWriteOwned),
+    transport: (OurReadOwned, OurWriteOwned),
+    conf: Rc<ServerConf>,
+    client_buf: RollMut,
+    driver: OurDriver,
+) -> Result<ServeOutcome, ServeError<OurDriver::Error>>
+where
+    OurDriver: ServerDriver<H1Encoder<OurWriteOwned>>,
+    OurReadOwned: ReadOwned,
+    OurWriteOwned: WriteOwned,
+{
+    serve_with_early_data(transport, conf, client_buf, 0, driver).await
+}
+
+/// Like [serve], for a connection whose first `early_data_len` bytes came in
+/// TLS 1.3 early data, cf. [crate::ConnectionInfo::early_data_len]. Those
+/// bytes count whether they're in `client_buf` or still to be read.
+pub async fn serve_with_early_data<OurDriver, OurReadOwned, OurWriteOwned>(
+    transport: (OurReadOwned, OurWriteOwned),
+    conf: Rc<ServerConf>,
+    client_buf: RollMut,
+    early_data_len: usize,
+    driver: OurDriver,
+) -> Result<ServeOutcome, ServeError<OurDriver::Error>>
+where
+    OurDriver: ServerDriver<H1Encoder<OurWriteOwned>>,
+    OurReadOwned: ReadOwned,
+    OurWriteOwned: WriteOwned,
+{
+    serve_accepted(transport, conf, client_buf, early_data_len, None, driver).await
+}
+
+/// Like [serve_with_early_data], for connections accepted by a
+/// [crate::server::Server]: hands `connection` to the driver with every
+/// request
+pub(crate) async fn serve_accepted<OurDriver, OurReadOwned, OurWriteOwned>(
+    (transport_r, mut transport_w): (OurReadOwned, OurWriteOwned),
     conf: Rc<ServerConf>,
     mut client_buf: RollMut,
+    early_data_len: usize,
+    connection: Option<Rc<ConnectionInfo>>,
     driver: OurDriver,
 ) -> Result<ServeOutcome, ServeError<OurDriver::Error>>
 where
@@ -44,11 +270,18 @@ where
     OurReadOwned: ReadOwned,
     OurWriteOwned: WriteOwned,
 {
+    let early_data = EarlyData::new(early_data_len, client_buf.len());
+    let mut transport_r = early_data.wrap(transport_r);
+
+    let request_parser = |i: Roll| -> IResult<Roll, Request> {
+        super::parse::request(i, &conf.validation, conf.preserve_header_case)
+    };
+
     loop {
-        let req;
+        let mut req;
         (client_buf, req) = match read_and_parse(
             "Http1Request",
-            super::parse::request,
+            request_parser,
             &mut transport_r,
             client_buf,
             conf.max_http_header_len,
@@ -73,37 +306,118 @@ where
 
                     return Ok(ServeOutcome::RequestHeadersTooLargeOnHttp1Conn);
                 }
+                ReadAndParseError::ParsingError { .. } => {
+                    debug!(?e, "malformed request, replying with 400 and hanging up");
+                    reply_and_hang_up(&mut transport_w, StatusCode::BAD_REQUEST).await?;
+                    return Ok(ServeOutcome::ClientDidntSpeakHttp11);
+                }
                 _ => {
                     debug!(?e, "error reading request header from downstream");
                     return Ok(ServeOutcome::ClientDidntSpeakHttp11);
                 }
             },
         };
+        if early_data.covers(client_buf.len()) {
+            req.headers.insert(EARLY_DATA.clone(), "1".into());
+        }
+        req.connection = connection.clone();
         debug!("got request {req:?}");
 
-        let chunked = req.headers.is_chunked_transfer_encoding();
+        if let Err(reason) = check_request_target(&req) {
+            debug!(uri = %req.uri, reason, "invalid request target, replying with 400 and hanging up");
+            reply_and_hang_up(&mut transport_w, StatusCode::BAD_REQUEST).await?;
+            return Ok(ServeOutcome::ClientDidntSpeakHttp11);
+        }
+
+        if conf.reject_unsafe_early_data && early_data::is_unsafe(&req) {
+            debug!(method = %req.method, "request came in early data, replying with 425 and hanging up");
+            reply_and_hang_up(&mut transport_w, early_data::too_early()).await?;
+            return Ok(ServeOutcome::ServerRequestedConnectionClose);
+        }
+
+        if req.method == Method::Connect {
+            return serve_connect(req, (transport_r, transport_w), conf, client_buf, driver).await;
+        }
+
+        let (body_kind, must_close) = match request_body_kind(&req, &conf) {
+            Ok(t) => t,
+            Err(status) => {
+                debug!(%status, "invalid request framing, hanging up");
+                reply_and_hang_up(&mut transport_w, status).await?;
+                return Ok(ServeOutcome::InvalidRequestFramingOnHttp1Conn);
+            }
+        };
         let connection_close = req.headers.is_connection_close();
-        let content_len = req.headers.content_length().unwrap_or_default();
 
-        let mut req_body = H1Body::new(
-            transport_r,
-            client_buf,
-            if chunked {
-                H1BodyKind::Chunked
-            } else {
-                H1BodyKind::ContentLength(content_len)
-            },
-        );
+        let cancellation = Cancellation::new(conf.request_timeout.map(|t| Instant::now() + t));
+        let mut req_body =
+            H1Body::new(transport_r, client_buf, body_kind).with_cancellation(cancellation.clone());
 
-        let responder = Responder::new(H1Encoder::new(transport_w));
+        let (encoder, transport_slot) = H1Encoder::new(transport_w)
+            .with_server_conf(conf.clone())
+            .handing_back_transport();
+        let responder = Responder::new(encoder).with_cancellation(cancellation);
 
-        let resp = driver
-            .handle(req, &mut req_body, responder)
-            .await
-            .map_err(ServeError::Driver)?;
+        let resp = match catch_unwind(driver.handle(req, &mut req_body, responder)).await {
+            Ok(Ok(resp)) => resp,
+            failed => {
+                let (transport, wrote_final_response) = match transport_slot.take() {
+                    Some(t) => t,
+                    None => {
+                        debug!("handler failed and held on to the responder, hanging up");
+                        return Ok(ServeOutcome::ServerRequestedConnectionClose);
+                    }
+                };
+
+                let (mut res, body) = match failed {
+                    Ok(Err(e)) => {
+                        debug!("handler returned an error: {e}");
+                        if wrote_final_response {
+                            return Err(ServeError::Driver(e));
+                        }
+                        (conf.error_hook)(&HandlerFailure::Error(&e))
+                    }
+                    Err(payload) => {
+                        let message = panic_message(payload.as_ref());
+                        debug!(?message, "handler panicked");
+                        if wrote_final_response {
+                            return Err(ServeError::DriverPanicked {
+                                message: message.map(ToOwned::to_owned),
+                            });
+                        }
+                        (conf.error_hook)(&HandlerFailure::Panic(message))
+                    }
+                    Ok(Ok(_)) => unreachable!(),
+                };
+
+                // if the handler didn't read the whole request body, there's
+                // no telling where the next request starts.
+                let keep_alive = req_body.eof() && !connection_close && !must_close;
+                if !keep_alive {
+                    res.headers.insert(header::CONNECTION, "close".into());
+                }
+
+                let responder =
+                    Responder::new(H1Encoder::new(transport).with_server_conf(conf.clone()));
+                let resp = match responder
+                    .write_final_response_with_body(res, &mut SinglePieceBody::from(body))
+                    .await
+                {
+                    Ok(resp) => resp,
+                    Err(e) => {
+                        debug!("could not send error response: {e}");
+                        return Ok(ServeOutcome::ServerRequestedConnectionClose);
+                    }
+                };
+                if !keep_alive {
+                    return Ok(ServeOutcome::ServerRequestedConnectionClose);
+                }
+                resp
+            }
+        };
 
         // TODO: if we sent `connection: close` we should close now
-        transport_w = resp.into_inner().transport_w;
+        transport_w = resp.into_inner().into_transport();
 
         (client_buf, transport_r) = req_body
             .into_inner()
@@ -113,5 +427,226 @@ where
             debug!("client requested connection close");
             return Ok(ServeOutcome::ClientRequestedConnectionClose);
         }
+
+        if must_close {
+            debug!("request framing was suspicious, closing connection");
+            return Ok(ServeOutcome::ServerRequestedConnectionClose);
+        }
+    }
+}
+
+/// Works out the length of a request body, cf. <https://httpwg.org/specs/rfc9112.html#message.body.length>
+///
+/// Also returns whether the connection must be closed after the response.
+/// Requests with ambiguous framing are rejected with the returned status: a
+/// proxy in front of us could disagree with us on where they end, and let a
+/// client smuggle a request past it.
+fn request_body_kind(req: &Request, conf: &ServerConf) -> Result<(H1BodyKind, bool), StatusCode> {
+    let headers = &req.headers;
+    let has_content_length = headers.contains_key(header::CONTENT_LENGTH);
+
+    if headers.contains_key(header::TRANSFER_ENCODING) {
+        let codings: Vec<&[u8]> = headers
+            .get_all(header::TRANSFER_ENCODING)
+            .iter()
+            .flat_map(|value| value.split(|&b| b == b','))
+            .map(|coding| coding.trim_ascii())
+            .filter(|coding| !coding.is_empty())
+            .collect();
+
+        // when chunked isn't the final coding, the only way to find the end of
+        // the body is for the client to close the connection.
+        match codings.last() {
+            Some(coding) if coding.eq_ignore_ascii_case(b"chunked") => {}
+            _ => return Err(StatusCode::BAD_REQUEST),
+        }
+        if codings.len() > 1 {
+            // we don't know how to decode anything else
+            return Err(StatusCode::NOT_IMPLEMENTED);
+        }
+
+        if has_content_length && !conf.validation.allow_content_length_with_transfer_encoding {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+
+        // transfer-encoding wins over content-length, but both that and
+        // transfer-encoding in an HTTP/1.0 request hint that something
+        // along the way doesn't understand the framing.
+        let must_close = has_content_length || req.version == Version::HTTP_10;
+        return Ok((H1BodyKind::Chunked, must_close));
+    }
+
+    // `content-length: 42, 42` or repeated `content-length` headers are fine,
+    // as long as they all agree.
+    let mut content_len = None;
+    for value in headers.get_all(header::CONTENT_LENGTH) {
+        for member in value.split(|&b| b == b',') {
+            let len = from_digits(member.trim_ascii()).ok_or(StatusCode::BAD_REQUEST)?;
+            if content_len.replace(len).is_some_and(|prev| prev != len) {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+        }
+    }
+
+    Ok((
+        H1BodyKind::ContentLength(content_len.unwrap_or_default()),
+        false,
+    ))
+}
+
+/// Checks that the request target is in a form allowed for its method, and
+/// agrees with the `host` header, cf. <https://httpwg.org/specs/rfc9112.html#request.target>
+///
+/// A target and a `host` that disagree (whichever one we went with) could
+/// let a client trick us, or a cache in front of us, into serving one
+/// site's content for another.
+fn check_request_target(req: &Request) -> Result<(), &'static str> {
+    match req.target_form() {
+        // CONNECT targets get a closer look, cf. [serve_connect]
+        RequestTargetForm::Origin | RequestTargetForm::Absolute => {}
+        RequestTargetForm::Authority => {
+            if req.method != Method::Connect {
+                return Err("only CONNECT request targets may be in authority-form");
+            }
+        }
+        RequestTargetForm::Asterisk => {
+            if req.method != Method::Options {
+                return Err("only OPTIONS request targets may be '*'");
+            }
+        }
+    }
+
+    let mut hosts = req.headers.get_all(header::HOST).iter();
+    let Some(host) = hosts.next() else {
+        return Ok(());
+    };
+    if hosts.next().is_some() {
+        return Err("more than one host header");
+    }
+    let target_authority = req.uri.authority();
+    if host.is_empty() {
+        // that's how clients say there's no authority, cf. <https://httpwg.org/specs/rfc9112.html#rfc.section.3.2.p.6>
+        return match target_authority {
+            Some(_) => Err("empty host header, but the request target has an authority"),
+            None => Ok(()),
+        };
+    }
+    let host: Authority = std::str::from_utf8(host)
+        .ok()
+        .and_then(|host| host.parse().ok())
+        .ok_or("invalid host header")?;
+    match target_authority {
+        Some(authority) if *authority != host => {
+            Err("host header does not match the request target's authority")
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Sends an empty response with the given status, for requests we won't
+/// serve, after which the connection must be closed.
+async fn reply_and_hang_up<DriverError>(
+    transport_w: &mut impl WriteOwned,
+    status: StatusCode,
+) -> Result<(), ServeError<DriverError>> {
+    let reply = format!(
+        "HTTP/1.1 {} {}\r\nconnection: close\r\ncontent-length: 0\r\n\r\n",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or_default()
+    );
+    transport_w
+        .write_all_owned(reply.into_bytes())
+        .await
+        .map_err(ServeError::DownstreamWrite)
+}
+
+/// A CONNECT request: the driver either denies it, or sends a 2xx response
+/// and tunnels bytes, e.g. with [crate::tunnel::copy_bidirectional]. Either
+/// way, we're done with the connection afterwards.
+async fn serve_connect<OurDriver, OurReadOwned, OurWriteOwned>(
+    req: Request,
+    (transport_r, mut transport_w): (OurReadOwned, OurWriteOwned),
+    conf: Rc<ServerConf>,
+    client_buf: RollMut,
+    driver: OurDriver,
+) -> Result<ServeOutcome, ServeError<OurDriver::Error>>
+where
+    OurDriver: ServerDriver<H1Encoder<OurWriteOwned>>,
+    OurReadOwned: ReadOwned,
+    OurWriteOwned: WriteOwned,
+{
+    if connect_target(&req.uri).is_none() {
+        debug!(uri = %req.uri, "CONNECT request target is not in authority-form, replying with 400 and hanging up");
+        let reply = b"HTTP/1.1 400 Bad Request\r\nconnection: close\r\ncontent-length: 0\r\n\r\n";
+        transport_w
+            .write_all_owned(reply)
+            .await
+            .map_err(ServeError::DownstreamWrite)?;
+        return Ok(ServeOutcome::Http1ConnectHandled);
+    }
+
+    let mut req_body = H1Body::new(transport_r, client_buf, H1BodyKind::Tunnel);
+    let responder = Responder::new(
+        H1Encoder::new(transport_w)
+            .with_server_conf(conf)
+            .for_connect_request(),
+    );
+
+    match catch_unwind(driver.handle(req, &mut req_body, responder)).await {
+        Ok(res) => {
+            res.map_err(ServeError::Driver)?;
+        }
+        Err(payload) => {
+            return Err(ServeError::DriverPanicked {
+                message: panic_message(payload.as_ref()).map(ToOwned::to_owned),
+            })
+        }
+    }
+
+    Ok(ServeOutcome::Http1ConnectHandled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ServerConf;
+    use crate::error::ConfError;
+
+    #[test]
+    fn test_conf_validation() {
+        for conf in [
+            ServerConf::default(),
+            ServerConf::edge(),
+            ServerConf::internal(),
+            ServerConf::conformance_test(),
+        ] {
+            conf.validate().unwrap();
+        }
+        assert!(
+            !ServerConf::internal()
+                .validation
+                .allow_content_length_with_transfer_encoding
+        );
+
+        let res = ServerConf::builder()
+            .max_http_header_len(8 * 1024)
+            .max_header_record_len(16 * 1024)
+            .build();
+        assert!(matches!(
+            res,
+            Err(ConfError::Exceeds {
+                setting: "max_header_record_len",
+                ..
+            })
+        ));
+        let res = ServerConf::edge()
+            .into_builder()
+            .max_header_records(0)
+            .build();
+        assert!(matches!(
+            res,
+            Err(ConfError::Zero {
+                setting: "max_header_records"
+            })
+        ));
     }
 }
@@ -0,0 +1,80 @@
+//! Where decoded header values live. HPACK decoding hands us bytes that only
+//! live as long as the header block (or the decoder's dynamic table), so they
+//! have to be copied: rather than one heap allocation per header, they're
+//! packed into [buffet] buffers, one header block at a time.
+
+use buffet::{bufpool::BUF_SIZE, Piece, RollMut};
+
+/// Values bigger than this get a heap allocation of their own, so as to not
+/// waste most of a buffer on the one value that didn't fit.
+const MAX_ARENA_VALUE: usize = BUF_SIZE as usize / 4;
+
+/// A bump allocator for the headers of a single request (or response).
+///
+/// There's no explicit reset: every [Piece] it hands out keeps its buffer
+/// alive, and the buffer goes back to the pool once the last of them is
+/// dropped, i.e. once the request is done with.
+#[derive(Default)]
+pub(crate) struct HeaderArena {
+    buf: Option<RollMut>,
+}
+
+impl HeaderArena {
+    /// Copies `bytes` into the arena
+    pub(crate) fn alloc(&mut self, bytes: &[u8]) -> Piece {
+        if bytes.is_empty() {
+            return Piece::empty();
+        }
+        if bytes.len() > MAX_ARENA_VALUE {
+            return bytes.to_vec().into();
+        }
+
+        if self
+            .buf
+            .as_ref()
+            .map_or(true, |buf| buf.cap() < bytes.len())
+        {
+            // whatever's left of the previous buffer is wasted, but it's
+            // only a quarter of a buffer at most
+            self.buf = RollMut::alloc().ok();
+        }
+        match &mut self.buf {
+            Some(buf) => {
+                buf.put(bytes).unwrap();
+                buf.take_all().into()
+            }
+            // the pool is exhausted, the heap isn't
+            None => bytes.to_vec().into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffet::bufpool::{self, BUF_SIZE};
+
+    use super::HeaderArena;
+
+    #[test]
+    fn test_header_arena_packs_values() {
+        bufpool::initialize_allocator().unwrap();
+        let free_before = bufpool::num_free();
+
+        let mut arena = HeaderArena::default();
+        let small: Vec<_> = (0..100)
+            .map(|i| arena.alloc(format!("value-{i}").as_bytes()))
+            .collect();
+        assert_eq!(&small[42][..], b"value-42");
+        assert_eq!(bufpool::num_free(), free_before - 1);
+
+        let big = vec![b'a'; BUF_SIZE as usize];
+        assert_eq!(&arena.alloc(&big)[..], &big[..]);
+        assert_eq!(bufpool::num_free(), free_before - 1);
+
+        // the buffer outlives the arena, for as long as values do
+        drop(arena);
+        assert_eq!(bufpool::num_free(), free_before - 1);
+        drop(small);
+        assert_eq!(bufpool::num_free(), free_before);
+    }
+}
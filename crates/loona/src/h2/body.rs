@@ -37,6 +37,9 @@ pub(crate) struct StreamIncoming {
 pub enum StreamIncomingError {
     #[error("stream reset")]
     StreamReset,
+
+    #[error("peer hung up before sending the whole request body")]
+    PeerHungUp,
 }
 
 impl StreamIncoming {
@@ -132,6 +135,9 @@ pub(crate) struct H2Body {
 pub(crate) enum H2BodyError {
     #[error("Stream reset")]
     StreamReset,
+
+    #[error("Peer hung up before sending the whole body")]
+    PeerHungUp,
 }
 
 impl AsRef<dyn std::error::Error> for H2BodyError {
@@ -165,6 +171,7 @@ impl Body for H2Body {
                         }
                     }
                     Err(StreamIncomingError::StreamReset) => return Err(H2BodyError::StreamReset),
+                    Err(StreamIncomingError::PeerHungUp) => return Err(H2BodyError::PeerHungUp),
                 },
                 None => {
                     self.eof = true;
@@ -0,0 +1,1588 @@
+//! The client side of HTTP/2: requests made through a [ClientHandle] share
+//! a single connection, each on its own stream.
+
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    collections::{HashMap, VecDeque},
+    future::Future,
+    rc::Rc,
+    sync::atomic::AtomicU32,
+    time::Duration,
+};
+
+use b_x::BX;
+use buffet::{Piece, PieceList, ReadOwned, Roll, RollMut, WriteOwned};
+use http::{header, HeaderName, StatusCode, Version};
+use loona_h2::{
+    self as parse, nom::Finish, ContinuationFlags, DataFlags, ErrorCode, Frame, FrameType, GoAway,
+    HeadersFlags, IntoPiece, KnownErrorCode, PingFlags, PrioritySpec, RstStream, Setting,
+    SettingPairs, Settings, SettingsFlags, StreamId, WindowUpdate,
+};
+use smallvec::{smallvec, SmallVec};
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::Instant,
+};
+use tracing::{debug, trace};
+
+use crate::{
+    early_data::EarlyData, util::ReadAndParseError, Body, BodyChunk, ClientDriver, Headers,
+    HeadersExt, Method, Request, Response,
+};
+
+use super::{
+    arena::HeaderArena,
+    body::{ChunkPosition, H2Body, IncomingMessageResult, StreamIncoming, StreamIncomingError},
+    encode::{encode_header_block, queue_header_block},
+    server::{
+        deframe_loop, sleep_until_maybe, DEFAULT_WINDOW_SIZE, MAX_FLOW_CONTROL_WINDOW,
+        MAX_MAX_FRAME_SIZE, MAX_WINDOW_SIZE, MIN_MAX_FRAME_SIZE,
+    },
+    types::{
+        BodyOutgoing, ConnState, GoAwayDebugData, H2ConnectionError, H2Event, H2EventPayload,
+        H2StreamError, HeadersOutgoing, StreamState,
+    },
+};
+
+/// The largest stream ID there can be, cf. <https://httpwg.org/specs/rfc9113.html#StreamIdentifiers>
+const MAX_STREAM_ID: u32 = (1 << 31) - 1;
+
+/// HTTP/2 client configuration, values out of the protocol's limits are
+/// clamped
+#[derive(Debug, Clone)]
+pub struct ClientConf {
+    /// How much of each response body the server may send before hearing
+    /// back from us (SETTINGS_INITIAL_WINDOW_SIZE). At most 2^31-1.
+    pub initial_stream_window_size: u32,
+
+    /// Same as [ClientConf::initial_stream_window_size], but for all streams
+    /// of the connection combined. At most 2^31-1.
+    pub connection_window_size: u32,
+
+    /// The largest frame payload the server may send us
+    /// (SETTINGS_MAX_FRAME_SIZE), between 2^14 and 2^24-1.
+    pub max_frame_size: u32,
+
+    /// If set, close the connection (with a GOAWAY frame) once it's had no
+    /// open streams for this long: requests made through it fail after
+    /// that.
+    pub idle_timeout: Option<Duration>,
+
+    /// Whether GOAWAY frames we send over a protocol violation carry a short
+    /// reason as debug data, cf. [crate::h2::ServerConf::goaway_debug_data]
+    pub goaway_debug_data: bool,
+}
+
+impl Default for ClientConf {
+    fn default() -> Self {
+        Self {
+            initial_stream_window_size: DEFAULT_WINDOW_SIZE,
+            connection_window_size: DEFAULT_WINDOW_SIZE,
+            max_frame_size: MIN_MAX_FRAME_SIZE,
+            idle_timeout: None,
+            goaway_debug_data: true,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum H2ClientError<DriverError> {
+    #[error("An error occurred with the client driver: {0}")]
+    DriverError(#[source] DriverError),
+
+    #[error("Could not write the request body: {0}")]
+    WhileWritingRequestBody(#[source] BX),
+
+    #[error("The server reset the stream, with error code {error_code:?}")]
+    StreamReset { error_code: ErrorCode },
+
+    #[error("The server is going away and did not process the request")]
+    NotProcessed,
+
+    #[error("The connection was closed")]
+    ConnectionClosed,
+
+    #[error("Malformed response: {0}")]
+    MalformedResponse(&'static str),
+}
+
+impl<DriverError> H2ClientError<DriverError> {
+    /// Whether the server didn't process the request at all, so that it can
+    /// be retried (on another connection), even if it's not idempotent, cf.
+    /// <https://httpwg.org/specs/rfc9113.html#Reliability>
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::NotProcessed => true,
+            Self::StreamReset { error_code } => {
+                error_code.as_repr() == KnownErrorCode::RefusedStream as u32
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<DriverError> From<H2ClientError<DriverError>> for BX
+where
+    DriverError: std::error::Error + 'static,
+{
+    fn from(e: H2ClientError<DriverError>) -> Self {
+        BX::from_err(e)
+    }
+}
+
+/// Why a stream didn't make it to the end, as told to whoever's waiting on it
+#[derive(Debug, Clone, Copy)]
+enum StreamFailure {
+    Reset(ErrorCode),
+    NotProcessed,
+    ConnectionClosed,
+    Malformed(&'static str),
+}
+
+impl<DriverError> From<StreamFailure> for H2ClientError<DriverError> {
+    fn from(failure: StreamFailure) -> Self {
+        match failure {
+            StreamFailure::Reset(error_code) => H2ClientError::StreamReset { error_code },
+            StreamFailure::NotProcessed => H2ClientError::NotProcessed,
+            StreamFailure::ConnectionClosed => H2ClientError::ConnectionClosed,
+            StreamFailure::Malformed(reason) => H2ClientError::MalformedResponse(reason),
+        }
+    }
+}
+
+/// A handle to an HTTP/2 client connection, cf. [handshake]. It's cheap to
+/// clone, and requests made through any clone of it share the connection.
+#[derive(Clone)]
+pub struct ClientHandle {
+    tx: mpsc::Sender<ClientEvent>,
+    shared: Rc<Shared>,
+}
+
+/// What the connection lets handles know about it
+struct Shared {
+    /// set once the connection is closed or going away
+    closed: Cell<bool>,
+
+    /// the server's SETTINGS_MAX_CONCURRENT_STREAMS
+    max_concurrent_streams: Cell<Option<u32>>,
+}
+
+enum ClientEvent {
+    Open(OpenRequest),
+    /// pieces of a request body, which only ever carry
+    /// [H2EventPayload::BodyChunk], [H2EventPayload::BodyEnd],
+    /// [H2EventPayload::Trailers] or [H2EventPayload::Reset]
+    Stream(H2Event),
+}
+
+struct OpenRequest {
+    pseudo_headers: Vec<(&'static [u8], Vec<u8>)>,
+    headers: Headers,
+    end_stream: bool,
+    is_head: bool,
+    opened: oneshot::Sender<Result<(StreamId, mpsc::Receiver<ResponseEvent>), StreamFailure>>,
+}
+
+type ResponseEvent = Result<ResponseHead, StreamFailure>;
+
+enum ResponseHead {
+    Informational(Response),
+    Final(Response, H2Body),
+}
+
+/// Starts an HTTP/2 connection over `transport`, which must already be
+/// speaking HTTP/2 (because ALPN negotiated `h2`, or with prior knowledge).
+///
+/// Returns a handle to make requests with, and the future that drives the
+/// connection: nothing happens until it's polled (e.g. spawned with
+/// [buffet::spawn]). It resolves once the connection is closed: when the
+/// server hangs up, when it's going away and the requests it's still
+/// processing are done, or once every handle is dropped and no request is
+/// in flight.
+#[allow(clippy::type_complexity)]
+pub fn handshake<R, W>(
+    (transport_r, transport_w): (R, W),
+    conf: Rc<ClientConf>,
+) -> Result<
+    (
+        ClientHandle,
+        impl Future<Output = Result<(), H2ConnectionError>>,
+    ),
+    buffet::bufpool::Error,
+>
+where
+    R: ReadOwned,
+    W: WriteOwned,
+{
+    let mut state = ConnState::default();
+    state.self_settings.enable_push = false;
+    state.self_settings.initial_window_size =
+        conf.initial_stream_window_size.min(MAX_FLOW_CONTROL_WINDOW);
+    state.self_settings.max_frame_size = conf
+        .max_frame_size
+        .clamp(MIN_MAX_FRAME_SIZE, MAX_MAX_FRAME_SIZE);
+
+    let shared = Rc::new(Shared {
+        closed: Cell::new(false),
+        max_concurrent_streams: Cell::new(state.peer_settings.max_concurrent_streams),
+    });
+    let (tx, ev_rx) = mpsc::channel::<ClientEvent>(32);
+
+    let mut hpack_dec = loona_hpack::Decoder::new();
+    hpack_dec.set_max_allowed_table_size(Settings::default().header_table_size.try_into().unwrap());
+
+    let client_buf = RollMut::alloc()?;
+    let mut cx = ClientContext {
+        conf,
+        state,
+        hpack_dec,
+        hpack_enc: loona_hpack::Encoder::new(),
+        out_scratch: RollMut::alloc()?,
+        transport_w,
+        ev_rx,
+        shared: shared.clone(),
+        streams: Default::default(),
+        pending: Default::default(),
+        next_stream_id: 1,
+        goaway_recv: None,
+        handles_gone: false,
+    };
+
+    let handle = ClientHandle { tx, shared };
+    Ok((
+        handle,
+        async move { cx.work(client_buf, transport_r).await },
+    ))
+}
+
+impl ClientHandle {
+    /// Whether the connection is closed or going away: new requests would
+    /// fail.
+    pub fn is_closed(&self) -> bool {
+        self.shared.closed.get() || self.tx.is_closed()
+    }
+
+    /// How many requests the server lets us have in flight at once
+    /// (SETTINGS_MAX_CONCURRENT_STREAMS), if it set a limit. Requests past
+    /// that wait for others to complete.
+    pub fn max_concurrent_streams(&self) -> Option<u32> {
+        self.shared.max_concurrent_streams.get()
+    }
+
+    /// Performs a request on its own stream. Any number of them can be in
+    /// flight at once.
+    ///
+    /// The request's `:authority` is taken from the URI, or from its `host`
+    /// header, and its `:scheme` from the URI, defaulting to `https`.
+    /// Connection-specific headers are left out. The request body is sent
+    /// while the response is being read, and the driver reads the response
+    /// body as it arrives. If the request is dropped before it's done, its
+    /// stream is reset.
+    pub async fn request<D>(
+        &self,
+        mut req: Request,
+        body: &mut impl Body,
+        mut driver: D,
+    ) -> Result<D::Return, H2ClientError<D::Error>>
+    where
+        D: ClientDriver,
+    {
+        let pseudo_headers = request_pseudo_headers(&req);
+        // `:authority` replaces it, cf. RFC 9113, section 8.3.1
+        req.headers.remove(header::HOST);
+        strip_connection_headers(&mut req.headers);
+        if req
+            .headers
+            .get(header::TE)
+            .is_some_and(|te| &te[..] != b"trailers")
+        {
+            req.headers.remove(header::TE);
+        }
+        match body.content_len() {
+            Some(0) => {}
+            Some(len) => {
+                req.headers
+                    .insert(header::CONTENT_LENGTH, len.to_string().into_bytes().into());
+            }
+            None => {
+                req.headers.remove(header::CONTENT_LENGTH);
+            }
+        }
+        let end_stream = body.content_len() == Some(0) || body.eof();
+
+        let (opened_tx, opened_rx) = oneshot::channel();
+        let open = OpenRequest {
+            pseudo_headers,
+            headers: req.headers,
+            end_stream,
+            is_head: req.method == Method::Head,
+            opened: opened_tx,
+        };
+        self.tx
+            .send(ClientEvent::Open(open))
+            .await
+            .map_err(|_| H2ClientError::ConnectionClosed)?;
+        let (stream_id, mut responses) = opened_rx
+            .await
+            .map_err(|_| H2ClientError::ConnectionClosed)??;
+
+        let mut guard = ResetOnDrop {
+            stream_id,
+            tx: self.tx.clone(),
+            armed: true,
+        };
+
+        let send_body_fut = async {
+            if end_stream {
+                return Ok(());
+            }
+            loop {
+                let chunk = body
+                    .next_chunk()
+                    .await
+                    .map_err(|e| H2ClientError::WhileWritingRequestBody(BX::from_err(e)))?;
+                let (payload, done) = match chunk {
+                    BodyChunk::Chunk(chunk) => (H2EventPayload::BodyChunk(chunk), false),
+                    BodyChunk::Done {
+                        trailers: Some(trailers),
+                    } => (H2EventPayload::Trailers(trailers), true),
+                    BodyChunk::Done { trailers: None } => (H2EventPayload::BodyEnd, true),
+                };
+                self.tx
+                    .send(ClientEvent::Stream(H2Event { stream_id, payload }))
+                    .await
+                    .map_err(|_| H2ClientError::ConnectionClosed)?;
+                if done {
+                    debug!(%stream_id, "done writing request body");
+                    return Ok(());
+                }
+            }
+        };
+
+        let recv_res_fut = async move {
+            loop {
+                match responses.recv().await {
+                    None => return Err(H2ClientError::ConnectionClosed),
+                    Some(Err(failure)) => return Err(failure.into()),
+                    Some(Ok(ResponseHead::Informational(res))) => driver
+                        .on_informational_response(res)
+                        .await
+                        .map_err(H2ClientError::DriverError)?,
+                    Some(Ok(ResponseHead::Final(res, mut res_body))) => {
+                        let ret = driver
+                            .on_final_response(res, &mut res_body)
+                            .await
+                            .map_err(H2ClientError::DriverError)?;
+                        return Ok((ret, res_body.eof));
+                    }
+                }
+            }
+        };
+
+        let ((), (ret, res_body_done)) = tokio::try_join!(send_body_fut, recv_res_fut)?;
+        // if the driver didn't read the response body in full, the server
+        // might still be sending it
+        guard.armed = !res_body_done;
+        Ok(ret)
+    }
+}
+
+/// Resets a stream if the request is dropped before it's done, e.g. because
+/// the caller gave up on it
+struct ResetOnDrop {
+    stream_id: StreamId,
+    tx: mpsc::Sender<ClientEvent>,
+    armed: bool,
+}
+
+impl Drop for ResetOnDrop {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+
+        let ev = ClientEvent::Stream(H2Event {
+            stream_id: self.stream_id,
+            payload: H2EventPayload::Reset,
+        });
+        if let Err(mpsc::error::TrySendError::Full(ev)) = self.tx.try_send(ev) {
+            let tx = self.tx.clone();
+            buffet::spawn(async move {
+                if tx.send(ev).await.is_err() {
+                    debug!("could not send event to h2 connection handler");
+                }
+            });
+        }
+    }
+}
+
+/// The pseudo-headers for a request, cf. <https://httpwg.org/specs/rfc9113.html#HttpRequest>
+fn request_pseudo_headers(req: &Request) -> Vec<(&'static [u8], Vec<u8>)> {
+    let mut pseudo_headers: Vec<(&'static [u8], Vec<u8>)> =
+        vec![(b":method", req.method.to_string().into_bytes())];
+
+    // RFC 9113, section 8.5 'The CONNECT method': The ":scheme" and ":path"
+    // pseudo-header fields MUST be omitted, unless it's an extended CONNECT.
+    let is_plain_connect = req.method == Method::Connect && req.protocol.is_none();
+    if !is_plain_connect {
+        let scheme = req.uri.scheme_str().unwrap_or("https");
+        pseudo_headers.push((b":scheme", scheme.as_bytes().to_vec()));
+    }
+
+    let authority = match req.uri.authority() {
+        Some(authority) => Some(authority.as_str().as_bytes().to_vec()),
+        None => req.headers.get(header::HOST).map(|host| host.to_vec()),
+    };
+    if let Some(authority) = authority {
+        pseudo_headers.push((b":authority", authority));
+    }
+
+    if !is_plain_connect {
+        let path = match req.uri.path_and_query().map(|pq| pq.as_str()) {
+            Some(path) if !path.is_empty() => path,
+            _ => "/",
+        };
+        pseudo_headers.push((b":path", path.as_bytes().to_vec()));
+    }
+
+    if let Some(protocol) = &req.protocol {
+        pseudo_headers.push((b":protocol", protocol.as_bytes().to_vec()));
+    }
+
+    pseudo_headers
+}
+
+static KEEP_ALIVE: HeaderName = HeaderName::from_static("keep-alive");
+static PROXY_CONNECTION: HeaderName = HeaderName::from_static("proxy-connection");
+
+/// Whether this header is specific to HTTP/1.1 connections: they're
+/// forbidden in HTTP/2, cf. RFC 9113, section 8.2.2
+fn is_connection_specific(name: &HeaderName) -> bool {
+    name == header::CONNECTION
+        || name == KEEP_ALIVE
+        || name == PROXY_CONNECTION
+        || name == header::TRANSFER_ENCODING
+        || name == header::UPGRADE
+}
+
+fn strip_connection_headers(headers: &mut Headers) {
+    for name in [
+        header::CONNECTION,
+        KEEP_ALIVE.clone(),
+        PROXY_CONNECTION.clone(),
+        header::TRANSFER_ENCODING,
+        header::UPGRADE,
+    ] {
+        headers.remove(name);
+    }
+}
+
+/// What we know about a stream besides its [StreamState]
+struct ClientStream {
+    /// where response headers go
+    responses: mpsc::Sender<ResponseEvent>,
+
+    /// the response body, handed over along with the final response: if
+    /// it's still here, we haven't received it yet.
+    body_rx: Option<mpsc::Receiver<IncomingMessageResult>>,
+
+    /// responses to HEAD requests announce a length but have no body
+    is_head: bool,
+}
+
+/// Drives an h2 client connection: writes requests, reads responses.
+struct ClientContext<OurWriter>
+where
+    OurWriter: WriteOwned,
+{
+    conf: Rc<ClientConf>,
+    state: ConnState,
+
+    hpack_dec: loona_hpack::Decoder<'static>,
+    hpack_enc: loona_hpack::Encoder<'static>,
+    out_scratch: RollMut,
+
+    transport_w: OurWriter,
+
+    ev_rx: mpsc::Receiver<ClientEvent>,
+    shared: Rc<Shared>,
+
+    streams: HashMap<StreamId, ClientStream>,
+
+    /// requests waiting for a stream, because we're at the server's
+    /// SETTINGS_MAX_CONCURRENT_STREAMS
+    pending: VecDeque<OpenRequest>,
+
+    /// the ID of the next stream we open: ours are odd
+    next_stream_id: u32,
+
+    /// the last stream the server said it'll process, if it's going away
+    goaway_recv: Option<StreamId>,
+
+    /// whether every [ClientHandle] was dropped
+    handles_gone: bool,
+}
+
+impl<OurWriteOwned> ClientContext<OurWriteOwned>
+where
+    OurWriteOwned: WriteOwned,
+{
+    async fn work(
+        &mut self,
+        client_buf: RollMut,
+        transport_r: impl ReadOwned,
+    ) -> Result<(), H2ConnectionError> {
+        let res = {
+            let (tx, rx) = mpsc::channel::<(Frame, Roll)>(32);
+            let max_frame_size = Rc::new(AtomicU32::new(self.state.self_settings.max_frame_size));
+
+            let mut deframe_task = std::pin::pin!(deframe_loop(
+                client_buf,
+                transport_r,
+                tx,
+                max_frame_size,
+                EarlyData::none(),
+            ));
+            let mut process_task = std::pin::pin!(self.process_loop(rx));
+
+            tokio::select! {
+                res = &mut deframe_task => {
+                    debug!(?res, "h2 client deframe task finished");
+                    match res {
+                        // the process task still has frames to go through
+                        Ok(()) => (&mut process_task).await,
+                        Err(e) => Err(e),
+                    }
+                }
+                res = &mut process_task => {
+                    debug!(?res, "h2 client process task finished");
+                    res
+                }
+            }
+        };
+
+        if let Err(e) = &res {
+            if !matches!(e, H2ConnectionError::ReadAndParse(_)) {
+                let error_code = e.as_known_error_code();
+                debug!("Connection error: {e} ({e:?}) (code {error_code:?})");
+                // we're done either way
+                let additional_debug_data = if self.conf.goaway_debug_data {
+                    e.goaway_debug_data()
+                } else {
+                    Piece::empty()
+                };
+                let _ = self.send_goaway(error_code, additional_debug_data).await;
+            }
+        }
+        res
+    }
+
+    /// Writes the connection preface: the magic string, then our initial
+    /// settings. The server may be sending its own at the same time.
+    async fn send_preface(&mut self) -> Result<(), H2ConnectionError> {
+        self.transport_w
+            .write_all_owned(parse::PREFACE)
+            .await
+            .map_err(H2ConnectionError::WriteError)?;
+        debug!("Sending initial settings");
+        let setting_payload = {
+            let s = &self.state.self_settings;
+            let pairs = [
+                (Setting::EnablePush, 0),
+                (Setting::InitialWindowSize, s.initial_window_size),
+                (Setting::MaxFrameSize, s.max_frame_size),
+            ];
+            SettingPairs(&pairs[..])
+                .into_piece(&mut self.out_scratch)
+                .map_err(H2ConnectionError::WriteError)?
+        };
+        let frame = Frame::new(
+            FrameType::Settings(Default::default()),
+            StreamId::CONNECTION,
+        );
+        self.write_frame(frame, PieceList::single(setting_payload))
+            .await?;
+
+        // the connection window can only be grown with a WINDOW_UPDATE
+        let conn_window = self.conn_window();
+        if conn_window > self.state.incoming_capacity {
+            let increment = conn_window - self.state.incoming_capacity;
+            self.state.incoming_capacity = conn_window;
+            self.send_window_update(StreamId::CONNECTION, increment as u32)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn process_loop(
+        &mut self,
+        mut rx: mpsc::Receiver<(Frame, Roll)>,
+    ) -> Result<(), H2ConnectionError> {
+        self.send_preface().await?;
+
+        loop {
+            let idle_deadline = self
+                .conf
+                .idle_timeout
+                .zip(self.state.idle_since)
+                .map(|(timeout, idle_since)| idle_since + timeout);
+
+            tokio::select! {
+                biased;
+
+                maybe_frame = rx.recv() => {
+                    match maybe_frame {
+                        Some((frame, payload)) => self.process_frame(frame, payload, &mut rx).await?,
+                        None => {
+                            debug!("h2 client process task: server hung up");
+                            return Ok(());
+                        }
+                    }
+                }
+
+                ev = self.ev_rx.recv(), if !self.handles_gone => {
+                    match ev {
+                        Some(ev) => self.handle_event(ev).await?,
+                        None => {
+                            debug!("every client handle was dropped");
+                            self.handles_gone = true;
+                        }
+                    }
+                }
+
+                _ = self.state.send_data_maybe.notified() => {
+                    self.send_data_maybe().await?;
+                }
+
+                _ = sleep_until_maybe(idle_deadline) => {
+                    debug!("h2 client: connection has been idle for too long, closing it");
+                    self.shared.closed.set(true);
+                    self.send_goaway(KnownErrorCode::NoError, Piece::empty()).await?;
+                    return Ok(());
+                }
+            }
+
+            // streams closed on both ends are done
+            let state = &self.state;
+            self.streams.retain(|id, _| state.streams.contains_key(id));
+            self.open_pending().await?;
+
+            if self.state.streams.is_empty() {
+                self.state.idle_since.get_or_insert_with(Instant::now);
+            } else {
+                self.state.idle_since = None;
+            }
+
+            let done_opening = self.handles_gone || self.goaway_recv.is_some();
+            if done_opening && self.state.streams.is_empty() && self.pending.is_empty() {
+                if self.goaway_recv.is_none() {
+                    self.send_goaway(KnownErrorCode::NoError, Piece::empty())
+                        .await?;
+                }
+                debug!("h2 client: no streams left, closing connection");
+                return Ok(());
+            }
+        }
+    }
+
+    async fn handle_event(&mut self, ev: ClientEvent) -> Result<(), H2ConnectionError> {
+        match ev {
+            ClientEvent::Open(open) => {
+                if self.goaway_recv.is_some() {
+                    let _ = open.opened.send(Err(StreamFailure::NotProcessed));
+                } else {
+                    // opened right away if we're under the limit
+                    self.pending.push_back(open);
+                }
+            }
+            ClientEvent::Stream(ev) => {
+                trace!(?ev, "handling event");
+                match ev.payload {
+                    H2EventPayload::BodyChunk(chunk) => {
+                        if let Err(e) = self.state.push_body_chunk(ev.stream_id, chunk) {
+                            self.rst(ev.stream_id, e).await?;
+                        }
+                    }
+                    H2EventPayload::BodyEnd => self.state.end_body(ev.stream_id, None),
+                    H2EventPayload::Trailers(trailers) => {
+                        self.state.end_body(ev.stream_id, Some(trailers))
+                    }
+                    H2EventPayload::Reset => {
+                        if self.state.streams.contains_key(&ev.stream_id) {
+                            self.rst(ev.stream_id, H2StreamError::Cancel).await?;
+                        }
+                    }
+                    H2EventPayload::Headers(_) => {
+                        unreachable!("request headers are written when the stream is opened")
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Opens streams for pending requests, as far as the server's
+    /// SETTINGS_MAX_CONCURRENT_STREAMS allows
+    async fn open_pending(&mut self) -> Result<(), H2ConnectionError> {
+        let max_streams = self
+            .state
+            .peer_settings
+            .max_concurrent_streams
+            .unwrap_or(u32::MAX) as usize;
+
+        while self.state.streams.len() < max_streams {
+            let Some(open) = self.pending.pop_front() else {
+                break;
+            };
+            if open.opened.is_closed() {
+                // the request was dropped while waiting
+                continue;
+            }
+            if self.next_stream_id > MAX_STREAM_ID {
+                // stream IDs can't be reused: this connection is done opening
+                // streams
+                self.shared.closed.set(true);
+                let _ = open.opened.send(Err(StreamFailure::NotProcessed));
+                continue;
+            }
+            self.open_stream(open).await?;
+        }
+        Ok(())
+    }
+
+    async fn open_stream(&mut self, open: OpenRequest) -> Result<(), H2ConnectionError> {
+        let stream_id = StreamId(self.next_stream_id);
+        self.next_stream_id += 2;
+        debug!(%stream_id, streams = %self.state.streams.len() + 1, "opening stream");
+
+        // header blocks are encoded in the order they're written, since the
+        // encoder's dynamic table is shared by all streams
+        let pseudo_headers: SmallVec<[(&[u8], &[u8]); 5]> = open
+            .pseudo_headers
+            .iter()
+            .map(|(name, value)| (*name, &value[..]))
+            .collect();
+        let block = encode_header_block(
+            &mut self.hpack_enc,
+            &mut self.out_scratch,
+            &pseudo_headers,
+            &open.headers,
+        )
+        .map_err(H2ConnectionError::WriteError)?;
+
+        let (piece_tx, piece_rx) = mpsc::channel::<IncomingMessageResult>(1);
+        let (res_tx, res_rx) = mpsc::channel::<ResponseEvent>(1);
+
+        let incoming =
+            StreamIncoming::new(self.state.self_settings.initial_window_size, None, piece_tx);
+        let mut outgoing = self.state.mk_stream_outgoing(Default::default());
+        outgoing.headers = HeadersOutgoing::WroteAll;
+        if open.end_stream {
+            outgoing.body = BodyOutgoing::DoneSending;
+        }
+        self.state
+            .streams
+            .insert(stream_id, StreamState::Open { incoming, outgoing });
+        self.streams.insert(
+            stream_id,
+            ClientStream {
+                responses: res_tx,
+                body_rx: Some(piece_rx),
+                is_head: open.is_head,
+            },
+        );
+
+        let mut frames = vec![];
+        queue_header_block(
+            &mut frames,
+            stream_id,
+            block,
+            self.state.peer_settings.max_frame_size as usize,
+            open.end_stream,
+        );
+        for (frame, plist) in frames {
+            self.write_frame(frame, plist).await?;
+        }
+
+        if open.opened.send(Ok((stream_id, res_rx))).is_err() {
+            // the request was dropped in the meantime
+            self.rst(stream_id, H2StreamError::Cancel).await?;
+        }
+        Ok(())
+    }
+
+    async fn send_data_maybe(&mut self) -> Result<(), H2ConnectionError> {
+        let frames = self
+            .state
+            .queue_frames(&mut self.hpack_enc, &mut self.out_scratch)
+            .map_err(H2ConnectionError::WriteError)?;
+        for (frame, plist) in frames {
+            debug!(?frame, plist_len = %plist.len(), "writing");
+            self.write_frame(frame, plist).await?;
+        }
+        Ok(())
+    }
+
+    fn conn_window(&self) -> i64 {
+        self.conf
+            .connection_window_size
+            .min(MAX_FLOW_CONTROL_WINDOW) as i64
+    }
+
+    async fn send_window_update(
+        &mut self,
+        stream_id: StreamId,
+        increment: u32,
+    ) -> Result<(), H2ConnectionError> {
+        debug!(%stream_id, %increment, "sending window update");
+        let payload = WindowUpdate {
+            reserved: 0,
+            increment,
+        }
+        .into_piece(&mut self.out_scratch)
+        .map_err(H2ConnectionError::WriteError)?;
+        let frame = Frame::new(FrameType::WindowUpdate, stream_id);
+        self.write_frame(frame, PieceList::single(payload)).await
+    }
+
+    /// Accounts for `len` bytes of DATA received on the connection, giving
+    /// capacity back to the server once half of the window is used up.
+    async fn consume_connection_window(&mut self, len: i64) -> Result<(), H2ConnectionError> {
+        let next_cap = self.state.incoming_capacity - len;
+        if next_cap < 0 {
+            return Err(H2ConnectionError::WindowUnderflow {
+                stream_id: StreamId::CONNECTION,
+            });
+        }
+        self.state.incoming_capacity = next_cap;
+
+        let conn_window = self.conn_window();
+        if self.state.incoming_capacity <= conn_window / 2 {
+            let increment = conn_window - self.state.incoming_capacity;
+            self.state.incoming_capacity = conn_window;
+            self.send_window_update(StreamId::CONNECTION, increment as u32)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn write_frame(
+        &mut self,
+        mut frame: Frame,
+        payload: PieceList,
+    ) -> Result<(), H2ConnectionError> {
+        self.state.on_frame_written(&frame, payload.len());
+
+        frame.len = payload
+            .len()
+            .try_into()
+            .map_err(|_| H2ConnectionError::FrameTooLarge {
+                frame_type: frame.frame_type,
+                frame_size: payload.len() as _,
+                max_frame_size: u32::MAX,
+            })?;
+        debug!(?frame, ">");
+        let frame_roll = frame
+            .into_piece(&mut self.out_scratch)
+            .map_err(H2ConnectionError::WriteError)?;
+
+        if payload.is_empty() {
+            self.transport_w
+                .write_all_owned(frame_roll)
+                .await
+                .map_err(H2ConnectionError::WriteError)?;
+        } else {
+            self.transport_w
+                .writev_all_owned(payload.preceded_by(frame_roll))
+                .await
+                .map_err(H2ConnectionError::WriteError)?;
+        }
+
+        Ok(())
+    }
+
+    async fn send_goaway(
+        &mut self,
+        error_code: KnownErrorCode,
+        additional_debug_data: Piece,
+    ) -> Result<(), H2ConnectionError> {
+        // we never accept streams from the server, so there's no last one
+        debug!(
+            ?error_code,
+            debug_data = %GoAwayDebugData(&additional_debug_data),
+            "Sending GoAway"
+        );
+        let payload = GoAway {
+            last_stream_id: self.state.last_stream_id,
+            error_code: error_code.into(),
+            additional_debug_data,
+        }
+        .into_piece(&mut self.out_scratch)
+        .map_err(H2ConnectionError::WriteError)?;
+        let frame = Frame::new(FrameType::GoAway, StreamId::CONNECTION);
+        self.write_frame(frame, PieceList::single(payload)).await
+    }
+
+    /// Frames for streams we don't know about are fine if we opened (and
+    /// since closed) them: the server may not have heard about it yet, cf.
+    /// RFC 9113, section 5.1. Idle streams are another matter.
+    fn check_closed_stream(
+        &self,
+        frame_type: FrameType,
+        stream_id: StreamId,
+    ) -> Result<(), H2ConnectionError> {
+        if stream_id.is_server_initiated() || stream_id.0 >= self.next_stream_id {
+            return Err(H2ConnectionError::FrameForIdleStream {
+                frame_type,
+                stream_id,
+            });
+        }
+        debug!(%stream_id, ?frame_type, "ignoring frame for closed stream");
+        Ok(())
+    }
+
+    /// Forgets about a stream, letting whoever's waiting on it know that it
+    /// failed: the request, if it's still waiting on the response, the
+    /// response body otherwise.
+    fn remove_stream(&mut self, stream_id: StreamId, failure: StreamFailure) {
+        self.state.streams_with_pending_data.remove(&stream_id);
+        let ss = self.state.streams.remove(&stream_id);
+        debug!(%stream_id, ?failure, streams = %self.state.streams.len(), "closed stream");
+
+        let Some(cs) = self.streams.remove(&stream_id) else {
+            return;
+        };
+        if cs.body_rx.is_some() {
+            let responses = cs.responses;
+            buffet::spawn(async move {
+                let _ = responses.send(Err(failure)).await;
+            });
+        } else if let Some(
+            StreamState::Open { mut incoming, .. } | StreamState::HalfClosedLocal { mut incoming },
+        ) = ss
+        {
+            // just dropping it would make the body look complete
+            buffet::spawn(async move {
+                incoming.send_error(StreamIncomingError::StreamReset).await;
+            });
+        }
+    }
+
+    /// Send a RST_STREAM frame to the server.
+    async fn rst(
+        &mut self,
+        stream_id: StreamId,
+        e: H2StreamError,
+    ) -> Result<(), H2ConnectionError> {
+        let error_code = e.as_known_error_code();
+        debug!(%stream_id, ?error_code, "Sending RstStream because: {e}");
+
+        let failure = match e {
+            H2StreamError::MalformedResponse(reason) => StreamFailure::Malformed(reason),
+            H2StreamError::DataLengthDoesNotMatchContentLength { .. } => {
+                StreamFailure::Malformed("the response body doesn't match its content-length")
+            }
+            H2StreamError::TrailersNotEndStream => {
+                StreamFailure::Malformed("trailers must end the stream")
+            }
+            H2StreamError::StreamClosed => {
+                StreamFailure::Malformed("received a frame after the server ended the stream")
+            }
+            // nobody's listening, or we're the ones who gave up
+            _ => StreamFailure::Reset(error_code.into()),
+        };
+        self.remove_stream(stream_id, failure);
+
+        let payload = RstStream {
+            error_code: error_code.into(),
+        }
+        .into_piece(&mut self.out_scratch)
+        .map_err(H2ConnectionError::WriteError)?;
+        let frame = Frame::new(FrameType::RstStream, stream_id);
+        self.write_frame(frame, PieceList::single(payload)).await
+    }
+
+    /// The server sent END_STREAM on this stream
+    fn on_end_stream_received(&mut self, stream_id: StreamId) {
+        let Some(ss) = self.state.streams.get_mut(&stream_id) else {
+            return;
+        };
+        match std::mem::take(ss) {
+            StreamState::Open { outgoing, .. } => {
+                *ss = StreamState::HalfClosedRemote { outgoing };
+            }
+            _ => {
+                self.state.streams.remove(&stream_id);
+                debug!(
+                    "Closed stream {} (read END_STREAM), now have {} streams",
+                    stream_id,
+                    self.state.streams.len()
+                );
+            }
+        }
+    }
+
+    async fn process_frame(
+        &mut self,
+        frame: Frame,
+        mut payload: Roll,
+        rx: &mut mpsc::Receiver<(Frame, Roll)>,
+    ) -> Result<(), H2ConnectionError> {
+        match frame.frame_type {
+            FrameType::Data(flags) => {
+                if frame.stream_id == StreamId::CONNECTION {
+                    return Err(H2ConnectionError::StreamSpecificFrameToConnection {
+                        frame_type: frame.frame_type,
+                    });
+                }
+
+                // the whole payload counts against flow control windows,
+                // padding included
+                let flow_len = frame.len as i64;
+                self.consume_connection_window(flow_len).await?;
+
+                let awaiting_headers = match self.streams.get(&frame.stream_id) {
+                    Some(cs) => cs.body_rx.is_some(),
+                    None => return self.check_closed_stream(frame.frame_type, frame.stream_id),
+                };
+                if awaiting_headers {
+                    return self
+                        .rst(
+                            frame.stream_id,
+                            H2StreamError::MalformedResponse(
+                                "received DATA before the response headers",
+                            ),
+                        )
+                        .await;
+                }
+
+                let stream_window = self.state.self_settings.initial_window_size as i64;
+                let mut stream_window_update = None;
+
+                let Some(ss) = self.state.streams.get_mut(&frame.stream_id) else {
+                    return self.check_closed_stream(frame.frame_type, frame.stream_id);
+                };
+                match ss {
+                    StreamState::Open { incoming, .. }
+                    | StreamState::HalfClosedLocal { incoming } => {
+                        let next_cap = incoming.capacity - flow_len;
+                        if next_cap < 0 {
+                            return Err(H2ConnectionError::WindowUnderflow {
+                                stream_id: frame.stream_id,
+                            });
+                        }
+                        incoming.capacity = next_cap;
+
+                        let which = if frame.is_end_stream() {
+                            ChunkPosition::Last
+                        } else {
+                            ChunkPosition::NotLast
+                        };
+
+                        if let Err(e) = incoming.write_chunk(payload.into(), which).await {
+                            self.rst(frame.stream_id, e).await?;
+                        } else if flags.contains(DataFlags::EndStream) {
+                            self.on_end_stream_received(frame.stream_id);
+                        } else if incoming.capacity <= stream_window / 2 {
+                            // the driver has the chunk: give capacity back
+                            // to the server once half of the window is used up
+                            stream_window_update = Some(stream_window - incoming.capacity);
+                            incoming.capacity = stream_window;
+                        }
+                    }
+                    StreamState::HalfClosedRemote { .. } => {
+                        self.rst(frame.stream_id, H2StreamError::StreamClosed)
+                            .await?;
+                    }
+                    StreamState::Transition => unreachable!(),
+                }
+
+                if let Some(increment) = stream_window_update {
+                    self.send_window_update(frame.stream_id, increment as u32)
+                        .await?;
+                }
+            }
+            FrameType::Headers(flags) => {
+                if frame.stream_id == StreamId::CONNECTION {
+                    return Err(H2ConnectionError::StreamSpecificFrameToConnection {
+                        frame_type: frame.frame_type,
+                    });
+                }
+
+                if flags.contains(HeadersFlags::Priority) {
+                    // we don't act on priorities from the server
+                    (payload, _) = PrioritySpec::parse(payload).finish().map_err(|_| {
+                        H2ConnectionError::ReadAndParse(ReadAndParseError::ParsingError {
+                            parser: "PrioritySpec",
+                        })
+                    })?;
+                }
+
+                let block = read_header_block(frame.stream_id, flags, payload, rx).await?;
+                self.on_headers(
+                    frame.stream_id,
+                    &block,
+                    flags.contains(HeadersFlags::EndStream),
+                )
+                .await?;
+            }
+            FrameType::Priority => {
+                debug!(stream_id = %frame.stream_id, "ignoring priority frame");
+            }
+            FrameType::RstStream => {
+                if frame.len != 4 {
+                    self.rst(
+                        frame.stream_id,
+                        H2StreamError::InvalidRstStreamFrameSize {
+                            frame_size: frame.len,
+                        },
+                    )
+                    .await?;
+                    return Ok(());
+                }
+                let (_, rst) = RstStream::parse(payload).finish().map_err(|_| {
+                    H2ConnectionError::ReadAndParse(ReadAndParseError::ParsingError {
+                        parser: "RstStream",
+                    })
+                })?;
+
+                if !self.state.streams.contains_key(&frame.stream_id) {
+                    return self.check_closed_stream(frame.frame_type, frame.stream_id);
+                }
+                debug!(stream_id = %frame.stream_id, error_code = ?rst.error_code, "server reset stream");
+                self.remove_stream(frame.stream_id, StreamFailure::Reset(rst.error_code));
+            }
+            FrameType::Settings(s) => {
+                if frame.stream_id != StreamId::CONNECTION {
+                    return Err(H2ConnectionError::SettingsWithNonZeroStreamId {
+                        stream_id: frame.stream_id,
+                    });
+                }
+
+                if payload.len() % 6 != 0 {
+                    return Err(H2ConnectionError::SettingsInvalidLength {
+                        len: payload.len() as _,
+                    });
+                }
+
+                if s.contains(SettingsFlags::Ack) {
+                    debug!("Server has acknowledged our settings, cool");
+                    if !payload.is_empty() {
+                        return Err(H2ConnectionError::SettingsInvalidLength {
+                            len: payload.len() as _,
+                        });
+                    }
+                } else {
+                    let maybe_send_data = self
+                        .state
+                        .apply_peer_settings(&payload[..], &mut self.hpack_enc)?;
+                    self.shared
+                        .max_concurrent_streams
+                        .set(self.state.peer_settings.max_concurrent_streams);
+
+                    let frame = Frame::new(
+                        FrameType::Settings(SettingsFlags::Ack.into()),
+                        StreamId::CONNECTION,
+                    );
+                    self.write_frame(frame, PieceList::default()).await?;
+                    debug!("Acknowledged server settings");
+
+                    if maybe_send_data {
+                        self.state.send_data_maybe.notify_one();
+                    }
+                }
+            }
+            FrameType::PushPromise => {
+                return Err(H2ConnectionError::PushPromiseWhileDisabled);
+            }
+            FrameType::Ping(flags) => {
+                if frame.stream_id != StreamId::CONNECTION {
+                    return Err(H2ConnectionError::PingFrameWithNonZeroStreamId {
+                        stream_id: frame.stream_id,
+                    });
+                }
+
+                if frame.len != 8 {
+                    return Err(H2ConnectionError::PingFrameInvalidLength { len: frame.len });
+                }
+
+                if flags.contains(PingFlags::Ack) {
+                    // we never send pings
+                    return Ok(());
+                }
+
+                let flags = PingFlags::Ack.into();
+                let frame = Frame::new(FrameType::Ping(flags), StreamId::CONNECTION)
+                    .with_len(payload.len() as u32);
+                self.write_frame(frame, PieceList::default().followed_by(payload))
+                    .await?;
+            }
+            FrameType::GoAway => {
+                if frame.stream_id != StreamId::CONNECTION {
+                    return Err(H2ConnectionError::GoAwayWithNonZeroStreamId {
+                        stream_id: frame.stream_id,
+                    });
+                }
+
+                let (_, goaway) = GoAway::parse(payload).finish().map_err(|_| {
+                    H2ConnectionError::ReadAndParse(ReadAndParseError::ParsingError {
+                        parser: "GoAway",
+                    })
+                })?;
+                // a second GOAWAY can only lower the last stream ID
+                let last_stream_id = match self.goaway_recv {
+                    Some(prev) => prev.min(goaway.last_stream_id),
+                    None => goaway.last_stream_id,
+                };
+                debug!(
+                    %last_stream_id,
+                    error_code = ?goaway.error_code,
+                    debug_data = %GoAwayDebugData(&goaway.additional_debug_data),
+                    "server is going away"
+                );
+                self.goaway_recv = Some(last_stream_id);
+                self.shared.closed.set(true);
+
+                // streams past the last one won't be processed, but they can
+                // be retried elsewhere
+                let unprocessed: Vec<StreamId> = self
+                    .streams
+                    .keys()
+                    .filter(|id| **id > last_stream_id)
+                    .copied()
+                    .collect();
+                for id in unprocessed {
+                    self.remove_stream(id, StreamFailure::NotProcessed);
+                }
+                for open in self.pending.drain(..) {
+                    let _ = open.opened.send(Err(StreamFailure::NotProcessed));
+                }
+            }
+            FrameType::WindowUpdate => {
+                if payload.len() != 4 {
+                    return Err(H2ConnectionError::WindowUpdateInvalidLength {
+                        len: payload.len() as _,
+                    });
+                }
+
+                let (_, update) = WindowUpdate::parse(payload).finish().map_err(|_| {
+                    H2ConnectionError::ReadAndParse(ReadAndParseError::ParsingError {
+                        parser: "WindowUpdate",
+                    })
+                })?;
+                debug!(?update, "Received window update");
+
+                if update.increment == 0 {
+                    return Err(H2ConnectionError::WindowUpdateZeroIncrement);
+                }
+
+                if frame.stream_id == StreamId::CONNECTION {
+                    let new_capacity = self.state.outgoing_capacity + update.increment as i64;
+                    if new_capacity > MAX_WINDOW_SIZE {
+                        return Err(H2ConnectionError::WindowUpdateOverflow);
+                    };
+
+                    self.state.outgoing_capacity = new_capacity;
+                    self.state.send_data_maybe.notify_one();
+                } else {
+                    let Some(outgoing) = self
+                        .state
+                        .streams
+                        .get_mut(&frame.stream_id)
+                        .and_then(|ss| ss.outgoing_mut())
+                    else {
+                        // either we're done sending on that stream, or it's idle
+                        return self.check_closed_stream(frame.frame_type, frame.stream_id);
+                    };
+
+                    let new_capacity = outgoing.capacity + update.increment as i64;
+                    if new_capacity > MAX_WINDOW_SIZE {
+                        self.rst(frame.stream_id, H2StreamError::WindowUpdateOverflow)
+                            .await?;
+                        return Ok(());
+                    }
+
+                    let old_capacity = outgoing.capacity;
+                    outgoing.capacity = new_capacity;
+
+                    if old_capacity <= 0 && new_capacity > 0 {
+                        self.state.streams_with_pending_data.insert(frame.stream_id);
+                        if self.state.outgoing_capacity > 0 {
+                            self.state.send_data_maybe.notify_one();
+                        }
+                    }
+                }
+            }
+            FrameType::Continuation(_flags) => {
+                return Err(H2ConnectionError::UnexpectedContinuationFrame {
+                    stream_id: frame.stream_id,
+                });
+            }
+            FrameType::Unknown(ft) => {
+                trace!(
+                    "ignoring unknown frame with type 0x{:x}, flags 0x{:x}",
+                    ft.ty,
+                    ft.flags
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handles a header block the server sent: informational or final
+    /// response headers, or trailers.
+    async fn on_headers(
+        &mut self,
+        stream_id: StreamId,
+        block: &[u8],
+        end_stream: bool,
+    ) -> Result<(), H2ConnectionError> {
+        // `None` if we're ignoring this block
+        let is_trailers = match (
+            self.streams.get(&stream_id),
+            self.state.streams.get(&stream_id),
+        ) {
+            (Some(cs), Some(StreamState::Open { .. } | StreamState::HalfClosedLocal { .. })) => {
+                Some(cs.body_rx.is_none())
+            }
+            (_, Some(StreamState::HalfClosedRemote { .. })) => {
+                return Err(H2ConnectionError::StreamClosed { stream_id });
+            }
+            _ => None,
+        };
+
+        // the block must be decoded even if we end up ignoring it: the
+        // decoder's dynamic table is shared by all streams
+        let decoded = decode_header_block(&mut self.hpack_dec, block, is_trailers == Some(true))?;
+
+        let Some(is_trailers) = is_trailers else {
+            return self.check_closed_stream(FrameType::Headers(Default::default()), stream_id);
+        };
+        let (status, headers) = match decoded {
+            Ok(decoded) => decoded,
+            Err(reason) => {
+                return self
+                    .rst(stream_id, H2StreamError::MalformedResponse(reason))
+                    .await;
+            }
+        };
+
+        if is_trailers {
+            if !end_stream {
+                return self
+                    .rst(stream_id, H2StreamError::TrailersNotEndStream)
+                    .await;
+            }
+            let incoming = match self.state.streams.get_mut(&stream_id) {
+                Some(
+                    StreamState::Open { incoming, .. } | StreamState::HalfClosedLocal { incoming },
+                ) => incoming,
+                _ => unreachable!("checked above"),
+            };
+            if let Err(e) = incoming.write_trailers(headers).await {
+                return self.rst(stream_id, e).await;
+            }
+            self.on_end_stream_received(stream_id);
+            return Ok(());
+        }
+
+        let cs = self.streams.get_mut(&stream_id).expect("checked above");
+        let res = Response {
+            version: Version::HTTP_2,
+            status: status.expect("checked while decoding"),
+            headers,
+            header_case: None,
+        };
+
+        if res.status.is_informational() {
+            if end_stream || res.status == StatusCode::SWITCHING_PROTOCOLS {
+                return self
+                    .rst(
+                        stream_id,
+                        H2StreamError::MalformedResponse(
+                            "informational responses can't end the stream, and there's no 101 in HTTP/2 (RFC 9113, section 8.1)",
+                        ),
+                    )
+                    .await;
+            }
+            debug!(%stream_id, status = %res.status, "received informational response");
+            if cs
+                .responses
+                .send(Ok(ResponseHead::Informational(res)))
+                .await
+                .is_err()
+            {
+                self.rst(stream_id, H2StreamError::Cancel).await?;
+            }
+            return Ok(());
+        }
+
+        let content_length = if end_stream || cs.is_head || res.means_empty_body() {
+            Some(0)
+        } else {
+            res.headers.content_length()
+        };
+        let body = H2Body {
+            content_length,
+            eof: end_stream,
+            rx: cs.body_rx.take().expect("not trailers"),
+        };
+        let responses = cs.responses.clone();
+        if let Some(
+            StreamState::Open { incoming, .. } | StreamState::HalfClosedLocal { incoming },
+        ) = self.state.streams.get_mut(&stream_id)
+        {
+            incoming.content_length = content_length;
+        }
+
+        debug!(%stream_id, status = %res.status, ?content_length, "received response");
+        if responses
+            .send(Ok(ResponseHead::Final(res, body)))
+            .await
+            .is_err()
+        {
+            return self.rst(stream_id, H2StreamError::Cancel).await;
+        }
+        if end_stream {
+            self.on_end_stream_received(stream_id);
+        }
+        Ok(())
+    }
+}
+
+impl<OurWriteOwned> Drop for ClientContext<OurWriteOwned>
+where
+    OurWriteOwned: WriteOwned,
+{
+    fn drop(&mut self) {
+        self.shared.closed.set(true);
+
+        // requests still in flight won't get anywhere
+        let ids: Vec<StreamId> = self.streams.keys().copied().collect();
+        for id in ids {
+            self.remove_stream(id, StreamFailure::ConnectionClosed);
+        }
+        for open in self.pending.drain(..) {
+            let _ = open.opened.send(Err(StreamFailure::ConnectionClosed));
+        }
+    }
+}
+
+/// Reads a header block, which may continue in CONTINUATION frames
+async fn read_header_block(
+    stream_id: StreamId,
+    flags: loona_h2::enumflags2::BitFlags<HeadersFlags, u8>,
+    payload: Roll,
+    rx: &mut mpsc::Receiver<(Frame, Roll)>,
+) -> Result<Piece, H2ConnectionError> {
+    if flags.contains(HeadersFlags::EndHeaders) {
+        // good, no continuation frames needed
+        return Ok(payload.into());
+    }
+
+    let mut fragments: SmallVec<[Roll; 2]> = smallvec![payload];
+    loop {
+        let Some((frame, payload)) = rx.recv().await else {
+            return Err(H2ConnectionError::ExpectedContinuationFrame {
+                stream_id,
+                frame_type: None,
+            });
+        };
+        if frame.stream_id != stream_id {
+            return Err(H2ConnectionError::ExpectedContinuationForStream {
+                stream_id,
+                continuation_stream_id: frame.stream_id,
+            });
+        }
+        let cont_flags = match frame.frame_type {
+            FrameType::Continuation(flags) => flags,
+            other => {
+                return Err(H2ConnectionError::ExpectedContinuationFrame {
+                    stream_id,
+                    frame_type: Some(other),
+                })
+            }
+        };
+        fragments.push(payload);
+        if cont_flags.contains(ContinuationFlags::EndHeaders) {
+            break;
+        }
+    }
+
+    let total_len = fragments.iter().map(|f| f.len()).sum();
+    let mut block = Vec::with_capacity(total_len);
+    for frag in &fragments {
+        block.extend_from_slice(&frag[..]);
+    }
+    Ok(block.into())
+}
+
+/// Decodes a response's header block (or its trailers): the outer error is
+/// for the connection, the inner one for the stream. Responses always come
+/// with a status.
+#[allow(clippy::type_complexity)]
+fn decode_header_block(
+    hpack_dec: &mut loona_hpack::Decoder<'static>,
+    block: &[u8],
+    is_trailers: bool,
+) -> Result<Result<(Option<StatusCode>, Headers), &'static str>, H2ConnectionError> {
+    let mut status: Option<StatusCode> = None;
+    let mut headers = Headers::default();
+    let mut arena = HeaderArena::default();
+    // decoding needs to finish no matter what, to keep the decoder's state
+    // in sync with the server's encoder
+    let mut error: Option<&'static str> = None;
+    let mut saw_regular_header = false;
+
+    hpack_dec.decode_with_cb(block, |key: Cow<[u8]>, value: Cow<[u8]>| {
+        if error.is_some() {
+            return;
+        }
+
+        if let Some(pseudo) = key.strip_prefix(b":") {
+            if saw_regular_header {
+                error = Some("pseudo-header fields must appear before regular ones (RFC 9113, section 8.3)");
+                return;
+            }
+            if is_trailers || pseudo != b"status" {
+                error = Some("responses only have a ':status' pseudo-header, and trailers none (RFC 9113, section 8.3.2)");
+                return;
+            }
+            let Ok(code) = StatusCode::from_bytes(&value) else {
+                error = Some("invalid ':status' pseudo-header");
+                return;
+            };
+            if status.replace(code).is_some() {
+                error = Some("duplicate ':status' pseudo-header");
+            }
+        } else {
+            saw_regular_header = true;
+
+            if key.iter().any(|b| b.is_ascii_uppercase()) {
+                error = Some("field names must be lowercase (RFC 9113, section 8.2.1)");
+                return;
+            }
+            let Ok(name) = HeaderName::from_bytes(&key) else {
+                error = Some("invalid header name (RFC 9113, section 8.2.1)");
+                return;
+            };
+            if is_connection_specific(&name) {
+                error = Some("connection-specific headers are forbidden (RFC 9113, section 8.2.2)");
+                return;
+            }
+            headers.append(name, arena.alloc(&value));
+        }
+    })?;
+
+    if !is_trailers && error.is_none() && status.is_none() {
+        error = Some("missing ':status' pseudo-header (RFC 9113, section 8.3.2)");
+    }
+    Ok(match error {
+        Some(reason) => Err(reason),
+        None => Ok((status, headers)),
+    })
+}
@@ -1,11 +1,13 @@
-use buffet::Piece;
+use std::{cell::Cell, rc::Rc};
+
+use buffet::{Piece, PieceList, RollMut};
 use http::{StatusCode, Version};
 use tokio::sync::mpsc;
 use tracing::debug;
 
 use super::types::{H2Event, H2EventPayload};
-use crate::{Encoder, Response};
-use loona_h2::StreamId;
+use crate::{Encoder, Headers, Response};
+use loona_h2::{enumflags2::BitFlags, ContinuationFlags, Frame, FrameType, HeadersFlags, StreamId};
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[non_exhaustive]
@@ -15,11 +17,27 @@ pub enum EncoderState {
     ResponseDone,
 }
 
+/// What an [H2Encoder] does when it's dropped before the response is done
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum OnDrop {
+    /// Finish the response itself: a 500 if no headers were sent yet, a
+    /// stream reset otherwise
+    Finish,
+
+    /// Leave it to the task running the handler, which knows why the handler
+    /// failed: just record the state we were in
+    Defer,
+
+    /// We were dropped while deferring, in this state
+    Dropped(EncoderState),
+}
+
 /// Encodes HTTP/2 responses and bodies
 pub struct H2Encoder {
     stream_id: StreamId,
     tx: mpsc::Sender<H2Event>,
     state: EncoderState,
+    on_drop: Option<Rc<Cell<OnDrop>>>,
 }
 
 impl H2Encoder {
@@ -28,9 +46,17 @@ impl H2Encoder {
             stream_id,
             tx,
             state: EncoderState::ExpectResponseHeaders,
+            on_drop: None,
         }
     }
 
+    /// Defer finishing the response on drop to whoever holds the returned cell
+    pub(crate) fn deferring_drop(mut self) -> (Self, Rc<Cell<OnDrop>>) {
+        let on_drop = Rc::new(Cell::new(OnDrop::Defer));
+        self.on_drop = Some(on_drop.clone());
+        (self, on_drop)
+    }
+
     fn event(&self, payload: H2EventPayload) -> H2Event {
         H2Event {
             payload,
@@ -71,12 +97,6 @@ impl Encoder for H2Encoder {
     type Error = H2EncoderError;
 
     async fn write_response(&mut self, res: Response) -> Result<(), Self::Error> {
-        // FIXME: HTTP/2 _does_ support informational responses, cf. https://github.com/bearcove/loona/issues/190
-        assert!(
-            !res.status.is_informational(),
-            "http/2 does not support informational responses"
-        );
-
         if self.state != EncoderState::ExpectResponseHeaders {
             return Err(H2EncoderError::WrongState {
                 expected: EncoderState::ExpectResponseHeaders,
@@ -84,8 +104,13 @@ impl Encoder for H2Encoder {
             });
         }
 
+        // informational responses are header blocks of their own, that the
+        // final response follows on the same stream
+        let informational = res.status.is_informational();
         self.send(H2EventPayload::Headers(res)).await?;
-        self.state = EncoderState::ExpectResponseBody;
+        if !informational {
+            self.state = EncoderState::ExpectResponseBody;
+        }
 
         Ok(())
     }
@@ -118,21 +143,30 @@ impl Encoder for H2Encoder {
         Ok(())
     }
 
-    // TODO: handle trailers
-    async fn write_trailers(&mut self, _trailers: Box<crate::Headers>) -> Result<(), Self::Error> {
-        if self.state != EncoderState::ResponseDone {
+    async fn write_trailers(&mut self, trailers: Box<Headers>) -> Result<(), Self::Error> {
+        if self.state != EncoderState::ExpectResponseBody {
             return Err(H2EncoderError::WrongState {
-                expected: EncoderState::ResponseDone,
+                expected: EncoderState::ExpectResponseBody,
                 actual: self.state,
             });
         }
 
-        todo!("write trailers")
+        self.send(H2EventPayload::Trailers(trailers)).await?;
+        self.state = EncoderState::ResponseDone;
+
+        Ok(())
     }
 }
 
 impl Drop for H2Encoder {
     fn drop(&mut self) {
+        if let Some(on_drop) = &self.on_drop {
+            if let OnDrop::Defer = on_drop.get() {
+                on_drop.set(OnDrop::Dropped(self.state));
+                return;
+            }
+        }
+
         let mut evs = vec![];
 
         match self.state {
@@ -141,12 +175,14 @@ impl Drop for H2Encoder {
                     version: Version::HTTP_11,
                     status: StatusCode::INTERNAL_SERVER_ERROR,
                     headers: Default::default(),
+                    header_case: None,
                 })));
                 evs.push(self.event(H2EventPayload::BodyEnd));
             }
             EncoderState::ExpectResponseBody => {
-                // TODO: this should probably be RST_STREAM instead
-                evs.push(self.event(H2EventPayload::BodyEnd));
+                // ending the body here would pass a truncated response off as
+                // a complete one.
+                evs.push(self.event(H2EventPayload::Reset));
             }
             EncoderState::ResponseDone => {
                 // ah, good.
@@ -166,3 +202,82 @@ impl Drop for H2Encoder {
         }
     }
 }
+
+/// HPACK-encodes a header block: pseudo-headers first (like `:status` for
+/// responses, none for trailers), then `headers`. This must be done in the
+/// order blocks are written, since the encoder's dynamic table is shared by
+/// all streams.
+pub(crate) fn encode_header_block(
+    hpack_enc: &mut loona_hpack::Encoder<'static>,
+    out_scratch: &mut RollMut,
+    pseudo_headers: &[(&[u8], &[u8])],
+    headers: &Headers,
+) -> std::io::Result<Piece> {
+    // TODO: don't allocate so much for headers. all `encode_into`
+    // wants is an `IntoIter`, we can definitely have a custom iterator
+    // that operates on all this instead of using a `Vec`.
+
+    // TODO: enforce max header size
+    // TODO: prevent overwriting pseudo-headers, especially :status?
+    let mut block: Vec<(&[u8], &[u8])> = pseudo_headers.to_vec();
+
+    for (name, value) in headers.iter() {
+        if name == http::header::TRANSFER_ENCODING {
+            // do not set transfer-encoding: chunked when doing HTTP/2
+            continue;
+        }
+        block.push((name.as_str().as_bytes(), value));
+    }
+
+    assert_eq!(out_scratch.len(), 0);
+    hpack_enc.encode_into(block, out_scratch)?;
+    Ok(out_scratch.take_all().into())
+}
+
+/// Queues a HEADERS frame for `block`, followed by as many CONTINUATION
+/// frames as needed to respect the peer's max frame size.
+pub(crate) fn queue_header_block(
+    frames: &mut Vec<(Frame, PieceList)>,
+    stream_id: StreamId,
+    mut block: Piece,
+    max_fram: usize,
+    end_stream: bool,
+) {
+    let mut is_continuation = false;
+    loop {
+        let is_last = block.len() <= max_fram;
+        let written;
+        (written, block) = if is_last {
+            (block, Piece::empty())
+        } else {
+            debug!(write_size = %max_fram, requeued_len = %(block.len() - max_fram), "splitting headers");
+            block.split_at(max_fram)
+        };
+
+        let frame_type = if is_continuation {
+            let mut flags = BitFlags::<ContinuationFlags>::default();
+            if is_last {
+                flags |= ContinuationFlags::EndHeaders;
+            }
+            FrameType::Continuation(flags)
+        } else {
+            let mut flags = BitFlags::<HeadersFlags>::default();
+            if is_last {
+                flags |= HeadersFlags::EndHeaders;
+            }
+            if end_stream {
+                flags |= HeadersFlags::EndStream;
+            }
+            FrameType::Headers(flags)
+        };
+        frames.push((
+            Frame::new(frame_type, stream_id),
+            PieceList::single(written),
+        ));
+
+        if is_last {
+            return;
+        }
+        is_continuation = true;
+    }
+}
@@ -4,8 +4,13 @@
 mod server;
 pub use server::*;
 
+mod client;
+pub use client::*;
+
+mod arena;
 mod body;
 mod encode;
-pub use encode::H2EncoderError;
+pub use encode::{H2Encoder, H2EncoderError};
 
+pub mod priority;
 pub mod types;
@@ -0,0 +1,103 @@
+//! Extensible prioritization scheme for HTTP, cf. <https://www.rfc-editor.org/rfc/rfc9218>
+//!
+//! Clients signal the priority of a response with the `priority` request
+//! header, and may change it later with PRIORITY_UPDATE frames. When
+//! interleaving DATA frames, we send streams with a lower urgency first, and
+//! within an urgency level, non-incremental responses one after the other (in
+//! stream ID order) so they can be used as soon as possible, while
+//! incremental responses share bandwidth, one frame at a time.
+
+/// The priority of a response, cf. <https://www.rfc-editor.org/rfc/rfc9218#section-4>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Priority {
+    /// From 0 to 7, lower is more important. Defaults to 3.
+    pub urgency: u8,
+
+    /// Whether the client can make use of partial responses, and so would
+    /// rather have them share bandwidth with other responses than wait for
+    /// their turn. Defaults to false.
+    pub incremental: bool,
+}
+
+impl Default for Priority {
+    fn default() -> Self {
+        Self {
+            urgency: Self::DEFAULT_URGENCY,
+            incremental: false,
+        }
+    }
+}
+
+impl Priority {
+    pub const DEFAULT_URGENCY: u8 = 3;
+    pub const MAX_URGENCY: u8 = 7;
+
+    /// Parses a Priority field value (a structured field dictionary, e.g.
+    /// `u=5, i`). Parameters that are absent take their default value, both
+    /// for the `priority` header and for PRIORITY_UPDATE frames.
+    ///
+    /// Unknown keys, parameters, and invalid values are ignored, as the spec
+    /// says they should be, so this never fails.
+    pub fn parse(value: &[u8]) -> Self {
+        let mut this = Self::default();
+
+        let value = match std::str::from_utf8(value) {
+            Ok(value) => value,
+            Err(_) => return this,
+        };
+
+        for member in value.split(',') {
+            // ignore parameters, e.g. `u=1;foo=bar`
+            let member = member.split(';').next().unwrap_or_default().trim();
+            let (key, value) = match member.split_once('=') {
+                Some((key, value)) => (key, Some(value)),
+                None => (member, None),
+            };
+
+            match (key, value) {
+                ("u", Some(value)) => {
+                    if let Ok(urgency) = value.parse::<u8>() {
+                        if urgency <= Self::MAX_URGENCY {
+                            this.urgency = urgency;
+                        }
+                    }
+                }
+                ("i", None | Some("?1")) => this.incremental = true,
+                ("i", Some("?0")) => this.incremental = false,
+                _ => {
+                    // ignore unknown keys and invalid values
+                }
+            }
+        }
+
+        this
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Priority;
+
+    #[test]
+    fn test_priority_parse() {
+        assert_eq!(Priority::parse(b""), Priority::default());
+        assert_eq!(
+            Priority::parse(b"u=5, i"),
+            Priority {
+                urgency: 5,
+                incremental: true
+            }
+        );
+        assert_eq!(
+            Priority::parse(b"i=?1;foo=bar,u=0"),
+            Priority {
+                urgency: 0,
+                incremental: true
+            }
+        );
+
+        // invalid values and unknown keys are ignored
+        assert_eq!(Priority::parse(b"u=8, i=maybe, x=1"), Priority::default());
+        assert_eq!(Priority::parse(b"u=-1"), Priority::default());
+    }
+}
@@ -1,13 +1,15 @@
 use std::{
     borrow::Cow,
-    collections::{hash_map::Entry, HashSet},
-    io::Write,
+    collections::hash_map::Entry,
+    future::Future,
     rc::Rc,
     sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
 };
 
 use buffet::{Piece, PieceList, PieceStr, ReadOwned, Roll, RollMut, WriteOwned};
 use byteorder::{BigEndian, WriteBytesExt};
+use futures_util::{future::LocalBoxFuture, FutureExt};
 use http::{
     header,
     uri::{Authority, PathAndQuery, Scheme},
@@ -15,62 +17,582 @@ use http::{
 };
 use loona_h2::{
     self as parse, enumflags2::BitFlags, nom::Finish, ContinuationFlags, DataFlags, Frame,
-    FrameType, HeadersFlags, PingFlags, PrioritySpec, Setting, SettingPairs, Settings,
-    SettingsFlags, StreamId, WindowUpdate,
+    FrameType, GoAway, HeadersFlags, KnownErrorCode, PingFlags, PrioritySpec, PriorityUpdate,
+    Setting, SettingPairs, Settings, SettingsFlags, StreamId, WindowUpdate,
+    PRIORITY_UPDATE_FRAME_TYPE,
 };
 use parse::IntoPiece;
 use smallvec::{smallvec, SmallVec};
-use tokio::sync::mpsc;
+use tokio::{sync::mpsc, time::Instant};
 use tracing::{debug, trace};
 
 use crate::{
-    error::ServeError,
+    cancel::{CancelReason, Cancellation},
+    early_data::{self, EarlyData, EARLY_DATA},
+    error::{default_error_response, ConfError, ErrorHook, HandlerFailure, ServeError},
     h2::{
+        arena::HeaderArena,
         body::{H2Body, IncomingMessageResult, StreamIncoming, StreamIncomingError},
-        encode::H2Encoder,
+        encode::{EncoderState, H2Encoder, OnDrop},
+        priority::Priority,
         types::{
-            BodyOutgoing, ConnState, H2ConnectionError, H2Event, H2EventPayload, H2RequestError,
-            H2StreamError, HeadersOrTrailers, HeadersOutgoing, StreamOutgoing, StreamState,
+            BodyOutgoing, ConnState, GoAwayDebugData, H2ConnectionError, H2Event, H2EventPayload,
+            H2RequestError, H2StreamError, HeadersOrTrailers, HeadersOutgoing, StreamOutgoing,
+            StreamState,
         },
     },
-    util::{read_and_parse, ReadAndParseError},
-    Headers, Method, Request, Responder, ResponderOrBodyError, ServeOutcome, ServerDriver,
-    SinglePieceBody,
+    introspect::{
+        H2ConnectionDump, H2DumpRequest, H2StreamDump, H2StreamState, HpackTableDump,
+        ResponseProgress,
+    },
+    memory::MemoryBudget,
+    tunnel::is_valid_connect_authority,
+    util::{catch_unwind, panic_message, read_and_parse, ReadAndParseError},
+    validation::Validation,
+    ConnectionInfo, Headers, Method, Request, Responder, ResponderOrBodyError, ServeOutcome,
+    ServerDriver, SinglePieceBody,
 };
 
 use super::{body::ChunkPosition, types::H2ErrorLevel};
 
-pub const MAX_WINDOW_SIZE: i64 = u32::MAX as i64;
+/// The initial size of flow control windows, for streams and connections
+/// alike, cf. <https://httpwg.org/specs/rfc9113.html#InitialWindowSize>
+pub(crate) const DEFAULT_WINDOW_SIZE: u32 = (1 << 16) - 1;
+
+/// The largest flow control window there can be, cf. <https://httpwg.org/specs/rfc9113.html#FlowControl>
+pub(crate) const MAX_FLOW_CONTROL_WINDOW: u32 = (1 << 31) - 1;
+
+/// [MAX_FLOW_CONTROL_WINDOW], for comparing against capacities, which can
+/// go negative
+pub const MAX_WINDOW_SIZE: i64 = MAX_FLOW_CONTROL_WINDOW as i64;
+
+/// The smallest and largest SETTINGS_MAX_FRAME_SIZE there can be, cf. <https://httpwg.org/specs/rfc9113.html#SETTINGS_MAX_FRAME_SIZE>
+pub(crate) const MIN_MAX_FRAME_SIZE: u32 = 1 << 14;
+pub(crate) const MAX_MAX_FRAME_SIZE: u32 = (1 << 24) - 1;
+
+/// How large a header block clients may send by default: plenty for cookies,
+/// not enough to be a problem
+pub(crate) const DEFAULT_MAX_HEADER_LIST_SIZE: u32 = 64 * 1024;
+
+/// Frames written during a turn of the event loop go out in a single
+/// vectored write, unless they add up to more pieces than this (writev takes
+/// at most IOV_MAX = 1024 buffers)...
+const MAX_BATCH_PIECES: usize = 512;
 
-/// HTTP/2 server configuration
+/// ...or more bytes than this
+const MAX_BATCH_BYTES: usize = 256 * 1024;
+
+/// How many frames and events that are already there get handled in the
+/// same turn of the event loop, on top of the one it woke up for
+const MAX_TURN_ITEMS: usize = 32;
+
+/// HTTP/2 server configuration. [ServerConf::builder] and the presets check
+/// that settings hold together, struct literals don't: cf.
+/// [ServerConf::validate].
 pub struct ServerConf {
     pub max_streams: Option<u32>,
+
+    /// Whether to add a `date` header to responses that don't have one
+    pub date_header: bool,
+
+    /// The `server` header to add to responses that don't have one, if any
+    pub server_header: Option<Piece>,
+
+    /// Whether to advertise SETTINGS_ENABLE_CONNECT_PROTOCOL, allowing clients
+    /// to send extended CONNECT requests (with a `:protocol` pseudo-header),
+    /// e.g. for WebSockets over HTTP/2, cf. <https://www.rfc-editor.org/rfc/rfc8441>
+    pub enable_connect_protocol: bool,
+
+    /// If set, send a PING to the client when we haven't received any frame
+    /// from it for this long, to find out if it's still there.
+    pub keepalive_interval: Option<Duration>,
+
+    /// How long to wait for the client to acknowledge a keep-alive PING
+    /// before giving up on the connection.
+    pub keepalive_timeout: Duration,
+
+    /// If set, close connections that have had no open streams for this long
+    /// (with a GOAWAY frame).
+    pub idle_timeout: Option<Duration>,
+
+    /// If set, requests that take longer than this are cancelled, cf.
+    /// [crate::Responder::cancellation]. It's up to the handler to give up.
+    pub request_timeout: Option<Duration>,
+
+    /// Builds the response sent when a handler errors out or panics before
+    /// sending response headers. Either way, the connection survives.
+    pub error_hook: ErrorHook,
+
+    /// How much of each request body the client may send before hearing
+    /// back from us (SETTINGS_INITIAL_WINDOW_SIZE). Larger windows mean fewer
+    /// round-trips for large bodies, at the cost of more buffering. At most
+    /// 2^31-1.
+    pub initial_stream_window_size: u32,
+
+    /// Same as [ServerConf::initial_stream_window_size], but for all streams
+    /// of a connection combined. At most 2^31-1.
+    pub connection_window_size: u32,
+
+    /// The largest frame payload the client may send us
+    /// (SETTINGS_MAX_FRAME_SIZE), between 2^14 and 2^24-1. Since a frame
+    /// can't be larger than the stream's window, at most
+    /// [ServerConf::initial_stream_window_size].
+    pub max_frame_size: u32,
+
+    /// The largest header block the client may send us
+    /// (SETTINGS_MAX_HEADER_LIST_SIZE). A block whose HEADERS and
+    /// CONTINUATION frames add up to more than this, compressed, closes the
+    /// connection with ENHANCE_YOUR_CALM: that's how we don't buffer an
+    /// endless stream of CONTINUATION frames.
+    pub max_header_list_size: u32,
+
+    /// How many RST_STREAM frames the client may send within
+    /// [ServerConf::reset_window] before we close the connection with
+    /// ENHANCE_YOUR_CALM. Opening a stream and resetting it right away costs
+    /// a client next to nothing, but has us start on a request every time,
+    /// cf. the "rapid reset" attack (CVE-2023-44487). `None` means no limit.
+    pub max_resets: Option<u32>,
+
+    /// cf. [ServerConf::max_resets]
+    pub reset_window: Duration,
+
+    /// Whether GOAWAY frames we send over a protocol violation or a limit
+    /// carry a short reason as debug data, e.g. "connection was idle for too
+    /// long". It's meant to help whoever debugs the client, but does tell
+    /// peers a bit about how we work.
+    pub goaway_debug_data: bool,
+
+    /// Which requests that RFC 9113 tells us to reject are processed anyway.
+    /// By default, none are.
+    pub validation: Validation,
+
+    /// Whether requests that came in TLS early data, and aren't idempotent,
+    /// get a 425 Too Early instead of being handed to the driver, cf.
+    /// [crate::early_data]
+    pub reject_unsafe_early_data: bool,
+
+    /// Whether to keep serving a connection once the client stops sending,
+    /// i.e. half-closes it: requests it sent in full still get their
+    /// responses. We can't tell a half-close from the client going away
+    /// altogether, so this keeps handlers busy for nobody in that case.
+    /// Otherwise, every request in flight is cancelled with
+    /// [crate::CancelReason::ConnectionClosed].
+    pub serve_half_closed: bool,
 }
 
 impl Default for ServerConf {
     fn default() -> Self {
         Self {
             max_streams: Some(32),
+            date_header: true,
+            server_header: None,
+            enable_connect_protocol: false,
+            keepalive_interval: None,
+            keepalive_timeout: Duration::from_secs(20),
+            idle_timeout: None,
+            request_timeout: None,
+            error_hook: Rc::new(default_error_response),
+            initial_stream_window_size: DEFAULT_WINDOW_SIZE,
+            connection_window_size: DEFAULT_WINDOW_SIZE,
+            max_frame_size: MIN_MAX_FRAME_SIZE,
+            max_header_list_size: DEFAULT_MAX_HEADER_LIST_SIZE,
+            max_resets: Some(1000),
+            reset_window: Duration::from_secs(10),
+            goaway_debug_data: true,
+            validation: Validation::strict(),
+            reject_unsafe_early_data: true,
+            serve_half_closed: false,
         }
     }
 }
 
+impl ServerConf {
+    /// A configuration suited to gRPC, cf. <https://github.com/grpc/grpc/blob/master/doc/PROTOCOL-HTTP2.md>:
+    /// larger flow control windows, since gRPC messages can be megabytes
+    /// large, and room for many concurrent calls per connection.
+    ///
+    /// gRPC's other requirements hold with any configuration: the `te:
+    /// trailers` request header is accepted, trailers (which carry
+    /// `grpc-status`) are always sent, and bodies are passed through as-is:
+    /// loona never compresses or otherwise transforms them.
+    pub fn grpc() -> Self {
+        Self {
+            max_streams: Some(100),
+            initial_stream_window_size: 1024 * 1024,
+            connection_window_size: 8 * 1024 * 1024,
+            ..Default::default()
+        }
+    }
+
+    pub fn builder() -> ServerConfBuilder {
+        Self::default().into_builder()
+    }
+
+    /// Starts a builder from this configuration, e.g. to tweak a preset
+    pub fn into_builder(self) -> ServerConfBuilder {
+        ServerConfBuilder { conf: self }
+    }
+
+    /// For servers facing the internet: a cap on concurrent streams, and
+    /// connections that go quiet get pinged, then closed.
+    pub fn edge() -> Self {
+        Self {
+            max_streams: Some(100),
+            keepalive_interval: Some(Duration::from_secs(60)),
+            keepalive_timeout: Duration::from_secs(20),
+            idle_timeout: Some(Duration::from_secs(300)),
+            ..Default::default()
+        }
+    }
+
+    /// For servers behind a load balancer or another proxy, which keeps a
+    /// few long-lived connections open and sends many requests on each:
+    /// more concurrent streams, larger windows and frames, and lenient
+    /// validation, since requests were already vetted.
+    pub fn internal() -> Self {
+        Self {
+            max_streams: Some(1000),
+            initial_stream_window_size: 1024 * 1024,
+            connection_window_size: 16 * 1024 * 1024,
+            max_frame_size: 64 * 1024,
+            validation: Validation::lenient(),
+            ..Default::default()
+        }
+    }
+
+    /// For running conformance suites (like httpwg) against: the protocol's
+    /// default windows and frame size, which test cases make assumptions
+    /// about, a limit on concurrent streams for them to go over, and extended
+    /// CONNECT and half-closed connections, which they exercise.
+    pub fn conformance_test() -> Self {
+        Self {
+            max_streams: Some(32),
+            date_header: false,
+            enable_connect_protocol: true,
+            serve_half_closed: true,
+            ..Default::default()
+        }
+    }
+
+    /// Checks that settings hold together: windows and frame sizes are
+    /// within the protocol's limits, frames fit in stream windows, stream
+    /// windows fit in the connection window, and timeouts aren't zero, with
+    /// keep-alive PINGs answered before the next one is due.
+    pub fn validate(&self) -> Result<(), ConfError> {
+        let in_range = |setting, actual: u32, min: u32, max: u32| {
+            if (min..=max).contains(&actual) {
+                Ok(())
+            } else {
+                Err(ConfError::OutOfRange {
+                    setting,
+                    min: min as u64,
+                    max: max as u64,
+                    actual: actual as u64,
+                })
+            }
+        };
+        in_range(
+            "initial_stream_window_size",
+            self.initial_stream_window_size,
+            0,
+            MAX_FLOW_CONTROL_WINDOW,
+        )?;
+        // it starts at the default size, and can only grow
+        in_range(
+            "connection_window_size",
+            self.connection_window_size,
+            DEFAULT_WINDOW_SIZE,
+            MAX_FLOW_CONTROL_WINDOW,
+        )?;
+        in_range(
+            "max_frame_size",
+            self.max_frame_size,
+            MIN_MAX_FRAME_SIZE,
+            MAX_MAX_FRAME_SIZE,
+        )?;
+
+        let exceeds = |setting, actual: u32, other, limit: u32| {
+            if actual > limit {
+                Err(ConfError::Exceeds {
+                    setting,
+                    actual: actual as u64,
+                    other,
+                    limit: limit as u64,
+                })
+            } else {
+                Ok(())
+            }
+        };
+        // windows smaller than a frame are fine (that's the default), but
+        // can't be made use of
+        if self.max_frame_size > MIN_MAX_FRAME_SIZE {
+            exceeds(
+                "max_frame_size",
+                self.max_frame_size,
+                "initial_stream_window_size",
+                self.initial_stream_window_size,
+            )?;
+        }
+        exceeds(
+            "initial_stream_window_size",
+            self.initial_stream_window_size,
+            "connection_window_size",
+            self.connection_window_size,
+        )?;
+        if self.max_header_list_size == 0 {
+            return Err(ConfError::Zero {
+                setting: "max_header_list_size",
+            });
+        }
+
+        for (setting, timeout) in [
+            ("keepalive_interval", self.keepalive_interval),
+            ("keepalive_timeout", Some(self.keepalive_timeout)),
+            ("idle_timeout", self.idle_timeout),
+            ("request_timeout", self.request_timeout),
+            ("reset_window", self.max_resets.map(|_| self.reset_window)),
+        ] {
+            if timeout == Some(Duration::ZERO) {
+                return Err(ConfError::Zero { setting });
+            }
+        }
+        if let Some(interval) = self.keepalive_interval {
+            if self.keepalive_timeout > interval {
+                return Err(ConfError::Exceeds {
+                    setting: "keepalive_timeout",
+                    actual: self.keepalive_timeout.as_millis() as u64,
+                    other: "keepalive_interval",
+                    limit: interval.as_millis() as u64,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builds a [ServerConf], checked by [ServerConfBuilder::build]. Setters are
+/// documented on the matching [ServerConf] fields.
+pub struct ServerConfBuilder {
+    conf: ServerConf,
+}
+
+impl ServerConfBuilder {
+    pub fn max_streams(mut self, max_streams: Option<u32>) -> Self {
+        self.conf.max_streams = max_streams;
+        self
+    }
+
+    pub fn date_header(mut self, enabled: bool) -> Self {
+        self.conf.date_header = enabled;
+        self
+    }
+
+    pub fn server_header(mut self, value: Option<Piece>) -> Self {
+        self.conf.server_header = value;
+        self
+    }
+
+    pub fn enable_connect_protocol(mut self, enabled: bool) -> Self {
+        self.conf.enable_connect_protocol = enabled;
+        self
+    }
+
+    pub fn keepalive_interval(mut self, interval: Option<Duration>) -> Self {
+        self.conf.keepalive_interval = interval;
+        self
+    }
+
+    pub fn keepalive_timeout(mut self, timeout: Duration) -> Self {
+        self.conf.keepalive_timeout = timeout;
+        self
+    }
+
+    pub fn idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.conf.idle_timeout = timeout;
+        self
+    }
+
+    pub fn request_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.conf.request_timeout = timeout;
+        self
+    }
+
+    pub fn error_hook(mut self, hook: ErrorHook) -> Self {
+        self.conf.error_hook = hook;
+        self
+    }
+
+    pub fn initial_stream_window_size(mut self, size: u32) -> Self {
+        self.conf.initial_stream_window_size = size;
+        self
+    }
+
+    pub fn connection_window_size(mut self, size: u32) -> Self {
+        self.conf.connection_window_size = size;
+        self
+    }
+
+    pub fn max_frame_size(mut self, size: u32) -> Self {
+        self.conf.max_frame_size = size;
+        self
+    }
+
+    pub fn max_header_list_size(mut self, size: u32) -> Self {
+        self.conf.max_header_list_size = size;
+        self
+    }
+
+    pub fn max_resets(mut self, max_resets: Option<u32>) -> Self {
+        self.conf.max_resets = max_resets;
+        self
+    }
+
+    pub fn reset_window(mut self, window: Duration) -> Self {
+        self.conf.reset_window = window;
+        self
+    }
+
+    pub fn goaway_debug_data(mut self, enabled: bool) -> Self {
+        self.conf.goaway_debug_data = enabled;
+        self
+    }
+
+    pub fn validation(mut self, validation: Validation) -> Self {
+        self.conf.validation = validation;
+        self
+    }
+
+    pub fn reject_unsafe_early_data(mut self, enabled: bool) -> Self {
+        self.conf.reject_unsafe_early_data = enabled;
+        self
+    }
+
+    pub fn serve_half_closed(mut self, enabled: bool) -> Self {
+        self.conf.serve_half_closed = enabled;
+        self
+    }
+
+    /// Errors out if settings don't hold together, cf. [ServerConf::validate]
+    pub fn build(self) -> Result<ServerConf, ConfError> {
+        self.conf.validate()?;
+        Ok(self.conf)
+    }
+}
+
 pub async fn serve<OurDriver, OurReadOwned, OurWriteOwned>(
+    transport: (OurReadOwned, OurWriteOwned),
+    conf: Rc<ServerConf>,
+    client_buf: RollMut,
+    driver: Rc<OurDriver>,
+) -> Result<(), ServeError<OurDriver::Error>>
+where
+    OurDriver: ServerDriver<H2Encoder> + 'static,
+    OurReadOwned: ReadOwned,
+    OurWriteOwned: WriteOwned,
+{
+    serve_with_early_data(transport, conf, client_buf, 0, driver).await
+}
+
+/// Like [serve], but once `shutdown` completes, shuts the connection down
+/// gracefully: the client gets a GOAWAY frame, streams it opens after that
+/// are refused, and the connection closes once the streams in flight are
+/// done, cf. <https://httpwg.org/specs/rfc9113.html#GOAWAY>
+pub async fn serve_until<OurDriver, OurReadOwned, OurWriteOwned>(
+    transport: (OurReadOwned, OurWriteOwned),
+    conf: Rc<ServerConf>,
+    client_buf: RollMut,
+    driver: Rc<OurDriver>,
+    shutdown: impl Future<Output = ()> + 'static,
+) -> Result<(), ServeError<OurDriver::Error>>
+where
+    OurDriver: ServerDriver<H2Encoder> + 'static,
+    OurReadOwned: ReadOwned,
+    OurWriteOwned: WriteOwned,
+{
+    serve_accepted(
+        transport,
+        conf,
+        client_buf,
+        0,
+        None,
+        driver,
+        None,
+        Some(Box::pin(shutdown)),
+        MemoryBudget::unlimited(),
+    )
+    .await
+}
+
+/// Like [serve], for a connection whose first `early_data_len` bytes came in
+/// TLS 1.3 early data, cf. [crate::ConnectionInfo::early_data_len]. Those
+/// bytes count whether they're in `client_buf` or still to be read.
+pub async fn serve_with_early_data<OurDriver, OurReadOwned, OurWriteOwned>(
+    transport: (OurReadOwned, OurWriteOwned),
+    conf: Rc<ServerConf>,
+    client_buf: RollMut,
+    early_data_len: usize,
+    driver: Rc<OurDriver>,
+) -> Result<(), ServeError<OurDriver::Error>>
+where
+    OurDriver: ServerDriver<H2Encoder> + 'static,
+    OurReadOwned: ReadOwned,
+    OurWriteOwned: WriteOwned,
+{
+    serve_accepted(
+        transport,
+        conf,
+        client_buf,
+        early_data_len,
+        None,
+        driver,
+        None,
+        None,
+        MemoryBudget::unlimited(),
+    )
+    .await
+}
+
+/// Like [serve], for connections accepted by a [crate::server::Server]:
+/// hands `connection` to the driver with every request, answers requests for an [H2ConnectionDump] sent over `dump_rx` as it goes,
+/// cf. [crate::server::ServerHandle::dump], shuts down gracefully once
+/// `shutdown` completes, cf. [serve_until], and sheds streams that would go
+/// over `memory`, cf. [crate::memory]
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn serve_accepted<OurDriver, OurReadOwned, OurWriteOwned>(
     (transport_r, transport_w): (OurReadOwned, OurWriteOwned),
     conf: Rc<ServerConf>,
     client_buf: RollMut,
+    early_data_len: usize,
+    connection: Option<Rc<ConnectionInfo>>,
     driver: Rc<OurDriver>,
+    dump_rx: Option<mpsc::UnboundedReceiver<H2DumpRequest>>,
+    shutdown: Option<LocalBoxFuture<'static, ()>>,
+    memory: MemoryBudget,
 ) -> Result<(), ServeError<OurDriver::Error>>
 where
     OurDriver: ServerDriver<H2Encoder> + 'static,
     OurReadOwned: ReadOwned,
     OurWriteOwned: WriteOwned,
 {
-    let mut state = ConnState::default();
+    let mut state = ConnState {
+        memory,
+        ..Default::default()
+    };
     state.self_settings.max_concurrent_streams = conf.max_streams;
+    state.self_settings.enable_connect_protocol = conf.enable_connect_protocol;
+    state.self_settings.initial_window_size =
+        conf.initial_stream_window_size.min(MAX_FLOW_CONTROL_WINDOW);
+    state.self_settings.max_frame_size = conf
+        .max_frame_size
+        .clamp(MIN_MAX_FRAME_SIZE, MAX_MAX_FRAME_SIZE);
+    state.self_settings.max_header_list_size = conf.max_header_list_size;
 
     let mut cx =
-        ServerContext::new(driver.clone(), state, transport_w).map_err(ServeError::Alloc)?;
+        ServerContext::new(driver.clone(), conf, state, transport_w).map_err(ServeError::Alloc)?;
+    cx.dump_rx = dump_rx;
+    cx.shutdown = shutdown;
+    cx.early_data = EarlyData::new(early_data_len, client_buf.len());
+    cx.connection = connection;
     cx.work(client_buf, transport_r).await?;
 
     debug!("finished serving");
@@ -84,6 +606,7 @@ where
     OurWriter: WriteOwned,
 {
     driver: Rc<OurDriver>,
+    conf: Rc<ServerConf>,
     state: ConnState,
 
     hpack_dec: loona_hpack::Decoder<'static>,
@@ -97,8 +620,29 @@ where
     /// allow direct access from context methods
     transport_w: OurWriter,
 
+    /// frames that are written, but not flushed to `transport_w` yet, cf.
+    /// [ServerContext::flush_writes]
+    out_batch: PieceList,
+
     ev_tx: mpsc::Sender<H2Event>,
     ev_rx: mpsc::Receiver<H2Event>,
+
+    /// where requests for an [H2ConnectionDump] come from, if anyone's
+    /// interested
+    dump_rx: Option<mpsc::UnboundedReceiver<H2DumpRequest>>,
+
+    /// which streams had their request headers come in TLS early data
+    early_data: EarlyData,
+
+    /// handed to the driver with every request, cf. [Request::connection]
+    connection: Option<Rc<ConnectionInfo>>,
+
+    /// completes when it's time to shut down gracefully, cf. [serve_until]
+    shutdown: Option<LocalBoxFuture<'static, ()>>,
+
+    /// the last stream ID of the GOAWAY frame we sent when we started
+    /// shutting down gracefully, if we did: we refuse streams above it.
+    goaway_sent: Option<StreamId>,
 }
 
 impl<OurDriver, OurWriteOwned> ServerContext<OurDriver, OurWriteOwned>
@@ -108,6 +652,7 @@ where
 {
     pub(crate) fn new(
         driver: Rc<OurDriver>,
+        conf: Rc<ServerConf>,
         state: ConnState,
         transport_w: OurWriteOwned,
     ) -> Result<Self, buffet::bufpool::Error> {
@@ -117,11 +662,15 @@ where
 
         let hpack_enc = loona_hpack::Encoder::new();
 
-        let h2_server_chan_size: usize = std::env::var("H2_SERVER_CHAN_SIZE").unwrap_or("32".to_string()).parse().unwrap();
+        let h2_server_chan_size: usize = std::env::var("H2_SERVER_CHAN_SIZE")
+            .unwrap_or("32".to_string())
+            .parse()
+            .unwrap();
         let (ev_tx, ev_rx) = tokio::sync::mpsc::channel::<H2Event>(h2_server_chan_size);
 
         Ok(Self {
             driver,
+            conf,
             ev_tx,
             ev_rx,
             state,
@@ -130,6 +679,12 @@ where
             out_scratch: RollMut::alloc()?,
             goaway_recv: false,
             transport_w,
+            out_batch: Default::default(),
+            dump_rx: None,
+            early_data: EarlyData::none(),
+            connection: None,
+            shutdown: None,
+            goaway_sent: None,
         })
     }
 
@@ -137,8 +692,10 @@ where
     pub(crate) async fn work(
         &mut self,
         mut client_buf: RollMut,
-        mut transport_r: impl ReadOwned,
+        transport_r: impl ReadOwned,
     ) -> Result<ServeOutcome, ServeError<OurDriver::Error>> {
+        let mut transport_r = self.early_data.wrap(transport_r);
+
         // first read the preface
         {
             (client_buf, _) = match read_and_parse(
@@ -163,7 +720,7 @@ where
             debug!("Sending initial settings");
             let setting_payload = {
                 let s = &self.state.self_settings;
-                SettingPairs(&[
+                let mut pairs: SmallVec<[(Setting, u32); 7]> = smallvec![
                     (Setting::EnablePush, 0),
                     (Setting::HeaderTableSize, s.header_table_size),
                     (Setting::InitialWindowSize, s.initial_window_size),
@@ -173,9 +730,13 @@ where
                     ),
                     (Setting::MaxFrameSize, s.max_frame_size),
                     (Setting::MaxHeaderListSize, s.max_header_list_size),
-                ])
-                .into_piece(&mut self.out_scratch)
-                .map_err(ServeError::DownstreamWrite)?
+                ];
+                if s.enable_connect_protocol {
+                    pairs.push((Setting::EnableConnectProtocol, 1));
+                }
+                SettingPairs(&pairs[..])
+                    .into_piece(&mut self.out_scratch)
+                    .map_err(ServeError::DownstreamWrite)?
             };
             let frame = Frame::new(
                 FrameType::Settings(Default::default()),
@@ -183,9 +744,23 @@ where
             );
             self.write_frame(frame, PieceList::single(setting_payload))
                 .await?;
+
+            // the connection window can only be grown with a WINDOW_UPDATE
+            let conn_window = self
+                .conf
+                .connection_window_size
+                .min(MAX_FLOW_CONTROL_WINDOW) as i64;
+            if conn_window > self.state.incoming_capacity {
+                let increment = conn_window - self.state.incoming_capacity;
+                self.state.incoming_capacity = conn_window;
+                self.send_window_update(StreamId::CONNECTION, increment as u32)
+                    .await?;
+            }
+            self.flush_writes().await?;
         }
 
         let mut goaway_err: Option<H2ConnectionError> = None;
+        let mut read_err: Option<ReadAndParseError> = None;
 
         {
             let (tx, rx) = mpsc::channel::<(Frame, Roll)>(32);
@@ -194,11 +769,12 @@ where
             // FIXME: the process_task should update this
             let max_frame_size = Rc::new(AtomicU32::new(self.state.self_settings.max_frame_size));
 
-            let mut deframe_task = std::pin::pin!(Self::deframe_loop(
+            let mut deframe_task = std::pin::pin!(deframe_loop(
                 client_buf,
                 transport_r,
                 tx,
-                max_frame_size
+                max_frame_size,
+                self.early_data.clone(),
             ));
             let mut process_task = std::pin::pin!(self.process_loop(rx));
 
@@ -222,7 +798,7 @@ where
 
                                 debug!(%should_ignore_err, "deciding whether or not to propagate deframer error");
                                 if !should_ignore_err {
-                                    return Err(H2ConnectionError::ReadAndParse(e).into());
+                                    read_err = Some(e);
                                 }
                             },
                             e => {
@@ -232,11 +808,24 @@ where
                         }
                     }
 
-                    if let Err(e) = (&mut process_task).await {
-                        // what about the GOAWAY?
+                    // on a read error, don't wait on the process task, but
+                    // still send what it batched so far, below
+                    if read_err.is_none() {
+                        match (&mut process_task).await {
+                            Ok(()) => {}
+                            // the peer hung up: if it closed the connection
+                            // rather than half-closing it, it won't read the
+                            // responses we still had to send, and that's fine
+                            Err(H2ConnectionError::WriteError(e)) => {
+                                debug!("could not finish responding after the peer hung up: {e}");
+                            }
+                            Err(e) => {
+                                // what about the GOAWAY?
 
-                        debug!("h2 process task finished with error: {e}");
-                        return Err(e.into());
+                                debug!("h2 process task finished with error: {e}");
+                                return Err(e.into());
+                            }
+                        }
                     }
                 }
                 res = &mut process_task => {
@@ -249,162 +838,76 @@ where
             }
         }
 
+        if let Some(e) = read_err {
+            // the frames from the last turn are complete, the peer may still
+            // want them even though we can't read from it anymore: that
+            // flush is best-effort, the read error is what we report
+            if let Err(write_err) = self.flush_writes().await {
+                debug!("could not flush writes after a read error: {write_err}");
+            }
+            return Err(H2ConnectionError::ReadAndParse(e).into());
+        }
+
         if let Some(err) = goaway_err {
             let error_code = err.as_known_error_code();
             debug!("Connection error: {err} ({err:?}) (code {error_code:?})");
 
-            // TODO: don't heap-allocate here
-            let additional_debug_data = format!("{err}").into_bytes();
+            let additional_debug_data = if self.conf.goaway_debug_data {
+                err.goaway_debug_data()
+            } else {
+                Piece::empty()
+            };
 
             // TODO: figure out graceful shutdown: this would involve sending a goaway
             // before this point, and processing all the connections we've accepted
-            // FIXME: we have a GoAway encoder, why are we doing this manually
-            debug!(last_stream_id = %self.state.last_stream_id, ?error_code, "Sending GoAway");
-            let payload =
-                self.out_scratch
-                    .put_to_roll(8 + additional_debug_data.len(), |mut slice| {
-                        slice.write_u32::<BigEndian>(self.state.last_stream_id.0)?;
-                        slice.write_u32::<BigEndian>(error_code.repr())?;
-                        slice.write_all(additional_debug_data.as_slice())?;
-
-                        Ok(())
-                    })?;
+            debug!(
+                last_stream_id = %self.state.last_stream_id,
+                ?error_code,
+                debug_data = %GoAwayDebugData(&additional_debug_data),
+                "Sending GoAway"
+            );
+            let payload = GoAway {
+                last_stream_id: self.state.last_stream_id,
+                error_code: error_code.into(),
+                additional_debug_data,
+            }
+            .into_piece(&mut self.out_scratch)?;
 
             let frame = Frame::new(FrameType::GoAway, StreamId::CONNECTION);
             self.write_frame(frame, PieceList::single(payload))
                 .await
                 .map_err(ServeError::H2ConnectionError)?;
         }
-
-        Ok(ServeOutcome::SuccessfulHttp2GracefulShutdown)
-    }
-
-    async fn deframe_loop(
-        mut client_buf: RollMut,
-        mut transport_r: impl ReadOwned,
-        tx: mpsc::Sender<(Frame, Roll)>,
-        max_frame_size: Rc<AtomicU32>,
-    ) -> Result<(), H2ConnectionError> {
-        'read_frames: loop {
-            const MAX_FRAME_HEADER_SIZE: usize = 128;
-            let frame;
-            trace!("Reading frame... Buffer length: {}", client_buf.len());
-            let frame_res = read_and_parse(
-                "Http2Frame",
-                Frame::parse,
-                &mut transport_r,
-                client_buf,
-                MAX_FRAME_HEADER_SIZE,
-            )
-            .await;
-
-            let maybe_frame = match frame_res {
-                Ok(inner) => inner,
-                Err(e) => return Err(H2ConnectionError::ReadAndParse(e)),
-            };
-            (client_buf, frame) = match maybe_frame {
-                Some((client_buf, frame)) => (client_buf, frame),
-                None => {
-                    debug!("Peer hung up");
-                    break 'read_frames;
-                }
-            };
-            trace!(
-                "Reading frame... done! New buffer length: {}",
-                client_buf.len()
-            );
-            debug!(?frame, "<");
-
-            let max_frame_size = max_frame_size.load(Ordering::Relaxed);
-            if frame.len > max_frame_size {
-                return Err(H2ConnectionError::FrameTooLarge {
-                    frame_type: frame.frame_type,
-                    frame_size: frame.len,
-                    max_frame_size,
-                });
-            }
-
-            trace!(
-                "Reading payload of size {}... Buffer length: {}",
-                frame.len,
-                client_buf.len()
-            );
-            let mut payload;
-            (client_buf, payload) = match read_and_parse(
-                "FramePayload",
-                nom::bytes::streaming::take(frame.len as usize),
-                &mut transport_r,
-                client_buf,
-                frame.len as usize,
-            )
+        self.flush_writes()
             .await
-            .map_err(H2ConnectionError::ReadAndParse)?
-            {
-                Some((client_buf, payload)) => (client_buf, payload),
-                None => {
-                    return Err(H2ConnectionError::IncompleteFrame {
-                        frame_type: frame.frame_type,
-                        frame_size: frame.len,
-                    })
-                }
-            };
-            trace!(
-                "Reading payload... done! New buffer length: {}",
-                client_buf.len()
-            );
-
-            let has_padding = match frame.frame_type {
-                FrameType::Data(flags) => flags.contains(DataFlags::Padded),
-                FrameType::Headers(flags) => flags.contains(HeadersFlags::Padded),
-                _ => false,
-            };
-
-            if has_padding {
-                if payload.is_empty() {
-                    return Err(H2ConnectionError::PaddedFrameEmpty {
-                        frame_type: frame.frame_type,
-                    });
-                }
-
-                let padding_length_roll;
-                (padding_length_roll, payload) = payload.split_at(1);
-                let padding_length = padding_length_roll[0] as usize;
-                if payload.len() < padding_length {
-                    return Err(H2ConnectionError::PaddedFrameTooShort {
-                        frame_type: frame.frame_type,
-                        padding_length,
-                        frame_size: frame.len,
-                    });
-                }
+            .map_err(ServeError::H2ConnectionError)?;
 
-                // padding is on the end of the payload
-                let at = payload.len() - padding_length;
-                (payload, _) = payload.split_at(at);
-            }
-
-            if tx.send((frame, payload)).await.is_err() {
-                debug!("h2 deframer: receiver dropped, closing connection");
-                return Ok(());
-            }
-        }
-
-        Ok(())
+        Ok(ServeOutcome::SuccessfulHttp2GracefulShutdown)
     }
 
     async fn process_loop(
         &mut self,
         mut rx: mpsc::Receiver<(Frame, Roll)>,
     ) -> Result<(), H2ConnectionError> {
+        let mut peer_hung_up = false;
+
         loop {
+            let timer_deadline = self.next_timer_deadline();
+
             tokio::select! {
                 biased;
 
-                maybe_frame = rx.recv() => {
+                maybe_frame = rx.recv(), if !peer_hung_up => {
                     if let Some((frame, payload)) = maybe_frame {
+                        self.state.last_frame_received_at = Instant::now();
                         self.process_frame(frame, payload, &mut rx).await?;
                     } else {
                         debug!("h2 process task: peer hung up");
-                        break;
+                        if !self.conf.serve_half_closed {
+                            break;
+                        }
+                        peer_hung_up = true;
+                        self.on_peer_hung_up().await;
                     }
                 }
 
@@ -418,177 +921,240 @@ where
                 _ = self.state.send_data_maybe.notified() => {
                     self.send_data_maybe().await?;
                 }
-            }
-        }
-
-        Ok(())
-    }
-
-    async fn send_data_maybe(&mut self) -> Result<(), H2ConnectionError> {
-        let mut not_pending: HashSet<StreamId> = Default::default();
-
-        // this vec exists for borrow-checker reasons: we can't
-        // borrow self mutably twice in 'each_stream
-        // TODO: merge those frames! do a single writev_all call!
-        let mut frames: Vec<(Frame, PieceList)> = vec![];
 
-        let max_fram = self.state.peer_settings.max_frame_size as usize;
+                _ = sleep_until_maybe(timer_deadline) => {
+                    self.on_timer().await?;
+                }
 
-        let streams_with_pending_data: HashSet<_> = self
-            .state
-            .streams_with_pending_data
-            .iter()
-            .copied()
-            .collect();
+                Some(reply) = recv_maybe(&mut self.dump_rx) => {
+                    _ = reply.send(self.dump());
+                }
 
-        'each_stream: for id in streams_with_pending_data {
-            if self.state.outgoing_capacity <= 0 {
-                // that's all we can do
-                break 'each_stream;
+                () = complete_maybe(&mut self.shutdown) => {
+                    self.shutdown = None;
+                    self.start_graceful_shutdown().await?;
+                }
             }
 
-            let outgoing = self
+            // handle whatever else is already there, so that everything this
+            // turn writes (SETTINGS acks, WINDOW_UPDATEs, HEADERS, DATA...)
+            // goes out in a single vectored write
+            for _ in 0..MAX_TURN_ITEMS {
+                if let Ok((frame, payload)) = rx.try_recv() {
+                    self.state.last_frame_received_at = Instant::now();
+                    self.process_frame(frame, payload, &mut rx).await?;
+                } else if let Ok(ev) = self.ev_rx.try_recv() {
+                    self.handle_event(ev).await?;
+                } else {
+                    break;
+                }
+            }
+            if self
                 .state
-                .streams
-                .get_mut(&id)
-                .and_then(|ss| ss.outgoing_mut())
-                .expect("stream should not be in streams_with_pending_data if it's already closed / not in an outgoing state");
-
-            debug!(conn_cap = %self.state.outgoing_capacity, strm_cap = %outgoing.capacity, %max_fram, "ready to write");
-
-            if outgoing.headers.has_more_to_write() {
-                debug!("writing headers...");
-
-                if matches!(&outgoing.headers, HeadersOutgoing::WaitingForHeaders) {
-                    debug!("waiting for headers...");
+                .send_data_maybe
+                .notified()
+                .now_or_never()
+                .is_some()
+            {
+                self.send_data_maybe().await?;
+            }
+            self.flush_writes().await?;
 
-                    // shouldn't be pending then should it?
-                    not_pending.insert(id);
-                    continue 'each_stream;
+            if self.state.streams.is_empty() {
+                if self.goaway_sent.is_some() {
+                    debug!("no streams left, done shutting down gracefully");
+                    break;
                 }
+                if peer_hung_up {
+                    debug!("no streams left, and the peer hung up");
+                    break;
+                }
+                self.state.idle_since.get_or_insert_with(Instant::now);
+            } else {
+                self.state.idle_since = None;
+            }
+        }
 
-                'queue_header_frames: loop {
-                    debug!("writing headers...");
-
-                    let is_continuation =
-                        matches!(&outgoing.headers, HeadersOutgoing::WroteSome(_));
-                    let piece = outgoing.headers.take_piece();
-                    let piece_len = piece.len();
-
-                    if piece_len > max_fram {
-                        let write_size = max_fram;
-                        let (written, requeued) = piece.split_at(write_size);
-                        debug!(%write_size, requeued_len = %requeued.len(), "splitting headers");
-                        let frame_type = if is_continuation {
-                            FrameType::Continuation(Default::default())
-                        } else {
-                            FrameType::Headers(Default::default())
-                        };
-                        outgoing.headers = HeadersOutgoing::WroteSome(requeued);
-
-                        let frame = Frame::new(frame_type, id);
-                        frames.push((frame, PieceList::single(written)));
-                    } else {
-                        let frame_type = if is_continuation {
-                            FrameType::Continuation(
-                                BitFlags::<ContinuationFlags>::default()
-                                    | ContinuationFlags::EndHeaders,
-                            )
-                        } else {
-                            FrameType::Headers(
-                                BitFlags::<HeadersFlags>::default() | HeadersFlags::EndHeaders,
-                            )
-                        };
+        Ok(())
+    }
 
-                        let frame = Frame::new(frame_type, id);
-                        frames.push((frame, PieceList::single(piece)));
+    /// The peer half-closed the connection (or hung up, cf.
+    /// [ServerConf::serve_half_closed]): we can still respond to requests it
+    /// sent in full, but streams still waiting on request data are done for.
+    async fn on_peer_hung_up(&mut self) {
+        let stuck: Vec<StreamId> = self
+            .state
+            .streams
+            .iter()
+            .filter(|(_, ss)| {
+                matches!(
+                    ss,
+                    StreamState::Open { .. } | StreamState::HalfClosedLocal { .. }
+                )
+            })
+            .map(|(id, _)| *id)
+            .collect();
 
-                        break 'queue_header_frames;
-                    }
+        for stream_id in stuck {
+            self.state.streams_with_pending_data.remove(&stream_id);
+            match self.state.streams.remove(&stream_id) {
+                Some(StreamState::Open {
+                    mut incoming,
+                    outgoing,
+                }) => {
+                    outgoing.cancellation.cancel(CancelReason::ConnectionClosed);
+                    incoming.send_error(StreamIncomingError::PeerHungUp).await;
+                }
+                Some(StreamState::HalfClosedLocal { mut incoming, .. }) => {
+                    incoming.send_error(StreamIncomingError::PeerHungUp).await;
                 }
+                _ => unreachable!(),
             }
+            debug!(%stream_id, "dropped stream still waiting on request data");
+        }
+    }
 
-            let capacity = self.state.outgoing_capacity.min(outgoing.capacity) as usize;
-            // bytes written this turn, possibly over multiple frames
-            let mut total_bytes_written = 0;
-
-            if outgoing.body.has_more_to_write() {
-                'queue_body_frames: while total_bytes_written < capacity {
-                    // send as much body data as we can, respecting max frame size and
-                    // connection / stream capacity
-                    let mut plist = PieceList::default();
-                    let mut frame_len = 0;
-
-                    'build_frame: loop {
-                        let piece = match outgoing.body.pop_front() {
-                            None => break 'build_frame,
-                            Some(piece) => piece,
-                        };
-
-                        // do we need to split the piece because we don't have
-                        // enough capacity left / we hit the max frame size?
-                        let piece_len = piece.len();
-                        debug!(%piece_len, "popped a piece");
+    /// Tells the client we won't process any stream it opens from now on.
+    /// The connection closes once the streams in flight are done, cf.
+    /// [ServerContext::process_loop].
+    async fn start_graceful_shutdown(&mut self) -> Result<(), H2ConnectionError> {
+        let last_stream_id = self.state.last_stream_id;
+        debug!(%last_stream_id, "shutting down gracefully, sending GoAway");
+
+        let payload = GoAway {
+            last_stream_id,
+            error_code: KnownErrorCode::NoError.into(),
+            additional_debug_data: Piece::empty(),
+        }
+        .into_piece(&mut self.out_scratch)
+        .map_err(H2ConnectionError::WriteError)?;
 
-                        let fram_size_if_full_piece = frame_len + piece_len;
+        let frame = Frame::new(FrameType::GoAway, StreamId::CONNECTION);
+        self.write_frame(frame, PieceList::single(payload)).await?;
+        self.goaway_sent = Some(last_stream_id);
 
-                        let cap_left = capacity - total_bytes_written;
-                        let max_this_fram = max_fram.min(cap_left);
+        Ok(())
+    }
 
-                        if fram_size_if_full_piece > max_this_fram {
-                            // we can't fit this piece in the current frame, so
-                            // we have to split it
-                            let write_size = max_this_fram - frame_len;
-                            let (written, requeued) = piece.split_at(write_size);
-                            frame_len += write_size;
-                            debug!(written_len = %written.len(), requeued_len = %requeued.len(), "splitting piece");
+    fn dump(&self) -> H2ConnectionDump {
+        let mut streams: Vec<_> = self
+            .state
+            .streams
+            .iter()
+            .filter_map(|(id, stream)| {
+                let (state, incoming, outgoing) = match stream {
+                    StreamState::Open { incoming, outgoing } => {
+                        (H2StreamState::Open, Some(incoming), Some(outgoing))
+                    }
+                    StreamState::HalfClosedRemote { outgoing } => {
+                        (H2StreamState::HalfClosedRemote, None, Some(outgoing))
+                    }
+                    StreamState::HalfClosedLocal { incoming } => {
+                        (H2StreamState::HalfClosedLocal, Some(incoming), None)
+                    }
+                    StreamState::Transition => return None,
+                };
+                let queued_bytes = outgoing.map_or(0, |o| o.body.queued_len() as u64);
+                Some(H2StreamDump {
+                    id: id.0,
+                    state,
+                    send_window: outgoing.map(|o| o.capacity),
+                    recv_window: incoming.map(|i| i.capacity),
+                    received_bytes: incoming.map(|i| i.total_received),
+                    response: outgoing.map(|o| match (&o.headers, &o.body) {
+                        (HeadersOutgoing::WaitingForHeaders, _) => {
+                            ResponseProgress::WaitingForHeaders
+                        }
+                        (HeadersOutgoing::WroteNone(_), _) => ResponseProgress::HeadersQueued,
+                        (_, BodyOutgoing::StillReceiving(_)) => ResponseProgress::WritingBody,
+                        (_, _) => ResponseProgress::FlushingBody,
+                    }),
+                    queued_bytes,
+                })
+            })
+            .collect();
+        streams.sort_by_key(|s| s.id);
+
+        H2ConnectionDump {
+            last_stream_id: self.state.last_stream_id.0,
+            send_window: self.state.outgoing_capacity,
+            recv_window: self.state.incoming_capacity,
+            goaway_received: self.goaway_recv,
+            queued_bytes: streams.iter().map(|s| s.queued_bytes).sum(),
+            idle_for: self.state.idle_since.map(|since| since.elapsed()),
+            streams,
+            hpack_decoder: HpackTableDump::of_decoder(&self.hpack_dec),
+            hpack_encoder: HpackTableDump::of_encoder(&self.hpack_enc),
+        }
+    }
 
-                            plist.push_back(written);
-                            outgoing.body.push_front(requeued);
+    /// When we next need to check on the keep-alive PINGs or the idle timeout,
+    /// if ever.
+    fn next_timer_deadline(&self) -> Option<Instant> {
+        let keepalive = match self.state.ping_in_flight {
+            Some((_, sent_at)) => Some(sent_at + self.conf.keepalive_timeout),
+            None => self
+                .conf
+                .keepalive_interval
+                .map(|interval| self.state.last_frame_received_at + interval),
+        };
+        let idle = self
+            .conf
+            .idle_timeout
+            .zip(self.state.idle_since)
+            .map(|(timeout, idle_since)| idle_since + timeout);
+
+        match (keepalive, idle) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
 
-                            break 'build_frame;
-                        } else {
-                            // we can write the full piece
-                            let write_size = piece_len;
-                            frame_len += write_size;
+    async fn on_timer(&mut self) -> Result<(), H2ConnectionError> {
+        let now = Instant::now();
 
-                            plist.push_back(piece);
-                        }
-                    }
+        if let Some((_, sent_at)) = self.state.ping_in_flight {
+            if now >= sent_at + self.conf.keepalive_timeout {
+                debug!("keep-alive ping was not acknowledged in time, closing connection");
+                return Err(H2ConnectionError::KeepaliveTimeout);
+            }
+        }
 
-                    let mut flags: BitFlags<DataFlags> = Default::default();
-                    if outgoing.body.might_receive_more() {
-                        if frame_len == 0 {
-                            // the only time we want to send a zero-length frame
-                            // is if we have to send END_STREAM separately from
-                            // the last chunk.
-                            break 'queue_body_frames;
-                        }
-                    } else {
-                        flags |= DataFlags::EndStream;
-                    }
+        if let (Some(timeout), Some(idle_since)) = (self.conf.idle_timeout, self.state.idle_since) {
+            if now >= idle_since + timeout {
+                debug!("connection has been idle for too long, closing it");
+                return Err(H2ConnectionError::IdleTimeout);
+            }
+        }
 
-                    let frame = Frame::new(FrameType::Data(flags), id);
-                    debug!(?frame, %frame_len, "queuing");
-                    frames.push((frame, plist));
-                    total_bytes_written += frame_len;
+        if let Some(interval) = self.conf.keepalive_interval {
+            if self.state.ping_in_flight.is_none()
+                && now >= self.state.last_frame_received_at + interval
+            {
+                self.state.pings_sent += 1;
+                let payload = self.state.pings_sent.to_be_bytes();
+                debug!(?payload, "sending keep-alive ping");
 
-                    if flags.contains(DataFlags::EndStream) {
-                        break 'queue_body_frames;
-                    }
-                }
+                self.state.ping_in_flight = Some((payload, now));
+                let frame = Frame::new(FrameType::Ping(Default::default()), StreamId::CONNECTION)
+                    .with_len(payload.len() as u32);
+                self.write_frame(frame, PieceList::single(payload.to_vec()))
+                    .await?;
             }
         }
 
+        Ok(())
+    }
+
+    async fn send_data_maybe(&mut self) -> Result<(), H2ConnectionError> {
+        let frames = self
+            .state
+            .queue_frames(&mut self.hpack_enc, &mut self.out_scratch)
+            .map_err(H2ConnectionError::WriteError)?;
         for (frame, plist) in frames {
             debug!(?frame, plist_len = %plist.len(), "writing");
             self.write_frame(frame, plist).await?;
         }
-
-        for id in not_pending {
-            self.state.streams_with_pending_data.remove(&id);
-        }
-
         Ok(())
     }
 
@@ -596,7 +1162,7 @@ where
         trace!(?ev, "handling event");
 
         match ev.payload {
-            H2EventPayload::Headers(res) => {
+            H2EventPayload::Headers(mut res) => {
                 let outgoing = match self
                     .state
                     .streams
@@ -618,30 +1184,16 @@ where
                     unreachable!("got headers too late")
                 }
 
-                // TODO: don't allocate so much for headers. all `encode_into`
-                // wants is an `IntoIter`, we can definitely have a custom iterator
-                // that operates on all this instead of using a `Vec`.
-
-                // TODO: enforce max header size
-                let mut headers: Vec<(&[u8], &[u8])> = vec![];
-                // TODO: prevent overwriting pseudo-headers, especially :status?
-                headers.push((b":status", res.status.as_str().as_bytes()));
-
-                for (name, value) in res.headers.iter() {
-                    if name == http::header::TRANSFER_ENCODING {
-                        // do not set transfer-encoding: chunked when doing HTTP/2
-                        continue;
-                    }
-                    headers.push((name.as_str().as_bytes(), value));
+                if res.status.is_informational() {
+                    outgoing.interim.push(res);
+                } else {
+                    crate::date::add_response_headers(
+                        &mut res.headers,
+                        self.conf.date_header,
+                        self.conf.server_header.as_ref(),
+                    );
+                    outgoing.headers = HeadersOutgoing::WroteNone(Box::new(res));
                 }
-
-                assert_eq!(self.out_scratch.len(), 0);
-                self.hpack_enc
-                    .encode_into(headers, &mut self.out_scratch)
-                    .map_err(H2ConnectionError::WriteError)?;
-                let payload = self.out_scratch.take_all();
-
-                outgoing.headers = HeadersOutgoing::WroteNone(payload.into());
                 self.state.streams_with_pending_data.insert(ev.stream_id);
                 if self.state.outgoing_capacity > 0 && outgoing.capacity > 0 {
                     // worth revisiting then!
@@ -649,60 +1201,21 @@ where
                 }
             }
             H2EventPayload::BodyChunk(chunk) => {
-                let outgoing = match self
-                    .state
-                    .streams
-                    .get_mut(&ev.stream_id)
-                    .and_then(|s| s.outgoing_mut())
-                {
-                    None => {
-                        // ignore the event then, but at this point we should
-                        // tell the sender to stop sending chunks, which is not
-                        // possible if they all share the same ev_tx
-                        // TODO: make it possible to propagate errors to the sender
-                        return Ok(());
-                    }
-                    Some(outgoing) => outgoing,
-                };
-
-                // FIXME: this isn't great, because, due to biased polling, body pieces can pile
-                // up. when we've collected enough pieces for max frame size, we
-                // should really send them.
-                outgoing.body.push_back(chunk);
-
-                self.state.streams_with_pending_data.insert(ev.stream_id);
-                if self.state.outgoing_capacity > 0 && outgoing.capacity > 0 {
-                    // worth revisiting then!
-                    self.state.send_data_maybe.notify_one();
+                if let Err(e) = self.state.push_body_chunk(ev.stream_id, chunk) {
+                    self.rst(ev.stream_id, e).await?;
                 }
             }
-            H2EventPayload::BodyEnd => {
-                let outgoing = match self
+            H2EventPayload::BodyEnd => self.state.end_body(ev.stream_id, None),
+            H2EventPayload::Trailers(trailers) => self.state.end_body(ev.stream_id, Some(trailers)),
+            H2EventPayload::Reset => {
+                if self
                     .state
                     .streams
                     .get_mut(&ev.stream_id)
                     .and_then(|s| s.outgoing_mut())
+                    .is_some()
                 {
-                    None => return Ok(()),
-                    Some(outgoing) => outgoing,
-                };
-
-                match &mut outgoing.body {
-                    BodyOutgoing::StillReceiving(pieces) => {
-                        let pieces = std::mem::take(pieces);
-                        if pieces.is_empty() {
-                            // we'll need to send a zero-length data frame
-                            self.state.send_data_maybe.notify_one();
-                        }
-                        outgoing.body = BodyOutgoing::DoneReceiving(pieces);
-                        debug!(stream_id = %ev.stream_id, outgoing_body = ?outgoing.body, "got body end");
-                    }
-                    BodyOutgoing::DoneReceiving(_) => {
-                        unreachable!("got body end twice")
-                    }
-                    BodyOutgoing::DoneSending => {
-                        unreachable!("got body end after we sent everything")
-                    }
+                    self.rst(ev.stream_id, H2StreamError::HandlerFailed).await?;
                 }
             }
         }
@@ -710,89 +1223,52 @@ where
         Ok(())
     }
 
-    async fn write_frame(
+    async fn send_window_update(
         &mut self,
-        mut frame: Frame,
-        payload: PieceList,
+        stream_id: StreamId,
+        increment: u32,
     ) -> Result<(), H2ConnectionError> {
-        match &frame.frame_type {
-            FrameType::Data(flags) => {
-                let mut ss = match self.state.streams.entry(frame.stream_id) {
-                    std::collections::hash_map::Entry::Occupied(entry) => entry,
-                    std::collections::hash_map::Entry::Vacant(_) => unreachable!(
-                        "writing DATA frame for non-existent stream, this should never happen"
-                    ),
-                };
-
-                // update stream flow control window
-                {
-                    let outgoing = match ss.get_mut().outgoing_mut() {
-                        Some(og) => og,
-                        None => {
-                            unreachable!("writing DATA frame for stream in the wrong state")
-                        }
-                    };
-                    let payload_len: u32 = payload.len().try_into().unwrap();
-                    let next_cap = outgoing.capacity - payload_len as i64;
-
-                    if next_cap < 0 {
-                        unreachable!(
-                            "should never write a frame that makes the stream capacity negative: outgoing.capacity = {}, payload_len = {}",
-                            outgoing.capacity, payload.len()
-                        )
-                    }
-                    outgoing.capacity = next_cap;
-                }
-
-                // now update connection flow control window
-                {
-                    let payload_len: u32 = payload.len().try_into().unwrap();
-                    let next_cap = self.state.outgoing_capacity - payload_len as i64;
-
-                    if next_cap < 0 {
-                        unreachable!(
-                            "should never write a frame that makes the connection capacity negative: outgoing_capacity = {}, payload_len = {}",
-                            self.state.outgoing_capacity, payload.len()
-                        )
-                    }
-                    self.state.outgoing_capacity = next_cap;
-                }
+        debug!(%stream_id, %increment, "sending window update");
+        let payload = WindowUpdate {
+            reserved: 0,
+            increment,
+        }
+        .into_piece(&mut self.out_scratch)
+        .map_err(H2ConnectionError::WriteError)?;
+        let frame = Frame::new(FrameType::WindowUpdate, stream_id);
+        self.write_frame(frame, PieceList::single(payload)).await
+    }
 
-                if flags.contains(DataFlags::EndStream) {
-                    // we won't be sending any more data on this stream
-                    self.state
-                        .streams_with_pending_data
-                        .remove(&frame.stream_id);
+    /// Accounts for `len` bytes of DATA received on the connection, giving
+    /// capacity back to the peer once half of the window is used up.
+    async fn consume_connection_window(&mut self, len: i64) -> Result<(), H2ConnectionError> {
+        let next_cap = self.state.incoming_capacity - len;
+        if next_cap < 0 {
+            return Err(H2ConnectionError::WindowUnderflow {
+                stream_id: StreamId::CONNECTION,
+            });
+        }
+        self.state.incoming_capacity = next_cap;
+
+        let conn_window = self
+            .conf
+            .connection_window_size
+            .min(MAX_FLOW_CONTROL_WINDOW) as i64;
+        if self.state.incoming_capacity <= conn_window / 2 {
+            let increment = conn_window - self.state.incoming_capacity;
+            self.state.incoming_capacity = conn_window;
+            self.send_window_update(StreamId::CONNECTION, increment as u32)
+                .await?;
+        }
+        Ok(())
+    }
 
-                    match ss.get_mut() {
-                        StreamState::Open { .. } => {
-                            let incoming = match std::mem::take(ss.get_mut()) {
-                                StreamState::Open { incoming, .. } => incoming,
-                                _ => unreachable!(),
-                            };
-                            // this avoid having to re-insert the stream in the map
-                            *ss.get_mut() = StreamState::HalfClosedLocal { incoming };
-                        }
-                        _ => {
-                            // transition to closed
-                            ss.remove();
-                            debug!(
-                                "Closed stream {} (wrote data w/EndStream), now have {} streams",
-                                frame.stream_id,
-                                self.state.streams.len()
-                            );
-                        }
-                    }
-                }
-            }
-            FrameType::Settings(_) => {
-                // TODO: keep track of whether our new settings have been
-                // acknowledged
-            }
-            _ => {
-                // muffin.
-            }
-        };
+    async fn write_frame(
+        &mut self,
+        mut frame: Frame,
+        payload: PieceList,
+    ) -> Result<(), H2ConnectionError> {
+        self.state.on_frame_written(&frame, payload.len());
 
         // TODO: enforce max_frame_size from the peer settings, not just u32::max
         frame.len = payload
@@ -808,23 +1284,34 @@ where
             .into_piece(&mut self.out_scratch)
             .map_err(H2ConnectionError::WriteError)?;
 
-        if payload.is_empty() {
-            trace!("Writing frame without payload");
-            self.transport_w
-                .write_all_owned(frame_roll)
-                .await
-                .map_err(H2ConnectionError::WriteError)?;
-        } else {
-            trace!("Writing frame with payload");
-            self.transport_w
-                .writev_all_owned(payload.preceded_by(frame_roll))
-                .await
-                .map_err(H2ConnectionError::WriteError)?;
+        if self.out_batch.num_pieces() + 1 + payload.num_pieces() > MAX_BATCH_PIECES {
+            self.flush_writes().await?;
+        }
+        self.out_batch.push_back(frame_roll);
+        for piece in payload.into_vec_deque() {
+            self.out_batch.push_back(piece);
+        }
+        if self.out_batch.len() >= MAX_BATCH_BYTES {
+            self.flush_writes().await?;
         }
 
         Ok(())
     }
 
+    /// Writes out every frame [ServerContext::write_frame] batched so far
+    async fn flush_writes(&mut self) -> Result<(), H2ConnectionError> {
+        if self.out_batch.is_empty() {
+            return Ok(());
+        }
+
+        let batch = std::mem::take(&mut self.out_batch);
+        trace!(len = %batch.len(), pieces = %batch.num_pieces(), "flushing writes");
+        self.transport_w
+            .writev_all_owned(batch)
+            .await
+            .map_err(H2ConnectionError::WriteError)
+    }
+
     async fn process_frame(
         &mut self,
         frame: Frame,
@@ -839,16 +1326,29 @@ where
                     });
                 }
 
-                let ss = self.state.streams.get_mut(&frame.stream_id).ok_or(
-                    H2ConnectionError::StreamClosed {
+                if !self.state.streams.contains_key(&frame.stream_id) {
+                    return Err(H2ConnectionError::StreamClosed {
                         stream_id: frame.stream_id,
-                    },
-                )?;
+                    });
+                }
+
+                // the whole payload counts against flow control windows,
+                // padding included
+                let flow_len = frame.len as i64;
+                self.consume_connection_window(flow_len).await?;
+
+                let stream_window = self.state.self_settings.initial_window_size as i64;
+                let mut stream_window_update = None;
 
+                let ss = self
+                    .state
+                    .streams
+                    .get_mut(&frame.stream_id)
+                    .expect("checked above");
                 match ss {
                     StreamState::Open { incoming, .. }
                     | StreamState::HalfClosedLocal { incoming } => {
-                        let next_cap = incoming.capacity - payload.len() as i64;
+                        let next_cap = incoming.capacity - flow_len;
                         if next_cap < 0 {
                             return Err(H2ConnectionError::WindowUnderflow {
                                 stream_id: frame.stream_id,
@@ -862,7 +1362,6 @@ where
                             ChunkPosition::NotLast
                         };
 
-                        // TODO: give back capacity to peer at some point
                         if let Err(e) = incoming.write_chunk(payload.into(), which).await {
                             self.rst(frame.stream_id, e).await?;
                         } else if flags.contains(DataFlags::EndStream) {
@@ -879,6 +1378,11 @@ where
                                     self.state.streams.len()
                                 );
                             }
+                        } else if incoming.capacity <= stream_window / 2 {
+                            // the handler has the chunk: give capacity back
+                            // to the peer once half of the window is used up
+                            stream_window_update = Some(stream_window - incoming.capacity);
+                            incoming.capacity = stream_window;
                         }
                     }
                     StreamState::HalfClosedRemote { .. } => {
@@ -891,6 +1395,11 @@ where
                     }
                     StreamState::Transition => unreachable!(),
                 }
+
+                if let Some(increment) = stream_window_update {
+                    self.send_window_update(frame.stream_id, increment as u32)
+                        .await?;
+                }
             }
             FrameType::Headers(flags) => {
                 if flags.contains(HeadersFlags::Priority) {
@@ -945,9 +1454,6 @@ where
                                 });
                             }
                             std::cmp::Ordering::Greater => {
-                                // TODO: if we're shutting down, ignore streams higher
-                                // than the last one we accepted.
-
                                 let max_concurrent_streams = self
                                     .state
                                     .self_settings
@@ -955,7 +1461,14 @@ where
                                     .unwrap_or(u32::MAX);
                                 let num_streams_if_accept = self.state.streams.len() + 1;
 
-                                if num_streams_if_accept > max_concurrent_streams as _ {
+                                if self.goaway_sent.is_some() {
+                                    // we're shutting down, and told the client
+                                    // we wouldn't process this stream: it may
+                                    // retry it on another connection.
+                                    self.rst(frame.stream_id, H2StreamError::ShuttingDown)
+                                        .await?;
+                                    mode = ReadHeadersMode::Skip;
+                                } else if num_streams_if_accept > max_concurrent_streams as _ {
                                     // reset the stream, indicating we refused it
                                     self.rst(frame.stream_id, H2StreamError::RefusedStream)
                                         .await?;
@@ -1017,7 +1530,7 @@ where
                             self.state.streams.insert(
                                 stream_id,
                                 StreamState::HalfClosedRemote {
-                                    outgoing: self.state.mk_stream_outgoing(),
+                                    outgoing: self.state.mk_stream_outgoing(Default::default()),
                                 },
                             );
                             // TODO: inserting/removing here is probably unnecessary.
@@ -1031,6 +1544,7 @@ where
                                         version: Version::HTTP_2,
                                         status: e.status,
                                         headers: Default::default(),
+                                        header_case: None,
                                     },
                                     &mut SinglePieceBody::new(e.message),
                                 )
@@ -1087,12 +1601,26 @@ where
                     return Ok(());
                 }
                 // TODO: do something with the error code?
+                self.count_reset()?;
 
+                self.state
+                    .streams_with_pending_data
+                    .remove(&frame.stream_id);
                 match self.state.streams.remove(&frame.stream_id) {
                     None => {
-                        return Err(H2ConnectionError::RstStreamForUnknownStream {
-                            stream_id: frame.stream_id,
-                        })
+                        if frame.stream_id == StreamId::CONNECTION
+                            || frame.stream_id > self.state.last_stream_id
+                        {
+                            // the stream is idle (or not a stream at all)
+                            return Err(H2ConnectionError::RstStreamForUnknownStream {
+                                stream_id: frame.stream_id,
+                            });
+                        }
+
+                        // we've closed that stream, but the peer may not have
+                        // heard about it yet, cf. RFC 9113, section 5.1
+                        debug!(stream_id = %frame.stream_id, "ignoring rst for closed stream");
+                        return Ok(());
                     }
                     Some(ss) => {
                         debug!(
@@ -1101,12 +1629,18 @@ where
                             self.state.streams.len()
                         );
                         match ss {
-                            StreamState::Open { mut incoming, .. }
-                            | StreamState::HalfClosedLocal { mut incoming, .. } => {
+                            StreamState::Open {
+                                mut incoming,
+                                outgoing,
+                            } => {
+                                outgoing.cancellation.cancel(CancelReason::StreamReset);
+                                incoming.send_error(StreamIncomingError::StreamReset).await;
+                            }
+                            StreamState::HalfClosedLocal { mut incoming, .. } => {
                                 incoming.send_error(StreamIncomingError::StreamReset).await;
                             }
-                            StreamState::HalfClosedRemote { .. } => {
-                                // good
+                            StreamState::HalfClosedRemote { outgoing } => {
+                                outgoing.cancellation.cancel(CancelReason::StreamReset);
                             }
                             StreamState::Transition => unreachable!(),
                         }
@@ -1134,49 +1668,9 @@ where
                         });
                     }
                 } else {
-                    let original_initial_window_size = self.state.peer_settings.initial_window_size;
-                    let s = &mut self.state.peer_settings;
-
-                    Settings::parse(&payload[..], |code, value| {
-                        s.apply(code, value)?;
-                        match code {
-                            Setting::HeaderTableSize => {
-                                self.hpack_enc.set_max_table_size(value as _);
-                            }
-                            _ => {
-                                // nothing to do
-                            }
-                        }
-                        Ok(())
-                    })
-                    .map_err(H2ConnectionError::BadSettingValue)?;
-
-                    let initial_window_size_delta =
-                        (s.initial_window_size as i64) - (original_initial_window_size as i64);
-
-                    let mut maybe_send_data = false;
-                    if initial_window_size_delta != 0 {
-                        // apply that delta to all streams
-                        for (id, stream) in self.state.streams.iter_mut() {
-                            if let Some(outgoing) = stream.outgoing_mut() {
-                                let next_cap = outgoing.capacity + initial_window_size_delta;
-                                if next_cap > MAX_WINDOW_SIZE {
-                                    return Err(
-                                        H2ConnectionError::StreamWindowSizeOverflowDueToSettings {
-                                            stream_id: *id,
-                                        },
-                                    );
-                                }
-                                // if capacity was negative or zero, and is now greater than zero,
-                                // we need to maybe send data
-                                if next_cap > 0 && outgoing.capacity <= 0 {
-                                    debug!(?id, %next_cap, "stream capacity was <= 0, now > 0");
-                                    maybe_send_data = true;
-                                }
-                                outgoing.capacity = next_cap;
-                            }
-                        }
-                    }
+                    let maybe_send_data = self
+                        .state
+                        .apply_peer_settings(&payload[..], &mut self.hpack_enc)?;
 
                     let frame = Frame::new(
                         FrameType::Settings(SettingsFlags::Ack.into()),
@@ -1205,7 +1699,12 @@ where
                 }
 
                 if flags.contains(PingFlags::Ack) {
-                    // TODO: check that payload matches the one we sent?
+                    if let Some((sent, _)) = self.state.ping_in_flight {
+                        if payload[..] == sent[..] {
+                            debug!("keep-alive ping acknowledged");
+                            self.state.ping_in_flight = None;
+                        }
+                    }
                     return Ok(());
                 }
 
@@ -1223,6 +1722,17 @@ where
                     });
                 }
 
+                let (_, goaway) = GoAway::parse(payload).finish().map_err(|_| {
+                    H2ConnectionError::ReadAndParse(ReadAndParseError::ParsingError {
+                        parser: "GoAway",
+                    })
+                })?;
+                debug!(
+                    last_stream_id = %goaway.last_stream_id,
+                    error_code = ?goaway.error_code,
+                    debug_data = %GoAwayDebugData(&goaway.additional_debug_data),
+                    "client is going away"
+                );
                 self.goaway_recv = true;
 
                 // TODO: this should probably have other effects than setting
@@ -1264,9 +1774,20 @@ where
                     {
                         Some(ss) => ss,
                         None => {
-                            return Err(H2ConnectionError::WindowUpdateForUnknownOrClosedStream {
-                                stream_id: frame.stream_id,
-                            });
+                            if frame.stream_id > self.state.last_stream_id {
+                                // the stream is idle
+                                return Err(
+                                    H2ConnectionError::WindowUpdateForUnknownOrClosedStream {
+                                        stream_id: frame.stream_id,
+                                    },
+                                );
+                            }
+
+                            // we're done sending on that stream, but the peer
+                            // may not have heard about it yet, cf. RFC 9113,
+                            // section 6.9
+                            debug!(stream_id = %frame.stream_id, "ignoring window update for closed stream");
+                            return Ok(());
                         }
                     };
 
@@ -1301,6 +1822,55 @@ where
                     stream_id: frame.stream_id,
                 });
             }
+            FrameType::Unknown(ft) if ft.ty == PRIORITY_UPDATE_FRAME_TYPE => {
+                if frame.stream_id != StreamId::CONNECTION {
+                    return Err(H2ConnectionError::PriorityUpdateWithNonZeroStreamId {
+                        stream_id: frame.stream_id,
+                    });
+                }
+
+                let update = match PriorityUpdate::parse(payload) {
+                    Ok((_, update)) => update,
+                    Err(_) => {
+                        return Err(H2ConnectionError::PriorityUpdateInvalidLength {
+                            len: frame.len,
+                        });
+                    }
+                };
+                let stream_id = update.prioritized_stream_id;
+                if stream_id == StreamId::CONNECTION {
+                    return Err(H2ConnectionError::PriorityUpdateForStreamZero);
+                }
+
+                let priority = Priority::parse(&update.field_value[..]);
+                debug!(%stream_id, ?priority, "received priority update");
+
+                if let Some(outgoing) = self
+                    .state
+                    .streams
+                    .get_mut(&stream_id)
+                    .and_then(|ss| ss.outgoing_mut())
+                {
+                    outgoing.priority = priority;
+                } else if stream_id > self.state.last_stream_id {
+                    // the stream isn't open yet: remember the priority for when
+                    // it is, but don't let the peer make us buffer an unbounded
+                    // amount of updates.
+                    let max_buffered = self
+                        .state
+                        .self_settings
+                        .max_concurrent_streams
+                        .unwrap_or(100);
+                    if self.state.early_priority_updates.len() < max_buffered as usize
+                        || self.state.early_priority_updates.contains_key(&stream_id)
+                    {
+                        self.state
+                            .early_priority_updates
+                            .insert(stream_id, priority);
+                    }
+                }
+                // updates for closed streams are ignored
+            }
             FrameType::Unknown(ft) => {
                 trace!(
                     "ignoring unknown frame with type 0x{:x}, flags 0x{:x}",
@@ -1313,13 +1883,41 @@ where
         Ok(())
     }
 
+    /// Counts a RST_STREAM frame from the peer against
+    /// [ServerConf::max_resets]
+    fn count_reset(&mut self) -> Result<(), H2ConnectionError> {
+        let Some(max_resets) = self.conf.max_resets else {
+            return Ok(());
+        };
+
+        let now = Instant::now();
+        if now >= self.state.resets_since + self.conf.reset_window {
+            self.state.resets_since = now;
+            self.state.resets = 0;
+        }
+        self.state.resets += 1;
+        if self.state.resets > max_resets {
+            debug!("client reset more than {max_resets} streams, closing connection");
+            return Err(H2ConnectionError::TooManyResets {
+                max_resets,
+                window: self.conf.reset_window,
+            });
+        }
+        Ok(())
+    }
+
     /// Send a RST_STREAM frame to the peer.
     async fn rst(
         &mut self,
         stream_id: StreamId,
         e: H2StreamError,
     ) -> Result<(), H2ConnectionError> {
-        self.state.streams.remove(&stream_id);
+        self.state.streams_with_pending_data.remove(&stream_id);
+        if let Some(mut ss) = self.state.streams.remove(&stream_id) {
+            if let Some(outgoing) = ss.outgoing_mut() {
+                outgoing.cancellation.cancel(CancelReason::StreamReset);
+            }
+        }
 
         let error_code = e.as_known_error_code();
         debug!("Sending rst because: {e} (known error code: {error_code:?})");
@@ -1371,6 +1969,8 @@ where
             #[allow(unused, clippy::let_unit_value)]
             let flags = (); // don't accidentally use the `flags` variable
 
+            let max_len = self.conf.max_header_list_size as usize;
+            let mut len = payload.len();
             let mut fragments = smallvec![payload];
 
             loop {
@@ -1407,6 +2007,13 @@ where
                     }
                 };
 
+                len += continuation_payload.len();
+                if len > max_len {
+                    return Err(
+                        H2ConnectionError::HeaderBlockTooLarge { stream_id, max_len }.into(),
+                    );
+                }
+
                 // add fragment
                 fragments.push(continuation_payload);
 
@@ -1429,8 +2036,12 @@ where
         let mut scheme: Option<Scheme> = None;
         let mut path: Option<PieceStr> = None;
         let mut authority: Option<Authority> = None;
+        let mut protocol: Option<PieceStr> = None;
 
         let mut headers = Headers::default();
+        // decoded values are copied there, they're gone from the decoder
+        // once the block is decoded
+        let mut arena = HeaderArena::default();
 
         {
             // we assign to an outer variable because header decoding needs to finish no
@@ -1473,7 +2084,7 @@ where
                     // TODO: reject headers that occur after pseudo-headers
                     match &key[1..] {
                         b"method" => {
-                            let value: PieceStr = match Piece::from(value.to_vec()).to_str() {
+                            let value: PieceStr = match arena.alloc(&value).to_str() {
                                 Ok(p) => p,
                                 Err(_) => {
                                     req_error = Some(H2StreamError::BadRequest(
@@ -1487,7 +2098,7 @@ where
                             }
                         }
                         b"scheme" => {
-                            let value: PieceStr = match Piece::from(value.to_vec()).to_str() {
+                            let value: PieceStr = match arena.alloc(&value).to_str() {
                                 Ok(p) => p,
                                 Err(_) => {
                                     req_error = Some(H2StreamError::BadRequest(
@@ -1501,7 +2112,7 @@ where
                             }
                         }
                         b"path" => {
-                            let value: PieceStr = match Piece::from(value.to_vec()).to_str() {
+                            let value: PieceStr = match arena.alloc(&value).to_str() {
                                 Ok(val) => val,
                                 Err(_) => {
                                     req_error = Some(H2StreamError::BadRequest("invalid ':path' pseudo-header (not valid utf-8, which is _certainly_ not a valid URI, as defined by RFC 3986, section 2. See also RFC 9113, section 8.3.1). "));
@@ -1514,7 +2125,7 @@ where
                             }
                         }
                         b"authority" => {
-                            let value: PieceStr = match Piece::from(value.to_vec()).to_str() {
+                            let value: PieceStr = match arena.alloc(&value).to_str() {
                                 Ok(p) => p,
                                 Err(_) => {
                                     req_error = Some(H2StreamError::BadRequest(
@@ -1534,9 +2145,25 @@ where
                                 req_error = Some(H2StreamError::BadRequest("duplicate ':authority' pseudo-header. All HTTP/2 requests MUST include _exactly one_ valid value for the ':method', ':scheme', and ':path' pseudo-header fields, unless they are CONNECT requests (RFC 9113, section 8.3.1)"));
                             }
                         }
+                        b"protocol" if self.state.self_settings.enable_connect_protocol => {
+                            let value: PieceStr = match arena.alloc(&value).to_str() {
+                                Ok(p) => p,
+                                Err(_) => {
+                                    req_error = Some(H2StreamError::BadRequest(
+                                        "invalid ':protocol' pseudo-header: not valid utf-8",
+                                    ));
+                                    return;
+                                }
+                            };
+                            if protocol.replace(value).is_some() {
+                                req_error = Some(H2StreamError::BadRequest(
+                                    "duplicate ':protocol' pseudo-header (RFC 8441, section 4)",
+                                ));
+                            }
+                        }
                         _ => {
                             req_error = Some(H2StreamError::BadRequest(
-                                "received invalid pseudo-header. the only defined pseudo-headers are: ':method', ':scheme', ':path', ':authority', ':status' (RFC 9113, section 8.1)",
+                                "received invalid pseudo-header. the only defined pseudo-headers are: ':method', ':scheme', ':path', ':authority', ':status' (RFC 9113, section 8.1), and ':protocol' if SETTINGS_ENABLE_CONNECT_PROTOCOL was sent (RFC 8441, section 4)",
                             ));
                         }
                     }
@@ -1556,7 +2183,10 @@ where
                     // Note: An implementation that validates fields according to the definitions in
                     // Sections 5.1 and 5.5 of HTTP only needs an additional check that field
                     // names do not include uppercase characters.
-                    if key.iter().any(|b: &u8| b.is_ascii_uppercase()) {
+                    // (`HeaderName` lowercases them, if we're lenient)
+                    if !self.conf.validation.allow_uppercase_header_names
+                        && key.iter().any(|b: &u8| b.is_ascii_uppercase())
+                    {
                         req_error = Some(H2StreamError::BadRequest(
                             "A field name MUST NOT contain characters in the ranges 0x00-0x20, 0x41-0x5a, or 0x7f-0xff (all ranges inclusive). This specifically excludes all non-visible ASCII characters, ASCII SP (0x20), and uppercase characters ('A' to 'Z', ASCII 0x41 to 0x5a). See RFC9113, section 8.2.1, 'Field Validity'",
                         ));
@@ -1611,8 +2241,7 @@ where
                         return;
                     }
 
-                    let value: Piece = value.to_vec().into();
-                    headers.append(name, value);
+                    headers.append(name, arena.alloc(&value));
                 }
             };
 
@@ -1649,9 +2278,22 @@ where
                 // field that identifies an entity that differs from the entity in the
                 // ":authority" pseudo-header field.
 
+                if protocol.is_some() && method != Some(Method::Connect) {
+                    return Err(H2StreamError::BadRequest(
+                        "':protocol' pseudo-header is only allowed on CONNECT requests (RFC 8441, section 4)",
+                    )
+                    .into());
+                }
+                if protocol.is_some() && authority.is_none() {
+                    return Err(H2StreamError::BadRequest(
+                        "extended CONNECT requests MUST include an ':authority' pseudo-header (RFC 8441, section 4)",
+                    )
+                    .into());
+                }
+
                 let method = match method {
                     Some(method) => {
-                        if method == Method::Connect {
+                        if method == Method::Connect && protocol.is_none() {
                             // RFC 9113, section 8.5 'The CONNECT method': The ":scheme" and ":path"
                             // pseudo-header fields MUST be omitted.
                             if scheme.is_some() {
@@ -1672,13 +2314,6 @@ where
                                 )
                                 .into());
                             }
-
-                            // well, also, we just don't support the `CONNECT` method.
-                            return Err(H2RequestError {
-                                status: StatusCode::NOT_IMPLEMENTED,
-                                message: "CONNECT method is not supported".into(),
-                            }
-                            .into());
                         }
 
                         method
@@ -1690,95 +2325,127 @@ where
                     }
                 };
 
-                let scheme = match scheme {
-                    Some(scheme) => scheme,
-                    None => {
-                        return Err(
-                            H2StreamError::BadRequest("missing :scheme pseudo-header").into()
-                        );
-                    }
-                };
-
-                let path = match path {
-                    Some(path) => path,
-                    None => {
-                        return Err(
-                            H2StreamError::BadRequest("missing :path pseudo-header, cf. RFC9113, section 8.3.1: This pseudo-header field MUST NOT be empty for 'http' or 'https' URIs; 'http' or 'https' URIs that do not contain a path component MUST include a value of '/'.").into()
-                        );
-                    }
-                };
-
-                if path.len() == 0 && (scheme == Scheme::HTTP || scheme == Scheme::HTTPS) {
-                    return Err(H2StreamError::BadRequest(
-                        "as per RFC9113, section 8.3.1, ':path' header value MUST NOT be empty for 'http' and 'https' URIs",
-                    ).into());
-                }
-
-                let path_and_query: PathAndQuery = match path.parse() {
-                    Ok(p) => p,
-                    Err(_) => {
+                let uri = if method == Method::Connect && protocol.is_none() {
+                    // RFC 9113, section 8.5: The ":authority" pseudo-header field
+                    // contains the host and port to connect to.
+                    let authority = authority.unwrap();
+                    if !is_valid_connect_authority(&authority) {
                         return Err(H2StreamError::BadRequest(
-                            "':path' header value is not a valid PathAndQuery",
+                            "CONNECT ':authority' pseudo-header must be a host and a port (RFC 9113, section 8.5)",
                         )
                         .into());
                     }
-                };
+                    http::uri::Uri::from(authority)
+                } else {
+                    let scheme = match scheme {
+                        Some(scheme) => scheme,
+                        None => {
+                            return Err(
+                                H2StreamError::BadRequest("missing :scheme pseudo-header").into()
+                            );
+                        }
+                    };
 
-                let authority = match authority {
-                    Some(authority) => {
-                        // if there's a `host` header, it must match the `:authority` pseudo-header
-                        if let Some(host) = headers.get(header::HOST) {
-                            let host = std::str::from_utf8(host).map_err(|_| {
-                                H2StreamError::BadRequest("'host' header value is not utf-8")
-                            })?;
-                            let host_authority: Authority = host.parse().map_err(|_| {
-                                H2StreamError::BadRequest("'host' header value is not a valid URI")
-                            })?;
-                            if host_authority != authority {
-                                return Err(H2StreamError::BadRequest(
-                                    "'host' header value does not match ':authority' pseudo-header value, cf. RFC9113, Section 8.3.1: A server SHOULD treat a request as malformed if it contains a Host header field that identifies an entity that differs from the entity in the ':authority' pseudo-header field"
-                                ).into());
-                            }
+                    let path = match path {
+                        Some(path) => path,
+                        None => {
+                            return Err(
+                                H2StreamError::BadRequest("missing :path pseudo-header, cf. RFC9113, section 8.3.1: This pseudo-header field MUST NOT be empty for 'http' or 'https' URIs; 'http' or 'https' URIs that do not contain a path component MUST include a value of '/'.").into()
+                            );
                         }
+                    };
 
-                        Some(authority)
+                    if path.len() == 0 && (scheme == Scheme::HTTP || scheme == Scheme::HTTPS) {
+                        return Err(H2StreamError::BadRequest(
+                            "as per RFC9113, section 8.3.1, ':path' header value MUST NOT be empty for 'http' and 'https' URIs",
+                        ).into());
                     }
-                    None => match headers.get(header::HOST) {
-                        Some(host) => {
-                            let host = std::str::from_utf8(host).map_err(|_| {
-                                H2StreamError::BadRequest("'host' header value is not utf-8")
-                            })?;
-                            let authority: Authority = host.parse().map_err(|_| {
-                                H2StreamError::BadRequest("'host' header value is not a valid URI")
-                            })?;
+
+                    let path_and_query: PathAndQuery = match path.parse() {
+                        Ok(p) => p,
+                        Err(_) => {
+                            return Err(H2StreamError::BadRequest(
+                                "':path' header value is not a valid PathAndQuery",
+                            )
+                            .into());
+                        }
+                    };
+
+                    let authority = match authority {
+                        Some(authority) => {
+                            // if there's a `host` header, it must match the `:authority` pseudo-header
+                            if let Some(host) = headers.get(header::HOST) {
+                                let host = std::str::from_utf8(host).map_err(|_| {
+                                    H2StreamError::BadRequest("'host' header value is not utf-8")
+                                })?;
+                                let host_authority: Authority = host.parse().map_err(|_| {
+                                    H2StreamError::BadRequest(
+                                        "'host' header value is not a valid URI",
+                                    )
+                                })?;
+                                if host_authority != authority {
+                                    return Err(H2StreamError::BadRequest(
+                                        "'host' header value does not match ':authority' pseudo-header value, cf. RFC9113, Section 8.3.1: A server SHOULD treat a request as malformed if it contains a Host header field that identifies an entity that differs from the entity in the ':authority' pseudo-header field"
+                                    ).into());
+                                }
+                            }
+
                             Some(authority)
                         }
-                        None => None,
-                    },
-                };
+                        None => match headers.get(header::HOST) {
+                            Some(host) => {
+                                let host = std::str::from_utf8(host).map_err(|_| {
+                                    H2StreamError::BadRequest("'host' header value is not utf-8")
+                                })?;
+                                let authority: Authority = host.parse().map_err(|_| {
+                                    H2StreamError::BadRequest(
+                                        "'host' header value is not a valid URI",
+                                    )
+                                })?;
+                                Some(authority)
+                            }
+                            None => None,
+                        },
+                    };
 
-                let mut uri_parts: http::uri::Parts = Default::default();
-                uri_parts.scheme = Some(scheme);
-                uri_parts.authority = authority;
-                uri_parts.path_and_query = Some(path_and_query);
+                    let mut uri_parts: http::uri::Parts = Default::default();
+                    uri_parts.scheme = Some(scheme);
+                    uri_parts.authority = authority;
+                    uri_parts.path_and_query = Some(path_and_query);
 
-                let uri = match http::uri::Uri::from_parts(uri_parts) {
-                    Ok(uri) => uri,
-                    Err(_) => {
-                        return Err(H2RequestError {
-                            status: StatusCode::BAD_REQUEST,
-                            message: "invalid URI parts".into(),
+                    match http::uri::Uri::from_parts(uri_parts) {
+                        Ok(uri) => uri,
+                        Err(_) => {
+                            return Err(H2RequestError {
+                                status: StatusCode::BAD_REQUEST,
+                                message: "invalid URI parts".into(),
+                            }
+                            .into())
                         }
-                        .into())
                     }
                 };
 
-                let req = Request {
+                let mut req = Request {
                     method,
                     uri,
                     version: Version::HTTP_2,
                     headers,
+                    protocol,
+                    header_case: None,
+                    connection: self.connection.clone(),
                 };
+                if self.early_data.is_stream_marked(stream_id.0) {
+                    req.headers.insert(EARLY_DATA.clone(), "1".into());
+                }
+                if self.conf.reject_unsafe_early_data && early_data::is_unsafe(&req) {
+                    debug!(%stream_id, method = %req.method, "request came in early data, replying with 425");
+                    return Err(H2RequestError {
+                        status: early_data::too_early(),
+                        message: "request came in TLS early data, retry once the handshake is done"
+                            .into(),
+                    }
+                    .into());
+                }
                 let content_length: Option<u64> = match req
                     .headers
                     .get(http::header::CONTENT_LENGTH)
@@ -1803,7 +2470,24 @@ where
                     }
                 };
 
-                let responder = Responder::new(H2Encoder::new(stream_id, self.ev_tx.clone()));
+                // the decoded headers stick around for as long as the stream
+                // does, cf. `StreamOutgoing::memory`
+                let headers_size = req.uri.to_string().len()
+                    + req
+                        .headers
+                        .iter()
+                        .map(|(name, value)| name.as_str().len() + value.len())
+                        .sum::<usize>();
+                let Some(memory) = self.state.memory.try_charge(headers_size) else {
+                    debug!(%stream_id, %headers_size, used = %self.state.memory.used(), "over memory budget, refusing stream");
+                    return Err(H2StreamError::HeadersOverMemoryBudget.into());
+                };
+
+                let cancellation =
+                    Cancellation::new(self.conf.request_timeout.map(|t| Instant::now() + t));
+                let (encoder, on_drop) =
+                    H2Encoder::new(stream_id, self.ev_tx.clone()).deferring_drop();
+                let responder = Responder::new(encoder).with_cancellation(cancellation.clone());
 
                 let (piece_tx, piece_rx) = mpsc::channel::<IncomingMessageResult>(1); // TODO: is 1 a sensible value here?
 
@@ -1818,7 +2502,19 @@ where
                     content_length,
                     piece_tx,
                 );
-                let outgoing: StreamOutgoing = self.state.mk_stream_outgoing();
+                // a PRIORITY_UPDATE frame received before the request takes
+                // precedence over the `priority` header, cf. RFC 9218, section 7.1
+                let priority = match self.state.early_priority_updates.remove(&stream_id) {
+                    Some(priority) => priority,
+                    None => req
+                        .headers
+                        .get("priority")
+                        .map(|v| Priority::parse(v))
+                        .unwrap_or_default(),
+                };
+                let mut outgoing: StreamOutgoing = self.state.mk_stream_outgoing(priority);
+                outgoing.cancellation = cancellation;
+                outgoing.memory = memory;
                 self.state.streams.insert(
                     stream_id,
                     if end_stream {
@@ -1840,17 +2536,61 @@ where
                 // its entire state.
                 buffet::spawn({
                     let driver = self.driver.clone();
+                    let error_hook = self.conf.error_hook.clone();
+                    let ev_tx = self.ev_tx.clone();
                     async move {
                         let mut req_body = req_body;
                         let responder = responder;
 
-                        match driver.handle(req, &mut req_body, responder).await {
-                            Ok(_responder) => {
+                        let res = catch_unwind(driver.handle(req, &mut req_body, responder)).await;
+                        let state = match on_drop.replace(OnDrop::Finish) {
+                            OnDrop::Dropped(state) => state,
+                            // the responder is still alive somewhere, it'll
+                            // finish the response itself when it's dropped.
+                            _ => EncoderState::ResponseDone,
+                        };
+
+                        let failure_response = match &res {
+                            Ok(Ok(_responder)) => {
                                 debug!("Handler completed successfully, gave us a responder");
+                                return;
                             }
-                            Err(e) => {
-                                // TODO: actually handle that error.
-                                debug!("Handler returned an error: {e}")
+                            Ok(Err(e)) => {
+                                debug!("Handler returned an error: {e}");
+                                (state == EncoderState::ExpectResponseHeaders)
+                                    .then(|| error_hook(&HandlerFailure::Error(e)))
+                            }
+                            Err(payload) => {
+                                let message = panic_message(payload.as_ref());
+                                debug!(?message, "Handler panicked");
+                                (state == EncoderState::ExpectResponseHeaders)
+                                    .then(|| error_hook(&HandlerFailure::Panic(message)))
+                            }
+                        };
+
+                        match failure_response {
+                            Some((res, body)) => {
+                                let responder = Responder::new(H2Encoder::new(stream_id, ev_tx));
+                                if let Err(e) = responder
+                                    .write_final_response_with_body(
+                                        res,
+                                        &mut SinglePieceBody::from(body),
+                                    )
+                                    .await
+                                {
+                                    debug!("Could not send error response: {e}");
+                                }
+                            }
+                            None => {
+                                if state == EncoderState::ExpectResponseBody {
+                                    let ev = H2Event {
+                                        stream_id,
+                                        payload: H2EventPayload::Reset,
+                                    };
+                                    if ev_tx.send(ev).await.is_err() {
+                                        debug!("could not send event to h2 connection handler");
+                                    }
+                                }
                             }
                         }
                     }
@@ -1889,6 +2629,147 @@ where
     }
 }
 
+impl<OurDriver, OurWriteOwned> Drop for ServerContext<OurDriver, OurWriteOwned>
+where
+    OurDriver: ServerDriver<H2Encoder> + 'static,
+    OurWriteOwned: WriteOwned,
+{
+    fn drop(&mut self) {
+        // handlers might still be running: let them know nobody's listening
+        for ss in self.state.streams.values_mut() {
+            if let Some(outgoing) = ss.outgoing_mut() {
+                outgoing.cancellation.cancel(CancelReason::ConnectionClosed);
+            }
+        }
+    }
+}
+
+/// Reads frames from the peer and sends them, with padding stripped, to
+/// whoever processes them, until the peer hangs up or `tx` is dropped.
+/// Streams whose request headers end within early data are marked in
+/// `early_data`, which counts what's read from `transport_r`.
+pub(crate) async fn deframe_loop(
+    mut client_buf: RollMut,
+    mut transport_r: impl ReadOwned,
+    tx: mpsc::Sender<(Frame, Roll)>,
+    max_frame_size: Rc<AtomicU32>,
+    early_data: EarlyData,
+) -> Result<(), H2ConnectionError> {
+    'read_frames: loop {
+        const MAX_FRAME_HEADER_SIZE: usize = 128;
+        let frame;
+        trace!("Reading frame... Buffer length: {}", client_buf.len());
+        let frame_res = read_and_parse(
+            "Http2Frame",
+            Frame::parse,
+            &mut transport_r,
+            client_buf,
+            MAX_FRAME_HEADER_SIZE,
+        )
+        .await;
+
+        let maybe_frame = match frame_res {
+            Ok(inner) => inner,
+            Err(e) => return Err(H2ConnectionError::ReadAndParse(e)),
+        };
+        (client_buf, frame) = match maybe_frame {
+            Some((client_buf, frame)) => (client_buf, frame),
+            None => {
+                debug!("Peer hung up");
+                break 'read_frames;
+            }
+        };
+        trace!(
+            "Reading frame... done! New buffer length: {}",
+            client_buf.len()
+        );
+        debug!(?frame, "<");
+
+        let max_frame_size = max_frame_size.load(Ordering::Relaxed);
+        if frame.len > max_frame_size {
+            return Err(H2ConnectionError::FrameTooLarge {
+                frame_type: frame.frame_type,
+                frame_size: frame.len,
+                max_frame_size,
+            });
+        }
+
+        trace!(
+            "Reading payload of size {}... Buffer length: {}",
+            frame.len,
+            client_buf.len()
+        );
+        let mut payload;
+        (client_buf, payload) = match read_and_parse(
+            "FramePayload",
+            nom::bytes::streaming::take(frame.len as usize),
+            &mut transport_r,
+            client_buf,
+            frame.len as usize,
+        )
+        .await
+        .map_err(H2ConnectionError::ReadAndParse)?
+        {
+            Some((client_buf, payload)) => (client_buf, payload),
+            None => {
+                return Err(H2ConnectionError::IncompleteFrame {
+                    frame_type: frame.frame_type,
+                    frame_size: frame.len,
+                })
+            }
+        };
+        trace!(
+            "Reading payload... done! New buffer length: {}",
+            client_buf.len()
+        );
+
+        let ends_headers = match frame.frame_type {
+            FrameType::Headers(flags) => flags.contains(HeadersFlags::EndHeaders),
+            FrameType::Continuation(flags) => flags.contains(ContinuationFlags::EndHeaders),
+            _ => false,
+        };
+        if ends_headers && early_data.covers(client_buf.len()) {
+            early_data.mark_stream(frame.stream_id.0);
+        }
+
+        let has_padding = match frame.frame_type {
+            FrameType::Data(flags) => flags.contains(DataFlags::Padded),
+            FrameType::Headers(flags) => flags.contains(HeadersFlags::Padded),
+            _ => false,
+        };
+
+        if has_padding {
+            if payload.is_empty() {
+                return Err(H2ConnectionError::PaddedFrameEmpty {
+                    frame_type: frame.frame_type,
+                });
+            }
+
+            let padding_length_roll;
+            (padding_length_roll, payload) = payload.split_at(1);
+            let padding_length = padding_length_roll[0] as usize;
+            if payload.len() < padding_length {
+                return Err(H2ConnectionError::PaddedFrameTooShort {
+                    frame_type: frame.frame_type,
+                    padding_length,
+                    frame_size: frame.len,
+                });
+            }
+
+            // padding is on the end of the payload
+            let at = payload.len() - padding_length;
+            (payload, _) = payload.split_at(at);
+        }
+
+        if tx.send((frame, payload)).await.is_err() {
+            debug!("h2 deframer: receiver dropped, closing connection");
+            return Ok(());
+        }
+    }
+
+    Ok(())
+}
+
 enum ReadHeadersMode {
     // we're accepting the stream or processing trailers, we want to
     // process the headers we read.
@@ -1896,3 +2777,133 @@ enum ReadHeadersMode {
     // we're refusing the stream, we want to skip over the headers we read.
     Skip,
 }
+
+pub(crate) async fn sleep_until_maybe(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Receives from `rx`, if there's one: never completes otherwise
+async fn recv_maybe<T>(rx: &mut Option<mpsc::UnboundedReceiver<T>>) -> Option<T> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Completes when `fut` does, if there's one: never otherwise
+async fn complete_maybe(fut: &mut Option<LocalBoxFuture<'static, ()>>) {
+    match fut {
+        Some(fut) => fut.await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::ServerConf;
+    use crate::{
+        error::ConfError,
+        h2::types::{GoAwayDebugData, H2ConnectionError, MAX_GOAWAY_DEBUG_DATA},
+    };
+
+    #[test]
+    fn test_conf_validation() {
+        for conf in [
+            ServerConf::default(),
+            ServerConf::grpc(),
+            ServerConf::edge(),
+            ServerConf::internal(),
+            ServerConf::conformance_test(),
+        ] {
+            conf.validate().unwrap();
+        }
+
+        let res = ServerConf::builder().max_frame_size(1 << 20).build();
+        assert!(matches!(
+            res,
+            Err(ConfError::Exceeds {
+                setting: "max_frame_size",
+                ..
+            })
+        ));
+        let res = ServerConf::builder().max_frame_size(1 << 24).build();
+        assert!(matches!(
+            res,
+            Err(ConfError::OutOfRange {
+                setting: "max_frame_size",
+                ..
+            })
+        ));
+        let res = ServerConf::grpc()
+            .into_builder()
+            .connection_window_size(64 * 1024)
+            .build();
+        assert!(matches!(
+            res,
+            Err(ConfError::Exceeds {
+                setting: "initial_stream_window_size",
+                ..
+            })
+        ));
+        let res = ServerConf::builder().reset_window(Duration::ZERO).build();
+        assert!(matches!(
+            res,
+            Err(ConfError::Zero {
+                setting: "reset_window"
+            })
+        ));
+        // the window doesn't matter without a limit
+        ServerConf::builder()
+            .max_resets(None)
+            .reset_window(Duration::ZERO)
+            .build()
+            .unwrap();
+        let res = ServerConf::builder()
+            .keepalive_interval(Some(Duration::from_secs(10)))
+            .keepalive_timeout(Duration::from_secs(20))
+            .build();
+        assert!(matches!(
+            res,
+            Err(ConfError::Exceeds {
+                setting: "keepalive_timeout",
+                ..
+            })
+        ));
+
+        let conf = ServerConf::internal()
+            .into_builder()
+            .max_frame_size(1 << 20)
+            .initial_stream_window_size(1 << 20)
+            .build()
+            .unwrap();
+        assert_eq!(conf.max_frame_size, 1 << 20);
+    }
+
+    #[test]
+    fn test_goaway_debug_data() {
+        let data = H2ConnectionError::IdleTimeout.goaway_debug_data();
+        assert_eq!(&data[..], b"connection was idle for too long");
+
+        // long reasons get cut, on a char boundary
+        let err =
+            H2ConnectionError::WriteError(std::io::Error::other("é".repeat(MAX_GOAWAY_DEBUG_DATA)));
+        let data = err.goaway_debug_data();
+        assert!(data.len() <= MAX_GOAWAY_DEBUG_DATA);
+        std::str::from_utf8(&data[..]).unwrap();
+
+        // whatever peers send gets escaped, and cut too
+        assert_eq!(
+            GoAwayDebugData(b"bad\nthing\xff").to_string(),
+            "bad\\nthing\u{fffd}"
+        );
+        let long = vec![b'a'; MAX_GOAWAY_DEBUG_DATA + 1];
+        let formatted = GoAwayDebugData(&long).to_string();
+        assert_eq!(formatted.len(), MAX_GOAWAY_DEBUG_DATA + 3);
+        assert!(formatted.ends_with("a..."));
+    }
+}
@@ -1,17 +1,32 @@
 use std::{
     collections::{HashMap, HashSet, VecDeque},
     fmt,
+    time::Duration,
 };
 
-use buffet::Piece;
+use buffet::{Piece, PieceList, RollMut};
 use http::StatusCode;
 use loona_hpack::decoder::DecoderError;
-use tokio::sync::Notify;
-
-use crate::{util::ReadAndParseError, ResponderError, Response};
+use tokio::{sync::Notify, time::Instant};
+use tracing::debug;
+
+use crate::{
+    cancel::Cancellation,
+    memory::{Charge, MemoryBudget},
+    util::ReadAndParseError,
+    Headers, ResponderError, Response,
+};
 
-use super::{body::StreamIncoming, encode::H2EncoderError};
-use loona_h2::{FrameType, KnownErrorCode, Settings, SettingsError, StreamId};
+use super::{
+    body::StreamIncoming,
+    encode::{encode_header_block, queue_header_block, H2EncoderError},
+    priority::Priority,
+    server::MAX_WINDOW_SIZE,
+};
+use loona_h2::{
+    enumflags2::BitFlags, DataFlags, Frame, FrameType, HeadersFlags, KnownErrorCode, Setting,
+    Settings, SettingsError, StreamId,
+};
 
 pub(crate) struct ConnState {
     pub(crate) streams: HashMap<StreamId, StreamState>,
@@ -30,8 +45,34 @@ pub(crate) struct ConnState {
     pub(crate) send_data_maybe: Notify,
     pub(crate) streams_with_pending_data: HashSet<StreamId>,
 
+    /// priorities received in PRIORITY_UPDATE frames for streams that
+    /// aren't open yet, cf. <https://www.rfc-editor.org/rfc/rfc9218#section-7.1>
+    pub(crate) early_priority_updates: HashMap<StreamId, Priority>,
+
     pub(crate) incoming_capacity: i64,
     pub(crate) outgoing_capacity: i64,
+
+    /// when we last received a frame from the peer
+    pub(crate) last_frame_received_at: Instant,
+
+    /// the payload of the keep-alive PING we're waiting on an ack for, and
+    /// when we sent it
+    pub(crate) ping_in_flight: Option<([u8; 8], Instant)>,
+
+    /// how many keep-alive PINGs we've sent, used as their payload
+    pub(crate) pings_sent: u64,
+
+    /// since when the connection has had no open streams, if it has none
+    pub(crate) idle_since: Option<Instant>,
+
+    /// how many RST_STREAM frames the peer sent since `resets_since`, cf.
+    /// [crate::h2::ServerConf::max_resets]
+    pub(crate) resets: u32,
+    pub(crate) resets_since: Instant,
+
+    /// what streams charge their decoded headers and queued response bodies
+    /// against, cf. [crate::memory]
+    pub(crate) memory: MemoryBudget,
 }
 
 impl Default for ConnState {
@@ -45,9 +86,18 @@ impl Default for ConnState {
 
             send_data_maybe: Default::default(),
             streams_with_pending_data: Default::default(),
+            early_priority_updates: Default::default(),
 
             incoming_capacity: 0,
             outgoing_capacity: 0,
+
+            last_frame_received_at: Instant::now(),
+            ping_in_flight: None,
+            pings_sent: 0,
+            idle_since: Some(Instant::now()),
+            resets: 0,
+            resets_since: Instant::now(),
+            memory: MemoryBudget::unlimited(),
         };
         s.incoming_capacity = s.self_settings.initial_window_size as _;
         s.outgoing_capacity = s.peer_settings.initial_window_size as _;
@@ -58,13 +108,443 @@ impl Default for ConnState {
 
 impl ConnState {
     /// create a new [StreamOutgoing] based on our current settings
-    pub(crate) fn mk_stream_outgoing(&self) -> StreamOutgoing {
+    pub(crate) fn mk_stream_outgoing(&self, priority: Priority) -> StreamOutgoing {
         StreamOutgoing {
+            interim: Vec::new(),
             headers: HeadersOutgoing::WaitingForHeaders,
             body: BodyOutgoing::StillReceiving(Default::default()),
+            trailers: None,
             capacity: self.peer_settings.initial_window_size as _,
+            priority,
+            cancellation: Default::default(),
+            memory: self.memory.charge(),
+        }
+    }
+
+    /// Queues a piece of body for this stream, sent once flow control allows.
+    /// Errors out if that would go over the memory budget: the stream should
+    /// be reset then.
+    pub(crate) fn push_body_chunk(
+        &mut self,
+        stream_id: StreamId,
+        chunk: Piece,
+    ) -> Result<(), H2StreamError> {
+        let outgoing = match self
+            .streams
+            .get_mut(&stream_id)
+            .and_then(|s| s.outgoing_mut())
+        {
+            None => {
+                // ignore the event then, but at this point we should
+                // tell the sender to stop sending chunks, which is not
+                // possible if they all share the same ev_tx
+                // TODO: make it possible to propagate errors to the sender
+                return Ok(());
+            }
+            Some(outgoing) => outgoing,
+        };
+
+        if !outgoing.memory.try_grow(chunk.len()) {
+            return Err(H2StreamError::ResponseOverMemoryBudget);
+        }
+
+        // FIXME: this isn't great, because, due to biased polling, body pieces can pile
+        // up. when we've collected enough pieces for max frame size, we
+        // should really send them.
+        outgoing.body.push_back(chunk);
+
+        self.streams_with_pending_data.insert(stream_id);
+        if self.outgoing_capacity > 0 && outgoing.capacity > 0 {
+            // worth revisiting then!
+            self.send_data_maybe.notify_one();
+        }
+        Ok(())
+    }
+
+    /// The handler is done with the response body for this stream: we'll
+    /// send END_STREAM once all of it is written, along with `trailers`, if
+    /// any.
+    pub(crate) fn end_body(&mut self, stream_id: StreamId, trailers: Option<Box<Headers>>) {
+        let outgoing = match self
+            .streams
+            .get_mut(&stream_id)
+            .and_then(|s| s.outgoing_mut())
+        {
+            None => return,
+            Some(outgoing) => outgoing,
+        };
+
+        match &mut outgoing.body {
+            BodyOutgoing::StillReceiving(pieces) => {
+                let pieces = std::mem::take(pieces);
+                if pieces.is_empty() {
+                    // we'll need to send a zero-length data frame, or the
+                    // trailers
+                    self.send_data_maybe.notify_one();
+                }
+                outgoing.body = if pieces.is_empty() && trailers.is_some() {
+                    BodyOutgoing::DoneSending
+                } else {
+                    BodyOutgoing::DoneReceiving(pieces)
+                };
+                outgoing.trailers = trailers;
+                debug!(%stream_id, outgoing_body = ?outgoing.body, has_trailers = %outgoing.trailers.is_some(), "got body end");
+                self.streams_with_pending_data.insert(stream_id);
+            }
+            BodyOutgoing::DoneReceiving(_) => {
+                unreachable!("got body end twice")
+            }
+            BodyOutgoing::DoneSending => {
+                unreachable!("got body end after we sent everything")
+            }
+        }
+    }
+
+    /// Queues the HEADERS, DATA and trailer frames that streams have ready,
+    /// as far as flow control windows allow. They must be written in order,
+    /// with [ConnState::on_frame_written] called for each.
+    pub(crate) fn queue_frames(
+        &mut self,
+        hpack_enc: &mut loona_hpack::Encoder<'static>,
+        out_scratch: &mut RollMut,
+    ) -> std::io::Result<Vec<(Frame, PieceList)>> {
+        let mut not_pending: HashSet<StreamId> = Default::default();
+
+        // this vec exists for borrow-checker reasons: we can't
+        // borrow self mutably twice in 'each_stream
+        // TODO: merge those frames! do a single writev_all call!
+        let mut frames: Vec<(Frame, PieceList)> = vec![];
+
+        let max_fram = self.peer_settings.max_frame_size as usize;
+
+        // RFC 9218 scheduling: lower urgency first, then in stream ID order.
+        let mut streams_with_pending_data: Vec<(StreamId, Priority)> = self
+            .streams_with_pending_data
+            .iter()
+            .map(|id| {
+                let priority = self
+                    .streams
+                    .get_mut(id)
+                    .and_then(|ss| ss.outgoing_mut())
+                    .map(|og| og.priority)
+                    .unwrap_or_default();
+                (*id, priority)
+            })
+            .collect();
+        streams_with_pending_data.sort_by_key(|(id, priority)| (priority.urgency, *id));
+
+        // connection capacity left for this turn: it's only updated in
+        // `write_frame`, after we've queued frames for every stream.
+        let mut conn_capacity = self.outgoing_capacity;
+
+        // set when an incremental stream yielded after a single frame, so that
+        // streams of the same urgency get a turn, but less urgent ones don't.
+        let mut yielded_at_urgency: Option<u8> = None;
+
+        'each_stream: for (id, priority) in streams_with_pending_data {
+            if conn_capacity <= 0 {
+                // that's all we can do
+                break 'each_stream;
+            }
+
+            if let Some(urgency) = yielded_at_urgency {
+                if priority.urgency > urgency {
+                    // less urgent streams will have to wait for the next turn
+                    self.send_data_maybe.notify_one();
+                    break 'each_stream;
+                }
+            }
+
+            let outgoing = self.streams
+                .get_mut(&id)
+                .and_then(|ss| ss.outgoing_mut())
+                .expect("stream should not be in streams_with_pending_data if it's already closed / not in an outgoing state");
+
+            debug!(%conn_capacity, strm_cap = %outgoing.capacity, %max_fram, ?priority, "ready to write");
+
+            for res in outgoing.interim.drain(..) {
+                debug!(status = %res.status, "writing informational headers...");
+                let block = encode_header_block(
+                    hpack_enc,
+                    out_scratch,
+                    &[(b":status", res.status.as_str().as_bytes())],
+                    &res.headers,
+                )?;
+                queue_header_block(&mut frames, id, block, max_fram, false);
+            }
+
+            if outgoing.headers.has_more_to_write() {
+                let res = match std::mem::take(&mut outgoing.headers) {
+                    HeadersOutgoing::WroteNone(res) => res,
+                    headers => {
+                        debug!("waiting for headers...");
+                        outgoing.headers = headers;
+
+                        // shouldn't be pending then should it?
+                        not_pending.insert(id);
+                        continue 'each_stream;
+                    }
+                };
+
+                debug!("writing headers...");
+                let block = encode_header_block(
+                    hpack_enc,
+                    out_scratch,
+                    &[(b":status", res.status.as_str().as_bytes())],
+                    &res.headers,
+                )?;
+                queue_header_block(&mut frames, id, block, max_fram, false);
+            }
+
+            let capacity = conn_capacity.min(outgoing.capacity).max(0) as usize;
+            // bytes written this turn, possibly over multiple frames
+            let mut total_bytes_written = 0;
+
+            if outgoing.body.has_more_to_write() {
+                'queue_body_frames: while total_bytes_written < capacity {
+                    // send as much body data as we can, respecting max frame size and
+                    // connection / stream capacity
+                    let mut plist = PieceList::default();
+                    let mut frame_len = 0;
+
+                    'build_frame: loop {
+                        let piece = match outgoing.body.pop_front() {
+                            None => break 'build_frame,
+                            Some(piece) => piece,
+                        };
+
+                        // do we need to split the piece because we don't have
+                        // enough capacity left / we hit the max frame size?
+                        let piece_len = piece.len();
+                        debug!(%piece_len, "popped a piece");
+
+                        let fram_size_if_full_piece = frame_len + piece_len;
+
+                        let cap_left = capacity - total_bytes_written;
+                        let max_this_fram = max_fram.min(cap_left);
+
+                        if fram_size_if_full_piece > max_this_fram {
+                            // we can't fit this piece in the current frame, so
+                            // we have to split it
+                            let write_size = max_this_fram - frame_len;
+                            let (written, requeued) = piece.split_at(write_size);
+                            frame_len += write_size;
+                            debug!(written_len = %written.len(), requeued_len = %requeued.len(), "splitting piece");
+
+                            plist.push_back(written);
+                            outgoing.body.push_front(requeued);
+
+                            break 'build_frame;
+                        } else {
+                            // we can write the full piece
+                            let write_size = piece_len;
+                            frame_len += write_size;
+
+                            plist.push_back(piece);
+                        }
+                    }
+
+                    let mut flags: BitFlags<DataFlags> = Default::default();
+                    if outgoing.body.might_receive_more() || outgoing.trailers.is_some() {
+                        if frame_len == 0 {
+                            // the only time we want to send a zero-length frame
+                            // is if we have to send END_STREAM separately from
+                            // the last chunk, and trailers carry it if there
+                            // are any.
+                            break 'queue_body_frames;
+                        }
+                    } else {
+                        flags |= DataFlags::EndStream;
+                    }
+
+                    let frame = Frame::new(FrameType::Data(flags), id);
+                    debug!(?frame, %frame_len, "queuing");
+                    frames.push((frame, plist));
+                    total_bytes_written += frame_len;
+
+                    if flags.contains(DataFlags::EndStream) {
+                        break 'queue_body_frames;
+                    }
+
+                    if priority.incremental && total_bytes_written < capacity {
+                        // incremental streams of the same urgency take turns,
+                        // one frame at a time.
+                        yielded_at_urgency = Some(priority.urgency);
+                        self.send_data_maybe.notify_one();
+                        break 'queue_body_frames;
+                    }
+                }
+                outgoing.memory.shrink(total_bytes_written);
+            }
+
+            if !outgoing.body.has_more_to_write() {
+                if let Some(trailers) = outgoing.trailers.take() {
+                    debug!("writing trailers...");
+                    let block = encode_header_block(hpack_enc, out_scratch, &[], &trailers)?;
+                    queue_header_block(&mut frames, id, block, max_fram, true);
+                }
+            }
+
+            conn_capacity -= total_bytes_written as i64;
+        }
+
+        for id in not_pending {
+            self.streams_with_pending_data.remove(&id);
+        }
+
+        Ok(frames)
+    }
+
+    /// We won't be sending anything else on this stream
+    fn on_end_stream_written(&mut self, stream_id: StreamId) {
+        self.streams_with_pending_data.remove(&stream_id);
+
+        let mut ss = match self.streams.entry(stream_id) {
+            std::collections::hash_map::Entry::Occupied(entry) => entry,
+            std::collections::hash_map::Entry::Vacant(_) => {
+                unreachable!("wrote END_STREAM for non-existent stream, this should never happen")
+            }
+        };
+
+        match ss.get_mut() {
+            StreamState::Open { .. } => {
+                let incoming = match std::mem::take(ss.get_mut()) {
+                    StreamState::Open { incoming, .. } => incoming,
+                    _ => unreachable!(),
+                };
+                // this avoid having to re-insert the stream in the map
+                *ss.get_mut() = StreamState::HalfClosedLocal { incoming };
+            }
+            _ => {
+                // transition to closed
+                ss.remove();
+                debug!(
+                    "Closed stream {} (wrote END_STREAM), now have {} streams",
+                    stream_id,
+                    self.streams.len()
+                );
+            }
         }
     }
+
+    /// Applies the settings in a (non-ACK) SETTINGS frame from the peer,
+    /// adjusting the windows of open streams if the initial window size
+    /// changed. Returns whether some streams may now send data.
+    pub(crate) fn apply_peer_settings(
+        &mut self,
+        payload: &[u8],
+        hpack_enc: &mut loona_hpack::Encoder<'static>,
+    ) -> Result<bool, H2ConnectionError> {
+        let original_initial_window_size = self.peer_settings.initial_window_size;
+        let s = &mut self.peer_settings;
+
+        Settings::parse(payload, |code, value| {
+            s.apply(code, value)?;
+            match code {
+                Setting::HeaderTableSize => {
+                    hpack_enc.set_max_table_size(value as _);
+                }
+                _ => {
+                    // nothing to do
+                }
+            }
+            Ok(())
+        })
+        .map_err(H2ConnectionError::BadSettingValue)?;
+
+        let initial_window_size_delta =
+            (s.initial_window_size as i64) - (original_initial_window_size as i64);
+
+        let mut maybe_send_data = false;
+        if initial_window_size_delta != 0 {
+            // apply that delta to all streams
+            for (id, stream) in self.streams.iter_mut() {
+                if let Some(outgoing) = stream.outgoing_mut() {
+                    let next_cap = outgoing.capacity + initial_window_size_delta;
+                    if next_cap > MAX_WINDOW_SIZE {
+                        return Err(H2ConnectionError::StreamWindowSizeOverflowDueToSettings {
+                            stream_id: *id,
+                        });
+                    }
+                    // if capacity was negative or zero, and is now greater than zero,
+                    // we need to maybe send data
+                    if next_cap > 0 && outgoing.capacity <= 0 {
+                        debug!(?id, %next_cap, "stream capacity was <= 0, now > 0");
+                        maybe_send_data = true;
+                    }
+                    outgoing.capacity = next_cap;
+                }
+            }
+        }
+
+        Ok(maybe_send_data)
+    }
+
+    /// Updates flow control windows and stream states for a frame we're
+    /// about to write
+    pub(crate) fn on_frame_written(&mut self, frame: &Frame, payload_len: usize) {
+        match &frame.frame_type {
+            FrameType::Data(flags) => {
+                let mut ss = match self.streams.entry(frame.stream_id) {
+                    std::collections::hash_map::Entry::Occupied(entry) => entry,
+                    std::collections::hash_map::Entry::Vacant(_) => unreachable!(
+                        "writing DATA frame for non-existent stream, this should never happen"
+                    ),
+                };
+
+                // update stream flow control window
+                {
+                    let outgoing = match ss.get_mut().outgoing_mut() {
+                        Some(og) => og,
+                        None => {
+                            unreachable!("writing DATA frame for stream in the wrong state")
+                        }
+                    };
+                    let payload_len: u32 = payload_len.try_into().unwrap();
+                    let next_cap = outgoing.capacity - payload_len as i64;
+
+                    if next_cap < 0 {
+                        unreachable!(
+                            "should never write a frame that makes the stream capacity negative: outgoing.capacity = {}, payload_len = {}",
+                            outgoing.capacity, payload_len
+                        )
+                    }
+                    outgoing.capacity = next_cap;
+                }
+
+                // now update connection flow control window
+                {
+                    let payload_len: u32 = payload_len.try_into().unwrap();
+                    let next_cap = self.outgoing_capacity - payload_len as i64;
+
+                    if next_cap < 0 {
+                        unreachable!(
+                            "should never write a frame that makes the connection capacity negative: outgoing_capacity = {}, payload_len = {}",
+                            self.outgoing_capacity, payload_len
+                        )
+                    }
+                    self.outgoing_capacity = next_cap;
+                }
+
+                if flags.contains(DataFlags::EndStream) {
+                    self.on_end_stream_written(frame.stream_id);
+                }
+            }
+            FrameType::Headers(flags) => {
+                if flags.contains(HeadersFlags::EndStream) {
+                    // that's trailers
+                    self.on_end_stream_written(frame.stream_id);
+                }
+            }
+            FrameType::Settings(_) => {
+                // TODO: keep track of whether our new settings have been
+                // acknowledged
+            }
+            _ => {
+                // muffin.
+            }
+        };
+    }
 }
 
 // cf. RFC 9113, 5.1 Stream States:
@@ -146,12 +626,27 @@ impl StreamState {
 }
 
 pub(crate) struct StreamOutgoing {
+    // informational (1xx) responses, written before `headers`
+    pub(crate) interim: Vec<Response>,
     pub(crate) headers: HeadersOutgoing,
     pub(crate) body: BodyOutgoing,
 
+    // sent once the body is, with END_STREAM
+    pub(crate) trailers: Option<Box<Headers>>,
+
     // window size of the stream, ie. how many bytes
     // we can send to the receiver before waiting.
     pub(crate) capacity: i64,
+
+    // how this stream's DATA frames are scheduled relative to other streams
+    pub(crate) priority: Priority,
+
+    // fired if the stream goes away before the handler is done responding
+    pub(crate) cancellation: Cancellation,
+
+    // decoded request headers and queued body pieces, released as the body
+    // is sent, and all at once when the stream is done
+    pub(crate) memory: Charge,
 }
 
 #[derive(Default)]
@@ -159,11 +654,11 @@ pub(crate) enum HeadersOutgoing {
     // We have not yet sent any headers, and are waiting for the user to send them
     WaitingForHeaders,
 
-    // The user gave us headers to send, but we haven't started yet
-    WroteNone(Piece),
-
-    // We have sent some headers, but not all (we're still sending CONTINUATION frames)
-    WroteSome(Piece),
+    // The user gave us headers to send, but we haven't started yet. They're
+    // only HPACK-encoded when their frames are queued: the encoder's dynamic
+    // table is shared by all streams, so header blocks must be encoded in the
+    // order they're written.
+    WroteNone(Box<Response>),
 
     // We've sent everything
     #[default]
@@ -176,19 +671,9 @@ impl HeadersOutgoing {
         match self {
             HeadersOutgoing::WaitingForHeaders => true,
             HeadersOutgoing::WroteNone(_) => true,
-            HeadersOutgoing::WroteSome(_) => true,
             HeadersOutgoing::WroteAll => false,
         }
     }
-
-    #[inline(always)]
-    pub(crate) fn take_piece(&mut self) -> Piece {
-        match std::mem::take(self) {
-            Self::WroteNone(piece) => piece,
-            Self::WroteSome(piece) => piece,
-            _ => Piece::empty(),
-        }
-    }
 }
 
 pub(crate) enum BodyOutgoing {
@@ -238,6 +723,16 @@ impl BodyOutgoing {
         }
     }
 
+    /// How many bytes are waiting to be sent
+    pub(crate) fn queued_len(&self) -> usize {
+        match self {
+            BodyOutgoing::StillReceiving(pieces) | BodyOutgoing::DoneReceiving(pieces) => {
+                pieces.iter().map(|p| p.len()).sum()
+            }
+            BodyOutgoing::DoneSending => 0,
+        }
+    }
+
     #[inline(always)]
     pub(crate) fn pop_front(&mut self) -> Option<Piece> {
         match self {
@@ -369,6 +864,17 @@ pub enum H2ConnectionError {
     #[error("client sent a push promise frame, clients aren't allowed to do that, cf. RFC9113 section 8.4")]
     ClientSentPushPromise,
 
+    #[error(
+        "server sent a push promise frame, but we disabled server push, cf. RFC9113 section 8.4"
+    )]
+    PushPromiseWhileDisabled,
+
+    #[error("received {frame_type:?} frame for idle stream {stream_id}")]
+    FrameForIdleStream {
+        frame_type: FrameType,
+        stream_id: StreamId,
+    },
+
     #[error("received window update for unknown/closed stream {stream_id}")]
     WindowUpdateForUnknownOrClosedStream { stream_id: StreamId },
 
@@ -384,6 +890,9 @@ pub enum H2ConnectionError {
     #[error("H2 responder error: {0:?}")]
     ResponderError(#[from] ResponderError<H2EncoderError>),
 
+    #[error("header block for stream {stream_id} is larger than {max_len} bytes")]
+    HeaderBlockTooLarge { stream_id: StreamId, max_len: usize },
+
     #[error("received rst frame for unknown stream")]
     RstStreamForUnknownStream { stream_id: StreamId },
 
@@ -417,11 +926,29 @@ pub enum H2ConnectionError {
     #[error("received initial window size settings update that made the connection window size overflow")]
     StreamWindowSizeOverflowDueToSettings { stream_id: StreamId },
 
+    #[error("received priority update frame with non-zero stream id")]
+    PriorityUpdateWithNonZeroStreamId { stream_id: StreamId },
+
+    #[error("received priority update frame with invalid length {len}")]
+    PriorityUpdateInvalidLength { len: u32 },
+
+    #[error("received priority update frame for stream 0 (connection-wide)")]
+    PriorityUpdateForStreamZero,
+
     #[error("received window update frame with invalid length {len}")]
     WindowUpdateInvalidLength { len: usize },
 
     #[error("bad setting value: {0}")]
     BadSettingValue(SettingsError),
+
+    #[error("keep-alive ping was not acknowledged in time")]
+    KeepaliveTimeout,
+
+    #[error("connection was idle for too long")]
+    IdleTimeout,
+
+    #[error("client reset more than {max_resets} streams within {window:?}")]
+    TooManyResets { max_resets: u32, window: Duration },
 }
 
 impl H2ConnectionError {
@@ -444,8 +971,14 @@ impl H2ConnectionError {
             }) => KnownErrorCode::FlowControlError,
             // compression errors
             H2ConnectionError::HpackDecodingError(_) => KnownErrorCode::CompressionError,
+            // the peer is making us buffer too much
+            H2ConnectionError::HeaderBlockTooLarge { .. } => KnownErrorCode::EnhanceYourCalm,
+            H2ConnectionError::TooManyResets { .. } => KnownErrorCode::EnhanceYourCalm,
             // stream closed error
             H2ConnectionError::StreamClosed { .. } => KnownErrorCode::StreamClosed,
+            // not really errors: we're just done with this connection
+            H2ConnectionError::KeepaliveTimeout => KnownErrorCode::NoError,
+            H2ConnectionError::IdleTimeout => KnownErrorCode::NoError,
             // protocol errors
             H2ConnectionError::PaddedFrameTooShort { .. } => KnownErrorCode::ProtocolError,
             H2ConnectionError::StreamSpecificFrameToConnection { .. } => {
@@ -454,6 +987,39 @@ impl H2ConnectionError {
             _ => KnownErrorCode::ProtocolError,
         }
     }
+
+    /// A short reason for closing the connection over this error, to send as
+    /// GOAWAY debug data
+    pub(crate) fn goaway_debug_data(&self) -> Piece {
+        let mut reason = self.to_string();
+        if reason.len() > MAX_GOAWAY_DEBUG_DATA {
+            let mut end = MAX_GOAWAY_DEBUG_DATA;
+            while !reason.is_char_boundary(end) {
+                end -= 1;
+            }
+            reason.truncate(end);
+        }
+        reason.into_bytes().into()
+    }
+}
+
+/// How much GOAWAY debug data we send, and how much of the peer's we log:
+/// enough for a reason, not enough for a dump
+pub(crate) const MAX_GOAWAY_DEBUG_DATA: usize = 256;
+
+/// Formats the debug data of a GOAWAY frame for logs: as (lossy) UTF-8,
+/// with control characters escaped, cut at [MAX_GOAWAY_DEBUG_DATA] bytes
+pub(crate) struct GoAwayDebugData<'a>(pub(crate) &'a [u8]);
+
+impl fmt::Display for GoAwayDebugData<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let data = &self.0[..self.0.len().min(MAX_GOAWAY_DEBUG_DATA)];
+        write!(f, "{}", String::from_utf8_lossy(data).escape_debug())?;
+        if data.len() < self.0.len() {
+            f.write_str("...")?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -471,6 +1037,15 @@ pub(crate) enum H2StreamError {
     #[error("refused stream (would exceed max concurrent streams)")]
     RefusedStream,
 
+    #[error("refused stream (its headers would exceed the memory budget)")]
+    HeadersOverMemoryBudget,
+
+    #[error("refused stream (we're shutting down, and said so in a GOAWAY frame)")]
+    ShuttingDown,
+
+    #[error("queued response body would exceed the memory budget")]
+    ResponseOverMemoryBudget,
+
     #[error("trailers must have EndStream flag set")]
     TrailersNotEndStream,
 
@@ -489,8 +1064,14 @@ pub(crate) enum H2StreamError {
     #[error("bad request: {0}")]
     BadRequest(&'static str),
 
+    #[error("malformed response: {0}")]
+    MalformedResponse(&'static str),
+
     #[error("stream reset")]
     Cancel,
+
+    #[error("handler failed mid-response")]
+    HandlerFailed,
 }
 
 impl H2StreamError {
@@ -500,10 +1081,14 @@ impl H2StreamError {
 
         match self {
             Cancel => Code::Cancel,
+            HandlerFailed => Code::InternalError,
             // stream closed error
             StreamClosed => Code::StreamClosed,
             // stream refused error
             RefusedStream => Code::RefusedStream,
+            HeadersOverMemoryBudget => Code::RefusedStream,
+            ShuttingDown => Code::RefusedStream,
+            ResponseOverMemoryBudget => Code::InternalError,
             // frame size errors
             InvalidPriorityFrameSize { .. } => Code::FrameSizeError,
             InvalidRstStreamFrameSize { .. } => Code::FrameSizeError,
@@ -530,6 +1115,10 @@ pub(crate) enum H2EventPayload {
     Headers(Response),
     BodyChunk(Piece),
     BodyEnd,
+    /// ends the body, like [H2EventPayload::BodyEnd], but with trailers
+    Trailers(Box<Headers>),
+    /// the handler gave up on the response after sending some of it
+    Reset,
 }
 
 impl fmt::Debug for H2EventPayload {
@@ -538,6 +1127,8 @@ impl fmt::Debug for H2EventPayload {
             Self::Headers(_) => f.debug_tuple("Headers").finish(),
             Self::BodyChunk(_) => f.debug_tuple("BodyChunk").finish(),
             Self::BodyEnd => write!(f, "BodyEnd"),
+            Self::Trailers(_) => f.debug_tuple("Trailers").finish(),
+            Self::Reset => write!(f, "Reset"),
         }
     }
 }
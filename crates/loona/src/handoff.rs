@@ -0,0 +1,243 @@
+//! Handing listening sockets over to a new instance of the server, so that
+//! it can be restarted (e.g. upgraded) without refusing connections.
+//!
+//! The old instance duplicates its listeners (cf.
+//! [buffet::net::TcpListener::export]) and hands them over, either when
+//! spawning the new instance ([pass_to_command], through the environment,
+//! like systemd's socket activation does), or to an instance that's already
+//! running, over a unix socket ([send_listeners]). The new instance picks
+//! them up ([listeners_from_env], [recv_listeners]) and starts serving, then
+//! the old one calls [crate::server::ServerHandle::shutdown]: it stops
+//! accepting, and finishes serving the connections it has.
+//!
+//! Both instances share the listen queue: connections that arrive during
+//! the handover wait there until either of them accepts them, nothing gets
+//! refused.
+//!
+//! Everything here is blocking, and meant to be done once, at startup or
+//! restart time.
+
+use std::{
+    io::{self, IoSlice, IoSliceMut, Read, Write},
+    os::{
+        fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd},
+        unix::{net::UnixStream, process::CommandExt},
+    },
+    process::Command,
+};
+
+use buffet::net::{TcpListener, UnixListener};
+use nix::sys::socket::{recvmsg, sendmsg, ControlMessage, ControlMessageOwned, MsgFlags};
+
+/// The first file descriptor listeners are passed as, cf. <https://www.freedesktop.org/software/systemd/man/latest/sd_listen_fds.html>
+const LISTEN_FDS_START: RawFd = 3;
+
+/// How many listeners [recv_listeners] accepts at once
+pub const MAX_LISTENERS: usize = 64;
+
+/// A listening socket handed over by another process, with the name it was
+/// given there.
+#[derive(Debug)]
+pub struct InheritedListener {
+    pub name: String,
+    pub fd: OwnedFd,
+}
+
+impl InheritedListener {
+    pub fn into_tcp(self) -> io::Result<TcpListener> {
+        TcpListener::from_listening_fd(self.fd)
+    }
+
+    pub fn into_unix(self) -> io::Result<UnixListener> {
+        UnixListener::from_listening_fd(self.fd)
+    }
+}
+
+/// Arranges for `cmd` to inherit `listeners`, in the environment format
+/// [listeners_from_env] reads: starting at file descriptor 3, with
+/// `LISTEN_FDS` and `LISTEN_FDNAMES` set. The file descriptors are closed
+/// in this process once `cmd` is dropped.
+///
+/// Panics if a name contains a `:`, which separates names.
+pub fn pass_to_command(cmd: &mut Command, listeners: Vec<(String, OwnedFd)>) {
+    let names: Vec<&str> = listeners.iter().map(|(name, _)| name.as_str()).collect();
+    assert!(
+        names.iter().all(|name| !name.contains(':')),
+        "listener names must not contain ':'"
+    );
+    cmd.env("LISTEN_FDS", listeners.len().to_string())
+        .env("LISTEN_FDNAMES", names.join(":"))
+        // we can't know the child's PID before it starts
+        .env_remove("LISTEN_PID");
+
+    let fds: Vec<OwnedFd> = listeners.into_iter().map(|(_, fd)| fd).collect();
+    let num_fds = fds.len() as RawFd;
+    unsafe {
+        cmd.pre_exec(move || {
+            // move every fd out of the way first, so we don't overwrite
+            // one that's already in the `3..3+n` range
+            let mut moved = Vec::with_capacity(fds.len());
+            for fd in &fds {
+                let tmp = libc::fcntl(
+                    fd.as_raw_fd(),
+                    libc::F_DUPFD_CLOEXEC,
+                    LISTEN_FDS_START + num_fds,
+                );
+                if tmp < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                moved.push(tmp);
+            }
+            // `dup2` clears `FD_CLOEXEC`, so these survive the exec
+            for (i, tmp) in moved.into_iter().enumerate() {
+                if libc::dup2(tmp, LISTEN_FDS_START + i as RawFd) < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                libc::close(tmp);
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Takes the listeners this process was started with, if any: cf.
+/// [pass_to_command] (systemd passes them the same way). The environment
+/// variables are removed, so that child processes don't think they're for
+/// them, and a second call returns nothing.
+///
+/// Since it modifies the environment, this should be called early, before
+/// other threads are spawned.
+pub fn listeners_from_env() -> io::Result<Vec<InheritedListener>> {
+    let Ok(num_fds) = std::env::var("LISTEN_FDS") else {
+        return Ok(vec![]);
+    };
+    let pid = std::env::var("LISTEN_PID").ok();
+    let names = std::env::var("LISTEN_FDNAMES").unwrap_or_default();
+    for var in ["LISTEN_FDS", "LISTEN_PID", "LISTEN_FDNAMES"] {
+        std::env::remove_var(var);
+    }
+
+    if let Some(pid) = pid {
+        if pid != std::process::id().to_string() {
+            // meant for some other process, our parent maybe
+            return Ok(vec![]);
+        }
+    }
+
+    let num_fds: RawFd = num_fds.parse().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid LISTEN_FDS: {num_fds:?}"),
+        )
+    })?;
+    let mut names = names.split(':');
+
+    let mut listeners = Vec::with_capacity(num_fds as usize);
+    for fd in LISTEN_FDS_START..LISTEN_FDS_START + num_fds {
+        // don't leak them into our own children
+        if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        listeners.push(InheritedListener {
+            name: names.next().unwrap_or_default().to_string(),
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+        });
+    }
+    Ok(listeners)
+}
+
+/// Sends `listeners` over `stream`, to a process calling [recv_listeners]
+/// on the other end, with `SCM_RIGHTS`. Names must not contain newlines.
+pub fn send_listeners(stream: &UnixStream, listeners: &[(&str, BorrowedFd<'_>)]) -> io::Result<()> {
+    if listeners.is_empty() || listeners.len() > MAX_LISTENERS {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("can send 1 to {MAX_LISTENERS} listeners"),
+        ));
+    }
+    if listeners.iter().any(|(name, _)| name.contains('\n')) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "listener names must not contain newlines",
+        ));
+    }
+
+    // one line per listener: there's always at least one byte to carry the
+    // file descriptors.
+    let mut names = String::new();
+    for (name, _) in listeners {
+        names.push_str(name);
+        names.push('\n');
+    }
+    let fds: Vec<RawFd> = listeners.iter().map(|(_, fd)| fd.as_raw_fd()).collect();
+
+    let sent = sendmsg::<()>(
+        stream.as_raw_fd(),
+        &[IoSlice::new(names.as_bytes())],
+        &[ControlMessage::ScmRights(&fds)],
+        MsgFlags::empty(),
+        None,
+    )?;
+    { stream }.write_all(&names.as_bytes()[sent..])
+}
+
+/// Receives listeners sent by [send_listeners] over `stream`
+pub fn recv_listeners(stream: &UnixStream) -> io::Result<Vec<InheritedListener>> {
+    let mut buf = vec![0u8; 4096];
+    let mut cmsg_buf = nix::cmsg_space!([RawFd; MAX_LISTENERS]);
+
+    let mut fds = vec![];
+    let (bytes, truncated) = {
+        let mut iov = [IoSliceMut::new(&mut buf)];
+        let msg = recvmsg::<()>(
+            stream.as_raw_fd(),
+            &mut iov,
+            Some(&mut cmsg_buf),
+            MsgFlags::MSG_CMSG_CLOEXEC,
+        )?;
+        for cmsg in msg.cmsgs()? {
+            if let ControlMessageOwned::ScmRights(received) = cmsg {
+                fds.extend(
+                    received
+                        .into_iter()
+                        .map(|fd| unsafe { OwnedFd::from_raw_fd(fd) }),
+                );
+            }
+        }
+        (msg.bytes, msg.flags.contains(MsgFlags::MSG_CTRUNC))
+    };
+    let mut names = buf[..bytes].to_vec();
+
+    if truncated {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("received more than {MAX_LISTENERS} listeners"),
+        ));
+    }
+    if fds.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "no listeners received",
+        ));
+    }
+
+    // the names may not have made it in one go
+    while names.iter().filter(|&&b| b == b'\n').count() < fds.len() {
+        let n = { stream }.read(&mut buf)?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        names.extend_from_slice(&buf[..n]);
+    }
+    let names = String::from_utf8(names)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid listener names"))?;
+
+    Ok(names
+        .lines()
+        .zip(fds)
+        .map(|(name, fd)| InheritedListener {
+            name: name.to_string(),
+            fd,
+        })
+        .collect())
+}
@@ -0,0 +1,349 @@
+//! Looking inside a running [Server](crate::server::Server): which
+//! connections are live and, for HTTP/2, what state their streams are in,
+//! how much flow-control window is left on either side, and how much
+//! response data is queued. That's usually what you need to figure out why
+//! a stream is stuck.
+//!
+//! Get a [ServerDump] from [ServerHandle::dump], or serve it as JSON with
+//! [DebugEndpoint], ideally on a listener only operators can reach.
+
+use std::{fmt::Write, net::SocketAddr, time::Duration};
+
+use http::{header, StatusCode};
+use tokio::sync::oneshot;
+
+use crate::{
+    error::NeverError,
+    server::{Proto, ServerHandle, ServerStats},
+    Body, ConnectionInfo, Encoder, ExpectResponseHeaders, Headers, Request, Responder,
+    ResponderOrBodyError, Response, ResponseDone, ServerDriver, SinglePieceBody,
+};
+
+/// How long [ServerHandle::dump] waits for each HTTP/2 connection to describe
+/// itself. Connections are only asked between two events, so one that's
+/// stuck, e.g. writing to a peer that doesn't read, won't answer.
+pub const H2_DUMP_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How an HTTP/2 connection is asked to describe itself
+pub(crate) type H2DumpRequest = oneshot::Sender<H2ConnectionDump>;
+
+/// A snapshot of a [Server](crate::server::Server), cf. [ServerHandle::dump]
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ServerDump {
+    pub stats: ServerStats,
+
+    /// Live connections, oldest first
+    pub connections: Vec<ConnectionDump>,
+}
+
+/// A live connection
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ConnectionDump {
+    /// Identifies the connection for as long as the server runs
+    pub id: u64,
+    pub info: ConnectionInfo,
+    pub proto: Proto,
+
+    /// How long ago the connection was accepted
+    pub age: Duration,
+
+    /// Bytes of buffer memory the connection holds on to, cf.
+    /// [crate::memory]
+    pub memory_used: usize,
+
+    /// What's going on in the connection, for HTTP/2 connections that
+    /// answered within [H2_DUMP_TIMEOUT]
+    pub h2: Option<H2ConnectionDump>,
+}
+
+/// The state of an HTTP/2 connection
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct H2ConnectionDump {
+    /// The highest stream ID the peer opened
+    pub last_stream_id: u32,
+
+    /// How many bytes of DATA we may still send on the connection, across
+    /// all streams
+    pub send_window: i64,
+
+    /// How many bytes of DATA the peer may still send on the connection
+    pub recv_window: i64,
+
+    /// Whether the peer sent a GOAWAY
+    pub goaway_received: bool,
+
+    /// How many bytes of response bodies are waiting for flow-control
+    /// window, across all streams
+    pub queued_bytes: u64,
+
+    /// How long the connection has had no open streams, if it has none
+    pub idle_for: Option<Duration>,
+
+    /// Open (and half-closed) streams, by ID
+    pub streams: Vec<H2StreamDump>,
+
+    /// The dynamic table used to decode the peer's header blocks
+    pub hpack_decoder: HpackTableDump,
+
+    /// The dynamic table used to encode our header blocks
+    pub hpack_encoder: HpackTableDump,
+}
+
+/// An HTTP/2 stream that's not closed yet
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct H2StreamDump {
+    pub id: u32,
+    pub state: H2StreamState,
+
+    /// How many bytes of DATA we may still send on the stream, if we're
+    /// still sending
+    pub send_window: Option<i64>,
+
+    /// How many bytes of DATA the peer may still send on the stream, if
+    /// it's still sending
+    pub recv_window: Option<i64>,
+
+    /// How many bytes of request body were received so far, if the peer is
+    /// still sending
+    pub received_bytes: Option<u64>,
+
+    /// Where the response is at, if we're still sending
+    pub response: Option<ResponseProgress>,
+
+    /// How many bytes of the response body are waiting for flow-control
+    /// window
+    pub queued_bytes: u64,
+}
+
+/// cf. <https://httpwg.org/specs/rfc9113.html#StreamStates>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum H2StreamState {
+    Open,
+
+    /// The peer is done sending
+    HalfClosedRemote,
+
+    /// We're done sending
+    HalfClosedLocal,
+}
+
+/// How far along a response is, on an HTTP/2 stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ResponseProgress {
+    /// The handler hasn't written the response headers yet
+    WaitingForHeaders,
+
+    /// The response headers are written, but not sent yet
+    HeadersQueued,
+
+    /// The handler is writing the body
+    WritingBody,
+
+    /// The handler is done, some of the body has yet to be sent
+    FlushingBody,
+}
+
+/// The occupancy of an HPACK dynamic table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct HpackTableDump {
+    pub entries: usize,
+
+    /// In octets, as HPACK counts them (32 per entry, plus its name and
+    /// value)
+    pub size: usize,
+    pub max_size: usize,
+}
+
+impl HpackTableDump {
+    pub(crate) fn of_decoder(dec: &loona_hpack::Decoder<'_>) -> Self {
+        Self {
+            entries: dec.dynamic_table_len(),
+            size: dec.dynamic_table_size(),
+            max_size: dec.dynamic_table_max_size(),
+        }
+    }
+
+    pub(crate) fn of_encoder(enc: &loona_hpack::Encoder<'_>) -> Self {
+        Self {
+            entries: enc.dynamic_table_len(),
+            size: enc.dynamic_table_size(),
+            max_size: enc.dynamic_table_max_size(),
+        }
+    }
+}
+
+impl ServerDump {
+    /// Formats the dump as JSON, e.g. to serve it, cf. [DebugEndpoint].
+    /// Durations are in (fractional) seconds.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        let stats = &self.stats;
+        _ = write!(
+            out,
+            r#"{{"stats":{{"connections_accepted":{},"connections_active":{},"connection_errors":{},"accept_errors":{},"connections_refused":{},"memory_used":{}}},"connections":["#,
+            stats.connections_accepted,
+            stats.connections_active,
+            stats.connection_errors,
+            stats.accept_errors,
+            stats.connections_refused,
+            stats.memory_used
+        );
+        for (i, conn) in self.connections.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            conn.write_json(&mut out);
+        }
+        out.push_str("]}");
+        out
+    }
+}
+
+impl ConnectionDump {
+    fn write_json(&self, out: &mut String) {
+        let proto = match self.proto {
+            Proto::H1 => "h1",
+            Proto::H2 => "h2",
+        };
+        _ = write!(
+            out,
+            r#"{{"id":{},"proto":"{proto}","peer_addr":{},"local_addr":{},"age":{},"memory_used":{}"#,
+            self.id,
+            JsonAddr(self.info.peer_addr),
+            JsonAddr(self.info.local_addr),
+            self.age.as_secs_f64(),
+            self.memory_used
+        );
+        if let Some(h2) = &self.h2 {
+            out.push_str(r#","h2":"#);
+            h2.write_json(out);
+        }
+        out.push('}');
+    }
+}
+
+impl H2ConnectionDump {
+    fn write_json(&self, out: &mut String) {
+        _ = write!(
+            out,
+            r#"{{"last_stream_id":{},"send_window":{},"recv_window":{},"goaway_received":{},"queued_bytes":{},"idle_for":{},"hpack_decoder":{},"hpack_encoder":{},"streams":["#,
+            self.last_stream_id,
+            self.send_window,
+            self.recv_window,
+            self.goaway_received,
+            self.queued_bytes,
+            JsonOpt(self.idle_for.map(|d| d.as_secs_f64())),
+            self.hpack_decoder,
+            self.hpack_encoder,
+        );
+        for (i, stream) in self.streams.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            let state = match stream.state {
+                H2StreamState::Open => "open",
+                H2StreamState::HalfClosedRemote => "half_closed_remote",
+                H2StreamState::HalfClosedLocal => "half_closed_local",
+            };
+            let response = stream.response.map(|r| match r {
+                ResponseProgress::WaitingForHeaders => r#""waiting_for_headers""#,
+                ResponseProgress::HeadersQueued => r#""headers_queued""#,
+                ResponseProgress::WritingBody => r#""writing_body""#,
+                ResponseProgress::FlushingBody => r#""flushing_body""#,
+            });
+            _ = write!(
+                out,
+                r#"{{"id":{},"state":"{state}","send_window":{},"recv_window":{},"received_bytes":{},"response":{},"queued_bytes":{}}}"#,
+                stream.id,
+                JsonOpt(stream.send_window),
+                JsonOpt(stream.recv_window),
+                JsonOpt(stream.received_bytes),
+                JsonOpt(response),
+                stream.queued_bytes,
+            );
+        }
+        out.push_str("]}");
+    }
+}
+
+impl std::fmt::Display for HpackTableDump {
+    /// As JSON
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            r#"{{"entries":{},"size":{},"max_size":{}}}"#,
+            self.entries, self.size, self.max_size
+        )
+    }
+}
+
+/// Formats as the value, or `null`
+struct JsonOpt<T>(Option<T>);
+
+impl<T: std::fmt::Display> std::fmt::Display for JsonOpt<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Some(v) => write!(f, "{v}"),
+            None => f.write_str("null"),
+        }
+    }
+}
+
+/// Formats as a quoted address, or `null`. Addresses don't need escaping.
+struct JsonAddr(Option<SocketAddr>);
+
+impl std::fmt::Display for JsonAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            Some(addr) => write!(f, r#""{addr}""#),
+            None => f.write_str("null"),
+        }
+    }
+}
+
+/// Responds to every request with a [ServerDump] of another server, as
+/// JSON. Serve it with its own [Server](crate::server::Server), on a
+/// listener only operators can reach.
+pub struct DebugEndpoint {
+    handle: ServerHandle,
+}
+
+impl DebugEndpoint {
+    /// Serves dumps of the server `handle` belongs to
+    pub fn new(handle: ServerHandle) -> Self {
+        Self { handle }
+    }
+}
+
+impl<OurEncoder> ServerDriver<OurEncoder> for DebugEndpoint
+where
+    OurEncoder: Encoder,
+{
+    type Error = ResponderOrBodyError<OurEncoder::Error, NeverError>;
+
+    async fn handle(
+        &self,
+        _req: Request,
+        _req_body: &mut impl Body,
+        respond: Responder<OurEncoder, ExpectResponseHeaders>,
+    ) -> Result<Responder<OurEncoder, ResponseDone>, Self::Error> {
+        let json = self.handle.dump().await.to_json();
+        let mut headers = Headers::default();
+        headers.insert(header::CONTENT_TYPE, "application/json".into());
+        headers.insert(header::CACHE_CONTROL, "no-store".into());
+        let res = Response {
+            status: StatusCode::OK,
+            headers,
+            ..Default::default()
+        };
+        let mut body = SinglePieceBody::from(json.into_bytes());
+        respond.write_final_response_with_body(res, &mut body).await
+    }
+}
@@ -16,6 +16,39 @@ pub use http;
 
 pub mod error;
 
+pub mod date;
+
+pub mod proxy_protocol;
+
+pub mod server;
+
+pub mod introspect;
+
+pub mod memory;
+
+pub mod validation;
+
+mod early_data;
+
+pub mod tunnel;
+
+pub mod cancel;
+
+pub mod sse;
+
+pub mod pool;
+
+pub mod dns;
+
+pub mod client;
+
+pub mod files;
+
+pub mod handoff;
+
+#[cfg(feature = "rustls")]
+pub mod tls;
+
 #[allow(async_fn_in_trait)] // we never require Send
 pub trait ServerDriver<OurEncoder>
 where
@@ -30,3 +63,36 @@ where
         respond: Responder<OurEncoder, ExpectResponseHeaders>,
     ) -> Result<Responder<OurEncoder, ResponseDone>, Self::Error>;
 }
+
+impl<OurEncoder, OurDriver> ServerDriver<OurEncoder> for std::rc::Rc<OurDriver>
+where
+    OurEncoder: Encoder,
+    OurDriver: ServerDriver<OurEncoder>,
+{
+    type Error = OurDriver::Error;
+
+    async fn handle(
+        &self,
+        req: Request,
+        req_body: &mut impl Body,
+        respond: Responder<OurEncoder, ExpectResponseHeaders>,
+    ) -> Result<Responder<OurEncoder, ResponseDone>, Self::Error> {
+        (**self).handle(req, req_body, respond).await
+    }
+}
+
+/// Handles the response to a request made with a client, e.g.
+/// [h1::request] or [h2::ClientHandle::request]: informational responses
+/// first, if any, then the final response and its body.
+#[allow(async_fn_in_trait)] // we never require Send
+pub trait ClientDriver {
+    type Return;
+    type Error: std::error::Error + 'static;
+
+    async fn on_informational_response(&mut self, res: Response) -> Result<(), Self::Error>;
+    async fn on_final_response(
+        self,
+        res: Response,
+        body: &mut impl Body,
+    ) -> Result<Self::Return, Self::Error>;
+}
@@ -0,0 +1,207 @@
+//! Keeping track of the buffer memory connections hold on to, so that a
+//! [Server](crate::server::Server) under pressure sheds load instead of
+//! running out of memory.
+//!
+//! Each connection gets a budget of its own, carved out of a global one
+//! shared by all connections of the same [Server](crate::server::Server),
+//! cf. [MemoryLimits]. With a [ShardedServer](crate::server::ShardedServer),
+//! that's one global budget per shard. What's charged against it:
+//!
+//!   * the connection's read buffer
+//!   * for HTTP/2, the decoded request headers of each stream, for as long as
+//!     we're responding on it
+//!   * for HTTP/2, response bodies waiting for flow-control window
+//!
+//! When a charge doesn't fit, something gets shed: new connections are
+//! refused (dropped right after `accept`), new HTTP/2 streams are refused with
+//! REFUSED_STREAM (so clients know they can retry them), and HTTP/2 streams
+//! whose queued response would go over budget are reset.
+
+use std::{cell::Cell, rc::Rc};
+
+/// How much buffer memory connections of a [Server](crate::server::Server)
+/// may hold on to, in bytes. Both are unlimited by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryLimits {
+    /// For any one connection
+    pub per_connection: usize,
+
+    /// For all connections of the server, together. Each shard of a
+    /// [ShardedServer](crate::server::ShardedServer) runs a server of its
+    /// own, so they can use up to this much memory each: divide the limit
+    /// by the number of shards to cap the whole process.
+    pub global: usize,
+}
+
+impl Default for MemoryLimits {
+    fn default() -> Self {
+        Self {
+            per_connection: usize::MAX,
+            global: usize::MAX,
+        }
+    }
+}
+
+/// A number of bytes that can be handed out, possibly nested in a parent
+/// budget: charges count against every budget up the chain.
+#[derive(Clone)]
+pub(crate) struct MemoryBudget {
+    inner: Rc<BudgetInner>,
+}
+
+struct BudgetInner {
+    limit: Cell<usize>,
+    used: Cell<usize>,
+    parent: Option<MemoryBudget>,
+}
+
+impl MemoryBudget {
+    pub(crate) fn new(limit: usize) -> Self {
+        Self {
+            inner: Rc::new(BudgetInner {
+                limit: Cell::new(limit),
+                used: Default::default(),
+                parent: None,
+            }),
+        }
+    }
+
+    pub(crate) fn unlimited() -> Self {
+        Self::new(usize::MAX)
+    }
+
+    /// Returns a budget of at most `limit` bytes, which also count against
+    /// this one
+    pub(crate) fn child(&self, limit: usize) -> Self {
+        Self {
+            inner: Rc::new(BudgetInner {
+                limit: Cell::new(limit),
+                used: Default::default(),
+                parent: Some(self.clone()),
+            }),
+        }
+    }
+
+    /// Changes the limit. Charges already made stand, even if they're over.
+    pub(crate) fn set_limit(&self, limit: usize) {
+        self.inner.limit.set(limit);
+    }
+
+    /// How many bytes are currently charged against this budget
+    pub(crate) fn used(&self) -> usize {
+        self.inner.used.get()
+    }
+
+    /// Returns an empty charge, to grow later
+    pub(crate) fn charge(&self) -> Charge {
+        Charge {
+            budget: self.clone(),
+            amount: 0,
+        }
+    }
+
+    /// Charges `amount` bytes, if this budget and all its parents can afford
+    /// them. They're released when the [Charge] is dropped.
+    pub(crate) fn try_charge(&self, amount: usize) -> Option<Charge> {
+        let mut charge = self.charge();
+        charge.try_grow(amount).then_some(charge)
+    }
+
+    /// All or nothing: if any budget up the chain can't afford `amount`,
+    /// nothing is reserved.
+    fn try_reserve(&self, amount: usize) -> bool {
+        let inner = &self.inner;
+        let used = match inner.used.get().checked_add(amount) {
+            Some(used) if used <= inner.limit.get() => used,
+            _ => return false,
+        };
+        if let Some(parent) = &inner.parent {
+            if !parent.try_reserve(amount) {
+                return false;
+            }
+        }
+        inner.used.set(used);
+        true
+    }
+
+    fn release(&self, amount: usize) {
+        let inner = &self.inner;
+        inner.used.set(inner.used.get() - amount);
+        if let Some(parent) = &inner.parent {
+            parent.release(amount);
+        }
+    }
+}
+
+/// Bytes charged against a [MemoryBudget], released on drop
+pub(crate) struct Charge {
+    budget: MemoryBudget,
+    amount: usize,
+}
+
+impl Charge {
+    /// Charges `amount` more bytes, if the budget allows it. Returns whether
+    /// it did.
+    pub(crate) fn try_grow(&mut self, amount: usize) -> bool {
+        if !self.budget.try_reserve(amount) {
+            return false;
+        }
+        self.amount += amount;
+        true
+    }
+
+    /// Releases `amount` bytes (at most everything that's charged)
+    pub(crate) fn shrink(&mut self, amount: usize) {
+        let amount = amount.min(self.amount);
+        self.budget.release(amount);
+        self.amount -= amount;
+    }
+}
+
+impl Drop for Charge {
+    fn drop(&mut self) {
+        self.budget.release(self.amount);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MemoryBudget;
+
+    #[test]
+    fn charges_count_against_parents() {
+        let global = MemoryBudget::new(100);
+        let a = global.child(60);
+        let b = global.child(60);
+
+        let mut charge_a = a.try_charge(50).unwrap();
+        assert_eq!(global.used(), 50);
+
+        // fits in b's own budget, but not in what's left globally
+        assert!(b.try_charge(60).is_none());
+        assert_eq!(b.used(), 0);
+        assert_eq!(global.used(), 50);
+        let charge_b = b.try_charge(50).unwrap();
+        assert_eq!(global.used(), 100);
+        drop(charge_b);
+
+        // room globally, but over a's own budget
+        charge_a.shrink(40);
+        assert!(!charge_a.try_grow(51));
+        assert!(charge_a.try_grow(50));
+        assert_eq!(a.used(), 60);
+        assert_eq!(global.used(), 60);
+
+        drop(charge_a);
+        assert_eq!(global.used(), 0);
+    }
+
+    #[test]
+    fn unlimited_budget_never_overflows() {
+        let budget = MemoryBudget::unlimited();
+        let mut charge = budget.try_charge(usize::MAX).unwrap();
+        assert!(!charge.try_grow(1));
+        charge.shrink(usize::MAX);
+        assert_eq!(budget.used(), 0);
+    }
+}
@@ -0,0 +1,330 @@
+//! Keeps upstream connections around between requests
+//!
+//! A [Pool] holds connections per authority: whatever identifies an
+//! upstream (an address, a host and port, etc.). HTTP/1.1 connections serve
+//! one request at a time: [Pool::checkout] takes one out, and
+//! [Pool::checkin] gives it back once the response has been read in full
+//! (cf. the transport [crate::h1::request] returns). Multiplexed connections
+//! (HTTP/2) stay in the pool while in use, and serve up to
+//! [PoolConf::max_streams_per_conn] requests at once, cf.
+//! [Pool::insert_shared] and [Pool::checkout_shared].
+//!
+//! Connections that sat unused for longer than [PoolConf::idle_timeout] are
+//! evicted. Upstreams that fail can be marked unhealthy, which drops their
+//! idle connections and lets callers fail fast for a while.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    ops::Deref,
+    rc::Rc,
+    time::Duration,
+};
+
+use tokio::time::Instant;
+
+/// Limits for a [Pool]
+#[derive(Debug, Clone)]
+pub struct PoolConf {
+    /// How many idle HTTP/1.1 connections to keep per authority. Extra
+    /// connections are closed when checked in.
+    pub max_idle_per_authority: usize,
+
+    /// How long a connection can go unused before it's evicted
+    pub idle_timeout: Duration,
+
+    /// How many requests a multiplexed connection serves at once, at most.
+    /// The peer can lower it, cf. [Shared::set_max_streams].
+    pub max_streams_per_conn: u32,
+
+    /// How long an upstream stays unhealthy after [Pool::mark_unhealthy]
+    pub unhealthy_for: Duration,
+}
+
+impl Default for PoolConf {
+    fn default() -> Self {
+        Self {
+            max_idle_per_authority: 32,
+            idle_timeout: Duration::from_secs(90),
+            max_streams_per_conn: 100,
+            unhealthy_for: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Connections of type `C`, grouped by authorities of type `K`
+pub struct Pool<K, C> {
+    conf: PoolConf,
+    entries: RefCell<HashMap<K, Entry<C>>>,
+}
+
+struct Entry<C> {
+    idle: VecDeque<Idle<C>>,
+    shared: Vec<Rc<SharedInner<C>>>,
+    unhealthy_until: Option<Instant>,
+}
+
+impl<C> Default for Entry<C> {
+    fn default() -> Self {
+        Self {
+            idle: Default::default(),
+            shared: Default::default(),
+            unhealthy_until: None,
+        }
+    }
+}
+
+impl<C> Entry<C> {
+    fn evict(&mut self, now: Instant, idle_timeout: Duration) {
+        self.idle.retain(|idle| now < idle.since + idle_timeout);
+        self.shared.retain(|shared| {
+            shared.streams.get() > 0
+                || (!shared.closed.get() && now < shared.idle_since.get() + idle_timeout)
+        });
+        if self.unhealthy_until.is_some_and(|until| now >= until) {
+            self.unhealthy_until = None;
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.idle.is_empty() && self.shared.is_empty() && self.unhealthy_until.is_none()
+    }
+}
+
+struct Idle<C> {
+    conn: C,
+    since: Instant,
+}
+
+impl<K, C> Pool<K, C>
+where
+    K: Hash + Eq,
+{
+    pub fn new(conf: PoolConf) -> Self {
+        Self {
+            conf,
+            entries: Default::default(),
+        }
+    }
+
+    pub fn conf(&self) -> &PoolConf {
+        &self.conf
+    }
+
+    /// Takes out the most recently used idle connection to `key`, if any.
+    pub fn checkout(&self, key: &K) -> Option<C> {
+        self.with_entry(key, |entry| entry.idle.pop_back().map(|idle| idle.conn))
+    }
+
+    /// Gives back a connection to `key` that's ready for another request.
+    /// It's closed instead if the upstream is unhealthy, or if there are
+    /// enough idle connections to it already.
+    pub fn checkin(&self, key: K, conn: C) {
+        let now = Instant::now();
+        let mut entries = self.entries.borrow_mut();
+        let entry = entries.entry(key).or_default();
+        entry.evict(now, self.conf.idle_timeout);
+        if entry.unhealthy_until.is_none() && entry.idle.len() < self.conf.max_idle_per_authority {
+            entry.idle.push_back(Idle { conn, since: now });
+        }
+    }
+
+    /// Adds a multiplexed connection to `key`, and returns it with one
+    /// stream checked out, for the request that needed it.
+    pub fn insert_shared(&self, key: K, conn: C) -> Shared<C> {
+        let inner = Rc::new(SharedInner {
+            conn,
+            streams: Cell::new(1),
+            max_streams: Cell::new(self.conf.max_streams_per_conn),
+            closed: Cell::new(false),
+            idle_since: Cell::new(Instant::now()),
+        });
+        self.entries
+            .borrow_mut()
+            .entry(key)
+            .or_default()
+            .shared
+            .push(inner.clone());
+        Shared { inner }
+    }
+
+    /// Checks out a stream on a multiplexed connection to `key` that has
+    /// room for one more, if any.
+    pub fn checkout_shared(&self, key: &K) -> Option<Shared<C>> {
+        self.with_entry(key, |entry| {
+            let inner = entry.shared.iter().find(|shared| {
+                !shared.closed.get() && shared.streams.get() < shared.max_streams.get()
+            })?;
+            inner.streams.set(inner.streams.get() + 1);
+            Some(Shared {
+                inner: inner.clone(),
+            })
+        })
+    }
+
+    /// Marks `key` as unhealthy for [PoolConf::unhealthy_for], and closes
+    /// its idle connections: if the upstream restarted, they're not worth
+    /// trying.
+    pub fn mark_unhealthy(&self, key: K) {
+        let mut entries = self.entries.borrow_mut();
+        let entry = entries.entry(key).or_default();
+        entry.idle.clear();
+        for shared in &entry.shared {
+            shared.closed.set(true);
+        }
+        entry.unhealthy_until = Some(Instant::now() + self.conf.unhealthy_for);
+    }
+
+    /// Clears an unhealthy mark early, e.g. after a successful request
+    pub fn mark_healthy(&self, key: &K) {
+        self.with_entry(key, |entry| entry.unhealthy_until = None);
+    }
+
+    pub fn is_healthy(&self, key: &K) -> bool {
+        !self.with_entry(key, |entry| entry.unhealthy_until.is_some())
+    }
+
+    /// How many idle connections to `key` the pool holds, counting
+    /// multiplexed connections with no streams in use.
+    pub fn idle_count(&self, key: &K) -> usize {
+        self.with_entry(key, |entry| {
+            entry.idle.len()
+                + entry
+                    .shared
+                    .iter()
+                    .filter(|shared| shared.streams.get() == 0)
+                    .count()
+        })
+    }
+
+    /// Evicts idle connections that timed out, and expired unhealthy marks.
+    /// This happens on every operation on an authority anyway: only call it
+    /// to close connections to authorities that aren't used anymore.
+    pub fn evict_idle(&self) {
+        let now = Instant::now();
+        self.entries.borrow_mut().retain(|_, entry| {
+            entry.evict(now, self.conf.idle_timeout);
+            !entry.is_empty()
+        });
+    }
+
+    fn with_entry<T: Default>(&self, key: &K, f: impl FnOnce(&mut Entry<C>) -> T) -> T {
+        let mut entries = self.entries.borrow_mut();
+        let Some(entry) = entries.get_mut(key) else {
+            return Default::default();
+        };
+        entry.evict(Instant::now(), self.conf.idle_timeout);
+        let res = f(entry);
+        if entry.is_empty() {
+            entries.remove(key);
+        }
+        res
+    }
+}
+
+struct SharedInner<C> {
+    conn: C,
+    streams: Cell<u32>,
+    max_streams: Cell<u32>,
+    closed: Cell<bool>,
+    idle_since: Cell<Instant>,
+}
+
+/// A stream checked out on a multiplexed connection: dropping it makes room
+/// for another request.
+pub struct Shared<C> {
+    inner: Rc<SharedInner<C>>,
+}
+
+impl<C> Shared<C> {
+    /// Lowers how many requests the connection serves at once, e.g. to the
+    /// peer's `SETTINGS_MAX_CONCURRENT_STREAMS`. It never goes above
+    /// [PoolConf::max_streams_per_conn].
+    pub fn set_max_streams(&self, max_streams: u32) {
+        let max_streams = max_streams.min(self.inner.max_streams.get());
+        self.inner.max_streams.set(max_streams);
+    }
+
+    /// Stops handing out the connection, e.g. after a GOAWAY or an error.
+    /// It's evicted once its last stream is dropped.
+    pub fn close(&self) {
+        self.inner.closed.set(true);
+    }
+}
+
+impl<C> Deref for Shared<C> {
+    type Target = C;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner.conn
+    }
+}
+
+impl<C> Drop for Shared<C> {
+    fn drop(&mut self) {
+        let streams = self.inner.streams.get() - 1;
+        self.inner.streams.set(streams);
+        if streams == 0 {
+            self.inner.idle_since.set(Instant::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Pool, PoolConf};
+
+    #[test]
+    fn idle_connections() {
+        let pool = Pool::new(PoolConf {
+            max_idle_per_authority: 2,
+            ..Default::default()
+        });
+        for conn in 1..=3 {
+            pool.checkin("a", conn);
+        }
+        assert_eq!(pool.idle_count(&"a"), 2);
+        assert_eq!(pool.checkout(&"a"), Some(2));
+        assert_eq!(pool.checkout(&"b"), None);
+
+        pool.mark_unhealthy("a");
+        assert!(!pool.is_healthy(&"a"));
+        assert_eq!(pool.checkout(&"a"), None);
+        pool.checkin("a", 4);
+        assert_eq!(pool.idle_count(&"a"), 0);
+        pool.mark_healthy(&"a");
+        assert!(pool.is_healthy(&"a"));
+
+        let pool = Pool::new(PoolConf {
+            idle_timeout: Duration::ZERO,
+            ..Default::default()
+        });
+        pool.checkin("a", 1);
+        assert_eq!(pool.checkout(&"a"), None);
+    }
+
+    #[test]
+    fn shared_connections() {
+        let pool = Pool::new(PoolConf {
+            max_streams_per_conn: 2,
+            ..Default::default()
+        });
+
+        let first = pool.insert_shared("a", 1);
+        let second = pool.checkout_shared(&"a").unwrap();
+        assert_eq!(*second, 1);
+        assert!(pool.checkout_shared(&"a").is_none());
+
+        drop(second);
+        first.set_max_streams(1);
+        assert!(pool.checkout_shared(&"a").is_none());
+
+        first.close();
+        drop(first);
+        assert_eq!(pool.idle_count(&"a"), 0);
+        assert!(pool.checkout_shared(&"a").is_none());
+    }
+}
@@ -0,0 +1,304 @@
+//! HAProxy PROXY protocol, versions 1 and 2, cf. <https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt>
+//!
+//! When loona sits behind an L4 load balancer, the peer address of the
+//! TCP connection is that of the load balancer. Load balancers that speak
+//! the PROXY protocol send a header before anything else, relaying the
+//! address of the actual client: [read_proxy_header] reads it, so it can
+//! be applied to a [ConnectionInfo] before serving HTTP on the connection.
+//! [crate::server::Server] does that for listeners added after
+//! [with_proxy_protocol](crate::server::Server::with_proxy_protocol).
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use buffet::{ReadOwned, Roll, RollMut};
+use nom::{
+    bytes::streaming::{tag, take},
+    number::streaming::{be_u16, be_u8},
+    IResult,
+};
+
+use crate::{
+    util::{read_and_parse, ReadAndParseError},
+    ConnectionInfo,
+};
+
+/// Signature that starts every v2 header
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+
+/// Max length of a v1 header, including the `PROXY ` prefix and the CRLF
+const V1_MAX_LEN: usize = 107;
+
+/// Max length of a v2 header: the signature, version/command, family,
+/// length, and up to 64KiB of addresses and TLVs.
+const V2_MAX_LEN: usize = 16 + u16::MAX as usize;
+
+/// What a PROXY protocol header told us about the connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProxyHeader {
+    /// The connection was established by the proxy itself (e.g. for health
+    /// checks), or relays a protocol we don't know about: the addresses of
+    /// the transport should be used.
+    Local,
+
+    /// The connection was relayed on behalf of a client
+    Proxied {
+        /// The address of the client
+        src: SocketAddr,
+
+        /// The address the client connected to
+        dst: SocketAddr,
+    },
+}
+
+impl ProxyHeader {
+    /// Overrides the addresses of `info` with the ones relayed by the proxy,
+    /// if any
+    pub fn apply_to(&self, info: &mut ConnectionInfo) {
+        if let ProxyHeader::Proxied { src, dst } = self {
+            info.peer_addr = Some(*src);
+            info.local_addr = Some(*dst);
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum ProxyProtocolError {
+    /// The connection was closed before a PROXY protocol header was received
+    #[error("connection closed before PROXY protocol header was received")]
+    ClosedBeforeHeader,
+
+    /// The PROXY protocol header could not be read or parsed
+    #[error("error reading PROXY protocol header: {0}")]
+    ReadAndParse(#[from] ReadAndParseError),
+}
+
+/// Reads a v1 or v2 PROXY protocol header from the transport. This must be
+/// called before handing the connection (and `client_buf`) off to
+/// [crate::h1::serve] or [crate::h2::serve]: any bytes read past the header
+/// are kept in the returned buffer.
+pub async fn read_proxy_header(
+    transport_r: &mut impl ReadOwned,
+    client_buf: RollMut,
+) -> Result<(RollMut, ProxyHeader), ProxyProtocolError> {
+    read_and_parse(
+        "ProxyHeader",
+        proxy_header,
+        transport_r,
+        client_buf,
+        V2_MAX_LEN,
+    )
+    .await?
+    .ok_or(ProxyProtocolError::ClosedBeforeHeader)
+}
+
+/// Parses a v1 or v2 PROXY protocol header
+pub fn proxy_header(i: Roll) -> IResult<Roll, ProxyHeader> {
+    // note: `alt` doesn't play well with streaming parsers here, since the
+    // v1 tag would report "incomplete" for a partial v2 signature.
+    match i.first() {
+        Some(b'P') => proxy_header_v1(i),
+        Some(b'\r') => proxy_header_v2(i),
+        Some(_) => Err(invalid(i)),
+        None => Err(nom::Err::Incomplete(nom::Needed::Unknown)),
+    }
+}
+
+/// Looks like `PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\n`
+fn proxy_header_v1(i: Roll) -> IResult<Roll, ProxyHeader> {
+    let (i, _) = tag(&b"PROXY "[..])(i)?;
+
+    let line_len = match memchr::memmem::find(&i[..], b"\r\n") {
+        Some(n) => n,
+        None if i.len() + 6 >= V1_MAX_LEN => return Err(invalid(i)),
+        None => return Err(nom::Err::Incomplete(nom::Needed::Unknown)),
+    };
+    if line_len + 8 > V1_MAX_LEN {
+        return Err(invalid(i));
+    }
+
+    let (rest, line) = take(line_len)(i.clone())?;
+    let (rest, _) = tag(&b"\r\n"[..])(rest)?;
+
+    let line = std::str::from_utf8(&line[..]).map_err(|_| invalid(i.clone()))?;
+    let mut tokens = line.split(' ');
+    let header = match tokens.next() {
+        Some("UNKNOWN") => ProxyHeader::Local,
+        Some(proto @ ("TCP4" | "TCP6")) => {
+            let (src_ip, dst_ip, src_port, dst_port) = match (
+                tokens.next(),
+                tokens.next(),
+                tokens.next(),
+                tokens.next(),
+                tokens.next(),
+            ) {
+                (Some(a), Some(b), Some(c), Some(d), None) => (a, b, c, d),
+                _ => return Err(invalid(i)),
+            };
+
+            let parse_ip = |s: &str| -> Option<IpAddr> {
+                if proto == "TCP4" {
+                    s.parse::<Ipv4Addr>().ok().map(IpAddr::V4)
+                } else {
+                    s.parse::<Ipv6Addr>().ok().map(IpAddr::V6)
+                }
+            };
+            match (
+                parse_ip(src_ip),
+                parse_ip(dst_ip),
+                src_port.parse::<u16>(),
+                dst_port.parse::<u16>(),
+            ) {
+                (Some(src_ip), Some(dst_ip), Ok(src_port), Ok(dst_port)) => ProxyHeader::Proxied {
+                    src: SocketAddr::new(src_ip, src_port),
+                    dst: SocketAddr::new(dst_ip, dst_port),
+                },
+                _ => return Err(invalid(i)),
+            }
+        }
+        _ => return Err(invalid(i)),
+    };
+
+    Ok((rest, header))
+}
+
+/// Binary header: signature, version/command, address family, length,
+/// then addresses and TLVs (which we skip).
+fn proxy_header_v2(i: Roll) -> IResult<Roll, ProxyHeader> {
+    let (i, _) = tag(V2_SIGNATURE)(i)?;
+    let (i, ver_cmd) = be_u8(i)?;
+    let (i, family) = be_u8(i)?;
+    let (i, len) = be_u16(i)?;
+    let (rest, payload) = take(len)(i.clone())?;
+
+    const LOCAL: u8 = 0x0;
+    const PROXY: u8 = 0x1;
+    const AF_INET: u8 = 0x1;
+    const AF_INET6: u8 = 0x2;
+
+    if ver_cmd >> 4 != 2 {
+        return Err(invalid(i));
+    }
+
+    let header = match (ver_cmd & 0xf, family >> 4) {
+        (LOCAL, _) => ProxyHeader::Local,
+        (PROXY, AF_INET) => {
+            let p = &payload[..];
+            if p.len() < 12 {
+                return Err(invalid(i));
+            }
+            let src_ip = Ipv4Addr::new(p[0], p[1], p[2], p[3]);
+            let dst_ip = Ipv4Addr::new(p[4], p[5], p[6], p[7]);
+            let src_port = u16::from_be_bytes([p[8], p[9]]);
+            let dst_port = u16::from_be_bytes([p[10], p[11]]);
+            ProxyHeader::Proxied {
+                src: SocketAddr::new(src_ip.into(), src_port),
+                dst: SocketAddr::new(dst_ip.into(), dst_port),
+            }
+        }
+        (PROXY, AF_INET6) => {
+            let p = &payload[..];
+            if p.len() < 36 {
+                return Err(invalid(i));
+            }
+            let src_ip = Ipv6Addr::from(<[u8; 16]>::try_from(&p[0..16]).unwrap());
+            let dst_ip = Ipv6Addr::from(<[u8; 16]>::try_from(&p[16..32]).unwrap());
+            let src_port = u16::from_be_bytes([p[32], p[33]]);
+            let dst_port = u16::from_be_bytes([p[34], p[35]]);
+            ProxyHeader::Proxied {
+                src: SocketAddr::new(src_ip.into(), src_port),
+                dst: SocketAddr::new(dst_ip.into(), dst_port),
+            }
+        }
+        // unix sockets, unspecified, or unknown families: the spec says
+        // receivers must accept the connection and use the real addresses.
+        (PROXY, _) => ProxyHeader::Local,
+        _ => return Err(invalid(i)),
+    };
+
+    Ok((rest, header))
+}
+
+fn invalid(i: Roll) -> nom::Err<nom::error::Error<Roll>> {
+    nom::Err::Error(nom::error::Error::new(i, nom::error::ErrorKind::Verify))
+}
+
+#[cfg(test)]
+mod tests {
+    use buffet::{Roll, RollMut};
+
+    use super::{proxy_header, ProxyHeader};
+
+    fn roll(input: &[u8]) -> Roll {
+        buffet::bufpool::initialize_allocator().unwrap();
+        let mut buf = RollMut::alloc().unwrap();
+        buf.put(input).unwrap();
+        buf.filled()
+    }
+
+    #[test]
+    fn test_proxy_header_v1() {
+        let (rest, header) = proxy_header(roll(
+            b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\nGET /",
+        ))
+        .unwrap();
+        assert_eq!(
+            header,
+            ProxyHeader::Proxied {
+                src: "192.0.2.1:56324".parse().unwrap(),
+                dst: "198.51.100.1:443".parse().unwrap(),
+            }
+        );
+        assert_eq!(&rest[..], b"GET /");
+
+        let (_, header) = proxy_header(roll(b"PROXY TCP6 ::1 ::2 1 2\r\n")).unwrap();
+        assert_eq!(
+            header,
+            ProxyHeader::Proxied {
+                src: "[::1]:1".parse().unwrap(),
+                dst: "[::2]:2".parse().unwrap(),
+            }
+        );
+
+        let (_, header) = proxy_header(roll(b"PROXY UNKNOWN\r\n")).unwrap();
+        assert_eq!(header, ProxyHeader::Local);
+
+        assert!(proxy_header(roll(b"PROXY TCP4 192.0.2.1"))
+            .unwrap_err()
+            .is_incomplete());
+        assert!(proxy_header(roll(b"PROXY TCP4 ::1 ::2 1 2\r\n")).is_err());
+        assert!(proxy_header(roll(b"GET / HTTP/1.1\r\n")).is_err());
+    }
+
+    #[test]
+    fn test_proxy_header_v2() {
+        let mut input = super::V2_SIGNATURE.to_vec();
+        // version 2, PROXY command, TCP over IPv4, 12 bytes of addresses
+        input.extend_from_slice(&[0x21, 0x11, 0x00, 0x0c]);
+        input.extend_from_slice(&[192, 0, 2, 1, 198, 51, 100, 1]);
+        input.extend_from_slice(&56324_u16.to_be_bytes());
+        input.extend_from_slice(&443_u16.to_be_bytes());
+        input.extend_from_slice(b"PRI *");
+
+        let (rest, header) = proxy_header(roll(&input)).unwrap();
+        assert_eq!(
+            header,
+            ProxyHeader::Proxied {
+                src: "192.0.2.1:56324".parse().unwrap(),
+                dst: "198.51.100.1:443".parse().unwrap(),
+            }
+        );
+        assert_eq!(&rest[..], b"PRI *");
+
+        let mut input = super::V2_SIGNATURE.to_vec();
+        // version 2, LOCAL command, unspecified family, no addresses
+        input.extend_from_slice(&[0x20, 0x00, 0x00, 0x00]);
+        let (_, header) = proxy_header(roll(&input)).unwrap();
+        assert_eq!(header, ProxyHeader::Local);
+
+        assert!(proxy_header(roll(&super::V2_SIGNATURE[..5]))
+            .unwrap_err()
+            .is_incomplete());
+    }
+}
@@ -1,8 +1,10 @@
+use std::os::fd::RawFd;
+
 use b_x::BX;
 use buffet::Piece;
 use http::{header, StatusCode};
 
-use crate::{Body, BodyChunk, Headers, HeadersExt, Response};
+use crate::{cancel::Cancellation, Body, BodyChunk, Headers, HeadersExt, Response};
 
 pub trait ResponseState {}
 
@@ -61,6 +63,19 @@ where
 {
     encoder: OurEncoder,
     state: OurResponseState,
+    cancellation: Cancellation,
+}
+
+impl<OurEncoder, OurResponseState> Responder<OurEncoder, OurResponseState>
+where
+    OurEncoder: Encoder,
+    OurResponseState: ResponseState,
+{
+    /// Fires when the client is no longer waiting for this response, e.g.
+    /// because it reset the stream or hung up, or when the request timed out.
+    pub fn cancellation(&self) -> &Cancellation {
+        &self.cancellation
+    }
 }
 
 impl<OurEncoder> Responder<OurEncoder, ExpectResponseHeaders>
@@ -71,15 +86,22 @@ where
         Self {
             encoder,
             state: ExpectResponseHeaders,
+            cancellation: Default::default(),
         }
     }
 
+    pub fn with_cancellation(mut self, cancellation: Cancellation) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
     /// Send an informational status code, cf. <https://httpwg.org/specs/rfc9110.html#status.1xx>
     /// Errors out if the response status is not 1xx
     pub async fn write_interim_response(
         &mut self,
-        res: Response,
+        res: impl Into<Response>,
     ) -> Result<(), ResponderError<OurEncoder::Error>> {
+        let res = res.into();
         if !res.status.is_informational() {
             return Err(ResponderError::InterimResponseMustHaveStatusCode1xx {
                 actual: res.status,
@@ -115,26 +137,44 @@ where
                 bytes_written: 0,
             },
             encoder: self.encoder,
+            cancellation: self.cancellation,
         })
     }
 
-    /// Send the final response headers
+    /// Send the final response headers, from a [Response] or an
+    /// `http::Response<()>`.
     /// Errors out if the response status is < 200.
     /// Errors out if the client sent `expect: 100-continue`
     pub async fn write_final_response(
         self,
-        res: Response,
+        res: impl Into<Response>,
     ) -> ResponderResult<Responder<OurEncoder, ExpectResponseBody>, OurEncoder::Error> {
+        let res = res.into();
         let announced_content_length = res.headers.content_length();
         self.write_final_response_internal(res, announced_content_length)
             .await
     }
 
+    /// Sends the final response headers, and no body, whatever their
+    /// `content-length` says: that's how responses to HEAD requests look,
+    /// cf. <https://httpwg.org/specs/rfc9110.html#HEAD>. Without a
+    /// `content-length`, HTTP/1.1 responses are chunked, and the last chunk
+    /// gets sent.
+    pub async fn write_final_response_without_body(
+        self,
+        res: impl Into<Response>,
+    ) -> ResponderResult<Responder<OurEncoder, ResponseDone>, OurEncoder::Error> {
+        self.write_final_response_internal(res.into(), None)
+            .await?
+            .finish_body(None)
+            .await
+    }
+
     /// Writes a response with the given body. Sets `content-length` or
     /// `transfer-encoding` as needed.
     pub async fn write_final_response_with_body<TheirBody>(
         self,
-        mut res: Response,
+        res: impl Into<Response>,
         body: &mut TheirBody,
     ) -> Result<
         Responder<OurEncoder, ResponseDone>,
@@ -143,6 +183,7 @@ where
     where
         TheirBody: Body,
     {
+        let mut res = res.into();
         if let Some(clen) = body.content_len() {
             res.headers
                 .entry(header::CONTENT_LENGTH)
@@ -190,6 +231,11 @@ impl<E> Responder<E, ExpectResponseBody>
 where
     E: Encoder,
 {
+    /// cf. [Encoder::raw_fd]
+    pub(crate) fn raw_fd(&self) -> Option<RawFd> {
+        self.encoder.raw_fd()
+    }
+
     /// Send a response body chunk. Errors out if sending more than the
     /// announced content-length.
     #[inline]
@@ -203,10 +249,10 @@ where
 
     /// Finish the body, with optional trailers, cf. <https://developer.mozilla.org/en-US/docs/Web/HTTP/Headers/TE>
     /// Errors out if the sent body doesn't match the announced content-length.
-    /// Errors out if trailers that weren't announced are being sent, or if the
-    /// client didn't explicitly announce it accepted trailers, or if the
-    /// response is a 204, 205 or 304, or if the body wasn't sent with
-    /// chunked transfer encoding.
+    ///
+    /// Trailers always make it over HTTP/2. Over HTTP/1.1, they can only be
+    /// sent with chunked transfer encoding: for other bodies, they're
+    /// dropped.
     pub async fn finish_body(
         mut self,
         trailers: Option<Box<Headers>>,
@@ -221,21 +267,16 @@ where
                 );
             }
         }
-        self.encoder
-            .write_body_end()
-            .await
-            .map_err(ResponderError::EncoderError)?;
-
-        if let Some(trailers) = trailers {
-            self.encoder
-                .write_trailers(trailers)
-                .await
-                .map_err(ResponderError::EncoderError)?;
+        match trailers {
+            Some(trailers) => self.encoder.write_trailers(trailers).await,
+            None => self.encoder.write_body_end().await,
         }
+        .map_err(ResponderError::EncoderError)?;
 
         Ok(Responder {
             state: ResponseDone,
             encoder: self.encoder,
+            cancellation: self.cancellation,
         })
     }
 }
@@ -260,7 +301,15 @@ pub trait Encoder {
     /// the responder takes care of that for HTTP/1.1 and HTTP/2
     async fn write_body_chunk(&mut self, chunk: Piece) -> Result<(), Self::Error>;
     async fn write_body_end(&mut self) -> Result<(), Self::Error>;
+    /// Ends the body with trailers: this is called instead of
+    /// [Encoder::write_body_end], not after it.
     async fn write_trailers(&mut self, trailers: Box<Headers>) -> Result<(), Self::Error>;
+
+    /// The file descriptor the rest of the response body can be written to
+    /// as-is, if any, cf. [Body::raw_fd]
+    fn raw_fd(&self) -> Option<RawFd> {
+        None
+    }
 }
 
 #[cfg(test)]
@@ -297,6 +346,7 @@ mod tests {
                 status: StatusCode::OK,
                 version,
                 headers: Default::default(),
+                header_case: None,
             };
             res.headers.insert(header::CONTENT_LENGTH, "10".into());
 
@@ -0,0 +1,631 @@
+//! A server that owns one or more listeners and serves HTTP on every
+//! connection they accept, with a shared driver.
+//!
+//! This is what you want instead of hand-rolling an accept loop per socket:
+//! it tracks live connections, exposes some counters through [ServerStats],
+//! keeps their memory use in check (cf. [crate::memory]), and shuts
+//! everything down at once through a [ServerHandle].
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    rc::Rc,
+    time::Duration,
+};
+
+use buffet::{
+    bufpool::BUF_SIZE,
+    net::{TcpListener, TcpStream, UnixListener, UnixStream},
+    IntoHalves, RollMut,
+};
+use tokio::{
+    sync::{mpsc, oneshot, watch},
+    task::AbortHandle,
+    time::Instant,
+};
+use tracing::debug;
+
+use crate::{
+    client::AlpnProtocol,
+    h1::{self, encode::H1Encoder},
+    h2::{self, H2Encoder},
+    introspect::{ConnectionDump, H2DumpRequest, ServerDump, H2_DUMP_TIMEOUT},
+    memory::{MemoryBudget, MemoryLimits},
+    proxy_protocol::read_proxy_header,
+    ConnectionInfo, ServerDriver,
+};
+
+/// Something that accepts connections, e.g. a TCP or unix socket listener.
+///
+/// Implement this for your own types to serve HTTP over other transports,
+/// like TLS (for example with kTLS, which hands back a regular TCP stream).
+#[allow(async_fn_in_trait)] // we never require Send
+pub trait Listener: 'static {
+    type Stream: IntoHalves;
+
+    /// Accepts a new connection
+    async fn accept(&self) -> std::io::Result<(Self::Stream, ConnectionInfo)>;
+}
+
+impl Listener for TcpListener {
+    type Stream = TcpStream;
+
+    async fn accept(&self) -> std::io::Result<(Self::Stream, ConnectionInfo)> {
+        let (stream, peer_addr) = TcpListener::accept(self).await?;
+        let info = ConnectionInfo {
+            peer_addr: Some(peer_addr),
+            local_addr: self.local_addr().ok(),
+            ..Default::default()
+        };
+        Ok((stream, info))
+    }
+}
+
+impl Listener for UnixListener {
+    type Stream = UnixStream;
+
+    async fn accept(&self) -> std::io::Result<(Self::Stream, ConnectionInfo)> {
+        let stream = UnixListener::accept(self).await?;
+        Ok((stream, ConnectionInfo::default()))
+    }
+}
+
+#[cfg(feature = "sim")]
+impl Listener for buffet::sim::SimListener {
+    type Stream = buffet::sim::SimStream;
+
+    async fn accept(&self) -> std::io::Result<(Self::Stream, ConnectionInfo)> {
+        let stream = buffet::sim::SimListener::accept(self).await?;
+        Ok((stream, ConnectionInfo::default()))
+    }
+}
+
+/// Which protocol to speak on connections accepted by a listener
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Proto {
+    /// HTTP/1.1
+    H1,
+
+    /// HTTP/2 with prior knowledge (no ALPN, no upgrade)
+    H2,
+}
+
+/// Counters for a [Server]
+#[derive(Debug, Clone, Copy, Default)]
+#[non_exhaustive]
+pub struct ServerStats {
+    /// Number of connections accepted since the server started
+    pub connections_accepted: u64,
+
+    /// Number of connections currently being served
+    pub connections_active: u64,
+
+    /// Number of connections that ended with an error
+    pub connection_errors: u64,
+
+    /// Number of errors returned by `accept` on any of the listeners
+    pub accept_errors: u64,
+
+    /// Number of connections dropped right after `accept` because the
+    /// global memory budget was exhausted, cf. [crate::memory]
+    pub connections_refused: u64,
+
+    /// Bytes of buffer memory currently charged by connections, cf.
+    /// [crate::memory]
+    pub memory_used: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    connections_accepted: Cell<u64>,
+    connection_errors: Cell<u64>,
+    accept_errors: Cell<u64>,
+    connections_refused: Cell<u64>,
+}
+
+/// State shared between the accept loops, the connection tasks, and
+/// [ServerHandle]s.
+struct Shared {
+    counters: Counters,
+
+    /// Live connections, so we can abort them if they don't finish on their
+    /// own during shutdown
+    conns: RefCell<HashMap<u64, LiveConn>>,
+    next_conn_id: Cell<u64>,
+
+    /// Notified whenever a connection finishes
+    conn_done: tokio::sync::Notify,
+
+    /// Flipped to `Some(grace_period)` when shutting down
+    shutdown_tx: watch::Sender<Option<Duration>>,
+
+    /// What every connection's own budget is carved out of
+    memory: MemoryBudget,
+}
+
+struct LiveConn {
+    abort: AbortHandle,
+    info: ConnectionInfo,
+    proto: Proto,
+    accepted_at: Instant,
+    memory: MemoryBudget,
+
+    /// To ask HTTP/2 connections for an [crate::introspect::H2ConnectionDump]
+    dump_tx: Option<mpsc::UnboundedSender<H2DumpRequest>>,
+}
+
+impl Shared {
+    /// Completes once [ServerHandle::shutdown] is called
+    fn shutting_down(&self) -> impl Future<Output = ()> + 'static {
+        let mut shutdown_rx = self.shutdown_tx.subscribe();
+        async move {
+            // this only errors out if the sender is gone, and it lives as
+            // long as we do.
+            _ = shutdown_rx.wait_for(|s| s.is_some()).await;
+        }
+    }
+
+    fn stats(&self) -> ServerStats {
+        ServerStats {
+            connections_accepted: self.counters.connections_accepted.get(),
+            connections_active: self.conns.borrow().len() as u64,
+            connection_errors: self.counters.connection_errors.get(),
+            accept_errors: self.counters.accept_errors.get(),
+            connections_refused: self.counters.connections_refused.get(),
+            memory_used: self.memory.used() as u64,
+        }
+    }
+}
+
+/// A handle to a [Server], used to shut it down or look at its stats.
+#[derive(Clone)]
+pub struct ServerHandle {
+    shared: Rc<Shared>,
+}
+
+impl ServerHandle {
+    /// Stops accepting connections, then gives live connections
+    /// `grace_period` to finish on their own before aborting them: HTTP/2
+    /// connections get a GOAWAY frame, and close once their streams are
+    /// done. [Server::run] returns once that's done.
+    pub fn shutdown(&self, grace_period: Duration) {
+        self.shared.shutdown_tx.send_replace(Some(grace_period));
+    }
+
+    /// Returns a snapshot of the server's counters
+    pub fn stats(&self) -> ServerStats {
+        self.shared.stats()
+    }
+
+    /// Lists live connections and, for HTTP/2 connections, the state of
+    /// their streams, cf. [crate::introspect]
+    pub async fn dump(&self) -> ServerDump {
+        let now = Instant::now();
+        let mut pending = vec![];
+        for (&id, conn) in self.shared.conns.borrow().iter() {
+            let dump = ConnectionDump {
+                id,
+                info: conn.info.clone(),
+                proto: conn.proto,
+                age: now - conn.accepted_at,
+                memory_used: conn.memory.used(),
+                h2: None,
+            };
+            let reply_rx = conn.dump_tx.as_ref().and_then(|dump_tx| {
+                let (reply_tx, reply_rx) = oneshot::channel();
+                dump_tx.send(reply_tx).ok().map(|_| reply_rx)
+            });
+            pending.push((dump, reply_rx));
+        }
+
+        // connections answer concurrently, so it's one timeout for all
+        let deadline = now + H2_DUMP_TIMEOUT;
+        let mut connections = Vec::with_capacity(pending.len());
+        for (mut dump, reply_rx) in pending {
+            if let Some(reply_rx) = reply_rx {
+                if let Ok(Ok(h2)) = tokio::time::timeout_at(deadline, reply_rx).await {
+                    dump.h2 = Some(h2);
+                }
+            }
+            connections.push(dump);
+        }
+        connections.sort_by_key(|c| c.id);
+
+        ServerDump {
+            stats: self.shared.stats(),
+            connections,
+        }
+    }
+}
+
+type AcceptLoop = Pin<Box<dyn Future<Output = ()>>>;
+
+/// How long a connection gets to send its PROXY protocol header, cf.
+/// [Server::with_proxy_header_timeout]
+pub const DEFAULT_PROXY_HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Owns listeners and serves HTTP on the connections they accept, handing
+/// every request to the same driver.
+pub struct Server<OurDriver> {
+    driver: Rc<OurDriver>,
+    h1_conf: Rc<h1::ServerConf>,
+    h2_conf: Rc<h2::ServerConf>,
+    per_connection_memory: usize,
+    proxy_protocol: bool,
+    proxy_header_timeout: Duration,
+    accept_loops: Vec<AcceptLoop>,
+    shared: Rc<Shared>,
+}
+
+impl<OurDriver> Server<OurDriver>
+where
+    OurDriver: 'static,
+{
+    pub fn new(driver: OurDriver) -> Self {
+        let (shutdown_tx, _) = watch::channel(None);
+        Self {
+            driver: Rc::new(driver),
+            h1_conf: Default::default(),
+            h2_conf: Default::default(),
+            per_connection_memory: MemoryLimits::default().per_connection,
+            proxy_protocol: false,
+            proxy_header_timeout: DEFAULT_PROXY_HEADER_TIMEOUT,
+            accept_loops: Default::default(),
+            shared: Rc::new(Shared {
+                counters: Default::default(),
+                conns: Default::default(),
+                next_conn_id: Default::default(),
+                conn_done: Default::default(),
+                shutdown_tx,
+                memory: MemoryBudget::new(MemoryLimits::default().global),
+            }),
+        }
+    }
+
+    /// Sets the HTTP/1.1 configuration used for listeners added after this
+    pub fn with_h1_conf(mut self, conf: h1::ServerConf) -> Self {
+        self.h1_conf = Rc::new(conf);
+        self
+    }
+
+    /// Sets the HTTP/2 configuration used for listeners added after this
+    pub fn with_h2_conf(mut self, conf: h2::ServerConf) -> Self {
+        self.h2_conf = Rc::new(conf);
+        self
+    }
+
+    /// Sets how much buffer memory connections may hold on to, cf.
+    /// [crate::memory]. The global limit applies right away, the
+    /// per-connection limit to listeners added after this.
+    pub fn with_memory_limits(mut self, limits: MemoryLimits) -> Self {
+        self.shared.memory.set_limit(limits.global);
+        self.per_connection_memory = limits.per_connection;
+        self
+    }
+
+    /// Whether connections accepted by listeners added after this start with
+    /// a PROXY protocol header, cf. [crate::proxy_protocol]: the addresses it
+    /// relays end up in [ConnectionInfo], and connections without one are
+    /// dropped. Only enable this for listeners that a trusted proxy connects
+    /// to, since clients could claim to be anyone otherwise.
+    pub fn with_proxy_protocol(mut self, enabled: bool) -> Self {
+        self.proxy_protocol = enabled;
+        self
+    }
+
+    /// How long connections accepted by listeners added after this get to
+    /// send their PROXY protocol header, cf. [Server::with_proxy_protocol]:
+    /// those that take longer are dropped.
+    pub fn with_proxy_header_timeout(mut self, timeout: Duration) -> Self {
+        self.proxy_header_timeout = timeout;
+        self
+    }
+
+    /// Returns a handle that can be used to shut the server down, even while
+    /// it's running.
+    pub fn handle(&self) -> ServerHandle {
+        ServerHandle {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Adds a listener: connections it accepts will speak `proto`, unless
+    /// the listener negotiated another one, cf. [ConnectionInfo::alpn].
+    /// Nothing is accepted until [Server::run] is called.
+    pub fn listen<L>(&mut self, listener: L, proto: Proto)
+    where
+        L: Listener,
+        OurDriver:
+            ServerDriver<H1Encoder<<L::Stream as IntoHalves>::Write>> + ServerDriver<H2Encoder>,
+    {
+        let driver = self.driver.clone();
+        let h1_conf = self.h1_conf.clone();
+        let h2_conf = self.h2_conf.clone();
+        let per_connection_memory = self.per_connection_memory;
+        let proxy_protocol = self.proxy_protocol;
+        let proxy_header_timeout = self.proxy_header_timeout;
+        let shared = self.shared.clone();
+
+        self.accept_loops.push(Box::pin(async move {
+            let mut shutdown_rx = shared.shutdown_tx.subscribe();
+            loop {
+                let accepted = tokio::select! {
+                    accepted = listener.accept() => accepted,
+                    _ = shutdown_rx.wait_for(|s| s.is_some()) => {
+                        debug!("shutting down, no longer accepting connections");
+                        break;
+                    }
+                };
+
+                let (stream, info) = match accepted {
+                    Ok(t) => t,
+                    Err(e) => {
+                        debug!(%e, "error accepting connection");
+                        let errors = &shared.counters.accept_errors;
+                        errors.set(errors.get() + 1);
+                        continue;
+                    }
+                };
+
+                // every connection needs at least a read buffer: if that
+                // doesn't fit, we're better off not serving it at all.
+                let memory = shared.memory.child(per_connection_memory);
+                let Some(read_buf_charge) = memory.try_charge(BUF_SIZE as usize) else {
+                    debug!(?info, used = %shared.memory.used(), "over memory budget, refusing connection");
+                    let refused = &shared.counters.connections_refused;
+                    refused.set(refused.get() + 1);
+                    continue;
+                };
+
+                let accepted = &shared.counters.connections_accepted;
+                accepted.set(accepted.get() + 1);
+
+                let conn_id = shared.next_conn_id.get();
+                shared.next_conn_id.set(conn_id + 1);
+
+                let proto = match info.alpn {
+                    Some(AlpnProtocol::H2) => Proto::H2,
+                    Some(AlpnProtocol::Http11) => Proto::H1,
+                    None => proto,
+                };
+                let (dump_tx, dump_rx) = match proto {
+                    Proto::H1 => (None, None),
+                    Proto::H2 => {
+                        let (tx, rx) = mpsc::unbounded_channel();
+                        (Some(tx), Some(rx))
+                    }
+                };
+                let conn_fut = {
+                    let driver = driver.clone();
+                    let h1_conf = h1_conf.clone();
+                    let h2_conf = h2_conf.clone();
+                    let shared = shared.clone();
+                    let mut info = info.clone();
+                    let memory = memory.clone();
+
+                    async move {
+                        let _read_buf_charge = read_buf_charge;
+                        let (mut transport_r, transport_w) = stream.into_halves();
+                        let res = async {
+                            let mut client_buf = RollMut::alloc().map_err(|e| format!("{e}"))?;
+                            if proxy_protocol {
+                                let header;
+                                (client_buf, header) = tokio::time::timeout(
+                                    proxy_header_timeout,
+                                    read_proxy_header(&mut transport_r, client_buf),
+                                )
+                                .await
+                                .map_err(|_| "timed out reading PROXY protocol header".to_string())?
+                                .map_err(|e| format!("{e}"))?;
+                                header.apply_to(&mut info);
+                                if let Some(conn) = shared.conns.borrow_mut().get_mut(&conn_id) {
+                                    conn.info = info.clone();
+                                }
+                            }
+
+                            debug!(?info, ?proto, "serving connection");
+                            let io = (transport_r, transport_w);
+                            let early_data_len = info.early_data_len;
+                            let connection = Some(Rc::new(info.clone()));
+                            match proto {
+                                Proto::H1 => h1::serve_accepted(
+                                    io,
+                                    h1_conf,
+                                    client_buf,
+                                    early_data_len,
+                                    connection,
+                                    driver,
+                                )
+                                .await
+                                .map(|_| ())
+                                .map_err(|e| format!("{e}")),
+                                Proto::H2 => h2::serve_accepted(
+                                    io,
+                                    h2_conf,
+                                    client_buf,
+                                    early_data_len,
+                                    connection,
+                                    driver,
+                                    dump_rx,
+                                    Some(Box::pin(shared.shutting_down())),
+                                    memory,
+                                )
+                                .await
+                                .map_err(|e| format!("{e}")),
+                            }
+                        }
+                        .await;
+                        if let Err(e) = res {
+                            debug!(?info, %e, "connection ended with error");
+                            let errors = &shared.counters.connection_errors;
+                            errors.set(errors.get() + 1);
+                        }
+
+                        shared.conns.borrow_mut().remove(&conn_id);
+                        shared.conn_done.notify_waiters();
+                    }
+                };
+
+                // note: the connection task can't run before we register it,
+                // we're on a single-threaded runtime.
+                let handle = buffet::spawn(conn_fut);
+                shared.conns.borrow_mut().insert(
+                    conn_id,
+                    LiveConn {
+                        abort: handle.abort_handle(),
+                        info,
+                        proto,
+                        accepted_at: Instant::now(),
+                        memory,
+                        dump_tx,
+                    },
+                );
+            }
+        }));
+    }
+
+    /// Accepts and serves connections on all listeners, until
+    /// [ServerHandle::shutdown] is called and all connections are done.
+    pub async fn run(self) {
+        let accept_loops: Vec<_> = self.accept_loops.into_iter().map(buffet::spawn).collect();
+
+        let mut shutdown_rx = self.shared.shutdown_tx.subscribe();
+        let grace_period = match shutdown_rx.wait_for(|s| s.is_some()).await {
+            Ok(s) => s.unwrap_or_default(),
+            // can't happen, we're holding the sender
+            Err(_) => Duration::ZERO,
+        };
+        for accept_loop in accept_loops {
+            _ = accept_loop.await;
+        }
+
+        let shared = &self.shared;
+        let drained = tokio::time::timeout(grace_period, async {
+            loop {
+                let done = shared.conn_done.notified();
+                if shared.conns.borrow().is_empty() {
+                    break;
+                }
+                done.await;
+            }
+        })
+        .await;
+
+        if drained.is_err() {
+            let conns = std::mem::take(&mut *shared.conns.borrow_mut());
+            debug!(
+                "grace period elapsed, aborting {} remaining connections",
+                conns.len()
+            );
+            for (_, conn) in conns {
+                conn.abort.abort();
+            }
+        }
+    }
+}
+
+/// Passed to each shard of a [ShardedServer]
+pub struct ShardContext {
+    /// Index of this shard, in `0..num_shards`
+    pub shard_id: usize,
+
+    /// Total number of shards
+    pub num_shards: usize,
+
+    shutdown_rx: watch::Receiver<Option<Duration>>,
+}
+
+impl ShardContext {
+    /// Binds a TCP listener with `SO_REUSEPORT`, so that every shard can
+    /// bind the same address, cf. [buffet::net::TcpListener::bind_reuseport]
+    pub async fn bind(&self, addr: SocketAddr) -> std::io::Result<TcpListener> {
+        TcpListener::bind_reuseport(addr).await
+    }
+
+    /// Forwards [ShardedServer::shutdown] to this shard's server. Must be
+    /// called from within the shard's runtime.
+    pub fn attach(&self, handle: ServerHandle) {
+        let mut shutdown_rx = self.shutdown_rx.clone();
+        buffet::spawn(async move {
+            if let Ok(grace_period) = shutdown_rx.wait_for(|s| s.is_some()).await {
+                handle.shutdown(grace_period.unwrap_or_default());
+            }
+        });
+    }
+}
+
+/// Runs one [Server] per thread, each with its own io_uring and its own
+/// listeners.
+///
+/// For every shard to accept connections on the same address, listeners must
+/// be bound with `SO_REUSEPORT`, cf. [ShardContext::bind].
+/// The kernel then spreads incoming connections across shards by hashing
+/// their 4-tuple: it doesn't know how busy each shard is, so with few, or
+/// long-lived connections (HTTP/2!), some shards may end up serving a lot
+/// more than others. Keep an eye on each shard's [ServerStats] if that's a
+/// concern.
+///
+/// Shards share nothing, memory budgets included: the
+/// [MemoryLimits::global] limit of each shard's [Server] only applies to
+/// that shard.
+pub struct ShardedServer {
+    threads: Vec<std::thread::JoinHandle<()>>,
+    shutdown_tx: watch::Sender<Option<Duration>>,
+}
+
+impl ShardedServer {
+    /// Spawns `num_shards` threads (one per CPU if `None`), each running
+    /// whatever future `make_shard` returns on a fresh buffet runtime.
+    /// Typically, that future binds listeners, builds a [Server], calls
+    /// [ShardContext::attach] with its handle, and runs it.
+    pub fn spawn<F, Fut>(num_shards: Option<usize>, make_shard: F) -> std::io::Result<Self>
+    where
+        F: Fn(ShardContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()>,
+    {
+        let num_shards = match num_shards {
+            Some(n) => n,
+            None => std::thread::available_parallelism()?.get(),
+        };
+        let (shutdown_tx, shutdown_rx) = watch::channel(None);
+        let make_shard = std::sync::Arc::new(make_shard);
+
+        let mut threads = Vec::with_capacity(num_shards);
+        for shard_id in 0..num_shards {
+            let cx = ShardContext {
+                shard_id,
+                num_shards,
+                shutdown_rx: shutdown_rx.clone(),
+            };
+            let make_shard = make_shard.clone();
+            let thread = std::thread::Builder::new()
+                .name(format!("loona-shard-{shard_id}"))
+                .spawn(move || buffet::start(make_shard(cx)))?;
+            threads.push(thread);
+        }
+
+        Ok(Self {
+            threads,
+            shutdown_tx,
+        })
+    }
+
+    /// Shuts down every shard that called [ShardContext::attach], cf.
+    /// [ServerHandle::shutdown]
+    pub fn shutdown(&self, grace_period: Duration) {
+        self.shutdown_tx.send_replace(Some(grace_period));
+    }
+
+    /// Waits for all shards to finish. Panics if any of them panicked.
+    pub fn join(self) {
+        for thread in self.threads {
+            if let Err(e) = thread.join() {
+                std::panic::resume_unwind(e);
+            }
+        }
+    }
+}
@@ -0,0 +1,273 @@
+//! Server-Sent Events, cf. <https://html.spec.whatwg.org/multipage/server-sent-events.html>
+//!
+//! [channel] returns an [EventSender], for whatever produces events, and an
+//! [SseBody], to respond with (cf. [response] and
+//! [crate::Responder::write_final_response_with_body]). Each event is
+//! written to the connection as soon as it's sent: loona doesn't coalesce
+//! body chunks, over HTTP/1.1 or HTTP/2.
+//!
+//! Once the client goes away, writing the body fails and the [SseBody] gets
+//! dropped, which [EventSender::send] and [EventSender::closed] report. For
+//! HTTP/1.1, that's only noticed when something is written, which is one of
+//! the things keep-alive comments are for, cf. [SseBody::with_keep_alive].
+
+use std::{fmt, pin::Pin, time::Duration};
+
+use http::{header, StatusCode};
+use tokio::{
+    sync::mpsc,
+    time::{Instant, Sleep},
+};
+
+use crate::{error::NeverError, Body, BodyChunk, Headers, Response};
+
+/// How many events can be waiting to be written before
+/// [EventSender::send] waits
+const EVENT_BUFFER: usize = 16;
+
+/// A single event. Only the data is mandatory.
+#[derive(Debug, Clone, Default)]
+pub struct Event {
+    data: String,
+    event: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl Event {
+    /// An event carrying `data`, which may span multiple lines
+    pub fn new(data: impl Into<String>) -> Self {
+        Self {
+            data: data.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the event type (clients dispatch to listeners for it, instead
+    /// of `message`). Panics if `event` contains a newline.
+    pub fn with_event(mut self, event: impl Into<String>) -> Self {
+        let event = event.into();
+        assert!(!has_newline(&event), "event type must not contain newlines");
+        self.event = Some(event);
+        self
+    }
+
+    /// Sets the event ID, which clients send back in `last-event-id` when
+    /// reconnecting. Panics if `id` contains a newline or a NUL.
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        let id = id.into();
+        assert!(
+            !has_newline(&id) && !id.contains('\0'),
+            "event ID must not contain newlines or NUL"
+        );
+        self.id = Some(id);
+        self
+    }
+
+    /// Tells the client how long to wait before reconnecting
+    pub fn with_retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+}
+
+fn has_newline(s: &str) -> bool {
+    s.contains(['\r', '\n'])
+}
+
+/// Formats the event the way it goes over the wire, blank line included
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(event) = &self.event {
+            writeln!(f, "event: {event}")?;
+        }
+        if let Some(id) = &self.id {
+            writeln!(f, "id: {id}")?;
+        }
+        if let Some(retry) = &self.retry {
+            writeln!(f, "retry: {}", retry.as_millis())?;
+        }
+        // `lines` doesn't know about lone CRs, which are line breaks too
+        for line in self.data.split("\r\n").flat_map(|l| l.split(['\r', '\n'])) {
+            writeln!(f, "data: {line}")?;
+        }
+        writeln!(f)
+    }
+}
+
+/// A `200 OK` response with the headers event streams need
+pub fn response() -> Response {
+    let mut headers = Headers::default();
+    headers.insert(header::CONTENT_TYPE, "text/event-stream".into());
+    headers.insert(header::CACHE_CONTROL, "no-cache".into());
+    Response {
+        status: StatusCode::OK,
+        headers,
+        ..Default::default()
+    }
+}
+
+/// Returns a sender for events, and the response body they're written to
+pub fn channel() -> (EventSender, SseBody) {
+    let (tx, rx) = mpsc::channel(EVENT_BUFFER);
+    let body = SseBody {
+        rx,
+        keep_alive: None,
+        eof: false,
+    };
+    (EventSender { tx }, body)
+}
+
+/// The client disconnected, cf. [EventSender::send]
+#[derive(Debug, thiserror::Error)]
+#[error("client disconnected")]
+pub struct Disconnected;
+
+/// Sends events to an [SseBody]. Cloning it is cheap, and the stream ends
+/// once all clones are dropped.
+#[derive(Debug, Clone)]
+pub struct EventSender {
+    tx: mpsc::Sender<Event>,
+}
+
+impl EventSender {
+    /// Queues `event` to be written, waiting if too many events are
+    /// already queued. Fails if the client is gone.
+    pub async fn send(&self, event: Event) -> Result<(), Disconnected> {
+        self.tx.send(event).await.map_err(|_| Disconnected)
+    }
+
+    /// Resolves once the client is gone
+    pub async fn closed(&self) {
+        self.tx.closed().await
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+}
+
+/// A response body made of events, cf. [channel]
+pub struct SseBody {
+    rx: mpsc::Receiver<Event>,
+    keep_alive: Option<KeepAlive>,
+    eof: bool,
+}
+
+struct KeepAlive {
+    interval: Duration,
+    sleep: Pin<Box<Sleep>>,
+}
+
+impl SseBody {
+    /// Writes a comment whenever no event was sent for `interval`, so that
+    /// proxies don't give up on the connection, and so that we find out if
+    /// the client is gone.
+    pub fn with_keep_alive(mut self, interval: Duration) -> Self {
+        self.keep_alive = Some(KeepAlive {
+            interval,
+            sleep: Box::pin(tokio::time::sleep(interval)),
+        });
+        self
+    }
+}
+
+impl fmt::Debug for SseBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SseBody")
+            .field("keep_alive", &self.keep_alive.as_ref().map(|k| k.interval))
+            .field("eof", &self.eof)
+            .finish()
+    }
+}
+
+impl Body for SseBody {
+    type Error = NeverError;
+
+    fn content_len(&self) -> Option<u64> {
+        None
+    }
+
+    fn eof(&self) -> bool {
+        self.eof
+    }
+
+    async fn next_chunk(&mut self) -> Result<BodyChunk, Self::Error> {
+        if self.eof {
+            return Ok(BodyChunk::Done { trailers: None });
+        }
+
+        let chunk = match &mut self.keep_alive {
+            None => self.rx.recv().await.map(|ev| ev.to_string()),
+            Some(keep_alive) => {
+                let chunk = tokio::select! {
+                    ev = self.rx.recv() => ev.map(|ev| ev.to_string()),
+                    _ = keep_alive.sleep.as_mut() => Some(":\n\n".to_string()),
+                };
+                let deadline = Instant::now() + keep_alive.interval;
+                keep_alive.sleep.as_mut().reset(deadline);
+                chunk
+            }
+        };
+
+        match chunk {
+            Some(chunk) => Ok(BodyChunk::Chunk(chunk.into_bytes().into())),
+            None => {
+                self.eof = true;
+                Ok(BodyChunk::Done { trailers: None })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{Body, BodyChunk};
+
+    use super::{channel, Event};
+
+    async fn next_str(body: &mut impl Body) -> Option<String> {
+        match body.next_chunk().await.unwrap() {
+            BodyChunk::Chunk(chunk) => Some(String::from_utf8(chunk.to_vec()).unwrap()),
+            BodyChunk::Done { .. } => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sse() {
+        let ev = Event::new("one\ntwo\r\nthree\rfour")
+            .with_event("update")
+            .with_id("42")
+            .with_retry(Duration::from_secs(3));
+        assert_eq!(
+            ev.to_string(),
+            "event: update\nid: 42\nretry: 3000\ndata: one\ndata: two\ndata: three\ndata: four\n\n"
+        );
+        assert_eq!(Event::new("").to_string(), "data: \n\n");
+
+        // keep-alive comments only go out when there's nothing to send
+        let (tx, body) = channel();
+        let mut body = body.with_keep_alive(Duration::from_millis(20));
+        tx.send(Event::new("hi")).await.unwrap();
+        assert_eq!(next_str(&mut body).await.unwrap(), "data: hi\n\n");
+        assert_eq!(next_str(&mut body).await.unwrap(), ":\n\n");
+
+        // the stream ends with the last sender
+        let tx2 = tx.clone();
+        drop(tx);
+        tx2.send(Event::new("bye")).await.unwrap();
+        drop(tx2);
+        assert_eq!(next_str(&mut body).await.unwrap(), "data: bye\n\n");
+        assert_eq!(next_str(&mut body).await, None);
+        assert!(body.eof());
+
+        // and producers find out when the body is dropped
+        let (tx, body) = channel();
+        assert!(!tx.is_closed());
+        drop(body);
+        tx.closed().await;
+        assert!(tx.send(Event::new("hello?")).await.is_err());
+    }
+}
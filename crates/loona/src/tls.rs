@@ -0,0 +1,310 @@
+//! Certificates that change while the server runs, for rustls (with the
+//! `rustls` feature)
+//!
+//! A [CertResolver] goes in a rustls `ServerConfig` as its `cert_resolver`,
+//! and hands every handshake whatever certificate it holds at the time.
+//! Certificates are either pushed to it, cf. [CertResolver::set], or loaded
+//! from PEM files, cf. [CertFiles], which [CertResolver::watch] reloads when
+//! they change. That's what lets long-running deployments rotate
+//! certificates without restarting, and without dropping connections.
+//!
+//! An OCSP response can be stapled to the certificate, so that clients
+//! don't have to ask the certificate issuer whether it's been revoked. It's
+//! loaded from a file (DER-encoded, e.g. what `openssl ocsp -respout`
+//! writes) along with the certificate, and reloaded when it changes:
+//! fetching a fresh one before the last one expires is up to whatever
+//! writes that file.
+//!
+//! ```no_run
+//! use std::{sync::Arc, time::Duration};
+//!
+//! use loona::tls::{CertFiles, CertResolver};
+//!
+//! # fn main() -> Result<(), loona::tls::CertError> {
+//! let provider = Arc::new(rustls::crypto::aws_lc_rs::default_provider());
+//! let files = CertFiles::new("/etc/tls/fullchain.pem", "/etc/tls/key.pem")
+//!     .with_ocsp_response("/etc/tls/ocsp.der");
+//! let resolver = Arc::new(CertResolver::new(provider.clone()));
+//! resolver.load(&files)?;
+//!
+//! let server_config = rustls::ServerConfig::builder_with_provider(provider)
+//!     .with_safe_default_protocol_versions()
+//!     .unwrap()
+//!     .with_no_client_auth()
+//!     .with_cert_resolver(resolver.clone());
+//!
+//! loona::buffet::spawn(async move {
+//!     resolver.watch(files, Duration::from_secs(10)).await;
+//! });
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use base64::Engine;
+use rustls::{
+    crypto::CryptoProvider,
+    pki_types::{
+        CertificateDer, PrivateKeyDer, PrivatePkcs1KeyDer, PrivatePkcs8KeyDer, PrivateSec1KeyDer,
+    },
+    server::{ClientHello, ResolvesServerCert},
+    sign::CertifiedKey,
+    InconsistentKeys,
+};
+use tracing::debug;
+
+/// Where a certificate and its key are, in PEM files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertFiles {
+    /// The certificate first, then the intermediates that lead to a root
+    /// clients trust (`CERTIFICATE` blocks)
+    pub cert_chain: PathBuf,
+
+    /// The first `PRIVATE KEY`, `RSA PRIVATE KEY` or `EC PRIVATE KEY` block
+    /// is used
+    pub private_key: PathBuf,
+
+    /// An OCSP response for the certificate, DER-encoded, to staple. An empty
+    /// or missing file means there's nothing to staple (yet).
+    pub ocsp_response: Option<PathBuf>,
+}
+
+impl CertFiles {
+    pub fn new(cert_chain: impl Into<PathBuf>, private_key: impl Into<PathBuf>) -> Self {
+        Self {
+            cert_chain: cert_chain.into(),
+            private_key: private_key.into(),
+            ocsp_response: None,
+        }
+    }
+
+    pub fn with_ocsp_response(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ocsp_response = Some(path.into());
+        self
+    }
+
+    /// Loads the certificate, its key, and the OCSP response if there's one
+    pub fn load(&self, provider: &CryptoProvider) -> Result<CertifiedKey, CertError> {
+        let cert = pem_blocks(&self.cert_chain)?
+            .into_iter()
+            .filter(|(label, _)| label == "CERTIFICATE")
+            .map(|(_, der)| CertificateDer::from(der))
+            .collect::<Vec<_>>();
+        if cert.is_empty() {
+            return Err(CertError::NoCertificates {
+                path: self.cert_chain.clone(),
+            });
+        }
+
+        let key = pem_blocks(&self.private_key)?
+            .into_iter()
+            .find_map(|(label, der)| match &label[..] {
+                "PRIVATE KEY" => Some(PrivateKeyDer::from(PrivatePkcs8KeyDer::from(der))),
+                "RSA PRIVATE KEY" => Some(PrivatePkcs1KeyDer::from(der).into()),
+                "EC PRIVATE KEY" => Some(PrivateSec1KeyDer::from(der).into()),
+                _ => None,
+            })
+            .ok_or_else(|| CertError::NoPrivateKey {
+                path: self.private_key.clone(),
+            })?;
+        let key = provider
+            .key_provider
+            .load_private_key(key)
+            .map_err(|source| CertError::Key {
+                path: self.private_key.clone(),
+                source,
+            })?;
+
+        let mut certified = CertifiedKey::new(cert, key);
+        match certified.keys_match() {
+            // some keys can't tell what their public key is
+            Ok(()) | Err(rustls::Error::InconsistentKeys(InconsistentKeys::Unknown)) => {}
+            Err(source) => return Err(CertError::Mismatch { source }),
+        }
+
+        if let Some(path) = &self.ocsp_response {
+            certified.ocsp = match std::fs::read(path) {
+                Ok(der) => Some(der).filter(|der| !der.is_empty()),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(source) => {
+                    return Err(CertError::Read {
+                        path: path.clone(),
+                        source,
+                    })
+                }
+            };
+        }
+        Ok(certified)
+    }
+
+    /// When each file was last modified (and how large it is), to tell
+    /// whether they changed
+    fn stamp(&self) -> Vec<Option<(SystemTime, u64)>> {
+        [Some(&self.cert_chain), Some(&self.private_key)]
+            .into_iter()
+            .chain([self.ocsp_response.as_ref()])
+            .flatten()
+            .map(|path| {
+                let meta = std::fs::metadata(path).ok()?;
+                Some((meta.modified().ok()?, meta.len()))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CertError {
+    #[error("could not read {}: {source}", path.display())]
+    Read { path: PathBuf, source: io::Error },
+
+    #[error("{} is not valid PEM", path.display())]
+    Pem { path: PathBuf },
+
+    #[error("{} has no CERTIFICATE block", path.display())]
+    NoCertificates { path: PathBuf },
+
+    #[error("{} has no private key block", path.display())]
+    NoPrivateKey { path: PathBuf },
+
+    #[error("the key in {} is not usable: {source}", path.display())]
+    Key {
+        path: PathBuf,
+        source: rustls::Error,
+    },
+
+    #[error("the private key doesn't go with the certificate: {source}")]
+    Mismatch { source: rustls::Error },
+}
+
+/// Hands every handshake the certificate it currently holds: handshakes
+/// fail until it holds one.
+pub struct CertResolver {
+    provider: Arc<CryptoProvider>,
+    current: RwLock<Option<Arc<CertifiedKey>>>,
+}
+
+impl CertResolver {
+    /// `provider` loads private keys, it should be the one the
+    /// `ServerConfig` uses
+    pub fn new(provider: Arc<CryptoProvider>) -> Self {
+        Self {
+            provider,
+            current: Default::default(),
+        }
+    }
+
+    /// The certificate handshakes get, if any
+    pub fn current(&self) -> Option<Arc<CertifiedKey>> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Replaces the certificate: handshakes that already started keep the
+    /// previous one.
+    pub fn set(&self, certified: CertifiedKey) {
+        *self.current.write().unwrap() = Some(Arc::new(certified));
+    }
+
+    /// Replaces the OCSP response stapled to the current certificate, e.g.
+    /// after fetching a fresh one. Does nothing if there's no certificate.
+    pub fn set_ocsp_response(&self, ocsp: Option<Vec<u8>>) {
+        let mut current = self.current.write().unwrap();
+        if let Some(certified) = current.as_mut() {
+            let mut updated = CertifiedKey::clone(certified);
+            updated.ocsp = ocsp;
+            *certified = Arc::new(updated);
+        }
+    }
+
+    /// Loads `files` and serves them from now on. On error, the current
+    /// certificate stays.
+    pub fn load(&self, files: &CertFiles) -> Result<(), CertError> {
+        self.set(files.load(&self.provider)?);
+        Ok(())
+    }
+
+    /// Checks whether `files` changed every `interval`, and loads them again
+    /// if they did. Never returns: spawn it, and drop it to stop watching.
+    ///
+    /// Files that are halfway written, or a key that doesn't go with the
+    /// certificate yet, fail to load: the current certificate stays until
+    /// the next change. Write files elsewhere and rename them into place to
+    /// avoid that.
+    pub async fn watch(&self, files: CertFiles, interval: Duration) {
+        let mut stamp = files.stamp();
+        loop {
+            tokio::time::sleep(interval).await;
+
+            let new_stamp = files.stamp();
+            if new_stamp == stamp {
+                continue;
+            }
+            stamp = new_stamp;
+
+            match self.load(&files) {
+                Ok(()) => debug!(cert_chain = %files.cert_chain.display(), "reloaded certificate"),
+                Err(e) => {
+                    debug!(%e, "could not reload certificate, keeping the current one")
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for CertResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CertResolver")
+            .field("current", &self.current())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        self.current()
+    }
+}
+
+/// Reads the blocks of a PEM file, as (label, DER) pairs, cf.
+/// <https://www.rfc-editor.org/rfc/rfc7468>. Text outside of blocks is
+/// ignored.
+fn pem_blocks(path: &Path) -> Result<Vec<(String, Vec<u8>)>, CertError> {
+    let text = std::fs::read_to_string(path).map_err(|source| CertError::Read {
+        path: path.to_owned(),
+        source,
+    })?;
+    let bad_pem = || CertError::Pem {
+        path: path.to_owned(),
+    };
+
+    let mut blocks = vec![];
+    let mut lines = text.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        let Some(label) = line
+            .strip_prefix("-----BEGIN ")
+            .and_then(|l| l.strip_suffix("-----"))
+        else {
+            continue;
+        };
+        let end = format!("-----END {label}-----");
+
+        let mut b64 = String::new();
+        loop {
+            let line = lines.next().ok_or_else(bad_pem)?;
+            if line == end {
+                break;
+            }
+            b64.push_str(line);
+        }
+        let der = base64::engine::general_purpose::STANDARD
+            .decode(b64)
+            .map_err(|_| bad_pem())?;
+        blocks.push((label.to_owned(), der));
+    }
+    Ok(blocks)
+}
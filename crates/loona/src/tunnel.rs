@@ -0,0 +1,187 @@
+//! Byte tunnels for the CONNECT method, cf. <https://httpwg.org/specs/rfc9110.html#CONNECT>
+//!
+//! When a driver approves a CONNECT request, it sends a 2xx response and the
+//! request/response bodies turn into the two directions of a byte stream:
+//! for HTTP/1.1 that's the raw connection, for HTTP/2 it's DATA frames on the
+//! stream. [copy_bidirectional] shovels bytes between that and an upstream
+//! connection until both sides are done.
+
+use std::os::fd::RawFd;
+
+use buffet::{ReadOwned, RollMut, WriteOwned};
+use http::uri::{Authority, Uri};
+use tracing::debug;
+
+use crate::{
+    Body, BodyChunk, Encoder, ExpectResponseBody, Responder, ResponderError, ResponseDone,
+};
+
+/// The target of a CONNECT request must be a host and a port, e.g.
+/// `example.org:443`, cf. <https://httpwg.org/specs/rfc9110.html#CONNECT>
+pub(crate) fn is_valid_connect_authority(authority: &Authority) -> bool {
+    !authority.host().is_empty()
+        && authority.port_u16().is_some()
+        && !authority.as_str().contains('@')
+}
+
+/// For HTTP/1.1, the request target of a CONNECT request is in
+/// authority-form, cf. <https://httpwg.org/specs/rfc9112.html#authority-form>
+pub(crate) fn connect_target(uri: &Uri) -> Option<&Authority> {
+    if uri.scheme().is_some() || uri.path_and_query().is_some() {
+        return None;
+    }
+    uri.authority().filter(|a| is_valid_connect_authority(a))
+}
+
+/// How many bytes went through a tunnel, in each direction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TunnelStats {
+    /// Bytes sent by the client, written to the upstream
+    pub client_to_upstream: u64,
+
+    /// Bytes read from the upstream, sent to the client
+    pub upstream_to_client: u64,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum TunnelError<EncoderError, BodyError> {
+    /// Error reading from the client (the request body)
+    #[error("error reading from client: {0}")]
+    ClientRead(BodyError),
+
+    /// Error writing to the client (the response body)
+    #[error("error writing to client: {0}")]
+    ClientWrite(ResponderError<EncoderError>),
+
+    /// Error reading from the upstream
+    #[error("error reading from upstream: {0}")]
+    UpstreamRead(std::io::Error),
+
+    /// Error writing to the upstream
+    #[error("error writing to upstream: {0}")]
+    UpstreamWrite(std::io::Error),
+
+    /// Error moving bytes from one connection to the other with `splice(2)`
+    #[error("error splicing between connections: {0}")]
+    Splice(std::io::Error),
+
+    /// Could not allocate a buffer to read from the upstream
+    #[error("memory allocation error: {0}")]
+    Alloc(#[from] buffet::bufpool::Error),
+}
+
+/// Copies bytes from `req_body` to `upstream_w`, and from `upstream_r` to
+/// `responder`, concurrently.
+///
+/// When the client is done sending, the upstream's write half is shut down,
+/// and when the upstream is done sending, the response body is finished.
+/// This returns once both directions are done, or as soon as one of them
+/// errors out.
+///
+/// When both ends of a direction are plain sockets, e.g. HTTP/1.1 without
+/// TLS on one side and a TCP connection on the other, bytes are moved with
+/// `splice(2)` and never copied to userland. Otherwise, e.g. for HTTP/2 DATA
+/// frames, they go through buffers from the pool.
+pub async fn copy_bidirectional<E, B>(
+    req_body: &mut B,
+    mut responder: Responder<E, ExpectResponseBody>,
+    (mut upstream_r, mut upstream_w): (impl ReadOwned, impl WriteOwned),
+) -> Result<(Responder<E, ResponseDone>, TunnelStats), TunnelError<E::Error, B::Error>>
+where
+    E: Encoder,
+    B: Body,
+{
+    let client_to_upstream = async {
+        let mut copied = 0u64;
+        loop {
+            // bytes we'd already read from the client come out of
+            // `next_chunk` first, then `raw_fd` is set for the rest
+            if let Some(res) = splice(req_body.raw_fd(), upstream_w.raw_fd()).await {
+                copied += res.map_err(TunnelError::Splice)?;
+                break;
+            }
+
+            match req_body
+                .next_chunk()
+                .await
+                .map_err(TunnelError::ClientRead)?
+            {
+                BodyChunk::Chunk(chunk) => {
+                    copied += chunk.len() as u64;
+                    upstream_w
+                        .write_all_owned(chunk)
+                        .await
+                        .map_err(TunnelError::UpstreamWrite)?;
+                }
+                BodyChunk::Done { .. } => break,
+            }
+        }
+
+        debug!(%copied, "client done sending, shutting down upstream write half");
+        upstream_w
+            .shutdown()
+            .await
+            .map_err(TunnelError::UpstreamWrite)?;
+        Ok::<_, TunnelError<E::Error, B::Error>>(copied)
+    };
+
+    let upstream_to_client = async {
+        if let Some(res) = splice(upstream_r.raw_fd(), responder.raw_fd()).await {
+            let copied = res.map_err(TunnelError::Splice)?;
+            debug!(%copied, "upstream done sending, finishing response body");
+            let responder = responder
+                .finish_body(None)
+                .await
+                .map_err(TunnelError::ClientWrite)?;
+            return Ok((responder, copied));
+        }
+
+        let mut copied = 0u64;
+        let mut buf = RollMut::alloc()?;
+        loop {
+            buf.reserve()?;
+            let res;
+            (res, buf) = buf.read_into(usize::MAX, &mut upstream_r).await;
+            let n = res.map_err(TunnelError::UpstreamRead)?;
+            if n == 0 {
+                debug!(%copied, "upstream done sending, finishing response body");
+                let responder = responder
+                    .finish_body(None)
+                    .await
+                    .map_err(TunnelError::ClientWrite)?;
+                return Ok::<_, TunnelError<E::Error, B::Error>>((responder, copied));
+            }
+
+            copied += n as u64;
+            responder
+                .write_chunk(buf.take_all().into())
+                .await
+                .map_err(TunnelError::ClientWrite)?;
+        }
+    };
+
+    let (client_to_upstream, (responder, upstream_to_client)) =
+        tokio::try_join!(client_to_upstream, upstream_to_client)?;
+
+    Ok((
+        responder,
+        TunnelStats {
+            client_to_upstream,
+            upstream_to_client,
+        },
+    ))
+}
+
+/// Moves bytes from `from` to `to` with `splice(2)` until `from` reaches EOF,
+/// if both are set. Returns `None` otherwise, or if the runtime can't do it.
+async fn splice(from: Option<RawFd>, to: Option<RawFd>) -> Option<std::io::Result<u64>> {
+    #[cfg(all(target_os = "linux", feature = "uring"))]
+    if let (Some(from), Some(to)) = (from, to) {
+        debug!(%from, %to, "splicing");
+        return Some(buffet::net::splice_all(from, to).await);
+    }
+
+    let _ = (from, to);
+    None
+}
@@ -0,0 +1,25 @@
+use std::net::SocketAddr;
+
+use crate::client::AlpnProtocol;
+
+/// Information about the connection a request came in on.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionInfo {
+    /// The address of the client. When the server sits behind a load balancer
+    /// that speaks the PROXY protocol, this is the address of the actual client,
+    /// not that of the load balancer.
+    pub peer_addr: Option<SocketAddr>,
+
+    /// The address the client connected to
+    pub local_addr: Option<SocketAddr>,
+
+    /// How many bytes at the start of the connection came in as TLS 1.3
+    /// early data (0-RTT), for listeners that accept it. Requests that came
+    /// in those are marked, cf. [crate::Request::is_early_data].
+    pub early_data_len: usize,
+
+    /// The protocol negotiated with ALPN, for listeners that do TLS. It
+    /// overrides the [Proto](crate::server::Proto) the listener was added
+    /// with.
+    pub alpn: Option<AlpnProtocol>,
+}
@@ -1,11 +1,52 @@
 //! Types for HTTP headers
 
-use http::{header, HeaderMap};
+use http::{header, HeaderMap, HeaderName};
 
 use buffet::Piece;
 
 pub type Headers = HeaderMap<Piece>;
 
+/// How header names are spelled on the wire. HTTP/1.1 header names are
+/// case-insensitive, and [HeaderName] is always lowercase, but some older
+/// peers only understand, say, `Content-Type`.
+///
+/// The HTTP/1.1 server records it for requests if
+/// [crate::h1::ServerConf::preserve_header_case] is set, and the HTTP/1.1
+/// encoders use it for requests and responses: names it has nothing for are
+/// sent lowercase. It's ignored over HTTP/2, where names must be lowercase.
+#[derive(Clone, Default)]
+pub struct HeaderCase {
+    // for each name, how each of its occurrences is spelled, in order
+    names: HeaderMap<Piece>,
+}
+
+impl HeaderCase {
+    /// Spells every occurrence of `name` as `spelled`. Spellings that aren't
+    /// `name` in a different case are ignored when encoding.
+    pub fn insert(&mut self, name: HeaderName, spelled: impl Into<Piece>) {
+        self.names.insert(name, spelled.into());
+    }
+
+    /// Spells the next occurrence of `name` as `spelled`, cf. [Self::insert]
+    pub fn append(&mut self, name: HeaderName, spelled: impl Into<Piece>) {
+        self.names.append(name, spelled.into());
+    }
+
+    /// How the `index`-th occurrence of `name` is spelled, if known.
+    /// Occurrences past the last one recorded are spelled like it.
+    pub fn get(&self, name: &HeaderName, index: usize) -> Option<&Piece> {
+        let spellings = self.names.get_all(name);
+        spellings
+            .iter()
+            .nth(index)
+            .or_else(|| spellings.iter().last())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
 pub trait HeadersExt {
     /// Returns the content-length header
     fn content_length(&self) -> Option<u64>;
@@ -43,7 +84,7 @@ impl HeadersExt for HeaderMap<Piece> {
     }
 }
 
-fn from_digits(bytes: &[u8]) -> Option<u64> {
+pub(crate) fn from_digits(bytes: &[u8]) -> Option<u64> {
     // cannot use FromStr for u64, since it allows a signed prefix
     let mut result = 0u64;
     const RADIX: u64 = 10;
@@ -0,0 +1,233 @@
+//! Conversions from and to the [http] crate's request, response and header
+//! types, which hyper, tower, axum, etc. use.
+//!
+//! Header values that come from the `http` crate are kept as-is (cf.
+//! [buffet::PieceCore::HeaderValue]), so converting back and forth doesn't
+//! copy them. Other values are copied, and validated, on the way to the
+//! `http` crate.
+
+use std::rc::Rc;
+
+use buffet::{Piece, PieceCore, PieceStr};
+use http::{header::InvalidHeaderValue, HeaderMap, HeaderValue};
+
+use super::{ConnectionInfo, Headers, Method, Request, Response};
+
+/// Turns an [http::HeaderMap] into [Headers], without copying values
+pub fn from_http_headers(map: HeaderMap) -> Headers {
+    let mut headers = Headers::with_capacity(map.len());
+    let mut last_name = None;
+    for (name, value) in map {
+        // the iterator only yields a name for the first value of each header
+        if let Some(name) = name {
+            last_name = Some(name);
+        }
+        if let Some(name) = &last_name {
+            headers.append(name.clone(), PieceCore::HeaderValue(value).into());
+        }
+    }
+    headers
+}
+
+/// Turns [Headers] into an [http::HeaderMap]. Fails if a value isn't valid
+/// as far as the `http` crate is concerned: it's stricter than loona.
+pub fn to_http_headers(headers: &Headers) -> Result<HeaderMap, InvalidHeaderValue> {
+    let mut map = HeaderMap::with_capacity(headers.len());
+    for (name, value) in headers {
+        let value = match value {
+            Piece::Full {
+                core: PieceCore::HeaderValue(value),
+            } => value.clone(),
+            _ => HeaderValue::from_bytes(value)?,
+        };
+        map.append(name, value);
+    }
+    Ok(map)
+}
+
+impl From<http::Method> for Method {
+    fn from(method: http::Method) -> Self {
+        match method {
+            http::Method::GET => Method::Get,
+            http::Method::HEAD => Method::Head,
+            http::Method::POST => Method::Post,
+            http::Method::PUT => Method::Put,
+            http::Method::DELETE => Method::Delete,
+            http::Method::CONNECT => Method::Connect,
+            http::Method::OPTIONS => Method::Options,
+            http::Method::TRACE => Method::Trace,
+            other => Method::Other(PieceStr::from(other.as_str().to_owned())),
+        }
+    }
+}
+
+impl TryFrom<Method> for http::Method {
+    type Error = http::method::InvalidMethod;
+
+    fn try_from(method: Method) -> Result<Self, Self::Error> {
+        Ok(match method {
+            Method::Get => http::Method::GET,
+            Method::Head => http::Method::HEAD,
+            Method::Post => http::Method::POST,
+            Method::Put => http::Method::PUT,
+            Method::Delete => http::Method::DELETE,
+            Method::Connect => http::Method::CONNECT,
+            Method::Options => http::Method::OPTIONS,
+            Method::Trace => http::Method::TRACE,
+            Method::Other(other) => http::Method::from_bytes(other.as_bytes())?,
+        })
+    }
+}
+
+/// The `:protocol` pseudo-header of an HTTP/2 extended CONNECT request, as
+/// carried in [http::Extensions]: the `http` crate has no field for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectProtocol(pub String);
+
+/// Note: extensions other than [ConnectProtocol] and [ConnectionInfo] are
+/// dropped.
+impl From<http::request::Parts> for Request {
+    fn from(mut parts: http::request::Parts) -> Self {
+        Request {
+            method: parts.method.into(),
+            uri: parts.uri,
+            version: parts.version,
+            headers: from_http_headers(parts.headers),
+            protocol: parts
+                .extensions
+                .remove::<ConnectProtocol>()
+                .map(|p| p.0.into()),
+            header_case: None,
+            connection: parts.extensions.remove::<ConnectionInfo>().map(Rc::new),
+        }
+    }
+}
+
+/// Note: the `:protocol` pseudo-header of extended CONNECT requests ends up
+/// in the extensions, as a [ConnectProtocol], and so does the
+/// [ConnectionInfo] of requests a [crate::server::Server] accepted.
+impl TryFrom<Request> for http::request::Parts {
+    type Error = http::Error;
+
+    fn try_from(req: Request) -> Result<Self, Self::Error> {
+        let (mut parts, ()) = http::Request::new(()).into_parts();
+        parts.method = req.method.try_into()?;
+        parts.uri = req.uri;
+        parts.version = req.version;
+        parts.headers = to_http_headers(&req.headers)?;
+        if let Some(protocol) = req.protocol {
+            parts
+                .extensions
+                .insert(ConnectProtocol(protocol[..].to_owned()));
+        }
+        if let Some(connection) = req.connection {
+            parts.extensions.insert(ConnectionInfo::clone(&connection));
+        }
+        Ok(parts)
+    }
+}
+
+/// Note: the version is left for loona to pick (it's set per-connection
+/// anyway), and extensions are dropped.
+impl From<http::response::Parts> for Response {
+    fn from(parts: http::response::Parts) -> Self {
+        Response {
+            status: parts.status,
+            headers: from_http_headers(parts.headers),
+            ..Default::default()
+        }
+    }
+}
+
+/// Lets drivers pass `http` responses to e.g.
+/// [crate::Responder::write_final_response]
+impl From<http::Response<()>> for Response {
+    fn from(res: http::Response<()>) -> Self {
+        res.into_parts().0.into()
+    }
+}
+
+impl TryFrom<Response> for http::response::Parts {
+    type Error = InvalidHeaderValue;
+
+    fn try_from(res: Response) -> Result<Self, Self::Error> {
+        let (mut parts, ()) = http::Response::new(()).into_parts();
+        parts.status = res.status;
+        parts.version = res.version;
+        parts.headers = to_http_headers(&res.headers)?;
+        Ok(parts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use buffet::{Piece, PieceCore};
+    use http::{header, HeaderValue, StatusCode, Version};
+
+    use crate::{Method, Request, Response};
+
+    use super::{from_http_headers, to_http_headers, ConnectProtocol};
+
+    #[test]
+    fn test_http_types_roundtrip() {
+        buffet::bufpool::initialize_allocator().unwrap();
+
+        let mut req = Request {
+            method: Method::Post,
+            uri: "/foo?bar=baz".parse().unwrap(),
+            version: Version::HTTP_11,
+            ..Default::default()
+        };
+        req.headers.append(header::ACCEPT, "text/plain".into());
+        req.headers.append(header::ACCEPT, "text/html".into());
+
+        let parts = http::request::Parts::try_from(req).unwrap();
+        assert_eq!(parts.method, http::Method::POST);
+        assert_eq!(parts.headers.get_all(header::ACCEPT).iter().count(), 2);
+
+        let req = Request::from(parts);
+        assert_eq!(req.method, Method::Post);
+        assert_eq!(req.uri.path(), "/foo");
+        let accept: Vec<_> = req.headers.get_all(header::ACCEPT).iter().collect();
+        assert_eq!(accept.len(), 2);
+        assert_eq!(&accept[1][..], b"text/html");
+
+        // values that came from the http crate are kept as-is
+        assert!(matches!(
+            accept[0],
+            Piece::Full {
+                core: PieceCore::HeaderValue(_)
+            }
+        ));
+        let map = to_http_headers(&req.headers).unwrap();
+        assert!(from_http_headers(map) == req.headers);
+
+        // so is the `:protocol` pseudo-header, through the extensions
+        let req = Request {
+            method: Method::Connect,
+            protocol: Some("websocket".into()),
+            ..Default::default()
+        };
+        let parts = http::request::Parts::try_from(req).unwrap();
+        assert_eq!(
+            parts.extensions.get::<ConnectProtocol>(),
+            Some(&ConnectProtocol("websocket".into()))
+        );
+        let req = Request::from(parts);
+        assert_eq!(req.protocol.as_deref(), Some("websocket"));
+
+        let res = http::Response::builder()
+            .status(StatusCode::IM_A_TEAPOT)
+            .header(header::SERVER, HeaderValue::from_static("loona"))
+            .body(())
+            .unwrap();
+        let res = Response::from(res);
+        assert_eq!(res.status, StatusCode::IM_A_TEAPOT);
+        assert_eq!(&res.headers[header::SERVER][..], b"loona");
+
+        // the http crate is stricter about header values than loona is
+        let mut res = Response::default();
+        res.headers.insert("x-bad", b"a\nb".into());
+        assert!(http::response::Parts::try_from(res).is_err());
+    }
+}
@@ -55,6 +55,21 @@ impl Method {
         };
         s.into()
     }
+
+    /// Whether sending the request several times has the same effect as
+    /// sending it once, which makes it safe to retry, cf.
+    /// <https://httpwg.org/specs/rfc9110.html#idempotent.methods>
+    pub fn is_idempotent(&self) -> bool {
+        matches!(
+            self,
+            Method::Get
+                | Method::Head
+                | Method::Put
+                | Method::Delete
+                | Method::Options
+                | Method::Trace
+        )
+    }
 }
 
 impl From<PieceStr> for Method {
@@ -1,9 +1,13 @@
-use std::fmt::{self, Debug};
+use std::{
+    fmt::{self, Debug},
+    os::fd::RawFd,
+    rc::Rc,
+};
 
 use http::{StatusCode, Uri, Version};
 use tracing::debug;
 
-use buffet::Piece;
+use buffet::{Piece, PieceStr};
 
 mod headers;
 pub use headers::*;
@@ -11,6 +15,12 @@ pub use headers::*;
 mod method;
 pub use method::*;
 
+mod connection;
+pub use connection::*;
+
+mod http_types;
+pub use http_types::*;
+
 use crate::{error::NeverError, util::ReadAndParseError};
 
 /// An HTTP request
@@ -26,6 +36,18 @@ pub struct Request {
 
     /// Request headers
     pub headers: Headers,
+
+    /// HTTP/2 only: the `:protocol` pseudo-header of an extended CONNECT
+    /// request, e.g. `websocket`, cf. <https://www.rfc-editor.org/rfc/rfc8441>
+    pub protocol: Option<PieceStr>,
+
+    /// HTTP/1.1 only: how header names are spelled on the wire, if that
+    /// matters, cf. [HeaderCase]
+    pub header_case: Option<Box<HeaderCase>>,
+
+    /// Server only: the connection the request came in on, for connections
+    /// accepted by a [crate::server::Server]
+    pub connection: Option<Rc<ConnectionInfo>>,
 }
 
 impl Default for Request {
@@ -35,16 +57,70 @@ impl Default for Request {
             uri: "/".parse().unwrap(),
             version: Version::HTTP_11,
             headers: Default::default(),
+            protocol: None,
+            header_case: None,
+            connection: None,
         }
     }
 }
 
+impl Request {
+    /// Where to spell out header names for HTTP/1.1, cf. [HeaderCase]
+    pub fn header_case_mut(&mut self) -> &mut HeaderCase {
+        self.header_case.get_or_insert_with(Default::default)
+    }
+
+    /// How the request target was written, for HTTP/1.1 requests: only
+    /// absolute-form and authority-form targets have a scheme and an
+    /// authority in [Request::uri]. Over HTTP/2, the URI is always
+    /// absolute.
+    pub fn target_form(&self) -> RequestTargetForm {
+        if self.uri.scheme().is_some() {
+            RequestTargetForm::Absolute
+        } else if self.uri.authority().is_some() {
+            RequestTargetForm::Authority
+        } else if self.uri.path() == "*" {
+            RequestTargetForm::Asterisk
+        } else {
+            RequestTargetForm::Origin
+        }
+    }
+
+    /// Whether the request came in TLS 1.3 early data, and could be a
+    /// replay, cf. [crate::early_data]. That's what its `early-data: 1`
+    /// header says, whether we added it or a proxy in front of us did, cf.
+    /// <https://www.rfc-editor.org/rfc/rfc8470#section-5.1>
+    pub fn is_early_data(&self) -> bool {
+        self.headers
+            .get(&crate::early_data::EARLY_DATA)
+            .is_some_and(|v| &v[..] == b"1")
+    }
+}
+
+/// The forms of an HTTP/1.1 request target, cf. <https://httpwg.org/specs/rfc9112.html#request.target>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestTargetForm {
+    /// `/path?query`, most requests look like this
+    Origin,
+
+    /// `http://example.org/path?query`, for requests sent to a forward
+    /// proxy
+    Absolute,
+
+    /// `example.org:443`, for CONNECT requests only
+    Authority,
+
+    /// `*`, for server-wide OPTIONS requests only
+    Asterisk,
+}
+
 impl fmt::Debug for Request {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Request")
             .field("method", &self.method)
             .field("uri", &self.uri)
             .field("version", &self.version)
+            .field("protocol", &self.protocol)
             .finish()?;
 
         for (name, value) in &self.headers {
@@ -66,6 +142,10 @@ pub struct Response {
 
     /// Response headers
     pub headers: Headers,
+
+    /// HTTP/1.1 only: how header names are spelled on the wire, if that
+    /// matters, cf. [HeaderCase]
+    pub header_case: Option<Box<HeaderCase>>,
 }
 
 impl Default for Response {
@@ -74,11 +154,17 @@ impl Default for Response {
             version: Version::HTTP_11,
             status: StatusCode::OK,
             headers: Default::default(),
+            header_case: None,
         }
     }
 }
 
 impl Response {
+    /// Where to spell out header names for HTTP/1.1, cf. [HeaderCase]
+    pub fn header_case_mut(&mut self) -> &mut HeaderCase {
+        self.header_case.get_or_insert_with(Default::default)
+    }
+
     pub(crate) fn debug_print(&self) {
         debug!(code = %self.status, version = ?self.version, "got response");
         for (name, value) in &self.headers {
@@ -148,6 +234,15 @@ pub enum BodyError {
     #[error("invalid chunk terminator: {0}")]
     InvalidChunkTerminator(#[from] ReadAndParseError),
 
+    /// while doing chunked transfer-encoding, the trailer section after
+    /// the last chunk was malformed or too large
+    #[error("invalid trailers: {0}")]
+    InvalidTrailers(ReadAndParseError),
+
+    /// reading the next chunk took longer than the configured timeout
+    #[error("timed out while reading body")]
+    ReadTimeout,
+
     /// `write_chunk` was called but no content-length was announced, and
     /// no chunked transfer-encoding was announced
     #[error("write_chunk called when no body was expected")]
@@ -178,6 +273,15 @@ where
     fn content_len(&self) -> Option<u64>;
     fn eof(&self) -> bool;
     async fn next_chunk(&mut self) -> Result<BodyChunk, Self::Error>;
+
+    /// The file descriptor the rest of the body can be read from as-is, if
+    /// any, so it can be moved with `splice(2)` instead of `next_chunk`, cf.
+    /// [crate::tunnel::copy_bidirectional]. Only the client side of an
+    /// HTTP/1.1 tunnel has one, and only once the bytes we'd already read
+    /// have been handed out.
+    fn raw_fd(&self) -> Option<RawFd> {
+        None
+    }
 }
 
 impl Body for () {
@@ -216,6 +320,17 @@ pub enum ServeOutcome {
     /// we had to close the entire connection.
     RequestHeadersTooLargeOnHttp1Conn,
 
+    /// HTTP/1.1 only: We refused to service a request because its framing was
+    /// ambiguous or invalid (e.g. it had both `transfer-encoding` and
+    /// `content-length`), and closed the connection since we can't tell
+    /// where the next request starts.
+    InvalidRequestFramingOnHttp1Conn,
+
+    /// HTTP/1.1 only: We handled a CONNECT request. Whether it was approved
+    /// (and the connection became a tunnel) or denied, the connection can't
+    /// be used for further requests.
+    Http1ConnectHandled,
+
     /// HTTP/2 only: Client didn't speak HTTP/2 (missing/invalid request line)
     ClientDidntSpeakHttp2,
 
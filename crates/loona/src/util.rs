@@ -101,3 +101,28 @@ where
         };
     }
 }
+
+/// Runs `f`, catching any panic, like [std::panic::catch_unwind] but for
+/// futures. Handlers are driver code: we'd rather not take a whole
+/// connection down with them.
+pub(crate) async fn catch_unwind<F: std::future::Future>(
+    f: F,
+) -> Result<F::Output, Box<dyn std::any::Any + Send>> {
+    let mut f = std::pin::pin!(f);
+    std::future::poll_fn(move |cx| {
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f.as_mut().poll(cx))) {
+            Ok(std::task::Poll::Ready(output)) => std::task::Poll::Ready(Ok(output)),
+            Ok(std::task::Poll::Pending) => std::task::Poll::Pending,
+            Err(payload) => std::task::Poll::Ready(Err(payload)),
+        }
+    })
+    .await
+}
+
+/// The message of a panic, if its payload is a string (it usually is)
+pub(crate) fn panic_message(payload: &(dyn std::any::Any + Send)) -> Option<&str> {
+    payload
+        .downcast_ref::<&'static str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(|s| s.as_str()))
+}
@@ -0,0 +1,73 @@
+//! How strictly requests are held to the RFCs. Some deployments face clients
+//! that cut corners and would rather serve them than reject them, others sit
+//! behind a proxy that trusts whatever they accept, and can't afford to
+//! disagree with it on where a request ends.
+//!
+//! Start from [Validation::strict] (the default) or [Validation::lenient],
+//! then override individual rules:
+//!
+//! ```
+//! use loona::validation::Validation;
+//!
+//! let validation = Validation {
+//!     allow_obs_fold: true,
+//!     ..Validation::strict()
+//! };
+//! ```
+
+/// Which requests that the RFCs let us (or tell us to) reject are processed
+/// anyway, normalized as described on each rule. Rules only apply to the
+/// protocol they're about: the same value can be used for
+/// [crate::h1::ServerConf::validation] and [crate::h2::ServerConf::validation].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Validation {
+    /// HTTP/1.1: obsolete line folding in header values (a line that starts
+    /// with whitespace continues the previous value) is replaced with a
+    /// space, cf. <https://httpwg.org/specs/rfc9112.html#line.folding>
+    pub allow_obs_fold: bool,
+
+    /// HTTP/1.1: when a request has both `transfer-encoding` and
+    /// `content-length`, the latter is ignored, and the connection is closed
+    /// after the response, cf.
+    /// <https://httpwg.org/specs/rfc9112.html#message.body.length>. That's a
+    /// common vector for request smuggling.
+    pub allow_content_length_with_transfer_encoding: bool,
+
+    /// HTTP/1.1: spaces in the request target, e.g. `GET /a b HTTP/1.1`, are
+    /// percent-encoded, cf. <https://httpwg.org/specs/rfc9112.html#request.line>
+    pub allow_spaces_in_target: bool,
+
+    /// HTTP/2: uppercase characters in header names are lowercased, cf.
+    /// <https://httpwg.org/specs/rfc9113.html#HttpHeaders>
+    pub allow_uppercase_header_names: bool,
+}
+
+impl Validation {
+    /// Rejects everything the RFCs let us reject: requests get a 400 (for
+    /// HTTP/1.1), or their stream is reset with PROTOCOL_ERROR (for HTTP/2).
+    pub const fn strict() -> Self {
+        Self {
+            allow_obs_fold: false,
+            allow_content_length_with_transfer_encoding: false,
+            allow_spaces_in_target: false,
+            allow_uppercase_header_names: false,
+        }
+    }
+
+    /// Accepts and normalizes everything there's a rule for, for interop
+    /// with clients that don't quite follow the RFCs.
+    pub const fn lenient() -> Self {
+        Self {
+            allow_obs_fold: true,
+            allow_content_length_with_transfer_encoding: true,
+            allow_spaces_in_target: true,
+            allow_uppercase_header_names: true,
+        }
+    }
+}
+
+impl Default for Validation {
+    fn default() -> Self {
+        Self::strict()
+    }
+}
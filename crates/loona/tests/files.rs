@@ -0,0 +1,185 @@
+//! Serving a directory with [loona::files::FileServer], over HTTP/1.1
+
+mod helpers;
+
+use std::{path::PathBuf, rc::Rc};
+
+use b_x::BX;
+use buffet::{ReadOwned, RollMut, WriteOwned};
+use loona::{files::FileServer, h1};
+
+/// A fresh directory with an `index.html` and a `hello.txt` in `sub/`
+fn test_root() -> PathBuf {
+    let root = std::env::temp_dir().join(format!("loona-files-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("sub")).unwrap();
+    std::fs::write(root.join("sub/index.html"), "<h1>hi</h1>").unwrap();
+    std::fs::write(root.join("sub/hello.txt"), "hello, world!").unwrap();
+    root
+}
+
+/// Sends `req` over a fresh connection, returns the response head and body
+async fn roundtrip(server: FileServer, req: String) -> Result<(String, String), BX> {
+    let (server_write, mut client_read) = buffet::pipe();
+    let (mut client_write, server_read) = buffet::pipe();
+    buffet::spawn(async move {
+        h1::serve(
+            (server_read, server_write),
+            Rc::new(Default::default()),
+            RollMut::alloc()?,
+            server,
+        )
+        .await?;
+        Ok::<_, BX>(())
+    });
+
+    client_write.write_all_owned(req.into_bytes()).await?;
+    drop(client_write);
+
+    let mut out = vec![];
+    let mut buf = vec![0u8; 1024];
+    loop {
+        let res;
+        (res, buf) = client_read.read_owned(buf).await;
+        let n = res?;
+        if n == 0 {
+            break;
+        }
+        out.extend_from_slice(&buf[..n]);
+    }
+
+    let res = String::from_utf8(out)?;
+    let (head, body) = res.split_once("\r\n\r\n").expect("no response head");
+    Ok((head.to_string(), body.to_string()))
+}
+
+fn header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.lines()
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(": "))
+}
+
+#[test]
+fn serve_files() {
+    helpers::run(async move {
+        let root = test_root();
+        let server = FileServer::new(&root).with_prefix("/static");
+        let get = |path: &str, extra: &str| format!("GET {path} HTTP/1.1\r\n{extra}\r\n");
+
+        let (head, body) = roundtrip(server.clone(), get("/static/sub/hello.txt", "")).await?;
+        assert!(head.starts_with("HTTP/1.1 200"), "{head}");
+        assert_eq!(body, "hello, world!");
+        assert_eq!(header(&head, "content-length"), Some("13"));
+        assert_eq!(
+            header(&head, "content-type"),
+            Some("text/plain; charset=utf-8")
+        );
+        assert_eq!(header(&head, "accept-ranges"), Some("bytes"));
+        let etag = header(&head, "etag").unwrap().to_string();
+        let last_modified = header(&head, "last-modified").unwrap().to_string();
+
+        // revalidation
+        let (head, body) = roundtrip(
+            server.clone(),
+            get(
+                "/static/sub/hello.txt",
+                &format!("if-none-match: \"nope\", {etag}\r\n"),
+            ),
+        )
+        .await?;
+        assert!(head.starts_with("HTTP/1.1 304"), "{head}");
+        assert_eq!(body, "");
+        let (head, _) = roundtrip(
+            server.clone(),
+            get(
+                "/static/sub/hello.txt",
+                &format!("if-modified-since: {last_modified}\r\n"),
+            ),
+        )
+        .await?;
+        assert!(head.starts_with("HTTP/1.1 304"), "{head}");
+
+        // ranges
+        let (head, body) = roundtrip(
+            server.clone(),
+            get("/static/sub/hello.txt", "range: bytes=7-11\r\n"),
+        )
+        .await?;
+        assert!(head.starts_with("HTTP/1.1 206"), "{head}");
+        assert_eq!(header(&head, "content-range"), Some("bytes 7-11/13"));
+        assert_eq!(body, "world");
+        let (head, _) = roundtrip(
+            server.clone(),
+            get("/static/sub/hello.txt", "range: bytes=20-\r\n"),
+        )
+        .await?;
+        assert!(head.starts_with("HTTP/1.1 416"), "{head}");
+        assert_eq!(header(&head, "content-range"), Some("bytes */13"));
+        let (head, body) = roundtrip(
+            server.clone(),
+            get(
+                "/static/sub/hello.txt",
+                "range: bytes=7-11\r\nif-range: \"stale\"\r\n",
+            ),
+        )
+        .await?;
+        assert!(head.starts_with("HTTP/1.1 200"), "{head}");
+        assert_eq!(body, "hello, world!");
+
+        // HEAD announces the length, but sends no body
+        let (head, body) = roundtrip(
+            server.clone(),
+            "HEAD /static/sub/hello.txt HTTP/1.1\r\n\r\n".to_string(),
+        )
+        .await?;
+        assert!(head.starts_with("HTTP/1.1 200"), "{head}");
+        assert_eq!(header(&head, "content-length"), Some("13"));
+        assert_eq!(body, "");
+
+        // directories
+        let (head, _) = roundtrip(server.clone(), get("/static/sub", "")).await?;
+        assert!(head.starts_with("HTTP/1.1 301"), "{head}");
+        assert_eq!(header(&head, "location"), Some("/static/sub/"));
+        let (head, body) = roundtrip(server.clone(), get("/static/sub/", "")).await?;
+        assert!(head.starts_with("HTTP/1.1 200"), "{head}");
+        assert_eq!(
+            header(&head, "content-type"),
+            Some("text/html; charset=utf-8")
+        );
+        assert_eq!(body, "<h1>hi</h1>");
+
+        // not found, or not allowed
+        for path in ["/static/nope.txt", "/static/sub/../../etc/passwd", "/sub/"] {
+            let (head, _) = roundtrip(server.clone(), get(path, "")).await?;
+            assert!(head.starts_with("HTTP/1.1 404"), "{path}: {head}");
+        }
+
+        // symlinks are followed, but not out of the root
+        let outside = root.with_extension("outside");
+        let _ = std::fs::remove_dir_all(&outside);
+        std::fs::create_dir_all(&outside)?;
+        std::fs::write(outside.join("secret.txt"), "hunter2")?;
+        std::os::unix::fs::symlink("hello.txt", root.join("sub/link.txt"))?;
+        std::os::unix::fs::symlink(outside.join("secret.txt"), root.join("sub/secret.txt"))?;
+        std::os::unix::fs::symlink(&outside, root.join("sub/outside"))?;
+        let (head, body) = roundtrip(server.clone(), get("/static/sub/link.txt", "")).await?;
+        assert!(head.starts_with("HTTP/1.1 200"), "{head}");
+        assert_eq!(body, "hello, world!");
+        for path in ["/static/sub/secret.txt", "/static/sub/outside/secret.txt"] {
+            let (head, body) = roundtrip(server.clone(), get(path, "")).await?;
+            assert!(head.starts_with("HTTP/1.1 404"), "{path}: {head}");
+            assert!(!body.contains("hunter2"));
+        }
+        std::fs::remove_dir_all(&outside)?;
+
+        let (head, _) = roundtrip(
+            server.clone(),
+            "DELETE /static/sub/hello.txt HTTP/1.1\r\n\r\n".to_string(),
+        )
+        .await?;
+        assert!(head.starts_with("HTTP/1.1 405"), "{head}");
+        assert_eq!(header(&head, "allow"), Some("GET, HEAD"));
+
+        std::fs::remove_dir_all(&root)?;
+        Ok(())
+    })
+}
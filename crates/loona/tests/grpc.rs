@@ -0,0 +1,135 @@
+//! gRPC calls from tonic's client, against a hand-rolled gRPC server: this
+//! checks what gRPC needs from HTTP/2, cf. <https://github.com/grpc/grpc/blob/master/doc/PROTOCOL-HTTP2.md>
+
+mod helpers;
+
+use b_x::{BxForResults, BX};
+use http::{header, uri::PathAndQuery, StatusCode};
+use loona::{
+    h2,
+    server::{Proto, Server},
+    Body, BodyChunk, Encoder, ExpectResponseHeaders, Headers, Responder, Response, ResponseDone,
+    ServerDriver,
+};
+use prost::Message;
+use std::time::Duration;
+use tonic::{codec::ProstCodec, transport::Endpoint, Code};
+
+/// What prost would generate for `message Echo { bytes payload = 1; }`
+#[derive(Clone, PartialEq, prost::Message)]
+struct Echo {
+    #[prost(bytes = "vec", tag = "1")]
+    payload: Vec<u8>,
+}
+
+/// Echoes `Echo` messages back on `/test.Echo/Echo`, answers anything else
+/// with an UNIMPLEMENTED status.
+struct GrpcDriver;
+
+impl<OurEncoder> ServerDriver<OurEncoder> for GrpcDriver
+where
+    OurEncoder: Encoder,
+{
+    type Error = BX;
+
+    async fn handle(
+        &self,
+        req: loona::Request,
+        req_body: &mut impl Body,
+        res: Responder<OurEncoder, ExpectResponseHeaders>,
+    ) -> b_x::Result<Responder<OurEncoder, ResponseDone>> {
+        assert_eq!(&req.headers[header::TE][..], b"trailers");
+        assert_eq!(&req.headers[header::CONTENT_TYPE][..], b"application/grpc");
+
+        // a single length-prefixed message, uncompressed
+        let mut body = vec![];
+        while let BodyChunk::Chunk(chunk) = req_body.next_chunk().await.bx()? {
+            body.extend_from_slice(&chunk[..]);
+        }
+        assert_eq!(body[0], 0);
+        let len = u32::from_be_bytes(body[1..5].try_into().unwrap()) as usize;
+        assert_eq!(len, body.len() - 5);
+        let echo = Echo::decode(&body[5..]).bx()?;
+
+        let mut headers = Headers::default();
+        headers.insert(header::CONTENT_TYPE, "application/grpc".into());
+        let mut res = res
+            .write_final_response(Response {
+                status: StatusCode::OK,
+                headers,
+                ..Default::default()
+            })
+            .await?;
+
+        let mut trailers = Headers::default();
+        if req.uri.path() == "/test.Echo/Echo" {
+            let msg = echo.encode_to_vec();
+            let mut framed = vec![0];
+            framed.extend_from_slice(&(msg.len() as u32).to_be_bytes());
+            framed.extend_from_slice(&msg);
+            res.write_chunk(framed.into()).await?;
+            trailers.insert("grpc-status", "0".into());
+        } else {
+            trailers.insert("grpc-status", "12".into());
+            trailers.insert("grpc-message", "no such method".into());
+        }
+        Ok(res.finish_body(Some(Box::new(trailers))).await?)
+    }
+}
+
+#[test]
+fn grpc_tonic_client() {
+    helpers::run(async move {
+        let ln = loona::buffet::net::TcpListener::bind("127.0.0.1:0".parse()?).await?;
+        let addr = ln.local_addr()?;
+
+        let mut server = Server::new(GrpcDriver).with_h2_conf(h2::ServerConf::grpc());
+        server.listen(ln, Proto::H2);
+        let handle = server.handle();
+        let server_fut = loona::buffet::spawn(server.run());
+
+        let channel = Endpoint::from_shared(format!("http://{addr}"))
+            .bx()?
+            .connect()
+            .await
+            .bx()?;
+        let mut client = tonic::client::Grpc::new(channel);
+
+        // larger than the stream window in both directions, so it only
+        // makes it through if flow control windows get replenished
+        let payload: Vec<u8> = (0..3 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+        client.ready().await.bx()?;
+        let res = client
+            .unary(
+                tonic::Request::new(Echo {
+                    payload: payload.clone(),
+                }),
+                PathAndQuery::from_static("/test.Echo/Echo"),
+                ProstCodec::<Echo, Echo>::default(),
+            )
+            .await
+            .bx()?;
+        assert!(res.into_inner().payload == payload);
+
+        // the status only travels in trailers
+        client.ready().await.bx()?;
+        let status = client
+            .unary(
+                tonic::Request::new(Echo::default()),
+                PathAndQuery::from_static("/test.Echo/Nope"),
+                ProstCodec::<Echo, Echo>::default(),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Unimplemented);
+        assert_eq!(status.message(), "no such method");
+
+        handle.shutdown(Duration::from_secs(1));
+        tokio::time::timeout(Duration::from_secs(5), server_fut)
+            .await
+            .bx()?
+            .bx()?;
+
+        Ok(())
+    })
+}
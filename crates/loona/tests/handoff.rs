@@ -0,0 +1,107 @@
+//! Handing listeners over to another process, with [loona::handoff]
+
+mod helpers;
+
+use std::{
+    io::Read,
+    os::{fd::AsFd, unix::net::UnixStream},
+    process::Command,
+};
+
+use b_x::BX;
+use buffet::{
+    net::{TcpListener, UnixListener},
+    IntoHalves, WriteOwned,
+};
+use loona::handoff;
+
+/// One test only: the tracing setup in `helpers` is global, and this
+/// re-runs itself as the new instance.
+#[test]
+fn handoff() {
+    if std::env::var_os("LISTEN_FDS").is_some() {
+        // we're the new instance
+        helpers::run(async move {
+            let mut listeners = handoff::listeners_from_env()?;
+            assert!(std::env::var_os("LISTEN_FDS").is_none());
+            assert_eq!(listeners.len(), 1);
+            assert_eq!(listeners[0].name, "http");
+            let tcp = listeners.pop().unwrap().into_tcp()?;
+            let (stream, _) = tcp.accept().await?;
+            let (_, mut w) = stream.into_halves();
+            w.write_all_owned("handed over").await?;
+            Ok(())
+        });
+        return;
+    }
+
+    helpers::run(async move {
+        over_unix_socket().await?;
+        through_env().await
+    })
+}
+
+async fn over_unix_socket() -> Result<(), BX> {
+    // the old instance's listeners go away once exported, the exported
+    // sockets keep accepting connections
+    let (addr, tcp_fd) = {
+        let tcp = TcpListener::bind("127.0.0.1:0".parse()?).await?;
+        (tcp.local_addr()?, tcp.export()?)
+    };
+    let path = std::env::temp_dir().join(format!("loona-handoff-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let unix = UnixListener::bind(&path).await?;
+
+    let (old, new) = UnixStream::pair()?;
+    let unix_fd = unix.export()?;
+    drop(unix);
+    assert!(
+        path.exists(),
+        "exported unix listeners keep their socket file"
+    );
+    handoff::send_listeners(
+        &old,
+        &[("http", tcp_fd.as_fd()), ("admin", unix_fd.as_fd())],
+    )?;
+    drop((tcp_fd, unix_fd));
+
+    let mut received = handoff::recv_listeners(&new)?;
+    assert_eq!(
+        received.iter().map(|l| l.name.as_str()).collect::<Vec<_>>(),
+        ["http", "admin"]
+    );
+    let unix2 = received.pop().unwrap().into_unix()?;
+    let tcp2 = received.pop().unwrap().into_tcp()?;
+
+    assert_eq!(tcp2.local_addr()?, addr);
+    let client = std::net::TcpStream::connect(addr)?;
+    let (_stream, peer) = tcp2.accept().await?;
+    assert_eq!(peer, client.local_addr()?);
+
+    assert_eq!(unix2.local_path(), path);
+    let _client = std::os::unix::net::UnixStream::connect(&path)?;
+    unix2.accept().await?;
+
+    drop(unix2);
+    assert!(!path.exists());
+    Ok(())
+}
+
+async fn through_env() -> Result<(), BX> {
+    let (addr, mut child) = {
+        let tcp = TcpListener::bind("127.0.0.1:0".parse()?).await?;
+        let mut cmd = Command::new(std::env::current_exe()?);
+        cmd.args(["--exact", "handoff", "--test-threads", "1"]);
+        handoff::pass_to_command(&mut cmd, vec![("http".to_string(), tcp.export()?)]);
+        (tcp.local_addr()?, cmd.spawn()?)
+    };
+
+    // this waits in the listen queue until the child accepts it
+    let mut client = std::net::TcpStream::connect(addr)?;
+    let mut greeting = String::new();
+    client.read_to_string(&mut greeting)?;
+    assert_eq!(greeting, "handed over");
+
+    assert!(child.wait()?.success());
+    Ok(())
+}
@@ -1,13 +1,15 @@
 use std::error::Error as StdError;
 use std::rc::Rc;
+use std::time::Duration;
 
 use b_x::{BxForResults, BX};
 use buffet::{IntoHalves, PipeRead, PipeWrite, ReadOwned, RollMut, WriteOwned};
 use http::StatusCode;
 use loona::{
-    Body, BodyChunk, Encoder, ExpectResponseHeaders, Responder, Response, ResponseDone,
+    Body, BodyChunk, Encoder, ExpectResponseHeaders, Method, Responder, Response, ResponseDone,
     ServerDriver,
 };
+use tokio::sync::Notify;
 use tracing::Level;
 use tracing_subscriber::{filter::Targets, layer::SubscriberExt, util::SubscriberInitExt};
 
@@ -37,7 +39,10 @@ pub(crate) fn setup_tracing_and_error_reporting() {
         .init();
 }
 
-struct TestDriver;
+struct TestDriver {
+    /// notified when a request for [httpwg::GRACEFUL_SHUTDOWN_PATH] comes in
+    goaway: Rc<Notify>,
+}
 
 impl<OurEncoder> ServerDriver<OurEncoder> for TestDriver
 where
@@ -63,6 +68,20 @@ where
             }
         }
 
+        // extended CONNECT (RFC 8441): echo whatever goes through the tunnel
+        if _req.method == Method::Connect {
+            let mut res = res
+                .write_final_response(Response {
+                    status: StatusCode::OK,
+                    ..Default::default()
+                })
+                .await?;
+            while let BodyChunk::Chunk(chunk) = req_body.next_chunk().await.bx()? {
+                res.write_chunk(chunk).await?;
+            }
+            return Ok(res.finish_body(None).await?);
+        }
+
         // then read the full request body
         let mut req_body_len = 0;
         loop {
@@ -82,6 +101,10 @@ where
         }
         tracing::debug!(%req_body_len, "read request body");
 
+        if _req.uri.path() == httpwg::GRACEFUL_SHUTDOWN_PATH {
+            self.goaway.notify_one();
+        }
+
         let mut res = res
             .write_final_response(Response {
                 status: StatusCode::OK,
@@ -108,29 +131,51 @@ impl<W: WriteOwned + 'static, R: ReadOwned + 'static> IntoHalves for TwoHalves<W
     }
 }
 
-pub fn start_server() -> httpwg::Conn<TwoHalves<PipeWrite, PipeRead>> {
+type PipeIo = TwoHalves<PipeWrite, PipeRead>;
+
+/// Serves a single HTTP/2 connection over a pair of pipes, and returns the
+/// client end
+fn serve_connection() -> PipeIo {
     let (server_write, client_read) = loona::buffet::pipe();
     let (client_write, server_read) = loona::buffet::pipe();
 
     let serve_fut = async move {
-        let server_conf = Rc::new(loona::h2::ServerConf {
-            ..Default::default()
-        });
+        let server_conf = Rc::new(loona::h2::ServerConf::conformance_test());
 
         let client_buf = RollMut::alloc()?;
-        let driver = Rc::new(TestDriver);
+        let goaway = Rc::new(Notify::new());
+        let driver = Rc::new(TestDriver {
+            goaway: goaway.clone(),
+        });
         let io = (server_read, server_write);
-        loona::h2::serve(io, server_conf, client_buf, driver).await?;
+        let shutdown = async move { goaway.notified().await };
+        loona::h2::serve_until(io, server_conf, client_buf, driver, shutdown).await?;
         tracing::debug!("http/2 server done");
         Ok::<_, BX>(())
     };
 
     buffet::spawn(async move {
-        serve_fut.await.unwrap();
+        // connection errors are what a lot of tests (and all of the fuzzing)
+        // are after: panicking here would only stall the client while the
+        // backtrace gets captured
+        if let Err(e) = serve_fut.await {
+            tracing::debug!("http/2 server errored: {e}");
+        }
     });
 
+    TwoHalves(client_write, client_read)
+}
+
+/// Opens connections with [serve_connection]
+fn connector() -> httpwg::Connector<PipeIo> {
+    Rc::new(|| Box::pin(async { Ok(serve_connection()) }))
+}
+
+/// A client connection to a fresh server, which lets tests open more
+/// connections, each with a server of its own
+pub fn start_server() -> httpwg::Conn<PipeIo> {
     let config = Rc::new(httpwg::Config::default());
-    httpwg::Conn::new(config, TwoHalves(client_write, client_read))
+    httpwg::Conn::new(config, serve_connection()).with_connector(connector())
 }
 
 #[cfg(test)]
@@ -143,3 +188,285 @@ httpwg_macros::tests! {{
        result.unwrap()
    });
 }}
+
+/// Tests can be picked by id as well as by path, without `http2/6.7/1`
+/// picking `http2/6.7/10` too
+#[test]
+fn filter_by_id() {
+    let filter = httpwg::filter::Filter::parse("http2/6.7/1");
+    assert!(filter.matches("http2/6.7/1", "rfc9113", "6.7", "sends_ping_frame"));
+    assert!(!filter.matches("http2/6.7/10", "rfc9113", "6.7", "sends_other_frame"));
+
+    let filter = httpwg::filter::Filter::parse("http2/6");
+    assert!(filter.matches("http2/6.7/1", "rfc9113", "6.7", "sends_ping_frame"));
+    assert!(!filter.matches("http2/5.1/1", "rfc9113", "5.1", "idle_sends_data_frame"));
+}
+
+/// Tests that need a feature the server doesn't support get skipped
+#[test]
+fn unsupported_features() {
+    use httpwg::filter::unsupported;
+
+    assert_eq!(unsupported(&[], &["extended-connect"]), None);
+    assert_eq!(
+        unsupported(&["server-push", "extended-connect"], &["extended-connect"]),
+        Some("extended-connect")
+    );
+    assert_eq!(unsupported(&["server-push"], &[]), None);
+}
+
+#[test]
+fn all_tests_have_unique_ids() {
+    let suites = httpwg::all_tests::<PipeIo>();
+    let ids: Vec<_> = suites
+        .iter()
+        .flat_map(|s| &s.groups)
+        .flat_map(|g| &g.tests)
+        .map(|t| t.id)
+        .collect();
+    assert!(ids.contains(&"http2/6.7/1"));
+    let unique: std::collections::HashSet<_> = ids.iter().collect();
+    assert_eq!(unique.len(), ids.len());
+}
+
+/// Runs one test found through the registry rather than the `tests!` macro
+#[test]
+fn run_test_from_registry() {
+    crate::setup_tracing_and_error_reporting();
+
+    let test = httpwg::all_tests::<PipeIo>()
+        .into_iter()
+        .flat_map(|s| s.groups)
+        .flat_map(|g| g.tests)
+        .find(|t| t.id == "http2/6.7/1")
+        .unwrap();
+    buffet::start(async move { (test.run)(crate::start_server()).await.unwrap() });
+}
+
+/// Runs a few fixed seeds, so that failures are reproducible in CI, or just
+/// the one in `HTTPWG_FUZZ_SEED`
+#[test]
+fn fuzz_malformed_frames() {
+    crate::setup_tracing_and_error_reporting();
+
+    let seeds: Vec<u64> = match std::env::var(httpwg::fuzz::SEED_ENV_VAR) {
+        Ok(_) => vec![httpwg::fuzz::seed_from_env_or_time().unwrap()],
+        Err(_) => (0..64).collect(),
+    };
+
+    buffet::start(async move {
+        for seed in seeds {
+            let conn = crate::start_server();
+            httpwg::fuzz::run(conn, seed, httpwg::fuzz::DEFAULT_NUM_FRAMES)
+                .await
+                .unwrap();
+        }
+    });
+}
+
+/// A short benchmark run, to check that it gets through a few hundred
+/// requests over several connections: the numbers that matter come from
+/// `httpwg --bench` against a release build
+#[test]
+fn bench_smoke() {
+    crate::setup_tracing_and_error_reporting();
+
+    buffet::start(async move {
+        let load = httpwg::bench::Load {
+            connections: 2,
+            streams: 8,
+            requests: 200,
+            body_len: 100,
+        };
+        let config = Rc::new(httpwg::Config::default());
+        let report = httpwg::bench::run(config, crate::connector(), &load)
+            .await
+            .unwrap();
+        tracing::info!("{report}");
+        assert_eq!(report.completed, 400);
+        assert_eq!(report.failed, 0);
+    });
+}
+
+/// A short stress run, replacing connections as it goes, with bodies too big
+/// for the connection's window to take more than one of at a time
+#[test]
+fn stress_smoke() {
+    crate::setup_tracing_and_error_reporting();
+
+    buffet::start(async move {
+        let stress = httpwg::stress::Stress {
+            connections: 2,
+            streams: 8,
+            requests_per_connection: 50,
+            body_sizes: vec![0, 100, 16_384, 60_000],
+            duration: Duration::from_secs(1),
+            samples: 4,
+        };
+        let config = Rc::new(httpwg::Config::default());
+        let mut seen = 0;
+        let report =
+            httpwg::stress::run(config, crate::connector(), &stress, || None, |_| seen += 1)
+                .await
+                .unwrap();
+        tracing::info!("{report}");
+        assert_eq!(seen, 4);
+        assert_eq!(report.samples.len(), 4);
+        assert!(report.connections > 2, "{report}");
+        assert!(report.completed() > 0, "{report}");
+        assert_eq!(report.failed(), 0, "{report}");
+        assert_eq!(report.dropped, 0, "{:?}", report.last_drop);
+    });
+}
+
+/// The handshake should fail, rather than hang or pass, against a server
+/// that never acknowledges our SETTINGS
+#[test]
+fn handshake_flags_unacknowledged_settings() {
+    crate::setup_tracing_and_error_reporting();
+
+    buffet::start(async move {
+        let (mut server_write, client_read) = loona::buffet::pipe();
+        let (client_write, mut server_read) = loona::buffet::pipe();
+
+        // read whatever the client sends, so that it doesn't block
+        buffet::spawn(async move {
+            let mut buf = vec![0u8; 4096];
+            loop {
+                let res;
+                (res, buf) = server_read.read_owned(buf).await;
+                if !matches!(res, Ok(n) if n > 0) {
+                    break;
+                }
+            }
+        });
+
+        // an empty SETTINGS frame, then nothing at all
+        server_write
+            .write_all_owned(b"\x00\x00\x00\x04\x00\x00\x00\x00\x00".to_vec())
+            .await
+            .unwrap();
+
+        let config = Rc::new(httpwg::Config {
+            settings_ack_timeout: Duration::from_millis(50),
+            ..Default::default()
+        });
+        let mut conn = httpwg::Conn::new(config, TwoHalves(client_write, client_read));
+        let err = conn.handshake().await.unwrap_err();
+        assert!(err.to_string().contains("SETTINGS_TIMEOUT"), "{err}");
+    });
+}
+
+/// A PING gets answered, so the peer doesn't stay quiet after one, unless
+/// PINGs are all we mind about
+#[test]
+fn expect_silence_flags_answers() {
+    crate::setup_tracing_and_error_reporting();
+
+    buffet::start(async move {
+        let mut conn = crate::start_server();
+        conn.handshake().await.unwrap();
+        let quiet = Duration::from_millis(50);
+        conn.expect_silence(quiet).await.unwrap();
+
+        conn.write_ping(false, b"silence?".to_vec()).await.unwrap();
+        let err = conn.expect_silence(quiet).await.unwrap_err();
+        assert!(err.to_string().contains("Ping"), "{err}");
+
+        conn.write_ping(false, b"silence?".to_vec()).await.unwrap();
+        conn.expect_no_frame(httpwg::FrameT::GoAway, quiet)
+            .await
+            .unwrap();
+    });
+}
+
+/// In raw mode, what the peer sends comes out byte for byte, frames or not
+#[test]
+fn raw_mode_reads_bytes() {
+    crate::setup_tracing_and_error_reporting();
+
+    buffet::start(async move {
+        let mut conn = crate::start_server();
+        conn.handshake().await.unwrap();
+        conn.raw_mode(true);
+
+        conn.write_ping(false, b"rawbytes".to_vec()).await.unwrap();
+        let bytes = conn.read_raw(17).await.unwrap();
+        assert_eq!(bytes, b"\x00\x00\x08\x06\x01\x00\x00\x00\x00rawbytes");
+
+        conn.write_ping(false, b"rawbytes".to_vec()).await.unwrap();
+        let err = conn
+            .expect_silence(Duration::from_millis(50))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("[00, 00, 08, 06, 01"), "{err}");
+    });
+}
+
+/// Servers behind a Unix socket get tested like any other
+#[test]
+fn unix_socket_transport() {
+    crate::setup_tracing_and_error_reporting();
+
+    buffet::start(async move {
+        let path = std::env::temp_dir().join(format!("httpwg-{}.sock", std::process::id()));
+        let ln = buffet::net::UnixListener::bind(&path).await.unwrap();
+
+        buffet::spawn(async move {
+            let stream = ln.accept().await.unwrap();
+            let server_conf = Rc::new(loona::h2::ServerConf::conformance_test());
+            let driver = Rc::new(TestDriver {
+                goaway: Default::default(),
+            });
+            let client_buf = RollMut::alloc().unwrap();
+            if let Err(e) =
+                loona::h2::serve(stream.into_halves(), server_conf, client_buf, driver).await
+            {
+                tracing::debug!("http/2 server errored: {e}");
+            }
+        });
+
+        let stream = buffet::net::UnixStream::connect(&path).await.unwrap();
+        let mut conn = httpwg::Conn::new(Rc::new(httpwg::Config::default()), stream);
+        conn.handshake().await.unwrap();
+        conn.write_ping(false, b"unixsock".to_vec()).await.unwrap();
+        conn.verify_ping_frame_with_ack(b"unixsock").await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+    });
+}
+
+/// A recorded session should survive being dumped and read back, and
+/// replaying it against another server should get the same answers
+#[test]
+fn replay_recorded_session() {
+    crate::setup_tracing_and_error_reporting();
+
+    buffet::start(async move {
+        let config = Rc::new(httpwg::Config {
+            record_frames: true,
+            timeout: Duration::from_millis(100),
+            ..Default::default()
+        });
+
+        let mut conn = httpwg::Conn::new(config.clone(), crate::serve_connection());
+        let recorder = conn.recorder().unwrap();
+        conn.handshake().await.unwrap();
+        conn.write_ping(false, b"recorded".to_vec()).await.unwrap();
+        conn.verify_ping_frame_with_ack(b"recorded").await.unwrap();
+        drop(conn);
+
+        let recorded = httpwg::capture::read_bin(&recorder.to_bin()).unwrap();
+        assert_eq!(recorded.len(), recorder.frames().len());
+        let trace = httpwg::capture::render_trace(&recorded);
+        assert!(trace.contains("connection preface"), "{trace}");
+        assert!(trace.contains("< Conn:Ping"), "{trace}");
+
+        let conn = httpwg::Conn::new(config, crate::serve_connection());
+        let replayed = httpwg::replay::replay(conn, &recorded).await.unwrap();
+        let trace = httpwg::capture::render_trace(&replayed.frames);
+        assert!(
+            trace.contains("< Conn:Ping { len: 8, flags: Ack } data=7265636f72646564"),
+            "{trace}"
+        );
+    });
+}
@@ -4,15 +4,21 @@ use b_x::{BxForResults, BX};
 use bytes::BytesMut;
 use http::{header, StatusCode};
 use httparse::{Status, EMPTY_HEADER};
-use loona::buffet::{IntoHalves, ReadOwned, WriteOwned};
+use loona::buffet::{IntoHalves, PipeRead, PipeWrite, ReadOwned, WriteOwned};
 use loona::{
-    buffet::{PieceCore, RollMut},
-    h1, h2, Body, BodyChunk, Encoder, ExpectResponseHeaders, Headers, HeadersExt, Method, Request,
-    Responder, Response, ResponseDone, ServerDriver,
+    buffet::{Piece, PieceCore, RollMut},
+    cancel::CancelReason,
+    client,
+    error::HandlerFailure,
+    h1, h2,
+    server::{Proto, Server, ShardedServer},
+    validation::Validation,
+    Body, BodyChunk, ClientDriver, Encoder, ExpectResponseHeaders, Headers, HeadersExt, Method,
+    Request, Responder, Response, ResponseDone, ServerDriver, SinglePieceBody,
 };
 use pretty_assertions::assert_eq;
 use pretty_hex::PrettyHex;
-use std::{future::Future, net::SocketAddr, rc::Rc, time::Duration};
+use std::{cell::Cell, future::Future, net::SocketAddr, rc::Rc, time::Duration};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 use tokio::process::Command;
@@ -148,99 +154,2492 @@ fn serve_api() {
     })
 }
 
+/// Answers every request with a 200, once its body has been read, and
+/// whatever the closure makes of the request and its body
+struct Reply<F>(F)
+where
+    F: Fn(&loona::Request, &[u8]) -> String;
+
+impl<F, OurEncoder> ServerDriver<OurEncoder> for Reply<F>
+where
+    F: Fn(&loona::Request, &[u8]) -> String,
+    OurEncoder: Encoder,
+{
+    type Error = BX;
+
+    async fn handle(
+        &self,
+        req: loona::Request,
+        req_body: &mut impl Body,
+        res: Responder<OurEncoder, ExpectResponseHeaders>,
+    ) -> b_x::Result<Responder<OurEncoder, ResponseDone>> {
+        let mut body = vec![];
+        while let BodyChunk::Chunk(chunk) = req_body.next_chunk().await.bx()? {
+            body.extend_from_slice(&chunk[..]);
+        }
+        let reply = (self.0)(&req, &body);
+        let mut headers = Headers::default();
+        headers.insert(
+            header::CONTENT_LENGTH,
+            reply.len().to_string().into_bytes().into(),
+        );
+        let mut res = res
+            .write_final_response(Response {
+                headers,
+                ..Default::default()
+            })
+            .await?;
+        if !reply.is_empty() {
+            res.write_chunk(reply.into_bytes().into()).await?;
+        }
+        Ok(res.finish_body(None).await?)
+    }
+}
+
+/// Reads the whole response
+struct Collect;
+
+impl ClientDriver for Collect {
+    type Return = (Response, Vec<u8>);
+    type Error = BX;
+
+    async fn on_informational_response(&mut self, _res: Response) -> b_x::Result<()> {
+        Ok(())
+    }
+
+    async fn on_final_response(
+        self,
+        res: Response,
+        body: &mut impl Body,
+    ) -> b_x::Result<Self::Return> {
+        let mut data = vec![];
+        while let BodyChunk::Chunk(chunk) = body.next_chunk().await.bx()? {
+            data.extend_from_slice(&chunk[..]);
+        }
+        Ok((res, data))
+    }
+}
+
+/// Reads from `r` until the other side hangs up
+async fn read_to_string(mut r: impl ReadOwned) -> b_x::Result<String> {
+    let mut out = vec![];
+    let mut buf = vec![0u8; 1024];
+    loop {
+        let res;
+        (res, buf) = r.read_owned(buf).await;
+        let n = res?;
+        if n == 0 {
+            break;
+        }
+        out.extend_from_slice(&buf[..n]);
+    }
+    Ok(String::from_utf8(out)?)
+}
+
+/// Sends a request for `/` that closes the connection, returns everything
+/// the server sent back
+async fn get_and_close(io: impl IntoHalves) -> b_x::Result<String> {
+    let (r, mut w) = io.into_halves();
+    w.write_all_owned("GET / HTTP/1.1\r\nconnection: close\r\n\r\n")
+        .await?;
+    read_to_string(r).await
+}
+
+/// Runs `server` while `test` talks to it, then shuts it down
+async fn run_server<OurDriver: 'static>(
+    server: Server<OurDriver>,
+    test: impl Future<Output = b_x::Result<()>>,
+) -> b_x::Result<()> {
+    let handle = server.handle();
+    let server_fut = loona::buffet::spawn(server.run());
+    test.await?;
+    handle.shutdown(Duration::from_secs(1));
+    tokio::time::timeout(Duration::from_secs(5), server_fut)
+        .await
+        .bx()?
+        .bx()?;
+    Ok(())
+}
+
+/// Serves HTTP/1.1 over pipes, on a connection that starts with `early` as
+/// early data, returns the client's end
+fn serve_h1_over_pipes<OurDriver>(
+    conf: h1::ServerConf,
+    early: &'static str,
+    driver: OurDriver,
+) -> (PipeRead, PipeWrite)
+where
+    OurDriver: ServerDriver<h1::encode::H1Encoder<PipeWrite>, Error = BX> + 'static,
+{
+    let (server_write, client_read) = loona::buffet::pipe();
+    let (client_write, server_read) = loona::buffet::pipe();
+    loona::buffet::spawn(async move {
+        let mut client_buf = RollMut::alloc()?;
+        client_buf.put(early)?;
+        h1::serve_with_early_data(
+            (server_read, server_write),
+            Rc::new(conf),
+            client_buf,
+            early.len(),
+            driver,
+        )
+        .await?;
+        Ok::<_, BX>(())
+    });
+    (client_read, client_write)
+}
+
+/// Sends `req` over HTTP/1.1 then hangs up, returns everything the server
+/// sent back
+async fn h1_roundtrip<OurDriver>(
+    conf: h1::ServerConf,
+    driver: OurDriver,
+    req: &'static str,
+) -> b_x::Result<String>
+where
+    OurDriver: ServerDriver<h1::encode::H1Encoder<PipeWrite>, Error = BX> + 'static,
+{
+    h1_roundtrip_with_early_data(conf, driver, "", req).await
+}
+
+/// Like [h1_roundtrip], on a connection that starts with `early` as early
+/// data
+async fn h1_roundtrip_with_early_data<OurDriver>(
+    conf: h1::ServerConf,
+    driver: OurDriver,
+    early: &'static str,
+    rest: &'static str,
+) -> b_x::Result<String>
+where
+    OurDriver: ServerDriver<h1::encode::H1Encoder<PipeWrite>, Error = BX> + 'static,
+{
+    let (client_read, mut client_write) = serve_h1_over_pipes(conf, early, driver);
+    client_write.write_all_owned(rest).await?;
+    // pipes don't do half-closes, dropping is how we hang up
+    drop(client_write);
+    read_to_string(client_read).await
+}
+
+/// Serves h2 over pipes, counting the first `early_data_len` bytes the
+/// client sends as early data, returns the client's end
+fn serve_h2_over_pipes<OurDriver>(
+    conf: h2::ServerConf,
+    early_data_len: usize,
+    driver: OurDriver,
+) -> (PipeRead, PipeWrite)
+where
+    OurDriver: ServerDriver<h2::H2Encoder, Error = BX> + 'static,
+{
+    let (server_write, client_read) = loona::buffet::pipe();
+    let (client_write, server_read) = loona::buffet::pipe();
+    loona::buffet::spawn(async move {
+        h2::serve_with_early_data(
+            (server_read, server_write),
+            Rc::new(conf),
+            RollMut::alloc()?,
+            early_data_len,
+            Rc::new(driver),
+        )
+        .await?;
+        Ok::<_, BX>(())
+    });
+    (client_read, client_write)
+}
+
+/// Lets an [httpwg::Conn] talk to a server over a pair of pipes
+struct TwoHalves<W, R>(W, R);
+
+impl<W: WriteOwned + 'static, R: ReadOwned + 'static> IntoHalves for TwoHalves<W, R> {
+    type Read = R;
+    type Write = W;
+
+    fn into_halves(self) -> (Self::Read, Self::Write) {
+        (self.1, self.0)
+    }
+}
+
+type H2TestConn = httpwg::Conn<TwoHalves<PipeWrite, PipeRead>>;
+
+/// Wraps the client's end of [serve_h2_over_pipes] in an [httpwg::Conn],
+/// which still has to do its handshake
+fn httpwg_conn((client_read, client_write): (PipeRead, PipeWrite)) -> H2TestConn {
+    // the client gives up if it doesn't hear from the server for a while,
+    // and some tests stay quiet on purpose.
+    let config = httpwg::Config {
+        timeout: Duration::from_secs(1),
+        ..Default::default()
+    };
+    httpwg::Conn::new(Rc::new(config), TwoHalves(client_write, client_read))
+}
+
+/// Serves h2 over pipes with `driver`, cf. [httpwg_conn]
+fn serve_h2<OurDriver>(conf: h2::ServerConf, driver: OurDriver) -> H2TestConn
+where
+    OurDriver: ServerDriver<h2::H2Encoder, Error = BX> + 'static,
+{
+    httpwg_conn(serve_h2_over_pipes(conf, 0, driver))
+}
+
+/// Serves h2 over pipes, with a driver that doesn't expect any requests
+fn serve_h2_without_requests(conf: h2::ServerConf) -> H2TestConn {
+    serve_h2(
+        conf,
+        Reply(|_, _| unreachable!("this test doesn't send requests")),
+    )
+}
+
+/// Request headers for `method` on `path`
+fn h2_request(method: &'static str, path: &'static str) -> httpwg::Headers {
+    let mut headers = httpwg::Headers::default();
+    headers.append(":method", method);
+    headers.append(":scheme", "http");
+    headers.append(":path", path);
+    headers.append(":authority", "example.org");
+    headers
+}
+
+/// Waits for response headers, returns their stream and status
+async fn h2_response_status(conn: &mut H2TestConn) -> (loona_h2::StreamId, String) {
+    let (frame, payload) = conn.wait_for_frame(httpwg::FrameT::Headers).await.unwrap();
+    let headers = conn.decode_headers(payload.into()).unwrap();
+    let status = headers.get_first(&":status".into()).unwrap();
+    (frame.stream_id, String::from_utf8(status.to_vec()).unwrap())
+}
+
+fn goaway_error_code(payload: &[u8]) -> u32 {
+    u32::from_be_bytes(payload[4..8].try_into().unwrap())
+}
+
+#[test]
+fn serve_over_unix_socket() {
+    helpers::run(async move {
+        let path = std::env::temp_dir().join(format!("loona-test-{}.sock", std::process::id()));
+        let ln = loona::buffet::net::UnixListener::bind(&path).await?;
+
+        let serve_fut = loona::buffet::spawn(async move {
+            let stream = ln.accept().await?;
+            let conf = Rc::new(h1::ServerConf::default());
+            let client_buf = RollMut::alloc()?;
+            let driver = Reply(|_, _| String::new());
+            h1::serve(stream.into_halves(), conf, client_buf, driver).await?;
+            Ok::<_, BX>(())
+        });
+
+        let stream = loona::buffet::net::UnixStream::connect(&path).await?;
+        let res = get_and_close(stream).await?;
+        assert!(res.starts_with("HTTP/1.1 200 "), "{res}");
+        assert!(res.contains("\r\ndate: "), "{res}");
+
+        tokio::time::timeout(Duration::from_secs(5), serve_fut)
+            .await
+            .bx()?
+            .bx()??;
+        assert!(!path.exists(), "socket file should be removed on drop");
+
+        Ok(())
+    })
+}
+
+#[test]
+fn server_with_multiple_listeners() {
+    helpers::run(async move {
+        let tcp_ln = loona::buffet::net::TcpListener::bind("127.0.0.1:0".parse()?).await?;
+        let tcp_addr = tcp_ln.local_addr()?;
+        let unix_path =
+            std::env::temp_dir().join(format!("loona-server-test-{}.sock", std::process::id()));
+        let unix_ln = loona::buffet::net::UnixListener::bind(&unix_path).await?;
+
+        let mut server = Server::new(Reply(|_, _| String::new()));
+        server.listen(tcp_ln, Proto::H1);
+        server.listen(unix_ln, Proto::H1);
+        let handle = server.handle();
+
+        run_server(server, async {
+            let tcp_stream = loona::buffet::net::TcpStream::connect(tcp_addr).await?;
+            let res = get_and_close(tcp_stream).await?;
+            assert!(res.starts_with("HTTP/1.1 200 "), "{res}");
+            let unix_stream = loona::buffet::net::UnixStream::connect(&unix_path).await?;
+            let res = get_and_close(unix_stream).await?;
+            assert!(res.starts_with("HTTP/1.1 200 "), "{res}");
+
+            assert_eq!(handle.stats().connections_accepted, 2);
+            Ok(())
+        })
+        .await?;
+        assert_eq!(handle.stats().connections_active, 0);
+        assert!(!unix_path.exists());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn server_with_proxy_protocol() {
+    helpers::run(async move {
+        async fn peer_addr_seen(addr: SocketAddr, proxy_header: &[u8]) -> b_x::Result<String> {
+            let mut stream = TcpStream::connect(addr).await?;
+            stream.write_all(proxy_header).await?;
+            stream
+                .write_all(b"GET / HTTP/1.1\r\nconnection: close\r\n\r\n")
+                .await?;
+            let mut res = String::new();
+            stream.read_to_string(&mut res).await?;
+            assert!(
+                res.starts_with("HTTP/1.1 200"),
+                "unexpected response: {res}"
+            );
+            Ok(res.split("\r\n\r\n").nth(1).unwrap_or_default().to_string())
+        }
+
+        let ln = loona::buffet::net::TcpListener::bind("127.0.0.1:0".parse()?).await?;
+        let addr = ln.local_addr()?;
+        let driver = Reply(|req, _| {
+            req.connection
+                .as_ref()
+                .and_then(|conn| conn.peer_addr)
+                .map(|addr| addr.to_string())
+                .unwrap_or_default()
+        });
+        let mut server = Server::new(driver)
+            .with_proxy_protocol(true)
+            .with_proxy_header_timeout(Duration::from_millis(100));
+        server.listen(ln, Proto::H1);
+        let handle = server.handle();
+
+        run_server(server, async {
+            let v1 = b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\n";
+            assert_eq!(peer_addr_seen(addr, v1).await?, "192.0.2.1:56324");
+
+            let mut v2 = b"\r\n\r\n\0\r\nQUIT\n".to_vec();
+            // version 2, PROXY command, TCP over IPv6, 36 bytes of addresses
+            v2.extend_from_slice(&[0x21, 0x21, 0x00, 0x24]);
+            v2.extend_from_slice(&"2001:db8::1".parse::<std::net::Ipv6Addr>()?.octets());
+            v2.extend_from_slice(&"2001:db8::2".parse::<std::net::Ipv6Addr>()?.octets());
+            v2.extend_from_slice(&56324_u16.to_be_bytes());
+            v2.extend_from_slice(&443_u16.to_be_bytes());
+            assert_eq!(peer_addr_seen(addr, &v2).await?, "[2001:db8::1]:56324");
+
+            // connections without a header are dropped
+            let mut stream = TcpStream::connect(addr).await?;
+            stream
+                .write_all(b"GET / HTTP/1.1\r\nconnection: close\r\n\r\n")
+                .await?;
+            let mut res = Vec::new();
+            let _ = stream.read_to_end(&mut res).await;
+            assert!(res.is_empty());
+
+            // ...and so are connections that take too long to send one
+            let mut stream = TcpStream::connect(addr).await?;
+            let mut res = Vec::new();
+            let _ = tokio::time::timeout(Duration::from_secs(2), stream.read_to_end(&mut res))
+                .await
+                .bx()?;
+            assert!(res.is_empty());
+            Ok(())
+        })
+        .await?;
+        assert_eq!(handle.stats().connection_errors, 2);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn sharded_server() {
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let sharded = ShardedServer::spawn(Some(2), move |cx| async move {
+        let ln = cx.bind(addr).await.unwrap();
+        let mut server = Server::new(Reply(|_, _| String::new()));
+        server.listen(ln, Proto::H1);
+        cx.attach(server.handle());
+        server.run().await;
+    })
+    .unwrap();
+
+    for _ in 0..8 {
+        use std::io::{Read, Write};
+
+        let start = std::time::Instant::now();
+        let mut stream = loop {
+            match std::net::TcpStream::connect(addr) {
+                Ok(stream) => break stream,
+                Err(e) if start.elapsed() > Duration::from_secs(1) => panic!("{e}"),
+                Err(_) => std::thread::sleep(Duration::from_millis(10)),
+            }
+        };
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nconnection: close\r\n\r\n")
+            .unwrap();
+        let mut res = String::new();
+        stream.read_to_string(&mut res).unwrap();
+        assert!(
+            res.starts_with("HTTP/1.1 200"),
+            "unexpected response: {res:?}"
+        );
+    }
+
+    sharded.shutdown(Duration::from_secs(1));
+    sharded.join();
+}
+
+#[test]
+fn h1_connect_tunnel() {
+    helpers::run(async move {
+        struct TestDriver;
+
+        impl<OurEncoder> ServerDriver<OurEncoder> for TestDriver
+        where
+            OurEncoder: Encoder,
+        {
+            type Error = BX;
+
+            async fn handle(
+                &self,
+                req: loona::Request,
+                req_body: &mut impl Body,
+                res: Responder<OurEncoder, ExpectResponseHeaders>,
+            ) -> b_x::Result<Responder<OurEncoder, ResponseDone>> {
+                assert_eq!(req.method, Method::Connect);
+                let addr: SocketAddr = req.uri.authority().unwrap().as_str().parse()?;
+                let upstream = loona::buffet::net::TcpStream::connect(addr).await?;
+
+                let res = res
+                    .write_final_response(Response {
+                        status: StatusCode::OK,
+                        ..Default::default()
+                    })
+                    .await?;
+                let (res, stats) =
+                    loona::tunnel::copy_bidirectional(req_body, res, upstream.into_halves())
+                        .await
+                        .bx()?;
+                assert_eq!(stats.client_to_upstream, 5);
+                assert_eq!(stats.upstream_to_client, 5);
+                Ok(res)
+            }
+        }
+
+        async fn read_response_head(
+            r: &mut impl ReadOwned,
+            res_buf: &mut BytesMut,
+        ) -> b_x::Result<u16> {
+            let mut buf = vec![0u8; 1024];
+            loop {
+                let res;
+                (res, buf) = r.read_owned(buf).await;
+                let n = res?;
+                assert_ne!(n, 0, "server closed connection before responding");
+                res_buf.extend_from_slice(&buf[..n]);
+
+                let mut headers = [EMPTY_HEADER; 16];
+                let mut res = httparse::Response::new(&mut headers[..]);
+                if let Status::Complete(len) = res.parse(&res_buf[..]).bx()? {
+                    assert!(!res.headers.iter().any(|h| h.name == "transfer-encoding"));
+                    let code = res.code.unwrap();
+                    let _ = res_buf.split_to(len);
+                    return Ok(code);
+                }
+            }
+        }
+
+        // an upstream that echoes everything back
+        let upstream_ln = loona::buffet::net::TcpListener::bind("127.0.0.1:0".parse()?).await?;
+        let upstream_addr = upstream_ln.local_addr()?;
+        loona::buffet::spawn(async move {
+            let (stream, _) = upstream_ln.accept().await?;
+            let (mut r, mut w) = stream.into_halves();
+            let mut buf = vec![0u8; 1024];
+            loop {
+                let res;
+                (res, buf) = r.read_owned(buf).await;
+                let n = res?;
+                if n == 0 {
+                    break;
+                }
+                w.write_all_owned(buf[..n].to_vec()).await?;
+            }
+            w.shutdown().await?;
+            Ok::<_, BX>(())
+        });
+
+        let ln = loona::buffet::net::TcpListener::bind("127.0.0.1:0".parse()?).await?;
+        let addr = ln.local_addr()?;
+        let mut server = Server::new(TestDriver);
+        server.listen(ln, Proto::H1);
+
+        run_server(server, async {
+            // targets that aren't in authority-form are rejected
+            let stream = loona::buffet::net::TcpStream::connect(addr).await?;
+            let (mut r, mut w) = stream.into_halves();
+            w.write_all_owned("CONNECT /not-an-authority HTTP/1.1\r\n\r\n")
+                .await?;
+            assert_eq!(read_response_head(&mut r, &mut BytesMut::new()).await?, 400);
+
+            let stream = loona::buffet::net::TcpStream::connect(addr).await?;
+            let (mut r, mut w) = stream.into_halves();
+            w.write_all_owned(
+                format!("CONNECT {upstream_addr} HTTP/1.1\r\nhost: {upstream_addr}\r\n\r\n")
+                    .into_bytes(),
+            )
+            .await?;
+            let mut res_buf = BytesMut::new();
+            assert_eq!(read_response_head(&mut r, &mut res_buf).await?, 200);
+
+            w.write_all_owned("hello").await?;
+            w.shutdown().await?;
+
+            res_buf.extend_from_slice(read_to_string(r).await?.as_bytes());
+            assert_eq!(&res_buf[..], b"hello");
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn h1_request_smuggling() {
+    helpers::run(async move {
+        async fn roundtrip(validation: Validation, req: &'static str) -> b_x::Result<String> {
+            let conf = h1::ServerConf {
+                validation,
+                ..Default::default()
+            };
+            let driver = Reply(|req, body| {
+                let foo = req
+                    .headers
+                    .get("x-foo")
+                    .map(|v| String::from_utf8_lossy(v).into_owned())
+                    .unwrap_or_default();
+                format!("got {} bytes, x-foo={foo}", body.len())
+            });
+            h1_roundtrip(conf, driver, req).await
+        }
+
+        let (strict, lenient) = (Validation::strict(), Validation::lenient());
+        let te_and_cl = "POST / HTTP/1.1\r\ntransfer-encoding: chunked\r\ncontent-length: 3\r\n\r\n5\r\nhello\r\n0\r\n\r\nGET / HTTP/1.1\r\n\r\n";
+        let res = roundtrip(strict, te_and_cl).await?;
+        assert!(res.starts_with("HTTP/1.1 400 "), "{res}");
+        // in lenient mode, transfer-encoding wins, and the connection is closed
+        // after the response, so the second request isn't served.
+        let res = roundtrip(lenient, te_and_cl).await?;
+        assert!(res.starts_with("HTTP/1.1 200 "), "{res}");
+        assert!(res.contains("got 5 bytes"), "{res}");
+        assert_eq!(res.matches("HTTP/1.1").count(), 1, "{res}");
+
+        let res = roundtrip(
+            strict,
+            "POST / HTTP/1.1\r\ncontent-length: 3\r\ncontent-length: 5\r\n\r\nhello",
+        )
+        .await?;
+        assert!(res.starts_with("HTTP/1.1 400 "), "{res}");
+        let res = roundtrip(
+            strict,
+            "POST / HTTP/1.1\r\ncontent-length: 5, 5\r\n\r\nhello",
+        )
+        .await?;
+        assert!(res.contains("got 5 bytes"), "{res}");
+        let res = roundtrip(strict, "POST / HTTP/1.1\r\ncontent-length: +5\r\n\r\nhello").await?;
+        assert!(res.starts_with("HTTP/1.1 400 "), "{res}");
+
+        let res = roundtrip(
+            strict,
+            "POST / HTTP/1.1\r\ntransfer-encoding: gzip\r\n\r\nhello",
+        )
+        .await?;
+        assert!(res.starts_with("HTTP/1.1 400 "), "{res}");
+        let res = roundtrip(
+            strict,
+            "POST / HTTP/1.1\r\ntransfer-encoding: gzip, chunked\r\n\r\n0\r\n\r\n",
+        )
+        .await?;
+        assert!(res.starts_with("HTTP/1.1 501 "), "{res}");
+
+        let obs_fold = "GET / HTTP/1.1\r\nx-foo: bar\r\n  baz\r\n\r\n";
+        let res = roundtrip(strict, obs_fold).await?;
+        assert!(res.starts_with("HTTP/1.1 400 "), "{res}");
+        let res = roundtrip(lenient, obs_fold).await?;
+        assert!(res.contains("x-foo=bar baz"), "{res}");
+
+        // rules can be relaxed one at a time
+        let only_obs_fold = Validation {
+            allow_obs_fold: true,
+            ..strict
+        };
+        let res = roundtrip(only_obs_fold, obs_fold).await?;
+        assert!(res.contains("x-foo=bar baz"), "{res}");
+        let res = roundtrip(only_obs_fold, te_and_cl).await?;
+        assert!(res.starts_with("HTTP/1.1 400 "), "{res}");
+
+        let spaces = "GET /a b HTTP/1.1\r\n\r\n";
+        let res = roundtrip(strict, spaces).await?;
+        assert!(res.starts_with("HTTP/1.1 400 "), "{res}");
+        let res = roundtrip(lenient, spaces).await?;
+        assert!(res.starts_with("HTTP/1.1 200 "), "{res}");
+
+        Ok(())
+    })
+}
+
+#[test]
+fn h1_request_target_forms() {
+    helpers::run(async move {
+        async fn roundtrip(req: &'static str) -> b_x::Result<String> {
+            let driver = Reply(|req, _| {
+                format!(
+                    "{:?} scheme={:?} authority={:?} path={}",
+                    req.target_form(),
+                    req.uri.scheme_str(),
+                    req.uri.authority().map(|a| a.as_str()),
+                    req.uri.path(),
+                )
+            });
+            h1_roundtrip(Default::default(), driver, req).await
+        }
+
+        let res = roundtrip("GET /a?b HTTP/1.1\r\nhost: example.org\r\n\r\n").await?;
+        assert!(
+            res.contains("Origin scheme=None authority=None path=/a"),
+            "{res}"
+        );
+
+        // what clients send to forward proxies
+        let res =
+            roundtrip("GET http://example.org:8080/a?b HTTP/1.1\r\nhost: EXAMPLE.org:8080\r\n\r\n")
+                .await?;
+        assert!(
+            res.contains(
+                "Absolute scheme=Some(\"http\") authority=Some(\"example.org:8080\") path=/a"
+            ),
+            "{res}"
+        );
+
+        let res = roundtrip("OPTIONS * HTTP/1.1\r\nhost: example.org\r\n\r\n").await?;
+        assert!(
+            res.contains("Asterisk scheme=None authority=None path=*"),
+            "{res}"
+        );
+
+        for bad in [
+            // the target and the host header disagree
+            "GET http://example.org/ HTTP/1.1\r\nhost: example.com\r\n\r\n",
+            "GET http://example.org/ HTTP/1.1\r\nhost: \r\n\r\n",
+            "GET / HTTP/1.1\r\nhost: example.org\r\nhost: example.com\r\n\r\n",
+            "GET / HTTP/1.1\r\nhost: exa mple.org\r\n\r\n",
+            // forms reserved to other methods
+            "GET example.org:443 HTTP/1.1\r\nhost: example.org:443\r\n\r\n",
+            "GET * HTTP/1.1\r\n\r\n",
+            // not a URI
+            "GET http:// HTTP/1.1\r\n\r\n",
+        ] {
+            let res = roundtrip(bad).await?;
+            assert!(res.starts_with("HTTP/1.1 400 "), "{bad:?}: {res}");
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn h1_early_data() {
+    helpers::run(async move {
+        async fn roundtrip(
+            conf: h1::ServerConf,
+            early: &'static str,
+            rest: &'static str,
+        ) -> b_x::Result<String> {
+            let driver =
+                Reply(|req, _| format!("{} early={}", req.uri.path(), req.is_early_data()));
+            h1_roundtrip_with_early_data(conf, driver, early, rest).await
+        }
+
+        let res = roundtrip(
+            Default::default(),
+            "GET /a HTTP/1.1\r\n\r\n",
+            "GET /b HTTP/1.1\r\n\r\n",
+        )
+        .await?;
+        assert!(res.contains("/a early=true"), "{res}");
+        assert!(res.contains("/b early=false"), "{res}");
+
+        // only part of the head came in early data
+        let res = roundtrip(Default::default(), "GET /a HTTP/1.1\r\n", "\r\n").await?;
+        assert!(res.contains("/a early=false"), "{res}");
+
+        let post = "POST /a HTTP/1.1\r\ncontent-length: 5\r\n\r\nhello";
+        let res = roundtrip(Default::default(), post, "GET /b HTTP/1.1\r\n\r\n").await?;
+        assert!(res.starts_with("HTTP/1.1 425 "), "{res}");
+        assert_eq!(res.matches("HTTP/1.1").count(), 1, "{res}");
+
+        let conf = h1::ServerConf::builder()
+            .reject_unsafe_early_data(false)
+            .build()
+            .unwrap();
+        let res = roundtrip(conf, post, "").await?;
+        assert!(res.contains("/a early=true"), "{res}");
+
+        // a TLS-terminating proxy says so
+        let res = roundtrip(
+            Default::default(),
+            "",
+            "POST /a HTTP/1.1\r\nearly-data: 1\r\ncontent-length: 0\r\n\r\n",
+        )
+        .await?;
+        assert!(res.starts_with("HTTP/1.1 425 "), "{res}");
+
+        Ok(())
+    })
+}
+
+#[test]
+fn h1_response_trailers() {
+    helpers::run(async move {
+        struct TestDriver;
+
+        impl<OurEncoder> ServerDriver<OurEncoder> for TestDriver
+        where
+            OurEncoder: Encoder,
+        {
+            type Error = BX;
+
+            async fn handle(
+                &self,
+                req: loona::Request,
+                _req_body: &mut impl Body,
+                res: Responder<OurEncoder, ExpectResponseHeaders>,
+            ) -> b_x::Result<Responder<OurEncoder, ResponseDone>> {
+                let mut res_headers = Headers::default();
+                if req.uri.path() == "/fixed" {
+                    res_headers.insert(header::CONTENT_LENGTH, "2".into());
+                }
+                let mut res = res
+                    .write_final_response(Response {
+                        status: StatusCode::OK,
+                        headers: res_headers,
+                        ..Default::default()
+                    })
+                    .await?;
+                res.write_chunk("hi".into()).await?;
+
+                let mut trailers = Headers::default();
+                trailers.insert("x-checksum", "abc".into());
+                Ok(res.finish_body(Some(Box::new(trailers))).await?)
+            }
+        }
+
+        let conf = h1::ServerConf {
+            date_header: false,
+            ..Default::default()
+        };
+        let res = h1_roundtrip(
+            conf,
+            TestDriver,
+            "GET /chunked HTTP/1.1\r\n\r\nGET /fixed HTTP/1.1\r\n\r\n",
+        )
+        .await?;
+
+        // trailers follow the last chunk, and bodies that aren't chunked
+        // can't have any
+        assert_eq!(
+            res,
+            "HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n\
+             2\r\nhi\r\n0\r\nx-checksum: abc\r\n\r\n\
+             HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nhi"
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn h2_extended_connect() {
+    helpers::run(async move {
+        struct TestDriver;
+
+        impl<OurEncoder> ServerDriver<OurEncoder> for TestDriver
+        where
+            OurEncoder: Encoder,
+        {
+            type Error = BX;
+
+            async fn handle(
+                &self,
+                req: loona::Request,
+                req_body: &mut impl Body,
+                res: Responder<OurEncoder, ExpectResponseHeaders>,
+            ) -> b_x::Result<Responder<OurEncoder, ResponseDone>> {
+                assert_eq!(req.method, Method::Connect);
+                assert_eq!(req.protocol.as_deref(), Some("websocket"));
+                assert_eq!(req.uri.path(), "/chat");
+
+                // echo everything back, as it comes
+                let mut res = res
+                    .write_final_response(Response {
+                        status: StatusCode::OK,
+                        ..Default::default()
+                    })
+                    .await?;
+                while let BodyChunk::Chunk(chunk) = req_body.next_chunk().await.bx()? {
+                    res.write_chunk(chunk).await?;
+                }
+                Ok(res.finish_body(None).await?)
+            }
+        }
+
+        let conf = h2::ServerConf {
+            enable_connect_protocol: true,
+            ..Default::default()
+        };
+        let mut conn = serve_h2(conf, TestDriver);
+        conn.handshake().await.unwrap();
+        assert!(conn.settings.enable_connect_protocol);
+
+        let stream_id = loona_h2::StreamId(1);
+        let mut headers = httpwg::Headers::default();
+        headers.append(":method", "CONNECT");
+        headers.append(":protocol", "websocket");
+        headers.append(":scheme", "http");
+        headers.append(":path", "/chat");
+        headers.append(":authority", "example.org");
+        conn.encode_and_write_headers(stream_id, loona_h2::HeadersFlags::EndHeaders, &headers)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            h2_response_status(&mut conn).await,
+            (stream_id, "200".into())
+        );
+
+        conn.write_data(stream_id, false, "hello").await.unwrap();
+        let (_, payload) = conn.wait_for_frame(httpwg::FrameT::Data).await.unwrap();
+        assert_eq!(&payload[..], b"hello");
+
+        conn.write_data(stream_id, true, "").await.unwrap();
+        let (frame, _) = conn.wait_for_frame(httpwg::FrameT::Data).await.unwrap();
+        assert!(frame.is_end_stream());
+
+        Ok(())
+    })
+}
+
+#[test]
+fn h2_priority_scheduling() {
+    helpers::run(async move {
+        struct TestDriver;
+
+        impl<OurEncoder> ServerDriver<OurEncoder> for TestDriver
+        where
+            OurEncoder: Encoder,
+        {
+            type Error = BX;
+
+            async fn handle(
+                &self,
+                _req: loona::Request,
+                _req_body: &mut impl Body,
+                res: Responder<OurEncoder, ExpectResponseHeaders>,
+            ) -> b_x::Result<Responder<OurEncoder, ResponseDone>> {
+                let mut res = res
+                    .write_final_response(Response {
+                        status: StatusCode::OK,
+                        ..Default::default()
+                    })
+                    .await?;
+                for _ in 0..4 {
+                    res.write_chunk(vec![b'a'; 8192].into()).await?;
+                }
+                Ok(res.finish_body(None).await?)
+            }
+        }
+
+        let mut conn = serve_h2(Default::default(), TestDriver);
+        conn.handshake().await.unwrap();
+
+        // don't let the server send any DATA until both responses are queued
+        conn.write_and_ack_settings(&[(loona_h2::Setting::InitialWindowSize, 0)])
+            .await
+            .unwrap();
+
+        let less_urgent = loona_h2::StreamId(1);
+        let more_urgent = loona_h2::StreamId(3);
+
+        // stream 3 is prioritized before it's even opened
+        conn.write_frame(
+            loona_h2::FrameType::Unknown(loona_h2::EncodedFrameType {
+                ty: loona_h2::PRIORITY_UPDATE_FRAME_TYPE,
+                flags: 0,
+            })
+            .into_frame(loona_h2::StreamId::CONNECTION),
+            loona_h2::PriorityUpdate {
+                prioritized_stream_id: more_urgent,
+                field_value: "u=0".into(),
+            },
+        )
+        .await
+        .unwrap();
+
+        for (stream_id, priority) in [(less_urgent, Some("u=7")), (more_urgent, None)] {
+            let mut headers = h2_request("GET", "/");
+            if let Some(priority) = priority {
+                headers.append("priority", priority);
+            }
+            conn.encode_and_write_headers(
+                stream_id,
+                loona_h2::HeadersFlags::EndHeaders | loona_h2::HeadersFlags::EndStream,
+                &headers,
+            )
+            .await
+            .unwrap();
+        }
+
+        // give the driver time to hand over both bodies, then open the windows
+        // of both streams at once.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        conn.write_settings(&[(loona_h2::Setting::InitialWindowSize, 65535)])
+            .await
+            .unwrap();
+
+        loop {
+            let (frame, _) = conn.wait_for_frame(httpwg::FrameT::Data).await.unwrap();
+            assert_eq!(
+                frame.stream_id, more_urgent,
+                "the more urgent stream should be sent in full first"
+            );
+            if frame.is_end_stream() {
+                break;
+            }
+        }
+
+        let (frame, _) = conn.wait_for_frame(httpwg::FrameT::Data).await.unwrap();
+        assert_eq!(frame.stream_id, less_urgent);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn h2_keepalive_ping() {
+    helpers::run(async move {
+        let mut conn = serve_h2_without_requests(h2::ServerConf {
+            keepalive_interval: Some(Duration::from_millis(50)),
+            keepalive_timeout: Duration::from_millis(50),
+            ..Default::default()
+        });
+        conn.handshake().await.unwrap();
+
+        // an acknowledged ping keeps the connection alive...
+        let (frame, payload) = conn.wait_for_frame(httpwg::FrameT::Ping).await.unwrap();
+        assert!(!frame.is_ack());
+        conn.write_ping(true, payload).await.unwrap();
+
+        // ...until the next one goes unanswered
+        let (frame, _) = conn.wait_for_frame(httpwg::FrameT::Ping).await.unwrap();
+        assert!(!frame.is_ack());
+
+        let (_, payload) = conn.wait_for_frame(httpwg::FrameT::GoAway).await.unwrap();
+        assert_eq!(
+            goaway_error_code(&payload[..]),
+            loona_h2::KnownErrorCode::NoError as u32
+        );
+
+        Ok(())
+    })
+}
+
+/// Counts the vectored writes going through it
+struct CountWritev<W> {
+    inner: W,
+    writevs: Rc<Cell<usize>>,
+}
+
+impl<W: WriteOwned> WriteOwned for CountWritev<W> {
+    async fn write_owned(
+        &mut self,
+        buf: impl Into<Piece>,
+    ) -> loona::buffet::bufpool::BufResult<usize, Piece> {
+        self.inner.write_owned(buf).await
+    }
+
+    async fn writev_owned(&mut self, list: &loona::buffet::PieceList) -> std::io::Result<usize> {
+        self.writevs.set(self.writevs.get() + 1);
+        self.inner.writev_owned(list).await
+    }
+
+    async fn shutdown(&mut self) -> std::io::Result<()> {
+        self.inner.shutdown().await
+    }
+}
+
+#[test]
+fn h2_one_write_per_turn() {
+    helpers::run(async move {
+        let writevs: Rc<Cell<usize>> = Default::default();
+        let (server_write, client_read) = loona::buffet::pipe();
+        let (client_write, server_read) = loona::buffet::pipe();
+        let server_write = CountWritev {
+            inner: server_write,
+            writevs: writevs.clone(),
+        };
+        loona::buffet::spawn(async move {
+            h2::serve(
+                (server_read, server_write),
+                Rc::new(h2::ServerConf::default()),
+                RollMut::alloc()?,
+                Rc::new(Reply(|_, _| "hi".to_string())),
+            )
+            .await?;
+            Ok::<_, BX>(())
+        });
+        let mut conn = httpwg_conn((client_read, client_write));
+        conn.handshake().await.unwrap();
+
+        // pings that arrive in a single read are handled in the same turn, so
+        // all their acks go out in a single write
+        const PINGS: usize = 5;
+        let mut buf = Vec::new();
+        for i in 0..PINGS {
+            let frame = loona_h2::FrameType::Ping(Default::default())
+                .into_frame(loona_h2::StreamId::CONNECTION);
+            buf.extend(conn.encode_frame(frame, [i as u8; 8].to_vec()).unwrap());
+        }
+        let writevs_before = writevs.get();
+        let len = buf.len();
+        conn.send_chunked(buf, &httpwg::chunking::Chunking::Fixed(len))
+            .await
+            .unwrap();
+        for i in 0..PINGS {
+            let (frame, payload) = conn.wait_for_frame(httpwg::FrameT::Ping).await.unwrap();
+            assert!(frame.is_ack());
+            assert_eq!(&payload[..], &[i as u8; 8]);
+        }
+        assert_eq!(writevs.get() - writevs_before, 1);
+
+        // and batching doesn't hold back responses
+        let stream_id = conn.next_stream_id();
+        conn.encode_and_write_headers(
+            stream_id,
+            loona_h2::HeadersFlags::EndHeaders | loona_h2::HeadersFlags::EndStream,
+            &h2_request("GET", "/"),
+        )
+        .await
+        .unwrap();
+        let (response_stream, status) = h2_response_status(&mut conn).await;
+        assert_eq!(response_stream, stream_id);
+        assert_eq!(status, "200");
+        let (_, payload) = conn.wait_for_frame(httpwg::FrameT::Data).await.unwrap();
+        assert_eq!(&payload[..], b"hi");
+
+        Ok(())
+    })
+}
+
+#[test]
+fn h2_advertised_settings() {
+    helpers::run(async move {
+        let conf = h2::ServerConf::internal()
+            .into_builder()
+            .max_streams(Some(64))
+            .build()
+            .unwrap();
+        let mut conn = serve_h2_without_requests(conf);
+        conn.handshake().await.unwrap();
+        assert_eq!(conn.settings.max_concurrent_streams, Some(64));
+        assert_eq!(conn.settings.max_frame_size, 64 * 1024);
+        assert_eq!(conn.settings.initial_window_size, 1024 * 1024);
+
+        Ok(())
+    })
+}
+
+#[test]
+fn h2_idle_timeout() {
+    helpers::run(async move {
+        let mut conn = serve_h2_without_requests(h2::ServerConf {
+            idle_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        });
+        conn.handshake().await.unwrap();
+
+        let (frame, payload) = conn.wait_for_frame(httpwg::FrameT::GoAway).await.unwrap();
+        assert_eq!(
+            goaway_error_code(&payload[..]),
+            loona_h2::KnownErrorCode::NoError as u32
+        );
+        // goaway_debug_data is on by default
+        let payload = httpwg::Payload::decode(&frame, payload).unwrap();
+        payload.assert_debug_contains("idle").unwrap();
+
+        Ok(())
+    })
+}
+
+#[test]
+fn h2_lenient_validation() {
+    helpers::run(async move {
+        for validation in [Validation::strict(), Validation::lenient()] {
+            let conf = h2::ServerConf {
+                validation,
+                ..Default::default()
+            };
+            let driver = Reply(|req, _| {
+                req.headers
+                    .get("x-shouty")
+                    .map(|v| String::from_utf8_lossy(v).into_owned())
+                    .unwrap_or_default()
+            });
+            let mut conn = serve_h2(conf, driver);
+            conn.handshake().await.unwrap();
+
+            let mut headers = h2_request("GET", "/");
+            headers.append("X-Shouty", "hi");
+            let stream_id = loona_h2::StreamId(1);
+            conn.encode_and_write_headers(
+                stream_id,
+                loona_h2::HeadersFlags::EndStream | loona_h2::HeadersFlags::EndHeaders,
+                &headers,
+            )
+            .await
+            .unwrap();
+
+            if validation.allow_uppercase_header_names {
+                // the name is lowercased
+                assert_eq!(
+                    h2_response_status(&mut conn).await,
+                    (stream_id, "200".into())
+                );
+                let (_, payload) = conn.wait_for_frame(httpwg::FrameT::Data).await.unwrap();
+                assert_eq!(&payload[..], b"hi");
+            } else {
+                let (frame, _) = conn
+                    .wait_for_frame(httpwg::FrameT::RstStream)
+                    .await
+                    .unwrap();
+                assert_eq!(frame.stream_id, stream_id);
+            }
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn h2_early_data() {
+    helpers::run(async move {
+        // everything the client sends counts as early data
+        let driver = Reply(|req, _| format!("early={}", req.is_early_data()));
+        let mut conn = httpwg_conn(serve_h2_over_pipes(Default::default(), 1024 * 1024, driver));
+        conn.handshake().await.unwrap();
+
+        for (stream_id, method, status) in [(1, "GET", "200"), (3, "POST", "425")] {
+            let stream_id = loona_h2::StreamId(stream_id);
+            conn.encode_and_write_headers(
+                stream_id,
+                loona_h2::HeadersFlags::EndStream | loona_h2::HeadersFlags::EndHeaders,
+                &h2_request(method, "/"),
+            )
+            .await
+            .unwrap();
+
+            assert_eq!(
+                h2_response_status(&mut conn).await,
+                (stream_id, status.into()),
+                "{method}"
+            );
+            if method == "GET" {
+                let (_, payload) = conn.wait_for_frame(httpwg::FrameT::Data).await.unwrap();
+                assert_eq!(&payload[..], b"early=true");
+            }
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn h2_request_cancellation() {
+    helpers::run(async move {
+        /// Waits for the request to be cancelled, then reports why
+        struct TestDriver {
+            tx: tokio::sync::mpsc::UnboundedSender<CancelReason>,
+        }
+
+        impl<OurEncoder> ServerDriver<OurEncoder> for TestDriver
+        where
+            OurEncoder: Encoder,
+        {
+            type Error = BX;
+
+            async fn handle(
+                &self,
+                _req: loona::Request,
+                _req_body: &mut impl Body,
+                res: Responder<OurEncoder, ExpectResponseHeaders>,
+            ) -> b_x::Result<Responder<OurEncoder, ResponseDone>> {
+                let reason = res.cancellation().cancelled().await;
+                self.tx.send(reason).unwrap();
+                Err(BX::from_string(format!("cancelled: {reason:?}")))
+            }
+        }
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let conf = h2::ServerConf {
+            request_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        let mut conn = serve_h2(conf, TestDriver { tx });
+        conn.handshake().await.unwrap();
+
+        // the client gives up on stream 1, stream 3 times out, and stream 5
+        // is still waiting when the connection goes away
+        conn.send_empty_post_to_root(loona_h2::StreamId(1))
+            .await
+            .unwrap();
+        conn.write_rst_stream(loona_h2::StreamId(1), loona_h2::KnownErrorCode::Cancel)
+            .await
+            .unwrap();
+        assert_eq!(rx.recv().await, Some(CancelReason::StreamReset));
+
+        conn.send_empty_post_to_root(loona_h2::StreamId(3))
+            .await
+            .unwrap();
+        assert_eq!(rx.recv().await, Some(CancelReason::Timeout));
+
+        conn.send_empty_post_to_root(loona_h2::StreamId(5))
+            .await
+            .unwrap();
+        // give the server time to accept the stream
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        drop(conn);
+        assert_eq!(rx.recv().await, Some(CancelReason::ConnectionClosed));
+
+        // the server is done with the connection, and drops the driver
+        assert_eq!(rx.recv().await, None);
+        Ok(())
+    })
+}
+
+#[test]
+fn handler_failure_recovery() {
+    helpers::run(async move {
+        struct TestDriver;
+
+        impl<OurEncoder> ServerDriver<OurEncoder> for TestDriver
+        where
+            OurEncoder: Encoder,
+        {
+            type Error = BX;
+
+            async fn handle(
+                &self,
+                req: loona::Request,
+                _req_body: &mut impl Body,
+                res: Responder<OurEncoder, ExpectResponseHeaders>,
+            ) -> b_x::Result<Responder<OurEncoder, ResponseDone>> {
+                match req.uri.path() {
+                    "/panic" => panic!("boom"),
+                    "/error" => return Err(BX::from_string("nope".to_string())),
+                    _ => {}
+                }
+
+                let mut res = res
+                    .write_final_response(Response {
+                        status: StatusCode::OK,
+                        ..Default::default()
+                    })
+                    .await?;
+                res.write_chunk(b"ok".into()).await?;
+                if req.uri.path() == "/mid-body-panic" {
+                    panic!("boom, but later");
+                }
+                Ok(res.finish_body(None).await?)
+            }
+        }
+
+        fn error_hook(failure: &HandlerFailure<'_>) -> (Response, Piece) {
+            let body = match failure {
+                HandlerFailure::Error(e) => format!("error: {e}"),
+                HandlerFailure::Panic(message) => format!("panic: {}", message.unwrap_or_default()),
+                _ => unreachable!(),
+            };
+            let res = Response {
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                ..Default::default()
+            };
+            (res, body.into_bytes().into())
+        }
+
+        async fn roundtrip(req: &'static str) -> b_x::Result<String> {
+            let conf = h1::ServerConf {
+                error_hook: Rc::new(error_hook),
+                ..Default::default()
+            };
+            h1_roundtrip(conf, TestDriver, req).await
+        }
+
+        // failures before the response headers get the hook's response, and
+        // the connection lives on
+        let res = roundtrip(
+            "GET /panic HTTP/1.1\r\n\r\nGET /error HTTP/1.1\r\n\r\nGET /ok HTTP/1.1\r\n\r\n",
+        )
+        .await?;
+        assert_eq!(res.matches("HTTP/1.1 503 ").count(), 2, "{res}");
+        assert!(res.contains("panic: boom"), "{res}");
+        assert!(res.contains("error: nope"), "{res}");
+        assert!(res.contains("HTTP/1.1 200 "), "{res}");
+
+        // ...unless the request body wasn't read
+        let res = roundtrip(
+            "POST /panic HTTP/1.1\r\ncontent-length: 5\r\n\r\nhelloGET /ok HTTP/1.1\r\n\r\n",
+        )
+        .await?;
+        assert!(res.starts_with("HTTP/1.1 503 "), "{res}");
+        assert!(res.contains("connection: close"), "{res}");
+        assert_eq!(res.matches("HTTP/1.1").count(), 1, "{res}");
+
+        // failing mid-response closes the connection
+        let res = roundtrip("GET /mid-body-panic HTTP/1.1\r\n\r\nGET /ok HTTP/1.1\r\n\r\n").await?;
+        assert!(res.starts_with("HTTP/1.1 200 "), "{res}");
+        assert_eq!(res.matches("HTTP/1.1").count(), 1, "{res}");
+
+        // over HTTP/2, failing mid-response resets the stream, and the
+        // connection lives on either way
+        let conf = h2::ServerConf {
+            error_hook: Rc::new(error_hook),
+            ..Default::default()
+        };
+        let mut conn = serve_h2(conf, TestDriver);
+        conn.handshake().await.unwrap();
+
+        for (stream_id, path) in [(1, "/panic"), (3, "/mid-body-panic"), (5, "/ok")] {
+            let stream_id = loona_h2::StreamId(stream_id);
+            conn.encode_and_write_headers(
+                stream_id,
+                loona_h2::HeadersFlags::EndHeaders | loona_h2::HeadersFlags::EndStream,
+                &h2_request("GET", path),
+            )
+            .await
+            .unwrap();
+
+            if path == "/mid-body-panic" {
+                // whatever was sent of the response, it ends with a reset
+                let (frame, payload) = conn
+                    .wait_for_frame(httpwg::FrameT::RstStream)
+                    .await
+                    .unwrap();
+                assert_eq!(frame.stream_id, stream_id);
+                assert_eq!(
+                    u32::from_be_bytes(payload[..4].try_into().unwrap()),
+                    loona_h2::KnownErrorCode::InternalError as u32
+                );
+                continue;
+            }
+
+            let (res_stream_id, status) = h2_response_status(&mut conn).await;
+            assert_eq!(res_stream_id, stream_id);
+
+            if path == "/panic" {
+                assert_eq!(status, "503");
+                let (_, payload) = conn.wait_for_frame(httpwg::FrameT::Data).await.unwrap();
+                assert_eq!(&payload[..], b"panic: boom");
+            } else {
+                assert_eq!(status, "200");
+                conn.verify_stream_close(stream_id).await.unwrap();
+            }
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn request_api() {
+    helpers::run(async move {
+        let (mut server_write, client_read) = loona::buffet::pipe();
+        let (client_write, mut server_read) = loona::buffet::pipe();
+
+        let req = Request {
+            method: Method::Get,
+            uri: "/".parse().unwrap(),
+            ..Default::default()
+        };
+
+        struct TestDriver;
+
+        impl h1::ClientDriver for TestDriver {
+            type Return = ();
+            type Error = BX;
+
+            async fn on_informational_response(&mut self, _res: Response) -> b_x::Result<()> {
+                todo!("got informational response!")
+            }
+
+            async fn on_final_response(
+                self,
+                res: Response,
+                body: &mut impl Body,
+            ) -> b_x::Result<Self::Return> {
+                debug!(
+                    "got final response! content length = {:?}, is chunked = {}",
+                    res.headers.content_length(),
+                    res.headers.is_chunked_transfer_encoding(),
+                );
+
+                while let BodyChunk::Chunk(chunk) = body.next_chunk().await.bx()? {
+                    debug!("got a chunk: {:?}", chunk.hex_dump());
+                }
+
+                Ok(())
+            }
+        }
+
+        let driver = TestDriver;
+        let request_fut = loona::buffet::spawn(async {
+            #[allow(clippy::let_unit_value)]
+            let mut body = ();
+            h1::request((client_read, client_write), req, &mut body, driver).await
+        });
+
+        let mut req_buf = BytesMut::new();
+        let mut buf = vec![0u8; 1024];
+        loop {
+            let res;
+            (res, buf) = server_read.read_owned(buf).await;
+            let n = res.unwrap();
+            let chunk = &buf[..n];
+            debug!("Got a chunk:\n{:?}", chunk.hex_dump());
+            req_buf.extend_from_slice(chunk);
+
+            let mut headers = [EMPTY_HEADER; 16];
+            let mut req = httparse::Request::new(&mut headers[..]);
+            let body_offset = match req.parse(&req_buf[..]).bx()? {
+                Status::Complete(off) => off,
+                Status::Partial => {
+                    debug!("partial request, continuing");
+                    continue;
+                }
+            };
+
+            debug!("Got a complete request: {req:?}");
+            debug!("body_offset: {body_offset}");
+
+            break;
+        }
+
+        let body = "Hi there";
+        let body_len = body.len();
+        server_write
+            .write_all_owned(
+                format!("HTTP/1.1 200 OK\r\ncontent-length: {body_len}\r\n\r\n").into_bytes(),
+            )
+            .await?;
+        server_write.write_all_owned(body).await?;
+        drop(server_write);
+
+        tokio::time::timeout(Duration::from_secs(5), request_fut)
+            .await
+            .bx()?
+            .bx()??;
+
+        Ok(())
+    })
+}
+
+#[test]
+fn h1_client() {
+    helpers::run(async move {
+        async fn read_head(server_read: &mut impl ReadOwned) -> Result<String, BX> {
+            let mut head = Vec::new();
+            let mut buf = vec![0u8; 1024];
+            while !head.ends_with(b"\r\n\r\n") {
+                let res;
+                (res, buf) = server_read.read_owned(buf).await;
+                let n = res?;
+                assert_ne!(n, 0, "client went away before sending its request");
+                head.extend_from_slice(&buf[..n]);
+            }
+            Ok(String::from_utf8(head)?)
+        }
+
+        /// Reads a request head, answers with `response`
+        async fn answer(
+            server_read: &mut impl ReadOwned,
+            server_write: &mut impl WriteOwned,
+            response: &'static str,
+        ) -> Result<String, BX> {
+            let head = read_head(server_read).await?;
+            server_write.write_all_owned(response).await?;
+            Ok(head)
+        }
+
+        struct Collect;
+
+        impl h1::ClientDriver for Collect {
+            type Return = (Response, Vec<u8>, Option<Box<Headers>>);
+            type Error = BX;
+
+            async fn on_informational_response(&mut self, _res: Response) -> b_x::Result<()> {
+                Ok(())
+            }
+
+            async fn on_final_response(
+                self,
+                res: Response,
+                body: &mut impl Body,
+            ) -> b_x::Result<Self::Return> {
+                let mut data = vec![];
+                loop {
+                    match body.next_chunk().await.bx()? {
+                        BodyChunk::Chunk(chunk) => data.extend_from_slice(&chunk[..]),
+                        BodyChunk::Done { trailers } => return Ok((res, data, trailers)),
+                    }
+                }
+            }
+        }
+
+        let get = |uri: &str| Request {
+            method: Method::Get,
+            uri: uri.parse().unwrap(),
+            ..Default::default()
+        };
+
+        #[allow(clippy::let_unit_value)]
+        let mut no_body = ();
+        let (mut server_write, client_read) = loona::buffet::pipe();
+        let (client_write, mut server_read) = loona::buffet::pipe();
+
+        // the host header comes from the URI, chunk extensions are skipped
+        // and trailers are read: the connection can be reused after that.
+        let (head, res) = tokio::join!(
+            answer(
+                &mut server_read,
+                &mut server_write,
+                "HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n\
+                 5;name=value\r\nhello\r\n0\r\nx-checksum: abc\r\n\r\n",
+            ),
+            h1::request(
+                (client_read, client_write),
+                get("http://example.org/a?b"),
+                &mut no_body,
+                Collect,
+            ),
+        );
+        let head = head?;
+        assert!(head.starts_with("GET /a?b HTTP/1.1\r\n"), "{head}");
+        assert!(head.contains("host: example.org\r\n"), "{head}");
+        let (transport, (res, data, trailers)) = res?;
+        assert_eq!(res.status, StatusCode::OK);
+        assert_eq!(&data[..], b"hello");
+        let trailers = trailers.expect("trailers were sent");
+        assert_eq!(&trailers.get("x-checksum").unwrap()[..], b"abc");
+        let transport = transport.expect("the connection can be reused");
+
+        // without a length, the body ends when the server closes the
+        // connection, which can't be reused then.
+        let (head, res) = tokio::join!(
+            async {
+                let head = answer(
+                    &mut server_read,
+                    &mut server_write,
+                    "HTTP/1.1 200 OK\r\n\r\nuntil the end",
+                )
+                .await;
+                drop(server_write);
+                head
+            },
+            h1::request(transport, get("/c"), &mut no_body, Collect),
+        );
+        assert!(head?.starts_with("GET /c HTTP/1.1\r\n"));
+        let (transport, (_, data, _)) = res?;
+        assert_eq!(&data[..], b"until the end");
+        assert!(transport.is_none());
+
+        let conf = h1::ClientConf {
+            response_timeout: Some(Duration::from_millis(50)),
+            body_read_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+
+        // the server never answers
+        let (_server_write, client_read) = loona::buffet::pipe();
+        let (client_write, mut server_read) = loona::buffet::pipe();
+        let (head, res) = tokio::join!(
+            read_head(&mut server_read),
+            h1::request_with_conf(
+                (client_read, client_write),
+                &conf,
+                get("/slow"),
+                &mut no_body,
+                Collect,
+            ),
+        );
+        head?;
+        assert!(
+            matches!(res, Err(h1::Http1ClientError::ResponseTimeout)),
+            "{:?}",
+            res.err()
+        );
+
+        // the server stops sending halfway through the body
+        let (mut server_write, client_read) = loona::buffet::pipe();
+        let (client_write, mut server_read) = loona::buffet::pipe();
+        let (_, res) = tokio::join!(
+            answer(
+                &mut server_read,
+                &mut server_write,
+                "HTTP/1.1 200 OK\r\ncontent-length: 10\r\n\r\nabc",
+            ),
+            h1::request_with_conf(
+                (client_read, client_write),
+                &conf,
+                get("/stalled"),
+                &mut no_body,
+                Collect,
+            ),
+        );
+        match res {
+            Err(h1::Http1ClientError::DriverError(e)) => {
+                assert!(e.to_string().contains("timed out"), "{e}")
+            }
+            res => panic!("expected a body read timeout, got {:?}", res.err()),
+        }
+
+        Ok(())
+    })
+}
+
+#[test]
+fn h2_client() {
+    helpers::run(async move {
+        // echoes the path and the request body, requests past the second one
+        // wait for a stream
+        let driver = Reply(|req, body| {
+            assert_eq!(req.uri.authority().map(|a| a.as_str()), Some("example.org"));
+            format!("{} {}", req.uri.path(), String::from_utf8_lossy(body))
+        });
+        let conf = h2::ServerConf {
+            max_streams: Some(2),
+            ..Default::default()
+        };
+        let transport = serve_h2_over_pipes(conf, 0, driver);
+
+        let (handle, conn) = h2::handshake(transport, Default::default())?;
+        let conn = loona::buffet::spawn(conn);
+
+        let request = |handle: h2::ClientHandle, path: &'static str, body: &'static str| async move {
+            let req = Request {
+                method: if body.is_empty() {
+                    Method::Get
+                } else {
+                    Method::Post
+                },
+                uri: format!("https://example.org{path}").parse().unwrap(),
+                ..Default::default()
+            };
+            let mut body = SinglePieceBody::from(body);
+            handle.request(req, &mut body, Collect).await
+        };
+
+        let results = futures_util::future::join_all(
+            [("/a", ""), ("/b", "hello"), ("/c", ""), ("/d", "world")]
+                .map(|(path, body)| request(handle.clone(), path, body)),
+        )
+        .await;
+        assert_eq!(handle.max_concurrent_streams(), Some(2));
+
+        for (res, (path, body)) in
+            results
+                .into_iter()
+                .zip([("/a", ""), ("/b", "hello"), ("/c", ""), ("/d", "world")])
+        {
+            let (res, data) = res?;
+            assert_eq!(res.status, StatusCode::OK);
+            assert_eq!(res.version, http::Version::HTTP_2);
+            assert_eq!(String::from_utf8(data)?, format!("{path} {body}"));
+        }
+
+        // the connection closes once every handle is gone
+        assert!(!handle.is_closed());
+        drop(handle);
+        conn.await.unwrap().bx()?;
+
+        Ok(())
+    })
+}
+
+/// Informational responses are sent as they are: only the final response
+/// gets a `date` header, like over HTTP/1.1
+#[test]
+fn h2_informational_response() {
+    helpers::run(async move {
+        struct TestDriver;
+
+        impl<OurEncoder> ServerDriver<OurEncoder> for TestDriver
+        where
+            OurEncoder: Encoder,
+        {
+            type Error = BX;
+
+            async fn handle(
+                &self,
+                _req: loona::Request,
+                _req_body: &mut impl Body,
+                mut res: Responder<OurEncoder, ExpectResponseHeaders>,
+            ) -> b_x::Result<Responder<OurEncoder, ResponseDone>> {
+                let mut headers = Headers::default();
+                headers.insert(header::LINK, "</style.css>; rel=preload".into());
+                res.write_interim_response(Response {
+                    status: StatusCode::from_u16(103).unwrap(),
+                    headers,
+                    ..Default::default()
+                })
+                .await?;
+                let res = res
+                    .write_final_response(Response {
+                        status: StatusCode::OK,
+                        ..Default::default()
+                    })
+                    .await?;
+                Ok(res.finish_body(None).await?)
+            }
+        }
+
+        #[derive(Default)]
+        struct CollectInformational {
+            informational: Vec<Response>,
+        }
+
+        impl ClientDriver for CollectInformational {
+            type Return = (Vec<Response>, Response);
+            type Error = BX;
+
+            async fn on_informational_response(&mut self, res: Response) -> b_x::Result<()> {
+                self.informational.push(res);
+                Ok(())
+            }
+
+            async fn on_final_response(
+                self,
+                res: Response,
+                body: &mut impl Body,
+            ) -> b_x::Result<Self::Return> {
+                while let BodyChunk::Chunk(_) = body.next_chunk().await.bx()? {}
+                Ok((self.informational, res))
+            }
+        }
+
+        let transport = serve_h2_over_pipes(Default::default(), 0, TestDriver);
+        let (handle, conn) = h2::handshake(transport, Default::default())?;
+        let conn = loona::buffet::spawn(conn);
+
+        let req = Request {
+            uri: "https://example.org/".parse().unwrap(),
+            ..Default::default()
+        };
+        let (informational, res) = handle
+            .request(
+                req,
+                &mut SinglePieceBody::from(""),
+                CollectInformational::default(),
+            )
+            .await?;
+
+        assert_eq!(informational.len(), 1);
+        assert_eq!(informational[0].status.as_u16(), 103);
+        assert!(informational[0].headers.get(header::LINK).is_some());
+        assert!(informational[0].headers.get(header::DATE).is_none());
+        assert_eq!(res.status, StatusCode::OK);
+        assert!(res.headers.get(header::DATE).is_some());
+
+        drop(handle);
+        conn.await.unwrap().bx()?;
+
+        Ok(())
+    })
+}
+
 #[test]
-fn request_api() {
+fn h2_client_against_httpwg_server() {
     helpers::run(async move {
-        let (mut server_write, client_read) = loona::buffet::pipe();
-        let (client_write, mut server_read) = loona::buffet::pipe();
+        let (server_write, client_read) = loona::buffet::pipe();
+        let (client_write, server_read) = loona::buffet::pipe();
+
+        let (handle, conn) = h2::handshake((client_read, client_write), Default::default())?;
+        let conn = loona::buffet::spawn(conn);
+        let res = loona::buffet::spawn(async move {
+            let req = Request {
+                method: Method::Get,
+                uri: "https://example.org/hello".parse().unwrap(),
+                ..Default::default()
+            };
+            handle.request(req, &mut (), Collect).await
+        });
+
+        // httpwg plays the server this time
+        let mut server = httpwg::Conn::accept(
+            Rc::new(httpwg::Config::default()),
+            TwoHalves(server_write, server_read),
+        )
+        .await
+        .unwrap();
+
+        let stream_id = loona_h2::StreamId(1);
+        let headers = server.read_headers(stream_id).await.unwrap();
+        for (name, value) in [
+            (":method", "GET"),
+            (":scheme", "https"),
+            (":authority", "example.org"),
+            (":path", "/hello"),
+        ] {
+            let name = Piece::from(name);
+            assert_eq!(
+                headers.get_first(&name).map(|v| &v[..]),
+                Some(value.as_bytes())
+            );
+        }
+
+        let mut headers = httpwg::Headers::default();
+        headers.append(":status", "200");
+        server
+            .encode_and_write_headers(stream_id, loona_h2::HeadersFlags::EndHeaders, &headers)
+            .await
+            .unwrap();
+        server
+            .write_data(stream_id, true, b"hi there")
+            .await
+            .unwrap();
+
+        let (res, data) = res.await.unwrap()?;
+        assert_eq!(res.status, StatusCode::OK);
+        assert_eq!(&data[..], b"hi there");
+
+        // the client hangs up once its handle is gone
+        server.verify_connection_close().await.unwrap();
+        conn.await.unwrap().bx()?;
+
+        Ok(())
+    })
+}
+
+#[test]
+fn client_pools_connections() {
+    helpers::run(async move {
+        /// Serves every connection it makes itself, over pipes
+        struct PipeConnector {
+            alpn: Option<client::AlpnProtocol>,
+            connects: Cell<usize>,
+        }
+
+        impl client::Connector for PipeConnector {
+            type Transport = client::Connected<Self::Read, Self::Write>;
+            type Read = loona::buffet::PipeRead;
+            type Write = loona::buffet::PipeWrite;
+            type Error = std::io::Error;
+
+            async fn connect(
+                &self,
+                origin: &client::Origin,
+            ) -> Result<Self::Transport, Self::Error> {
+                assert_eq!(origin.to_string(), "https://example.org:443");
+                self.connects.set(self.connects.get() + 1);
+
+                let driver = Reply(|req, _| req.uri.path().to_string());
+                let transport = if self.alpn == Some(client::AlpnProtocol::H2) {
+                    serve_h2_over_pipes(Default::default(), 0, driver)
+                } else {
+                    serve_h1_over_pipes(Default::default(), "", driver)
+                };
+                Ok(client::Connected {
+                    transport,
+                    alpn: self.alpn,
+                })
+            }
+
+            async fn handshake(
+                &self,
+                _origin: &client::Origin,
+                transport: Self::Transport,
+            ) -> Result<client::Connected<Self::Read, Self::Write>, Self::Error> {
+                Ok(transport)
+            }
+        }
+
+        async fn get(
+            client: &client::Client<PipeConnector>,
+            path: &str,
+        ) -> Result<Vec<u8>, client::ClientError<BX>> {
+            let req = Request {
+                method: Method::Get,
+                uri: format!("https://example.org{path}").parse().unwrap(),
+                ..Default::default()
+            };
+            let (_, data) = client.request(req, &mut (), Collect).await?;
+            Ok(data)
+        }
 
+        // requests made at the same time share a single h2 connection
+        let h2_client = client::Client::new(
+            PipeConnector {
+                alpn: Some(client::AlpnProtocol::H2),
+                connects: Default::default(),
+            },
+            Default::default(),
+        );
+        let results =
+            futures_util::future::join_all(["/a", "/b", "/c"].map(|path| get(&h2_client, path)))
+                .await;
+        for (res, path) in results.into_iter().zip(["/a", "/b", "/c"]) {
+            assert_eq!(&res?[..], path.as_bytes());
+        }
+        assert_eq!(&get(&h2_client, "/d").await?[..], b"/d");
+
+        // h1 connections serve one request at a time: they're only reused
+        // once they're done
+        let h1_client = client::Client::new(
+            PipeConnector {
+                alpn: Some(client::AlpnProtocol::Http11),
+                connects: Default::default(),
+            },
+            Default::default(),
+        );
+        let (a, b) = tokio::join!(get(&h1_client, "/a"), get(&h1_client, "/b"));
+        assert_eq!((&a?[..], &b?[..]), (&b"/a"[..], &b"/b"[..]));
+        assert_eq!(&get(&h1_client, "/c").await?[..], b"/c");
+
+        assert_eq!(h2_client.connector().connects.get(), 1);
+        assert_eq!(h1_client.connector().connects.get(), 2);
+
+        // the plain TCP connector doesn't do TLS
+        let tcp_client = client::Client::new(client::TcpConnector::new(), Default::default());
         let req = Request {
-            method: Method::Get,
-            uri: "/".parse().unwrap(),
+            uri: "https://example.org/".parse().unwrap(),
+            ..Default::default()
+        };
+        let res = tcp_client.request(req, &mut (), Collect).await;
+        assert!(
+            matches!(res, Err(client::ClientError::Connect(_))),
+            "{:?}",
+            res.err()
+        );
+
+        let req = Request {
+            uri: "/relative".parse().unwrap(),
             ..Default::default()
         };
+        let res = tcp_client.request(req, &mut (), Collect).await;
+        assert!(matches!(res, Err(client::ClientError::InvalidUri)));
+
+        Ok(())
+    })
+}
 
+#[test]
+fn client_redirects_and_retries() {
+    helpers::run(async move {
         struct TestDriver;
 
-        impl h1::ClientDriver for TestDriver {
-            type Return = ();
+        impl<OurEncoder> ServerDriver<OurEncoder> for TestDriver
+        where
+            OurEncoder: Encoder,
+        {
             type Error = BX;
 
-            async fn on_informational_response(&mut self, _res: Response) -> b_x::Result<()> {
-                todo!("got informational response!")
+            async fn handle(
+                &self,
+                req: loona::Request,
+                req_body: &mut impl Body,
+                res: Responder<OurEncoder, ExpectResponseHeaders>,
+            ) -> b_x::Result<Responder<OurEncoder, ResponseDone>> {
+                while let BodyChunk::Chunk(_) = req_body.next_chunk().await.bx()? {}
+
+                let redirect = match req.uri.path() {
+                    "/old" => Some((StatusCode::FOUND, "new")),
+                    "/form" => Some((StatusCode::SEE_OTHER, "/done")),
+                    "/loop" => Some((StatusCode::TEMPORARY_REDIRECT, "/loop")),
+                    _ => None,
+                };
+                let mut headers = Headers::default();
+                let status = match redirect {
+                    Some((status, location)) => {
+                        headers.insert(header::LOCATION, location.into());
+                        status
+                    }
+                    None => StatusCode::OK,
+                };
+                let mut res = res
+                    .write_final_response(Response {
+                        status,
+                        headers,
+                        ..Default::default()
+                    })
+                    .await?;
+                res.write_chunk(
+                    format!("{} {}", req.method, req.uri.path())
+                        .into_bytes()
+                        .into(),
+                )
+                .await?;
+                Ok(res.finish_body(None).await?)
             }
+        }
 
-            async fn on_final_response(
-                self,
-                res: Response,
-                body: &mut impl Body,
-            ) -> b_x::Result<Self::Return> {
-                debug!(
-                    "got final response! content length = {:?}, is chunked = {}",
-                    res.headers.content_length(),
-                    res.headers.is_chunked_transfer_encoding(),
-                );
+        /// Serves h1 over pipes, except for the first few connections,
+        /// which the server closes right away
+        struct FlakyConnector {
+            broken: Cell<usize>,
+            connects: Cell<usize>,
+        }
 
-                while let BodyChunk::Chunk(chunk) = body.next_chunk().await.bx()? {
-                    debug!("got a chunk: {:?}", chunk.hex_dump());
-                }
+        impl client::Connector for FlakyConnector {
+            type Transport = client::Connected<Self::Read, Self::Write>;
+            type Read = loona::buffet::PipeRead;
+            type Write = loona::buffet::PipeWrite;
+            type Error = std::io::Error;
 
-                Ok(())
+            async fn connect(
+                &self,
+                _origin: &client::Origin,
+            ) -> Result<Self::Transport, Self::Error> {
+                self.connects.set(self.connects.get() + 1);
+                let transport = if self.broken.get() > 0 {
+                    self.broken.set(self.broken.get() - 1);
+                    let (_, client_read) = loona::buffet::pipe();
+                    let (client_write, _) = loona::buffet::pipe();
+                    (client_read, client_write)
+                } else {
+                    serve_h1_over_pipes(Default::default(), "", TestDriver)
+                };
+
+                Ok(client::Connected {
+                    transport,
+                    alpn: None,
+                })
+            }
+
+            async fn handshake(
+                &self,
+                _origin: &client::Origin,
+                transport: Self::Transport,
+            ) -> Result<client::Connected<Self::Read, Self::Write>, Self::Error> {
+                Ok(transport)
             }
         }
 
-        let driver = TestDriver;
-        let request_fut = loona::buffet::spawn(async {
-            #[allow(clippy::let_unit_value)]
-            let mut body = ();
-            h1::request((client_read, client_write), req, &mut body, driver).await
+        async fn request(
+            client: &client::Client<FlakyConnector>,
+            method: Method,
+            path: &str,
+            body: &mut impl Body,
+        ) -> Result<(StatusCode, String), client::ClientError<BX>> {
+            let req = Request {
+                method,
+                uri: format!("http://example.org{path}").parse().unwrap(),
+                ..Default::default()
+            };
+            let (res, data) = client.request(req, body, Collect).await?;
+            Ok((res.status, String::from_utf8_lossy(&data).into_owned()))
+        }
+
+        let new_client = |conf: client::ClientConf| {
+            client::Client::new(
+                FlakyConnector {
+                    broken: Default::default(),
+                    connects: Default::default(),
+                },
+                conf,
+            )
+        };
+
+        let client = new_client(client::ClientConf {
+            redirect: client::RedirectPolicy {
+                max_redirects: 3,
+                ..Default::default()
+            },
+            ..Default::default()
         });
 
-        let mut req_buf = BytesMut::new();
-        let mut buf = vec![0u8; 1024];
-        loop {
-            let res;
-            (res, buf) = server_read.read_owned(buf).await;
-            let n = res.unwrap();
-            let chunk = &buf[..n];
-            debug!("Got a chunk:\n{:?}", chunk.hex_dump());
-            req_buf.extend_from_slice(chunk);
+        // relative locations are resolved against the request's URI
+        let (status, body) = request(&client, Method::Get, "/old", &mut ()).await?;
+        assert_eq!((status, &body[..]), (StatusCode::OK, "GET /new"));
+
+        // a 303 turns into a GET, without the body
+        let (_, body) = request(
+            &client,
+            Method::Post,
+            "/form",
+            &mut SinglePieceBody::from("name=value"),
+        )
+        .await?;
+        assert_eq!(body, "GET /done");
+
+        let res = request(&client, Method::Get, "/loop", &mut ()).await;
+        assert!(
+            matches!(res, Err(client::ClientError::TooManyRedirects { max: 3 })),
+            "{:?}",
+            res.err()
+        );
+
+        // redirects with a body to send again go to the driver
+        let (status, _) = request(
+            &client,
+            Method::Put,
+            "/loop",
+            &mut SinglePieceBody::from("data"),
+        )
+        .await?;
+        assert_eq!(status, StatusCode::TEMPORARY_REDIRECT);
+
+        // idempotent requests are retried when the connection fails before
+        // the response arrives, others aren't
+        let client = new_client(Default::default());
+        client.connector().broken.set(1);
+        let (_, body) = request(&client, Method::Get, "/a", &mut ()).await?;
+        assert_eq!(body, "GET /a");
+        assert_eq!(client.connector().connects.get(), 2);
+
+        let client = new_client(Default::default());
+        client.connector().broken.set(1);
+        let res = request(&client, Method::Post, "/b", &mut ()).await;
+        let err = res.expect_err("POST requests aren't retried");
+        assert_eq!(
+            err.retry_reason(),
+            Some(client::RetryReason::ConnectionFailed)
+        );
+        assert_eq!(client.connector().connects.get(), 1);
 
-            let mut headers = [EMPTY_HEADER; 16];
-            let mut req = httparse::Request::new(&mut headers[..]);
-            let body_offset = match req.parse(&req_buf[..]).bx()? {
-                Status::Complete(off) => off,
-                Status::Partial => {
-                    debug!("partial request, continuing");
-                    continue;
+        Ok(())
+    })
+}
+
+#[test]
+fn client_timeouts_and_cancellation() {
+    helpers::run(async move {
+        /// Streams a body until the request is cancelled, then reports
+        /// whether that's because the client reset the stream
+        struct TestDriver {
+            reset: Rc<Cell<bool>>,
+        }
+
+        impl<OurEncoder> ServerDriver<OurEncoder> for TestDriver
+        where
+            OurEncoder: Encoder,
+        {
+            type Error = BX;
+
+            async fn handle(
+                &self,
+                _req: loona::Request,
+                _req_body: &mut impl Body,
+                res: Responder<OurEncoder, ExpectResponseHeaders>,
+            ) -> b_x::Result<Responder<OurEncoder, ResponseDone>> {
+                let mut res = res.write_final_response(Response::default()).await?;
+                loop {
+                    if let Some(reason) = res.cancellation().reason() {
+                        self.reset.set(reason == CancelReason::StreamReset);
+                        return Err(BX::from_string(format!("cancelled: {reason:?}")));
+                    }
+                    res.write_chunk(b"forever".into()).await?;
+                    tokio::time::sleep(Duration::from_millis(5)).await;
                 }
-            };
+            }
+        }
 
-            debug!("Got a complete request: {req:?}");
-            debug!("body_offset: {body_offset}");
+        #[derive(Clone, Copy, PartialEq)]
+        enum Stall {
+            Connect,
+            Handshake,
+            Response,
+            Never,
+        }
 
-            break;
+        /// Connects over pipes, stalling at some point
+        struct StallingConnector {
+            stall: Stall,
+            reset: Rc<Cell<bool>>,
         }
 
-        let body = "Hi there";
-        let body_len = body.len();
-        server_write
-            .write_all_owned(
-                format!("HTTP/1.1 200 OK\r\ncontent-length: {body_len}\r\n\r\n").into_bytes(),
+        impl client::Connector for StallingConnector {
+            type Transport = (Self::Read, Self::Write);
+            type Read = loona::buffet::PipeRead;
+            type Write = loona::buffet::PipeWrite;
+            type Error = std::io::Error;
+
+            async fn connect(
+                &self,
+                _origin: &client::Origin,
+            ) -> Result<Self::Transport, Self::Error> {
+                match self.stall {
+                    Stall::Connect => std::future::pending().await,
+                    Stall::Response => {
+                        // the server never reads the request, let alone
+                        // answers it
+                        let (server_write, client_read) = loona::buffet::pipe();
+                        let (client_write, server_read) = loona::buffet::pipe();
+                        loona::buffet::spawn(async move {
+                            let _transport = (server_read, server_write);
+                            std::future::pending::<()>().await;
+                        });
+                        Ok((client_read, client_write))
+                    }
+                    _ => {
+                        let driver = TestDriver {
+                            reset: self.reset.clone(),
+                        };
+                        Ok(serve_h2_over_pipes(Default::default(), 0, driver))
+                    }
+                }
+            }
+
+            async fn handshake(
+                &self,
+                _origin: &client::Origin,
+                transport: Self::Transport,
+            ) -> Result<client::Connected<Self::Read, Self::Write>, Self::Error> {
+                if self.stall == Stall::Handshake {
+                    std::future::pending::<()>().await;
+                }
+                Ok(client::Connected {
+                    transport,
+                    alpn: Some(client::AlpnProtocol::H2),
+                })
+            }
+        }
+
+        let make_client = |stall| {
+            client::Client::new(
+                StallingConnector {
+                    stall,
+                    reset: Default::default(),
+                },
+                client::ClientConf {
+                    connect_timeout: Some(Duration::from_millis(50)),
+                    handshake_timeout: Some(Duration::from_millis(50)),
+                    request_timeout: Some(Duration::from_millis(200)),
+                    ..Default::default()
+                },
             )
-            .await?;
-        server_write.write_all_owned(body).await?;
-        drop(server_write);
+        };
+        let get = || Request {
+            uri: "https://example.org/".parse().unwrap(),
+            ..Default::default()
+        };
 
-        tokio::time::timeout(Duration::from_secs(5), request_fut)
-            .await
-            .bx()?
-            .bx()??;
+        let res = make_client(Stall::Connect)
+            .request(get(), &mut (), Collect)
+            .await;
+        assert!(
+            matches!(res, Err(client::ClientError::ConnectTimeout)),
+            "{:?}",
+            res.err()
+        );
+
+        let res = make_client(Stall::Handshake)
+            .request(get(), &mut (), Collect)
+            .await;
+        assert!(
+            matches!(res, Err(client::ClientError::HandshakeTimeout)),
+            "{:?}",
+            res.err()
+        );
+
+        let res = make_client(Stall::Response)
+            .request(get(), &mut (), Collect)
+            .await;
+        assert!(
+            matches!(res, Err(client::ClientError::RequestTimeout)),
+            "{:?}",
+            res.err()
+        );
+
+        // the request times out while the driver reads the body, which never
+        // ends: the stream gets reset, which the server notices the next
+        // time it writes
+        let client = make_client(Stall::Never);
+        let res = client.request(get(), &mut (), Collect).await;
+        assert!(
+            matches!(res, Err(client::ClientError::RequestTimeout)),
+            "{:?}",
+            res.err()
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(client.connector().reset.get());
 
         Ok(())
     })
 }
 
+#[test]
+fn client_resolves_names_and_races_connections() {
+    helpers::run(async move {
+        /// Resolves `example.test` to a closed port, then to our server
+        struct TestResolver {
+            addrs: Vec<SocketAddr>,
+        }
+
+        impl loona::dns::Resolve for TestResolver {
+            async fn resolve(&self, host: &str, _port: u16) -> std::io::Result<Vec<SocketAddr>> {
+                match host {
+                    "example.test" => Ok(self.addrs.clone()),
+                    _ => Ok(vec![]),
+                }
+            }
+        }
+
+        let ln = loona::buffet::net::TcpListener::bind("127.0.0.1:0".parse()?).await?;
+        let addr = ln.local_addr()?;
+        let mut server = Server::new(Reply(|_, _| String::new()));
+        server.listen(ln, Proto::H1);
+
+        run_server(server, async {
+            let closed_addr = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+            let client = client::Client::new(
+                client::TcpConnector::with_resolver(TestResolver {
+                    addrs: vec![closed_addr, addr],
+                }),
+                Default::default(),
+            );
+            let req = Request {
+                uri: format!("http://example.test:{}/", addr.port())
+                    .parse()
+                    .unwrap(),
+                ..Default::default()
+            };
+            let (res, _) = client.request(req, &mut (), Collect).await?;
+            assert_eq!(res.status, StatusCode::OK);
+
+            let req = Request {
+                uri: "http://nowhere.test/".parse().unwrap(),
+                ..Default::default()
+            };
+            let res = client.request(req, &mut (), Collect).await;
+            assert!(
+                matches!(res, Err(client::ClientError::Connect(_))),
+                "{:?}",
+                res.err()
+            );
+            Ok(())
+        })
+        .await
+    })
+}
+
+#[test]
+fn server_dump_lists_connections_and_streams() {
+    helpers::run(async move {
+        /// Holds on to requests until told to let them go
+        struct TestDriver {
+            release: Rc<tokio::sync::Notify>,
+        }
+
+        impl<OurEncoder> ServerDriver<OurEncoder> for TestDriver
+        where
+            OurEncoder: Encoder,
+        {
+            type Error = BX;
+
+            async fn handle(
+                &self,
+                _req: loona::Request,
+                _req_body: &mut impl Body,
+                res: Responder<OurEncoder, ExpectResponseHeaders>,
+            ) -> b_x::Result<Responder<OurEncoder, ResponseDone>> {
+                self.release.notified().await;
+                let res = res.write_final_response(Response::default()).await?;
+                Ok(res.finish_body(None).await?)
+            }
+        }
+
+        async fn get_text(
+            client: &client::Client<client::TcpConnector>,
+            addr: SocketAddr,
+        ) -> b_x::Result<String> {
+            let req = Request {
+                uri: format!("http://{addr}/").parse().unwrap(),
+                ..Default::default()
+            };
+            let (res, data) = client.request(req, &mut (), Collect).await?;
+            assert_eq!(res.status, StatusCode::OK);
+            Ok(String::from_utf8(data)?)
+        }
+
+        let release: Rc<tokio::sync::Notify> = Default::default();
+        let ln = loona::buffet::net::TcpListener::bind("127.0.0.1:0".parse()?).await?;
+        let addr = ln.local_addr()?;
+        let mut server = Server::new(TestDriver {
+            release: release.clone(),
+        });
+        server.listen(ln, Proto::H2);
+        let handle = server.handle();
+
+        let debug_ln = loona::buffet::net::TcpListener::bind("127.0.0.1:0".parse()?).await?;
+        let debug_addr = debug_ln.local_addr()?;
+        let mut debug_server = Server::new(loona::introspect::DebugEndpoint::new(handle.clone()));
+        debug_server.listen(debug_ln, Proto::H1);
+
+        let test = async {
+            let client = Rc::new(client::Client::new(
+                client::TcpConnector::new(),
+                client::ClientConf {
+                    h2_prior_knowledge: true,
+                    ..Default::default()
+                },
+            ));
+            let mut requests = vec![];
+            for _ in 0..2 {
+                let client = client.clone();
+                requests.push(loona::buffet::spawn(async move {
+                    get_text(&client, addr).await
+                }));
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+
+            let dump = handle.dump().await;
+            assert_eq!(dump.stats.connections_active, 1);
+            assert_eq!(dump.connections.len(), 1);
+            let conn = &dump.connections[0];
+            assert_eq!(conn.proto, Proto::H2);
+            assert_eq!(conn.info.local_addr, Some(addr));
+            let h2 = conn
+                .h2
+                .as_ref()
+                .expect("h2 connections describe themselves");
+            assert_eq!(h2.last_stream_id, 3);
+            assert_eq!(h2.streams.iter().map(|s| s.id).collect::<Vec<_>>(), [1, 3]);
+            for stream in &h2.streams {
+                assert_eq!(
+                    stream.state,
+                    loona::introspect::H2StreamState::HalfClosedRemote
+                );
+                assert_eq!(
+                    stream.response,
+                    Some(loona::introspect::ResponseProgress::WaitingForHeaders)
+                );
+                assert_eq!(stream.queued_bytes, 0);
+            }
+            assert_eq!(h2.hpack_decoder.max_size, 4096);
+            assert!(h2.hpack_decoder.size <= h2.hpack_decoder.max_size);
+
+            let debug_client = client::Client::new(client::TcpConnector::new(), Default::default());
+            let json = get_text(&debug_client, debug_addr).await?;
+            debug!(%json, "got dump");
+            assert!(json.starts_with(r#"{"stats":{"connections_accepted":1,"#));
+            assert!(json.contains(r#""proto":"h2""#));
+            assert!(json.contains(r#""state":"half_closed_remote","#));
+            assert!(json.contains(r#""response":"waiting_for_headers""#));
+
+            release.notify_waiters();
+            for request in requests {
+                assert_eq!(request.await.bx()??, "");
+            }
+            Ok(())
+        };
+        // boxed, nesting it as is overflows the test thread's stack
+        run_server(server, run_server(debug_server, Box::pin(test))).await
+    })
+}
+
 #[test]
 fn proxy_statuses() {
     #[allow(drop_bounds)]
@@ -0,0 +1,314 @@
+//! Whole connection lifecycles on a simulated network, cf. [buffet::sim]:
+//! virtual time, injected faults, replayable from a seed.
+
+// `helpers::run` starts a regular runtime, we only want its tracing setup
+#[path = "helpers/tracing_common.rs"]
+mod tracing_common;
+
+use std::{future::Future, sync::Once, time::Duration};
+
+use b_x::{BxForResults, BX};
+use buffet::sim::{self, Faults, Net};
+use http::StatusCode;
+use loona::{
+    client::{Client, ClientConf, ClientError, SimConnector},
+    memory::MemoryLimits,
+    server::{Proto, Server, ServerHandle},
+    Body, BodyChunk, ClientDriver, Encoder, ExpectResponseHeaders, Request, Responder, Response,
+    ResponseDone, ServerDriver,
+};
+use tokio::time::Instant;
+
+/// Runs `f` in a simulation, with tracing set up
+fn run<F, Fut>(seed: u64, f: F) -> Fut::Output
+where
+    F: FnOnce(Net) -> Fut,
+    Fut: Future,
+{
+    static TRACING: Once = Once::new();
+    TRACING.call_once(tracing_common::setup_tracing);
+    sim::run(seed, f)
+}
+
+/// Sleeps for as long as the request path says (in milliseconds), then
+/// responds with a 200, and as many bytes of body as the query says (or just
+/// "done")
+struct SleepyDriver;
+
+impl<OurEncoder> ServerDriver<OurEncoder> for SleepyDriver
+where
+    OurEncoder: Encoder,
+{
+    type Error = BX;
+
+    async fn handle(
+        &self,
+        req: loona::Request,
+        req_body: &mut impl Body,
+        res: Responder<OurEncoder, ExpectResponseHeaders>,
+    ) -> b_x::Result<Responder<OurEncoder, ResponseDone>> {
+        while let BodyChunk::Chunk(_) = req_body.next_chunk().await.bx()? {}
+        let millis: u64 = req.uri.path().trim_start_matches('/').parse().unwrap_or(0);
+        tokio::time::sleep(Duration::from_millis(millis)).await;
+
+        let mut res = res.write_final_response(Response::default()).await?;
+        match req.uri.query().and_then(|q| q.parse::<usize>().ok()) {
+            Some(mut remaining) => {
+                while remaining > 0 {
+                    let n = remaining.min(16 * 1024);
+                    res.write_chunk(vec![b'a'; n].into()).await?;
+                    remaining -= n;
+                }
+            }
+            None => res.write_chunk("done".into()).await?,
+        }
+        Ok(res.finish_body(None).await?)
+    }
+}
+
+struct Status;
+
+impl ClientDriver for Status {
+    type Return = StatusCode;
+    type Error = BX;
+
+    async fn on_informational_response(&mut self, _res: Response) -> b_x::Result<()> {
+        Ok(())
+    }
+
+    async fn on_final_response(
+        self,
+        res: Response,
+        body: &mut impl Body,
+    ) -> b_x::Result<Self::Return> {
+        while let BodyChunk::Chunk(_) = body.next_chunk().await.bx()? {}
+        Ok(res.status)
+    }
+}
+
+fn serve(net: &Net, addr: &str, proto: Proto) -> (ServerHandle, tokio::task::JoinHandle<()>) {
+    serve_with_limits(net, addr, proto, Default::default())
+}
+
+fn serve_with_limits(
+    net: &Net,
+    addr: &str,
+    proto: Proto,
+    limits: MemoryLimits,
+) -> (ServerHandle, tokio::task::JoinHandle<()>) {
+    let mut server = Server::new(SleepyDriver).with_memory_limits(limits);
+    server.listen(net.bind(addr).unwrap(), proto);
+    let handle = server.handle();
+    (handle, buffet::spawn(server.run()))
+}
+
+fn get(path: &str) -> Request {
+    Request {
+        uri: format!("http://server:80{path}").parse().unwrap(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn sim_request_timeout_on_virtual_clock() {
+    run(sim::seed(), |net| async move {
+        let (handle, server) = serve(&net, "server:80", Proto::H1);
+        let client = Client::new(
+            SimConnector { net: net.clone() },
+            ClientConf {
+                request_timeout: Some(Duration::from_secs(30)),
+                ..Default::default()
+            },
+        );
+
+        // an hour-long request, cut short after 30 (virtual) seconds
+        let start = Instant::now();
+        let res = client.request(get("/3600000"), &mut (), Status).await;
+        assert!(matches!(res, Err(ClientError::RequestTimeout)), "{res:?}");
+        assert_eq!(start.elapsed(), Duration::from_secs(30));
+
+        let res = client.request(get("/10"), &mut (), Status).await;
+        assert_eq!(res.unwrap(), StatusCode::OK);
+
+        handle.shutdown(Duration::ZERO);
+        server.await.unwrap();
+    })
+}
+
+#[test]
+fn sim_graceful_shutdown() {
+    run(sim::seed(), |net| async move {
+        let (handle, server) = serve(&net, "server:80", Proto::H2);
+        let client = std::rc::Rc::new(Client::new(
+            SimConnector { net: net.clone() },
+            ClientConf {
+                h2_prior_knowledge: true,
+                ..Default::default()
+            },
+        ));
+
+        let start = Instant::now();
+        let short = buffet::spawn({
+            let client = client.clone();
+            async move { client.request(get("/5000"), &mut (), Status).await }
+        });
+        let long = buffet::spawn({
+            let client = client.clone();
+            async move { client.request(get("/60000"), &mut (), Status).await }
+        });
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        // the short request finishes within the grace period, the long one
+        // gets cut off when it's over
+        handle.shutdown(Duration::from_secs(10));
+        server.await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_secs(11));
+        assert_eq!(short.await.unwrap().unwrap(), StatusCode::OK);
+        assert!(long.await.unwrap().is_err());
+        assert_eq!(handle.stats().connections_active, 0);
+
+        // nobody's listening anymore
+        let res = client.request(get("/0"), &mut (), Status).await;
+        assert!(matches!(res, Err(ClientError::Connect(_))), "{res:?}");
+    })
+}
+
+#[test]
+fn sim_graceful_shutdown_h2_goaway() {
+    run(sim::seed(), |net| async move {
+        let (handle, server) = serve(&net, "server:80", Proto::H2);
+        let client = Client::new(
+            SimConnector { net: net.clone() },
+            ClientConf {
+                h2_prior_knowledge: true,
+                ..Default::default()
+            },
+        );
+
+        let start = Instant::now();
+        let res = client.request(get("/1000"), &mut (), Status).await;
+        assert_eq!(res.unwrap(), StatusCode::OK);
+
+        // the connection is idle: the server says GOAWAY and hangs up right
+        // away, instead of waiting for the grace period to be over
+        handle.shutdown(Duration::from_secs(60));
+        server.await.unwrap();
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+        assert_eq!(handle.stats().connections_active, 0);
+    })
+}
+
+/// Makes requests over a network that drops connections, returning what
+/// happened to each one, and when
+fn requests_over_faulty_network(seed: u64) -> Vec<(String, Duration)> {
+    run(seed, |net| async move {
+        net.set_faults(Faults {
+            min_latency: Duration::from_millis(1),
+            max_latency: Duration::from_millis(50),
+            max_segment_size: 64,
+            reset_probability: 0.02,
+        });
+        let (handle, server) = serve(&net, "server:80", Proto::H1);
+        let client = Client::new(SimConnector { net: net.clone() }, Default::default());
+
+        let start = Instant::now();
+        let mut outcomes = vec![];
+        for i in 0..20 {
+            let res = client.request(get(&format!("/{i}")), &mut (), Status).await;
+            let outcome = match res {
+                Ok(status) => status.to_string(),
+                Err(e) => e.to_string(),
+            };
+            outcomes.push((outcome, start.elapsed()));
+        }
+
+        handle.shutdown(Duration::ZERO);
+        server.await.unwrap();
+        outcomes
+    })
+}
+
+#[test]
+fn sim_replays_from_seed() {
+    let seed = sim::seed();
+    let outcomes = requests_over_faulty_network(seed);
+    // retries hide most resets, so most requests should go through
+    let ok = outcomes
+        .iter()
+        .filter(|(o, _)| o.starts_with("200"))
+        .count();
+    assert!(ok >= 10, "seed {seed}: {outcomes:#?}");
+    assert_eq!(
+        requests_over_faulty_network(seed),
+        outcomes,
+        "seed {seed} didn't replay the same way"
+    );
+}
+
+#[test]
+fn sim_refuses_connections_over_memory_budget() {
+    run(sim::seed(), |net| async move {
+        // room for a single connection's read buffer
+        let limits = MemoryLimits {
+            global: buffet::bufpool::BUF_SIZE as usize,
+            ..Default::default()
+        };
+        let (handle, server) = serve_with_limits(&net, "server:80", Proto::H1, limits);
+        let client = std::rc::Rc::new(Client::new(
+            SimConnector { net: net.clone() },
+            Default::default(),
+        ));
+
+        let slow = buffet::spawn({
+            let client = client.clone();
+            async move { client.request(get("/1000"), &mut (), Status).await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        // the first connection is busy, and there's no room for another
+        let res = client.request(get("/0"), &mut (), Status).await;
+        assert!(res.is_err(), "{res:?}");
+        assert_eq!(slow.await.unwrap().unwrap(), StatusCode::OK);
+        let stats = handle.stats();
+        assert!(stats.connections_refused > 0, "{stats:?}");
+        assert_eq!(stats.connections_accepted, 1);
+
+        handle.shutdown(Duration::ZERO);
+        server.await.unwrap();
+        assert_eq!(handle.stats().memory_used, 0);
+    })
+}
+
+#[test]
+fn sim_resets_streams_over_memory_budget() {
+    run(sim::seed(), |net| async move {
+        let limits = MemoryLimits {
+            per_connection: 256 * 1024,
+            ..Default::default()
+        };
+        let (handle, server) = serve_with_limits(&net, "server:80", Proto::H2, limits);
+        let client = Client::new(
+            SimConnector { net: net.clone() },
+            ClientConf {
+                h2_prior_knowledge: true,
+                ..Default::default()
+            },
+        );
+
+        // way more than fits in the default flow-control window, so the
+        // response piles up on the server
+        let res = client.request(get("/0?4194304"), &mut (), Status).await;
+        assert!(res.is_err(), "{res:?}");
+
+        // responses that fit are fine, on the same connection
+        let res = client.request(get("/0?65536"), &mut (), Status).await;
+        assert_eq!(res.unwrap(), StatusCode::OK);
+        let stats = handle.stats();
+        assert_eq!(stats.connections_accepted, 1);
+        assert_eq!(stats.connections_refused, 0);
+
+        handle.shutdown(Duration::ZERO);
+        server.await.unwrap();
+        assert_eq!(handle.stats().memory_used, 0);
+    })
+}
@@ -0,0 +1,218 @@
+//! Rotating certificates with [loona::tls::CertResolver], over in-memory
+//! rustls handshakes
+
+mod helpers;
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use b_x::BxForResults;
+use base64::Engine;
+use loona::tls::{CertFiles, CertResolver};
+use rustls::{
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{aws_lc_rs, CryptoProvider},
+    pki_types::{CertificateDer, ServerName, UnixTime},
+    ClientConfig, ClientConnection, Connection, DigitallySignedStruct, ServerConfig,
+    ServerConnection, SignatureScheme,
+};
+
+/// Accepts any certificate, and remembers what the server presented
+#[derive(Debug)]
+struct RecordingVerifier {
+    provider: Arc<CryptoProvider>,
+    seen: Mutex<Option<(Vec<u8>, Vec<u8>)>>,
+}
+
+impl ServerCertVerifier for RecordingVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        *self.seen.lock().unwrap() = Some((end_entity.to_vec(), ocsp_response.to_vec()));
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+/// Does a handshake, returns the certificate and OCSP response the server
+/// presented
+fn handshake(
+    server_config: &Arc<ServerConfig>,
+    verifier: &Arc<RecordingVerifier>,
+) -> Result<(Vec<u8>, Vec<u8>), rustls::Error> {
+    let client_config = ClientConfig::builder_with_provider(verifier.provider.clone())
+        .with_safe_default_protocol_versions()?
+        .dangerous()
+        .with_custom_certificate_verifier(verifier.clone())
+        .with_no_client_auth();
+    let mut client = Connection::from(ClientConnection::new(
+        Arc::new(client_config),
+        ServerName::try_from("localhost").unwrap(),
+    )?);
+    let mut server = Connection::from(ServerConnection::new(server_config.clone())?);
+
+    fn transfer(from: &mut Connection, to: &mut Connection) -> Result<(), rustls::Error> {
+        let mut buf = vec![];
+        while from.wants_write() {
+            from.write_tls(&mut buf).unwrap();
+        }
+        to.read_tls(&mut &buf[..]).unwrap();
+        to.process_new_packets()?;
+        Ok(())
+    }
+    while client.is_handshaking() || server.is_handshaking() {
+        transfer(&mut client, &mut server)?;
+        transfer(&mut server, &mut client)?;
+    }
+
+    Ok(verifier.seen.lock().unwrap().take().unwrap())
+}
+
+/// A self-signed certificate for `localhost`, and its key, as PEM
+fn gen_cert() -> (Vec<u8>, String, String) {
+    fn pem(label: &str, der: &[u8]) -> String {
+        let b64 = base64::engine::general_purpose::STANDARD.encode(der);
+        let mut out = format!("-----BEGIN {label}-----\n");
+        for line in b64.as_bytes().chunks(64) {
+            out.push_str(std::str::from_utf8(line).unwrap());
+            out.push('\n');
+        }
+        out.push_str(&format!("-----END {label}-----\n"));
+        out
+    }
+
+    let certified_key = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let der = certified_key.cert.der().to_vec();
+    let key = certified_key.key_pair.serialize_der();
+    (
+        der.clone(),
+        pem("CERTIFICATE", &der),
+        pem("PRIVATE KEY", &key),
+    )
+}
+
+/// A fresh directory to write certificates to
+fn test_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("loona-tls-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn cert_resolver_reloads_files() {
+    helpers::run(async move {
+        let provider = Arc::new(aws_lc_rs::default_provider());
+        let resolver = Arc::new(CertResolver::new(provider.clone()));
+        let server_config = Arc::new(
+            ServerConfig::builder_with_provider(provider.clone())
+                .with_safe_default_protocol_versions()
+                .bx()?
+                .with_no_client_auth()
+                .with_cert_resolver(resolver.clone()),
+        );
+        let verifier = Arc::new(RecordingVerifier {
+            provider,
+            seen: Default::default(),
+        });
+
+        // nothing to serve yet
+        assert!(handshake(&server_config, &verifier).is_err());
+
+        let dir = test_dir();
+        let files = CertFiles::new(dir.join("cert.pem"), dir.join("key.pem"))
+            .with_ocsp_response(dir.join("ocsp.der"));
+        let (der_a, cert_a, key_a) = gen_cert();
+        std::fs::write(&files.cert_chain, &cert_a)?;
+        std::fs::write(&files.private_key, &key_a)?;
+        resolver.load(&files).bx()?;
+        assert_eq!(
+            handshake(&server_config, &verifier).bx()?,
+            (der_a.clone(), vec![])
+        );
+
+        let watcher = loona::buffet::spawn({
+            let resolver = resolver.clone();
+            let files = files.clone();
+            async move { resolver.watch(files, Duration::from_millis(10)).await }
+        });
+
+        // the new certificate doesn't go with the old key: keep serving the
+        // old one
+        let (der_b, cert_b, key_b) = gen_cert();
+        std::fs::write(&files.cert_chain, &cert_b)?;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(handshake(&server_config, &verifier).bx()?.0, der_a);
+
+        std::fs::write(&files.private_key, &key_b)?;
+        std::fs::write(files.ocsp_response.as_ref().unwrap(), b"staple")?;
+        let mut reloaded = false;
+        for _ in 0..500 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            if resolver.current().unwrap().ocsp.is_some() {
+                reloaded = true;
+                break;
+            }
+        }
+        assert!(reloaded, "the certificate should have been reloaded");
+        assert_eq!(
+            handshake(&server_config, &verifier).bx()?,
+            (der_b.clone(), b"staple".to_vec())
+        );
+
+        resolver.set_ocsp_response(Some(b"fresh staple".to_vec()));
+        assert_eq!(
+            handshake(&server_config, &verifier).bx()?,
+            (der_b, b"fresh staple".to_vec())
+        );
+
+        watcher.abort();
+
+        std::fs::write(&files.private_key, "not a key")?;
+        let err = files.load(&aws_lc_rs::default_provider()).unwrap_err();
+        assert!(err.to_string().contains("no private key"), "{err}");
+
+        Ok(())
+    })
+}